@@ -1,10 +1,21 @@
 //! 配置模块
 //!
-//! 支持从 JSON 文件加载系统配置
+//! 支持从 JSON 文件加载系统配置，并可通过 AKSHARE_ 前缀的环境变量覆盖。
+//!
+//! 优先级：环境变量 > 配置文件 > 默认值。环境变量名为 AKSHARE_ 加上以双下划线分隔的
+//! 配置路径，如 AKSHARE_SERVER__PORT=9000、AKSHARE_API__API_KEY=xxx
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::fs;
 use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+/// 全局配置（启动时写入，供服务层读取）
+static GLOBAL_CONFIG: OnceLock<RwLock<AppConfig>> = OnceLock::new();
+
+/// 环境变量覆盖前缀
+const ENV_PREFIX: &str = "AKSHARE_";
 
 /// 服务器配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,24 +37,204 @@ pub struct ApiConfig {
     /// API Key（为空则不启用认证）
     #[serde(default)]
     pub api_key: String,
+    /// 除 Authorization: Bearer 外，传递 API Key 的请求头名称
+    #[serde(default = "default_key_header")]
+    pub key_header: String,
+    /// 是否允许通过查询参数 ?api_key= 传递 API Key（请求头优先）
+    #[serde(default)]
+    pub allow_query_key: bool,
+    /// 管理接口（/admin/*）的密钥，通过 X-Admin-Key 请求头传递；为空时管理接口只允许本机访问
+    #[serde(default)]
+    pub admin_key: String,
     /// 请求超时时间（秒）
     #[serde(default = "default_timeout")]
     pub timeout_secs: u64,
     /// 连接超时时间（秒）
     #[serde(default = "default_connect_timeout")]
     pub connect_timeout_secs: u64,
+    /// 实时行情、品种列表等轻量请求的超时时间（秒，0 表示使用 timeout_secs）
+    #[serde(default = "default_realtime_timeout")]
+    pub realtime_timeout_secs: u64,
+    /// 交易所 Excel / ZIP 文件下载的超时时间（秒，0 表示使用 timeout_secs）
+    #[serde(default = "default_download_timeout")]
+    pub download_timeout_secs: u64,
+    /// 请求上游数据源时使用的 User-Agent
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    /// 行情时间距当前超过该秒数时标记为过期（stale）
+    #[serde(default = "default_stale_threshold")]
+    pub stale_threshold_secs: i64,
+    /// 新浪请求全局并发上限
+    #[serde(default = "default_sina_max_concurrency")]
+    pub sina_max_concurrency: usize,
+    /// 同时进行的交易所 Excel / ZIP 文件解析任务数上限（在阻塞线程池中执行，修改需重启）
+    #[serde(default = "default_max_parse_jobs")]
+    pub max_parse_jobs: usize,
+    /// 新浪请求排队超过该毫秒数时记录告警日志
+    #[serde(default = "default_sina_queue_warn_ms")]
+    pub sina_queue_warn_ms: u64,
+    /// 国内期货实时行情数据源顺序（sina / eastmoney），前一个失败或无数据时依次改用后一个
+    #[serde(default = "default_realtime_sources")]
+    pub realtime_sources: Vec<String>,
+    /// 检测到新浪 IP 封禁（456/403/拒绝访问）后暂停请求新浪的冷却时间（秒，0 表示不启用）
+    #[serde(default = "default_sina_ban_cooldown_secs")]
+    pub sina_ban_cooldown_secs: u64,
+    /// 兼容旧客户端：上游缺失的开高低收、最新价等价格字段序列化为 0 而不是 null
+    #[serde(default)]
+    pub missing_price_as_zero: bool,
+    /// 允许请求已过合约月份的合约实时行情（默认拒绝并返回 404）
+    #[serde(default)]
+    pub allow_expired_realtime: bool,
+    /// 查询字符串最大长度（字节），超过时返回 414（0 表示不限制）
+    #[serde(default = "default_max_query_length")]
+    pub max_query_length: usize,
+    /// 列表响应单页最大条数，超过时截断并返回 next_page 翻页令牌（0 表示不限制）
+    #[serde(default = "default_max_response_rows")]
+    pub max_response_rows: usize,
+    /// 按日循环请求接口允许的最大日期跨度（天）
+    #[serde(default = "default_max_range_days")]
+    pub max_range_days: i64,
+    /// 按 node 获取品种合约列表时，未指定条数的默认上限
+    #[serde(default = "default_node_list_limit")]
+    pub node_list_limit: usize,
+    /// 市场概览缓存时间（秒）
+    #[serde(default = "default_overview_cache_secs")]
+    pub overview_cache_secs: u64,
+    /// 同一上游在时间窗口内连续失败达到该次数时熔断（0 表示不启用熔断）
+    #[serde(default = "default_breaker_failure_threshold")]
+    pub breaker_failure_threshold: u32,
+    /// 统计连续失败的时间窗口（秒）
+    #[serde(default = "default_breaker_window_secs")]
+    pub breaker_window_secs: u64,
+    /// 熔断后的冷却时间（秒），到期后放行一个探测请求
+    #[serde(default = "default_breaker_cooldown_secs")]
+    pub breaker_cooldown_secs: u64,
 }
 
 /// 日志配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogConfig {
-    /// 日志级别: trace, debug, info, warn, error
+    /// 日志过滤规则，语法同 RUST_LOG：trace, debug, info, warn, error，
+    /// 或按模块指定，如 actix_ak::services=debug,info
     #[serde(default = "default_log_level")]
     pub level: String,
 }
 
-/// 应用配置
+/// 跨域配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorsConfig {
+    /// 允许的来源列表（为空则仅允许同源，"*" 表示任意来源）
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// 是否允许携带凭据（Cookie / Authorization）
+    #[serde(default)]
+    pub allow_credentials: bool,
+    /// 允许的请求方法
+    #[serde(default = "default_cors_methods")]
+    pub allowed_methods: Vec<String>,
+    /// 预检结果缓存时间（秒）
+    #[serde(default = "default_cors_max_age")]
+    pub max_age_secs: usize,
+}
+
+/// 上游 HTTP 客户端连接池配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// 每个上游域名保留的最大空闲连接数
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+    /// 空闲连接保留时间（秒）
+    #[serde(default = "default_pool_idle_timeout")]
+    pub pool_idle_timeout_secs: u64,
+    /// 允许跳过 TLS 证书校验的上游域名（须与请求地址的域名完全相同），其他域名的跳过校验请求被拒绝
+    #[serde(default = "default_allow_insecure_hosts")]
+    pub allow_insecure_hosts: Vec<String>,
+}
+
+/// 期货数据配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FuturesConfig {
+    /// 始终并入主力连续合约一览表的额外合约（如 SI0，可写成 GFEX:SI0 指定交易所），
+    /// 用于补充按名称规则未识别的品种
+    #[serde(default)]
+    pub extra_main_contracts: Vec<String>,
+    /// 不开夜盘的日期（YYYYMMDD，通常为节假日前一个交易日），用于行情过期判断
+    #[serde(default)]
+    pub no_night_session_dates: Vec<String>,
+    /// 额外的交易所休市日期（YYYYMMDD），补充内置节假日表未覆盖的年份或临时休市
+    #[serde(default)]
+    pub holidays: Vec<String>,
+}
+
+/// 本地存储配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageConfig {
+    /// 数据缓存目录（为空则不缓存）
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+}
+
+/// 调试配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DebugConfig {
+    /// 是否启用调试接口（/futures/debug/*），生产环境应关闭
+    #[serde(default)]
+    pub endpoints_enabled: bool,
+}
+
+/// 模拟数据配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockConfig {
+    /// 是否启用模拟模式：主要接口返回 fixture 文件中的固定数据，不请求任何上游
+    #[serde(default)]
+    pub enabled: bool,
+    /// fixture 文件目录
+    #[serde(default = "default_fixtures_dir")]
+    pub fixtures_dir: String,
+}
+
+/// 启动配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StartupConfig {
+    /// 启动后是否在后台预热共享缓存（品种映射、99期货网品种映射、OpenCTP 交易费用表）
+    #[serde(default)]
+    pub warm_cache: bool,
+    /// 品种映射后台刷新间隔（秒，0 表示不启用），在缓存过期前主动刷新；超过缓存有效期时按有效期前 60 秒刷新
+    #[serde(default)]
+    pub symbol_mark_refresh_secs: u64,
+}
+
+/// 各接口未指定 limit 时的默认条数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefaultsConfig {
+    /// 期货日K线默认条数（GET /futures/{symbol}/history）
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize,
+    /// 按交易所列出期货时的默认条数（GET /futures?exchange=）
+    #[serde(default = "default_list_limit")]
+    pub list_limit: usize,
+    /// 按交易所列出期货时最多请求的品种数
+    #[serde(default = "default_list_varieties_per_exchange")]
+    pub list_varieties_per_exchange: usize,
+    /// 未指定交易所时每个交易所请求的品种数
+    #[serde(default = "default_list_varieties_all_exchanges")]
+    pub list_varieties_all_exchanges: usize,
+    /// 股票列表默认条数（GET /stocks）
+    #[serde(default = "default_stock_list_limit")]
+    pub stock_list_limit: usize,
+}
+
+/// 配置来源记录
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources {
+    /// 读取的配置文件路径（未找到时为 None）
+    pub file: Option<String>,
+    /// 被环境变量覆盖的配置路径，如 server.port
+    pub env_overrides: Vec<String>,
+}
+
+/// 应用配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppConfig {
     /// 服务器配置
     #[serde(default)]
@@ -54,14 +245,81 @@ pub struct AppConfig {
     /// 日志配置
     #[serde(default)]
     pub log: LogConfig,
+    /// 跨域配置
+    #[serde(default)]
+    pub cors: CorsConfig,
+    /// 上游 HTTP 客户端配置
+    #[serde(default)]
+    pub http: HttpConfig,
+    /// 期货数据配置
+    #[serde(default)]
+    pub futures: FuturesConfig,
+    /// 本地存储配置
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// 调试配置
+    #[serde(default)]
+    pub debug: DebugConfig,
+    /// 模拟数据配置
+    #[serde(default)]
+    pub mock: MockConfig,
+    /// 启动配置
+    #[serde(default)]
+    pub startup: StartupConfig,
+    /// 接口默认条数配置
+    #[serde(default)]
+    pub defaults: DefaultsConfig,
+    /// 配置来源（不参与序列化）
+    #[serde(skip)]
+    pub sources: ConfigSources,
 }
 
 // 默认值函数
 fn default_host() -> String { "0.0.0.0".to_string() }
 fn default_port() -> u16 { 8080 }
+fn default_key_header() -> String { "X-API-Key".to_string() }
 fn default_timeout() -> u64 { 30 }
 fn default_connect_timeout() -> u64 { 10 }
+fn default_realtime_timeout() -> u64 { 3 }
+fn default_download_timeout() -> u64 { 60 }
+fn default_user_agent() -> String {
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36".to_string()
+}
+fn default_stale_threshold() -> i64 { 300 }
+fn default_sina_max_concurrency() -> usize { 16 }
+fn default_max_parse_jobs() -> usize { 4 }
+fn default_sina_queue_warn_ms() -> u64 { 1000 }
+fn default_sina_ban_cooldown_secs() -> u64 { 300 }
+fn default_realtime_sources() -> Vec<String> {
+    vec!["sina".to_string(), "eastmoney".to_string()]
+}
+fn default_max_range_days() -> i64 { 92 }
+fn default_max_query_length() -> usize { 4096 }
+fn default_max_response_rows() -> usize { 5000 }
+fn default_node_list_limit() -> usize { 100 }
+fn default_overview_cache_secs() -> u64 { 15 }
+fn default_breaker_failure_threshold() -> u32 { 5 }
+fn default_breaker_window_secs() -> u64 { 60 }
+fn default_breaker_cooldown_secs() -> u64 { 30 }
 fn default_log_level() -> String { "info".to_string() }
+fn default_fixtures_dir() -> String { "fixtures".to_string() }
+fn default_cors_methods() -> Vec<String> {
+    vec!["GET".to_string(), "POST".to_string()]
+}
+fn default_cors_max_age() -> usize { 3600 }
+fn default_pool_max_idle_per_host() -> usize { 16 }
+fn default_pool_idle_timeout() -> u64 { 90 }
+fn default_allow_insecure_hosts() -> Vec<String> {
+    ["www.99qh.com", "www.9qihuo.com", "www.gtjaqh.com", "www.czce.com.cn"]
+        .iter()
+        .map(|host| host.to_string())
+        .collect()
+}
+fn default_history_limit() -> usize { 30 }
+fn default_list_limit() -> usize { 20 }
+fn default_list_varieties_per_exchange() -> usize { 5 }
+fn default_list_varieties_all_exchanges() -> usize { 2 }
+fn default_stock_list_limit() -> usize { 20 }
 
 impl Default for ServerConfig {
     fn default() -> Self {
@@ -77,8 +335,30 @@ impl Default for ApiConfig {
     fn default() -> Self {
         Self {
             api_key: String::new(),
+            admin_key: String::new(),
+            key_header: default_key_header(),
+            allow_query_key: false,
             timeout_secs: default_timeout(),
             connect_timeout_secs: default_connect_timeout(),
+            realtime_timeout_secs: default_realtime_timeout(),
+            download_timeout_secs: default_download_timeout(),
+            user_agent: default_user_agent(),
+            stale_threshold_secs: default_stale_threshold(),
+            sina_max_concurrency: default_sina_max_concurrency(),
+            max_parse_jobs: default_max_parse_jobs(),
+            sina_queue_warn_ms: default_sina_queue_warn_ms(),
+            sina_ban_cooldown_secs: default_sina_ban_cooldown_secs(),
+            realtime_sources: default_realtime_sources(),
+            missing_price_as_zero: false,
+            allow_expired_realtime: false,
+            max_range_days: default_max_range_days(),
+            max_query_length: default_max_query_length(),
+            max_response_rows: default_max_response_rows(),
+            node_list_limit: default_node_list_limit(),
+            overview_cache_secs: default_overview_cache_secs(),
+            breaker_failure_threshold: default_breaker_failure_threshold(),
+            breaker_window_secs: default_breaker_window_secs(),
+            breaker_cooldown_secs: default_breaker_cooldown_secs(),
         }
     }
 }
@@ -91,12 +371,86 @@ impl Default for LogConfig {
     }
 }
 
-impl Default for AppConfig {
+impl LogConfig {
+    /// 解析日志过滤规则，返回各条规则中的最高级别
+    ///
+    /// 规则以逗号分隔，每条为 `级别`、`模块路径` 或 `模块路径=级别`；只写模块路径时该模块
+    /// 输出全部级别。末尾的 `/正则` 消息过滤不参与解析。任一条规则无效时返回错误
+    pub fn max_level(&self) -> anyhow::Result<log::LevelFilter> {
+        let spec = self.level.split('/').next().unwrap_or_default();
+        let mut max = log::LevelFilter::Off;
+
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let level = match directive.split_once('=') {
+                Some((module, level)) => {
+                    if module.trim().is_empty() {
+                        anyhow::bail!("日志规则 {} 缺少模块路径", directive);
+                    }
+                    level
+                        .trim()
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("日志规则 {} 的级别无效", directive))?
+                }
+                // 单独的级别作为全局级别，否则视为模块路径
+                None => directive.parse().unwrap_or(log::LevelFilter::Trace),
+            };
+            max = max.max(level);
+        }
+
+        Ok(max)
+    }
+
+    /// 是否包含按模块指定的规则（启动后无法通过热更新调整）
+    pub fn has_module_directives(&self) -> bool {
+        self.level
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .any(|d| d.contains('=') || d.parse::<log::LevelFilter>().is_err())
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allow_credentials: false,
+            allowed_methods: default_cors_methods(),
+            max_age_secs: default_cors_max_age(),
+        }
+    }
+}
+
+impl Default for MockConfig {
     fn default() -> Self {
         Self {
-            server: ServerConfig::default(),
-            api: ApiConfig::default(),
-            log: LogConfig::default(),
+            enabled: false,
+            fixtures_dir: default_fixtures_dir(),
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            pool_idle_timeout_secs: default_pool_idle_timeout(),
+            allow_insecure_hosts: default_allow_insecure_hosts(),
+        }
+    }
+}
+
+impl Default for DefaultsConfig {
+    fn default() -> Self {
+        Self {
+            history_limit: default_history_limit(),
+            list_limit: default_list_limit(),
+            list_varieties_per_exchange: default_list_varieties_per_exchange(),
+            list_varieties_all_exchanges: default_list_varieties_all_exchanges(),
+            stock_list_limit: default_stock_list_limit(),
         }
     }
 }
@@ -109,26 +463,101 @@ impl AppConfig {
         Ok(config)
     }
 
+    /// 从默认路径读取配置文件并应用环境变量覆盖
+    ///
+    /// 未找到配置文件时以默认值为基础；文件或环境变量解析失败时返回错误
+    pub fn load_from_disk() -> anyhow::Result<Self> {
+        let config_paths = ["config.json", "config/config.json"];
+
+        let (base, file) = match config_paths.iter().find(|p| Path::new(p).exists()) {
+            Some(path) => {
+                let config = Self::from_file(path)
+                    .map_err(|e| anyhow::anyhow!("加载配置文件 {} 失败: {}", path, e))?;
+                log::info!("从 {} 加载配置成功", path);
+                (config, Some(path.to_string()))
+            }
+            None => {
+                log::warn!("未找到配置文件（config.json 或 config/config.json），使用默认值");
+                (Self::default(), None)
+            }
+        };
+
+        let (mut config, env_overrides) = base.apply_env_overrides(std::env::vars())?;
+        config.sources = ConfigSources { file, env_overrides };
+        Ok(config)
+    }
+
+    /// 应用 AKSHARE_ 前缀的环境变量覆盖，返回新配置和被覆盖的配置路径
+    ///
+    /// 值按目标字段的类型解析：数字、布尔按字面量解析，字符串原样使用，
+    /// 数组支持 JSON 数组或逗号分隔；当前为 null 的可选字段先按 JSON 字面量解析
+    /// （数字、布尔、数组），不是合法字面量时作为字符串，填 null 表示清空
+    pub fn apply_env_overrides(
+        self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> anyhow::Result<(Self, Vec<String>)> {
+        let mut tree = serde_json::to_value(&self)?;
+        let mut overridden = Vec::new();
+
+        let mut vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+            .collect();
+        vars.sort();
+
+        for (key, raw) in vars {
+            let path: Vec<String> = key[ENV_PREFIX.len()..]
+                .split("__")
+                .map(|segment| segment.to_lowercase())
+                .collect();
+            let Some(slot) = path.iter().try_fold(&mut tree, |node, segment| node.get_mut(segment))
+            else {
+                log::warn!("忽略未知的配置环境变量: {}", key);
+                continue;
+            };
+            *slot = env_value(slot, &raw)
+                .map_err(|e| anyhow::anyhow!("环境变量 {} 的值无效: {}", key, e))?;
+            overridden.push(path.join("."));
+        }
+
+        let mut config: AppConfig = serde_json::from_value(tree)?;
+        config.sources = self.sources;
+        Ok((config, overridden))
+    }
+
+    /// 记录各配置项的来源（API Key 脱敏）
+    pub fn log_sources(&self) {
+        match &self.sources.file {
+            Some(path) => log::info!("配置文件: {}", path),
+            None => log::info!("配置文件: 未找到，使用默认值"),
+        }
+        if self.sources.env_overrides.is_empty() {
+            return;
+        }
+        let tree = serde_json::to_value(self).unwrap_or(Value::Null);
+        for path in &self.sources.env_overrides {
+            let value = if path.contains("api_key") || path.contains("admin_key") {
+                "******".to_string()
+            } else {
+                path.split('.')
+                    .try_fold(&tree, |node, segment| node.get(segment))
+                    .map(|v| v.to_string())
+                    .unwrap_or_default()
+            };
+            log::info!("配置 {} 来自环境变量: {}", path, value);
+        }
+    }
+
     /// 加载配置，优先从文件，失败则使用默认值
     pub fn load() -> Self {
-        let config_paths = ["config.json", "config/config.json"];
-        
-        for path in config_paths {
-            if Path::new(path).exists() {
-                match Self::from_file(path) {
-                    Ok(config) => {
-                        log::info!("从 {} 加载配置成功", path);
-                        return config;
-                    }
-                    Err(e) => {
-                        log::warn!("加载配置文件 {} 失败: {}", path, e);
-                    }
-                }
+        match Self::load_from_disk() {
+            Ok(config) => config,
+            Err(e) => {
+                log::warn!("{}", e);
+                log::info!("使用默认配置");
+                Self::default()
             }
         }
-        
-        log::info!("使用默认配置");
-        Self::default()
     }
 
     /// 获取服务器绑定地址
@@ -136,3 +565,365 @@ impl AppConfig {
         format!("{}:{}", self.server.host, self.server.port)
     }
 }
+
+/// 按现有值的类型解析环境变量
+fn env_value(current: &Value, raw: &str) -> anyhow::Result<Value> {
+    let raw = raw.trim();
+    Ok(match current {
+        Value::String(_) => Value::String(raw.to_string()),
+        Value::Bool(_) => Value::Bool(raw.parse()?),
+        Value::Number(_) => serde_json::from_str::<serde_json::Number>(raw)
+            .map(Value::Number)
+            .map_err(|_| anyhow::anyhow!("应为数字: {}", raw))?,
+        Value::Array(_) if raw.starts_with('[') => serde_json::from_str(raw)?,
+        Value::Array(_) => Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        ),
+        Value::Null if raw.is_empty() => Value::Null,
+        // 可选字段的默认值为 null，无法得知目标类型，按 JSON 字面量解析（如 Option<u64> 填 30）
+        Value::Null => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+        _ => Value::String(raw.to_string()),
+    })
+}
+
+/// 设置全局配置（仅首次调用生效）
+pub fn init_global(config: AppConfig) {
+    if GLOBAL_CONFIG.set(RwLock::new(config)).is_err() {
+        log::warn!("全局配置已初始化，忽略重复设置");
+    }
+}
+
+/// 测试用：以模拟模式初始化全局配置，读取仓库 fixtures 目录
+///
+/// 全局配置只能初始化一次，同一进程内的测试共享模拟模式，需要 fixture 的测试先调用
+#[cfg(test)]
+pub fn init_mock_for_tests() {
+    let mut config = AppConfig::default();
+    config.mock.enabled = true;
+    config.mock.fixtures_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures").to_string();
+    init_global(config);
+}
+
+/// 在读锁内读取全局配置的部分字段，未初始化时读取默认配置
+///
+/// 按请求读取配置时使用，只复制需要的字段，不克隆整个配置
+pub fn read<T>(f: impl FnOnce(&AppConfig) -> T) -> T {
+    match GLOBAL_CONFIG.get().and_then(|lock| lock.read().ok()) {
+        Some(config) => f(&config),
+        None => f(&AppConfig::default()),
+    }
+}
+
+/// API Key 认证设置
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeyAuth {
+    /// API Key
+    pub api_key: String,
+    /// 传递 API Key 的请求头名称
+    pub key_header: String,
+    /// 是否允许通过查询参数传递
+    pub allow_query_key: bool,
+}
+
+/// 获取当前 API Key 认证设置（每次请求读取，支持热更新）
+pub fn api_key_auth() -> ApiKeyAuth {
+    GLOBAL_CONFIG
+        .get()
+        .and_then(|lock| {
+            lock.read().ok().map(|c| ApiKeyAuth {
+                api_key: c.api.api_key.clone(),
+                key_header: c.api.key_header.clone(),
+                allow_query_key: c.api.allow_query_key,
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// 管理接口密钥（每次请求读取，支持热更新），为空表示只允许本机访问
+pub fn admin_key() -> String {
+    read(|c| c.api.admin_key.clone())
+}
+
+/// 缺失价格是否按 0 输出（序列化时读取，支持热更新）
+pub fn missing_price_as_zero() -> bool {
+    GLOBAL_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok().map(|c| c.api.missing_price_as_zero))
+        .unwrap_or(false)
+}
+
+/// 是否允许请求已到期合约的实时行情（每次请求读取，支持热更新）
+pub fn allow_expired_realtime() -> bool {
+    GLOBAL_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok().map(|c| c.api.allow_expired_realtime))
+        .unwrap_or(false)
+}
+
+/// 某日（YYYYMMDD）是否配置为额外的休市日期（支持热更新）
+pub fn is_configured_holiday(date: &str) -> bool {
+    GLOBAL_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok().map(|c| c.futures.holidays.iter().any(|d| d == date)))
+        .unwrap_or(false)
+}
+
+/// 某日（YYYYMMDD）是否配置为不开夜盘（支持热更新）
+pub fn is_no_night_session_date(date: &str) -> bool {
+    GLOBAL_CONFIG
+        .get()
+        .and_then(|lock| {
+            lock.read()
+                .ok()
+                .map(|c| c.futures.no_night_session_dates.iter().any(|d| d == date))
+        })
+        .unwrap_or(false)
+}
+
+/// 列表响应单页最大条数（构造响应时读取，支持热更新）
+pub fn max_response_rows() -> usize {
+    GLOBAL_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok().map(|c| c.api.max_response_rows))
+        .unwrap_or_else(default_max_response_rows)
+}
+
+/// 查询字符串最大字节数，0 表示不限制（每次请求读取，支持热更新）
+pub fn max_query_length() -> usize {
+    GLOBAL_CONFIG
+        .get()
+        .and_then(|lock| lock.read().ok().map(|c| c.api.max_query_length))
+        .unwrap_or_else(default_max_query_length)
+}
+
+/// 配置热更新结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadReport {
+    /// 已生效的配置项
+    pub applied: Vec<String>,
+    /// 需要重启服务才能生效的配置项
+    pub requires_restart: Vec<String>,
+}
+
+/// 重新读取配置文件并替换全局配置
+///
+/// 返回 (旧配置, 新配置, 变更报告)。读取失败时保留原配置
+pub fn reload_global() -> anyhow::Result<(AppConfig, AppConfig, ReloadReport)> {
+    let new = AppConfig::load_from_disk()?;
+    let lock = GLOBAL_CONFIG
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("全局配置尚未初始化"))?;
+    let mut guard = lock
+        .write()
+        .map_err(|_| anyhow::anyhow!("全局配置锁已损坏"))?;
+    let old = std::mem::replace(&mut *guard, new.clone());
+    drop(guard);
+
+    let report = reload_report(&old, &new);
+    Ok((old, new, report))
+}
+
+/// 比较新旧配置，区分可立即生效和需要重启的变更项
+pub fn reload_report(old: &AppConfig, new: &AppConfig) -> ReloadReport {
+    let mut applied = Vec::new();
+    let mut requires_restart = Vec::new();
+    let mut diff = |name: &str, changed: bool, live: bool| {
+        if changed {
+            if live {
+                applied.push(name.to_string());
+            } else {
+                requires_restart.push(name.to_string());
+            }
+        }
+    };
+
+    // 每次请求时读取的配置可直接生效
+    diff("api.api_key", old.api.api_key != new.api.api_key, true);
+    diff("api.admin_key", old.api.admin_key != new.api.admin_key, true);
+    diff("api.realtime_timeout_secs", old.api.realtime_timeout_secs != new.api.realtime_timeout_secs, true);
+    diff("api.download_timeout_secs", old.api.download_timeout_secs != new.api.download_timeout_secs, true);
+    diff("api.stale_threshold_secs", old.api.stale_threshold_secs != new.api.stale_threshold_secs, true);
+    diff("api.sina_max_concurrency", old.api.sina_max_concurrency != new.api.sina_max_concurrency, true);
+    diff("api.sina_queue_warn_ms", old.api.sina_queue_warn_ms != new.api.sina_queue_warn_ms, true);
+    diff("api.sina_ban_cooldown_secs", old.api.sina_ban_cooldown_secs != new.api.sina_ban_cooldown_secs, true);
+    diff("api.realtime_sources", old.api.realtime_sources != new.api.realtime_sources, true);
+    diff("api.allow_query_key", old.api.allow_query_key != new.api.allow_query_key, true);
+    diff("api.missing_price_as_zero", old.api.missing_price_as_zero != new.api.missing_price_as_zero, true);
+    diff("api.allow_expired_realtime", old.api.allow_expired_realtime != new.api.allow_expired_realtime, true);
+    diff("api.max_range_days", old.api.max_range_days != new.api.max_range_days, true);
+    diff("api.max_query_length", old.api.max_query_length != new.api.max_query_length, true);
+    diff("api.max_response_rows", old.api.max_response_rows != new.api.max_response_rows, true);
+    diff("api.node_list_limit", old.api.node_list_limit != new.api.node_list_limit, true);
+    diff("api.overview_cache_secs", old.api.overview_cache_secs != new.api.overview_cache_secs, true);
+    diff("api.breaker_failure_threshold", old.api.breaker_failure_threshold != new.api.breaker_failure_threshold, true);
+    diff("api.breaker_window_secs", old.api.breaker_window_secs != new.api.breaker_window_secs, true);
+    diff("api.breaker_cooldown_secs", old.api.breaker_cooldown_secs != new.api.breaker_cooldown_secs, true);
+    diff("futures.extra_main_contracts", old.futures.extra_main_contracts != new.futures.extra_main_contracts, true);
+    diff("futures.no_night_session_dates", old.futures.no_night_session_dates != new.futures.no_night_session_dates, true);
+    diff("futures.holidays", old.futures.holidays != new.futures.holidays, true);
+    diff("http.allow_insecure_hosts", old.http.allow_insecure_hosts != new.http.allow_insecure_hosts, true);
+    diff("storage.cache_dir", old.storage.cache_dir != new.storage.cache_dir, true);
+    diff("log.level", old.log.level != new.log.level, true);
+    diff("debug.endpoints_enabled", old.debug.endpoints_enabled != new.debug.endpoints_enabled, true);
+    diff("mock.enabled", old.mock.enabled != new.mock.enabled, true);
+    diff("mock.fixtures_dir", old.mock.fixtures_dir != new.mock.fixtures_dir, true);
+    diff("defaults.history_limit", old.defaults.history_limit != new.defaults.history_limit, true);
+    diff("defaults.list_limit", old.defaults.list_limit != new.defaults.list_limit, true);
+    diff(
+        "defaults.list_varieties_per_exchange",
+        old.defaults.list_varieties_per_exchange != new.defaults.list_varieties_per_exchange,
+        true,
+    );
+    diff(
+        "defaults.list_varieties_all_exchanges",
+        old.defaults.list_varieties_all_exchanges != new.defaults.list_varieties_all_exchanges,
+        true,
+    );
+    diff("defaults.stock_list_limit", old.defaults.stock_list_limit != new.defaults.stock_list_limit, true);
+    diff("startup.warm_cache", old.startup.warm_cache != new.startup.warm_cache, false);
+    diff(
+        "startup.symbol_mark_refresh_secs",
+        old.startup.symbol_mark_refresh_secs != new.startup.symbol_mark_refresh_secs,
+        false,
+    );
+    // 启动时固化在监听器、共享客户端或中间件中的配置需要重启
+    diff("server.host", old.server.host != new.server.host, false);
+    diff("server.port", old.server.port != new.server.port, false);
+    diff("server.workers", old.server.workers != new.server.workers, false);
+    diff("api.timeout_secs", old.api.timeout_secs != new.api.timeout_secs, false);
+    diff("api.connect_timeout_secs", old.api.connect_timeout_secs != new.api.connect_timeout_secs, false);
+    diff("api.user_agent", old.api.user_agent != new.api.user_agent, false);
+    // 认证立即按新请求头名称校验，但 CORS 允许的请求头在启动时固化
+    diff("api.key_header", old.api.key_header != new.api.key_header, false);
+    diff("api.max_parse_jobs", old.api.max_parse_jobs != new.api.max_parse_jobs, false);
+    diff("cors", format!("{:?}", old.cors) != format!("{:?}", new.cors), false);
+    diff("http.pool_max_idle_per_host", old.http.pool_max_idle_per_host != new.http.pool_max_idle_per_host, false);
+    diff("http.pool_idle_timeout_secs", old.http.pool_idle_timeout_secs != new.http.pool_idle_timeout_secs, false);
+
+    ReloadReport { applied, requires_restart }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn env_overrides_file_values() {
+        let file: AppConfig =
+            serde_json::from_str(r#"{"server": {"host": "127.0.0.1", "port": 8080}}"#).unwrap();
+        assert_eq!(file.server.port, 8080);
+
+        let (config, overridden) = file
+            .apply_env_overrides(vars(&[
+                ("AKSHARE_SERVER__PORT", "9090"),
+                ("AKSHARE_STORAGE__CACHE_DIR", "/data/cache"),
+                ("AKSHARE_API__REALTIME_SOURCES", "eastmoney, sina"),
+                ("OTHER_SERVER__PORT", "1"),
+            ]))
+            .unwrap();
+        assert_eq!(config.server.port, 9090);
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.storage.cache_dir.as_deref(), Some("/data/cache"));
+        assert_eq!(config.api.realtime_sources, ["eastmoney", "sina"]);
+        assert_eq!(overridden, ["api.realtime_sources", "server.port", "storage.cache_dir"]);
+    }
+
+    #[test]
+    fn reload_reports_restart_only_keys_separately() {
+        let old = AppConfig::default();
+        let mut new = old.clone();
+        new.server.port = old.server.port + 1;
+        new.api.user_agent = "other-agent".to_string();
+        new.api.api_key = "rotated".to_string();
+        new.api.max_range_days = 30;
+
+        let report = reload_report(&old, &new);
+        assert_eq!(report.applied, ["api.api_key", "api.max_range_days"]);
+        assert_eq!(report.requires_restart, ["server.port", "api.user_agent"]);
+
+        let unchanged = reload_report(&old, &old.clone());
+        assert!(unchanged.applied.is_empty() && unchanged.requires_restart.is_empty());
+    }
+
+    #[test]
+    fn env_override_with_invalid_number_fails() {
+        let result = AppConfig::default().apply_env_overrides(vars(&[("AKSHARE_SERVER__PORT", "abc")]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn null_defaults_parse_env_as_json_literal() {
+        assert_eq!(env_value(&Value::Null, "30").unwrap(), serde_json::json!(30));
+        assert_eq!(env_value(&Value::Null, "true").unwrap(), serde_json::json!(true));
+        assert_eq!(env_value(&Value::Null, "[1, 2]").unwrap(), serde_json::json!([1, 2]));
+        assert_eq!(env_value(&Value::Null, "/data/cache").unwrap(), serde_json::json!("/data/cache"));
+        assert_eq!(env_value(&Value::Null, "null").unwrap(), Value::Null);
+        assert_eq!(env_value(&Value::Null, "").unwrap(), Value::Null);
+
+        // Option<u64> 字段的默认值为 null，环境变量中的数字应能反序列化
+        #[derive(Deserialize)]
+        struct Limits {
+            max: Option<u64>,
+        }
+        let limits: Limits =
+            serde_json::from_value(serde_json::json!({ "max": env_value(&Value::Null, "30").unwrap() })).unwrap();
+        assert_eq!(limits.max, Some(30));
+    }
+
+    fn log(level: &str) -> LogConfig {
+        LogConfig { level: level.to_string() }
+    }
+
+    #[test]
+    fn module_log_filter_parses_and_applies_per_target() {
+        let config = log("actix_ak::services=debug,info");
+        assert_eq!(config.max_level().unwrap(), log::LevelFilter::Debug);
+        assert!(config.has_module_directives());
+
+        // 与启动时相同，由 env_logger 解析同一字符串
+        let filter = env_logger::filter::Builder::new().parse(&config.level).build();
+        let enabled = |target: &str, level: log::Level| {
+            filter.enabled(&log::Metadata::builder().target(target).level(level).build())
+        };
+        assert!(enabled("actix_ak::services::futures::sina", log::Level::Debug));
+        assert!(!enabled("actix_ak::handlers", log::Level::Debug));
+        assert!(enabled("actix_ak::handlers", log::Level::Info));
+        assert!(!enabled("actix_web::middleware", log::Level::Debug));
+    }
+
+    #[test]
+    fn warn_level_suppresses_service_request_traces() {
+        // 服务层的请求/解析日志均为 debug 级别
+        let traces = [
+            "actix_ak::services::futures::kline",
+            "actix_ak::services::futures::position_rank",
+            "actix_ak::services::futures::sina",
+        ];
+        let enabled = |level: &str, target: &str| {
+            let filter = env_logger::filter::Builder::new().parse(level).build();
+            filter.enabled(&log::Metadata::builder().target(target).level(log::Level::Debug).build())
+        };
+        for target in traces {
+            assert!(!enabled("warn", target), "{}", target);
+            assert!(enabled("debug", target), "{}", target);
+        }
+    }
+
+    #[test]
+    fn plain_and_invalid_log_levels() {
+        assert_eq!(log("warn").max_level().unwrap(), log::LevelFilter::Warn);
+        assert!(!log("warn").has_module_directives());
+        assert_eq!(log("info,actix_ak").max_level().unwrap(), log::LevelFilter::Trace);
+        assert_eq!(log("error,hyper=off/timeout").max_level().unwrap(), log::LevelFilter::Error);
+        assert!(log("actix_ak=loud").max_level().is_err());
+        assert!(log("=debug").max_level().is_err());
+    }
+}
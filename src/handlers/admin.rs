@@ -0,0 +1,101 @@
+//! 管理接口
+//!
+//! 提供配置热更新、数据源状态看板等运维操作。除 API Key 认证外，
+//! 还需通过 AdminGuard：配置了 api.admin_key 时校验 X-Admin-Key，否则只允许本机访问
+
+use actix_web::{web, HttpResponse, Result};
+use log::LevelFilter;
+use std::sync::OnceLock;
+
+use crate::config;
+use super::routes::{register, RouteEntry, RouteInfo};
+use crate::middleware::AdminGuard;
+use crate::models::ApiResponse;
+use crate::services::futures::{resize_sina_limiter, source_health};
+
+/// 启动时 env_logger 的日志级别上限
+///
+/// env_logger 的过滤规则在启动时固化，热更新只能在该级别以内调整
+static STARTUP_LOG_LEVEL: OnceLock<LevelFilter> = OnceLock::new();
+
+/// 启动时的日志过滤规则是否包含按模块指定的规则
+static STARTUP_MODULE_FILTER: OnceLock<bool> = OnceLock::new();
+
+/// 记录启动时的日志级别上限和过滤规则（在日志初始化后调用）
+pub fn record_startup_log_level(log_config: &config::LogConfig) {
+    let _ = STARTUP_LOG_LEVEL.set(log::max_level());
+    let _ = STARTUP_MODULE_FILTER.set(log_config.has_module_directives());
+}
+
+/// 重新加载配置文件
+///
+/// POST /api/v1/admin/reload
+///
+/// 重新读取 config.json，API Key、新浪并发限制、缓存目录等配置立即生效；
+/// 监听地址、超时、CORS 等启动时固化的配置会在 requires_restart 中列出
+pub async fn reload_config() -> Result<HttpResponse> {
+    let (old, new, mut report) = match config::reload_global() {
+        Ok(result) => result,
+        Err(e) => {
+            log::error!("配置热更新失败: {}", e);
+            return Ok(HttpResponse::InternalServerError()
+                .json(ApiResponse::<()>::error(format!("配置热更新失败: {}", e))));
+        }
+    };
+
+    if old.api.sina_max_concurrency != new.api.sina_max_concurrency
+        || old.api.sina_queue_warn_ms != new.api.sina_queue_warn_ms
+    {
+        resize_sina_limiter(new.api.sina_max_concurrency, new.api.sina_queue_warn_ms);
+    }
+
+    if old.log.level != new.log.level {
+        let ceiling = STARTUP_LOG_LEVEL.get().copied().unwrap_or(LevelFilter::Trace);
+        let module_filter = STARTUP_MODULE_FILTER.get().copied().unwrap_or(false)
+            || new.log.has_module_directives();
+        match new.log.max_level() {
+            Ok(level) if level <= ceiling && !module_filter => log::set_max_level(level),
+            Ok(_) => {
+                // 按模块的规则和高于启动级别的日志已被 env_logger 在启动时固化，需要重启才能生效
+                log::set_max_level(ceiling);
+                report.applied.retain(|name| name != "log.level");
+                report.requires_restart.push("log.level".to_string());
+            }
+            Err(e) => {
+                log::warn!("无效的日志过滤规则 {}: {}", new.log.level, e);
+                report.applied.retain(|name| name != "log.level");
+            }
+        }
+    }
+
+    log::info!(
+        "配置已重新加载，已生效: {:?}，需重启: {:?}",
+        report.applied,
+        report.requires_restart
+    );
+    Ok(HttpResponse::Ok().json(ApiResponse::success(report)))
+}
+
+/// 获取上游数据源状态看板
+///
+/// GET /api/v1/admin/sources
+///
+/// 每个已知上游数据源一项：最近成功时间、最近错误、连续失败次数、熔断状态和新浪封禁冷却
+pub async fn get_sources() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(ApiResponse::success_list(source_health())))
+}
+
+/// 本模块的路由表，config 按表注册
+pub const ROUTES: &[RouteEntry] = &[
+    RouteInfo::post("/api/v1/admin/reload", "重新加载配置文件").bind(|r| r.to(reload_config)),
+    RouteInfo::get("/api/v1/admin/sources", "上游数据源状态看板").bind(|r| r.to(get_sources)),
+];
+
+/// 配置管理路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .wrap(AdminGuard::new())
+            .configure(|cfg| register(cfg, "/api/v1/admin", ROUTES)),
+    );
+}
@@ -9,205 +9,460 @@
 //! - GET /futures/{symbol} - 获取单个合约实时数据
 //! - GET /futures/{symbol}/history - 获取日K线数据
 //! - GET /futures/{symbol}/minute - 获取分钟K线数据
+//! - GET /futures/minute/{symbol}/multi?periods=1,5,15 - 同时获取多个周期的分钟K线
 //! - GET /futures/{symbol}/detail - 获取合约详情
+//! - POST /futures/batch/detail - 批量获取实时数据，返回每个合约的获取状态
+//! - GET /futures/sse?symbols=CU2405,RB2405 - 实时行情 SSE 推送
 //! 
 //! ### 品种和交易所
 //! - GET /futures/exchanges - 获取交易所列表
-//! - GET /futures/symbols - 获取品种映射表
+//! - GET /futures/symbols - 获取按交易所分组的品种映射表
 //! - GET /futures/symbols/{exchange} - 获取指定交易所品种
+//! - GET /futures/variety-map?q=铜 - 中文品种名称与英文代码对照表
+//! - GET /futures/overview - 首页市场概览
+//! - GET /futures/variety/{name}/contracts - 获取品种所有在交易合约
+//! - GET /futures/contracts/{symbol}/expiry - 按交易所规则推算合约最后交易日和交割日
 //! 
 //! ### 主力连续合约
-//! - GET /futures/main/display - 获取主力连续合约一览
+//! - GET /futures/main - 获取主力连续合约一览（同 /futures/main/display）
 //! - GET /futures/main/{symbol}/daily - 获取主力连续日K线
+//! - GET /futures/main/{key} - 交易所主力合约列表，或主力连续日K线（key 非交易所代码时）
 //! 
 //! ### 持仓和费用
 //! - GET /futures/hold_pos - 获取持仓排名
-//! - GET /futures/fees - 获取交易费用
+//! - GET /futures/hold-pos/sina - 新浪成交持仓排名（成交量/多单/空单）
+//! - GET /futures/position-rank/{exchange} - 持仓排名表统一入口
+//! - GET /futures/position-rank/sum - 持仓排名汇总统一入口（单日或日期区间）
+//! - GET /futures/position-net/{exchange}/{contract} - 获取会员净持仓
+//! - GET /futures/concentration/{exchange}/{contract} - 获取合约持仓集中度（CR5、HHI）
+//! - GET /futures/position-diff/{exchange}/{contract} - 对比两日会员持仓排名
+//! - GET /futures/fees?exchange=SHFE&sort=long_margin_rate&order=desc - 获取交易费用（可过滤、排序）
+//! - GET /futures/fees/per-lot/{symbol} - 按当前价格计算单手手续费和保证金
+//! - GET /futures/comm-info - 获取手续费信息（九期网不可用时降级为 OpenCTP）
 //! - GET /futures/rule - 获取交易规则
+//! - GET /futures/warehouse/{exchange}?aggregate=variety - 仓单日报（可按品种汇总）
+//! 
+//! ### 库存数据
+//! - GET /futures/inventory99 - 获取99期货网库存数据
+//! - GET /futures/inventory/{symbol} - 按日期范围获取库存数据
 //! 
 //! ### 现货价格
 //! - GET /futures/spot_price - 获取现货价格及基差
-//! - GET /futures/spot_price_previous - 获取历史现货价格
+//! - GET /futures/spot_price_previous - 获取历史现货价格（别名 /futures/spot-price/previous）
 //! - GET /futures/spot_price_daily - 获取现货价格日线
+//! 
+//! ### 外盘期货
+//! - GET /futures/foreign/symbols - 获取外盘品种列表
+//! - GET /futures/foreign/realtime?codes=GC,CL - 获取外盘实时行情（也支持 POST 代码数组）
+//! - GET /futures/foreign/{code}/hist - 获取外盘日K线
+//! - GET /futures/foreign/{code}/detail - 获取外盘合约详情
+//! 
+//! ### 调试
+//! - GET /futures/debug/format - 诊断实时行情代码格式（需开启 debug.endpoints_enabled）
 
-use actix_web::{web, HttpResponse, Result};
+use std::collections::BTreeMap;
+use actix_web::{http::header, web, HttpResponse, Result};
+use serde::Serialize;
+use super::routes::{register, RouteEntry, RouteInfo};
+use super::{error_response, error_status, ok_or_empty, ok_or_empty_on};
+use crate::config;
 use crate::models::{
-    ApiResponse, FuturesInfo, FuturesHistoryData, FuturesQuery,
-    FuturesSymbolMark, FuturesContractDetail,
-    FuturesMainContract, FuturesMainDailyData, FuturesHoldPosition,
-    FuturesHoldPosQuery, FuturesMainQuery,
-    ForeignFuturesHistData, ForeignFuturesDetail, FuturesFeesInfo,
-    FuturesCommInfo, FuturesCommQuery, FuturesRule, FuturesRuleQuery,
-    Futures99Symbol, FuturesInventory99, FuturesInventory99Query,
-    FuturesSpotPrice, FuturesSpotPriceQuery,
-    FuturesSpotPricePrevious, FuturesSpotPricePreviousQuery,
-    FuturesSpotPriceDailyQuery, RankTableQuery, RankSumDailyQuery, RankTableResponse,
-    RankSum, CzceWarehouseReceiptResponse, DceWarehouseReceipt,
-    ShfeWarehouseReceiptResponse, GfexWarehouseReceiptResponse
+    parse_yyyymmdd, ApiResponse, FuturesBatchItem, FuturesInfo, FuturesHistoryData, FuturesQuery, FuturesQuote, PrecisionQuery,
+    RealtimeFieldsQuery, KlineMetaQuery,
+    DebugFormatQuery, RealtimeRequestDebug, FuturesStreamQuery,
+    FuturesSymbolEntry, FuturesSymbolsQuery, FuturesVarietyMapQuery, FuturesContractDetail, FuturesContractExpiry,
+    FuturesHoldPosQuery, FuturesMainQuery, SinaHoldPosQuery, SinaHoldPosType,
+    ForeignFuturesDetail, ForeignRealtimeQuery,
+    FuturesCommInfoResult, FuturesCommQuery, FuturesFeesInfo, FuturesFeesQuery, FuturesRule,
+    FuturesRuleQuery,
+    FuturesInventory99, FuturesInventory99Query, FuturesInventoryRangeQuery,
+    FuturesSpotPriceQuery,
+    FuturesSpotPricePreviousQuery,
+    FuturesSpotPriceDailyQuery, RankTableQuery, RankSumDailyQuery, RankSumQuery,
+    VarietyContractsQuery, PositionConcentration, PositionNetQuery, PositionNetResponse, PositionDiffQuery, PositionDiffResponse,
+    FuturesOverview, FuturesPerLotCost, DebugRawQuery, FuturesCommInfo, WarehouseAggregateQuery, WarehouseReceiptTotal,
+    with_price_format
 };
 use crate::services::futures::{
-    FuturesService, get_futures_history, get_futures_minute_data,
-    get_foreign_futures_symbols, get_foreign_futures_realtime,
+    FuturesService, apply_history_precision, get_contract_expiry, apply_info_precision,
+    ensure_published, get_futures_history, get_futures_minute_data, get_futures_minute_multi, MINUTE_PERIODS,
+    get_foreign_futures_symbols, get_foreign_futures_realtime, validate_foreign_codes,
     get_futures_display_main_sina, get_futures_main_sina, get_futures_hold_pos_sina,
-    get_futures_foreign_hist, get_futures_foreign_detail, get_futures_fees_info,
-    get_futures_comm_info, get_futures_rule,
+    variety_name_map,
+    get_futures_foreign_hist, get_futures_foreign_detail, get_futures_fees_info, filter_fees_info, sort_fees_info, get_futures_per_lot_cost,
+    filter_futures_rules, get_futures_comm_info, get_futures_comm_info_with_fallback, get_futures_rule,
     get_99_symbol_map, get_futures_inventory_99, get_futures_spot_price,
+    find_99_product_id, get_futures_inventory_99_by_id, filter_inventory_99,
     get_futures_spot_price_previous, get_futures_spot_price_daily,
-    get_shfe_rank_table, get_cffex_rank_table, get_dce_rank_table, get_rank_table_czce,
-    get_gfex_rank_table, get_rank_sum, get_rank_sum_daily,
+    get_latest_spot_price_previous, filter_spot_price_previous,
+    get_rank_table, RANK_EXCHANGES, get_rank_sum_cached, get_rank_sum_daily, rank_cache_key, check_date_range, get_position_net, get_position_concentration, get_position_diff,
     futures_warehouse_receipt_czce, futures_warehouse_receipt_dce,
-    futures_shfe_warehouse_receipt, futures_gfex_warehouse_receipt
+    futures_shfe_warehouse_receipt, futures_gfex_warehouse_receipt,
+    aggregate_czce_receipts, aggregate_dce_receipts, aggregate_shfe_receipts, aggregate_gfex_receipts,
+    with_file_cache, beijing_today_yyyymmdd, beijing_trading_day_yyyymmdd, kline_meta, get_futures_overview, validate_contract_symbol
 };
 
+/// 按 fields 参数输出实时行情列表：quote 为精简视图，full（默认）为完整对象
+fn realtime_list_response(futures_list: Vec<FuturesInfo>, quote_only: bool) -> HttpResponse {
+    realtime_list_response_with_warnings(futures_list, quote_only, Vec::new())
+}
+
+/// 同 realtime_list_response，另附带提示信息
+fn realtime_list_response_with_warnings(
+    mut futures_list: Vec<FuturesInfo>,
+    quote_only: bool,
+    warnings: Vec<String>,
+) -> HttpResponse {
+    futures_list.iter_mut().for_each(FuturesInfo::clear_price_strings);
+    if quote_only {
+        let quotes: Vec<FuturesQuote> = futures_list.iter().map(FuturesQuote::from).collect();
+        HttpResponse::Ok().json(ApiResponse::success_list(quotes).with_warnings(warnings))
+    } else {
+        HttpResponse::Ok().json(ApiResponse::success_list(futures_list).with_warnings(warnings))
+    }
+}
+
+/// fields 参数无效时的 400 响应
+fn bad_fields_response(e: anyhow::Error) -> HttpResponse {
+    HttpResponse::BadRequest().json(ApiResponse::<Vec<FuturesInfo>>::error(e.to_string()))
+}
+
+/// 解析 debug_raw 参数，取值无效或未开启 debug.endpoints_enabled 时返回错误
+fn debug_raw_requested(query: &DebugRawQuery) -> anyhow::Result<bool> {
+    let enabled = query.enabled()?;
+    if enabled && !config::read(|c| c.debug.endpoints_enabled) {
+        return Err(anyhow::anyhow!("debug_raw 需在配置中开启 debug.endpoints_enabled"));
+    }
+    Ok(enabled)
+}
+
+/// 校验 YYYYMMDD 日期参数，任一不合法时返回 400 响应
+///
+/// 服务层按位置截取年月日拼接上游地址，非法日期需在调用前拦截
+fn invalid_dates(dates: &[(&str, Option<&str>)]) -> Option<HttpResponse> {
+    dates.iter().find_map(|&(field, value)| {
+        let e = parse_yyyymmdd(value?).err()?;
+        let response = ApiResponse::<()>::error(format!("{} {}", field, e));
+        Some(HttpResponse::BadRequest().json(response))
+    })
+}
+
+/// 按日查询接口实际使用的日期：date 缺省时取北京时间最近交易日（周末回退到周五）
+fn effective_date(date: Option<&str>) -> String {
+    date.map(str::to_string).unwrap_or_else(beijing_trading_day_yyyymmdd)
+}
+
+/// 校验合约代码格式，存在明显错误的代码时返回 400 响应，列出全部无效代码
+///
+/// 格式错误的代码（如 CU240）请求新浪只会得到空数据，需在请求上游前拦截
+fn invalid_symbols<S: AsRef<str>>(symbols: &[S]) -> Option<HttpResponse> {
+    let errors: Vec<String> = symbols
+        .iter()
+        .filter_map(|s| validate_contract_symbol(s.as_ref()).err())
+        .map(|e| e.to_string())
+        .collect();
+    if errors.is_empty() {
+        return None;
+    }
+    let response = ApiResponse::<()>::error(errors.join("; "));
+    Some(HttpResponse::BadRequest().json(response))
+}
+
 /// 获取单个期货合约实时数据
 /// 
 /// GET /api/v1/futures/{symbol}
 /// 
 /// # 参数
 /// - symbol: 合约代码（如 RB2510）
-pub async fn get_futures_info(path: web::Path<String>) -> Result<HttpResponse> {
+/// - fields: full（默认）或 quote（仅返回 symbol/price/change_percent/ts）
+/// - precision: raw（默认）或 fixed（另返回按最小变动价位对齐的 *_str 字符串价格）
+pub async fn get_futures_info(
+    path: web::Path<String>,
+    fields: web::Query<RealtimeFieldsQuery>,
+    precision: web::Query<PrecisionQuery>,
+) -> Result<HttpResponse> {
     let symbol = path.into_inner();
+    if let Some(response) = invalid_symbols(&[&symbol]) {
+        return Ok(response);
+    }
+    let quote_only = match fields.quote_only() {
+        Ok(quote_only) => quote_only,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    let fixed = match precision.fixed() {
+        Ok(fixed) => fixed,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
     let service = FuturesService::new();
     
     match service.get_futures_info(&symbol).await {
-        Ok(futures_info) => {
+        Ok(futures_info) if quote_only => {
+            let response = ApiResponse::success(FuturesQuote::from(&futures_info));
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Ok(mut futures_info) => {
+            apply_info_precision(&mut futures_info, fixed).await;
             let response = ApiResponse::success(futures_info);
             Ok(HttpResponse::Ok().json(response))
         }
         Err(e) => {
             let response = ApiResponse::<FuturesInfo>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
 
-/// 获取上期所持仓排名表
-/// GET /futures/rank/shfe?date=20240102&vars=CU,AL
-pub async fn get_rank_shfe(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
+/// 按交易所获取持仓排名表，带文件缓存，按品种统一过滤
+///
+/// /futures/rank/{exchange} 与 /futures/position-rank/{exchange} 共用，exchange 为大写交易所代码
+async fn rank_table_response(exchange: &str, query: &RankTableQuery) -> HttpResponse {
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return response;
+    }
+    let date = effective_date(query.date.as_deref());
     let vars = query
         .vars
         .as_ref()
         .map(|v| v.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect());
 
-    match get_shfe_rank_table(&query.date, vars).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<RankTableResponse>>::error(e.to_string()))),
+    let result = with_file_cache(
+        &format!("{}_rank", exchange.to_lowercase()),
+        &date,
+        &rank_cache_key(query.vars.as_deref()),
+        query.no_cache == Some(1),
+        || async { ensure_published(&date, get_rank_table(exchange, &date, vars).await?) },
+    )
+    .await;
+
+    ok_or_empty_on(result, &date)
+}
+
+/// 持仓排名表统一入口
+/// GET /futures/position-rank/{exchange}?date=20240102&vars=CU,AL
+///
+/// exchange 为 SHFE/CFFEX/DCE/CZCE/GFEX（不区分大小写），返回格式与 /futures/rank/{exchange} 相同
+pub async fn get_position_rank(
+    path: web::Path<String>,
+    query: web::Query<RankTableQuery>,
+) -> Result<HttpResponse> {
+    let exchange = path.into_inner().trim().to_uppercase();
+    if !RANK_EXCHANGES.contains(&exchange.as_str()) {
+        let response = ApiResponse::<()>::error(format!(
+            "不支持的交易所: {}，可选 {}",
+            exchange,
+            RANK_EXCHANGES.join("/")
+        ));
+        return Ok(HttpResponse::BadRequest().json(response));
     }
+    Ok(rank_table_response(&exchange, &query).await)
+}
+
+/// 获取上期所持仓排名表
+/// GET /futures/rank/shfe?date=20240102&vars=CU,AL
+pub async fn get_rank_shfe(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
+    Ok(rank_table_response("SHFE", &query).await)
 }
 
 /// 获取中金所持仓排名表
 /// GET /futures/rank/cffex?date=20240102&vars=IF,IC
 pub async fn get_rank_cffex(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
-    let vars = query
-        .vars
-        .as_ref()
-        .map(|v| v.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect());
-
-    match get_cffex_rank_table(&query.date, vars).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<RankTableResponse>>::error(e.to_string()))),
-    }
+    Ok(rank_table_response("CFFEX", &query).await)
 }
 
 /// 获取大商所持仓排名表
 /// GET /futures/rank/dce?date=20240102&vars=M,Y
 pub async fn get_rank_dce(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
-    let vars = query
-        .vars
-        .as_ref()
-        .map(|v| v.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect());
-
-    match get_dce_rank_table(&query.date, vars).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<RankTableResponse>>::error(e.to_string()))),
-    }
+    Ok(rank_table_response("DCE", &query).await)
 }
 
 /// 获取郑商所持仓排名表
-/// GET /futures/rank/czce?date=20240102
+/// GET /futures/rank/czce?date=20240102&vars=SR,CF
 pub async fn get_rank_czce(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
-    match get_rank_table_czce(&query.date).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<RankTableResponse>>::error(e.to_string()))),
-    }
+    Ok(rank_table_response("CZCE", &query).await)
 }
 
 /// 获取广期所持仓排名表
 /// GET /futures/rank/gfex?date=20240102&vars=SI,LC
 pub async fn get_rank_gfex(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
-    let vars = query
-        .vars
-        .as_ref()
-        .map(|v| v.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect());
+    Ok(rank_table_response("GFEX", &query).await)
+}
 
-    match get_gfex_rank_table(&query.date, vars).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<RankTableResponse>>::error(e.to_string()))),
-    }
+/// 解析持仓排名汇总的品种列表
+fn rank_sum_vars(vars: Option<&str>) -> Option<Vec<String>> {
+    vars.map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
 }
 
 /// 获取持仓排名汇总
 /// GET /futures/rank/sum?date=20240102&vars=CU,AL
+///
+/// 结果按日期和品种写入文件缓存（rank_sum），历史日期命中缓存时不再请求各交易所
 pub async fn get_rank_sum_data(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
-    let vars = query
-        .vars
-        .as_ref()
-        .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect());
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
+    }
+    let date = effective_date(query.date.as_deref());
+    let vars = rank_sum_vars(query.vars.as_deref());
 
-    match get_rank_sum(&query.date, vars).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<RankSum>>::error(e.to_string()))),
+    Ok(ok_or_empty_on(get_rank_sum_cached(&date, vars, query.no_cache == Some(1)).await, &date))
+}
+
+/// 持仓排名汇总统一入口
+/// GET /futures/position-rank/sum?date=20240102&vars=CU,AL
+/// GET /futures/position-rank/sum?start_date=20240102&end_date=20240110&vars=CU,AL
+///
+/// date 与 start_date/end_date 二选一：单日同 /futures/rank/sum，区间同 /futures/rank/sum_daily，
+/// 区间跨度不能超过 api.max_range_days；都未提供时查询最近交易日
+pub async fn get_position_rank_sum(query: web::Query<RankSumQuery>) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[
+        ("date", query.date.as_deref()),
+        ("start_date", query.start_date.as_deref()),
+        ("end_date", query.end_date.as_deref()),
+    ]) {
+        return Ok(response);
+    }
+    let vars = rank_sum_vars(query.vars.as_deref());
+
+    match (&query.date, &query.start_date, &query.end_date) {
+        (date, None, None) => {
+            let date = effective_date(date.as_deref());
+            Ok(ok_or_empty_on(
+                get_rank_sum_cached(&date, vars, query.no_cache == Some(1)).await,
+                &date,
+            ))
+        }
+        (None, Some(start_date), Some(end_date)) => {
+            let range = parse_yyyymmdd(start_date)
+                .and_then(|start| Ok((start, parse_yyyymmdd(end_date)?)))
+                .and_then(|(start, end)| {
+                    if start > end {
+                        anyhow::bail!("start_date 不能晚于 end_date");
+                    }
+                    check_date_range(start, end)
+                });
+            if let Err(e) = range {
+                let response = ApiResponse::<()>::error(e.to_string());
+                return Ok(HttpResponse::BadRequest().json(response));
+            }
+            Ok(ok_or_empty(get_rank_sum_daily(start_date, end_date, vars).await))
+        }
+        _ => {
+            let response = ApiResponse::<()>::error(
+                "start_date 和 end_date 需同时提供（日期区间），查询单日请使用 date".to_string(),
+            );
+            Ok(HttpResponse::BadRequest().json(response))
+        }
     }
 }
 
 /// 获取持仓排名汇总（日期区间）
 /// GET /futures/rank/sum_daily?start_date=20240102&end_date=20240110&vars=CU,AL
 pub async fn get_rank_sum_daily_data(query: web::Query<RankSumDailyQuery>) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[
+        ("start_date", Some(&query.start_date)),
+        ("end_date", Some(&query.end_date)),
+    ]) {
+        return Ok(response);
+    }
     let vars = query
         .vars
         .as_ref()
         .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect());
 
-    match get_rank_sum_daily(&query.start_date, &query.end_date, vars).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<RankSum>>::error(e.to_string()))),
+    Ok(ok_or_empty(get_rank_sum_daily(&query.start_date, &query.end_date, vars).await))
+}
+
+/// 仓单日报响应：按品种汇总时返回各品种合计，否则返回逐仓库明细
+fn warehouse_response<T: Serialize>(
+    result: anyhow::Result<Vec<T>>,
+    date: &str,
+    by_variety: bool,
+    totals: fn(&[T]) -> Vec<WarehouseReceiptTotal>,
+) -> HttpResponse {
+    if by_variety {
+        ok_or_empty_on(result.map(|data| totals(&data)), date)
+    } else {
+        ok_or_empty_on(result, date)
     }
 }
 
 /// 获取郑商所仓单日报
-/// GET /futures/warehouse/czce?date=20240102
-pub async fn get_warehouse_czce(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
-    match futures_warehouse_receipt_czce(&query.date).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<CzceWarehouseReceiptResponse>>::error(e.to_string()))),
+/// GET /futures/warehouse/czce?date=20240102&aggregate=variety
+pub async fn get_warehouse_czce(
+    query: web::Query<RankTableQuery>,
+    aggregate: web::Query<WarehouseAggregateQuery>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
     }
+    let by_variety = match aggregate.by_variety() {
+        Ok(by_variety) => by_variety,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    let date = effective_date(query.date.as_deref());
+    let result = futures_warehouse_receipt_czce(&date)
+        .await
+        .and_then(|data| ensure_published(&date, data));
+    Ok(warehouse_response(result, &date, by_variety, aggregate_czce_receipts))
 }
 
 /// 获取大商所仓单日报
-/// GET /futures/warehouse/dce?date=20240102
-pub async fn get_warehouse_dce(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
-    match futures_warehouse_receipt_dce(&query.date).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<DceWarehouseReceipt>>::error(e.to_string()))),
+/// GET /futures/warehouse/dce?date=20240102&aggregate=variety
+pub async fn get_warehouse_dce(
+    query: web::Query<RankTableQuery>,
+    aggregate: web::Query<WarehouseAggregateQuery>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
     }
+    let by_variety = match aggregate.by_variety() {
+        Ok(by_variety) => by_variety,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    let date = effective_date(query.date.as_deref());
+    let result = futures_warehouse_receipt_dce(&date)
+        .await
+        .and_then(|data| ensure_published(&date, data));
+    Ok(warehouse_response(result, &date, by_variety, aggregate_dce_receipts))
 }
 
 /// 获取上期所仓单日报
-/// GET /futures/warehouse/shfe?date=20240102
-pub async fn get_warehouse_shfe(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
-    match futures_shfe_warehouse_receipt(&query.date).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<ShfeWarehouseReceiptResponse>>::error(e.to_string()))),
+/// GET /futures/warehouse/shfe?date=20240102&aggregate=variety
+pub async fn get_warehouse_shfe(
+    query: web::Query<RankTableQuery>,
+    aggregate: web::Query<WarehouseAggregateQuery>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
     }
+    let by_variety = match aggregate.by_variety() {
+        Ok(by_variety) => by_variety,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    let date = effective_date(query.date.as_deref());
+    let result = futures_shfe_warehouse_receipt(&date)
+        .await
+        .and_then(|data| ensure_published(&date, data));
+    Ok(warehouse_response(result, &date, by_variety, aggregate_shfe_receipts))
 }
 
 /// 获取广期所仓单日报
-/// GET /futures/warehouse/gfex?date=20240102
-pub async fn get_warehouse_gfex(query: web::Query<RankTableQuery>) -> Result<HttpResponse> {
-    match futures_gfex_warehouse_receipt(&query.date).await {
-        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(ApiResponse::<Vec<GfexWarehouseReceiptResponse>>::error(e.to_string()))),
+/// GET /futures/warehouse/gfex?date=20240102&aggregate=variety
+pub async fn get_warehouse_gfex(
+    query: web::Query<RankTableQuery>,
+    aggregate: web::Query<WarehouseAggregateQuery>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
     }
+    let by_variety = match aggregate.by_variety() {
+        Ok(by_variety) => by_variety,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    let date = effective_date(query.date.as_deref());
+    let result = futures_gfex_warehouse_receipt(&date)
+        .await
+        .and_then(|data| ensure_published(&date, data));
+    Ok(warehouse_response(result, &date, by_variety, aggregate_gfex_receipts))
 }
 
 /// 获取期货日K线历史数据
@@ -217,26 +472,65 @@ pub async fn get_warehouse_gfex(query: web::Query<RankTableQuery>) -> Result<Htt
 /// # 参数
 /// - symbol: 合约代码
 /// - limit: 返回数量限制（可选，默认30）
+/// - start_date/end_date: 日期范围（YYYYMMDD，可选），先按范围过滤再取最近的 limit 条
+/// - precision: raw（默认）或 fixed（另返回按最小变动价位对齐的 *_str 字符串价格）
+/// - with_meta: 为 1 时附带 meta（报价单位、最小变动价位、合约乘数）
 pub async fn get_history(
     path: web::Path<String>,
     query: web::Query<FuturesQuery>,
+    precision: web::Query<PrecisionQuery>,
+    meta: web::Query<KlineMetaQuery>,
 ) -> Result<HttpResponse> {
     let symbol = path.into_inner();
-    
-    match get_futures_history(&symbol, &query).await {
-        Ok(history_data) => {
-            let response = ApiResponse::success(history_data);
+    if let Some(response) = invalid_symbols(&[&symbol]) {
+        return Ok(response);
+    }
+    let parsed = query
+        .validate()
+        .and_then(|_| Ok((precision.fixed()?, meta.enabled()?)));
+    let (fixed, with_meta) = match parsed {
+        Ok(flags) => flags,
+        Err(e) => {
+            let response = ApiResponse::<Vec<FuturesHistoryData>>::error(e.to_string());
+            return Ok(HttpResponse::BadRequest().json(response));
+        }
+    };
+    let defaults = config::read(|c| c.defaults.clone());
+    // 只有指定了早于今天的 end_date 时结果才不会再变化，缓存按 end_date 分目录，
+    // 文件名包含 start_date 和 limit；未指定 end_date 时按今天计，不读写缓存
+    let cache_date = query.end_date.clone().unwrap_or_else(beijing_today_yyyymmdd);
+    let cache_key = format!(
+        "{}_{}_{}",
+        symbol,
+        query.start_date.as_deref().unwrap_or("all"),
+        query.limit.unwrap_or(defaults.history_limit)
+    );
+
+    let result = with_file_cache(
+        "sina_daily",
+        &cache_date,
+        &cache_key,
+        query.no_cache == Some(1),
+        || get_futures_history(&symbol, &query, &defaults),
+    )
+    .await;
+
+    match result {
+        Ok(mut history_data) => {
+            apply_history_precision(&mut history_data, fixed).await;
+            let meta = if with_meta { Some(kline_meta(&symbol).await) } else { None };
+            let response = ApiResponse::success_list(history_data).with_meta(meta);
             Ok(HttpResponse::Ok().json(response))
         }
         Err(e) => {
             let response = ApiResponse::<Vec<FuturesHistoryData>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
 
 /// 获取期货分钟K线数据
-/// GET /futures/{symbol}/minute?period=5
+/// GET /futures/{symbol}/minute?period=5&with_meta=1
 #[derive(serde::Deserialize)]
 pub struct MinuteQuery {
     pub period: Option<String>,  // 1, 5, 15, 30, 60
@@ -245,35 +539,125 @@ pub struct MinuteQuery {
 pub async fn get_minute(
     path: web::Path<String>,
     query: web::Query<MinuteQuery>,
+    meta: web::Query<KlineMetaQuery>,
 ) -> Result<HttpResponse> {
     let symbol = path.into_inner();
+    if let Some(response) = invalid_symbols(&[&symbol]) {
+        return Ok(response);
+    }
     let period = query.period.as_deref().unwrap_or("5");
+    let with_meta = match meta.enabled() {
+        Ok(with_meta) => with_meta,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
     
     match get_futures_minute_data(&symbol, period).await {
-        Ok(minute_data) => {
-            let response = ApiResponse::success(minute_data);
+        Ok(mut minute_data) => {
+            minute_data.iter_mut().for_each(FuturesHistoryData::clear_price_strings);
+            let meta = if with_meta { Some(kline_meta(&symbol).await) } else { None };
+            let response = ApiResponse::success_list(minute_data).with_meta(meta);
             Ok(HttpResponse::Ok().json(response))
         }
         Err(e) => {
             let response = ApiResponse::<Vec<FuturesHistoryData>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
+        }
+    }
+}
+
+/// 多周期分钟K线查询参数
+#[derive(serde::Deserialize)]
+pub struct MultiMinuteQuery {
+    /// 逗号分隔的周期，如 1,5,15
+    pub periods: Option<String>,
+}
+
+impl MultiMinuteQuery {
+    /// 解析周期列表：每个周期须在 MINUTE_PERIODS 中，重复的周期只取一次，
+    /// 最多 MINUTE_PERIODS.len() 个
+    fn parse(&self) -> anyhow::Result<Vec<u32>> {
+        let raw: Vec<&str> = self
+            .periods
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect();
+        if raw.is_empty() {
+            anyhow::bail!("缺少 periods 参数，如 periods=1,5,15");
+        }
+        if raw.len() > MINUTE_PERIODS.len() {
+            anyhow::bail!("periods 最多 {} 个", MINUTE_PERIODS.len());
+        }
+        let mut periods = Vec::with_capacity(raw.len());
+        for p in raw {
+            let period = p
+                .parse::<u32>()
+                .ok()
+                .filter(|period| MINUTE_PERIODS.contains(period))
+                .ok_or_else(|| anyhow::anyhow!("不支持的分钟周期: {}（可选 1/5/15/30/60）", p))?;
+            if !periods.contains(&period) {
+                periods.push(period);
+            }
+        }
+        Ok(periods)
+    }
+}
+
+/// 同时获取多个周期的分钟K线
+/// GET /futures/minute/{symbol}/multi?periods=1,5,15
+///
+/// 各周期并发获取，返回 {周期: [K线]}
+pub async fn get_minute_multi(
+    path: web::Path<String>,
+    query: web::Query<MultiMinuteQuery>,
+) -> Result<HttpResponse> {
+    let symbol = path.into_inner();
+    if let Some(response) = invalid_symbols(&[&symbol]) {
+        return Ok(response);
+    }
+    let periods = match query.parse() {
+        Ok(periods) => periods,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+
+    match get_futures_minute_multi(&symbol, &periods).await {
+        Ok(mut bars) => {
+            bars.values_mut()
+                .flatten()
+                .for_each(FuturesHistoryData::clear_price_strings);
+            Ok(HttpResponse::Ok().json(ApiResponse::success(bars)))
+        }
+        Err(e) => {
+            let response = ApiResponse::<()>::error(e.to_string());
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
 
 /// 获取期货列表（按交易所或品种）
 /// GET /futures?exchange=SHFE&limit=20
-pub async fn list_futures(query: web::Query<FuturesQuery>) -> Result<HttpResponse> {
+pub async fn list_futures(
+    query: web::Query<FuturesQuery>,
+    fields: web::Query<RealtimeFieldsQuery>,
+) -> Result<HttpResponse> {
+    if let Err(e) = query.validate() {
+        let response = ApiResponse::<Vec<FuturesInfo>>::error(e.to_string());
+        return Ok(HttpResponse::BadRequest().json(response));
+    }
+    let quote_only = match fields.quote_only() {
+        Ok(quote_only) => quote_only,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    let defaults = config::read(|c| c.defaults.clone());
     let mut service = FuturesService::new();
     
-    match service.list_main_futures(&query).await {
-        Ok(futures_list) => {
-            let response = ApiResponse::success(futures_list);
-            Ok(HttpResponse::Ok().json(response))
-        }
+    match service.list_main_futures(&query, &defaults).await {
+        Ok(futures_list) => Ok(realtime_list_response(futures_list, quote_only)),
         Err(e) => {
             let response = ApiResponse::<Vec<FuturesInfo>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
@@ -283,14 +667,14 @@ pub async fn list_futures(query: web::Query<FuturesQuery>) -> Result<HttpRespons
 pub async fn get_exchanges() -> Result<HttpResponse> {
     let service = FuturesService::new();
     let exchanges = service.get_exchanges();
-    let response = ApiResponse::success(exchanges);
+    let response = ApiResponse::success_list(exchanges);
     Ok(HttpResponse::Ok().json(response))
 }
 
 /// 批量获取期货实时数据
 /// POST /futures/batch
 pub async fn get_multiple_futures(
-    _query: web::Query<FuturesQuery>,
+    fields: web::Query<RealtimeFieldsQuery>,
     body: web::Json<Vec<String>>,
 ) -> Result<HttpResponse> {
     let symbols = body.into_inner();
@@ -300,32 +684,192 @@ pub async fn get_multiple_futures(
         let response = ApiResponse::<Vec<FuturesInfo>>::error("合约代码列表不能为空".to_string());
         return Ok(HttpResponse::BadRequest().json(response));
     }
+    if let Some(response) = invalid_symbols(&symbols) {
+        return Ok(response);
+    }
+    let quote_only = match fields.quote_only() {
+        Ok(quote_only) => quote_only,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
     
     match service.get_multiple_futures(&symbols).await {
-        Ok(futures_list) => {
-            let response = ApiResponse::success(futures_list);
-            Ok(HttpResponse::Ok().json(response))
+        Ok(futures_list) => Ok(realtime_list_response(futures_list, quote_only)),
+        Err(e) => {
+            let response = ApiResponse::<Vec<FuturesInfo>>::error(e.to_string());
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
+    }
+}
+
+/// 批量获取期货实时数据，并返回每个合约的获取状态
+/// POST /futures/batch/detail
+///
+/// 每个请求的合约返回一项 {symbol, status, data}，status 为 ok / no_data / parse_error，
+/// 无数据的合约不会被跳过
+pub async fn get_multiple_futures_detailed(body: web::Json<Vec<String>>) -> Result<HttpResponse> {
+    let symbols = body.into_inner();
+
+    if symbols.is_empty() {
+        let response = ApiResponse::<Vec<FuturesBatchItem>>::error("合约代码列表不能为空".to_string());
+        return Ok(HttpResponse::BadRequest().json(response));
+    }
+    if let Some(response) = invalid_symbols(&symbols) {
+        return Ok(response);
+    }
+
+    let result = FuturesService::new()
+        .get_multiple_futures_detailed(&symbols)
+        .await
+        .map(|mut items| {
+            items
+                .iter_mut()
+                .filter_map(|item| item.data.as_mut())
+                .for_each(FuturesInfo::clear_price_strings);
+            items
+        });
+    Ok(ok_or_empty(result))
+}
+
+/// SSE 心跳间隔，防止代理因空闲关闭连接
+const SSE_HEARTBEAT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// 实时行情 SSE 推送
+/// GET /futures/sse?symbols=CU2405,RB2405&interval_secs=3
+///
+/// 每隔 interval_secs 推送一次批量行情（data 为 JSON 数组），获取失败时推送 error 事件并继续；
+/// 定期发送注释心跳。客户端断开后流被丢弃，不再请求上游
+pub async fn get_futures_sse(query: web::Query<FuturesStreamQuery>) -> Result<HttpResponse> {
+    let symbols = match query.symbol_list() {
+        Ok(symbols) => symbols,
         Err(e) => {
             let response = ApiResponse::<Vec<FuturesInfo>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            return Ok(HttpResponse::BadRequest().json(response));
         }
+    };
+    if let Some(response) = invalid_symbols(&symbols) {
+        return Ok(response);
     }
+    let interval = match query.interval() {
+        Ok(interval) => interval,
+        Err(e) => {
+            let response = ApiResponse::<Vec<FuturesInfo>>::error(e.to_string());
+            return Ok(HttpResponse::BadRequest().json(response));
+        }
+    };
+    let quote_only = match query.quote_only() {
+        Ok(quote_only) => quote_only,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+
+    log::info!("SSE 连接建立: {}", symbols.join(","));
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(realtime_event_stream(symbols, interval, quote_only)))
 }
 
-/// 获取期货品种映射表
-/// GET /futures/symbols
-pub async fn get_symbol_mark() -> Result<HttpResponse> {
+/// SSE 连接状态
+struct SseState {
+    service: FuturesService,
+    symbols: Vec<String>,
+    interval: std::time::Duration,
+    quote_only: bool,
+    next_data: tokio::time::Instant,
+    next_heartbeat: tokio::time::Instant,
+    _guard: SseCloseGuard,
+}
+
+/// 流被丢弃（客户端断开）时记录日志
+struct SseCloseGuard(String);
+
+impl Drop for SseCloseGuard {
+    fn drop(&mut self) {
+        log::info!("SSE 连接关闭: {}", self.0);
+    }
+}
+
+/// 按间隔生成行情事件和心跳的事件流
+fn realtime_event_stream(
+    symbols: Vec<String>,
+    interval: std::time::Duration,
+    quote_only: bool,
+) -> impl futures::Stream<Item = Result<web::Bytes>> {
+    let now = tokio::time::Instant::now();
+    let state = SseState {
+        service: FuturesService::new(),
+        _guard: SseCloseGuard(symbols.join(",")),
+        symbols,
+        interval,
+        quote_only,
+        next_data: now,
+        next_heartbeat: now + SSE_HEARTBEAT,
+    };
+
+    futures::stream::unfold(state, |mut state| async move {
+        if state.next_heartbeat < state.next_data {
+            tokio::time::sleep_until(state.next_heartbeat).await;
+            state.next_heartbeat += SSE_HEARTBEAT;
+            return Some((Ok(web::Bytes::from_static(b": heartbeat\n\n")), state));
+        }
+
+        tokio::time::sleep_until(state.next_data).await;
+        let event = realtime_event(&state.service, &state.symbols, state.quote_only).await;
+        let now = tokio::time::Instant::now();
+        state.next_data = now + state.interval;
+        state.next_heartbeat = now + SSE_HEARTBEAT;
+        Some((Ok(web::Bytes::from(event)), state))
+    })
+}
+
+/// 获取一次批量行情并编码为 SSE 事件
+async fn realtime_event(service: &FuturesService, symbols: &[String], quote_only: bool) -> String {
+    let data = match service.get_multiple_futures(symbols).await {
+        Ok(list) if quote_only => {
+            let quotes: Vec<FuturesQuote> = list.iter().map(FuturesQuote::from).collect();
+            with_price_format(|| serde_json::to_string(&quotes))
+        }
+        Ok(mut list) => {
+            list.iter_mut().for_each(FuturesInfo::clear_price_strings);
+            with_price_format(|| serde_json::to_string(&list))
+        }
+        Err(e) => {
+            let message = serde_json::json!({ "message": e.to_string() });
+            return format!("event: error\ndata: {}\n\n", message);
+        }
+    };
+
+    match data {
+        Ok(json) => format!("data: {}\n\n", json),
+        Err(e) => format!("event: error\ndata: {}\n\n", serde_json::json!({ "message": e.to_string() })),
+    }
+}
+
+/// 获取按交易所分组的期货品种映射表
+/// GET /futures/symbols?exchange=SHFE
+///
+/// 返回 {交易所代码: [{symbol, mark}]}，exchange 无效时返回 400
+pub async fn get_symbol_mark(query: web::Query<FuturesSymbolsQuery>) -> Result<HttpResponse> {
+    let exchange = query.exchange.as_deref();
+    if let Some(code) = exchange.map(str::trim).filter(|e| !e.is_empty()) {
+        if !is_exchange_code(code) {
+            let response = ApiResponse::<BTreeMap<String, Vec<FuturesSymbolEntry>>>::error(
+                format!("未知交易所: {}，可选 SHFE/DCE/CZCE/CFFEX/GFEX/INE", code),
+            );
+            return Ok(HttpResponse::BadRequest().json(response));
+        }
+    }
     let mut service = FuturesService::new();
-    
-    match service.get_symbol_mark().await {
-        Ok(symbols) => {
-            let response = ApiResponse::success(symbols);
+
+    match service.get_symbol_marks_grouped(exchange).await {
+        Ok(grouped) => {
+            let response = ApiResponse::success(grouped);
             Ok(HttpResponse::Ok().json(response))
         }
         Err(e) => {
-            let response = ApiResponse::<Vec<FuturesSymbolMark>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            let response =
+                ApiResponse::<BTreeMap<String, Vec<FuturesSymbolEntry>>>::error(e.to_string());
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
@@ -336,40 +880,57 @@ pub async fn get_exchange_symbols(path: web::Path<String>) -> Result<HttpRespons
     let exchange = path.into_inner();
     let mut service = FuturesService::new();
     
-    match service.get_exchange_symbols(&exchange).await {
-        Ok(symbols) => {
-            let response = ApiResponse::success(symbols);
-            Ok(HttpResponse::Ok().json(response))
-        }
-        Err(e) => {
-            let response = ApiResponse::<Vec<FuturesSymbolMark>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
-        }
+    Ok(ok_or_empty(service.get_exchange_symbols(&exchange).await))
+}
+
+/// 获取中文品种名称与英文代码对照表
+/// GET /futures/variety-map?q=铜
+///
+/// 返回 [{cn, en, exchange}]，按交易所分组排列；q 匹配中文名称或英文代码
+pub async fn get_variety_map(query: web::Query<FuturesVarietyMapQuery>) -> Result<HttpResponse> {
+    let response = ApiResponse::success_list(variety_name_map(query.q.as_deref()));
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// 主力合约路由分发
+/// GET /futures/main/{key}
+///
+/// key 为交易所代码（如 SHFE）时返回该交易所主力合约列表，
+/// 否则视为主力连续合约代码（如 RB0），返回日K线数据（支持 start_date/end_date）
+pub async fn get_main_by_key(
+    path: web::Path<String>,
+    query: web::Query<FuturesMainQuery>,
+) -> Result<HttpResponse> {
+    let key = path.into_inner();
+    if is_exchange_code(&key) {
+        main_contracts_response(&key).await
+    } else {
+        main_daily_response(&key, &query).await
     }
 }
 
+/// 是否为支持的交易所代码
+fn is_exchange_code(code: &str) -> bool {
+    matches!(
+        code.to_uppercase().as_str(),
+        "DCE" | "CZCE" | "SHFE" | "INE" | "CFFEX" | "GFEX"
+    )
+}
+
 /// 获取交易所主力合约列表
-/// GET /futures/main/{exchange}
-pub async fn get_main_contracts(path: web::Path<String>) -> Result<HttpResponse> {
-    let exchange = path.into_inner();
+async fn main_contracts_response(exchange: &str) -> Result<HttpResponse> {
     let mut service = FuturesService::new();
-    
-    match service.get_main_contracts(&exchange).await {
-        Ok(contracts) => {
-            let response = ApiResponse::success(contracts);
-            Ok(HttpResponse::Ok().json(response))
-        }
-        Err(e) => {
-            let response = ApiResponse::<Vec<String>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
-        }
-    }
+
+    Ok(ok_or_empty(service.get_main_contracts(exchange).await))
 }
 
 /// 获取合约详情
 /// GET /futures/{symbol}/detail
 pub async fn get_contract_detail(path: web::Path<String>) -> Result<HttpResponse> {
     let symbol = path.into_inner();
+    if let Some(response) = invalid_symbols(&[&symbol]) {
+        return Ok(response);
+    }
     let service = FuturesService::new();
     
     match service.get_contract_detail(&symbol).await {
@@ -379,7 +940,21 @@ pub async fn get_contract_detail(path: web::Path<String>) -> Result<HttpResponse
         }
         Err(e) => {
             let response = ApiResponse::<FuturesContractDetail>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
+        }
+    }
+}
+
+/// 获取合约到期信息（最后交易日、最后交割日）
+/// GET /futures/contracts/{symbol}/expiry
+///
+/// 按交易所规则推算，不请求上游；合约代码无效、主力连续合约或不支持的品种返回 400
+pub async fn get_contract_expiry_info(path: web::Path<String>) -> Result<HttpResponse> {
+    match get_contract_expiry(&path.into_inner()) {
+        Ok(expiry) => Ok(HttpResponse::Ok().json(ApiResponse::success(expiry))),
+        Err(e) => {
+            let response = ApiResponse::<FuturesContractExpiry>::error(e.to_string());
+            Ok(HttpResponse::BadRequest().json(response))
         }
     }
 }
@@ -388,66 +963,168 @@ pub async fn get_contract_detail(path: web::Path<String>) -> Result<HttpResponse
 /// GET /futures/foreign/symbols
 pub async fn get_foreign_symbols() -> Result<HttpResponse> {
     let symbols = get_foreign_futures_symbols();
-    let response = ApiResponse::success(symbols);
+    let response = ApiResponse::success_list(symbols);
     Ok(HttpResponse::Ok().json(response))
 }
 
 /// 获取外盘期货实时行情
 /// POST /futures/foreign/realtime
-pub async fn get_foreign_realtime(body: web::Json<Vec<String>>) -> Result<HttpResponse> {
-    let codes = body.into_inner();
-    
-    if codes.is_empty() {
-        let response = ApiResponse::<Vec<FuturesInfo>>::error("品种代码列表不能为空".to_string());
+pub async fn get_foreign_realtime(
+    fields: web::Query<RealtimeFieldsQuery>,
+    body: web::Json<Vec<String>>,
+) -> Result<HttpResponse> {
+    foreign_realtime_response(&body.into_inner(), &fields).await
+}
+
+/// 获取外盘期货实时行情
+/// GET /futures/foreign/realtime?codes=GC,CL
+pub async fn get_foreign_realtime_by_query(
+    query: web::Query<ForeignRealtimeQuery>,
+    fields: web::Query<RealtimeFieldsQuery>,
+) -> Result<HttpResponse> {
+    let codes: Vec<String> = query.codes.split(',').map(|s| s.to_string()).collect();
+    foreign_realtime_response(&codes, &fields).await
+}
+
+/// 校验品种代码后获取外盘实时行情
+///
+/// 未知代码被忽略并在 warnings 中列出；没有任何有效代码时返回 400
+async fn foreign_realtime_response(
+    codes: &[String],
+    fields: &RealtimeFieldsQuery,
+) -> Result<HttpResponse> {
+    let quote_only = match fields.quote_only() {
+        Ok(quote_only) => quote_only,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    let (valid, unknown) = validate_foreign_codes(codes);
+    if valid.is_empty() {
+        let message = if unknown.is_empty() {
+            "品种代码列表不能为空".to_string()
+        } else {
+            format!(
+                "未知的外盘品种代码: {}，请使用 /futures/foreign/symbols 查看可用品种",
+                unknown.join(", ")
+            )
+        };
+        let response = ApiResponse::<Vec<FuturesInfo>>::error(message);
         return Ok(HttpResponse::BadRequest().json(response));
     }
-    
-    match get_foreign_futures_realtime(&codes).await {
-        Ok(futures_list) => {
-            let response = ApiResponse::success(futures_list);
-            Ok(HttpResponse::Ok().json(response))
+
+    match get_foreign_futures_realtime(codes).await {
+        Ok((futures_list, unknown)) => {
+            let warnings: Vec<String> = unknown
+                .iter()
+                .map(|code| format!("未知的外盘品种代码 {}，已忽略", code))
+                .collect();
+            Ok(realtime_list_response_with_warnings(futures_list, quote_only, warnings))
         }
         Err(e) => {
             let response = ApiResponse::<Vec<FuturesInfo>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
 
 /// 获取品种所有合约实时数据
 /// GET /futures/realtime/{symbol}
-pub async fn get_realtime_by_symbol(path: web::Path<String>) -> Result<HttpResponse> {
+pub async fn get_realtime_by_symbol(
+    path: web::Path<String>,
+    fields: web::Query<RealtimeFieldsQuery>,
+) -> Result<HttpResponse> {
     let symbol = path.into_inner();
+    let quote_only = match fields.quote_only() {
+        Ok(quote_only) => quote_only,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
     let mut service = FuturesService::new();
     
     match service.get_futures_realtime_by_symbol(&symbol).await {
-        Ok(futures_list) => {
-            let response = ApiResponse::success(futures_list);
-            Ok(HttpResponse::Ok().json(response))
-        }
+        Ok(futures_list) => Ok(realtime_list_response(futures_list, quote_only)),
         Err(e) => {
             let response = ApiResponse::<Vec<FuturesInfo>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
 
-/// 获取主力连续合约一览表
-/// GET /futures/main/display
-/// 对应 akshare 的 futures_display_main_sina()
-pub async fn get_display_main_contracts() -> Result<HttpResponse> {
-    match get_futures_display_main_sina().await {
-        Ok(contracts) => {
-            let response = ApiResponse::success(contracts);
-            Ok(HttpResponse::Ok().json(response))
+/// 获取品种所有在交易合约（按持仓量降序）
+/// GET /futures/variety/{name}/contracts?limit=10
+///
+/// 品种名称模糊匹配到多个品种时返回 409 及候选品种
+pub async fn get_variety_contracts(
+    path: web::Path<String>,
+    query: web::Query<VarietyContractsQuery>,
+    fields: web::Query<RealtimeFieldsQuery>,
+) -> Result<HttpResponse> {
+    let name = path.into_inner();
+    let quote_only = match fields.quote_only() {
+        Ok(quote_only) => quote_only,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    let mut service = FuturesService::new();
+
+    let candidates = match service.find_symbol_candidates(&name).await {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            let response = ApiResponse::<Vec<FuturesInfo>>::error(e.to_string());
+            return Ok(HttpResponse::build(error_status(&e)).json(response));
+        }
+    };
+
+    let node = match candidates.as_slice() {
+        [] => {
+            let response = ApiResponse::<Vec<FuturesInfo>>::error(format!(
+                "未找到品种 {}，请使用 /futures/symbols 查看可用品种",
+                name
+            ));
+            return Ok(HttpResponse::NotFound().json(response));
+        }
+        [only] => only.mark.clone(),
+        _ => {
+            let names: Vec<&str> = candidates.iter().map(|c| c.symbol.as_str()).collect();
+            let message = format!("品种名称 {} 匹配到多个品种: {}", name, names.join(", "));
+            let response = ApiResponse::error_with_data(message, candidates);
+            return Ok(HttpResponse::Conflict().json(response));
+        }
+    };
+
+    match service.get_futures_by_node(&node, None).await {
+        Ok(mut futures_list) => {
+            futures_list.sort_by_key(|f| std::cmp::Reverse(f.open_interest));
+            if let Some(limit) = query.limit {
+                futures_list.truncate(limit);
+            }
+            Ok(realtime_list_response(futures_list, quote_only))
         }
         Err(e) => {
-            let response = ApiResponse::<Vec<FuturesMainContract>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            let response = ApiResponse::<Vec<FuturesInfo>>::error(e.to_string());
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
 
+/// 获取首页市场概览
+/// GET /futures/overview
+///
+/// 包含精选主力连续合约行情、涨跌幅榜和最近一日基差极值，获取失败的部分会被省略
+pub async fn get_overview() -> Result<HttpResponse> {
+    match get_futures_overview().await {
+        Ok(overview) => Ok(HttpResponse::Ok().json(ApiResponse::success(overview))),
+        Err(e) => {
+            let response = ApiResponse::<FuturesOverview>::error(e.to_string());
+            Ok(HttpResponse::build(error_status(&e)).json(response))
+        }
+    }
+}
+
+/// 获取主力连续合约一览表
+/// GET /futures/main 或 /futures/main/display
+/// 对应 akshare 的 futures_display_main_sina()
+pub async fn get_display_main_contracts() -> Result<HttpResponse> {
+    Ok(ok_or_empty(get_futures_display_main_sina().await))
+}
+
 /// 获取主力连续合约日K线数据
 /// GET /futures/main/{symbol}/daily?start_date=20240101&end_date=20240301
 /// 对应 akshare 的 futures_main_sina()
@@ -455,58 +1132,78 @@ pub async fn get_main_daily(
     path: web::Path<String>,
     query: web::Query<FuturesMainQuery>,
 ) -> Result<HttpResponse> {
-    let symbol = path.into_inner();
-    
-    match get_futures_main_sina(
-        &symbol,
-        query.start_date.as_deref(),
-        query.end_date.as_deref(),
-    ).await {
-        Ok(data) => {
-            let response = ApiResponse::success(data);
-            Ok(HttpResponse::Ok().json(response))
-        }
-        Err(e) => {
-            let response = ApiResponse::<Vec<FuturesMainDailyData>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
-        }
+    main_daily_response(&path.into_inner(), &query).await
+}
+
+async fn main_daily_response(symbol: &str, query: &FuturesMainQuery) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[
+        ("start_date", query.start_date.as_deref()),
+        ("end_date", query.end_date.as_deref()),
+    ]) {
+        return Ok(response);
     }
+    Ok(ok_or_empty(
+        get_futures_main_sina(
+            symbol,
+            query.start_date.as_deref(),
+            query.end_date.as_deref(),
+        ).await,
+    ))
 }
 
 /// 获取期货持仓排名数据
 /// GET /futures/hold_pos?pos_type=volume&contract=RB2510&date=20250107
 /// 对应 akshare 的 futures_hold_pos_sina()
 pub async fn get_hold_pos(query: web::Query<FuturesHoldPosQuery>) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
+    }
+    if let Some(response) = invalid_symbols(&[&query.contract]) {
+        return Ok(response);
+    }
     let pos_type = query.pos_type.as_deref().unwrap_or("volume");
-    
-    match get_futures_hold_pos_sina(pos_type, &query.contract, &query.date).await {
-        Ok(positions) => {
-            let response = ApiResponse::success(positions);
-            Ok(HttpResponse::Ok().json(response))
-        }
-        Err(e) => {
-            let response = ApiResponse::<Vec<FuturesHoldPosition>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
-        }
+    let Some(pos_type) = SinaHoldPosType::from_str(pos_type) else {
+        let response = ApiResponse::<()>::error(format!(
+            "无效的持仓类型: {}，可选 volume/long/short 或 成交量/多单持仓/空单持仓",
+            pos_type
+        ));
+        return Ok(HttpResponse::BadRequest().json(response));
+    };
+
+    let date = effective_date(query.date.as_deref());
+    Ok(ok_or_empty_on(get_futures_hold_pos_sina(pos_type, &query.contract, &date).await, &date))
+}
+
+/// 获取新浪成交持仓排名（名次、期货公司、数值、增减）
+/// GET /futures/hold-pos/sina?symbol=成交量&contract=OI2501&date=20250107
+/// 对应 akshare 的 futures_hold_pos_sina()
+pub async fn get_hold_pos_sina(query: web::Query<SinaHoldPosQuery>) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
     }
+    if let Some(response) = invalid_symbols(&[&query.contract]) {
+        return Ok(response);
+    }
+    let symbol = query.symbol.as_deref().unwrap_or("成交量");
+    let Some(pos_type) = SinaHoldPosType::from_str(symbol) else {
+        let response = ApiResponse::<()>::error(format!(
+            "无效的持仓类型: {}，可选 成交量/多单持仓/空单持仓 或 volume/long/short",
+            symbol
+        ));
+        return Ok(HttpResponse::BadRequest().json(response));
+    };
+
+    let date = effective_date(query.date.as_deref());
+    Ok(ok_or_empty_on(get_futures_hold_pos_sina(pos_type, &query.contract, &date).await, &date))
 }
 
 /// 获取外盘期货历史数据（日K线）
-/// GET /futures/foreign/{symbol}/history
+/// GET /futures/foreign/{symbol}/history（别名 /futures/foreign/{symbol}/hist）
 /// 对应 akshare 的 futures_foreign_hist()
 pub async fn get_foreign_history(path: web::Path<String>) -> Result<HttpResponse> {
     let symbol = path.into_inner();
     
-    match get_futures_foreign_hist(&symbol).await {
-        Ok(data) => {
-            let response = ApiResponse::success(data);
-            Ok(HttpResponse::Ok().json(response))
-        }
-        Err(e) => {
-            let response = ApiResponse::<Vec<ForeignFuturesHistData>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
-        }
-    }
+    Ok(ok_or_empty(get_futures_foreign_hist(&symbol).await))
 }
 
 /// 获取外盘期货合约详情
@@ -522,90 +1219,325 @@ pub async fn get_foreign_detail(path: web::Path<String>) -> Result<HttpResponse>
         }
         Err(e) => {
             let response = ApiResponse::<ForeignFuturesDetail>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
 
 /// 获取期货交易费用参照表
-/// GET /futures/fees
+/// GET /futures/fees?exchange=SHFE&product=rb&sort=long_margin_rate&order=desc
 /// 对应 akshare 的 futures_fees_info()
-pub async fn get_fees_info() -> Result<HttpResponse> {
-    match get_futures_fees_info().await {
-        Ok(fees) => {
-            let response = ApiResponse::success(fees);
-            Ok(HttpResponse::Ok().json(response))
-        }
+///
+/// # 参数
+/// - exchange: 交易所代码或名称（可选）
+/// - product: 品种代码或名称（可选）
+/// - sort: 排序字段（可选），按对应的 *_num 数值排序，数值缺失的排在最后
+/// - order: asc（默认）或 desc
+/// - debug_raw: 为 1 时每行附带上游原始单元格 _raw（需开启 debug.endpoints_enabled）
+pub async fn get_fees_info(
+    query: web::Query<FuturesFeesQuery>,
+    debug: web::Query<DebugRawQuery>,
+) -> Result<HttpResponse> {
+    let debug_raw = match debug_raw_requested(&debug) {
+        Ok(debug_raw) => debug_raw,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    let sort_order = match query.sort_order() {
+        Ok(sort_order) => sort_order,
         Err(e) => {
             let response = ApiResponse::<Vec<FuturesFeesInfo>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            return Ok(HttpResponse::BadRequest().json(response));
         }
+    };
+
+    let result = get_futures_fees_info().await.map(|fees| {
+        let mut fees = filter_fees_info(fees, query.exchange.as_deref(), query.product.as_deref());
+        if let Some((field, descending)) = sort_order {
+            sort_fees_info(&mut fees, field, descending);
+        }
+        if !debug_raw {
+            fees.iter_mut().for_each(FuturesFeesInfo::clear_raw);
+        }
+        fees
+    });
+    Ok(ok_or_empty(result))
+}
+
+/// 按当前价格计算合约单手手续费和保证金
+/// GET /futures/fees/per-lot/{symbol}
+///
+/// 交易费用表中没有该品种时返回 404
+pub async fn get_fees_per_lot(path: web::Path<String>) -> Result<HttpResponse> {
+    let symbol = path.into_inner();
+    if let Some(response) = invalid_symbols(&[&symbol]) {
+        return Ok(response);
+    }
+
+    match get_futures_per_lot_cost(&symbol).await {
+        Ok(Some(data)) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
+        Ok(None) => {
+            let response =
+                ApiResponse::<FuturesPerLotCost>::error(format!("交易费用表中未找到合约 {}", symbol));
+            Ok(HttpResponse::NotFound().json(response))
+        }
+        Err(e) => Ok(error_response(&e)),
     }
 }
 
 /// 获取期货手续费信息（九期网）
 /// GET /futures/comm_info?exchange=所有
 /// 对应 akshare 的 futures_comm_info()
-pub async fn get_comm_info(query: web::Query<FuturesCommQuery>) -> Result<HttpResponse> {
+pub async fn get_comm_info(
+    query: web::Query<FuturesCommQuery>,
+    debug: web::Query<DebugRawQuery>,
+) -> Result<HttpResponse> {
+    let debug_raw = match debug_raw_requested(&debug) {
+        Ok(debug_raw) => debug_raw,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
     let exchange = query.exchange.as_deref();
-    
-    match get_futures_comm_info(exchange).await {
-        Ok(data) => {
-            let response = ApiResponse::success(data);
+
+    let result = get_futures_comm_info(exchange).await.map(|mut rows| {
+        if !debug_raw {
+            rows.iter_mut().for_each(FuturesCommInfo::clear_raw);
+        }
+        rows
+    });
+    Ok(ok_or_empty(result))
+}
+
+/// 获取期货手续费信息（九期网不可用时自动降级为 OpenCTP）
+/// GET /futures/comm-info?exchange=上期所
+///
+/// 返回 source 标明实际数据源，降级时附带 note 说明替换原因
+pub async fn get_comm_info_fallback(
+    query: web::Query<FuturesCommQuery>,
+    debug: web::Query<DebugRawQuery>,
+) -> Result<HttpResponse> {
+    let debug_raw = match debug_raw_requested(&debug) {
+        Ok(debug_raw) => debug_raw,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    match get_futures_comm_info_with_fallback(query.exchange.as_deref()).await {
+        Ok(mut result) => {
+            if !debug_raw {
+                result.clear_raw();
+            }
+            let response = ApiResponse::success(result);
             Ok(HttpResponse::Ok().json(response))
         }
         Err(e) => {
-            let response = ApiResponse::<Vec<FuturesCommInfo>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            let response = ApiResponse::<FuturesCommInfoResult>::error(e.to_string());
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
 
 /// 获取期货交易规则
-/// GET /futures/rule?date=20250328
+/// GET /futures/rule?date=20250328&exchange=SHFE&product=CU
 /// 对应 akshare 的 futures_rule()
-pub async fn get_rule(query: web::Query<FuturesRuleQuery>) -> Result<HttpResponse> {
-    let date = query.date.as_deref();
-    
-    match get_futures_rule(date).await {
+///
+/// # 参数
+/// - date: 交易日期（可选，默认最近交易日）
+/// - exchange: 交易所代码或名称（可选）
+/// - product: 品种代码或名称（可选）
+/// - debug_raw: 为 1 时每行附带上游原始单元格 _raw（需开启 debug.endpoints_enabled）
+pub async fn get_rule(
+    query: web::Query<FuturesRuleQuery>,
+    debug: web::Query<DebugRawQuery>,
+) -> Result<HttpResponse> {
+    let debug_raw = match debug_raw_requested(&debug) {
+        Ok(debug_raw) => debug_raw,
+        Err(e) => return Ok(bad_fields_response(e)),
+    };
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
+    }
+    let date = effective_date(query.date.as_deref());
+
+    match get_futures_rule(Some(&date))
+        .await
+        .and_then(|rules| ensure_published(&date, rules))
+    {
         Ok(rules) => {
-            let response = ApiResponse::success(rules);
+            let mut rules =
+                filter_futures_rules(rules, query.exchange.as_deref(), query.product.as_deref());
+            if !debug_raw {
+                rules.iter_mut().for_each(FuturesRule::clear_raw);
+            }
+            let response = ApiResponse::success_list(rules).with_effective_date(&date);
             Ok(HttpResponse::Ok().json(response))
         }
         Err(e) => {
             let response = ApiResponse::<Vec<FuturesRule>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
 
-/// 获取99期货网品种映射表
-/// GET /futures/inventory99/symbols
-pub async fn get_inventory99_symbols() -> Result<HttpResponse> {
-    match get_99_symbol_map().await {
-        Ok(symbols) => {
-            let response = ApiResponse::success(symbols);
-            Ok(HttpResponse::Ok().json(response))
+/// 获取合约会员净持仓及前20多空汇总
+/// GET /futures/position-net/{exchange}/{contract}?date=20240102
+pub async fn get_position_net_data(
+    path: web::Path<(String, String)>,
+    query: web::Query<PositionNetQuery>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
+    }
+    let date = effective_date(query.date.as_deref());
+    let (exchange, contract) = path.into_inner();
+
+    if !RANK_EXCHANGES.contains(&exchange.to_uppercase().as_str()) {
+        let response = ApiResponse::<PositionNetResponse>::error(format!(
+            "不支持的交易所: {}，可选 {}",
+            exchange,
+            RANK_EXCHANGES.join("/")
+        ));
+        return Ok(HttpResponse::BadRequest().json(response));
+    }
+
+    match get_position_net(&exchange, &contract, &date).await {
+        Ok(Some(data)) => Ok(HttpResponse::Ok().json(ApiResponse::success(data).with_effective_date(&date))),
+        Ok(None) => {
+            let response = ApiResponse::<PositionNetResponse>::error(format!(
+                "{} 在 {} 的持仓排名中未找到合约 {}",
+                date, exchange, contract
+            ));
+            Ok(HttpResponse::NotFound().json(response))
         }
-        Err(e) => {
-            let response = ApiResponse::<Vec<Futures99Symbol>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+        Err(e) => Ok(HttpResponse::build(error_status(&e)).json(ApiResponse::<PositionNetResponse>::error(e.to_string()))),
+    }
+}
+
+/// 获取合约持仓集中度（多空 CR5 和 HHI，基于前20会员排名）
+/// GET /futures/concentration/{exchange}/{contract}?date=20240102
+pub async fn get_concentration_data(
+    path: web::Path<(String, String)>,
+    query: web::Query<PositionNetQuery>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
+    }
+    let date = effective_date(query.date.as_deref());
+    let (exchange, contract) = path.into_inner();
+
+    if !RANK_EXCHANGES.contains(&exchange.to_uppercase().as_str()) {
+        let response = ApiResponse::<PositionConcentration>::error(format!(
+            "不支持的交易所: {}，可选 {}",
+            exchange,
+            RANK_EXCHANGES.join("/")
+        ));
+        return Ok(HttpResponse::BadRequest().json(response));
+    }
+
+    match get_position_concentration(&exchange, &contract, &date).await {
+        Ok(Some(data)) => Ok(HttpResponse::Ok().json(ApiResponse::success(data).with_effective_date(&date))),
+        Ok(None) => {
+            let response = ApiResponse::<PositionConcentration>::error(format!(
+                "{} 在 {} 的持仓排名中未找到合约 {}",
+                date, exchange, contract
+            ));
+            Ok(HttpResponse::NotFound().json(response))
         }
+        Err(e) => Ok(error_response(&e)),
     }
 }
 
+/// 对比合约两个交易日的会员持仓排名
+/// GET /futures/position-diff/{exchange}/{contract}?date1=20240102&date2=20240103
+///
+/// 任一日期没有排名数据时返回 422（错误链中的 RankDateUnavailable，由 error_status 映射），
+/// 错误信息中注明是哪个日期；其余错误同样按 error_status 返回
+pub async fn get_position_diff_data(
+    path: web::Path<(String, String)>,
+    query: web::Query<PositionDiffQuery>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[("date1", Some(&query.date1)), ("date2", Some(&query.date2))]) {
+        return Ok(response);
+    }
+    let (exchange, contract) = path.into_inner();
+
+    if !RANK_EXCHANGES.contains(&exchange.to_uppercase().as_str()) {
+        let response = ApiResponse::<PositionDiffResponse>::error(format!(
+            "不支持的交易所: {}，可选 {}",
+            exchange,
+            RANK_EXCHANGES.join("/")
+        ));
+        return Ok(HttpResponse::BadRequest().json(response));
+    }
+
+    match get_position_diff(&exchange, &contract, &query.date1, &query.date2).await {
+        Ok(data) => Ok(HttpResponse::Ok().json(ApiResponse::success(data))),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+/// 获取99期货网品种映射表
+/// GET /futures/inventory99/symbols
+pub async fn get_inventory99_symbols() -> Result<HttpResponse> {
+    Ok(ok_or_empty(get_99_symbol_map().await))
+}
+
 /// 获取99期货网库存数据
 /// GET /futures/inventory99?symbol=豆一
 /// 对应 akshare 的 futures_inventory_99()
 pub async fn get_inventory99(query: web::Query<FuturesInventory99Query>) -> Result<HttpResponse> {
-    match get_futures_inventory_99(&query.symbol).await {
+    Ok(ok_or_empty(get_futures_inventory_99(&query.symbol).await))
+}
+
+/// 获取99期货网库存数据（按日期范围过滤）
+/// GET /futures/inventory/{symbol}?start_date=20240101&end_date=20240630&limit=30
+/// symbol 支持中文名称（如 豆一）或英文代码（如 A）
+pub async fn get_inventory_range(
+    path: web::Path<String>,
+    query: web::Query<FuturesInventoryRangeQuery>,
+) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[
+        ("start_date", query.start_date.as_deref()),
+        ("end_date", query.end_date.as_deref()),
+    ]) {
+        return Ok(response);
+    }
+    let symbol = path.into_inner();
+
+    let symbols = match get_99_symbol_map().await {
+        Ok(symbols) => symbols,
+        Err(e) => {
+            let response = ApiResponse::<Vec<FuturesInventory99>>::error(e.to_string());
+            return Ok(HttpResponse::build(error_status(&e)).json(response));
+        }
+    };
+
+    let product_id = match find_99_product_id(&symbols, &symbol) {
+        Some(id) => id,
+        None => {
+            let available: Vec<String> = symbols
+                .iter()
+                .map(|s| format!("{}({})", s.name, s.code))
+                .collect();
+            let response = ApiResponse::<Vec<FuturesInventory99>>::error(format!(
+                "未找到品种 {}，可用品种: {}",
+                symbol,
+                available.join(", ")
+            ));
+            return Ok(HttpResponse::NotFound().json(response));
+        }
+    };
+
+    match get_futures_inventory_99_by_id(product_id).await {
         Ok(data) => {
-            let response = ApiResponse::success(data);
+            let data = filter_inventory_99(
+                data,
+                query.start_date.as_deref(),
+                query.end_date.as_deref(),
+                query.limit,
+            );
+            let response = ApiResponse::success_list(data);
             Ok(HttpResponse::Ok().json(response))
         }
         Err(e) => {
             let response = ApiResponse::<Vec<FuturesInventory99>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
@@ -614,34 +1546,38 @@ pub async fn get_inventory99(query: web::Query<FuturesInventory99Query>) -> Resu
 /// GET /futures/spot_price?date=20240430&symbols=RB,CU
 /// 对应 akshare 的 futures_spot_price()
 pub async fn get_spot_price(query: web::Query<FuturesSpotPriceQuery>) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
+    }
+    let date = effective_date(query.date.as_deref());
     let symbols: Option<Vec<&str>> = query.symbols.as_ref()
         .map(|s| s.split(',').map(|x| x.trim()).collect());
     
-    match get_futures_spot_price(&query.date, symbols).await {
-        Ok(data) => {
-            let response = ApiResponse::success(data);
-            Ok(HttpResponse::Ok().json(response))
-        }
-        Err(e) => {
-            let response = ApiResponse::<Vec<FuturesSpotPrice>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
-        }
-    }
+    Ok(ok_or_empty_on(get_futures_spot_price(&date, symbols).await, &date))
 }
 
 /// 获取期货现货价格及基差历史数据（包含180日统计）
-/// GET /futures/spot_price_previous?date=20240430
+/// GET /futures/spot_price_previous?date=20240430&commodity=螺纹&fallback=true
 /// 对应 akshare 的 futures_spot_price_previous()
+///
+/// date 默认为最近交易日（北京时间）；fallback=true 时当日无数据则回溯到最近有数据的交易日，
+/// effective_date 为实际命中的日期
 pub async fn get_spot_price_previous(query: web::Query<FuturesSpotPricePreviousQuery>) -> Result<HttpResponse> {
-    match get_futures_spot_price_previous(&query.date).await {
-        Ok(data) => {
-            let response = ApiResponse::success(data);
-            Ok(HttpResponse::Ok().json(response))
-        }
-        Err(e) => {
-            let response = ApiResponse::<Vec<FuturesSpotPricePrevious>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+    if let Some(response) = invalid_dates(&[("date", query.date.as_deref())]) {
+        return Ok(response);
+    }
+    let date = effective_date(query.date.as_deref());
+    let result = if query.fallback {
+        get_latest_spot_price_previous(&date).await
+    } else {
+        get_futures_spot_price_previous(&date).await.map(|data| (date, data))
+    };
+    match result {
+        Ok((date, data)) => {
+            let data = filter_spot_price_previous(data, query.commodity.as_deref());
+            Ok(ok_or_empty_on(Ok(data), &date))
         }
+        Err(e) => Ok(error_response(&e)),
     }
 }
 
@@ -649,72 +1585,590 @@ pub async fn get_spot_price_previous(query: web::Query<FuturesSpotPricePreviousQ
 /// GET /futures/spot_price_daily?start_date=20240101&end_date=20240105&symbols=RB,CU
 /// 对应 akshare 的 futures_spot_price_daily()
 pub async fn get_spot_price_daily(query: web::Query<FuturesSpotPriceDailyQuery>) -> Result<HttpResponse> {
+    if let Some(response) = invalid_dates(&[
+        ("start_date", Some(&query.start_date)),
+        ("end_date", Some(&query.end_date)),
+    ]) {
+        return Ok(response);
+    }
     let symbols: Option<Vec<&str>> = query.symbols.as_ref()
         .map(|s| s.split(',').map(|x| x.trim()).collect());
     
-    match get_futures_spot_price_daily(&query.start_date, &query.end_date, symbols).await {
-        Ok(data) => {
-            let response = ApiResponse::success(data);
-            Ok(HttpResponse::Ok().json(response))
-        }
-        Err(e) => {
-            let response = ApiResponse::<Vec<FuturesSpotPrice>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
-        }
+    Ok(ok_or_empty(get_futures_spot_price_daily(&query.start_date, &query.end_date, symbols).await))
+}
+
+/// 诊断实时行情代码格式
+/// GET /futures/debug/format?symbol=CU2405
+///
+/// 返回格式化后的新浪代码和将要请求的 URL（不发送请求）。
+/// 需在配置中开启 debug.endpoints_enabled，否则返回 404
+pub async fn get_debug_format(query: web::Query<DebugFormatQuery>) -> Result<HttpResponse> {
+    if !config::read(|c| c.debug.endpoints_enabled) {
+        let response = ApiResponse::<RealtimeRequestDebug>::error("调试接口未启用".to_string());
+        return Ok(HttpResponse::NotFound().json(response));
     }
+
+    let symbol = query.symbol.trim();
+    if symbol.is_empty() {
+        let response = ApiResponse::<RealtimeRequestDebug>::error("合约代码不能为空".to_string());
+        return Ok(HttpResponse::BadRequest().json(response));
+    }
+
+    let service = FuturesService::new();
+    let response = ApiResponse::success(service.describe_realtime_request(symbol));
+    Ok(HttpResponse::Ok().json(response))
 }
 
+/// 本模块的路由表（注册顺序即匹配顺序，字面路径须在同级的 {symbol} 等参数路径之前）
+pub const ROUTES: &[RouteEntry] = &[
+    // 列表和基础信息
+    RouteInfo::get("/api/v1/futures", "期货列表（按交易所）").bind(|r| r.to(list_futures)),
+    RouteInfo::get("/api/v1/futures/exchanges", "交易所列表").bind(|r| r.to(get_exchanges)),
+    RouteInfo::get("/api/v1/futures/symbols", "按交易所分组的品种映射表").bind(|r| r.to(get_symbol_mark)),
+    RouteInfo::get("/api/v1/futures/symbols/{exchange}", "指定交易所的品种").bind(|r| r.to(get_exchange_symbols)),
+    RouteInfo::get("/api/v1/futures/variety-map", "中文品种名称与英文代码对照表").bind(|r| r.to(get_variety_map)),
+    RouteInfo::post("/api/v1/futures/batch", "批量获取实时行情").bind(|r| r.to(get_multiple_futures)),
+    RouteInfo::post("/api/v1/futures/batch/detail", "批量获取实时行情，返回每个合约的获取状态")
+        .bind(|r| r.to(get_multiple_futures_detailed)),
+    RouteInfo::get("/api/v1/futures/overview", "首页市场概览").bind(|r| r.to(get_overview)),
+    RouteInfo::get("/api/v1/futures/sse", "实时行情 SSE 推送").bind(|r| r.to(get_futures_sse)),
+    RouteInfo::get("/api/v1/futures/minute/{symbol}/multi", "同时获取多个周期的分钟K线").bind(|r| r.to(get_minute_multi)),
+    // 交易费用和手续费
+    RouteInfo::get("/api/v1/futures/fees", "交易费用（可过滤、排序）").bind(|r| r.to(get_fees_info)),
+    RouteInfo::get("/api/v1/futures/fees/per-lot/{symbol}", "单手手续费和保证金").bind(|r| r.to(get_fees_per_lot)),
+    RouteInfo::get("/api/v1/futures/comm_info", "九期网手续费信息").bind(|r| r.to(get_comm_info)),
+    RouteInfo::get("/api/v1/futures/comm-info", "手续费信息（九期网不可用时降级为 OpenCTP）")
+        .bind(|r| r.to(get_comm_info_fallback)),
+    RouteInfo::get("/api/v1/futures/rule", "交易规则").bind(|r| r.to(get_rule)),
+    // 99期货网库存数据
+    RouteInfo::get("/api/v1/futures/inventory99", "99期货网库存数据").bind(|r| r.to(get_inventory99)),
+    RouteInfo::get("/api/v1/futures/inventory99/symbols", "99期货网品种映射表")
+        .bind(|r| r.to(get_inventory99_symbols)),
+    RouteInfo::get("/api/v1/futures/inventory/{symbol}", "按日期范围获取库存数据").bind(|r| r.to(get_inventory_range)),
+    // 现货价格及基差
+    RouteInfo::get("/api/v1/futures/spot_price", "现货价格及基差").bind(|r| r.to(get_spot_price)),
+    RouteInfo::get("/api/v1/futures/spot_price_previous", "历史现货价格").bind(|r| r.to(get_spot_price_previous)),
+    RouteInfo::get("/api/v1/futures/spot-price/previous", "历史现货价格（别名）")
+        .bind(|r| r.to(get_spot_price_previous)),
+    RouteInfo::get("/api/v1/futures/spot_price_daily", "现货价格日线（日期范围）").bind(|r| r.to(get_spot_price_daily)),
+    // 持仓排名表与汇总
+    RouteInfo::get("/api/v1/futures/rank/shfe", "上期所持仓排名").bind(|r| r.to(get_rank_shfe)),
+    RouteInfo::get("/api/v1/futures/rank/cffex", "中金所持仓排名").bind(|r| r.to(get_rank_cffex)),
+    RouteInfo::get("/api/v1/futures/rank/dce", "大商所持仓排名").bind(|r| r.to(get_rank_dce)),
+    RouteInfo::get("/api/v1/futures/rank/czce", "郑商所持仓排名").bind(|r| r.to(get_rank_czce)),
+    RouteInfo::get("/api/v1/futures/rank/gfex", "广期所持仓排名").bind(|r| r.to(get_rank_gfex)),
+    RouteInfo::get("/api/v1/futures/rank/sum", "持仓排名汇总").bind(|r| r.to(get_rank_sum_data)),
+    RouteInfo::get("/api/v1/futures/position-rank/sum", "持仓排名汇总统一入口（单日或日期区间）")
+        .bind(|r| r.to(get_position_rank_sum)),
+    RouteInfo::get("/api/v1/futures/position-rank/{exchange}", "持仓排名表统一入口").bind(|r| r.to(get_position_rank)),
+    RouteInfo::get("/api/v1/futures/rank/sum_daily", "持仓排名汇总（日期区间）").bind(|r| r.to(get_rank_sum_daily_data)),
+    RouteInfo::get("/api/v1/futures/position-net/{exchange}/{contract}", "会员净持仓")
+        .bind(|r| r.to(get_position_net_data)),
+    RouteInfo::get("/api/v1/futures/concentration/{exchange}/{contract}", "持仓集中度（CR5、HHI）")
+        .bind(|r| r.to(get_concentration_data)),
+    RouteInfo::get("/api/v1/futures/position-diff/{exchange}/{contract}", "对比两日会员持仓排名")
+        .bind(|r| r.to(get_position_diff_data)),
+    // 仓单日报
+    RouteInfo::get("/api/v1/futures/warehouse/czce", "郑商所仓单日报").bind(|r| r.to(get_warehouse_czce)),
+    RouteInfo::get("/api/v1/futures/warehouse/dce", "大商所仓单日报").bind(|r| r.to(get_warehouse_dce)),
+    RouteInfo::get("/api/v1/futures/warehouse/shfe", "上期所仓单日报").bind(|r| r.to(get_warehouse_shfe)),
+    RouteInfo::get("/api/v1/futures/warehouse/gfex", "广期所仓单日报").bind(|r| r.to(get_warehouse_gfex)),
+    // 主力连续合约
+    RouteInfo::get("/api/v1/futures/main", "主力连续合约一览").bind(|r| r.to(get_display_main_contracts)),
+    RouteInfo::get("/api/v1/futures/main/display", "主力连续合约一览（别名）").bind(|r| r.to(get_display_main_contracts)),
+    RouteInfo::get("/api/v1/futures/main/{symbol}/daily", "主力连续日K线").bind(|r| r.to(get_main_daily)),
+    RouteInfo::get("/api/v1/futures/main/{key}", "交易所主力合约列表或主力连续日K线").bind(|r| r.to(get_main_by_key)),
+    // 持仓排名
+    RouteInfo::get("/api/v1/futures/hold_pos", "新浪持仓排名").bind(|r| r.to(get_hold_pos)),
+    RouteInfo::get("/api/v1/futures/hold-pos/sina", "新浪成交持仓排名（名次、期货公司、数值、增减）")
+        .bind(|r| r.to(get_hold_pos_sina)),
+    // 调试接口（需开启 debug.endpoints_enabled）
+    RouteInfo::get("/api/v1/futures/debug/format", "诊断实时行情代码格式（需开启 debug.endpoints_enabled）")
+        .bind(|r| r.to(get_debug_format)),
+    // 外盘期货
+    RouteInfo::get("/api/v1/futures/foreign/symbols", "外盘品种列表").bind(|r| r.to(get_foreign_symbols)),
+    RouteInfo::get("/api/v1/futures/foreign/realtime", "外盘实时行情")
+        .bind(|r| r.to(get_foreign_realtime_by_query)),
+    RouteInfo::post("/api/v1/futures/foreign/realtime", "外盘实时行情（代码数组）").bind(|r| r.to(get_foreign_realtime)),
+    RouteInfo::get("/api/v1/futures/foreign/{symbol}/history", "外盘日K线").bind(|r| r.to(get_foreign_history)),
+    RouteInfo::get("/api/v1/futures/foreign/{symbol}/hist", "外盘日K线（别名）").bind(|r| r.to(get_foreign_history)),
+    RouteInfo::get("/api/v1/futures/foreign/{symbol}/detail", "外盘合约详情").bind(|r| r.to(get_foreign_detail)),
+    // 品种实时数据
+    RouteInfo::get("/api/v1/futures/realtime/{symbol}", "品种所有合约实时行情").bind(|r| r.to(get_realtime_by_symbol)),
+    RouteInfo::get("/api/v1/futures/variety/{name}/contracts", "品种所有在交易合约")
+        .bind(|r| r.to(get_variety_contracts)),
+    RouteInfo::get("/api/v1/futures/contracts/{symbol}/expiry", "合约最后交易日和交割日")
+        .bind(|r| r.to(get_contract_expiry_info)),
+    // 单个合约
+    RouteInfo::get("/api/v1/futures/{symbol}", "单个合约实时行情").bind(|r| r.to(get_futures_info)),
+    RouteInfo::get("/api/v1/futures/{symbol}/history", "日K线").bind(|r| r.to(get_history)),
+    RouteInfo::get("/api/v1/futures/{symbol}/minute", "分钟K线").bind(|r| r.to(get_minute)),
+    RouteInfo::get("/api/v1/futures/{symbol}/detail", "合约详情").bind(|r| r.to(get_contract_detail)),
+];
+
 /// 配置期货相关路由
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::scope("/futures")
-            // 列表和基础信息
-            .route("", web::get().to(list_futures))
-            .route("/exchanges", web::get().to(get_exchanges))
-            .route("/symbols", web::get().to(get_symbol_mark))
-            .route("/symbols/{exchange}", web::get().to(get_exchange_symbols))
-            .route("/batch", web::post().to(get_multiple_futures))
-            // 交易费用和手续费
-            .route("/fees", web::get().to(get_fees_info))
-            .route("/comm_info", web::get().to(get_comm_info))
-            .route("/rule", web::get().to(get_rule))
-            // 99期货网库存数据
-            .route("/inventory99", web::get().to(get_inventory99))
-            .route("/inventory99/symbols", web::get().to(get_inventory99_symbols))
-            // 现货价格及基差
-            .route("/spot_price", web::get().to(get_spot_price))
-            .route("/spot_price_previous", web::get().to(get_spot_price_previous))
-            .route("/spot_price_daily", web::get().to(get_spot_price_daily))
-            // 持仓排名表与汇总
-            .route("/rank/shfe", web::get().to(get_rank_shfe))
-            .route("/rank/cffex", web::get().to(get_rank_cffex))
-            .route("/rank/dce", web::get().to(get_rank_dce))
-            .route("/rank/czce", web::get().to(get_rank_czce))
-            .route("/rank/gfex", web::get().to(get_rank_gfex))
-            .route("/rank/sum", web::get().to(get_rank_sum_data))
-            .route("/rank/sum_daily", web::get().to(get_rank_sum_daily_data))
-            // 仓单日报
-            .route("/warehouse/czce", web::get().to(get_warehouse_czce))
-            .route("/warehouse/dce", web::get().to(get_warehouse_dce))
-            .route("/warehouse/shfe", web::get().to(get_warehouse_shfe))
-            .route("/warehouse/gfex", web::get().to(get_warehouse_gfex))
-            // 主力连续合约
-            .route("/main/display", web::get().to(get_display_main_contracts))
-            .route("/main/{symbol}/daily", web::get().to(get_main_daily))
-            .route("/main/{exchange}", web::get().to(get_main_contracts))
-            // 持仓排名
-            .route("/hold_pos", web::get().to(get_hold_pos))
-            // 外盘期货
-            .route("/foreign/symbols", web::get().to(get_foreign_symbols))
-            .route("/foreign/realtime", web::post().to(get_foreign_realtime))
-            .route("/foreign/{symbol}/history", web::get().to(get_foreign_history))
-            .route("/foreign/{symbol}/detail", web::get().to(get_foreign_detail))
-            // 品种实时数据
-            .route("/realtime/{symbol}", web::get().to(get_realtime_by_symbol))
-            // 单个合约
-            .route("/{symbol}", web::get().to(get_futures_info))
-            .route("/{symbol}/history", web::get().to(get_history))
-            .route("/{symbol}/minute", web::get().to(get_minute))
-            .route("/{symbol}/detail", web::get().to(get_contract_detail))
-    );
+    cfg.service(web::scope("/futures").configure(|cfg| register(cfg, "/api/v1/futures", ROUTES)));
+}
+
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test as actix_test, App};
+    use serde_json::{json, Value};
+
+    use crate::config;
+
+    async fn get_json(uri: &str) -> (u16, Value) {
+        config::init_mock_for_tests();
+        let app = actix_test::init_service(App::new().configure(crate::handlers::config)).await;
+        let response = actix_test::call_service(&app, actix_test::TestRequest::get().uri(uri).to_request()).await;
+        let status = response.status().as_u16();
+        (status, actix_test::read_body_json(response).await)
+    }
+
+    #[actix_web::test]
+    async fn foreign_symbols_lists_known_codes() {
+        let (status, body) = get_json("/api/v1/futures/foreign/symbols").await;
+        assert_eq!(status, 200);
+        let codes: Vec<&str> = body["data"].as_array().unwrap().iter().map(|s| s["code"].as_str().unwrap()).collect();
+        assert!(codes.contains(&"GC") && codes.contains(&"CL"), "{:?}", codes);
+        assert_eq!(body["count"], codes.len());
+    }
+
+    #[actix_web::test]
+    async fn foreign_realtime_ignores_unknown_codes_with_warning() {
+        let (status, body) = get_json("/api/v1/futures/foreign/realtime?codes=gc,CL,XYZ").await;
+        assert_eq!(status, 200, "{}", body);
+        let symbols: Vec<&str> = body["data"].as_array().unwrap().iter().map(|s| s["symbol"].as_str().unwrap()).collect();
+        assert_eq!(symbols, ["GC", "CL"]);
+        assert_eq!(body["warnings"][0], "未知的外盘品种代码 XYZ，已忽略");
+    }
+
+    #[actix_web::test]
+    async fn foreign_realtime_without_valid_codes_is_400() {
+        let (status, body) = get_json("/api/v1/futures/foreign/realtime?codes=XYZ,ABC").await;
+        assert_eq!(status, 400);
+        assert!(body["message"].as_str().unwrap().starts_with("未知的外盘品种代码: XYZ, ABC"), "{}", body);
+
+        let (status, body) = get_json("/api/v1/futures/foreign/realtime?codes=,").await;
+        assert_eq!(status, 400);
+        assert_eq!(body["message"], "品种代码列表不能为空");
+    }
+
+    #[actix_web::test]
+    async fn foreign_realtime_accepts_posted_code_array() {
+        config::init_mock_for_tests();
+        let app = actix_test::init_service(App::new().configure(crate::handlers::config)).await;
+        let request = actix_test::TestRequest::post()
+            .uri("/api/v1/futures/foreign/realtime?fields=quote")
+            .set_json(["CAD"])
+            .to_request();
+        let body: Value = actix_test::call_and_read_body_json(&app, request).await;
+        assert_eq!(body["data"][0]["symbol"], "CAD");
+        assert!(body["data"][0].get("name").is_none(), "{}", body);
+    }
+
+    #[actix_web::test]
+    async fn batch_detail_reports_status_per_symbol() {
+        config::init_mock_for_tests();
+        let app = actix_test::init_service(App::new().configure(crate::handlers::config)).await;
+        let request = actix_test::TestRequest::post()
+            .uri("/api/v1/futures/batch/detail")
+            .set_json(["RB2601", "ZN2601"])
+            .to_request();
+        let body: Value = actix_test::call_and_read_body_json(&app, request).await;
+        assert_eq!(body["success"], true, "{}", body);
+
+        let items = body["data"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!((&items[0]["symbol"], &items[0]["status"]), (&json!("RB2601"), &json!("ok")));
+        assert_eq!(items[0]["data"]["symbol"], "RB2601");
+        assert_eq!((&items[1]["symbol"], &items[1]["status"]), (&json!("ZN2601"), &json!("no_data")));
+        assert!(items[1]["data"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn rule_endpoint_filters_by_exchange_and_product() {
+        let codes = |body: &Value| -> Vec<String> {
+            body["data"].as_array().unwrap().iter().map(|r| r["code"].as_str().unwrap().to_string()).collect()
+        };
+        let (status, body) = get_json("/api/v1/futures/rule?exchange=SHFE").await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(codes(&body), ["RB", "CU", "AU"]);
+
+        let (_, body) = get_json("/api/v1/futures/rule?exchange=SHFE&product=cu").await;
+        assert_eq!(codes(&body), ["CU"]);
+
+        let (status, body) = get_json("/api/v1/futures/rule?exchange=DCE&product=CU").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["count"], 0);
+    }
+
+    #[actix_web::test]
+    async fn main_list_returns_continuous_contracts() {
+        let (status, body) = get_json("/api/v1/futures/main").await;
+        assert_eq!(status, 200, "{}", body);
+        let list = body["data"].as_array().unwrap();
+        assert_eq!(body["count"], list.len());
+        assert_eq!(list[0]["symbol"], "RB0");
+        assert_eq!(list[0]["exchange"], "shfe");
+    }
+
+    #[actix_web::test]
+    async fn main_daily_filters_by_date_range() {
+        let dates = |body: &Value| -> Vec<String> {
+            body["data"].as_array().unwrap().iter().map(|d| d["date"].as_str().unwrap().to_string()).collect()
+        };
+        let (status, body) = get_json("/api/v1/futures/main/RB0?start_date=20251202&end_date=20251204").await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(dates(&body), ["2025-12-02", "2025-12-03", "2025-12-04"]);
+
+        let (_, body) = get_json("/api/v1/futures/main/RB0/daily?start_date=20251205").await;
+        assert_eq!(dates(&body), ["2025-12-05"]);
+        assert!(body["data"][0]["settle"].is_null());
+
+        let (status, _) = get_json("/api/v1/futures/main/RB0?start_date=2025-12-01").await;
+        assert_eq!(status, 400);
+    }
+
+    #[actix_web::test]
+    async fn debug_format_is_404_unless_enabled() {
+        // 默认配置未开启 debug.endpoints_enabled
+        let (status, body) = get_json("/api/v1/futures/debug/format?symbol=CU2405").await;
+        assert_eq!(status, 404);
+        assert_eq!(body["message"], "调试接口未启用");
+    }
+
+    #[actix_web::test]
+    async fn overview_contains_expected_sections() {
+        let (status, body) = get_json("/api/v1/futures/overview").await;
+        assert_eq!(status, 200, "{}", body);
+        let data = body["data"].as_object().unwrap();
+        let mut keys: Vec<&str> = data.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["basis", "main_quotes", "top_movers", "updated_at"]);
+        assert!(data["main_quotes"].is_array());
+        assert!(data["top_movers"]["gainers"].is_array() && data["top_movers"]["losers"].is_array());
+        for key in ["date", "max_basis", "min_basis"] {
+            assert!(data["basis"].get(key).is_some(), "{}: {}", key, data["basis"]);
+        }
+        let max = data["basis"]["max_basis"]["dom_basis_rate"].as_f64().unwrap();
+        let min = data["basis"]["min_basis"]["dom_basis_rate"].as_f64().unwrap();
+        assert!(max >= min);
+    }
+
+    #[actix_web::test]
+    async fn sse_sets_event_stream_type_and_emits_quotes() {
+        use actix_web::body::MessageBody;
+
+        config::init_mock_for_tests();
+        let app = actix_test::init_service(App::new().configure(crate::handlers::config)).await;
+        let request = actix_test::TestRequest::get()
+            .uri("/api/v1/futures/sse?symbols=RB2601,CU2601&fields=quote")
+            .to_request();
+        let response = actix_test::call_service(&app, request).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+        assert_eq!(response.headers().get("cache-control").unwrap(), "no-cache");
+
+        // 第一个事件立即推送，之后的流不再读取
+        let mut body = response.into_body();
+        let chunk = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            std::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx)),
+        )
+        .await
+        .expect("应立即推送第一个事件")
+        .expect("流不应结束")
+        .unwrap();
+        let event = std::str::from_utf8(&chunk).unwrap();
+        let json = event.strip_prefix("data: ").and_then(|e| e.strip_suffix("\n\n")).expect(event);
+        let quotes: Value = serde_json::from_str(json).unwrap();
+        let symbols: Vec<&str> = quotes.as_array().unwrap().iter().map(|q| q["symbol"].as_str().unwrap()).collect();
+        assert_eq!(symbols, ["RB2601", "CU2601"]);
+        assert!(quotes[0].get("price").is_some());
+    }
+
+    #[actix_web::test]
+    async fn sse_rejects_bad_parameters_before_streaming() {
+        let (status, _) = get_json("/api/v1/futures/sse?symbols=").await;
+        assert_eq!(status, 400);
+        let (status, body) = get_json("/api/v1/futures/sse?symbols=RB2601&interval_secs=0").await;
+        assert_eq!(status, 400);
+        assert_eq!(body["message"], "interval_secs 必须在 1-60 之间，当前为 0");
+    }
+
+    #[actix_web::test]
+    async fn symbols_grouped_by_exchange_code() {
+        let (status, body) = get_json("/api/v1/futures/symbols").await;
+        assert_eq!(status, 200);
+        let grouped = body["data"].as_object().unwrap();
+        let exchanges: Vec<&str> = grouped.keys().map(String::as_str).collect();
+        assert_eq!(exchanges, ["CFFEX", "CZCE", "DCE", "GFEX", "SHFE"]);
+
+        let shfe = grouped["SHFE"].as_array().unwrap();
+        assert_eq!(shfe.len(), 3);
+        assert!(shfe.iter().all(|e| e["symbol"].is_string() && e["mark"].is_string()));
+        assert!(shfe.iter().any(|e| e["symbol"] == "螺纹钢" && e["mark"] == "lwg_qh"));
+    }
+
+    #[actix_web::test]
+    async fn symbols_exchange_filter_narrows_result() {
+        let (status, body) = get_json("/api/v1/futures/symbols?exchange=dce").await;
+        assert_eq!(status, 200);
+        let grouped = body["data"].as_object().unwrap();
+        assert_eq!(grouped.keys().collect::<Vec<_>>(), ["DCE"]);
+        assert_eq!(grouped["DCE"].as_array().unwrap().len(), 3);
+
+        let (status, body) = get_json("/api/v1/futures/symbols?exchange=LME").await;
+        assert_eq!(status, 400);
+        assert!(body["message"].as_str().unwrap().contains("LME"));
+    }
+
+    #[actix_web::test]
+    async fn spot_price_without_matching_symbol_is_empty_200() {
+        let (status, body) = get_json("/api/v1/futures/spot_price?date=20240105&symbols=AU").await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["success"], true);
+        assert_eq!(body["data"], json!([]));
+        assert_eq!(body["count"], 0);
+    }
+
+    #[actix_web::test]
+    async fn position_rank_dispatches_each_exchange() {
+        let symbols = |body: &Value| -> Vec<String> {
+            body["data"].as_array().unwrap().iter().map(|t| t["symbol"].as_str().unwrap().to_string()).collect()
+        };
+        let cases = [
+            ("shfe", "CU", "cu2601"),
+            ("CFFEX", "if", "IF2512"),
+            ("dce", "M", "m2601"),
+            ("czce", "TA", "TA601"),
+            ("gfex", "lc", "lc2601"),
+        ];
+        for (exchange, variety, contract) in cases {
+            let (status, body) = get_json(&format!("/api/v1/futures/position-rank/{}?date=20251205", exchange)).await;
+            assert_eq!(status, 200, "{}: {}", exchange, body);
+            assert_eq!(body["count"], 2, "{}", exchange);
+            assert_eq!(body["effective_date"], "20251205");
+            assert!(body["data"][0]["data"][0]["long_party_name"].is_string(), "{}", exchange);
+
+            let uri = format!("/api/v1/futures/position-rank/{}?date=20251205&vars={}", exchange, variety);
+            let (status, body) = get_json(&uri).await;
+            assert_eq!(status, 200, "{}", uri);
+            assert_eq!(symbols(&body), [contract], "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn position_rank_rejects_unknown_exchange_and_bad_date() {
+        let (status, body) = get_json("/api/v1/futures/position-rank/lme?date=20251205").await;
+        assert_eq!(status, 400);
+        assert!(body["message"].as_str().unwrap().contains("LME"));
+
+        let (status, _) = get_json("/api/v1/futures/position-rank/shfe?date=2025-13-45").await;
+        assert_eq!(status, 400);
+    }
+
+    #[actix_web::test]
+    async fn spot_price_previous_filters_by_commodity_on_both_routes() {
+        for route in ["/api/v1/futures/spot-price/previous", "/api/v1/futures/spot_price_previous"] {
+            // commodity=螺纹
+            let (status, body) = get_json(&format!("{}?date=20240105&commodity=%E8%9E%BA%E7%BA%B9", route)).await;
+            assert_eq!(status, 200, "{}: {}", route, body);
+            assert_eq!(body["effective_date"], "20240105");
+            assert_eq!(body["count"], 1);
+            assert_eq!(body["data"][0]["commodity"], "螺纹钢");
+            assert_eq!(body["data"][0]["basis_percentile"], 0.6);
+        }
+
+        let (status, body) = get_json("/api/v1/futures/spot-price/previous?date=20240105&fallback=true").await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["effective_date"], "20240105");
+        assert_eq!(body["count"], 3);
+        assert!(body["data"][2]["basis_percentile"].is_null());
+
+        let (status, _) = get_json("/api/v1/futures/spot-price/previous?date=2024-1-5").await;
+        assert_eq!(status, 400);
+    }
+
+    fn rank_sum_rows(body: &Value) -> Vec<(String, String)> {
+        body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| (r["date"].as_str().unwrap().to_string(), r["symbol"].as_str().unwrap().to_string()))
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn position_rank_sum_single_day_across_exchanges() {
+        let (status, body) = get_json("/api/v1/futures/position-rank/sum?date=20251205&vars=cu,M").await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["effective_date"], "20251205");
+        let symbols: Vec<String> = rank_sum_rows(&body).into_iter().map(|(_, symbol)| symbol).collect();
+        assert_eq!(symbols, ["CU", "M", "cu2601", "m2601"]);
+
+        // 每个品种只有一个合约，品种合计与合约合计相同
+        let data = body["data"].as_array().unwrap();
+        assert_eq!(data[0]["vol_top5"], data[2]["vol_top5"]);
+        assert_eq!(data[1]["long_open_interest_top20"], data[3]["long_open_interest_top20"]);
+        assert_eq!(data[1]["variety"], "M");
+    }
+
+    #[actix_web::test]
+    async fn position_rank_sum_range_covers_each_day() {
+        let (status, body) =
+            get_json("/api/v1/futures/position-rank/sum?start_date=20251201&end_date=20251205&vars=CU").await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["count"], 10);
+        let rows = rank_sum_rows(&body);
+        let dates: Vec<&str> = rows.iter().step_by(2).map(|(date, _)| date.as_str()).collect();
+        assert_eq!(dates, ["20251201", "20251202", "20251203", "20251204", "20251205"]);
+        assert!(rows.iter().all(|(_, symbol)| symbol == "CU" || symbol == "cu2601"));
+    }
+
+    #[actix_web::test]
+    async fn position_rank_sum_rejects_bad_date_combinations() {
+        for query in [
+            "start_date=20251205&end_date=20251201",
+            "start_date=20251201",
+            "date=20251205&start_date=20251201&end_date=20251205",
+            "start_date=20250101&end_date=20251205",
+        ] {
+            let (status, body) = get_json(&format!("/api/v1/futures/position-rank/sum?{}", query)).await;
+            assert_eq!(status, 400, "{}: {}", query, body);
+            assert_eq!(body["success"], false);
+        }
+    }
+
+    #[actix_web::test]
+    async fn minute_multi_returns_bars_keyed_by_period() {
+        let (status, body) = get_json("/api/v1/futures/minute/RB2601/multi?periods=15,1,5,1").await;
+        assert_eq!(status, 200);
+        let data = body["data"].as_object().unwrap();
+        assert_eq!(data.keys().collect::<Vec<_>>(), vec!["1", "15", "5"]);
+        for bars in data.values() {
+            assert!(!bars.as_array().unwrap().is_empty());
+        }
+    }
+
+    #[actix_web::test]
+    async fn minute_multi_rejects_invalid_periods() {
+        for periods in ["", "1,7", "abc", "1,5,15,30,60,1"] {
+            let uri = format!("/api/v1/futures/minute/RB2601/multi?periods={}", periods);
+            let (status, body) = get_json(&uri).await;
+            assert_eq!(status, 400, "periods={}", periods);
+            assert_eq!(body["success"], false);
+        }
+    }
+
+    #[actix_web::test]
+    async fn kline_meta_attached_only_when_requested() {
+        for path in ["/api/v1/futures/CU2601/history", "/api/v1/futures/CU2601/minute"] {
+            let (status, body) = get_json(path).await;
+            assert_eq!(status, 200, "{}", path);
+            assert!(body.get("meta").is_none(), "{}: {}", path, body);
+
+            let (status, body) = get_json(&format!("{}?with_meta=1", path)).await;
+            assert_eq!(status, 200, "{}", path);
+            assert_eq!(body["meta"], json!({"price_unit": "元/吨", "tick_size": 10.0, "contract_multiplier": 5.0}), "{}", path);
+            assert!(!body["data"].as_array().unwrap().is_empty());
+
+            let (status, _) = get_json(&format!("{}?with_meta=2", path)).await;
+            assert_eq!(status, 400, "{}", path);
+        }
+
+        let (_, body) = get_json("/api/v1/futures/AU2602/history?with_meta=1").await;
+        assert_eq!(body["meta"], json!({"price_unit": "元/克", "tick_size": 0.02, "contract_multiplier": 1000.0}));
+    }
+
+    #[actix_web::test]
+    async fn foreign_realtime_skips_unknown_codes_with_warning() {
+        let (status, body) = get_json("/api/v1/futures/foreign/realtime?codes=GC,XYZ,gc").await;
+        assert_eq!(status, 200);
+        let symbols: Vec<&str> = body["data"].as_array().unwrap().iter().map(|r| r["symbol"].as_str().unwrap()).collect();
+        assert_eq!(symbols, ["GC"]);
+        let warnings = body["warnings"].as_array().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].as_str().unwrap().contains("XYZ"), "{:?}", warnings);
+
+        let (status, body) = get_json("/api/v1/futures/foreign/realtime?codes=GC").await;
+        assert_eq!(status, 200);
+        assert!(body.get("warnings").is_none());
+
+        let (status, _) = get_json("/api/v1/futures/foreign/realtime?codes=XYZ").await;
+        assert_eq!(status, 400);
+    }
+
+    #[actix_web::test]
+    async fn debug_raw_absent_by_default_and_gated_by_debug_flag() {
+        let (status, body) = get_json("/api/v1/futures/rule").await;
+        assert_eq!(status, 200);
+        let rows = body["data"].as_array().unwrap();
+        assert!(!rows.is_empty());
+        assert!(rows.iter().all(|row| row.get("_raw").is_none()));
+
+        // 默认配置未开启 debug.endpoints_enabled
+        for uri in ["/api/v1/futures/rule?debug_raw=1", "/api/v1/futures/fees?debug_raw=1", "/api/v1/futures/rule?debug_raw=2"] {
+            let (status, body) = get_json(uri).await;
+            assert_eq!(status, 400, "{}", uri);
+            assert_eq!(body["success"], false);
+        }
+    }
+
+    #[actix_web::test]
+    async fn variety_map_lists_pairs_and_filters() {
+        let (status, body) = get_json("/api/v1/futures/variety-map").await;
+        assert_eq!(status, 200);
+        let rows = body["data"].as_array().unwrap();
+        assert!(rows.len() > 50);
+        assert!(rows.contains(&json!({"cn": "铜", "en": "CU", "exchange": "SHFE"})));
+
+        // q=铜
+        let (status, body) = get_json("/api/v1/futures/variety-map?q=%E9%93%9C").await;
+        assert_eq!(status, 200);
+        let codes: Vec<&str> = body["data"].as_array().unwrap().iter().map(|r| r["en"].as_str().unwrap()).collect();
+        assert!(codes.contains(&"CU"), "{:?}", codes);
+        assert!(codes.len() < rows.len());
+    }
+
+    #[actix_web::test]
+    async fn variety_contracts_sorted_by_open_interest_with_limit() {
+        let (status, body) = get_json("/api/v1/futures/variety/%E8%9E%BA%E7%BA%B9%E9%92%A2/contracts?limit=3").await;
+        assert_eq!(status, 200, "{}", body);
+        let open_interest: Vec<u64> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|info| info["open_interest"].as_u64().unwrap())
+            .collect();
+        assert_eq!(open_interest, [3456789, 1987654, 1543210]);
+    }
+
+    #[actix_web::test]
+    async fn ambiguous_variety_returns_409_with_candidates() {
+        // "豆" 同时匹配豆粕和豆一
+        let (status, body) = get_json("/api/v1/futures/variety/%E8%B1%86/contracts").await;
+        assert_eq!(status, 409);
+        assert_eq!(body["success"], false);
+        let candidates: Vec<&str> =
+            body["data"].as_array().unwrap().iter().map(|c| c["symbol"].as_str().unwrap()).collect();
+        assert_eq!(candidates, ["豆粕", "豆一"]);
+    }
+
+    #[actix_web::test]
+    async fn exact_name_wins_over_substring_matches() {
+        // "豆一" 完全匹配，不与其他包含该名称的品种冲突
+        let (status, _) = get_json("/api/v1/futures/variety/%E8%B1%86%E4%B8%80/contracts").await;
+        assert_eq!(status, 200);
+    }
+
+    #[actix_web::test]
+    async fn unknown_variety_returns_404() {
+        let (status, body) = get_json("/api/v1/futures/variety/%E6%A3%89%E8%8A%B1/contracts").await;
+        assert_eq!(status, 404);
+        assert!(body["message"].as_str().unwrap().contains("/futures/symbols"));
+    }
 }
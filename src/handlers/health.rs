@@ -3,6 +3,7 @@
 //! 用于监控服务运行状态
 
 use actix_web::{web, HttpResponse, Result};
+use super::routes::{register, RouteEntry, RouteInfo};
 use crate::models::ApiResponse;
 
 /// 健康检查处理函数
@@ -14,7 +15,11 @@ pub async fn health_check() -> Result<HttpResponse> {
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// 本模块的路由表，config 按表注册
+pub const ROUTES: &[RouteEntry] =
+    &[RouteInfo::get("/api/v1/health", "健康检查").bind(|r| r.to(health_check))];
+
 /// 配置健康检查路由
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.route("/health", web::get().to(health_check));
+    register(cfg, "/api/v1", ROUTES);
 }
\ No newline at end of file
@@ -0,0 +1,49 @@
+//! 运行指标接口
+//!
+//! 暴露上游请求限流、熔断、请求合并、缓存预热等运行时状态
+
+use actix_web::{web, HttpResponse, Result};
+use serde::Serialize;
+
+use super::routes::{register, RouteEntry, RouteInfo};
+use crate::models::ApiResponse;
+use crate::services::futures::{
+    cache_warmup_stats, coalesce_stats, sina_limiter_stats, upstream_breaker_stats,
+    CacheWarmupStats, CoalesceStats, SinaLimiterStats, UpstreamBreakerStats,
+};
+
+/// 运行指标
+#[derive(Debug, Serialize)]
+pub struct Metrics {
+    /// 新浪请求并发限制状态
+    pub sina_limiter: SinaLimiterStats,
+    /// 各上游域名的熔断状态（仅包含出现过失败的域名）
+    pub upstream_breakers: Vec<UpstreamBreakerStats>,
+    /// 并发相同请求合并统计
+    pub request_coalescing: CoalesceStats,
+    /// 启动缓存预热状态，未开启 startup.warm_cache 时不返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_warmup: Option<CacheWarmupStats>,
+}
+
+/// 获取运行指标
+///
+/// GET /api/v1/metrics
+pub async fn get_metrics() -> Result<HttpResponse> {
+    let metrics = Metrics {
+        sina_limiter: sina_limiter_stats(),
+        upstream_breakers: upstream_breaker_stats(),
+        request_coalescing: coalesce_stats(),
+        cache_warmup: cache_warmup_stats(),
+    };
+    Ok(HttpResponse::Ok().json(ApiResponse::success(metrics)))
+}
+
+/// 本模块的路由表，config 按表注册
+pub const ROUTES: &[RouteEntry] = &[RouteInfo::get("/api/v1/metrics", "运行指标（限流、熔断、请求合并、缓存预热）")
+    .bind(|r| r.to(get_metrics))];
+
+/// 配置运行指标路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    register(cfg, "/api/v1", ROUTES);
+}
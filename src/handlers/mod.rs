@@ -5,17 +5,162 @@
 pub mod stock;    // 股票相关接口
 pub mod futures;  // 期货相关接口
 pub mod health;   // 健康检查接口
+pub mod openapi;  // OpenAPI 文档接口
+pub mod metrics;  // 运行指标接口
+pub mod admin;    // 管理接口
+pub mod routes;   // 路由清单接口
 
-use actix_web::web;
+use actix_web::{
+    http::{header, StatusCode},
+    web, HttpResponse,
+};
+use serde::Serialize;
+
+use crate::models::ApiResponse;
+use crate::services::futures::{
+    ContractExpired, DataNotFound, DataNotYetAvailable, NonTradingDay, RankDateUnavailable, RateLimited,
+    UpstreamBlocked, UpstreamUnavailable,
+};
 
 /// 配置所有 API 路由
 /// 
-/// 所有接口统一使用 /api/v1 前缀
+/// 所有接口统一使用 /api/v1 前缀。各模块的 config 按其 ROUTES 路由表注册（见 routes 模块）
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api/v1")
             .configure(health::config)   // 健康检查: /api/v1/health
+            .configure(openapi::config)  // 接口文档: /api/v1/openapi.json
+            .configure(routes::config)   // 路由清单: /api/v1/routes
+            .configure(metrics::config)  // 运行指标: /api/v1/metrics
+            .configure(admin::config)    // 管理接口: /api/v1/admin
             .configure(stock::config)    // 股票接口: /api/v1/stocks
             .configure(futures::config)  // 期货接口: /api/v1/futures
     );
-}
\ No newline at end of file
+}
+/// 根据服务层错误选择响应状态码
+///
+/// 上游熔断中返回 503，新浪 IP 封禁冷却中返回 429，上游反爬虫拒绝访问返回 502，当日数据尚未发布返回 425，
+/// 非交易日、日期无数据或合约已到期返回 404，持仓对比中某一日期没有排名数据返回 422，其余错误返回 500
+pub fn error_status(e: &anyhow::Error) -> StatusCode {
+    if e.chain().any(|cause| cause.is::<UpstreamUnavailable>()) {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else if e.chain().any(|cause| cause.is::<RateLimited>()) {
+        StatusCode::TOO_MANY_REQUESTS
+    } else if e.chain().any(|cause| cause.is::<UpstreamBlocked>()) {
+        StatusCode::BAD_GATEWAY
+    } else if e.chain().any(|cause| cause.is::<DataNotYetAvailable>()) {
+        StatusCode::from_u16(425).expect("425 Too Early")
+    } else if e
+        .chain()
+        .any(|cause| {
+            cause.is::<NonTradingDay>() || cause.is::<DataNotFound>() || cause.is::<ContractExpired>()
+        })
+    {
+        StatusCode::NOT_FOUND
+    } else if e.chain().any(|cause| cause.is::<RankDateUnavailable>()) {
+        StatusCode::UNPROCESSABLE_ENTITY
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// 列表接口的统一响应
+///
+/// 约定：查询成功但没有匹配的数据时返回 200、data 为空数组、count 为 0，客户端无需区分
+/// "空结果"和"错误"；错误只用于真正的失败（解析失败、网络错误、熔断等），状态码由
+/// error_status 决定。按日发布的交易所数据在该日期无数据时由服务层 ensure_published
+/// 转为 404/425，属于请求的日期本身不可用，不在此列
+pub fn ok_or_empty<T: Serialize>(result: anyhow::Result<Vec<T>>) -> HttpResponse {
+    match result {
+        Ok(data) => HttpResponse::Ok().json(ApiResponse::success_list(data)),
+        Err(e) => error_response(&e),
+    }
+}
+
+/// 按日查询的列表响应，成功时附带实际查询日期 effective_date，其余同 ok_or_empty
+pub fn ok_or_empty_on<T: Serialize>(result: anyhow::Result<Vec<T>>, date: &str) -> HttpResponse {
+    match result {
+        Ok(data) => HttpResponse::Ok().json(ApiResponse::success_list(data).with_effective_date(date)),
+        Err(e) => error_response(&e),
+    }
+}
+
+/// 按 error_status 构造错误响应，新浪封禁冷却中（429）时附带 Retry-After 头
+pub fn error_response(e: &anyhow::Error) -> HttpResponse {
+    let mut builder = HttpResponse::build(error_status(e));
+    if let Some(limited) = e.chain().find_map(|cause| cause.downcast_ref::<RateLimited>()) {
+        builder.insert_header((header::RETRY_AFTER, limited.retry_after_secs.max(1).to_string()));
+    }
+    builder.json(ApiResponse::<()>::error(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn error_status_maps_typed_errors_directly_and_through_context() {
+        let cases: Vec<(fn() -> anyhow::Error, u16)> = vec![
+            (|| UpstreamUnavailable { host: "hq.sinajs.cn".to_string(), retry_after_secs: 30 }.into(), 503),
+            (|| RateLimited { retry_after_secs: 60 }.into(), 429),
+            (|| UpstreamBlocked { source: "上期所", status: 403, suggestion: "稍后重试" }.into(), 502),
+            (|| DataNotYetAvailable { date: "20240102".to_string() }.into(), 425),
+            (|| NonTradingDay { date: "2024-01-01".to_string() }.into(), 404),
+            (|| DataNotFound { date: "20240102".to_string(), reason: "交易所未发布" }.into(), 404),
+            (|| ContractExpired { contract: "RB2101".to_string(), month: "2021-01".to_string() }.into(), 404),
+            (|| anyhow::anyhow!("解析失败"), 500),
+        ];
+
+        for (make, expected) in cases {
+            let direct = make();
+            assert_eq!(error_status(&direct).as_u16(), expected, "{}", direct);
+
+            let wrapped = Err::<(), _>(make()).context("获取数据失败").context("处理请求失败").unwrap_err();
+            assert_eq!(error_status(&wrapped).as_u16(), expected, "{:#}", wrapped);
+        }
+    }
+
+    #[test]
+    fn rank_date_unavailable_maps_to_422_through_context() {
+        let missing = || -> anyhow::Result<()> {
+            Err(RankDateUnavailable { date: "20240101".to_string(), reason: "非交易日".to_string() }.into())
+        };
+        let wrapped = missing().context("对比持仓排名失败").unwrap_err();
+        assert_eq!(error_status(&wrapped), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error_status(&missing().unwrap_err()), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    async fn body_json(response: HttpResponse) -> serde_json::Value {
+        let bytes = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn ok_or_empty_returns_200_with_empty_array_and_zero_count() {
+        let response = ok_or_empty(Ok(Vec::<String>::new()));
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_json(response).await;
+        assert_eq!(body["success"], true);
+        assert_eq!(body["data"], serde_json::json!([]));
+        assert_eq!(body["count"], 0);
+
+        let body = body_json(ok_or_empty_on(Ok(Vec::<String>::new()), "20240105")).await;
+        assert_eq!(body["data"], serde_json::json!([]));
+        assert_eq!(body["count"], 0);
+        assert_eq!(body["effective_date"], "20240105");
+    }
+
+    #[actix_web::test]
+    async fn ok_or_empty_keeps_errors_for_real_failures() {
+        let response = ok_or_empty::<String>(Err(anyhow::anyhow!("解析失败")));
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = body_json(response).await;
+        assert_eq!(body["success"], false);
+        assert!(body.get("count").is_none_or(|c| c.is_null()));
+
+        let response = ok_or_empty::<String>(Err(RateLimited { retry_after_secs: 0 }.into()));
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "1");
+    }
+}
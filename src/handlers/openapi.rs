@@ -0,0 +1,1422 @@
+//! OpenAPI 文档接口
+//!
+//! 手工构建 OpenAPI 3 规范文档，供前端和第三方工具生成客户端
+
+use actix_web::{web, HttpResponse, Result};
+use serde_json::{json, Map, Value};
+
+use super::routes::{register, RouteEntry, RouteInfo};
+use crate::config;
+
+/// 获取 OpenAPI 规范文档
+///
+/// GET /api/v1/openapi.json
+pub async fn get_openapi_spec() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(build_openapi_spec()))
+}
+
+/// 本模块的路由表，config 按表注册
+pub const ROUTES: &[RouteEntry] =
+    &[RouteInfo::get("/api/v1/openapi.json", "OpenAPI 3 接口文档").bind(|r| r.to(get_openapi_spec))];
+
+/// 配置 OpenAPI 文档路由
+pub fn config(cfg: &mut web::ServiceConfig) {
+    register(cfg, "/api/v1", ROUTES);
+}
+
+// ==================== 文档构建 ====================
+
+/// 构建完整的 OpenAPI 文档
+pub fn build_openapi_spec() -> Value {
+    let auth = config::api_key_auth();
+    let mut security = vec![
+        json!({ "bearerAuth": [] }),
+        json!({ "apiKeyHeader": [] }),
+    ];
+    let mut schemes = json!({
+        "bearerAuth": { "type": "http", "scheme": "bearer" },
+        "apiKeyHeader": { "type": "apiKey", "in": "header", "name": auth.key_header },
+    });
+    if auth.allow_query_key {
+        security.push(json!({ "apiKeyQuery": [] }));
+        schemes["apiKeyQuery"] = json!({ "type": "apiKey", "in": "query", "name": "api_key" });
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "AkShare 后端服务",
+            "description": "提供期货和股票数据的 RESTful API 服务",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": "/api/v1" }],
+        "security": security,
+        "paths": build_paths(),
+        "components": {
+            "securitySchemes": schemes,
+            "schemas": build_schemas(),
+        },
+    })
+}
+
+/// 构建接口路径定义
+fn build_paths() -> Value {
+    let rank_params = || {
+        vec![
+            query_param("date", "交易日期（YYYYMMDD），默认最近交易日", false, "string"),
+            query_param("vars", "品种代码列表，逗号分隔，如 CU,AL", false, "string"),
+            query_param("no_cache", "为 1 时跳过本地文件缓存", false, "integer"),
+        ]
+    };
+    let rank_table = |summary: &str| {
+        json!({ "get": operation(summary, rank_params(), array_of("RankTableResponse")) })
+    };
+
+    // 实时行情支持 fields=quote 精简视图
+    let fields_param = || {
+        query_param(
+            "fields",
+            "full（默认）返回完整行情，quote 仅返回 symbol/price/change_percent/ts",
+            false,
+            "string",
+        )
+    };
+    // 单合约行情和日K线支持 precision=fixed 字符串价格
+    let precision_param = || {
+        query_param(
+            "precision",
+            "raw（默认）仅返回浮点价格，fixed 另返回按最小变动价位对齐的 *_str 字符串价格",
+            false,
+            "string",
+        )
+    };
+    // 日K线和分钟K线支持 with_meta=1 附带报价单位等合约元数据
+    let with_meta_param = || {
+        query_param(
+            "with_meta",
+            "为 1 时附带 meta（报价单位、最小变动价位、合约乘数）",
+            false,
+            "integer",
+        )
+    };
+    let debug_raw_param = || {
+        query_param(
+            "debug_raw",
+            "为 1 时每行附带解析所用的上游原始单元格 _raw（需开启 debug.endpoints_enabled，否则返回 400）",
+            false,
+            "integer",
+        )
+    };
+    let realtime_list = || {
+        json!({ "oneOf": [array_of("FuturesInfo"), array_of("FuturesQuote")] })
+    };
+    // 未单独定义模型的列表数据
+    let object_list = || json!({ "type": "array", "items": { "type": "object" } });
+    let date_param = || query_param("date", "交易日期（YYYYMMDD），默认最近交易日", false, "string");
+
+    let mut paths = Map::new();
+
+    paths.insert(
+        "/health".to_string(),
+        json!({ "get": {
+            "summary": "健康检查",
+            "security": [],
+            "responses": { "200": response_of(json!({ "type": "string" })) },
+        }}),
+    );
+    paths.insert(
+        "/openapi.json".to_string(),
+        json!({ "get": {
+            "summary": "OpenAPI 3 接口文档（本文档）",
+            "responses": { "200": { "description": "OpenAPI 文档", "content": { "application/json": { "schema": { "type": "object" } } } } },
+        }}),
+    );
+    paths.insert(
+        "/routes".to_string(),
+        json!({ "get": {
+            "summary": "列出服务提供的所有接口（方法、路径、说明）",
+            "security": [],
+            "responses": { "200": response_of(array_of("RouteInfo")) },
+        }}),
+    );
+
+    paths.insert(
+        "/metrics".to_string(),
+        json!({ "get": operation("获取运行指标（上游请求限流和熔断状态）", vec![], schema_ref("Metrics")) }),
+    );
+    paths.insert(
+        "/admin/reload".to_string(),
+        json!({ "post": operation("重新加载配置文件（热更新无需重启的配置；需 X-Admin-Key 或本机访问）", vec![], schema_ref("ReloadReport")) }),
+    );
+    paths.insert(
+        "/admin/sources".to_string(),
+        json!({ "get": operation("获取上游数据源状态看板（最近成功时间、最近错误、熔断状态；需 X-Admin-Key 或本机访问）", vec![], array_of("SourceHealth")) }),
+    );
+
+    // 期货实时行情
+    paths.insert(
+        "/futures".to_string(),
+        json!({ "get": operation(
+            "获取期货列表（按交易所或品种）",
+            vec![
+                query_param("exchange", "交易所代码：DCE/CZCE/SHFE/INE/CFFEX/GFEX", false, "string"),
+                query_param("limit", "返回数量限制", false, "integer"),
+                fields_param(),
+            ],
+            realtime_list(),
+        )}),
+    );
+    paths.insert(
+        "/futures/exchanges".to_string(),
+        json!({ "get": operation("获取支持的交易所列表", vec![], object_list()) }),
+    );
+    paths.insert(
+        "/futures/symbols".to_string(),
+        json!({ "get": operation(
+            "获取按交易所分组的品种映射表（{交易所代码: [{symbol, mark}]}，exchange 无效时返回 400）",
+            vec![query_param("exchange", "交易所代码（SHFE/DCE/CZCE/CFFEX/GFEX），为空返回全部", false, "string")],
+            json!({ "type": "object", "additionalProperties": array_of("FuturesSymbolMark") }),
+        )}),
+    );
+    paths.insert(
+        "/futures/symbols/{exchange}".to_string(),
+        json!({ "get": operation(
+            "获取指定交易所的品种列表",
+            vec![path_param("exchange", "交易所代码（如 SHFE）")],
+            object_list(),
+        )}),
+    );
+    paths.insert(
+        "/futures/{symbol}".to_string(),
+        json!({ "get": {
+            "summary": "获取单个合约实时数据",
+            "parameters": [path_param("symbol", "合约代码（如 RB2510）"), fields_param(), precision_param()],
+            "responses": with_response(
+                standard_responses(json!({ "oneOf": [schema_ref("FuturesInfo"), schema_ref("FuturesQuote")] })),
+                "404",
+                "合约已到期摘牌（合约月份早于当前交易日）",
+            ),
+        }}),
+    );
+    paths.insert(
+        "/futures/batch".to_string(),
+        json!({ "post": {
+            "summary": "批量获取期货实时数据",
+            "parameters": [fields_param()],
+            "requestBody": {
+                "required": true,
+                "content": { "application/json": { "schema": {
+                    "type": "array", "items": { "type": "string" }
+                }}},
+            },
+            "responses": standard_responses(realtime_list()),
+        }}),
+    );
+    paths.insert(
+        "/futures/batch/detail".to_string(),
+        json!({ "post": {
+            "summary": "批量获取期货实时数据，返回每个合约的获取状态（ok / no_data / parse_error）",
+            "requestBody": {
+                "required": true,
+                "content": { "application/json": { "schema": {
+                    "type": "array", "items": { "type": "string" }
+                }}},
+            },
+            "responses": standard_responses(array_of("FuturesBatchItem")),
+        }}),
+    );
+    paths.insert(
+        "/futures/realtime/{symbol}".to_string(),
+        json!({ "get": operation(
+            "获取品种所有合约实时数据",
+            vec![path_param("symbol", "品种名称（如 沪铜）"), fields_param()],
+            realtime_list(),
+        )}),
+    );
+    paths.insert(
+        "/futures/sse".to_string(),
+        json!({ "get": {
+            "summary": "实时行情 SSE 推送（每个 data 事件为 FuturesInfo 或 FuturesQuote 数组）",
+            "parameters": [
+                query_param("symbols", "合约代码，逗号分隔（最多 50 个）", true, "string"),
+                query_param("interval_secs", "推送间隔（秒），1-60，默认 3", false, "integer"),
+                fields_param(),
+            ],
+            "responses": {
+                "200": {
+                    "description": "text/event-stream 事件流；获取失败时推送 error 事件，定期发送注释心跳",
+                    "content": { "text/event-stream": { "schema": { "type": "string" } } },
+                },
+                "400": {
+                    "description": "参数错误",
+                    "content": { "application/json": { "schema": envelope(json!({ "nullable": true })) } },
+                },
+                "401": { "description": "无效的 API Key" },
+            },
+        }}),
+    );
+
+    paths.insert(
+        "/futures/variety/{name}/contracts".to_string(),
+        json!({ "get": {
+            "summary": "获取品种所有在交易合约（按持仓量降序）",
+            "parameters": [
+                path_param("name", "品种名称（如 沪铜、PTA）"),
+                query_param("limit", "返回数量限制", false, "integer"),
+                fields_param(),
+            ],
+            "responses": {
+                "200": response_of(realtime_list()),
+                "401": { "description": "无效的 API Key" },
+                "404": { "description": "未找到品种" },
+                "409": {
+                    "description": "品种名称匹配到多个品种，data 中返回候选品种",
+                    "content": { "application/json": { "schema": envelope(array_of("FuturesSymbolMark")) } },
+                },
+            },
+        }}),
+    );
+
+    // 外盘期货
+    paths.insert(
+        "/futures/foreign/symbols".to_string(),
+        json!({ "get": operation("获取外盘期货品种列表", vec![], array_of("ForeignFuturesSymbol")) }),
+    );
+    paths.insert(
+        "/futures/foreign/realtime".to_string(),
+        json!({
+            "get": operation(
+                "获取外盘期货实时行情（未知代码忽略并列入 warnings，全部未知时返回 400）",
+                vec![
+                    query_param("codes", "品种代码列表，逗号分隔，如 GC,CL", true, "string"),
+                    fields_param(),
+                ],
+                realtime_list(),
+            ),
+            "post": {
+                "summary": "获取外盘期货实时行情（未知代码忽略并列入 warnings，全部未知时返回 400）",
+                "parameters": [fields_param()],
+                "requestBody": {
+                    "required": true,
+                    "content": { "application/json": { "schema": {
+                        "type": "array", "items": { "type": "string" }
+                    }}},
+                },
+                "responses": standard_responses(realtime_list()),
+            },
+        }),
+    );
+    let foreign_history = json!({ "get": operation(
+        "获取外盘期货日K线",
+        vec![path_param("symbol", "外盘品种代码（如 GC）")],
+        array_of("ForeignFuturesHistData"),
+    )});
+    paths.insert("/futures/foreign/{symbol}/history".to_string(), foreign_history.clone());
+    paths.insert("/futures/foreign/{symbol}/hist".to_string(), foreign_history);
+    paths.insert(
+        "/futures/foreign/{symbol}/detail".to_string(),
+        json!({ "get": operation(
+            "获取外盘期货合约详情",
+            vec![path_param("symbol", "外盘品种代码（如 GC）")],
+            json!({ "type": "object" }),
+        )}),
+    );
+    paths.insert(
+        "/futures/contracts/{symbol}/expiry".to_string(),
+        json!({ "get": operation(
+            "按交易所规则推算合约最后交易日和最后交割日（不含法定节假日）",
+            vec![path_param("symbol", "具体月份合约代码（如 CU2405、MA505）")],
+            schema_ref("FuturesContractExpiry"),
+        )}),
+    );
+    paths.insert(
+        "/futures/variety-map".to_string(),
+        json!({ "get": operation(
+            "中文品种名称与英文代码对照表（按交易所分组排列）",
+            vec![query_param("q", "过滤关键字：匹配中文名称或英文代码（不区分大小写）", false, "string")],
+            array_of("FuturesVarietyName"),
+        )}),
+    );
+
+    paths.insert(
+        "/futures/hold-pos/sina".to_string(),
+        json!({ "get": operation(
+            "新浪成交持仓排名（新浪封禁冷却中返回 429 并带 Retry-After）",
+            vec![
+                query_param("symbol", "成交量/多单持仓/空单持仓 或 volume/long/short，默认成交量", false, "string"),
+                query_param("contract", "合约代码（如 OI2501）", true, "string"),
+                query_param("date", "交易日期（YYYYMMDD），默认最近交易日", false, "string"),
+            ],
+            array_of("SinaHoldPosition"),
+        )}),
+    );
+
+    paths.insert(
+        "/futures/hold_pos".to_string(),
+        json!({ "get": operation(
+            "新浪持仓排名（新浪封禁冷却中返回 429 并带 Retry-After）",
+            vec![
+                query_param("pos_type", "volume/long/short 或 成交量/多单持仓/空单持仓，默认 volume", false, "string"),
+                query_param("contract", "合约代码（如 RB2510）", true, "string"),
+                date_param(),
+            ],
+            array_of("SinaHoldPosition"),
+        )}),
+    );
+
+    // 合约详情
+    paths.insert(
+        "/futures/{symbol}/detail".to_string(),
+        json!({ "get": operation(
+            "获取合约详情（交易单位、报价单位、最小变动价位等）",
+            vec![path_param("symbol", "合约代码（如 CU2602）")],
+            json!({ "type": "object" }),
+        )}),
+    );
+
+    // 期货K线
+    paths.insert(
+        "/futures/{symbol}/history".to_string(),
+        json!({ "get": operation(
+            "获取日K线数据",
+            vec![
+                path_param("symbol", "合约代码"),
+                query_param("limit", "返回数量限制（默认30）", false, "integer"),
+                query_param("no_cache", "为 1 时跳过本地文件缓存", false, "integer"),
+                precision_param(),
+                with_meta_param(),
+            ],
+            array_of("FuturesHistoryData"),
+        )}),
+    );
+    paths.insert(
+        "/futures/{symbol}/minute".to_string(),
+        json!({ "get": operation(
+            "获取分钟K线数据",
+            vec![
+                path_param("symbol", "合约代码"),
+                query_param("period", "周期：1/5/15/30/60（默认5）", false, "string"),
+                with_meta_param(),
+            ],
+            array_of("FuturesHistoryData"),
+        )}),
+    );
+    paths.insert(
+        "/futures/minute/{symbol}/multi".to_string(),
+        json!({ "get": operation(
+            "同时获取多个周期的分钟K线（按周期分组）",
+            vec![
+                path_param("symbol", "合约代码"),
+                query_param("periods", "逗号分隔的周期：1/5/15/30/60，最多 5 个", true, "string"),
+            ],
+            json!({ "type": "object", "additionalProperties": array_of("FuturesHistoryData") }),
+        )}),
+    );
+
+    // 市场概览
+    paths.insert(
+        "/futures/overview".to_string(),
+        json!({ "get": operation(
+            "获取首页市场概览（缓存 api.overview_cache_secs 秒，获取失败的部分省略）",
+            vec![],
+            schema_ref("FuturesOverview"),
+        )}),
+    );
+
+    // 主力连续合约
+    let main_contracts = json!({ "get": operation("获取主力连续合约一览表", vec![], array_of("FuturesMainContract")) });
+    paths.insert("/futures/main".to_string(), main_contracts.clone());
+    paths.insert("/futures/main/display".to_string(), main_contracts);
+    paths.insert(
+        "/futures/main/{key}".to_string(),
+        json!({ "get": operation(
+            "获取主力连续日K线（key 为交易所代码时返回该交易所主力合约代码列表）",
+            vec![
+                path_param("key", "主力连续合约代码（如 RB0）或交易所代码（如 SHFE）"),
+                query_param("start_date", "开始日期（YYYYMMDD）", false, "string"),
+                query_param("end_date", "结束日期（YYYYMMDD）", false, "string"),
+            ],
+            json!({ "oneOf": [array_of("FuturesMainDailyData"), { "type": "array", "items": { "type": "string" } }] }),
+        )}),
+    );
+    paths.insert(
+        "/futures/main/{symbol}/daily".to_string(),
+        json!({ "get": operation(
+            "获取主力连续日K线",
+            vec![
+                path_param("symbol", "主力连续合约代码（如 RB0）"),
+                query_param("start_date", "开始日期（YYYYMMDD）", false, "string"),
+                query_param("end_date", "结束日期（YYYYMMDD）", false, "string"),
+            ],
+            array_of("FuturesMainDailyData"),
+        )}),
+    );
+
+    // 库存数据
+    paths.insert(
+        "/futures/inventory99".to_string(),
+        json!({ "get": operation(
+            "获取99期货网库存数据",
+            vec![query_param("symbol", "品种名称（如 豆一）或代码（如 A）", true, "string")],
+            array_of("FuturesInventory99"),
+        )}),
+    );
+    paths.insert(
+        "/futures/inventory99/symbols".to_string(),
+        json!({ "get": operation("获取99期货网品种映射表", vec![], object_list()) }),
+    );
+    paths.insert(
+        "/futures/inventory/{symbol}".to_string(),
+        json!({ "get": operation(
+            "按日期范围获取99期货网库存数据",
+            vec![
+                path_param("symbol", "品种名称（如 豆一）或代码（如 A）"),
+                query_param("start_date", "开始日期（YYYYMMDD，含）", false, "string"),
+                query_param("end_date", "结束日期（YYYYMMDD，含）", false, "string"),
+                query_param("limit", "仅返回最近 N 条", false, "integer"),
+            ],
+            array_of("FuturesInventory99"),
+        )}),
+    );
+
+    // 交易费用
+    paths.insert(
+        "/futures/fees".to_string(),
+        json!({ "get": operation(
+            "获取期货交易费用参照表，可按交易所、品种过滤并按数值字段排序",
+            vec![
+                query_param("exchange", "交易所代码（如 SHFE）或名称", false, "string"),
+                query_param("product", "品种代码（如 rb）或名称", false, "string"),
+                query_param(
+                    "sort",
+                    "排序字段：contract_size/price_tick/open_fee_rate/open_fee/close_fee_rate/close_fee/close_today_fee_rate/close_today_fee/long_margin_rate/short_margin_rate，其他值返回 400",
+                    false,
+                    "string",
+                ),
+                query_param("order", "排序方向 asc（默认）/ desc", false, "string"),
+                debug_raw_param(),
+            ],
+            array_of("FuturesFeesInfo"),
+        )}),
+    );
+    paths.insert(
+        "/futures/fees/per-lot/{symbol}".to_string(),
+        json!({ "get": {
+            "summary": "按当前价格和交易费用表计算合约单手手续费和保证金",
+            "parameters": [path_param("symbol", "合约代码（如 RB2601）")],
+            "responses": with_response(
+                standard_responses(schema_ref("FuturesPerLotCost")),
+                "404",
+                "交易费用表中没有该品种",
+            ),
+        }}),
+    );
+
+    let comm_params = || {
+        vec![
+            query_param("exchange", "交易所名称（如 上海期货交易所），默认所有", false, "string"),
+            debug_raw_param(),
+        ]
+    };
+    paths.insert(
+        "/futures/comm_info".to_string(),
+        json!({ "get": operation("获取九期网期货手续费信息", comm_params(), object_list()) }),
+    );
+    paths.insert(
+        "/futures/comm-info".to_string(),
+        json!({ "get": operation(
+            "获取期货手续费信息（九期网不可用时降级为 OpenCTP，source 标明实际数据源）",
+            comm_params(),
+            json!({ "type": "object" }),
+        )}),
+    );
+
+    // 现货价格及基差
+    paths.insert(
+        "/futures/spot_price".to_string(),
+        json!({ "get": operation(
+            "获取现货价格及基差",
+            vec![date_param(), query_param("symbols", "品种代码列表，逗号分隔（如 RB,CU）", false, "string")],
+            array_of("FuturesSpotPrice"),
+        )}),
+    );
+    let spot_previous = json!({ "get": operation(
+        "获取历史现货价格及 180 日基差统计",
+        vec![
+            date_param(),
+            query_param("fallback", "为 true 时当日无数据则回溯到最近有数据的交易日", false, "boolean"),
+            query_param("commodity", "商品名称关键字（如 螺纹）", false, "string"),
+        ],
+        object_list(),
+    )});
+    paths.insert("/futures/spot_price_previous".to_string(), spot_previous.clone());
+    paths.insert("/futures/spot-price/previous".to_string(), spot_previous);
+    paths.insert(
+        "/futures/spot_price_daily".to_string(),
+        json!({ "get": operation(
+            "获取现货价格日线（日期范围，非交易日跳过）",
+            vec![
+                query_param("start_date", "开始日期（YYYYMMDD）", true, "string"),
+                query_param("end_date", "结束日期（YYYYMMDD）", true, "string"),
+                query_param("symbols", "品种代码列表，逗号分隔（如 RB,CU）", false, "string"),
+            ],
+            array_of("FuturesSpotPrice"),
+        )}),
+    );
+
+    // 交易规则
+    paths.insert(
+        "/futures/rule".to_string(),
+        json!({ "get": operation(
+            "获取期货交易规则（非交易日返回 404）",
+            vec![
+                query_param("date", "交易日期（YYYYMMDD），默认最近交易日", false, "string"),
+                query_param("exchange", "交易所代码（如 SHFE）或名称", false, "string"),
+                query_param("product", "品种代码（如 CU）或名称", false, "string"),
+                debug_raw_param(),
+            ],
+            array_of("FuturesRule"),
+        )}),
+    );
+
+    // 持仓排名
+    paths.insert("/futures/rank/shfe".to_string(), rank_table("获取上期所持仓排名表"));
+    paths.insert("/futures/rank/cffex".to_string(), rank_table("获取中金所持仓排名表"));
+    paths.insert("/futures/rank/dce".to_string(), rank_table("获取大商所持仓排名表"));
+    paths.insert("/futures/rank/gfex".to_string(), rank_table("获取广期所持仓排名表"));
+    paths.insert("/futures/rank/czce".to_string(), rank_table("获取郑商所持仓排名表"));
+    paths.insert(
+        "/futures/rank/sum".to_string(),
+        json!({ "get": operation("获取持仓排名汇总（单日）", rank_params(), array_of("RankSum")) }),
+    );
+    paths.insert(
+        "/futures/rank/sum_daily".to_string(),
+        json!({ "get": operation(
+            "获取持仓排名汇总（日期区间，不超过 api.max_range_days）",
+            vec![
+                query_param("start_date", "开始日期（YYYYMMDD）", true, "string"),
+                query_param("end_date", "结束日期（YYYYMMDD）", true, "string"),
+                query_param("vars", "品种代码列表，逗号分隔（如 CU,AL）", false, "string"),
+            ],
+            array_of("RankSum"),
+        )}),
+    );
+    paths.insert(
+        "/futures/position-rank/{exchange}".to_string(),
+        json!({ "get": operation(
+            "持仓排名表统一入口，按交易所分发并按品种统一过滤（未知交易所返回 400，上游反爬虫拒绝返回 502）",
+            [vec![path_param("exchange", "交易所代码：SHFE/CFFEX/DCE/CZCE/GFEX")], rank_params()].concat(),
+            array_of("RankTableResponse"),
+        )}),
+    );
+    paths.insert(
+        "/futures/position-rank/sum".to_string(),
+        json!({ "get": operation(
+            "持仓排名汇总统一入口：date 查询单日，或 start_date + end_date 查询日期区间（二选一，区间不超过 api.max_range_days）",
+            vec![
+                query_param("date", "交易日期（YYYYMMDD，单日），与区间都未提供时默认最近交易日", false, "string"),
+                query_param("start_date", "开始日期（YYYYMMDD，区间）", false, "string"),
+                query_param("end_date", "结束日期（YYYYMMDD，区间）", false, "string"),
+                query_param("vars", "品种代码列表，逗号分隔（如 CU,AL）", false, "string"),
+                query_param("no_cache", "为 1 时跳过本地文件缓存（仅单日）", false, "integer"),
+            ],
+            array_of("RankSum"),
+        )}),
+    );
+    paths.insert(
+        "/futures/position-net/{exchange}/{contract}".to_string(),
+        json!({ "get": operation(
+            "获取合约会员净持仓及前20多空汇总",
+            vec![
+                path_param("exchange", "交易所代码：SHFE/CFFEX/DCE/CZCE/GFEX"),
+                path_param("contract", "合约代码（如 RB2510）"),
+                query_param("date", "交易日期（YYYYMMDD），默认最近交易日", false, "string"),
+            ],
+            schema_ref("PositionNetResponse"),
+        )}),
+    );
+    paths.insert(
+        "/futures/concentration/{exchange}/{contract}".to_string(),
+        json!({ "get": operation(
+            "获取合约持仓集中度：多空前5名集中度 CR5 和前20名赫芬达尔指数 HHI",
+            vec![
+                path_param("exchange", "交易所代码：SHFE/CFFEX/DCE/CZCE/GFEX"),
+                path_param("contract", "合约代码（如 RB2510）"),
+                query_param("date", "交易日期（YYYYMMDD），默认最近交易日", false, "string"),
+            ],
+            schema_ref("PositionConcentration"),
+        )}),
+    );
+    paths.insert(
+        "/futures/position-diff/{exchange}/{contract}".to_string(),
+        json!({ "get": operation(
+            "对比合约两个交易日的会员持仓排名（任一日期无数据时返回 422）",
+            vec![
+                path_param("exchange", "交易所代码：SHFE/CFFEX/DCE/CZCE/GFEX"),
+                path_param("contract", "合约代码（如 RB2510）"),
+                query_param("date1", "第一个交易日（YYYYMMDD）", true, "string"),
+                query_param("date2", "第二个交易日（YYYYMMDD）", true, "string"),
+            ],
+            schema_ref("PositionDiffResponse"),
+        )}),
+    );
+
+    // 仓单日报
+    for (exchange, name) in [("czce", "郑商所"), ("dce", "大商所"), ("shfe", "上期所"), ("gfex", "广期所")] {
+        paths.insert(
+            format!("/futures/warehouse/{}", exchange),
+            json!({ "get": operation(
+                &format!("获取{}仓单日报", name),
+                vec![
+                    date_param(),
+                    query_param("vars", "品种代码列表，逗号分隔（如 SR,CF）", false, "string"),
+                    query_param("aggregate", "variety 时按品种汇总各仓库仓单", false, "string"),
+                    query_param("no_cache", "为 1 时跳过本地文件缓存", false, "integer"),
+                ],
+                object_list(),
+            )}),
+        );
+    }
+
+    // 调试
+    paths.insert(
+        "/futures/debug/format".to_string(),
+        json!({ "get": operation(
+            "诊断实时行情代码格式（需开启 debug.endpoints_enabled，否则返回 404）",
+            vec![query_param("symbol", "合约代码（如 CU2405）", true, "string")],
+            schema_ref("RealtimeRequestDebug"),
+        )}),
+    );
+
+    // 股票
+    paths.insert(
+        "/stocks".to_string(),
+        json!({ "get": operation(
+            "获取股票列表",
+            vec![query_param("limit", "返回数量限制（默认20）", false, "integer")],
+            array_of("StockInfo"),
+        )}),
+    );
+    paths.insert(
+        "/stocks/{symbol}".to_string(),
+        json!({ "get": operation(
+            "获取单只股票信息",
+            vec![path_param("symbol", "股票代码（如 sh600000）")],
+            schema_ref("StockInfo"),
+        )}),
+    );
+    paths.insert(
+        "/stocks/history/bulk".to_string(),
+        json!({ "get": {
+            "summary": "批量获取股票日K线（默认 NDJSON 流式输出，每行带 code，失败的股票输出 error 行；format=parquet 时返回 Parquet 文件）",
+            "parameters": [
+                query_param("codes", "股票代码，逗号分隔，sh/sz/bj 加 6 位数字（最多 50 个）", true, "string"),
+                query_param("start_date", "开始日期（YYYYMMDD）", false, "string"),
+                query_param("end_date", "结束日期（YYYYMMDD）", false, "string"),
+                query_param("format", "输出格式：ndjson（默认）或 parquet，其他值返回 400", false, "string"),
+            ],
+            "responses": {
+                "200": {
+                    "description": "application/x-ndjson，每行为 StockHistoryData 加 code 字段，或 {code, error}；\
+                        format=parquet 时为 Parquet 文件（code/date 字符串，OHLC 为 double，volume 为 int64），\
+                        获取失败的股票代码列在 X-Failed-Codes 响应头中",
+                    "content": {
+                        "application/x-ndjson": { "schema": { "type": "string" } },
+                        "application/vnd.apache.parquet": { "schema": { "type": "string", "format": "binary" } },
+                    },
+                },
+                "400": {
+                    "description": "参数错误",
+                    "content": { "application/json": { "schema": envelope(json!({ "nullable": true })) } },
+                },
+                "401": { "description": "无效的 API Key" },
+            },
+        }}),
+    );
+    paths.insert(
+        "/stocks/{symbol}/history".to_string(),
+        json!({ "get": operation(
+            "获取股票历史K线数据",
+            vec![
+                path_param("symbol", "股票代码"),
+                query_param("start_date", "开始日期（YYYYMMDD）", false, "string"),
+                query_param("end_date", "结束日期（YYYYMMDD）", false, "string"),
+                query_param("limit", "返回数量限制（未指定日期范围时默认30）", false, "integer"),
+                query_param("adjust", "复权方式：none（默认）/ qfq（前复权）/ hfq（后复权）", false, "string"),
+            ],
+            array_of("StockHistoryData"),
+        )}),
+    );
+
+    Value::Object(paths)
+}
+
+/// 构建数据模型定义
+fn build_schemas() -> Value {
+    let mut schemas = Map::new();
+    for group in [common_schemas(), futures_schemas(), fees_schemas(), stock_schemas()] {
+        if let Value::Object(map) = group {
+            schemas.extend(map);
+        }
+    }
+    Value::Object(schemas)
+}
+
+/// 通用模型：运行指标、配置热更新、数据来源
+fn common_schemas() -> Value {
+    json!({
+        "SinaLimiterStats": object_schema(&[
+            ("max_permits", "integer", "最大并发数"),
+            ("in_use", "integer", "当前占用的许可数"),
+            ("available", "integer", "当前可用的许可数"),
+            ("total_requests", "integer", "累计请求数"),
+            ("slow_acquires", "integer", "排队超过告警阈值的请求数"),
+            ("max_wait_ms", "integer", "最长排队时间（毫秒）"),
+            ("ban_remaining_secs", "integer", "新浪 IP 封禁冷却剩余秒数（未封禁时为 0）"),
+            ("bans", "integer", "累计检测到封禁的次数"),
+            ("ban_rejected", "integer", "封禁冷却期内被直接拒绝的请求数"),
+        ]),
+        "UpstreamBreakerStats": object_schema(&[
+            ("host", "string", "上游域名"),
+            ("state", "string", "状态：closed / open / half_open"),
+            ("consecutive_failures", "integer", "当前窗口内的连续失败次数"),
+            ("open_remaining_secs", "integer", "熔断剩余秒数"),
+            ("trips", "integer", "累计熔断次数"),
+            ("rejected", "integer", "累计被拒绝的请求数"),
+        ]),
+        "FuturesVarietyName": object_schema(&[
+            ("cn", "string", "中文品种名称（同一品种的别名各占一项）"),
+            ("en", "string", "英文品种代码"),
+            ("exchange", "string", "交易所代码"),
+        ]),
+        "RouteInfo": object_schema(&[
+            ("method", "string", "HTTP 方法"),
+            ("path", "string", "完整路径（含 /api/v1 前缀）"),
+            ("description", "string", "一句话说明"),
+        ]),
+        "FuturesContractExpiry": object_schema(&[
+            ("contract", "string", "合约代码（大写）"),
+            ("variety", "string", "品种代码"),
+            ("exchange", "string", "交易所代码"),
+            ("last_trading_day", "string", "最后交易日 YYYY-MM-DD"),
+            ("delivery_day", "string", "最后交割日 YYYY-MM-DD"),
+            ("days_to_expiry", "integer", "距最后交易日的自然日数，已到期为负数"),
+            ("rule", "string", "所用规则说明"),
+        ]),
+        "SinaHoldPosition": object_schema(&[
+            ("rank", "integer", "名次"),
+            ("company", "string", "期货公司"),
+            ("value", "integer", "数值（成交量/多单持仓/空单持仓）"),
+            ("change", "integer", "比上交易日增减"),
+        ]),
+        "SourceHealth": object_schema(&[
+            ("name", "string", "数据源名称"),
+            ("host", "string", "上游域名"),
+            ("last_success_at", "string?", "最近一次成功请求的时间（北京时间），启动后未请求过时为 null"),
+            ("last_error", "string?", "最近一次错误（URL 只保留域名）"),
+            ("last_error_at", "string?", "最近一次错误的时间（北京时间）"),
+            ("consecutive_failures", "integer", "连续失败次数（成功后清零）"),
+            ("breaker_state", "string", "熔断状态：closed / open / half_open"),
+            ("breaker_open_remaining_secs", "integer", "熔断剩余秒数"),
+            ("ban_remaining_secs", "integer", "新浪封禁冷却剩余秒数（非新浪数据源为 0）"),
+        ]),
+        "CoalesceStats": object_schema(&[
+            ("in_flight", "integer", "当前进行中的获取数"),
+            ("fetches", "integer", "累计发起的获取次数"),
+            ("coalesced", "integer", "累计合并到进行中获取的请求数（节省的上游请求数）"),
+        ]),
+        "Metrics": {
+            "type": "object",
+            "required": ["sina_limiter", "upstream_breakers", "request_coalescing"],
+            "properties": {
+                "sina_limiter": schema_ref("SinaLimiterStats"),
+                "upstream_breakers": array_of("UpstreamBreakerStats"),
+                "request_coalescing": schema_ref("CoalesceStats"),
+            },
+        },
+        "DataProvenance": object_schema(&[
+            ("source", "string", "来源标签：sina/eastmoney/100ppi/99qihuo/9qihuo/openctp/gtja/shfe/dce/czce/cffex/gfex，命中本地缓存时为 cache"),
+            ("fetched_at", "string", "获取时间（北京时间）"),
+            ("url", "string", "上游 URL（命中缓存时为缓存文件路径）"),
+        ]),
+        "KlineMeta": object_schema(&[
+            ("price_unit", "string?", "报价单位，如 元/吨、元/克；未知品种为 null"),
+            ("tick_size", "number?", "最小变动价位；未知品种为 null"),
+            ("contract_multiplier", "number?", "合约乘数（交易单位）；未知品种为 null"),
+        ]),
+        "ReloadReport": {
+            "type": "object",
+            "required": ["applied", "requires_restart"],
+            "properties": {
+                "applied": {
+                    "type": "array", "items": { "type": "string" },
+                    "description": "已生效的配置项",
+                },
+                "requires_restart": {
+                    "type": "array", "items": { "type": "string" },
+                    "description": "需要重启服务才能生效的配置项",
+                },
+            },
+        },
+    })
+}
+
+/// 期货数据模型
+fn futures_schemas() -> Value {
+    json!({
+        "FuturesInfo": object_schema(&[
+            ("symbol", "string", "合约代码"),
+            ("name", "string", "合约名称"),
+            ("current_price", "number?", "最新价（上游缺失时为 null）"),
+            ("change", "number", "涨跌额"),
+            ("change_percent", "number", "涨跌幅（百分比）"),
+            ("volume", "integer", "成交量（手）"),
+            ("open", "number?", "开盘价（上游缺失时为 null）"),
+            ("high", "number?", "最高价（上游缺失时为 null）"),
+            ("low", "number?", "最低价（上游缺失时为 null）"),
+            ("settlement", "number?", "结算价"),
+            ("prev_settlement", "number?", "昨结算价"),
+            ("open_interest", "integer?", "持仓量（手）"),
+            ("updated_at", "string", "更新时间（服务端抓取时间）"),
+            ("quote_time", "string?", "行情时间（上游报价时间，北京时间）"),
+            ("trading_date", "string?", "行情所属交易日（YYYY-MM-DD），夜盘归属下一个交易日；外盘为 null"),
+            ("stale", "boolean", "行情是否过期"),
+            ("source", "string?", "实时行情来源：sina 或 eastmoney（新浪失败时的备用数据源）"),
+            ("current_price_str", "string?", "最新价原始字符串（仅 precision=fixed）"),
+            ("open_str", "string?", "开盘价原始字符串（仅 precision=fixed）"),
+            ("high_str", "string?", "最高价原始字符串（仅 precision=fixed）"),
+            ("low_str", "string?", "最低价原始字符串（仅 precision=fixed）"),
+            ("settlement_str", "string?", "结算价原始字符串（仅 precision=fixed）"),
+            ("prev_settlement_str", "string?", "昨结算价原始字符串（仅 precision=fixed）"),
+        ]),
+        "FuturesSymbolMark": object_schema(&[
+            ("exchange", "string", "交易所名称（中文）"),
+            ("symbol", "string", "品种名称"),
+            ("mark", "string", "新浪 API 的 node 参数"),
+        ]),
+        "ForeignFuturesSymbol": object_schema(&[
+            ("symbol", "string", "品种中文名"),
+            ("code", "string", "品种代码"),
+        ]),
+        "FuturesHistoryData": object_schema(&[
+            ("symbol", "string", "合约代码"),
+            ("date", "string", "日期"),
+            ("open", "number?", "开盘价（上游缺失时为 null）"),
+            ("high", "number?", "最高价（上游缺失时为 null）"),
+            ("low", "number?", "最低价（上游缺失时为 null）"),
+            ("close", "number?", "收盘价（上游缺失时为 null）"),
+            ("volume", "integer", "成交量（手）"),
+            ("settlement", "number?", "结算价"),
+            ("open_interest", "integer?", "持仓量（手）"),
+            ("open_str", "string?", "开盘价原始字符串（仅 precision=fixed）"),
+            ("high_str", "string?", "最高价原始字符串（仅 precision=fixed）"),
+            ("low_str", "string?", "最低价原始字符串（仅 precision=fixed）"),
+            ("close_str", "string?", "收盘价原始字符串（仅 precision=fixed）"),
+            ("settlement_str", "string?", "结算价原始字符串（仅 precision=fixed）"),
+        ]),
+        "FuturesInventory99": object_schema(&[
+            ("date", "string", "日期"),
+            ("close_price", "number?", "收盘价"),
+            ("inventory", "number?", "库存"),
+        ]),
+        "PositionRankData": object_schema(&[
+            ("rank", "integer", "排名"),
+            ("vol_party_name", "string", "成交量排序的当前名次会员"),
+            ("vol", "integer", "该会员成交量"),
+            ("vol_chg", "integer", "该会员成交量变化量"),
+            ("long_party_name", "string", "持多单排序的当前名次会员"),
+            ("long_open_interest", "integer", "该会员持多单"),
+            ("long_open_interest_chg", "integer", "该会员持多单变化量"),
+            ("short_party_name", "string", "持空单排序的当前名次会员"),
+            ("short_open_interest", "integer", "该会员持空单"),
+            ("short_open_interest_chg", "integer", "该会员持空单变化量"),
+            ("symbol", "string", "标的合约"),
+            ("variety", "string", "品种"),
+        ]),
+        "RankSum": rank_sum_schema(),
+        "RankTableResponse": {
+            "type": "object",
+            "required": ["symbol", "data"],
+            "properties": {
+                "symbol": { "type": "string", "description": "合约代码" },
+                "data": array_of("PositionRankData"),
+            },
+        },
+        "FuturesBatchItem": {
+            "type": "object",
+            "required": ["symbol", "status"],
+            "properties": {
+                "symbol": { "type": "string", "description": "请求的合约代码" },
+                "status": {
+                    "type": "string",
+                    "enum": ["ok", "no_data", "parse_error"],
+                    "description": "获取状态：ok 正常，no_data 上游无数据（未知或已退市合约），parse_error 无法解析",
+                },
+                "data": {
+                    "allOf": [schema_ref("FuturesInfo")],
+                    "nullable": true,
+                    "description": "行情数据，status 不为 ok 时为 null",
+                },
+            },
+        },
+        "FuturesQuote": object_schema(&[
+            ("symbol", "string", "合约代码"),
+            ("price", "number?", "最新价（上游缺失时为 null）"),
+            ("change_percent", "number", "涨跌幅（百分比）"),
+            ("ts", "string", "行情时间，上游未提供时为服务端抓取时间"),
+        ]),
+        "FuturesSpotPrice": object_schema(&[
+            ("date", "string", "日期 YYYYMMDD"),
+            ("symbol", "string", "品种代码"),
+            ("spot_price", "number", "现货价格"),
+            ("near_contract", "string", "临近交割合约"),
+            ("near_contract_price", "number", "临近交割合约结算价"),
+            ("dominant_contract", "string", "主力合约"),
+            ("dominant_contract_price", "number", "主力合约结算价"),
+            ("near_basis", "number", "临近交割合约基差"),
+            ("dom_basis", "number", "主力合约基差"),
+            ("near_basis_rate", "number", "临近交割合约基差率"),
+            ("dom_basis_rate", "number", "主力合约基差率"),
+        ]),
+        "OverviewMovers": {
+            "type": "object",
+            "required": ["gainers", "losers"],
+            "properties": {
+                "gainers": array_of("FuturesQuote"),
+                "losers": array_of("FuturesQuote"),
+            },
+        },
+        "OverviewBasis": {
+            "type": "object",
+            "required": ["date", "max_basis", "min_basis"],
+            "properties": {
+                "date": { "type": "string", "description": "现货数据日期 YYYYMMDD" },
+                "max_basis": schema_ref("FuturesSpotPrice"),
+                "min_basis": schema_ref("FuturesSpotPrice"),
+            },
+        },
+        "FuturesOverview": {
+            "type": "object",
+            "required": ["updated_at"],
+            "description": "获取失败的部分不出现在响应中",
+            "properties": {
+                "updated_at": { "type": "string", "description": "概览生成时间（北京时间）" },
+                "main_quotes": array_of("FuturesQuote"),
+                "top_movers": schema_ref("OverviewMovers"),
+                "basis": schema_ref("OverviewBasis"),
+            },
+        },
+        "RealtimeRequestDebug": object_schema(&[
+            ("symbol", "string", "输入的合约代码"),
+            ("formatted_symbol", "string", "新浪实时行情格式代码（nf_/CFF_ 前缀）"),
+            ("is_cffex", "boolean", "是否判定为中金所合约"),
+            ("url", "string", "将要请求的上游 URL"),
+        ]),
+        "ForeignFuturesHistData": object_schema(&[
+            ("date", "string", "日期"),
+            ("open", "number", "开盘价"),
+            ("high", "number", "最高价"),
+            ("low", "number", "最低价"),
+            ("close", "number", "收盘价"),
+            ("volume", "integer", "成交量"),
+            ("settlement", "number?", "结算价（数据源提供时）"),
+            ("open_interest", "integer?", "持仓量（数据源提供时）"),
+        ]),
+        "FuturesMainContract": object_schema(&[
+            ("symbol", "string", "合约代码（如 V0, RB0）"),
+            ("name", "string", "合约名称（如 PVC连续）"),
+            ("exchange", "string", "交易所代码"),
+            ("last_price", "number?", "最新价（行情获取失败时为 null）"),
+            ("open_interest", "integer?", "持仓量（行情获取失败时为 null）"),
+        ]),
+        "FuturesMainDailyData": object_schema(&[
+            ("date", "string", "日期"),
+            ("open", "number", "开盘价"),
+            ("high", "number", "最高价"),
+            ("low", "number", "最低价"),
+            ("close", "number", "收盘价"),
+            ("volume", "integer", "成交量（手）"),
+            ("hold", "integer", "持仓量（手）"),
+            ("settle", "number?", "动态结算价"),
+        ]),
+        "FuturesRule": with_raw_cells(object_schema(&[
+            ("exchange", "string", "交易所"),
+            ("product", "string", "品种"),
+            ("code", "string", "代码"),
+            ("margin_rate", "number?", "交易保证金比例(%)"),
+            ("price_limit", "number?", "涨跌停板幅度(%)"),
+            ("contract_size", "number?", "合约乘数"),
+            ("price_tick", "number?", "最小变动价位"),
+            ("max_order_size", "integer?", "限价单每笔最大下单手数"),
+            ("special_note", "string?", "特殊合约参数调整"),
+            ("remark", "string?", "调整备注"),
+        ])),
+        "PositionNetMember": object_schema(&[
+            ("party_name", "string", "会员简称"),
+            ("long_open_interest", "integer", "持多单"),
+            ("long_open_interest_chg", "integer", "持多单变化量"),
+            ("short_open_interest", "integer", "持空单"),
+            ("short_open_interest_chg", "integer", "持空单变化量"),
+            ("net", "integer", "净持仓（多 - 空）"),
+            ("net_chg", "integer", "净持仓变化量"),
+        ]),
+        "PositionNetResponse": {
+            "type": "object",
+            "required": [
+                "exchange", "symbol", "date", "long_top20", "long_chg_top20", "short_top20",
+                "short_chg_top20", "net_top20", "net_chg_top20", "members",
+            ],
+            "properties": {
+                "exchange": { "type": "string", "description": "交易所代码" },
+                "symbol": { "type": "string", "description": "合约代码" },
+                "date": { "type": "string", "description": "交易日期" },
+                "long_top20": { "type": "integer", "description": "前20会员持多单总和" },
+                "long_chg_top20": { "type": "integer", "description": "前20会员持多单变化总和" },
+                "short_top20": { "type": "integer", "description": "前20会员持空单总和" },
+                "short_chg_top20": { "type": "integer", "description": "前20会员持空单变化总和" },
+                "net_top20": { "type": "integer", "description": "前20会员净持仓" },
+                "net_chg_top20": { "type": "integer", "description": "前20会员净持仓变化" },
+                "members": array_of("PositionNetMember"),
+            },
+        },
+        "PositionConcentration": object_schema(&[
+            ("exchange", "string", "交易所代码"),
+            ("symbol", "string", "合约代码"),
+            ("date", "string", "交易日期"),
+            ("long_cr5", "number?", "多单前5名持仓 / 前20名持仓（无多单数据时为 null）"),
+            ("short_cr5", "number?", "空单前5名持仓 / 前20名持仓（无空单数据时为 null）"),
+            ("long_hhi", "number?", "多单前20名持仓占比平方和，0-1（无多单数据时为 null）"),
+            ("short_hhi", "number?", "空单前20名持仓占比平方和，0-1（无空单数据时为 null）"),
+        ]),
+        "PositionDiffMember": object_schema(&[
+            ("party_name", "string", "会员简称"),
+            ("long_d1", "integer?", "第一日持多单（未上榜为 null）"),
+            ("long_d2", "integer?", "第二日持多单（未上榜为 null）"),
+            ("long_delta", "integer", "持多单变化（未上榜按 0 计）"),
+            ("short_d1", "integer?", "第一日持空单（未上榜为 null）"),
+            ("short_d2", "integer?", "第二日持空单（未上榜为 null）"),
+            ("short_delta", "integer", "持空单变化（未上榜按 0 计）"),
+        ]),
+        "PositionDiffResponse": {
+            "type": "object",
+            "required": [
+                "exchange", "symbol", "date1", "date2", "members",
+                "long_entered", "long_left", "short_entered", "short_left",
+            ],
+            "properties": {
+                "exchange": { "type": "string", "description": "交易所代码" },
+                "symbol": { "type": "string", "description": "合约代码" },
+                "date1": { "type": "string", "description": "第一个交易日" },
+                "date2": { "type": "string", "description": "第二个交易日" },
+                "members": array_of("PositionDiffMember"),
+                "long_entered": { "type": "array", "items": { "type": "string" }, "description": "新进入持多单前20的会员" },
+                "long_left": { "type": "array", "items": { "type": "string" }, "description": "退出持多单前20的会员" },
+                "short_entered": { "type": "array", "items": { "type": "string" }, "description": "新进入持空单前20的会员" },
+                "short_left": { "type": "array", "items": { "type": "string" }, "description": "退出持空单前20的会员" },
+            },
+        },
+    })
+}
+
+/// 交易费用数据模型
+fn fees_schemas() -> Value {
+    json!({
+        "FuturesFeesInfo": with_raw_cells(object_schema(&[
+            ("exchange", "string", "交易所"),
+            ("contract_code", "string", "合约代码"),
+            ("contract_name", "string", "合约名称"),
+            ("product_code", "string", "品种代码"),
+            ("product_name", "string", "品种名称"),
+            ("contract_size", "string", "合约乘数"),
+            ("price_tick", "string", "最小跳动"),
+            ("open_fee_rate", "string", "开仓费率"),
+            ("open_fee", "string", "开仓费用/手"),
+            ("close_fee_rate", "string", "平仓费率"),
+            ("close_fee", "string", "平仓费用/手"),
+            ("close_today_fee_rate", "string", "平今费率"),
+            ("close_today_fee", "string", "平今费用/手"),
+            ("long_margin_rate", "string", "做多保证金率"),
+            ("short_margin_rate", "string", "做空保证金率"),
+            ("updated_at", "string", "更新时间"),
+            ("contract_size_num", "number?", "合约乘数（数值）"),
+            ("price_tick_num", "number?", "最小跳动（数值）"),
+            ("open_fee_rate_num", "number?", "开仓费率（小数，如 0.0001）"),
+            ("open_fee_num", "number?", "开仓费用/手（数值）"),
+            ("close_fee_rate_num", "number?", "平仓费率（小数）"),
+            ("close_fee_num", "number?", "平仓费用/手（数值）"),
+            ("close_today_fee_rate_num", "number?", "平今费率（小数）"),
+            ("close_today_fee_num", "number?", "平今费用/手（数值）"),
+            ("long_margin_rate_num", "number?", "做多保证金率（小数，如 0.1）"),
+            ("short_margin_rate_num", "number?", "做空保证金率（小数）"),
+        ])),
+        "FuturesPerLotCost": object_schema(&[
+            ("symbol", "string", "请求的合约代码"),
+            ("exchange", "string", "交易所"),
+            ("contract_code", "string", "交易费用表中匹配的合约代码"),
+            ("product_code", "string", "品种代码"),
+            ("price", "number", "计算使用的价格（最新价，缺失时为昨结算价）"),
+            ("contract_size", "number", "合约乘数"),
+            ("open_cost", "number?", "开仓手续费/手（元）"),
+            ("close_cost", "number?", "平仓手续费/手（元）"),
+            ("close_today_cost", "number?", "平今手续费/手（元）"),
+            ("long_margin", "number?", "做多保证金/手（元）"),
+            ("short_margin", "number?", "做空保证金/手（元）"),
+            ("fees_updated_at", "string", "交易费用表更新时间"),
+        ]),
+    })
+}
+
+/// 股票数据模型
+fn stock_schemas() -> Value {
+    json!({
+        "StockInfo": object_schema(&[
+            ("symbol", "string", "股票代码"),
+            ("name", "string", "股票名称"),
+            ("current_price", "number", "当前价格"),
+            ("change", "number", "涨跌额"),
+            ("change_percent", "number", "涨跌幅（百分比）"),
+            ("volume", "integer", "成交量"),
+            ("amount", "number", "成交额"),
+            ("open", "number", "今开盘"),
+            ("high", "number", "最高价"),
+            ("low", "number", "最低价"),
+            ("prev_close", "number", "昨收盘"),
+            ("market_cap", "number?", "市值"),
+            ("updated_at", "string", "更新时间"),
+        ]),
+        "StockHistoryData": object_schema(&[
+            ("symbol", "string", "股票代码"),
+            ("date", "string", "日期"),
+            ("open", "number", "开盘价"),
+            ("high", "number", "最高价"),
+            ("low", "number", "最低价"),
+            ("close", "number", "收盘价"),
+            ("volume", "integer", "成交量"),
+        ]),
+    })
+}
+
+// ==================== 辅助函数 ====================
+
+/// 构建对象模型，类型以 `?` 结尾表示可为 null 的可选字段
+/// 持仓排名汇总：前 5/10/15/20 名会员的成交量、持多单、持空单及变化量合计
+fn rank_sum_schema() -> Value {
+    const MEASURES: [(&str, &str); 6] = [
+        ("vol", "成交量"),
+        ("vol_chg", "成交量变化"),
+        ("long_open_interest", "持多单"),
+        ("long_open_interest_chg", "持多单变化"),
+        ("short_open_interest", "持空单"),
+        ("short_open_interest_chg", "持空单变化"),
+    ];
+    let mut fields: Vec<(String, String)> = vec![
+        ("symbol".to_string(), "合约代码，品种汇总行为品种代码".to_string()),
+        ("variety".to_string(), "品种".to_string()),
+    ];
+    for top in [5, 10, 15, 20] {
+        for (name, label) in MEASURES {
+            fields.push((format!("{}_top{}", name, top), format!("前{}名{}合计", top, label)));
+        }
+    }
+    fields.push(("date".to_string(), "交易日期（YYYYMMDD）".to_string()));
+
+    let fields: Vec<(&str, &str, &str)> = fields
+        .iter()
+        .map(|(name, description)| {
+            let ty = if matches!(name.as_str(), "symbol" | "variety" | "date") {
+                "string"
+            } else {
+                "integer"
+            };
+            (name.as_str(), ty, description.as_str())
+        })
+        .collect();
+    object_schema(&fields)
+}
+
+fn object_schema(fields: &[(&str, &str, &str)]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+
+    for (name, ty, description) in fields {
+        let (ty, nullable) = match ty.strip_suffix('?') {
+            Some(t) => (t, true),
+            None => (*ty, false),
+        };
+        let mut prop = json!({ "type": ty, "description": description });
+        if nullable {
+            prop["nullable"] = json!(true);
+        } else {
+            required.push(json!(name));
+        }
+        properties.insert(name.to_string(), prop);
+    }
+
+    json!({ "type": "object", "required": required, "properties": properties })
+}
+
+/// 为表格行模型添加可选的 _raw 字段（?debug_raw=1 时返回的上游原始单元格）
+fn with_raw_cells(mut schema: Value) -> Value {
+    schema["properties"]["_raw"] = json!({
+        "type": "array",
+        "items": { "type": "string" },
+        "nullable": true,
+        "description": "上游原始单元格（仅 debug_raw=1 时返回，单元格超过 200 字符或整行超过 2 KiB 时截断）",
+    });
+    schema
+}
+
+/// 引用 components 中的模型
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+/// 模型数组
+fn array_of(name: &str) -> Value {
+    json!({ "type": "array", "items": schema_ref(name) })
+}
+
+/// 统一响应包装 ApiResponse<T>
+fn envelope(data: Value) -> Value {
+    json!({
+        "type": "object",
+        "required": ["success", "message", "timestamp"],
+        "properties": {
+            "success": { "type": "boolean" },
+            "data": data,
+            "message": { "type": "string" },
+            "timestamp": { "type": "string", "description": "北京时间（RFC 3339）" },
+            "count": { "type": "integer", "description": "列表接口返回的记录数" },
+            "total": { "type": "integer", "description": "列表被分页时分页前的总记录数" },
+            "truncated": { "type": "boolean", "description": "列表超过 api.max_response_rows 被截断时为 true" },
+            "next_page": { "type": "string", "description": "下一页令牌，作为 page_token 查询参数传回" },
+            "meta": schema_ref("KlineMeta"),
+            "effective_date": { "type": "string", "description": "按日查询接口实际使用的日期（YYYYMMDD），date 缺省时为最近交易日" },
+            "warnings": {
+                "type": "array", "items": { "type": "string" },
+                "description": "请求部分被忽略时的提示，如未知的外盘品种代码",
+            },
+            "provenance": schema_ref("DataProvenance"),
+        },
+    })
+}
+
+/// 单个响应定义
+fn response_of(data: Value) -> Value {
+    json!({
+        "description": "Success",
+        "content": { "application/json": { "schema": envelope(data) } },
+    })
+}
+
+/// 标准响应集合（成功 / 认证失败 / 新浪封禁冷却 / 服务端错误 / 上游熔断）
+fn standard_responses(data: Value) -> Value {
+    let error = json!({
+        "description": "Error",
+        "content": { "application/json": { "schema": envelope(json!({ "nullable": true })) } },
+    });
+    json!({
+        "200": response_of(data),
+        "401": { "description": "无效的 API Key" },
+        "429": { "description": "新浪 IP 封禁冷却中", "content": error["content"].clone() },
+        "500": error.clone(),
+        "503": { "description": "上游数据源熔断中", "content": error["content"].clone() },
+    })
+}
+
+/// 在标准响应上追加一个错误状态码
+fn with_response(mut responses: Value, status: &str, description: &str) -> Value {
+    let content = responses["500"]["content"].clone();
+    responses[status] = json!({ "description": description, "content": content });
+    responses
+}
+
+/// GET 操作定义
+fn operation(summary: &str, parameters: Vec<Value>, data: Value) -> Value {
+    json!({
+        "summary": summary,
+        "parameters": parameters,
+        "responses": standard_responses(data),
+    })
+}
+
+/// 查询参数
+fn query_param(name: &str, description: &str, required: bool, ty: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "required": required,
+        "description": description,
+        "schema": { "type": ty },
+    })
+}
+
+/// 路径参数
+fn path_param(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "path",
+        "required": true,
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::routes::all_routes;
+    use actix_web::{test as actix_test, App};
+
+    /// 收集文档中所有 $ref 引用
+    fn collect_refs(value: &Value, refs: &mut Vec<String>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(r)) = map.get("$ref") {
+                    refs.push(r.clone());
+                }
+                map.values().for_each(|v| collect_refs(v, refs));
+            }
+            Value::Array(items) => items.iter().for_each(|v| collect_refs(v, refs)),
+            _ => {}
+        }
+    }
+
+    #[actix_web::test]
+    async fn spec_is_openapi3_and_lists_registered_routes() {
+        let app = actix_test::init_service(App::new().configure(crate::handlers::config)).await;
+        let req = actix_test::TestRequest::get().uri("/api/v1/openapi.json").to_request();
+        let spec: Value = actix_test::call_and_read_body_json(&app, req).await;
+
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(spec["info"]["title"].is_string());
+        assert!(spec["info"]["version"].is_string());
+        assert_eq!(spec["servers"][0]["url"], "/api/v1");
+        let paths = spec["paths"].as_object().unwrap();
+
+        let routes = all_routes();
+        for route in &routes {
+            let path = route.path.strip_prefix("/api/v1").unwrap_or(route.path);
+            let operation = paths.get(path).and_then(|p| p.get(route.method.to_lowercase()));
+            assert!(operation.is_some(), "文档缺少 {} {}", route.method, route.path);
+            assert!(operation.unwrap()["summary"].is_string(), "{} {}", route.method, route.path);
+        }
+        for (path, operations) in paths {
+            for method in operations.as_object().unwrap().keys() {
+                let registered = routes.iter().any(|r| {
+                    r.path.strip_prefix("/api/v1") == Some(path.as_str()) && r.method.eq_ignore_ascii_case(method)
+                });
+                assert!(registered, "文档中的 {} {} 没有注册", method, path);
+            }
+        }
+
+        let schemas = spec["components"]["schemas"].as_object().unwrap();
+        let mut refs = Vec::new();
+        collect_refs(&spec, &mut refs);
+        for r in refs {
+            let name = r.strip_prefix("#/components/schemas/").unwrap_or_else(|| panic!("未知引用 {}", r));
+            assert!(schemas.contains_key(name), "引用的模型 {} 未定义", name);
+        }
+    }
+}
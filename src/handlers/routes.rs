@@ -0,0 +1,141 @@
+//! 路由清单接口
+//!
+//! Actix 不便枚举已注册的路由，各处理器模块改为维护一张 ROUTES 路由表：
+//! 每个条目同时给出清单信息和处理器，模块的 config 通过 register 按表注册，
+//! 清单与实际注册的路由始终一致。清单比 OpenAPI 文档轻量，便于快速查看可用接口
+
+use actix_web::{http::Method, web, HttpResponse, Result, Route};
+use serde::Serialize;
+
+use super::{admin, futures, health, metrics, openapi, stock};
+use crate::models::ApiResponse;
+
+/// 路由清单条目
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RouteInfo {
+    /// HTTP 方法
+    pub method: &'static str,
+    /// 完整路径（含 /api/v1 前缀）
+    pub path: &'static str,
+    /// 一句话说明
+    pub description: &'static str,
+}
+
+impl RouteInfo {
+    pub const fn get(path: &'static str, description: &'static str) -> Self {
+        Self { method: "GET", path, description }
+    }
+
+    pub const fn post(path: &'static str, description: &'static str) -> Self {
+        Self { method: "POST", path, description }
+    }
+
+    /// 绑定处理器，bind 接收已设置好 HTTP 方法的 Route
+    pub const fn bind(self, bind: fn(Route) -> Route) -> RouteEntry {
+        RouteEntry { info: self, bind }
+    }
+}
+
+/// 路由表条目：清单信息和处理器
+#[derive(Clone, Copy)]
+pub struct RouteEntry {
+    pub info: RouteInfo,
+    bind: fn(Route) -> Route,
+}
+
+/// 按路由表注册路由，prefix 为所在 scope 的完整路径前缀（如 /api/v1/futures），
+/// 表中路径去掉该前缀后注册；同一路径的不同方法按表中顺序注册为多个资源
+pub fn register(cfg: &mut web::ServiceConfig, prefix: &str, table: &[RouteEntry]) {
+    for entry in table {
+        let path = entry
+            .info
+            .path
+            .strip_prefix(prefix)
+            .unwrap_or_else(|| panic!("路由 {} 不在前缀 {} 下", entry.info.path, prefix));
+        let method = Method::from_bytes(entry.info.method.as_bytes()).expect("有效的 HTTP 方法");
+        cfg.route(path, (entry.bind)(web::route().method(method)));
+    }
+}
+
+/// 本模块的路由
+pub const ROUTES: &[RouteEntry] =
+    &[RouteInfo::get("/api/v1/routes", "列出服务提供的所有接口").bind(|r| r.to(list_routes))];
+
+/// 所有已注册的路由，按 handlers::config 中的注册顺序
+pub fn all_routes() -> Vec<RouteInfo> {
+    [
+        health::ROUTES,
+        openapi::ROUTES,
+        ROUTES,
+        metrics::ROUTES,
+        admin::ROUTES,
+        stock::ROUTES,
+        futures::ROUTES,
+    ]
+    .concat()
+    .into_iter()
+    .map(|entry| entry.info)
+    .collect()
+}
+
+/// 获取路由清单
+///
+/// GET /api/v1/routes
+pub async fn list_routes() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(ApiResponse::success_list(all_routes())))
+}
+
+/// 配置路由清单接口
+pub fn config(cfg: &mut web::ServiceConfig) {
+    register(cfg, "/api/v1", ROUTES);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, App, HttpRequest};
+    use std::collections::HashSet;
+
+    /// 将路径参数替换为示例值
+    fn concrete(pattern: &str) -> String {
+        pattern
+            .split('/')
+            .map(|segment| if segment.starts_with('{') { "x1" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    #[actix_web::test]
+    async fn every_route_resolves_to_its_own_pattern() {
+        // 在注册了全部路由的应用中查询资源表，不调用处理器
+        async fn patterns(req: HttpRequest) -> HttpResponse {
+            let resolved: Vec<(String, Option<String>)> = all_routes()
+                .iter()
+                .map(|route| (route.path.to_string(), req.resource_map().match_pattern(&concrete(route.path))))
+                .collect();
+            HttpResponse::Ok().json(resolved)
+        }
+        let app = actix_test::init_service(
+            App::new()
+                .configure(crate::handlers::config)
+                .route("/__patterns", web::get().to(patterns)),
+        )
+        .await;
+        let resolved: Vec<(String, Option<String>)> =
+            actix_test::call_and_read_body_json(&app, actix_test::TestRequest::get().uri("/__patterns").to_request()).await;
+
+        for (path, pattern) in &resolved {
+            assert_eq!(pattern.as_deref(), Some(path.as_str()), "{} 被其他路由遮蔽或未注册", path);
+        }
+        assert_eq!(resolved.len(), all_routes().len());
+    }
+
+    #[test]
+    fn route_table_has_no_duplicates() {
+        let mut seen = HashSet::new();
+        for route in all_routes() {
+            assert!(route.path.starts_with("/api/v1/"), "{}", route.path);
+            assert!(seen.insert((route.method, route.path)), "重复的路由 {} {}", route.method, route.path);
+        }
+    }
+}
@@ -3,9 +3,19 @@
 //! 提供股票数据的 HTTP API 端点
 
 use actix_web::{web, HttpResponse, Result};
-use crate::models::{ApiResponse, StockInfo, StockHistoryData, StockQuery};
+use chrono::NaiveDate;
+use futures::StreamExt;
+use super::routes::{register, RouteEntry, RouteInfo};
+use super::{error_status, ok_or_empty};
+use crate::config;
+use crate::models::{
+    ApiResponse, BulkFormat, StockBulkHistoryQuery, StockBulkLine, StockHistoryData, StockInfo, StockQuery,
+};
 use crate::services::stock;
 
+/// 批量历史K线同时请求的股票数
+const BULK_CONCURRENCY: usize = 4;
+
 /// 获取单只股票信息
 /// 
 /// GET /api/v1/stocks/{symbol}
@@ -22,33 +32,133 @@ pub async fn get_stock_info(path: web::Path<String>) -> Result<HttpResponse> {
         }
         Err(e) => {
             let response = ApiResponse::<StockInfo>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            Ok(HttpResponse::build(error_status(&e)).json(response))
         }
     }
 }
 
 /// 获取股票历史K线数据
 /// 
-/// GET /api/v1/stocks/{symbol}/history?limit=30
+/// GET /api/v1/stocks/{symbol}/history?start_date=20240101&end_date=20240301&adjust=qfq
 /// 
 /// # 参数
 /// - symbol: 股票代码
-/// - limit: 返回数量限制（可选，默认30）
+/// - start_date / end_date: 日期范围 YYYYMMDD（可选）
+/// - limit: 返回数量限制（可选，未指定日期范围时默认30）
+/// - adjust: 复权方式 none / qfq / hfq（可选，默认不复权）
 pub async fn get_stock_history(
     path: web::Path<String>,
     query: web::Query<StockQuery>,
 ) -> Result<HttpResponse> {
     let symbol = path.into_inner();
+    if let Err(e) = query.date_range().and_then(|_| query.adjust_mode()) {
+        let response = ApiResponse::<Vec<StockHistoryData>>::error(e.to_string());
+        return Ok(HttpResponse::BadRequest().json(response));
+    }
     
-    match stock::get_stock_history(&symbol, &query).await {
-        Ok(history_data) => {
-            let response = ApiResponse::success(history_data);
-            Ok(HttpResponse::Ok().json(response))
+    Ok(ok_or_empty(stock::get_stock_history(&symbol, &query).await))
+}
+
+/// 批量获取股票日K线（NDJSON 流式输出，或 format=parquet 输出 Parquet 文件）
+/// 
+/// GET /api/v1/stocks/history/bulk?codes=sh600000,sz000001&start_date=20240101&end_date=20240301
+/// 
+/// 代码先统一校验，通过后以有限并发逐只获取，按请求顺序每行输出一条带 code 的记录；
+/// 单只股票失败时输出一行 error，不影响其他股票
+pub async fn get_stock_history_bulk(query: web::Query<StockBulkHistoryQuery>) -> Result<HttpResponse> {
+    let format = match query.output_format() {
+        Ok(format) => format,
+        Err(e) => {
+            let response = ApiResponse::<Vec<StockHistoryData>>::error(e.to_string());
+            return Ok(HttpResponse::BadRequest().json(response));
+        }
+    };
+    let codes = match query.code_list() {
+        Ok(codes) => codes,
+        Err(e) => {
+            let response = ApiResponse::<Vec<StockHistoryData>>::error(e.to_string());
+            return Ok(HttpResponse::BadRequest().json(response));
         }
+    };
+    let (start, end) = match query.date_range() {
+        Ok(range) => range,
         Err(e) => {
             let response = ApiResponse::<Vec<StockHistoryData>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
+            return Ok(HttpResponse::BadRequest().json(response));
+        }
+    };
+
+    if format == BulkFormat::Parquet {
+        return Ok(bulk_history_parquet(codes, start, end).await);
+    }
+
+    let lines = futures::stream::iter(codes)
+        .map(move |code| async move {
+            let lines = match stock::get_stock_history_range(&code, start, end).await {
+                Ok(history) => history
+                    .into_iter()
+                    .map(|data| StockBulkLine::Record { code: code.clone(), data })
+                    .collect(),
+                Err(e) => {
+                    log::warn!("批量获取 {} 历史K线失败: {}", code, e);
+                    vec![StockBulkLine::Error { code, error: e.to_string() }]
+                }
+            };
+            futures::stream::iter(lines)
+        })
+        .buffered(BULK_CONCURRENCY)
+        .flatten()
+        .map(|line| {
+            let mut bytes = serde_json::to_vec(&line).unwrap_or_default();
+            bytes.push(b'\n');
+            Ok::<_, actix_web::Error>(web::Bytes::from(bytes))
+        });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(lines))
+}
+
+/// 批量历史K线的 Parquet 输出
+///
+/// 全部股票获取完成后一次性编码；获取失败的股票不写入文件，代码列在 X-Failed-Codes 响应头中
+async fn bulk_history_parquet(
+    codes: Vec<String>,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> HttpResponse {
+    let results: Vec<(String, anyhow::Result<Vec<StockHistoryData>>)> = futures::stream::iter(codes)
+        .map(|code| async move {
+            let result = stock::get_stock_history_range(&code, start, end).await;
+            (code, result)
+        })
+        .buffered(BULK_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut rows = Vec::new();
+    let mut failed = Vec::new();
+    for (code, result) in results {
+        match result {
+            Ok(history) => rows.extend(history.into_iter().map(|data| (code.clone(), data))),
+            Err(e) => {
+                log::warn!("批量获取 {} 历史K线失败: {}", code, e);
+                failed.push(code);
+            }
+        }
+    }
+
+    match stock::encode_stock_history_parquet(&rows) {
+        Ok(bytes) => {
+            let mut response = HttpResponse::Ok();
+            response.content_type(stock::PARQUET_CONTENT_TYPE);
+            if !failed.is_empty() {
+                response.insert_header(("X-Failed-Codes", failed.join(",")));
+            }
+            response.body(bytes)
         }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(ApiResponse::<Vec<StockHistoryData>>::error(format!("Parquet 编码失败: {}", e))),
     }
 }
 
@@ -59,24 +169,110 @@ pub async fn get_stock_history(
 /// # 参数
 /// - limit: 返回数量限制（可选）
 pub async fn list_stocks(query: web::Query<StockQuery>) -> Result<HttpResponse> {
-    match stock::list_stocks(&query).await {
-        Ok(stocks) => {
-            let response = ApiResponse::success(stocks);
-            Ok(HttpResponse::Ok().json(response))
-        }
-        Err(e) => {
-            let response = ApiResponse::<Vec<StockInfo>>::error(e.to_string());
-            Ok(HttpResponse::InternalServerError().json(response))
-        }
-    }
+    let defaults = config::read(|c| c.defaults.clone());
+    Ok(ok_or_empty(stock::list_stocks(&query, &defaults).await))
 }
 
+/// 本模块的路由表，config 按表注册
+pub const ROUTES: &[RouteEntry] = &[
+    RouteInfo::get("/api/v1/stocks", "股票列表").bind(|r| r.to(list_stocks)),
+    RouteInfo::get("/api/v1/stocks/history/bulk", "批量导出股票历史K线（NDJSON / Parquet）")
+        .bind(|r| r.to(get_stock_history_bulk)),
+    RouteInfo::get("/api/v1/stocks/{symbol}", "单只股票实时行情").bind(|r| r.to(get_stock_info)),
+    RouteInfo::get("/api/v1/stocks/{symbol}/history", "股票历史K线（支持复权）").bind(|r| r.to(get_stock_history)),
+];
+
 /// 配置股票相关路由
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::scope("/stocks")
-            .route("", web::get().to(list_stocks))                    // 股票列表
-            .route("/{symbol}", web::get().to(get_stock_info))        // 单只股票信息
-            .route("/{symbol}/history", web::get().to(get_stock_history))  // 历史K线
-    );
-}
\ No newline at end of file
+    cfg.service(web::scope("/stocks").configure(|cfg| register(cfg, "/api/v1/stocks", ROUTES)));
+}
+#[cfg(test)]
+mod tests {
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    use crate::config;
+    use crate::middleware::ProvenanceMiddleware;
+    use crate::services::futures::sina_limiter_stats;
+
+    /// 模拟模式下接口直接返回 fixture 内容，不发出任何上游请求
+    ///
+    /// 全局配置只能初始化一次，本测试之后同一进程内的其他测试也处于模拟模式
+    #[actix_web::test]
+    async fn mock_mode_serves_fixture_without_network() {
+        let fixtures_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+        config::init_mock_for_tests();
+
+        let fixture: Vec<Value> =
+            serde_json::from_str(&std::fs::read_to_string(format!("{}/stocks.json", fixtures_dir)).unwrap())
+                .unwrap();
+        let expected = fixture
+            .into_iter()
+            .find(|stock| stock["symbol"] == "sh600519")
+            .unwrap();
+
+        let requests_before = sina_limiter_stats().total_requests;
+        let app = test::init_service(
+            App::new()
+                .wrap(ProvenanceMiddleware::new())
+                .configure(crate::handlers::config),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/api/v1/stocks/sh600519").to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["success"], true);
+        assert_eq!(body["data"], expected);
+        assert_eq!(body["provenance"]["source"], "mock");
+        assert_eq!(sina_limiter_stats().total_requests, requests_before);
+    }
+
+    /// NDJSON 批量输出：每行带请求的股票代码，按请求顺序分块输出
+    #[actix_web::test]
+    async fn bulk_history_streams_ndjson_labeled_by_code() {
+        config::init_mock_for_tests();
+        let app = test::init_service(App::new().configure(crate::handlers::config)).await;
+        let req = test::TestRequest::get()
+            .uri("/api/v1/stocks/history/bulk?codes=sh600000,SZ000001,sh600000")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap().to_str().unwrap(),
+            "application/x-ndjson"
+        );
+
+        let body = test::read_body(resp).await;
+        let lines: Vec<Value> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(!lines.is_empty());
+        assert!(lines.iter().all(|line| line.get("error").is_none() && line["date"].is_string()));
+
+        let codes: Vec<&str> = lines.iter().map(|line| line["code"].as_str().unwrap()).collect();
+        let split = codes.iter().position(|code| *code == "sz000001").unwrap();
+        assert!(split > 0);
+        assert!(codes[..split].iter().all(|code| *code == "sh600000"));
+        assert!(codes[split..].iter().all(|code| *code == "sz000001"));
+        assert_eq!(codes.len(), split * 2);
+    }
+
+    /// 代码在开始输出前统一校验，任一非法即返回 400 JSON
+    #[actix_web::test]
+    async fn bulk_history_rejects_invalid_code_before_streaming() {
+        config::init_mock_for_tests();
+        let app = test::init_service(App::new().configure(crate::handlers::config)).await;
+        let req = test::TestRequest::get()
+            .uri("/api/v1/stocks/history/bulk?codes=sh600000,600519")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 400);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["success"], false);
+        assert!(body["message"].as_str().unwrap().contains("600519"));
+    }
+}
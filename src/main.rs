@@ -13,7 +13,10 @@ use actix_web::{App, HttpServer, middleware::Logger};
 use env_logger::Env;
 
 use crate::config::AppConfig;
-use crate::middleware::ApiKeyMiddleware;
+use crate::middleware::{
+    build_cors, redacted_request_line, ApiKeyMiddleware, MessagePackMiddleware, PaginationMiddleware,
+    ProvenanceMiddleware, QueryLengthLimit,
+};
 
 /// 应用程序入口
 /// 
@@ -25,19 +28,72 @@ async fn main() -> std::io::Result<()> {
     
     // 初始化日志系统
     env_logger::init_from_env(Env::default().default_filter_or(&config.log.level));
+    handlers::admin::record_startup_log_level(&config.log);
+    if let Err(e) = config.log.max_level() {
+        log::warn!("日志过滤规则 {} 无效，无效部分已被忽略: {}", config.log.level, e);
+    }
+    config.log_sources();
 
     log::info!("启动 AkShare 后端服务");
     log::info!("监听地址: {}", config.bind_addr());
+    log::info!(
+        "上游连接池: 每域名最多 {} 个空闲连接，空闲 {} 秒后关闭",
+        config.http.pool_max_idle_per_host,
+        config.http.pool_idle_timeout_secs
+    );
+    if !config.http.allow_insecure_hosts.is_empty() {
+        log::warn!(
+            "以下上游跳过 TLS 证书校验: {}",
+            config.http.allow_insecure_hosts.join(", ")
+        );
+    }
+
+    if config.mock.enabled {
+        log::warn!(
+            "模拟模式已启用: 接口返回 {} 目录中的 fixture 数据，不请求上游",
+            config.mock.fixtures_dir
+        );
+    }
+
+    config::init_global(config.clone());
+
+    // 后台预热共享缓存，失败只记录警告，不阻塞启动
+    if config.startup.warm_cache {
+        services::futures::spawn_cache_warmup();
+    }
+    if config.startup.symbol_mark_refresh_secs > 0 {
+        services::futures::spawn_symbol_mark_refresher(config.startup.symbol_mark_refresh_secs);
+    }
 
-    let api_key = config.api.api_key.clone();
     let bind_addr = config.bind_addr();
     let workers = config.server.workers;
+    let cors_config = config.cors.clone();
+    let key_header = config.api.key_header.clone();
+
+    if cors_config.allowed_origins.is_empty() {
+        log::info!("CORS 未配置允许来源，仅允许同源访问");
+    } else {
+        log::info!("CORS 允许来源: {:?}", cors_config.allowed_origins);
+    }
 
     // 创建并启动 HTTP 服务器
     let mut server = HttpServer::new(move || {
         App::new()
-            .wrap(Logger::default())
-            .wrap(ApiKeyMiddleware::new(api_key.clone()))
+            .wrap(ProvenanceMiddleware::new())
+            // 列表响应超过 api.max_response_rows 时分页，后续页按 page_token 从快照截取
+            .wrap(PaginationMiddleware::new())
+            // 与 Logger::default() 格式一致，请求行中的 api_key 查询参数脱敏
+            .wrap(
+                Logger::new(r#"%a "%{request_line}xi" %s %b "%{Referer}i" "%{User-Agent}i" %T"#)
+                    .custom_request_replace("request_line", redacted_request_line),
+            )
+            .wrap(ApiKeyMiddleware::new())
+            // 过长的查询字符串在认证和参数解析之前拒绝
+            .wrap(QueryLengthLimit::new())
+            // 认证失败、414 等错误响应同样按 Accept 转为 MessagePack
+            .wrap(MessagePackMiddleware::new())
+            // CORS 放在最外层，预检请求无需携带 API Key
+            .wrap(build_cors(&cors_config, &key_header))
             .configure(handlers::config)
     });
 
@@ -0,0 +1,141 @@
+//! 管理接口访问控制中间件
+//!
+//! 管理接口（/admin/*）在 API Key 认证之外再做一层限制：
+//! - 配置了 api.admin_key 时，请求头 X-Admin-Key 须与之相同
+//! - 未配置时只接受本机（回环地址）发起的请求
+//!
+//! 反向代理部署时对端地址为代理本身，应配置 api.admin_key。
+//! 设置每次请求从全局配置读取，配置热更新后立即生效
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error, HttpResponse,
+};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+use crate::config;
+
+/// 传递管理密钥的请求头
+pub const ADMIN_KEY_HEADER: &str = "X-Admin-Key";
+
+/// 管理接口访问控制中间件
+pub struct AdminGuard;
+
+impl AdminGuard {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AdminGuard
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = AdminGuardService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AdminGuardService {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct AdminGuardService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for AdminGuardService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let admin_key = config::admin_key();
+
+        Box::pin(async move {
+            if admin_allowed(&req, &admin_key) {
+                let res = service.call(req).await?;
+                return Ok(res.map_into_left_body());
+            }
+
+            let message = if admin_key.is_empty() {
+                "管理接口仅允许本机访问，远程访问需配置 api.admin_key".to_string()
+            } else {
+                format!("无效的管理密钥，请在 {} 请求头中提供 api.admin_key", ADMIN_KEY_HEADER)
+            };
+            let response = HttpResponse::Forbidden().json(serde_json::json!({
+                "code": 403,
+                "message": message,
+                "data": null
+            }));
+            Ok(req.into_response(response).map_into_right_body())
+        })
+    }
+}
+
+/// 是否允许访问管理接口：配置了管理密钥时按密钥校验，否则只允许回环地址
+fn admin_allowed(req: &ServiceRequest, admin_key: &str) -> bool {
+    if admin_key.is_empty() {
+        return req.peer_addr().is_some_and(|addr| addr.ip().is_loopback());
+    }
+    req.headers()
+        .get(ADMIN_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|key| key == admin_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn without_admin_key_only_loopback_is_allowed() {
+        let local = TestRequest::post()
+            .uri("/api/v1/admin/reload")
+            .peer_addr("127.0.0.1:50000".parse().unwrap())
+            .to_srv_request();
+        assert!(admin_allowed(&local, ""));
+
+        let remote = TestRequest::post()
+            .uri("/api/v1/admin/reload")
+            .peer_addr("203.0.113.5:50000".parse().unwrap())
+            .to_srv_request();
+        assert!(!admin_allowed(&remote, ""));
+
+        let unknown = TestRequest::post().uri("/api/v1/admin/reload").to_srv_request();
+        assert!(!admin_allowed(&unknown, ""));
+    }
+
+    #[test]
+    fn admin_key_is_required_when_configured() {
+        let with_key = TestRequest::post()
+            .uri("/api/v1/admin/reload")
+            .peer_addr("203.0.113.5:50000".parse().unwrap())
+            .insert_header((ADMIN_KEY_HEADER, "admin-secret"))
+            .to_srv_request();
+        assert!(admin_allowed(&with_key, "admin-secret"));
+        assert!(!admin_allowed(&with_key, "other"));
+
+        // 配置了密钥后本机请求同样需要密钥
+        let local = TestRequest::post()
+            .uri("/api/v1/admin/reload")
+            .peer_addr("127.0.0.1:50000".parse().unwrap())
+            .to_srv_request();
+        assert!(!admin_allowed(&local, "admin-secret"));
+    }
+}
@@ -1,25 +1,31 @@
 //! API Key 认证中间件
 //!
-//! 通过 Header 中的 Authorization: Bearer <token> 进行认证
+//! 依次从以下位置读取 API Key，请求头优先：
+//! - Authorization: Bearer <token>
+//! - api.key_header 配置的请求头（默认 X-API-Key）
+//! - 查询参数 ?api_key=（需开启 api.allow_query_key）
+//!
+//! 认证设置每次请求从全局配置读取，配置热更新后立即生效。
+//! 访问日志中的 api_key 查询参数由 redacted_request_line 脱敏
 
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpResponse,
+    http::header,
+    web, Error, HttpResponse,
     body::EitherBody,
 };
 use futures::future::{ok, LocalBoxFuture, Ready};
+use std::collections::HashMap;
 use std::rc::Rc;
 
+use crate::config;
+
 /// API Key 中间件
-pub struct ApiKeyMiddleware {
-    api_key: Rc<String>,
-}
+pub struct ApiKeyMiddleware;
 
 impl ApiKeyMiddleware {
-    pub fn new(api_key: String) -> Self {
-        Self {
-            api_key: Rc::new(api_key),
-        }
+    pub fn new() -> Self {
+        Self
     }
 }
 
@@ -37,14 +43,12 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ok(ApiKeyMiddlewareService {
             service: Rc::new(service),
-            api_key: self.api_key.clone(),
         })
     }
 }
 
 pub struct ApiKeyMiddlewareService<S> {
     service: Rc<S>,
-    api_key: Rc<String>,
 }
 
 impl<S, B> Service<ServiceRequest> for ApiKeyMiddlewareService<S>
@@ -60,24 +64,20 @@ where
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let service = self.service.clone();
-        let api_key = self.api_key.clone();
+        let auth = config::api_key_auth();
 
         Box::pin(async move {
-            // 跳过健康检查接口
-            if req.path().ends_with("/health") {
+            // 跳过健康检查、接口文档和路由清单
+            if req.path().ends_with("/health")
+                || req.path().ends_with("/openapi.json")
+                || req.path() == "/api/v1/routes"
+            {
                 let res = service.call(req).await?;
                 return Ok(res.map_into_left_body());
             }
 
-            // 验证 Bearer Token
-            let provided_key = req
-                .headers()
-                .get("Authorization")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.strip_prefix("Bearer "));
-
-            match provided_key {
-                Some(key) if key == api_key.as_str() => {
+            match provided_key(&req, &auth) {
+                Some(key) if key == auth.api_key => {
                     let res = service.call(req).await?;
                     Ok(res.map_into_left_body())
                 }
@@ -85,7 +85,7 @@ where
                     let response = HttpResponse::Unauthorized()
                         .json(serde_json::json!({
                             "code": 401,
-                            "message": "无效的 Bearer Token",
+                            "message": "无效的 API Key",
                             "data": null
                         }));
                     Ok(req.into_response(response).map_into_right_body())
@@ -94,3 +94,118 @@ where
         })
     }
 }
+
+/// 查询参数中的 API Key 名称
+const QUERY_KEY: &str = "api_key";
+
+/// 取出请求携带的 API Key：Bearer Token、配置的请求头、查询参数（开启时）依次查找
+fn provided_key(req: &ServiceRequest, auth: &config::ApiKeyAuth) -> Option<String> {
+    let headers = req.headers();
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let custom = headers
+        .get(auth.key_header.as_str())
+        .and_then(|v| v.to_str().ok());
+    if let Some(key) = bearer.or(custom) {
+        return Some(key.to_string());
+    }
+
+    if !auth.allow_query_key {
+        return None;
+    }
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.into_inner().remove(QUERY_KEY))
+}
+
+/// 访问日志使用的请求行（同 Logger 的 %r），查询参数中的 api_key 替换为 ***
+pub fn redacted_request_line(req: &ServiceRequest) -> String {
+    let query = req.query_string();
+    let target = if query.is_empty() {
+        req.path().to_string()
+    } else {
+        let redacted: Vec<String> = query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if name == QUERY_KEY => format!("{}=***", QUERY_KEY),
+                _ => pair.to_string(),
+            })
+            .collect();
+        format!("{}?{}", req.path(), redacted.join("&"))
+    };
+    format!("{} {} {:?}", req.method(), target, req.version())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn auth(allow_query_key: bool) -> config::ApiKeyAuth {
+        config::ApiKeyAuth {
+            api_key: "secret".to_string(),
+            key_header: "X-API-Key".to_string(),
+            allow_query_key,
+        }
+    }
+
+    #[test]
+    fn header_only_key_is_accepted() {
+        let req = TestRequest::get()
+            .uri("/api/v1/stocks")
+            .insert_header((header::AUTHORIZATION, "Bearer secret"))
+            .to_srv_request();
+        assert_eq!(provided_key(&req, &auth(false)).as_deref(), Some("secret"));
+
+        let req = TestRequest::get()
+            .uri("/api/v1/stocks")
+            .insert_header(("X-API-Key", "secret"))
+            .to_srv_request();
+        assert_eq!(provided_key(&req, &auth(false)).as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn query_only_key_requires_allow_query_key() {
+        let req = TestRequest::get()
+            .uri("/api/v1/stocks?api_key=secret")
+            .to_srv_request();
+        assert_eq!(provided_key(&req, &auth(true)).as_deref(), Some("secret"));
+        assert_eq!(provided_key(&req, &auth(false)), None);
+        assert_eq!(
+            redacted_request_line(&req),
+            "GET /api/v1/stocks?api_key=*** HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn header_wins_when_both_are_present() {
+        let req = TestRequest::get()
+            .uri("/api/v1/stocks?api_key=from-query")
+            .insert_header((header::AUTHORIZATION, "Bearer from-header"))
+            .to_srv_request();
+        assert_eq!(
+            provided_key(&req, &auth(true)).as_deref(),
+            Some("from-header")
+        );
+    }
+
+    #[actix_web::test]
+    async fn missing_key_gets_401_api_key_message() {
+        use actix_web::{test as actix_test, web, App};
+
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(ApiKeyMiddleware::new())
+                .route("/api/v1/stocks", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let req = actix_test::TestRequest::get().uri("/api/v1/stocks").to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+        let body: serde_json::Value = actix_test::read_body_json(resp).await;
+        assert_eq!(body["code"], 401);
+        assert_eq!(body["message"], "无效的 API Key");
+    }
+}
@@ -0,0 +1,127 @@
+//! 跨域（CORS）中间件
+//!
+//! 根据配置构建 actix-cors，未配置允许来源时仅允许同源访问
+
+use actix_cors::Cors;
+use actix_web::http::header;
+
+use crate::config::CorsConfig;
+
+/// 根据配置构建 CORS 中间件
+///
+/// 预检请求（OPTIONS）由 CORS 中间件直接响应，不会进入 API Key 认证；
+/// key_header 为自定义的 API Key 请求头，一并加入允许的请求头
+pub fn build_cors(config: &CorsConfig, key_header: &str) -> Cors {
+    if config.allowed_origins.is_empty() {
+        return Cors::default();
+    }
+
+    let mut cors = Cors::default()
+        .allowed_headers(vec![header::AUTHORIZATION, header::CONTENT_TYPE, header::ACCEPT])
+        .max_age(config.max_age_secs);
+    if let Ok(name) = header::HeaderName::try_from(key_header) {
+        cors = cors.allowed_header(name);
+    }
+
+    if config.allowed_origins.iter().any(|o| o == "*") {
+        cors = cors.allow_any_origin();
+    } else {
+        for origin in &config.allowed_origins {
+            cors = cors.allowed_origin(origin);
+        }
+    }
+
+    let methods: Vec<&str> = config.allowed_methods.iter().map(|m| m.as_str()).collect();
+    cors = cors.allowed_methods(methods);
+
+    if config.allow_credentials {
+        cors = cors.supports_credentials();
+    }
+
+    cors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test as actix_test, web, App, HttpResponse};
+
+    const ALLOWED: &str = "https://app.example.com";
+
+    fn config() -> CorsConfig {
+        CorsConfig {
+            allowed_origins: vec![ALLOWED.to_string()],
+            allow_credentials: true,
+            ..CorsConfig::default()
+        }
+    }
+
+    fn header_str<B>(resp: &actix_web::dev::ServiceResponse<B>, name: header::HeaderName) -> String {
+        resp.headers().get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_lowercase()
+    }
+
+    #[actix_web::test]
+    async fn preflight_from_allowed_origin_lists_methods_and_headers() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(build_cors(&config(), "X-API-Key"))
+                .route("/api/v1/health", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = actix_test::TestRequest::default()
+            .method(actix_web::http::Method::OPTIONS)
+            .uri("/api/v1/health")
+            .insert_header((header::ORIGIN, ALLOWED))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization, x-api-key"))
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(header_str(&resp, header::ACCESS_CONTROL_ALLOW_ORIGIN), ALLOWED);
+        assert_eq!(header_str(&resp, header::ACCESS_CONTROL_ALLOW_CREDENTIALS), "true");
+        assert_eq!(header_str(&resp, header::ACCESS_CONTROL_MAX_AGE), "3600");
+        let methods = header_str(&resp, header::ACCESS_CONTROL_ALLOW_METHODS);
+        assert!(methods.contains("get") && methods.contains("post"), "{}", methods);
+        let headers = header_str(&resp, header::ACCESS_CONTROL_ALLOW_HEADERS);
+        assert!(headers.contains("authorization") && headers.contains("x-api-key"), "{}", headers);
+
+        let req = actix_test::TestRequest::get()
+            .uri("/api/v1/health")
+            .insert_header((header::ORIGIN, ALLOWED))
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(header_str(&resp, header::ACCESS_CONTROL_ALLOW_ORIGIN), ALLOWED);
+    }
+
+    #[actix_web::test]
+    async fn disallowed_origin_is_rejected() {
+        for cors in [config(), CorsConfig::default()] {
+            let app = actix_test::init_service(
+                App::new()
+                    .wrap(build_cors(&cors, "X-API-Key"))
+                    .route("/api/v1/health", web::get().to(HttpResponse::Ok)),
+            )
+            .await;
+
+            let req = actix_test::TestRequest::default()
+                .method(actix_web::http::Method::OPTIONS)
+                .uri("/api/v1/health")
+                .insert_header((header::ORIGIN, "https://evil.example.com"))
+                .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+                .to_request();
+            let resp = actix_test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            assert!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+            // 非预检请求照常处理，但不带 Allow-Origin，浏览器不会把响应交给页面
+            let req = actix_test::TestRequest::get()
+                .uri("/api/v1/health")
+                .insert_header((header::ORIGIN, "https://evil.example.com"))
+                .to_request();
+            let resp = actix_test::call_service(&app, req).await;
+            assert!(resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        }
+    }
+}
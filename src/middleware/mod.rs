@@ -1,5 +1,17 @@
 //! 中间件模块
 
+pub mod admin_guard;
 pub mod api_key;
+pub mod cors;
+pub mod msgpack;
+pub mod pagination;
+pub mod provenance;
+pub mod query_limit;
 
-pub use api_key::ApiKeyMiddleware;
+pub use admin_guard::AdminGuard;
+pub use api_key::{redacted_request_line, ApiKeyMiddleware};
+pub use cors::build_cors;
+pub use msgpack::MessagePackMiddleware;
+pub use pagination::PaginationMiddleware;
+pub use provenance::ProvenanceMiddleware;
+pub use query_limit::QueryLengthLimit;
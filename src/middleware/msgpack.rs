@@ -0,0 +1,152 @@
+//! MessagePack 响应中间件
+//!
+//! 请求头 Accept 包含 application/msgpack（或 application/x-msgpack），或查询参数
+//! msgpack=1 时，将 JSON 响应（ApiResponse 信封）转为 MessagePack 输出，
+//! Content-Type 为 application/msgpack。结构体按字段名编码为 map（由 rmp-serde 编码）。
+//! 查询参数不使用 format，避免与接口自身的 format 参数（如批量导出的 parquet）冲突。
+//! 非 JSON 响应（NDJSON、SSE、纯文本等）保持不变，默认仍为 JSON
+
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{self, HeaderValue},
+    Error,
+};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use serde_json::Value;
+use std::rc::Rc;
+
+/// MessagePack 内容类型
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// MessagePack 响应中间件
+pub struct MessagePackMiddleware;
+
+impl MessagePackMiddleware {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for MessagePackMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = MessagePackMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(MessagePackMiddlewareService {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct MessagePackMiddlewareService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for MessagePackMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let wants_msgpack = wants_msgpack(&req);
+
+        Box::pin(async move {
+            let mut res = service.call(req).await?.map_into_boxed_body();
+            res.headers_mut()
+                .append(header::VARY, HeaderValue::from_static("Accept"));
+            if !wants_msgpack || !is_json(&res) {
+                return Ok(res);
+            }
+
+            let (req, res) = res.into_parts();
+            let (mut res, body) = res.into_parts();
+            let bytes = body::to_bytes(body).await.map_err(|e| {
+                actix_web::error::ErrorInternalServerError(format!("读取响应失败: {}", e))
+            })?;
+            let value: Value = serde_json::from_slice(&bytes).map_err(|e| {
+                actix_web::error::ErrorInternalServerError(format!("响应不是有效的 JSON: {}", e))
+            })?;
+
+            let packed = encode(&value).map_err(|e| {
+                actix_web::error::ErrorInternalServerError(format!("MessagePack 编码失败: {}", e))
+            })?;
+            res.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(MSGPACK_CONTENT_TYPE),
+            );
+            res.headers_mut().remove(header::CONTENT_LENGTH);
+            Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(packed))))
+        })
+    }
+}
+
+/// 客户端是否请求 MessagePack 格式
+fn wants_msgpack(req: &ServiceRequest) -> bool {
+    let accept = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    accept.contains(MSGPACK_CONTENT_TYPE)
+        || accept.contains("application/x-msgpack")
+        || req.query_string().split('&').any(|pair| pair == "msgpack=1")
+}
+
+/// 响应是否为 JSON
+fn is_json(res: &ServiceResponse<BoxBody>) -> bool {
+    res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"))
+}
+
+/// 将 JSON 值编码为 MessagePack
+fn encode(value: &Value) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    rmp_serde::to_vec_named(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn msgpack_round_trip_matches_json() {
+        let value = json!({
+            "success": true,
+            "data": [
+                { "symbol": "RB2601", "price": 3102.5, "volume": 1_234_567_890_123_u64, "change": -12 },
+                { "symbol": "螺纹钢", "price": null, "tags": [] },
+            ],
+            "count": 2,
+            "message": "x".repeat(300),
+            "numbers": (0..20).collect::<Vec<i32>>(),
+        });
+        let packed = encode(&value).unwrap();
+        let decoded: Value = rmp_serde::from_slice(&packed).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn small_values_use_compact_encoding() {
+        assert_eq!(encode(&json!(null)).unwrap(), [0xc0]);
+        assert_eq!(encode(&json!(5)).unwrap(), [0x05]);
+        assert_eq!(encode(&json!(-1)).unwrap(), [0xff]);
+        assert_eq!(encode(&json!({"a": true})).unwrap(), [0x81, 0xa1, b'a', 0xc3]);
+    }
+}
@@ -0,0 +1,346 @@
+//! 列表响应分页中间件
+//!
+//! 列表响应（ApiResponse 信封中 data 为数组）超过 api.max_response_rows 条时，只返回第一页，
+//! 附带分页前的总条数 total、truncated 标记和下一页令牌 next_page。完整结果作为快照保留
+//! SNAPSHOT_TTL，后续页直接从快照截取，不再调用接口、不再请求上游，各页数据也来自同一次获取。
+//!
+//! page_token 由上一页响应的 next_page 给出，客户端应原样传回，不应自行构造；
+//! 无法解析或不属于本请求的 page_token 返回 400，快照已过期返回 410
+
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header, StatusCode},
+    web, Error, HttpResponse,
+};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::config;
+
+/// 分页快照保留时间
+const SNAPSHOT_TTL: Duration = Duration::from_secs(600);
+/// 同时保留的快照数上限，超出时丢弃最早的快照
+const MAX_SNAPSHOTS: usize = 32;
+
+/// 翻页令牌参数
+#[derive(Debug, Deserialize)]
+struct PageTokenQuery {
+    page_token: Option<String>,
+}
+
+/// 一次列表响应的完整结果
+struct Snapshot {
+    /// 请求路径和去掉 page_token 后的查询字符串，令牌只能用于同一请求
+    target: String,
+    /// 完整的响应信封
+    envelope: Arc<Value>,
+    created_at: Instant,
+}
+
+static SNAPSHOTS: OnceLock<Mutex<HashMap<u64, Snapshot>>> = OnceLock::new();
+static NEXT_SNAPSHOT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn snapshots() -> std::sync::MutexGuard<'static, HashMap<u64, Snapshot>> {
+    SNAPSHOTS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 保存完整结果，返回快照编号
+fn store_snapshot(target: String, envelope: Arc<Value>) -> u64 {
+    let id = NEXT_SNAPSHOT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut snapshots = snapshots();
+    snapshots.retain(|_, s| s.created_at.elapsed() < SNAPSHOT_TTL);
+    if snapshots.len() >= MAX_SNAPSHOTS {
+        if let Some(oldest) = snapshots.iter().min_by_key(|(_, s)| s.created_at).map(|(id, _)| *id) {
+            snapshots.remove(&oldest);
+        }
+    }
+    snapshots.insert(id, Snapshot { target, envelope, created_at: Instant::now() });
+    id
+}
+
+/// 查找快照；编号不存在或已过期为 None，属于其他请求为 Some(Err)
+fn find_snapshot(id: u64, target: &str) -> Option<Result<Arc<Value>, ()>> {
+    let snapshots = snapshots();
+    let snapshot = snapshots
+        .get(&id)
+        .filter(|s| s.created_at.elapsed() < SNAPSHOT_TTL)?;
+    Some(if snapshot.target == target {
+        Ok(snapshot.envelope.clone())
+    } else {
+        Err(())
+    })
+}
+
+/// 生成快照 id 从 offset 行开始的翻页令牌
+fn encode_page_token(id: u64, offset: usize) -> String {
+    format!("{:x}-{}", id, offset)
+}
+
+/// 解析翻页令牌，返回快照编号和起始行
+fn decode_page_token(token: &str) -> Option<(u64, usize)> {
+    let (id, offset) = token.trim().split_once('-')?;
+    Some((u64::from_str_radix(id, 16).ok()?, offset.parse().ok()?))
+}
+
+/// 令牌对应的请求：路径加上去掉 page_token 的查询字符串
+fn page_target(req: &ServiceRequest) -> String {
+    let query: Vec<&str> = req
+        .query_string()
+        .split('&')
+        .filter(|pair| !pair.is_empty() && pair.split('=').next() != Some("page_token"))
+        .collect();
+    format!("{}?{}", req.path(), query.join("&"))
+}
+
+/// 列表信封的总条数，非列表响应为 None
+fn list_len(envelope: &Value) -> Option<usize> {
+    envelope.get("data")?.as_array().map(Vec::len)
+}
+
+/// 从完整信封截取 offset 起最多 max_rows 行（0 表示不限制），
+/// 后面还有数据时附带 truncated 和快照 id 的下一页令牌
+fn page_of(envelope: &Value, id: u64, offset: usize, max_rows: usize) -> Value {
+    let empty = Vec::new();
+    let rows = envelope.get("data").and_then(Value::as_array).unwrap_or(&empty);
+    let total = rows.len();
+    let start = offset.min(total);
+    let end = if max_rows == 0 { total } else { start.saturating_add(max_rows).min(total) };
+
+    let mut page: Map<String, Value> = envelope
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(key, _)| !matches!(key.as_str(), "data" | "count" | "total" | "truncated" | "next_page"))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    page.insert("data".to_string(), Value::Array(rows[start..end].to_vec()));
+    page.insert("count".to_string(), Value::from(end - start));
+    page.insert("total".to_string(), Value::from(total));
+    if end < total {
+        page.insert("truncated".to_string(), Value::Bool(true));
+        page.insert("next_page".to_string(), Value::String(encode_page_token(id, end)));
+    }
+    Value::Object(page)
+}
+
+/// 响应是否为 JSON
+fn is_json(res: &ServiceResponse<BoxBody>) -> bool {
+    res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"))
+}
+
+/// 列表响应分页中间件
+pub struct PaginationMiddleware;
+
+impl PaginationMiddleware {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for PaginationMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = PaginationMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(PaginationMiddlewareService {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct PaginationMiddlewareService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for PaginationMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let max_rows = config::max_response_rows();
+
+        Box::pin(async move {
+            let target = page_target(&req);
+            let token = web::Query::<PageTokenQuery>::from_query(req.query_string())
+                .ok()
+                .and_then(|query| query.into_inner().page_token);
+
+            // 后续页：从快照截取，不调用接口
+            if let Some(token) = token {
+                let snapshot = decode_page_token(&token).map(|(id, offset)| {
+                    (id, offset, find_snapshot(id, &target))
+                });
+                let response = match snapshot {
+                    Some((id, offset, Some(Ok(envelope)))) => {
+                        HttpResponse::Ok().json(page_of(&envelope, id, offset, max_rows))
+                    }
+                    Some((_, _, None)) => HttpResponse::build(StatusCode::GONE).json(serde_json::json!({
+                        "code": 410,
+                        "message": "page_token 已过期，请去掉 page_token 重新请求第一页",
+                        "data": null
+                    })),
+                    _ => HttpResponse::BadRequest().json(serde_json::json!({
+                        "code": 400,
+                        "message": format!("无效的 page_token: {}，请使用上一页响应中的 next_page", token),
+                        "data": null
+                    })),
+                };
+                return Ok(req.into_response(response));
+            }
+
+            let res = service.call(req).await?.map_into_boxed_body();
+            if max_rows == 0 || res.status() != StatusCode::OK || !is_json(&res) {
+                return Ok(res);
+            }
+
+            let (req, res) = res.into_parts();
+            let (mut res, body) = res.into_parts();
+            let bytes = body::to_bytes(body).await.map_err(|e| {
+                actix_web::error::ErrorInternalServerError(format!("读取响应失败: {}", e))
+            })?;
+            let envelope = match serde_json::from_slice::<Value>(&bytes) {
+                Ok(envelope) if list_len(&envelope).is_some_and(|len| len > max_rows) => envelope,
+                _ => return Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(bytes)))),
+            };
+
+            let envelope = Arc::new(envelope);
+            let id = store_snapshot(target, envelope.clone());
+            let page = serde_json::to_vec(&page_of(&envelope, id, 0, max_rows))?;
+            res.headers_mut().remove(header::CONTENT_LENGTH);
+            Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(page))))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, App};
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+
+    fn envelope(rows: usize) -> Value {
+        json!({
+            "success": true,
+            "data": (0..rows).map(|i| json!({ "seq": i })).collect::<Vec<_>>(),
+            "message": "Success",
+            "count": rows,
+            "effective_date": "20250620",
+        })
+    }
+
+    #[test]
+    fn large_list_is_split_into_pages_without_losing_rows() {
+        let rows = 120_003;
+        let full = envelope(rows);
+        let mut seen = 0;
+        let mut pages = 0;
+        let mut offset = 0;
+        loop {
+            let page = page_of(&full, 7, offset, 5000);
+            pages += 1;
+            assert_eq!(page["total"], rows);
+            assert_eq!(page["effective_date"], "20250620");
+            let data = page["data"].as_array().unwrap();
+            assert_eq!(page["count"], data.len());
+            assert!(data.len() <= 5000);
+            for row in data {
+                assert_eq!(row["seq"], seen);
+                seen += 1;
+            }
+            match page.get("next_page").and_then(Value::as_str) {
+                Some(token) => {
+                    assert_eq!(page["truncated"], true);
+                    let (id, next) = decode_page_token(token).unwrap();
+                    assert_eq!(id, 7);
+                    offset = next;
+                }
+                None => {
+                    assert!(page.get("truncated").is_none());
+                    break;
+                }
+            }
+        }
+        assert_eq!(seen, rows);
+        assert_eq!(pages, 25);
+    }
+
+    #[test]
+    fn out_of_range_offset_returns_empty_last_page() {
+        let page = page_of(&envelope(10), 1, 50, 5);
+        assert_eq!(page["data"], json!([]));
+        assert_eq!(page["count"], 0);
+        assert_eq!(page["total"], 10);
+        assert!(page.get("next_page").is_none());
+    }
+
+    #[actix_web::test]
+    async fn later_pages_are_served_from_snapshot() {
+        let max_rows = config::max_response_rows();
+        let rows = max_rows * 2 + 7;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+        let app = actix_test::init_service(
+            App::new().wrap(PaginationMiddleware::new()).route(
+                "/list",
+                web::get().to(move || {
+                    handler_calls.fetch_add(1, Ordering::SeqCst);
+                    async move { HttpResponse::Ok().json(envelope(rows)) }
+                }),
+            ),
+        )
+        .await;
+
+        let mut uri = "/list?date=20250620".to_string();
+        let mut counts = Vec::new();
+        let mut first_token = None;
+        loop {
+            let page: Value = actix_test::call_and_read_body_json(&app, actix_test::TestRequest::get().uri(&uri).to_request()).await;
+            assert_eq!(page["total"], rows);
+            counts.push(page["count"].as_u64().unwrap() as usize);
+            let Some(token) = page.get("next_page").and_then(Value::as_str) else { break };
+            first_token.get_or_insert_with(|| token.to_string());
+            uri = format!("/list?date=20250620&page_token={}", token);
+        }
+        assert_eq!(counts, [max_rows, max_rows, 7]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 令牌不能用于其他查询，不存在的快照返回 410
+        let token = first_token.unwrap();
+        let req = actix_test::TestRequest::get().uri(&format!("/list?date=20250619&page_token={}", token));
+        assert_eq!(actix_test::call_service(&app, req.to_request()).await.status(), StatusCode::BAD_REQUEST);
+        let req = actix_test::TestRequest::get().uri("/list?date=20250620&page_token=ffffffff-10");
+        assert_eq!(actix_test::call_service(&app, req.to_request()).await.status(), StatusCode::GONE);
+        let req = actix_test::TestRequest::get().uri("/list?date=20250620&page_token=r5000");
+        assert_eq!(actix_test::call_service(&app, req.to_request()).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,61 @@
+//! 数据来源追踪中间件
+//!
+//! 为每个请求建立独立的来源追踪作用域，处理器通过 ApiResponse::success
+//! 构造响应时自动带上本次请求访问的上游
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+use crate::services::futures::with_provenance_scope;
+
+/// 数据来源追踪中间件
+pub struct ProvenanceMiddleware;
+
+impl ProvenanceMiddleware {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ProvenanceMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ProvenanceMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ProvenanceMiddlewareService {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct ProvenanceMiddlewareService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ProvenanceMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        Box::pin(with_provenance_scope(async move { service.call(req).await }))
+    }
+}
@@ -0,0 +1,110 @@
+//! 查询字符串长度限制中间件
+//!
+//! 查询字符串超过 api.max_query_length 字节时直接返回 414，不进入参数解析和处理器。
+//! 限制每次请求从全局配置读取，配置热更新后立即生效
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::StatusCode,
+    Error, HttpResponse,
+};
+use futures::future::{ok, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+use crate::config;
+
+/// 查询字符串长度限制中间件
+pub struct QueryLengthLimit;
+
+impl QueryLengthLimit {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for QueryLengthLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = QueryLengthLimitService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(QueryLengthLimitService {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct QueryLengthLimitService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for QueryLengthLimitService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let max_len = config::max_query_length();
+
+        Box::pin(async move {
+            let len = req.query_string().len();
+            if max_len > 0 && len > max_len {
+                let response = HttpResponse::build(StatusCode::URI_TOO_LONG).json(serde_json::json!({
+                    "code": 414,
+                    "message": format!("查询字符串过长: {} 字节，上限 {} 字节", len, max_len),
+                    "data": null
+                }));
+                return Ok(req.into_response(response).map_into_right_body());
+            }
+
+            let res = service.call(req).await?;
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test as actix_test, web, App};
+
+    #[actix_web::test]
+    async fn query_over_limit_is_rejected_with_414() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(QueryLengthLimit::new())
+                .route("/echo", web::get().to(|| async { HttpResponse::Ok().body("ok") })),
+        )
+        .await;
+        let limit = config::max_query_length();
+        assert!(limit > 0);
+
+        let at_limit = format!("/echo?q={}", "a".repeat(limit - 2));
+        let req = actix_test::TestRequest::get().uri(&at_limit).to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let over_limit = format!("/echo?q={}", "a".repeat(limit - 1));
+        let req = actix_test::TestRequest::get().uri(&over_limit).to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::URI_TOO_LONG);
+        let body: serde_json::Value = actix_test::read_body_json(resp).await;
+        assert_eq!(body["code"], 414);
+        let expected = format!("查询字符串过长: {} 字节，上限 {} 字节", limit + 1, limit);
+        assert_eq!(body["message"], expected.as_str());
+    }
+}
@@ -9,7 +9,40 @@
 
 #![allow(dead_code)]
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+use std::cell::Cell;
+
+use crate::config;
+
+thread_local! {
+    /// 当前正在序列化的响应使用的 missing_price_as_zero 取值，由 with_price_format 设置
+    static MISSING_PRICE_AS_ZERO: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+/// 在 f 执行期间固定 api.missing_price_as_zero 的取值
+///
+/// 整个响应只读取一次配置，避免逐行逐字段加锁，也保证同一响应内的输出一致。
+/// 可嵌套调用，以最外层读取的取值为准
+pub fn with_price_format<R>(f: impl FnOnce() -> R) -> R {
+    let previous = MISSING_PRICE_AS_ZERO.get();
+    MISSING_PRICE_AS_ZERO.set(Some(previous.unwrap_or_else(config::missing_price_as_zero)));
+    let result = f();
+    MISSING_PRICE_AS_ZERO.set(previous);
+    result
+}
+
+/// 序列化价格字段：上游缺失时输出 null，开启 api.missing_price_as_zero 时输出 0 以兼容旧客户端
+///
+/// 在 with_price_format 之外序列化时每次读取配置
+fn serialize_price<S: Serializer>(value: &Option<f64>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(v) => serializer.serialize_some(v),
+        None if MISSING_PRICE_AS_ZERO.get().unwrap_or_else(config::missing_price_as_zero) => {
+            serializer.serialize_some(&0.0)
+        }
+        None => serializer.serialize_none(),
+    }
+}
 
 /// 期货合约实时行情
 /// 
@@ -20,8 +53,9 @@ pub struct FuturesInfo {
     pub symbol: String,
     /// 合约名称
     pub name: String,
-    /// 当前价格/最新价
-    pub current_price: f64,
+    /// 当前价格/最新价（上游缺失时为 null，与 0 区分）
+    #[serde(serialize_with = "serialize_price")]
+    pub current_price: Option<f64>,
     /// 涨跌额
     pub change: f64,
     /// 涨跌幅（百分比）
@@ -29,19 +63,267 @@ pub struct FuturesInfo {
     /// 成交量（手）
     pub volume: u64,
     /// 开盘价
-    pub open: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub open: Option<f64>,
     /// 最高价
-    pub high: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub high: Option<f64>,
     /// 最低价
-    pub low: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub low: Option<f64>,
     /// 结算价
     pub settlement: Option<f64>,
     /// 昨结算价
     pub prev_settlement: Option<f64>,
     /// 持仓量（手）
     pub open_interest: Option<u64>,
-    /// 更新时间
+    /// 更新时间（服务端抓取时间）
     pub updated_at: String,
+    /// 行情时间（上游返回的报价时间，北京时间）
+    #[serde(default)]
+    pub quote_time: Option<String>,
+    /// 行情所属交易日（YYYY-MM-DD），夜盘归属下一个交易日；外盘行情为 None
+    #[serde(default)]
+    pub trading_date: Option<String>,
+    /// 行情是否过期（行情时间距当前超过配置阈值）
+    #[serde(default)]
+    pub stale: bool,
+    /// 实时行情来源（sina / eastmoney），新浪不可用时由备用数据源兜底，可据此区分
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// 上游原始价格字符串（?precision=fixed 时返回，按最小变动价位的小数位数对齐）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_price_str: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_str: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high_str: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_str: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement_str: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_settlement_str: Option<String>,
+}
+
+/// 实时行情精简视图（?fields=quote）
+///
+/// 供高频轮询客户端使用，只保留价格相关字段
+#[derive(Debug, Serialize, Clone)]
+pub struct FuturesQuote {
+    /// 合约代码
+    pub symbol: String,
+    /// 最新价（上游缺失时为 null）
+    #[serde(serialize_with = "serialize_price")]
+    pub price: Option<f64>,
+    /// 涨跌幅（百分比）
+    pub change_percent: f64,
+    /// 行情时间，上游未提供时为服务端抓取时间
+    pub ts: String,
+}
+
+/// 批量行情中单个合约的获取状态
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FuturesBatchStatus {
+    /// 行情正常
+    Ok,
+    /// 上游无数据（返回空字符串、FAILURE 或缺少该合约），通常为未知或已退市合约
+    NoData,
+    /// 上游有数据但无法解析
+    ParseError,
+}
+
+/// 批量行情中单个合约的结果（POST /futures/batch/detail）
+#[derive(Debug, Serialize, Clone)]
+pub struct FuturesBatchItem {
+    /// 请求的合约代码
+    pub symbol: String,
+    /// 获取状态
+    pub status: FuturesBatchStatus,
+    /// 行情数据，status 不为 ok 时为 null
+    pub data: Option<FuturesInfo>,
+}
+
+impl FuturesInfo {
+    /// 去除上游原始价格字符串（默认输出不包含 *_str 字段）
+    pub fn clear_price_strings(&mut self) {
+        self.current_price_str = None;
+        self.open_str = None;
+        self.high_str = None;
+        self.low_str = None;
+        self.settlement_str = None;
+        self.prev_settlement_str = None;
+    }
+}
+
+impl From<&FuturesInfo> for FuturesQuote {
+    fn from(info: &FuturesInfo) -> Self {
+        Self {
+            symbol: info.symbol.clone(),
+            price: info.current_price,
+            change_percent: info.change_percent,
+            ts: info.quote_time.clone().unwrap_or_else(|| info.updated_at.clone()),
+        }
+    }
+}
+
+/// 实时行情字段投影参数
+#[derive(Debug, Deserialize)]
+pub struct RealtimeFieldsQuery {
+    /// full（默认）返回完整行情，quote 返回精简视图
+    pub fields: Option<String>,
+}
+
+impl RealtimeFieldsQuery {
+    /// 是否只返回精简视图，fields 取值无效时返回错误
+    pub fn quote_only(&self) -> anyhow::Result<bool> {
+        match self.fields.as_deref().map(str::trim) {
+            None | Some("") | Some("full") => Ok(false),
+            Some("quote") => Ok(true),
+            Some(other) => Err(anyhow::anyhow!("fields 只能为 full 或 quote，当前为 {}", other)),
+        }
+    }
+}
+
+/// K 线品种元数据参数
+#[derive(Debug, Deserialize)]
+pub struct KlineMetaQuery {
+    /// 为 1 时在响应中附带 meta（报价单位、最小变动价位、合约乘数）
+    pub with_meta: Option<u8>,
+}
+
+impl KlineMetaQuery {
+    /// 是否附带 meta，with_meta 取值无效时返回错误
+    pub fn enabled(&self) -> anyhow::Result<bool> {
+        match self.with_meta {
+            None | Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            Some(other) => Err(anyhow::anyhow!("with_meta 只能为 0 或 1，当前为 {}", other)),
+        }
+    }
+}
+
+/// K 线品种元数据，便于图表客户端格式化坐标轴
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct KlineMeta {
+    /// 报价单位（如 元/吨、元/克、指数点）
+    pub price_unit: Option<String>,
+    /// 最小变动价位，交易规则中无该品种时为 null
+    pub tick_size: Option<f64>,
+    /// 合约乘数，交易规则中无该品种时为 null
+    pub contract_multiplier: Option<f64>,
+}
+
+/// 上游原始数据调试参数
+#[derive(Debug, Deserialize)]
+pub struct DebugRawQuery {
+    /// 为 1 时在每行附带解析所用的上游原始单元格（_raw），需开启 debug.endpoints_enabled
+    pub debug_raw: Option<u8>,
+}
+
+impl DebugRawQuery {
+    /// 是否附带 _raw，debug_raw 取值无效时返回错误
+    pub fn enabled(&self) -> anyhow::Result<bool> {
+        match self.debug_raw {
+            None | Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            Some(other) => Err(anyhow::anyhow!("debug_raw 只能为 0 或 1，当前为 {}", other)),
+        }
+    }
+}
+
+/// 价格精度参数
+#[derive(Debug, Deserialize)]
+pub struct PrecisionQuery {
+    /// raw（默认）只返回浮点价格，fixed 另返回按最小变动价位对齐的 *_str 字符串价格
+    pub precision: Option<String>,
+}
+
+impl PrecisionQuery {
+    /// 是否返回字符串价格，precision 取值无效时返回错误
+    pub fn fixed(&self) -> anyhow::Result<bool> {
+        match self.precision.as_deref().map(str::trim) {
+            None | Some("") | Some("raw") => Ok(false),
+            Some("fixed") => Ok(true),
+            Some(other) => Err(anyhow::anyhow!("precision 只能为 raw 或 fixed，当前为 {}", other)),
+        }
+    }
+}
+
+/// SSE 推送单个连接允许订阅的最大合约数
+pub const MAX_STREAM_SYMBOLS: usize = 50;
+
+/// SSE 推送默认间隔（秒）
+pub const DEFAULT_STREAM_INTERVAL_SECS: u64 = 3;
+
+/// 实时行情 SSE 推送查询参数
+#[derive(Debug, Deserialize)]
+pub struct FuturesStreamQuery {
+    /// 合约代码，逗号分隔，如 CU2405,RB2405
+    pub symbols: String,
+    /// 推送间隔（秒），1-60，默认 3
+    pub interval_secs: Option<u64>,
+    /// full（默认）推送完整行情，quote 推送精简视图
+    pub fields: Option<String>,
+}
+
+impl FuturesStreamQuery {
+    /// 解析合约列表：去空白、去重，数量须在 1..=MAX_STREAM_SYMBOLS 之间
+    pub fn symbol_list(&self) -> anyhow::Result<Vec<String>> {
+        let mut symbols: Vec<String> = Vec::new();
+        for symbol in self.symbols.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            if !symbols.iter().any(|s| s.eq_ignore_ascii_case(symbol)) {
+                symbols.push(symbol.to_string());
+            }
+        }
+
+        if symbols.is_empty() {
+            return Err(anyhow::anyhow!("symbols 不能为空"));
+        }
+        if symbols.len() > MAX_STREAM_SYMBOLS {
+            return Err(anyhow::anyhow!(
+                "symbols 最多 {} 个，当前为 {}",
+                MAX_STREAM_SYMBOLS,
+                symbols.len()
+            ));
+        }
+        Ok(symbols)
+    }
+
+    /// 推送间隔，超出 1-60 秒时返回错误
+    pub fn interval(&self) -> anyhow::Result<std::time::Duration> {
+        let secs = self.interval_secs.unwrap_or(DEFAULT_STREAM_INTERVAL_SECS);
+        if !(1..=60).contains(&secs) {
+            return Err(anyhow::anyhow!("interval_secs 必须在 1-60 之间，当前为 {}", secs));
+        }
+        Ok(std::time::Duration::from_secs(secs))
+    }
+
+    /// 是否只推送精简视图，fields 取值无效时返回错误
+    pub fn quote_only(&self) -> anyhow::Result<bool> {
+        RealtimeFieldsQuery { fields: self.fields.clone() }.quote_only()
+    }
+}
+
+/// 实时行情请求诊断信息（不发送请求）
+#[derive(Debug, Serialize, Clone)]
+pub struct RealtimeRequestDebug {
+    /// 输入的合约代码
+    pub symbol: String,
+    /// 新浪实时行情格式代码（nf_/CFF_ 前缀）
+    pub formatted_symbol: String,
+    /// 是否判定为中金所合约
+    pub is_cffex: bool,
+    /// 将要请求的上游 URL
+    pub url: String,
+}
+
+/// 代码格式诊断查询参数
+#[derive(Debug, Deserialize)]
+pub struct DebugFormatQuery {
+    /// 合约代码（如 CU2405）
+    pub symbol: String,
 }
 
 /// 期货历史K线数据
@@ -54,24 +336,50 @@ pub struct FuturesHistoryData {
     /// 日期
     pub date: String,
     /// 开盘价
-    pub open: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub open: Option<f64>,
     /// 最高价
-    pub high: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub high: Option<f64>,
     /// 最低价
-    pub low: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub low: Option<f64>,
     /// 收盘价
-    pub close: f64,
+    #[serde(serialize_with = "serialize_price")]
+    pub close: Option<f64>,
     /// 成交量（手）
     pub volume: u64,
     /// 结算价
     pub settlement: Option<f64>,
     /// 持仓量（手）
     pub open_interest: Option<u64>,
+    /// 上游原始价格字符串（?precision=fixed 时返回，按最小变动价位的小数位数对齐）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_str: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high_str: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_str: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub close_str: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement_str: Option<String>,
+}
+
+impl FuturesHistoryData {
+    /// 去除上游原始价格字符串（默认输出不包含 *_str 字段）
+    pub fn clear_price_strings(&mut self) {
+        self.open_str = None;
+        self.high_str = None;
+        self.low_str = None;
+        self.close_str = None;
+        self.settlement_str = None;
+    }
 }
 
 /// 期货查询参数
 #[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct FuturesQuery {
     /// 合约代码
     pub symbol: Option<String>,
@@ -85,6 +393,133 @@ pub struct FuturesQuery {
     pub end_date: Option<String>,
     /// 返回数量限制
     pub limit: Option<usize>,
+    /// 为 1 时跳过本地文件缓存
+    pub no_cache: Option<u8>,
+}
+
+/// 单次查询允许的最大返回数量
+pub const MAX_QUERY_LIMIT: usize = 5000;
+
+impl FuturesQuery {
+    /// 创建查询参数构建器
+    pub fn builder() -> FuturesQueryBuilder {
+        FuturesQueryBuilder::default()
+    }
+
+    /// 校验查询参数
+    ///
+    /// - 日期必须为合法的 YYYYMMDD
+    /// - 开始日期不能晚于结束日期
+    /// - limit 必须在 1..=MAX_QUERY_LIMIT 之间
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let start = parse_query_date("start_date", self.start_date.as_deref())?;
+        let end = parse_query_date("end_date", self.end_date.as_deref())?;
+        if let (Some(start), Some(end)) = (start, end) {
+            if start > end {
+                anyhow::bail!(
+                    "开始日期 {} 不能晚于结束日期 {}",
+                    start.format("%Y%m%d"),
+                    end.format("%Y%m%d")
+                );
+            }
+        }
+
+        if let Some(limit) = self.limit {
+            if limit == 0 || limit > MAX_QUERY_LIMIT {
+                anyhow::bail!("limit 必须在 1 到 {} 之间，当前为 {}", MAX_QUERY_LIMIT, limit);
+            }
+        }
+
+        if let Some(flag) = self.no_cache {
+            if flag > 1 {
+                anyhow::bail!("no_cache 只能为 0 或 1，当前为 {}", flag);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 解析 YYYYMMDD 格式的日期
+///
+/// 要求恰好 8 位数字且为有效日期，服务层按位置截取年月日前应先经过此校验
+pub fn parse_yyyymmdd(value: &str) -> anyhow::Result<chrono::NaiveDate> {
+    if value.len() != 8 || !value.chars().all(|c| c.is_ascii_digit()) {
+        anyhow::bail!("格式错误，应为 YYYYMMDD: {}", value);
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y%m%d")
+        .map_err(|_| anyhow::anyhow!("不是有效日期: {}", value))
+}
+
+/// 解析 YYYYMMDD 格式的查询日期
+pub(crate) fn parse_query_date(
+    field: &str,
+    value: Option<&str>,
+) -> anyhow::Result<Option<chrono::NaiveDate>> {
+    value
+        .map(|value| parse_yyyymmdd(value).map_err(|e| anyhow::anyhow!("{} {}", field, e)))
+        .transpose()
+}
+
+/// 期货查询参数构建器
+#[derive(Debug, Default)]
+pub struct FuturesQueryBuilder {
+    query: FuturesQuery,
+}
+
+impl FuturesQueryBuilder {
+    pub fn symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.query.symbol = Some(symbol.into());
+        self
+    }
+
+    pub fn exchange(mut self, exchange: impl Into<String>) -> Self {
+        self.query.exchange = Some(exchange.into());
+        self
+    }
+
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.query.category = Some(category.into());
+        self
+    }
+
+    pub fn start_date(mut self, date: impl Into<String>) -> Self {
+        self.query.start_date = Some(date.into());
+        self
+    }
+
+    pub fn end_date(mut self, date: impl Into<String>) -> Self {
+        self.query.end_date = Some(date.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.query.limit = Some(limit);
+        self
+    }
+
+    pub fn no_cache(mut self, no_cache: bool) -> Self {
+        self.query.no_cache = Some(no_cache as u8);
+        self
+    }
+
+    /// 构建查询参数（不做校验）
+    pub fn build(self) -> FuturesQuery {
+        self.query
+    }
+
+    /// 构建并校验查询参数
+    pub fn try_build(self) -> anyhow::Result<FuturesQuery> {
+        self.query.validate()?;
+        Ok(self.query)
+    }
+}
+
+/// 品种合约列表查询参数
+#[derive(Debug, Deserialize)]
+pub struct VarietyContractsQuery {
+    /// 返回数量限制
+    pub limit: Option<usize>,
 }
 
 /// 交易所信息
@@ -112,6 +547,42 @@ pub struct FuturesSymbolMark {
     pub mark: String,
 }
 
+/// 按交易所分组的品种映射项
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FuturesSymbolEntry {
+    /// 品种名称（如 PTA、铜）
+    pub symbol: String,
+    /// 新浪 API 的 node 参数（如 pta_qh、tong_qh）
+    pub mark: String,
+}
+
+/// 品种映射表查询参数
+#[derive(Debug, Deserialize)]
+pub struct FuturesSymbolsQuery {
+    /// 交易所代码（SHFE/DCE/CZCE/CFFEX/GFEX，INE 归入 SHFE），为空返回全部
+    pub exchange: Option<String>,
+}
+
+/// 中文品种名称与英文代码对照项
+///
+/// 同一品种的多个中文别名各占一项
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FuturesVarietyName {
+    /// 中文品种名称（如 铜、螺纹钢）
+    pub cn: String,
+    /// 英文品种代码（如 CU、RB）
+    pub en: String,
+    /// 交易所代码（SHFE/INE/DCE/CZCE/GFEX/CFFEX）
+    pub exchange: String,
+}
+
+/// 品种对照表查询参数
+#[derive(Debug, Deserialize)]
+pub struct FuturesVarietyMapQuery {
+    /// 过滤关键字：中文名称包含该关键字，或英文代码包含该关键字（不区分大小写）
+    pub q: Option<String>,
+}
+
 /// 期货合约详情
 /// 
 /// 包含合约的交易规则和参数
@@ -147,6 +618,27 @@ pub struct FuturesContractDetail {
     pub delivery_method: String,
 }
 
+/// 合约到期信息
+///
+/// 按交易所规则推算的最后交易日和最后交割日（只跳过周末，不含法定节假日）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FuturesContractExpiry {
+    /// 合约代码（大写，如 CU2405）
+    pub contract: String,
+    /// 品种代码
+    pub variety: String,
+    /// 交易所代码
+    pub exchange: String,
+    /// 最后交易日 YYYY-MM-DD
+    pub last_trading_day: String,
+    /// 最后交割日 YYYY-MM-DD
+    pub delivery_day: String,
+    /// 距最后交易日的自然日数（北京时间今天起算，已到期为负数）
+    pub days_to_expiry: i64,
+    /// 所用规则说明
+    pub rule: String,
+}
+
 /// 外盘期货品种信息
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ForeignFuturesSymbol {
@@ -156,6 +648,13 @@ pub struct ForeignFuturesSymbol {
     pub code: String,
 }
 
+/// 外盘期货实时行情查询参数
+#[derive(Debug, Deserialize)]
+pub struct ForeignRealtimeQuery {
+    /// 品种代码列表，逗号分隔，如 "GC,CL"
+    pub codes: String,
+}
+
 /// 主力连续合约信息
 /// 
 /// 对应 akshare 的 futures_display_main_sina() 返回结果
@@ -167,6 +666,12 @@ pub struct FuturesMainContract {
     pub name: String,
     /// 交易所代码
     pub exchange: String,
+    /// 最新价（实时行情获取失败时为 null）
+    #[serde(default)]
+    pub last_price: Option<f64>,
+    /// 持仓量（手，实时行情获取失败时为 null）
+    #[serde(default)]
+    pub open_interest: Option<u64>,
 }
 
 /// 主力连续合约日K线数据
@@ -192,30 +697,15 @@ pub struct FuturesMainDailyData {
     pub settle: Option<f64>,
 }
 
-/// 期货持仓排名数据
-/// 
-/// 对应 akshare 的 futures_hold_pos_sina() 返回结果
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct FuturesHoldPosition {
-    /// 名次
-    pub rank: u32,
-    /// 期货公司名称
-    pub company: String,
-    /// 数值（成交量/多单持仓/空单持仓）
-    pub value: i64,
-    /// 比上交易日增减
-    pub change: i64,
-}
-
 /// 持仓排名查询参数
 #[derive(Debug, Deserialize)]
 pub struct FuturesHoldPosQuery {
-    /// 类型：volume(成交量), long(多单持仓), short(空单持仓)
+    /// 类型：volume(成交量), long(多单持仓), short(空单持仓)，也可用中文名称，默认 volume
     pub pos_type: Option<String>,
     /// 合约代码（如 RB2510）
     pub contract: String,
-    /// 查询日期（YYYYMMDD）
-    pub date: String,
+    /// 查询日期（YYYYMMDD），默认最近交易日（北京时间）
+    pub date: Option<String>,
 }
 
 /// 主力连续日数据查询参数
@@ -244,6 +734,12 @@ pub struct ForeignFuturesHistData {
     pub close: f64,
     /// 成交量
     pub volume: u64,
+    /// 结算价（数据源提供时）
+    #[serde(default)]
+    pub settlement: Option<f64>,
+    /// 持仓量（数据源提供时）
+    #[serde(default)]
+    pub open_interest: Option<u64>,
 }
 
 /// 外盘期货合约详情
@@ -283,6 +779,15 @@ pub struct FuturesCommInfo {
     pub fee_total: Option<f64>,              // 手续费(开+平)
     pub net_profit_per_tick: Option<f64>,    // 每跳净利
     pub remark: Option<String>,              // 备注
+    #[serde(rename = "_raw", default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Vec<String>>,            // 上游原始单元格（?debug_raw=1 时返回）
+}
+
+impl FuturesCommInfo {
+    /// 去除上游原始单元格（默认输出不包含 _raw 字段）
+    pub fn clear_raw(&mut self) {
+        self.raw = None;
+    }
 }
 
 /// 期货手续费查询参数
@@ -305,12 +810,70 @@ pub struct FuturesRule {
     pub max_order_size: Option<u64>,   // 限价单每笔最大下单手数
     pub special_note: Option<String>,  // 特殊合约参数调整
     pub remark: Option<String>,        // 调整备注
+    #[serde(rename = "_raw", default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Vec<String>>,      // 上游原始单元格（?debug_raw=1 时返回）
+}
+
+impl FuturesRule {
+    /// 去除上游原始单元格（默认输出不包含 _raw 字段）
+    pub fn clear_raw(&mut self) {
+        self.raw = None;
+    }
 }
 
 /// 期货交易规则查询参数
 #[derive(Debug, Deserialize)]
 pub struct FuturesRuleQuery {
-    pub date: Option<String>,  // 交易日期 YYYYMMDD
+    pub date: Option<String>,     // 交易日期 YYYYMMDD，默认最近交易日（北京时间）
+    pub exchange: Option<String>, // 交易所代码（如 SHFE）或名称
+    pub product: Option<String>,  // 品种代码（如 CU）或名称
+}
+
+/// 期货交易费用查询参数
+#[derive(Debug, Deserialize)]
+pub struct FuturesFeesQuery {
+    pub exchange: Option<String>, // 交易所代码（如 SHFE）或名称
+    pub product: Option<String>,  // 品种代码（如 rb，不区分大小写）或品种名称
+    pub sort: Option<String>,     // 排序字段，取值见 FEES_SORT_FIELDS
+    pub order: Option<String>,    // 排序方向 asc（默认）/ desc
+}
+
+/// 交易费用表可排序的字段，按对应的 *_num 数值排序
+pub const FEES_SORT_FIELDS: [&str; 10] = [
+    "contract_size",
+    "price_tick",
+    "open_fee_rate",
+    "open_fee",
+    "close_fee_rate",
+    "close_fee",
+    "close_today_fee_rate",
+    "close_today_fee",
+    "long_margin_rate",
+    "short_margin_rate",
+];
+
+impl FuturesFeesQuery {
+    /// 解析排序参数，返回 (排序字段, 是否降序)；未指定 sort 时返回 None
+    ///
+    /// sort 不在 FEES_SORT_FIELDS 中或 order 不是 asc/desc 时返回错误
+    pub fn sort_order(&self) -> anyhow::Result<Option<(&'static str, bool)>> {
+        let descending = match self.order.as_deref().map(str::trim) {
+            None | Some("") | Some("asc") => false,
+            Some("desc") => true,
+            Some(other) => anyhow::bail!("order 只能为 asc 或 desc，当前为 {}", other),
+        };
+        let Some(sort) = self.sort.as_deref().map(str::trim).filter(|s| !s.is_empty()) else {
+            return Ok(None);
+        };
+        match FEES_SORT_FIELDS.iter().find(|field| **field == sort) {
+            Some(field) => Ok(Some((field, descending))),
+            None => anyhow::bail!(
+                "sort 不支持 {}，可选 {}",
+                sort,
+                FEES_SORT_FIELDS.join("/")
+            ),
+        }
+    }
 }
 
 /// 期货交易费用信息
@@ -333,6 +896,90 @@ pub struct FuturesFeesInfo {
     pub long_margin_rate: String,      // 做多保证金率
     pub short_margin_rate: String,     // 做空保证金率
     pub updated_at: String,            // 更新时间
+    // 以下为解析后的数值：费率和保证金率换算为小数（"10%" → 0.1、"0.5‱" → 0.00005），费用为元
+    pub contract_size_num: Option<f64>,        // 合约乘数
+    pub price_tick_num: Option<f64>,           // 最小跳动
+    pub open_fee_rate_num: Option<f64>,        // 开仓费率
+    pub open_fee_num: Option<f64>,             // 开仓费用/手
+    pub close_fee_rate_num: Option<f64>,       // 平仓费率
+    pub close_fee_num: Option<f64>,            // 平仓费用/手
+    pub close_today_fee_rate_num: Option<f64>, // 平今费率
+    pub close_today_fee_num: Option<f64>,      // 平今费用/手
+    pub long_margin_rate_num: Option<f64>,     // 做多保证金率
+    pub short_margin_rate_num: Option<f64>,    // 做空保证金率
+    #[serde(rename = "_raw", default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Vec<String>>,              // 上游原始单元格 HTML（?debug_raw=1 时返回）
+}
+
+impl FuturesFeesInfo {
+    /// 去除上游原始单元格（默认输出不包含 _raw 字段）
+    pub fn clear_raw(&mut self) {
+        self.raw = None;
+    }
+
+    /// 排序字段（FEES_SORT_FIELDS 之一）对应的数值，无法解析或字段未知时为 None
+    pub fn sort_value(&self, field: &str) -> Option<f64> {
+        match field {
+            "contract_size" => self.contract_size_num,
+            "price_tick" => self.price_tick_num,
+            "open_fee_rate" => self.open_fee_rate_num,
+            "open_fee" => self.open_fee_num,
+            "close_fee_rate" => self.close_fee_rate_num,
+            "close_fee" => self.close_fee_num,
+            "close_today_fee_rate" => self.close_today_fee_rate_num,
+            "close_today_fee" => self.close_today_fee_num,
+            "long_margin_rate" => self.long_margin_rate_num,
+            "short_margin_rate" => self.short_margin_rate_num,
+            _ => None,
+        }
+    }
+}
+
+/// 单手交易成本（GET /futures/fees/per-lot/{symbol}）
+///
+/// 按当前价格和 OpenCTP 交易费用表计算，金额单位为元；费用表对应项缺失时为 None
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FuturesPerLotCost {
+    pub symbol: String,                 // 请求的合约代码
+    pub exchange: String,               // 交易所
+    pub contract_code: String,          // 费用表中匹配的合约代码
+    pub product_code: String,           // 品种代码
+    pub price: f64,                     // 计算使用的价格（最新价，缺失时为昨结算价）
+    pub contract_size: f64,             // 合约乘数
+    pub open_cost: Option<f64>,         // 开仓手续费/手
+    pub close_cost: Option<f64>,        // 平仓手续费/手
+    pub close_today_cost: Option<f64>,  // 平今手续费/手
+    pub long_margin: Option<f64>,       // 做多保证金/手
+    pub short_margin: Option<f64>,      // 做空保证金/手
+    pub fees_updated_at: String,        // 费用表更新时间
+}
+
+/// 手续费数据
+/// 九期网可用时为九期网手续费表，降级时为 OpenCTP 交易费用表
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum CommInfoData {
+    Qihuo(Vec<FuturesCommInfo>),     // 九期网数据
+    OpenCtp(Vec<FuturesFeesInfo>),   // OpenCTP 数据
+}
+
+/// 期货手续费信息（九期网不可用时降级为 OpenCTP）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FuturesCommInfoResult {
+    pub source: String,              // 实际数据源：9qihuo / openctp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,        // 数据源替换说明，仅降级时返回
+    pub data: CommInfoData,          // 手续费数据
+}
+
+impl FuturesCommInfoResult {
+    /// 去除各行的上游原始单元格（默认输出不包含 _raw 字段）
+    pub fn clear_raw(&mut self) {
+        match &mut self.data {
+            CommInfoData::Qihuo(rows) => rows.iter_mut().for_each(FuturesCommInfo::clear_raw),
+            CommInfoData::OpenCtp(rows) => rows.iter_mut().for_each(FuturesFeesInfo::clear_raw),
+        }
+    }
 }
 
 
@@ -360,6 +1007,14 @@ pub struct FuturesInventory99Query {
     pub symbol: String,  // 品种名称或代码，如"豆一"或"A"
 }
 
+/// 库存数据日期范围查询参数
+#[derive(Debug, Deserialize)]
+pub struct FuturesInventoryRangeQuery {
+    pub start_date: Option<String>,  // 开始日期 YYYYMMDD（含）
+    pub end_date: Option<String>,    // 结束日期 YYYYMMDD（含）
+    pub limit: Option<usize>,        // 仅返回最近 N 条
+}
+
 /// 期货现货价格及基差数据
 /// 对应 akshare 的 futures_spot_price() 返回结果
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -380,7 +1035,7 @@ pub struct FuturesSpotPrice {
 /// 期货现货价格查询参数
 #[derive(Debug, Deserialize)]
 pub struct FuturesSpotPriceQuery {
-    pub date: String,                    // 交易日期 YYYYMMDD
+    pub date: Option<String>,            // 交易日期 YYYYMMDD，默认最近交易日（北京时间）
     pub symbols: Option<String>,         // 品种代码列表，逗号分隔，如"RB,CU"，为空时返回所有品种
 }
 
@@ -398,12 +1053,16 @@ pub struct FuturesSpotPricePrevious {
     pub basis_180d_high: Option<f64>,    // 180日内主力基差最高
     pub basis_180d_low: Option<f64>,     // 180日内主力基差最低
     pub basis_180d_avg: Option<f64>,     // 180日内主力基差平均
+    pub basis_percentile: Option<f64>,   // 当前基差在180日高低区间中的位置 [0,1]，高低相同时为空
 }
 
 /// 期货现货价格历史查询参数
 #[derive(Debug, Deserialize)]
 pub struct FuturesSpotPricePreviousQuery {
-    pub date: String,  // 交易日期 YYYYMMDD
+    pub date: Option<String>,       // 交易日期 YYYYMMDD，默认最近交易日（北京时间）
+    #[serde(default)]
+    pub fallback: bool,             // 当日无数据时回溯到最近有数据的交易日
+    pub commodity: Option<String>,  // 商品名称关键字，如"螺纹"，为空时返回所有商品
 }
 
 
@@ -416,6 +1075,35 @@ pub struct FuturesSpotPriceDailyQuery {
 }
 
 
+/// 市场概览：涨跌幅榜（主力连续合约）
+#[derive(Debug, Serialize, Clone)]
+pub struct OverviewMovers {
+    pub gainers: Vec<FuturesQuote>,      // 涨幅前列，按涨幅从大到小
+    pub losers: Vec<FuturesQuote>,       // 跌幅前列，按跌幅从大到小
+}
+
+/// 市场概览：最近一日主力基差率极值
+#[derive(Debug, Serialize, Clone)]
+pub struct OverviewBasis {
+    pub date: String,                    // 现货数据日期 YYYYMMDD
+    pub max_basis: FuturesSpotPrice,     // 主力基差率最高的品种
+    pub min_basis: FuturesSpotPrice,     // 主力基差率最低的品种
+}
+
+/// 市场概览（首页看板）
+/// 获取失败的部分不出现在响应中
+#[derive(Debug, Serialize, Clone)]
+pub struct FuturesOverview {
+    pub updated_at: String,              // 概览生成时间（北京时间）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub main_quotes: Option<Vec<FuturesQuote>>, // 精选主力连续合约行情
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_movers: Option<OverviewMovers>,     // 涨跌幅榜
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub basis: Option<OverviewBasis>,           // 基差极值
+}
+
+
 /// 期货持仓排名汇总数据
 /// 对应 akshare 的 get_rank_sum() 返回结果
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -474,6 +1162,16 @@ pub struct RankSumDailyQuery {
     pub vars: Option<String>,            // 品种代码列表，逗号分隔，如"RB,CU"，为空时返回所有品种
 }
 
+/// 持仓排名汇总统一查询参数：date 查询单日，或 start_date + end_date 查询日期区间
+#[derive(Debug, Deserialize)]
+pub struct RankSumQuery {
+    pub date: Option<String>,            // 交易日期 YYYYMMDD（单日），与区间都未提供时默认最近交易日
+    pub start_date: Option<String>,      // 开始日期 YYYYMMDD（区间）
+    pub end_date: Option<String>,        // 结束日期 YYYYMMDD（区间）
+    pub vars: Option<String>,            // 品种代码列表，逗号分隔，如"RB,CU"，为空时返回所有品种
+    pub no_cache: Option<u8>,            // 为 1 时跳过本地文件缓存（仅单日）
+}
+
 /// 期货持仓排名表数据（单个会员）
 /// 对应 akshare 的 get_shfe_rank_table/get_dce_rank_table/get_cffex_rank_table/get_rank_table_czce 返回结果
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -495,8 +1193,9 @@ pub struct PositionRankData {
 /// 期货持仓排名表查询参数
 #[derive(Debug, Deserialize)]
 pub struct RankTableQuery {
-    pub date: String,                        // 交易日期 YYYYMMDD
+    pub date: Option<String>,                // 交易日期 YYYYMMDD，默认最近交易日（北京时间）
     pub vars: Option<String>,                // 品种代码列表，逗号分隔，如"RB,CU"，为空时返回所有品种
+    pub no_cache: Option<u8>,                // 为 1 时跳过本地文件缓存
 }
 
 /// 期货持仓排名表响应（按合约分组）
@@ -506,6 +1205,86 @@ pub struct RankTableResponse {
     pub data: Vec<PositionRankData>,         // 排名数据列表
 }
 
+/// 会员净持仓（仅统计同时出现在持多单和持空单排名中的会员）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PositionNetMember {
+    pub party_name: String,                  // 会员简称
+    pub long_open_interest: i64,             // 持多单
+    pub long_open_interest_chg: i64,         // 持多单变化量
+    pub short_open_interest: i64,            // 持空单
+    pub short_open_interest_chg: i64,        // 持空单变化量
+    pub net: i64,                            // 净持仓（多 - 空）
+    pub net_chg: i64,                        // 净持仓变化量
+}
+
+/// 合约净持仓统计
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PositionNetResponse {
+    pub exchange: String,                    // 交易所代码
+    pub symbol: String,                      // 合约代码
+    pub date: String,                        // 交易日期 YYYYMMDD
+    pub long_top20: i64,                     // 前20会员持多单总和
+    pub long_chg_top20: i64,                 // 前20会员持多单变化总和
+    pub short_top20: i64,                    // 前20会员持空单总和
+    pub short_chg_top20: i64,                // 前20会员持空单变化总和
+    pub net_top20: i64,                      // 前20会员净持仓（多 - 空）
+    pub net_chg_top20: i64,                  // 前20会员净持仓变化
+    pub members: Vec<PositionNetMember>,     // 会员净持仓，按净持仓从大到小排序
+}
+
+/// 合约持仓集中度（基于前20会员持仓排名）
+///
+/// 交易所只公布前20名会员，总量以前20名之和代替：CR5 = 前5名持仓 / 前20名持仓，
+/// HHI = 前20名各会员持仓占比的平方和（0-1）。某一侧没有持仓数据时对应字段为 None
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PositionConcentration {
+    pub exchange: String,                    // 交易所代码
+    pub symbol: String,                      // 合约代码
+    pub date: String,                        // 交易日期 YYYYMMDD
+    pub long_cr5: Option<f64>,               // 多单前5名集中度
+    pub short_cr5: Option<f64>,              // 空单前5名集中度
+    pub long_hhi: Option<f64>,               // 多单赫芬达尔指数
+    pub short_hhi: Option<f64>,              // 空单赫芬达尔指数
+}
+
+/// 净持仓、持仓集中度查询参数
+#[derive(Debug, Deserialize)]
+pub struct PositionNetQuery {
+    pub date: Option<String>,                // 交易日期 YYYYMMDD，默认最近交易日（北京时间）
+}
+
+/// 会员两日持仓对比（某侧未进入排名时为 None）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PositionDiffMember {
+    pub party_name: String,                  // 会员简称
+    pub long_d1: Option<i64>,                // 第一日持多单
+    pub long_d2: Option<i64>,                // 第二日持多单
+    pub long_delta: i64,                     // 持多单变化（未上榜按 0 计）
+    pub short_d1: Option<i64>,               // 第一日持空单
+    pub short_d2: Option<i64>,               // 第二日持空单
+    pub short_delta: i64,                    // 持空单变化（未上榜按 0 计）
+}
+
+/// 合约两日持仓排名对比
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PositionDiffResponse {
+    pub exchange: String,                    // 交易所代码
+    pub symbol: String,                      // 合约代码
+    pub date1: String,                       // 第一个交易日 YYYYMMDD
+    pub date2: String,                       // 第二个交易日 YYYYMMDD
+    pub members: Vec<PositionDiffMember>,    // 会员持仓对比
+    pub long_entered: Vec<String>,           // 新进入持多单前20的会员
+    pub long_left: Vec<String>,              // 退出持多单前20的会员
+    pub short_entered: Vec<String>,          // 新进入持空单前20的会员
+    pub short_left: Vec<String>,             // 退出持空单前20的会员
+}
+
+/// 持仓对比查询参数
+#[derive(Debug, Deserialize)]
+pub struct PositionDiffQuery {
+    pub date1: String,                       // 第一个交易日 YYYYMMDD
+    pub date2: String,                       // 第二个交易日 YYYYMMDD
+}
 
 /// 郑商所仓单日报数据
 /// 对应 akshare 的 futures_warehouse_receipt_czce() 返回结果
@@ -515,6 +1294,7 @@ pub struct CzceWarehouseReceipt {
     pub warehouse_receipt: Option<i64>,      // 仓单数量
     pub valid_forecast: Option<i64>,         // 有效预报
     pub change: Option<i64>,                 // 增减
+    pub unit: String,                        // 单位（如 张），未知时为空
 }
 
 /// 郑商所仓单日报响应（按品种分组）
@@ -536,6 +1316,7 @@ pub struct DceWarehouseReceipt {
     pub last_receipt: i64,                   // 昨日仓单量（手）
     pub today_receipt: i64,                  // 今日仓单量（手）
     pub change: i64,                         // 增减（手）
+    pub unit: String,                        // 单位（如 手），未知时为空
 }
 
 
@@ -569,6 +1350,7 @@ pub struct GfexWarehouseReceipt {
     pub last_receipt: i64,                   // 昨日仓单量
     pub today_receipt: i64,                  // 今日仓单量
     pub change: i64,                         // 增减
+    pub unit: String,                        // 单位（如 手），未知时为空
 }
 
 /// 广期所仓单日报响应（按品种分组）
@@ -579,6 +1361,35 @@ pub struct GfexWarehouseReceiptResponse {
 }
 
 
+/// 仓单日报汇总参数
+#[derive(Debug, Deserialize)]
+pub struct WarehouseAggregateQuery {
+    /// variety 时按品种汇总各仓库仓单，不返回逐仓库明细
+    pub aggregate: Option<String>,
+}
+
+impl WarehouseAggregateQuery {
+    /// 是否按品种汇总，aggregate 取值无效时返回错误
+    pub fn by_variety(&self) -> anyhow::Result<bool> {
+        match self.aggregate.as_deref().map(str::trim) {
+            None | Some("") => Ok(false),
+            Some("variety") => Ok(true),
+            Some(other) => Err(anyhow::anyhow!("aggregate 只能为 variety，当前为 {}", other)),
+        }
+    }
+}
+
+/// 仓单日报按品种汇总
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WarehouseReceiptTotal {
+    pub variety: String,                     // 品种（上期所为品种名称，其余为品种代码）
+    pub total_today: i64,                    // 今日仓单合计
+    pub total_last: i64,                     // 昨日仓单合计
+    pub total_change: i64,                   // 增减合计
+    pub warehouse_count: usize,              // 参与汇总的仓库数
+}
+
+
 /// 新浪期货持仓排名数据
 /// 对应 akshare 的 futures_hold_pos_sina() 返回结果
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -589,6 +1400,14 @@ pub struct SinaHoldPosition {
     pub change: i64,                         // 比上交易日增减
 }
 
+/// 新浪成交持仓排名查询参数（/futures/hold-pos/sina）
+#[derive(Debug, Deserialize)]
+pub struct SinaHoldPosQuery {
+    pub symbol: Option<String>,              // 数据类型：成交量/多单持仓/空单持仓 或 volume/long/short，默认成交量
+    pub contract: String,                    // 合约代码，如 OI2501
+    pub date: Option<String>,                // 查询日期 YYYYMMDD，默认最近交易日（北京时间）
+}
+
 /// 新浪期货持仓类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SinaHoldPosType {
@@ -598,8 +1417,9 @@ pub enum SinaHoldPosType {
 }
 
 impl SinaHoldPosType {
+    /// 解析持仓类型，支持中文名称和英文名称（英文不区分大小写）
     pub fn from_str(s: &str) -> Option<Self> {
-        match s {
+        match s.trim().to_ascii_lowercase().as_str() {
             "成交量" | "volume" | "vol" => Some(Self::Volume),
             "多单持仓" | "多单" | "long" => Some(Self::Long),
             "空单持仓" | "空单" | "short" => Some(Self::Short),
@@ -623,3 +1443,141 @@ impl SinaHoldPosType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_infos() -> Vec<FuturesInfo> {
+        serde_json::from_str(include_str!("../../fixtures/futures_realtime.json")).unwrap()
+    }
+
+    #[test]
+    fn quote_projection_has_exactly_four_keys() {
+        let info = &sample_infos()[0];
+        let value = serde_json::to_value(FuturesQuote::from(info)).unwrap();
+        let mut keys: Vec<&str> = value.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["change_percent", "price", "symbol", "ts"]);
+        assert_eq!(value["symbol"], "RB2601");
+        assert_eq!(value["price"], 3102.0);
+        assert_eq!(value["ts"], "2025-12-05T14:59:58+08:00");
+    }
+
+    #[test]
+    fn quote_ts_falls_back_to_updated_at() {
+        let mut info = sample_infos().remove(0);
+        info.quote_time = None;
+        assert_eq!(FuturesQuote::from(&info).ts, info.updated_at);
+    }
+
+    #[test]
+    fn fields_query_values() {
+        let query = |fields: Option<&str>| RealtimeFieldsQuery { fields: fields.map(str::to_string) };
+        assert!(!query(None).quote_only().unwrap());
+        assert!(!query(Some("full")).quote_only().unwrap());
+        assert!(query(Some("quote")).quote_only().unwrap());
+        assert!(query(Some("compact")).quote_only().is_err());
+    }
+
+    /// 按指定的 missing_price_as_zero 取值序列化
+    fn to_value_with_flag<T: Serialize>(flag: bool, value: &T) -> serde_json::Value {
+        MISSING_PRICE_AS_ZERO.set(Some(flag));
+        let value = with_price_format(|| serde_json::to_value(value).unwrap());
+        MISSING_PRICE_AS_ZERO.set(None);
+        value
+    }
+
+    #[test]
+    fn missing_price_is_null_unless_compat_flag_is_set() {
+        let mut info = sample_infos().remove(0);
+        info.current_price = None;
+        info.open = Some(0.0);
+        info.high = None;
+
+        let value = to_value_with_flag(false, &info);
+        assert!(value["current_price"].is_null());
+        assert!(value["high"].is_null());
+        assert_eq!(value["open"], 0.0);
+
+        let value = to_value_with_flag(true, &info);
+        assert_eq!(value["current_price"], 0.0);
+        assert_eq!(value["high"], 0.0);
+        assert_eq!(value["open"], 0.0);
+
+        // 默认配置下响应信封中的缺失价格为 null
+        let value = serde_json::to_value(crate::models::ApiResponse::success(vec![info])).unwrap();
+        assert!(value["data"][0]["current_price"].is_null());
+    }
+
+    #[test]
+    fn price_format_is_fixed_for_the_whole_scope() {
+        MISSING_PRICE_AS_ZERO.set(Some(true));
+        let inner = with_price_format(|| with_price_format(|| MISSING_PRICE_AS_ZERO.get()));
+        assert_eq!(inner, Some(true));
+        assert_eq!(MISSING_PRICE_AS_ZERO.get(), Some(true));
+        MISSING_PRICE_AS_ZERO.set(None);
+
+        with_price_format(|| assert!(MISSING_PRICE_AS_ZERO.get().is_some()));
+        assert_eq!(MISSING_PRICE_AS_ZERO.get(), None);
+    }
+
+    #[test]
+    fn query_validate_limit_bounds() {
+        assert!(FuturesQuery::builder().limit(1).build().validate().is_ok());
+        assert!(FuturesQuery::builder().limit(MAX_QUERY_LIMIT).build().validate().is_ok());
+        assert!(FuturesQuery::builder().build().validate().is_ok());
+
+        let err = FuturesQuery::builder().limit(0).build().validate().unwrap_err();
+        assert_eq!(err.to_string(), "limit 必须在 1 到 5000 之间，当前为 0");
+        let err = FuturesQuery::builder().limit(5001).build().validate().unwrap_err();
+        assert_eq!(err.to_string(), "limit 必须在 1 到 5000 之间，当前为 5001");
+    }
+
+    #[test]
+    fn query_validate_rejects_start_after_end() {
+        let query = |start: &str, end: &str| FuturesQuery::builder().start_date(start).end_date(end).build();
+        let err = query("20240301", "20240229").validate().unwrap_err();
+        assert_eq!(err.to_string(), "开始日期 20240301 不能晚于结束日期 20240229");
+        assert!(query("20240229", "20240229").validate().is_ok());
+        assert!(query("20240229", "20240301").validate().is_ok());
+    }
+
+    #[test]
+    fn parse_yyyymmdd_accepts_valid_dates() {
+        let date = parse_yyyymmdd("20240229").unwrap();
+        assert_eq!(date, chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+        assert!(parse_yyyymmdd("20251231").is_ok());
+    }
+
+    #[test]
+    fn parse_yyyymmdd_rejects_impossible_dates() {
+        for value in ["20240230", "20230229", "20241301", "20240100"] {
+            let err = parse_yyyymmdd(value).unwrap_err();
+            assert!(err.to_string().contains("不是有效日期"), "{}: {}", value, err);
+        }
+    }
+
+    #[test]
+    fn parse_yyyymmdd_rejects_wrong_shape() {
+        for value in ["2024021", "202402291", "", "2024-02-2", "2024O229", "２0240229", " 20240229"] {
+            let err = parse_yyyymmdd(value).unwrap_err();
+            assert!(err.to_string().contains("格式错误"), "{}: {}", value, err);
+        }
+        let err = parse_query_date("start_date", Some("2024-02-29")).unwrap_err();
+        assert!(err.to_string().starts_with("start_date 格式错误"), "{}", err);
+        assert_eq!(parse_query_date("start_date", None).unwrap(), None);
+    }
+
+    #[test]
+    fn hold_pos_type_accepts_english_and_chinese_names() {
+        for (english, chinese) in [("volume", "成交量"), ("long", "多单持仓"), ("short", "空单持仓")] {
+            let english = SinaHoldPosType::from_str(english).unwrap();
+            let chinese = SinaHoldPosType::from_str(chinese).unwrap();
+            assert_eq!(english, chinese);
+            assert_eq!(english.table_index(), chinese.table_index());
+        }
+        assert_eq!(SinaHoldPosType::from_str(" VOLUME ").unwrap().table_index(), 2);
+        assert!(SinaHoldPosType::from_str("total").is_none());
+    }
+}
@@ -2,10 +2,13 @@
 //! 
 //! 定义统一的 API 响应格式
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use chrono::Utc;
 use chrono_tz::Asia::Shanghai;
 
+use crate::models::{with_price_format, KlineMeta};
+use crate::services::futures::current_provenance;
+
 /// 获取北京时间（UTC+8）
 fn get_beijing_time() -> chrono::DateTime<chrono_tz::Tz> {
     Utc::now().with_timezone(&Shanghai)
@@ -18,21 +21,72 @@ fn get_beijing_time() -> chrono::DateTime<chrono_tz::Tz> {
 /// - data: 响应数据（成功时有值）
 /// - message: 响应消息
 /// - timestamp: 响应时间戳（北京时间）
+/// - count: 列表接口的记录数（空结果为 0）
+/// - total / truncated / next_page: 列表超过 api.max_response_rows 被分页时的总条数、截断标记和下一页令牌
+/// - meta: K 线接口 with_meta=1 时的品种元数据
+/// - effective_date: 按日查询接口实际使用的日期（date 缺省时为默认交易日）
+/// - warnings: 请求部分被忽略时的提示（如未知的外盘品种代码）
+/// - provenance: 数据来源（成功且访问过上游或缓存时才有）
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     /// 请求是否成功
     pub success: bool,
     /// 响应数据
+    #[serde(serialize_with = "serialize_data", bound(serialize = "T: Serialize"))]
     pub data: Option<T>,
     /// 响应消息
     pub message: String,
     /// 响应时间戳（ISO 8601 格式）
     pub timestamp: String,
+    /// 列表接口的记录数，非列表接口不输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    /// 分页前的总记录数，未分页不输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    /// 列表被截断时为 true，未截断不输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<bool>,
+    /// 下一页令牌，作为 page_token 查询参数传回以获取后续数据
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_page: Option<String>,
+    /// K 线品种元数据（报价单位、最小变动价位、合约乘数），仅在请求 with_meta=1 时输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<KlineMeta>,
+    /// 按日查询接口实际使用的日期（YYYYMMDD），其他接口不输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_date: Option<String>,
+    /// 提示信息（请求部分被忽略时），没有提示时不输出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<Vec<String>>,
+    /// 数据来源
+    #[serde(skip_serializing_if = "Option::is_none", skip_deserializing)]
+    pub provenance: Option<DataProvenance>,
+}
+
+/// 序列化响应数据，整个 data 共用同一个缺失价格输出方式（见 with_price_format）
+fn serialize_data<T: Serialize, S: Serializer>(data: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+    with_price_format(|| data.serialize(serializer))
+}
+
+/// 数据来源
+///
+/// 记录本次响应数据来自哪个上游，便于排查上游数据问题
+#[derive(Debug, Serialize, Clone)]
+pub struct DataProvenance {
+    /// 来源标签，如 sina、100ppi、99qihuo、openctp、gtja、shfe，命中本地缓存时为 cache
+    pub source: &'static str,
+    /// 获取时间（北京时间）
+    pub fetched_at: String,
+    /// 请求的上游 URL（命中缓存时为缓存文件路径）
+    pub url: String,
 }
 
 impl<T> ApiResponse<T> {
     /// 创建成功响应
     /// 
+    /// 自动附加当前请求记录的数据来源
+    /// 
     /// # 参数
     /// - data: 响应数据
     pub fn success(data: T) -> Self {
@@ -41,6 +95,14 @@ impl<T> ApiResponse<T> {
             data: Some(data),
             message: "Success".to_string(),
             timestamp: get_beijing_time().to_rfc3339(),
+            count: None,
+            total: None,
+            truncated: None,
+            next_page: None,
+            meta: None,
+            effective_date: None,
+            warnings: None,
+            provenance: current_provenance(),
         }
     }
 
@@ -54,6 +116,64 @@ impl<T> ApiResponse<T> {
             data: None,
             message,
             timestamp: get_beijing_time().to_rfc3339(),
+            count: None,
+            total: None,
+            truncated: None,
+            next_page: None,
+            meta: None,
+            effective_date: None,
+            warnings: None,
+            provenance: None,
         }
     }
-}
\ No newline at end of file
+
+    /// 附带 K 线品种元数据
+    pub fn with_meta(self, meta: Option<KlineMeta>) -> Self {
+        Self { meta, ..self }
+    }
+
+    /// 附带实际查询日期
+    pub fn with_effective_date(self, date: &str) -> Self {
+        Self { effective_date: Some(date.to_string()), ..self }
+    }
+
+    /// 附带提示信息，列表为空时不输出
+    pub fn with_warnings(self, warnings: Vec<String>) -> Self {
+        Self { warnings: (!warnings.is_empty()).then_some(warnings), ..self }
+    }
+
+    /// 创建携带数据的错误响应
+    /// 
+    /// # 参数
+    /// - message: 错误信息
+    /// - data: 辅助说明数据（如候选项）
+    pub fn error_with_data(message: String, data: T) -> Self {
+        Self {
+            success: false,
+            data: Some(data),
+            message,
+            timestamp: get_beijing_time().to_rfc3339(),
+            count: None,
+            total: None,
+            truncated: None,
+            next_page: None,
+            meta: None,
+            effective_date: None,
+            warnings: None,
+            provenance: None,
+        }
+    }
+}
+
+impl<T> ApiResponse<Vec<T>> {
+    /// 创建列表成功响应
+    ///
+    /// 附带记录数 count；没有数据时同样是成功响应，data 为空数组、count 为 0。
+    /// 超过 api.max_response_rows 的列表由 PaginationMiddleware 分页
+    pub fn success_list(data: Vec<T>) -> Self {
+        Self {
+            count: Some(data.len()),
+            ..Self::success(data)
+        }
+    }
+}
@@ -70,4 +70,165 @@ pub struct StockQuery {
     pub end_date: Option<String>,
     /// 返回数量限制
     pub limit: Option<usize>,
-}
\ No newline at end of file
+    /// 复权方式：none（默认）/ qfq（前复权）/ hfq（后复权）
+    pub adjust: Option<String>,
+}
+
+impl StockQuery {
+    /// 解析日期范围，开始日期不能晚于结束日期
+    pub fn date_range(
+        &self,
+    ) -> anyhow::Result<(Option<chrono::NaiveDate>, Option<chrono::NaiveDate>)> {
+        parse_date_range(self.start_date.as_deref(), self.end_date.as_deref())
+    }
+
+    /// 解析复权方式，未指定时为不复权
+    pub fn adjust_mode(&self) -> anyhow::Result<StockAdjust> {
+        match self.adjust.as_deref().map(str::trim) {
+            None => Ok(StockAdjust::None),
+            Some(value) => StockAdjust::from_str(value).ok_or_else(|| {
+                anyhow::anyhow!("adjust 只能为 none、qfq 或 hfq，当前为 {}", value)
+            }),
+        }
+    }
+}
+
+/// 解析 YYYYMMDD 日期范围，开始日期不能晚于结束日期
+fn parse_date_range(
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> anyhow::Result<(Option<chrono::NaiveDate>, Option<chrono::NaiveDate>)> {
+    let start = super::futures::parse_query_date("start_date", start_date)?;
+    let end = super::futures::parse_query_date("end_date", end_date)?;
+    if let (Some(start), Some(end)) = (start, end) {
+        if start > end {
+            anyhow::bail!("start_date 不能晚于 end_date");
+        }
+    }
+    Ok((start, end))
+}
+
+/// 复权方式，与 akshare 的 adjust 参数一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StockAdjust {
+    /// 不复权
+    None,
+    /// 前复权：价格除以前复权因子，最新价格不变
+    Qfq,
+    /// 后复权：价格乘以后复权因子，上市首日价格不变
+    Hfq,
+}
+
+impl StockAdjust {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "" | "none" => Some(Self::None),
+            "qfq" => Some(Self::Qfq),
+            "hfq" => Some(Self::Hfq),
+            _ => None,
+        }
+    }
+
+    /// 复权因子数据名称（新浪 qfq.js / hfq.js），不复权时为 None
+    pub fn factor_name(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Qfq => Some("qfq"),
+            Self::Hfq => Some("hfq"),
+        }
+    }
+}
+
+/// 复权因子：自 date 起（直到下一个因子日期前）适用的因子
+#[derive(Debug, Deserialize, Clone)]
+pub struct StockAdjustFactor {
+    /// 生效日期（YYYY-MM-DD）
+    pub date: String,
+    /// 复权因子
+    pub factor: f64,
+}
+
+/// 批量历史K线单次请求允许的最大股票数
+pub const MAX_BULK_CODES: usize = 50;
+
+/// 批量历史K线查询参数
+#[derive(Debug, Deserialize)]
+pub struct StockBulkHistoryQuery {
+    /// 股票代码，逗号分隔，如 sh600000,sz000001
+    pub codes: String,
+    /// 开始日期（YYYYMMDD）
+    pub start_date: Option<String>,
+    /// 结束日期（YYYYMMDD）
+    pub end_date: Option<String>,
+    /// 输出格式：ndjson（默认）或 parquet
+    pub format: Option<String>,
+}
+
+/// 批量历史K线输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkFormat {
+    /// 逐行 JSON，流式输出
+    Ndjson,
+    /// Parquet 列式文件，全部获取后一次输出
+    Parquet,
+}
+
+impl StockBulkHistoryQuery {
+    /// 解析输出格式：未指定时为 ndjson，不区分大小写；其余值返回错误
+    pub fn output_format(&self) -> anyhow::Result<BulkFormat> {
+        match self.format.as_deref().map(str::trim) {
+            None | Some("") => Ok(BulkFormat::Ndjson),
+            Some(format) if format.eq_ignore_ascii_case("ndjson") => Ok(BulkFormat::Ndjson),
+            Some(format) if format.eq_ignore_ascii_case("parquet") => Ok(BulkFormat::Parquet),
+            Some(format) => anyhow::bail!("不支持的输出格式: {}，可选 ndjson/parquet", format),
+        }
+    }
+
+    /// 解析股票代码：须为 sh/sz/bj 加 6 位数字，去重后最多 MAX_BULK_CODES 个
+    pub fn code_list(&self) -> anyhow::Result<Vec<String>> {
+        let mut codes: Vec<String> = Vec::new();
+        for code in self.codes.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let code = code.to_lowercase();
+            let valid = code.len() == 8
+                && ["sh", "sz", "bj"].contains(&&code[..2])
+                && code[2..].chars().all(|c| c.is_ascii_digit());
+            if !valid {
+                anyhow::bail!("股票代码格式错误，应为 sh/sz/bj 加 6 位数字: {}", code);
+            }
+            if !codes.contains(&code) {
+                codes.push(code);
+            }
+        }
+
+        if codes.is_empty() {
+            anyhow::bail!("codes 不能为空");
+        }
+        if codes.len() > MAX_BULK_CODES {
+            anyhow::bail!("codes 最多 {} 个，当前为 {}", MAX_BULK_CODES, codes.len());
+        }
+        Ok(codes)
+    }
+
+    /// 解析日期范围，开始日期不能晚于结束日期
+    pub fn date_range(
+        &self,
+    ) -> anyhow::Result<(Option<chrono::NaiveDate>, Option<chrono::NaiveDate>)> {
+        parse_date_range(self.start_date.as_deref(), self.end_date.as_deref())
+    }
+}
+
+/// 批量历史K线 NDJSON 输出行
+///
+/// 每行都带请求的股票代码；某只股票获取失败时输出一行 error
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum StockBulkLine {
+    /// K线记录
+    Record {
+        code: String,
+        #[serde(flatten)]
+        data: StockHistoryData,
+    },
+    /// 获取失败
+    Error { code: String, error: String },
+}
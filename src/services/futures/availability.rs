@@ -0,0 +1,71 @@
+//! 按日发布数据的可用性判断
+//!
+//! 持仓排名、仓单日报等数据由交易所在交易日收盘后（通常为傍晚）发布。
+//! 上游返回空数据时，区分"当日数据尚未发布"和"该日期没有数据"两种情况
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use std::fmt;
+
+use super::common::beijing_now;
+
+/// 请求的是今天的数据，交易所尚未发布
+#[derive(Debug)]
+pub struct DataNotYetAvailable {
+    /// 请求日期 YYYYMMDD
+    pub date: String,
+}
+
+impl fmt::Display for DataNotYetAvailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} 的数据尚未发布，交易所通常在收盘后傍晚发布，请稍后重试", self.date)
+    }
+}
+
+impl std::error::Error for DataNotYetAvailable {}
+
+/// 请求的日期没有数据（非交易日、未来日期或日期格式错误）
+#[derive(Debug)]
+pub struct DataNotFound {
+    /// 请求日期
+    pub date: String,
+    /// 原因
+    pub reason: &'static str,
+}
+
+impl fmt::Display for DataNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} 没有数据: {}", self.date, self.reason)
+    }
+}
+
+impl std::error::Error for DataNotFound {}
+
+/// 检查按日发布的数据是否为空，为空时按请求日期返回对应错误
+///
+/// - 今天（工作日）：DataNotYetAvailable
+/// - 未来日期、周末、更早的日期或无法解析的日期：DataNotFound
+pub fn ensure_published<T>(date: &str, data: Vec<T>) -> anyhow::Result<Vec<T>> {
+    if !data.is_empty() {
+        return Ok(data);
+    }
+
+    let today = beijing_now().date_naive();
+    let reason = match NaiveDate::parse_from_str(date, "%Y%m%d") {
+        Err(_) => "日期格式应为 YYYYMMDD",
+        Ok(day) if day > today => "日期晚于今天",
+        Ok(day) if matches!(day.weekday(), Weekday::Sat | Weekday::Sun) => "非交易日",
+        Ok(day) if day == today => {
+            return Err(DataNotYetAvailable {
+                date: date.to_string(),
+            }
+            .into())
+        }
+        Ok(_) => "可能为非交易日或所选品种无数据",
+    };
+
+    Err(DataNotFound {
+        date: date.to_string(),
+        reason,
+    }
+    .into())
+}
@@ -0,0 +1,83 @@
+//! CPU 密集的文件解析转到阻塞线程池执行
+//!
+//! calamine 读取郑商所 Excel、解压和解析大商所 ZIP 都是同步的 CPU 密集操作，
+//! 直接在异步处理函数中执行会占住 actix 工作线程，同一线程上的其他请求只能等待。
+//! 这些操作统一通过 run_blocking 交给 tokio 的阻塞线程池，
+//! 同时进行的解析任务数由 api.max_parse_jobs 限制（启动时读取），超出的任务排队等待
+
+use anyhow::{anyhow, Result};
+use std::sync::OnceLock;
+use tokio::sync::Semaphore;
+
+use crate::config;
+
+static PARSE_SLOTS: OnceLock<Semaphore> = OnceLock::new();
+
+fn parse_slots() -> &'static Semaphore {
+    PARSE_SLOTS.get_or_init(|| {
+        let max_jobs = config::read(|c| c.api.max_parse_jobs).max(1);
+        log::info!("文件解析并发上限: {}", max_jobs);
+        Semaphore::new(max_jobs)
+    })
+}
+
+/// 在阻塞线程池中执行解析任务，返回任务结果
+///
+/// what 为任务描述，用于任务异常终止时的错误信息
+pub async fn run_blocking<T, F>(what: &str, task: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let _permit = parse_slots()
+        .acquire()
+        .await
+        .expect("文件解析信号量不会被关闭");
+
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|e| anyhow!("{}任务异常终止: {}", what, e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// 单线程运行时上，解析期间其他任务仍在运行
+    #[tokio::test]
+    async fn runtime_thread_stays_responsive_during_parse() {
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let ticker = {
+            let ticks = ticks.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    ticks.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        };
+
+        let rows = run_blocking("测试解析", || {
+            std::thread::sleep(Duration::from_millis(300));
+            Ok(42)
+        })
+        .await
+        .unwrap();
+        ticker.abort();
+
+        assert_eq!(rows, 42);
+        assert!(ticks.load(Ordering::Relaxed) >= 5, "ticks = {}", ticks.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn parse_errors_and_panics_are_returned() {
+        let err = run_blocking::<(), _>("测试解析", || Err(anyhow!("格式错误"))).await.unwrap_err();
+        assert_eq!(err.to_string(), "格式错误");
+
+        let err = run_blocking::<(), _>("测试解析", || panic!("解析崩溃")).await.unwrap_err();
+        assert!(err.to_string().starts_with("测试解析任务异常终止"), "{}", err);
+    }
+}
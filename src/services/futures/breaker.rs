@@ -0,0 +1,355 @@
+//! 上游数据源熔断
+//!
+//! 按上游域名维护熔断状态（关闭 / 打开 / 半开）。同一域名在时间窗口内连续失败达到阈值后熔断，
+//! 冷却期内直接返回 UpstreamUnavailable，不再等待超时；冷却结束后只放行一个探测请求，
+//! 探测成功则恢复，失败则重新进入冷却
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::config;
+
+/// 上游熔断中，请求未发出
+#[derive(Debug)]
+pub struct UpstreamUnavailable {
+    /// 上游域名
+    pub host: String,
+    /// 距离允许探测的剩余秒数
+    pub retry_after_secs: u64,
+}
+
+impl fmt::Display for UpstreamUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "上游 {} 暂不可用（熔断中），请 {} 秒后重试",
+            self.host, self.retry_after_secs
+        )
+    }
+}
+
+impl std::error::Error for UpstreamUnavailable {}
+
+/// 熔断状态
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// 正常放行，记录窗口内的连续失败
+    Closed {
+        failures: u32,
+        window_start: Option<Instant>,
+    },
+    /// 熔断中，直到指定时间
+    Open { until: Instant },
+    /// 冷却结束，已放行一个探测请求
+    HalfOpen { since: Instant },
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: State,
+    /// 累计熔断次数
+    trips: u64,
+    /// 累计被拒绝的请求数
+    rejected: u64,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Self {
+            state: State::Closed { failures: 0, window_start: None },
+            trips: 0,
+            rejected: 0,
+        }
+    }
+}
+
+/// 熔断参数
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    threshold: u32,
+    window: Duration,
+    cooldown: Duration,
+}
+
+impl Settings {
+    fn current() -> Self {
+        config::read(|c| Self {
+            threshold: c.api.breaker_failure_threshold,
+            window: Duration::from_secs(c.api.breaker_window_secs),
+            cooldown: Duration::from_secs(c.api.breaker_cooldown_secs),
+        })
+    }
+}
+
+impl Breaker {
+    /// 请求前检查，冷却结束时转为半开并放行一个探测请求；阈值为 0 时不熔断
+    fn check(&mut self, host: &str, settings: Settings, now: Instant) -> Result<(), UpstreamUnavailable> {
+        if settings.threshold == 0 {
+            return Ok(());
+        }
+        match self.state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } if now >= until => {
+                log::info!("上游 {} 冷却结束，放行探测请求", host);
+                self.state = State::HalfOpen { since: now };
+                Ok(())
+            }
+            // 探测请求被取消未回报结果时，超过冷却时间再放行一个
+            State::HalfOpen { since } if now.duration_since(since) >= settings.cooldown => {
+                self.state = State::HalfOpen { since: now };
+                Ok(())
+            }
+            State::Open { until } => {
+                self.rejected += 1;
+                Err(UpstreamUnavailable {
+                    host: host.to_string(),
+                    retry_after_secs: until.saturating_duration_since(now).as_secs().max(1),
+                })
+            }
+            State::HalfOpen { .. } => {
+                self.rejected += 1;
+                Err(UpstreamUnavailable {
+                    host: host.to_string(),
+                    retry_after_secs: 1,
+                })
+            }
+        }
+    }
+
+    fn succeed(&mut self, host: &str) {
+        if matches!(self.state, State::HalfOpen { .. }) {
+            log::info!("上游 {} 探测成功，熔断恢复", host);
+        }
+        self.state = State::Closed { failures: 0, window_start: None };
+    }
+
+    fn fail(&mut self, host: &str, settings: Settings, now: Instant) {
+        if settings.threshold == 0 {
+            return;
+        }
+        let failures = match self.state {
+            State::Closed { failures, window_start: Some(start) }
+                if now.duration_since(start) <= settings.window =>
+            {
+                self.state = State::Closed {
+                    failures: failures + 1,
+                    window_start: Some(start),
+                };
+                failures + 1
+            }
+            State::Closed { .. } => {
+                self.state = State::Closed { failures: 1, window_start: Some(now) };
+                1
+            }
+            // 探测失败直接重新熔断
+            State::HalfOpen { .. } => settings.threshold,
+            State::Open { .. } => return,
+        };
+
+        if failures >= settings.threshold {
+            self.state = State::Open { until: now + settings.cooldown };
+            self.trips += 1;
+            log::warn!(
+                "🔌 上游 {} 连续失败 {} 次，熔断 {} 秒",
+                host,
+                failures,
+                settings.cooldown.as_secs()
+            );
+        }
+    }
+}
+
+static BREAKERS: OnceLock<Mutex<HashMap<String, Breaker>>> = OnceLock::new();
+
+fn breakers() -> std::sync::MutexGuard<'static, HashMap<String, Breaker>> {
+    BREAKERS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 请求前检查熔断状态，熔断中返回错误
+pub fn check_upstream(host: &str) -> Result<(), UpstreamUnavailable> {
+    let settings = Settings::current();
+    let mut map = breakers();
+    match map.get_mut(host) {
+        Some(breaker) => breaker.check(host, settings, Instant::now()),
+        None => Ok(()),
+    }
+}
+
+/// 记录请求成功，关闭熔断
+pub fn record_success(host: &str) {
+    if let Some(breaker) = breakers().get_mut(host) {
+        breaker.succeed(host);
+    }
+}
+
+/// 记录请求失败，达到阈值时熔断
+pub fn record_failure(host: &str) {
+    // 未启用熔断时不为域名建立状态，避免出现在 /metrics 中
+    let settings = Settings::current();
+    if settings.threshold == 0 {
+        return;
+    }
+
+    breakers()
+        .entry(host.to_string())
+        .or_default()
+        .fail(host, settings, Instant::now());
+}
+
+/// 上游熔断状态
+#[derive(Debug, Serialize)]
+pub struct UpstreamBreakerStats {
+    /// 上游域名
+    pub host: String,
+    /// 状态：closed / open / half_open
+    pub state: &'static str,
+    /// 当前窗口内的连续失败次数
+    pub consecutive_failures: u32,
+    /// 熔断剩余秒数
+    pub open_remaining_secs: u64,
+    /// 累计熔断次数
+    pub trips: u64,
+    /// 累计被拒绝的请求数
+    pub rejected: u64,
+}
+
+/// 获取所有上游的熔断状态
+pub fn upstream_breaker_stats() -> Vec<UpstreamBreakerStats> {
+    let now = Instant::now();
+    let map = breakers();
+    let mut stats: Vec<UpstreamBreakerStats> = map
+        .iter()
+        .map(|(host, breaker)| {
+            let (state, consecutive_failures, open_remaining_secs) = match breaker.state {
+                State::Closed { failures, .. } => ("closed", failures, 0),
+                State::Open { until } => {
+                    ("open", 0, until.saturating_duration_since(now).as_secs())
+                }
+                State::HalfOpen { .. } => ("half_open", 0, 0),
+            };
+            UpstreamBreakerStats {
+                host: host.clone(),
+                state,
+                consecutive_failures,
+                open_remaining_secs,
+                trips: breaker.trips,
+                rejected: breaker.rejected,
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| a.host.cmp(&b.host));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOST: &str = "hq.sinajs.cn";
+
+    fn settings(threshold: u32) -> Settings {
+        Settings {
+            threshold,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(30),
+        }
+    }
+
+    /// 在 start 时刻连续失败 n 次
+    fn failed(n: u32, settings: Settings, start: Instant) -> Breaker {
+        let mut breaker = Breaker::default();
+        for i in 0..n {
+            breaker.fail(HOST, settings, start + Duration::from_secs(i as u64));
+        }
+        breaker
+    }
+
+    #[test]
+    fn opens_after_threshold_within_window() {
+        let start = Instant::now();
+        let mut breaker = failed(2, settings(3), start);
+        assert!(matches!(breaker.state, State::Closed { failures: 2, .. }));
+        assert!(breaker.check(HOST, settings(3), start).is_ok());
+
+        breaker.fail(HOST, settings(3), start + Duration::from_secs(5));
+        assert!(matches!(breaker.state, State::Open { .. }));
+        assert_eq!(breaker.trips, 1);
+    }
+
+    #[test]
+    fn failures_outside_window_start_a_new_count() {
+        let start = Instant::now();
+        let mut breaker = failed(2, settings(3), start);
+        breaker.fail(HOST, settings(3), start + Duration::from_secs(120));
+        assert!(matches!(breaker.state, State::Closed { failures: 1, .. }));
+        assert_eq!(breaker.trips, 0);
+    }
+
+    #[test]
+    fn rejects_while_open_with_retry_after() {
+        let start = Instant::now();
+        let mut breaker = failed(3, settings(3), start);
+        // 第 3 次失败在 start+2s，熔断到 start+32s
+        let err = breaker.check(HOST, settings(3), start + Duration::from_secs(12)).unwrap_err();
+        assert_eq!(err.host, HOST);
+        assert_eq!(err.retry_after_secs, 20);
+        let err = breaker.check(HOST, settings(3), start + Duration::from_millis(31_900)).unwrap_err();
+        assert_eq!(err.retry_after_secs, 1);
+        assert_eq!(breaker.rejected, 2);
+    }
+
+    #[test]
+    fn half_open_allows_exactly_one_probe() {
+        let start = Instant::now();
+        let mut breaker = failed(3, settings(3), start);
+        let after_cooldown = start + Duration::from_secs(40);
+        assert!(breaker.check(HOST, settings(3), after_cooldown).is_ok());
+        assert!(matches!(breaker.state, State::HalfOpen { .. }));
+
+        let err = breaker.check(HOST, settings(3), after_cooldown + Duration::from_secs(1)).unwrap_err();
+        assert_eq!(err.retry_after_secs, 1);
+        // 探测请求一直未回报结果时，再过一个冷却期放行下一个
+        assert!(breaker.check(HOST, settings(3), after_cooldown + Duration::from_secs(30)).is_ok());
+    }
+
+    #[test]
+    fn probe_success_closes() {
+        let start = Instant::now();
+        let mut breaker = failed(3, settings(3), start);
+        assert!(breaker.check(HOST, settings(3), start + Duration::from_secs(40)).is_ok());
+        breaker.succeed(HOST);
+        assert!(matches!(breaker.state, State::Closed { failures: 0, window_start: None }));
+        assert!(breaker.check(HOST, settings(3), start + Duration::from_secs(41)).is_ok());
+    }
+
+    #[test]
+    fn probe_failure_reopens() {
+        let start = Instant::now();
+        let mut breaker = failed(3, settings(3), start);
+        let probe = start + Duration::from_secs(40);
+        assert!(breaker.check(HOST, settings(3), probe).is_ok());
+        breaker.fail(HOST, settings(3), probe);
+        assert_eq!(breaker.trips, 2);
+
+        let err = breaker.check(HOST, settings(3), probe + Duration::from_secs(1)).unwrap_err();
+        assert_eq!(err.retry_after_secs, 29);
+    }
+
+    #[test]
+    fn zero_threshold_disables_breaker() {
+        let start = Instant::now();
+        let breaker = failed(100, settings(0), start);
+        assert!(matches!(breaker.state, State::Closed { failures: 0, .. }));
+        assert_eq!(breaker.trips, 0);
+
+        // 熔断中途关闭后立即放行
+        let mut open = failed(3, settings(3), start);
+        assert!(open.check(HOST, settings(0), start + Duration::from_secs(5)).is_ok());
+    }
+}
@@ -0,0 +1,196 @@
+//! 本地文件缓存
+//!
+//! 配置 storage.cache_dir 后，将解析后的结果按 {cache_dir}/{source}/{date}/{key}.json 落盘。
+//! 只缓存历史日期的数据：历史日期不会再变化，命中缓存时直接读取文件，不再请求上游；
+//! 当天的数据可能尚不完整（如盘中或尚未全部发布），既不读取也不写入缓存。
+//! 未命中时按 source/date/key 合并并发的相同获取，只请求一次上游
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::path::PathBuf;
+
+use super::coalesce::coalesce;
+use super::common::beijing_today_yyyymmdd;
+use super::mock::mock_enabled;
+use super::provenance::record_cache;
+use crate::config;
+
+/// 带文件缓存的数据获取
+///
+/// - source: 数据来源目录名，如 "shfe_rank"
+/// - date: 数据日期 YYYYMMDD，早于今天（北京时间）时才读写缓存
+/// - key: 缓存文件名（不含扩展名），应包含除 date 外决定结果的全部参数
+/// - no_cache: 为 true 时跳过读取，但仍会用最新结果覆盖缓存
+///
+/// 未命中缓存时，同一 source/date/key 的并发请求共享一次 fetch（未配置缓存目录时同样合并）
+pub async fn with_file_cache<T, F, Fut>(
+    source: &str,
+    date: &str,
+    key: &str,
+    no_cache: bool,
+    fetch: F,
+) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    // 模拟数据不写入缓存，也不读取缓存中的真实数据
+    let path = cache_path(source, date, key).filter(|_| !mock_enabled());
+    let flight_key = format!("{}/{}/{}", source, date, key);
+    cached_fetch(path, date, &beijing_today_yyyymmdd(), &flight_key, no_cache, fetch).await
+}
+
+/// 缓存读写：date 早于 today 且 path 非空时才读取和写入 path
+async fn cached_fetch<T, F, Fut>(
+    path: Option<PathBuf>,
+    date: &str,
+    today: &str,
+    flight_key: &str,
+    no_cache: bool,
+    fetch: F,
+) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let Some(path) = path.filter(|_| date < today) else {
+        return coalesce(flight_key, fetch).await;
+    };
+
+    if !no_cache {
+        if let Ok(content) = tokio::fs::read_to_string(&path).await {
+            match serde_json::from_str(&content) {
+                Ok(data) => {
+                    log::debug!("命中文件缓存: {}", path.display());
+                    record_cache(&path.display().to_string());
+                    return Ok(data);
+                }
+                Err(e) => log::warn!("缓存文件 {} 解析失败，重新获取: {}", path.display(), e),
+            }
+        }
+    }
+
+    coalesce(flight_key, || async {
+        let data = fetch().await?;
+        if let Err(e) = write_cache(&path, &data).await {
+            log::warn!("写入缓存文件 {} 失败: {}", path.display(), e);
+        }
+        Ok(data)
+    })
+    .await
+}
+
+/// 计算缓存文件路径，未配置 cache_dir 时返回 None
+fn cache_path(source: &str, date: &str, key: &str) -> Option<PathBuf> {
+    let cache_dir = config::read(|c| c.storage.cache_dir.clone())?;
+    if cache_dir.trim().is_empty() {
+        return None;
+    }
+
+    Some(
+        PathBuf::from(cache_dir)
+            .join(sanitize(source))
+            .join(sanitize(date))
+            .join(format!("{}.json", sanitize(key))),
+    )
+}
+
+/// 将文件名中的非法字符替换为下划线
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+async fn write_cache<T: Serialize>(path: &PathBuf, data: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, serde_json::to_vec(data)?).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn temp_cache_file(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("actix-ak-cache-test-{}", std::process::id()))
+            .join(name)
+            .join("20240102")
+            .join("CU.json")
+    }
+
+    #[tokio::test]
+    async fn past_date_is_written_then_read_back() {
+        let path = temp_cache_file("past");
+        let _ = std::fs::remove_file(&path);
+        let calls = AtomicUsize::new(0);
+        let fetch = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![1, 2, 3])
+        };
+
+        let first: Vec<i32> =
+            cached_fetch(Some(path.clone()), "20240102", "20240103", "test/past/1", false, fetch)
+                .await
+                .unwrap();
+        assert_eq!(first, vec![1, 2, 3]);
+        assert!(path.exists());
+
+        let second: Vec<i32> = cached_fetch(
+            Some(path.clone()),
+            "20240102",
+            "20240103",
+            "test/past/2",
+            false,
+            || async { Err(anyhow::anyhow!("命中缓存时不应请求上游")) },
+        )
+        .await
+        .unwrap();
+        assert_eq!(second, vec![1, 2, 3]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn today_is_neither_written_nor_read() {
+        let path = temp_cache_file("today");
+        let _ = std::fs::remove_file(&path);
+
+        let data: Vec<i32> =
+            cached_fetch(Some(path.clone()), "20240102", "20240102", "test/today/1", false, || async {
+                Ok(vec![1])
+            })
+            .await
+            .unwrap();
+        assert_eq!(data, vec![1]);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn no_cache_refetches_and_overwrites() {
+        let path = temp_cache_file("no_cache");
+        let _ = std::fs::remove_file(&path);
+
+        let _: Vec<i32> =
+            cached_fetch(Some(path.clone()), "20240102", "20240103", "test/no_cache/1", false, || async {
+                Ok(vec![1])
+            })
+            .await
+            .unwrap();
+        let fresh: Vec<i32> =
+            cached_fetch(Some(path.clone()), "20240102", "20240103", "test/no_cache/2", true, || async {
+                Ok(vec![2])
+            })
+            .await
+            .unwrap();
+        assert_eq!(fresh, vec![2]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[2]");
+    }
+}
@@ -0,0 +1,386 @@
+//! 国内期货交易时段
+//!
+//! 按品种组划分日盘和夜盘时段（北京时间），用于判断某一时刻市场是否应处于交易中。
+//! 周末和交易所节假日休市：节假日取内置的各年休市安排（EXCHANGE_HOLIDAYS），
+//! 并可由配置 futures.holidays 补充。节假日前最后一个交易日不开夜盘，
+//! 另可由 futures.no_night_session_dates 指定其他不开夜盘的日期。
+//! 同时按夜盘归属规则计算行情所属的交易日
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Weekday};
+use chrono_tz::Tz;
+
+use crate::config;
+
+/// 交易时段（起止时间，分钟数自 00:00 起算，左闭右开）
+type Session = (u32, u32);
+
+const fn hm(hour: u32, minute: u32) -> u32 {
+    hour * 60 + minute
+}
+
+/// 商品期货日盘
+const COMMODITY_DAY: [Session; 3] = [
+    (hm(9, 0), hm(10, 15)),
+    (hm(10, 30), hm(11, 30)),
+    (hm(13, 30), hm(15, 0)),
+];
+/// 股指期货日盘
+const INDEX_DAY: [Session; 2] = [(hm(9, 30), hm(11, 30)), (hm(13, 0), hm(15, 0))];
+/// 国债期货日盘
+const BOND_DAY: [Session; 2] = [(hm(9, 30), hm(11, 30)), (hm(13, 0), hm(15, 15))];
+
+const INDEX_VARIETIES: [&str; 4] = ["IF", "IC", "IH", "IM"];
+const BOND_VARIETIES: [&str; 4] = ["T", "TF", "TS", "TL"];
+
+/// 夜盘至 02:30（黄金、白银、原油）
+const NIGHT_0230_VARIETIES: &[&str] = &["AU", "AG", "SC"];
+/// 夜盘至 01:00（有色金属）
+const NIGHT_0100_VARIETIES: &[&str] = &[
+    "CU", "AL", "ZN", "PB", "NI", "SN", "SS", "BC", "AO", "AD",
+];
+/// 夜盘至 23:00（黑色、能化、农产品等）
+const NIGHT_2300_VARIETIES: &[&str] = &[
+    // 上期所 / 能源中心
+    "RB", "HC", "BU", "RU", "FU", "SP", "BR", "NR", "LU",
+    // 大商所
+    "A", "B", "M", "Y", "P", "C", "CS", "RR", "J", "JM", "I", "L", "V", "PP", "EG", "EB", "PG",
+    "BZ",
+    // 郑商所
+    "SR", "CF", "CY", "TA", "OI", "RM", "MA", "FG", "ZC", "SA", "PF", "PX", "SH", "PR",
+];
+
+/// 品种的夜盘结束时间（分钟数，次日凌晨结束的以 24:00 之后计），无夜盘时为 None
+fn night_session_end(variety: &str) -> Option<u32> {
+    if NIGHT_0230_VARIETIES.contains(&variety) {
+        Some(hm(26, 30))
+    } else if NIGHT_0100_VARIETIES.contains(&variety) {
+        Some(hm(25, 0))
+    } else if NIGHT_2300_VARIETIES.contains(&variety) {
+        Some(hm(23, 0))
+    } else {
+        None
+    }
+}
+
+/// 品种的日盘时段
+fn day_sessions(variety: &str) -> &'static [Session] {
+    if INDEX_VARIETIES.contains(&variety) {
+        &INDEX_DAY
+    } else if BOND_VARIETIES.contains(&variety) {
+        &BOND_DAY
+    } else {
+        &COMMODITY_DAY
+    }
+}
+
+/// 从合约代码中取出大写品种代码，兼容 nf_ / CFF_ 前缀
+pub(crate) fn variety_of(symbol: &str) -> String {
+    let upper = symbol.trim().to_uppercase();
+    let code = upper
+        .strip_prefix("NF_")
+        .or_else(|| upper.strip_prefix("CFF_"))
+        .unwrap_or(&upper);
+    code.chars().take_while(|c| c.is_ascii_alphabetic()).collect()
+}
+
+/// 交易所节假日休市安排中落在工作日的日期（YYYYMMDD，升序），
+/// 按上期所、大商所、郑商所、中金所、广期所共同的年度休市通知整理
+const EXCHANGE_HOLIDAYS: &[&str] = &[
+    // 2024
+    "20240101", "20240209", "20240212", "20240213", "20240214", "20240215", "20240216",
+    "20240404", "20240405", "20240501", "20240502", "20240503", "20240610", "20240916",
+    "20240917", "20241001", "20241002", "20241003", "20241004", "20241007",
+    // 2025
+    "20250101", "20250128", "20250129", "20250130", "20250131", "20250203", "20250204",
+    "20250404", "20250501", "20250502", "20250505", "20250602", "20251001", "20251002",
+    "20251003", "20251006", "20251007", "20251008",
+    // 2026
+    "20260101", "20260102", "20260216", "20260217", "20260218", "20260219", "20260220",
+    "20260223", "20260406", "20260501", "20260504", "20260505", "20260619", "20260925",
+    "20261001", "20261002", "20261005", "20261006", "20261007",
+];
+
+fn is_weekday(weekday: Weekday) -> bool {
+    !matches!(weekday, Weekday::Sat | Weekday::Sun)
+}
+
+/// 是否为交易所节假日（内置休市安排或配置的 futures.holidays）
+fn is_holiday(date: NaiveDate) -> bool {
+    let key = date.format("%Y%m%d").to_string();
+    EXCHANGE_HOLIDAYS.binary_search(&key.as_str()).is_ok() || config::is_configured_holiday(&key)
+}
+
+/// 是否为交易日：工作日且不是节假日
+fn is_trading_day(date: NaiveDate) -> bool {
+    is_weekday(date.weekday()) && !is_holiday(date)
+}
+
+/// 某日晚间是否开夜盘：交易日、下一个工作日不是节假日（节假日前最后一个交易日不开夜盘），
+/// 且不在配置的取消夜盘日期中
+fn has_night_session(date: NaiveDate) -> bool {
+    let mut next_weekday = date + Duration::days(1);
+    while !is_weekday(next_weekday.weekday()) {
+        next_weekday += Duration::days(1);
+    }
+    is_trading_day(date)
+        && !is_holiday(next_weekday)
+        && !config::is_no_night_session_date(&date.format("%Y%m%d").to_string())
+}
+
+/// 判断某一时刻（北京时间）该合约所属品种是否应处于交易中
+///
+/// 日盘在交易日开放（周末和节假日休市）；夜盘从开夜盘的交易日晚间 21:00 开始，跨零点的部分归属前一晚
+pub fn is_market_open(symbol: &str, at: DateTime<Tz>) -> bool {
+    let variety = variety_of(symbol);
+    let minute = at.hour() * 60 + at.minute();
+    let date = at.date_naive();
+
+    if is_trading_day(date)
+        && day_sessions(&variety)
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&minute))
+    {
+        return true;
+    }
+
+    let Some(night_end) = night_session_end(&variety) else {
+        return false;
+    };
+    let night_start = hm(21, 0);
+
+    // 当晚夜盘
+    if (night_start..night_end.min(hm(24, 0))).contains(&minute) && has_night_session(date) {
+        return true;
+    }
+    // 前一晚夜盘跨零点部分
+    let after_midnight = minute + hm(24, 0);
+    after_midnight < night_end && has_night_session(date - Duration::days(1))
+}
+
+/// 夜盘集合竞价开始时间，此后的行情归属下一个交易日
+const NIGHT_AUCTION_START: u32 = hm(20, 55);
+/// 夜盘最晚结束时间，此前的凌晨行情属于前一晚夜盘
+const NIGHT_LATEST_END: u32 = hm(2, 30);
+/// 日盘开始时间
+const DAY_START: u32 = hm(9, 0);
+
+/// 某日之后的下一个交易日（跳过周末和节假日）
+pub(super) fn next_trading_day(date: NaiveDate) -> NaiveDate {
+    let mut next = date + Duration::days(1);
+    while !is_trading_day(next) {
+        next += Duration::days(1);
+    }
+    next
+}
+
+/// 不早于某日的第一个交易日（跳过周末和节假日）
+pub(super) fn upcoming_trading_day(date: NaiveDate) -> NaiveDate {
+    if is_trading_day(date) {
+        date
+    } else {
+        next_trading_day(date)
+    }
+}
+
+/// 不晚于某日的最近一个交易日（跳过周末和节假日）
+pub(super) fn latest_trading_day(date: NaiveDate) -> NaiveDate {
+    let mut day = date;
+    while !is_trading_day(day) {
+        day -= Duration::days(1);
+    }
+    day
+}
+
+/// 某一时刻（北京时间）按日发布数据的默认查询日期：当天为交易日时取当天，
+/// 周末和节假日回退到之前最近的交易日
+///
+/// 与 trading_date 不同，夜盘时段不归属下一个交易日，因为下一个交易日的日终数据尚未发布
+pub fn latest_trading_date(at: DateTime<Tz>) -> NaiveDate {
+    latest_trading_day(at.date_naive())
+}
+
+/// 某日晚间的行情所属交易日：开夜盘时为下一个交易日，否则为当日日盘所属的交易日
+fn evening_trading_day(date: NaiveDate) -> NaiveDate {
+    if has_night_session(date) {
+        next_trading_day(date)
+    } else {
+        latest_trading_day(date)
+    }
+}
+
+/// 计算某一时刻（北京时间）的行情所属交易日
+///
+/// 夜盘（含 20:55 起的集合竞价）归属下一个交易日，周五夜盘归属下周一；凌晨 02:30 前为前一晚夜盘的延续；
+/// 02:30 至 09:00 归属即将开始的日盘；未开夜盘的晚上（周末、节假日及节假日前一晚）
+/// 归属之前最近的交易日
+pub fn trading_date(at: DateTime<Tz>) -> NaiveDate {
+    let minute = at.hour() * 60 + at.minute();
+    let date = at.date_naive();
+
+    if minute >= NIGHT_AUCTION_START {
+        evening_trading_day(date)
+    } else if minute <= NIGHT_LATEST_END {
+        evening_trading_day(date - Duration::days(1))
+    } else if minute < DAY_START {
+        upcoming_trading_day(date)
+    } else {
+        latest_trading_day(date)
+    }
+}
+
+/// 判断行情是否过期：市场应处于交易中（且阈值时间之前也在交易中）而行情时间超过阈值
+///
+/// 休市期间的旧行情属于正常情况，不视为过期；刚开盘时上一时段的行情同样不视为过期
+pub fn is_quote_stale(
+    symbol: &str,
+    quote: DateTime<Tz>,
+    now: DateTime<Tz>,
+    threshold_secs: i64,
+) -> bool {
+    if (now - quote).num_seconds() <= threshold_secs {
+        return false;
+    }
+    is_market_open(symbol, now) && is_market_open(symbol, now - Duration::seconds(threshold_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use chrono_tz::Asia::Shanghai;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    /// 北京时间 "YYYY-MM-DD HH:MM"
+    fn at(s: &str) -> DateTime<Tz> {
+        let naive = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        Shanghai.from_local_datetime(&naive).unwrap()
+    }
+
+    #[test]
+    fn holiday_table_is_sorted_weekdays() {
+        assert!(EXCHANGE_HOLIDAYS.windows(2).all(|w| w[0] < w[1]));
+        for day in EXCHANGE_HOLIDAYS {
+            let day = NaiveDate::parse_from_str(day, "%Y%m%d").unwrap();
+            assert!(is_weekday(day.weekday()), "{} 为周末", day);
+        }
+    }
+
+    #[test]
+    fn weekday_holiday_steps_back_to_last_trading_day() {
+        // 2025-01-01 周三元旦
+        assert_eq!(latest_trading_date(at("2025-01-01 10:00")), date("2024-12-31"));
+        // 2024-06-10 周一端午，跨过周末回到周五
+        assert_eq!(latest_trading_date(at("2024-06-10 10:00")), date("2024-06-07"));
+        assert_eq!(latest_trading_date(at("2024-06-11 10:00")), date("2024-06-11"));
+        // 周末
+        assert_eq!(latest_trading_date(at("2024-06-16 10:00")), date("2024-06-14"));
+    }
+
+    #[test]
+    fn first_trading_day_after_national_day() {
+        assert_eq!(next_trading_day(date("2024-09-30")), date("2024-10-08"));
+        assert_eq!(upcoming_trading_day(date("2024-10-03")), date("2024-10-08"));
+        assert_eq!(latest_trading_date(at("2024-10-07 16:00")), date("2024-09-30"));
+        // 长假前最后一个交易日不开夜盘，晚间行情归属当日
+        assert_eq!(trading_date(at("2024-09-30 21:30")), date("2024-09-30"));
+        // 节后首日凌晨归属当日日盘
+        assert_eq!(trading_date(at("2024-10-08 08:00")), date("2024-10-08"));
+        // 节后首日夜盘归属下一个交易日
+        assert_eq!(trading_date(at("2024-10-08 21:30")), date("2024-10-09"));
+    }
+
+    #[test]
+    fn trading_date_session_boundaries() {
+        // 2025-03-12 周三
+        assert_eq!(trading_date(at("2025-03-12 14:00")), date("2025-03-12"));
+        assert_eq!(trading_date(at("2025-03-12 20:54")), date("2025-03-12"));
+        assert_eq!(trading_date(at("2025-03-12 20:55")), date("2025-03-13"), "夜盘集合竞价");
+        assert_eq!(trading_date(at("2025-03-12 22:00")), date("2025-03-13"));
+        assert_eq!(trading_date(at("2025-03-13 00:30")), date("2025-03-13"));
+        assert_eq!(trading_date(at("2025-03-13 02:30")), date("2025-03-13"));
+        assert_eq!(trading_date(at("2025-03-13 02:31")), date("2025-03-13"));
+        assert_eq!(trading_date(at("2025-03-13 08:59")), date("2025-03-13"));
+    }
+
+    #[test]
+    fn friday_night_belongs_to_monday() {
+        // 2025-03-14 周五
+        assert_eq!(trading_date(at("2025-03-14 14:00")), date("2025-03-14"));
+        assert_eq!(trading_date(at("2025-03-14 22:00")), date("2025-03-17"));
+        assert_eq!(trading_date(at("2025-03-15 01:30")), date("2025-03-17"));
+        // 周末白天和周日晚间没有行情，归属周五
+        assert_eq!(trading_date(at("2025-03-15 10:00")), date("2025-03-14"));
+        assert_eq!(trading_date(at("2025-03-16 22:00")), date("2025-03-14"));
+        assert_eq!(trading_date(at("2025-03-17 08:00")), date("2025-03-17"));
+    }
+
+    #[test]
+    fn night_without_night_session_stays_on_same_day() {
+        // 2024-06-07 周五，下周一端午休市，当晚不开夜盘
+        assert!(!has_night_session(date("2024-06-07")));
+        assert_eq!(trading_date(at("2024-06-07 22:00")), date("2024-06-07"));
+        assert_eq!(trading_date(at("2024-06-08 01:00")), date("2024-06-07"));
+        assert_eq!(trading_date(at("2024-06-11 08:00")), date("2024-06-11"));
+    }
+
+    #[test]
+    fn market_open_follows_product_sessions() {
+        // 2024-06-12 周三
+        assert!(is_market_open("RB2410", at("2024-06-12 09:30")));
+        assert!(!is_market_open("RB2410", at("2024-06-12 10:20")), "商品上午小节休息");
+        assert!(!is_market_open("RB2410", at("2024-06-12 12:00")), "午休");
+        assert!(!is_market_open("IF2406", at("2024-06-12 09:15")), "股指 09:30 开盘");
+        assert!(is_market_open("T2409", at("2024-06-12 15:10")), "国债收盘 15:15");
+        assert!(!is_market_open("IF2406", at("2024-06-12 15:10")));
+
+        // 夜盘
+        assert!(is_market_open("RB2410", at("2024-06-12 22:30")));
+        assert!(!is_market_open("RB2410", at("2024-06-12 23:30")), "黑色夜盘至 23:00");
+        assert!(is_market_open("CU2407", at("2024-06-13 00:30")), "有色夜盘至 01:00");
+        assert!(is_market_open("AU2408", at("2024-06-13 02:00")), "黄金夜盘至 02:30");
+        assert!(!is_market_open("IF2406", at("2024-06-12 21:30")), "股指无夜盘");
+        // 周五夜盘跨零点的部分在周六凌晨
+        assert!(is_market_open("AU2408", at("2024-06-15 01:00")));
+        assert!(!is_market_open("AU2408", at("2024-06-15 10:00")));
+    }
+
+    #[test]
+    fn holidays_close_day_and_night_sessions() {
+        // 2024-10-01 至 10-07 国庆休市
+        assert!(!is_market_open("RB2501", at("2024-10-02 10:00")));
+        assert!(!is_market_open("IF2410", at("2024-10-02 10:00")));
+        // 节前最后一个交易日（09-30）日盘照常、不开夜盘
+        assert!(is_market_open("RB2501", at("2024-09-30 14:00")));
+        assert!(!is_market_open("RB2501", at("2024-09-30 21:30")));
+        assert!(!is_market_open("AU2412", at("2024-10-01 01:00")));
+        // 节后首日恢复
+        assert!(is_market_open("RB2501", at("2024-10-08 09:30")));
+    }
+
+    #[test]
+    fn quote_staleness_only_during_sessions() {
+        let threshold = 300;
+        let stale = |symbol: &str, quote: &str, now: &str| {
+            is_quote_stale(symbol, at(quote), at(now), threshold)
+        };
+
+        // 交易中行情超过阈值未更新
+        assert!(stale("RB2410", "2024-06-12 09:30", "2024-06-12 09:40"));
+        assert!(!stale("RB2410", "2024-06-12 09:38", "2024-06-12 09:40"));
+        // 午休时上午收盘前的行情
+        assert!(!stale("RB2410", "2024-06-12 11:29", "2024-06-12 12:30"));
+        // 刚开盘时上一时段的行情
+        assert!(!stale("RB2410", "2024-06-12 11:29", "2024-06-12 13:32"));
+        // 夜盘交易中与夜盘收盘后
+        assert!(stale("RB2410", "2024-06-12 21:30", "2024-06-12 22:00"));
+        assert!(!stale("RB2410", "2024-06-12 23:00", "2024-06-13 00:30"));
+        // 无夜盘品种的晚间
+        assert!(!stale("IF2406", "2024-06-12 15:00", "2024-06-12 22:00"));
+        // 节假日日盘时段
+        assert!(!stale("RB2501", "2024-09-30 15:00", "2024-10-02 10:00"));
+        assert!(!stale("IF2410", "2024-09-30 15:00", "2024-10-02 10:00"));
+    }
+}
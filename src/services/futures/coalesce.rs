@@ -0,0 +1,187 @@
+//! 并发请求合并（single-flight）
+//!
+//! 相同签名的获取在上一次完成前再次到达时，等待同一次上游获取的结果，而不是各自请求上游，
+//! 降低缓存未命中时的突发请求量和被封禁的风险。只合并同时进行中的请求，完成后立即移除，
+//! 不额外缓存结果。
+//!
+//! 结果以 JSON 形式在等待者之间共享；错误以 SharedError 共享，保留原始错误链，
+//! 状态码映射与单独请求时一致。数据来源（provenance）同样复制给每个等待者
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::OnceCell;
+
+use super::provenance::{current_provenance, restore_provenance};
+use crate::models::{with_price_format, DataProvenance};
+
+/// 多个等待者共享的错误，source 指向原始错误，error_status 等按错误链判断的逻辑不受影响
+#[derive(Debug, Clone)]
+pub struct SharedError(Arc<anyhow::Error>);
+
+impl fmt::Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for SharedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&**self.0)
+    }
+}
+
+/// 一次获取的结果：序列化后的数据和数据来源
+type Outcome = std::result::Result<(serde_json::Value, Option<DataProvenance>), SharedError>;
+
+type Flight = Arc<OnceCell<Outcome>>;
+
+static IN_FLIGHT: OnceLock<Mutex<HashMap<String, Flight>>> = OnceLock::new();
+
+/// 累计发起的获取次数
+static FETCHES: AtomicU64 = AtomicU64::new(0);
+/// 累计合并到进行中获取的请求数
+static COALESCED: AtomicU64 = AtomicU64::new(0);
+
+fn in_flight() -> std::sync::MutexGuard<'static, HashMap<String, Flight>> {
+    IN_FLIGHT
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 合并相同 key 的并发获取
+///
+/// 没有进行中的获取时执行 fetch；已有时等待其结果。发起获取的请求被取消（如客户端断开）时，
+/// 由仍在等待的请求接替执行自己的 fetch
+pub async fn coalesce<T, F, Fut>(key: &str, fetch: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let (flight, joined) = {
+        let mut map = in_flight();
+        match map.get(key) {
+            Some(flight) => (flight.clone(), true),
+            None => {
+                let flight: Flight = Arc::new(OnceCell::new());
+                map.insert(key.to_string(), flight.clone());
+                (flight, false)
+            }
+        }
+    };
+    if joined {
+        COALESCED.fetch_add(1, Ordering::Relaxed);
+        log::debug!("合并进行中的请求: {}", key);
+    }
+
+    let outcome = flight
+        .get_or_init(|| async {
+            FETCHES.fetch_add(1, Ordering::Relaxed);
+            let data = fetch()
+                .await
+                .and_then(|data| with_price_format(|| serde_json::to_value(data)).map_err(Into::into))
+                .map_err(|e| SharedError(Arc::new(e)))?;
+            Ok((data, current_provenance()))
+        })
+        .await
+        .clone();
+
+    {
+        let mut map = in_flight();
+        if map.get(key).is_some_and(|current| Arc::ptr_eq(current, &flight)) {
+            map.remove(key);
+        }
+    }
+
+    let (data, provenance) = outcome?;
+    restore_provenance(provenance);
+    Ok(serde_json::from_value(data)?)
+}
+
+/// 请求合并统计
+#[derive(Debug, Serialize)]
+pub struct CoalesceStats {
+    /// 当前进行中的获取数
+    pub in_flight: usize,
+    /// 累计发起的获取次数
+    pub fetches: u64,
+    /// 累计合并到进行中获取的请求数（节省的上游请求数）
+    pub coalesced: u64,
+}
+
+/// 获取请求合并统计
+pub fn coalesce_stats() -> CoalesceStats {
+    CoalesceStats {
+        in_flight: in_flight().len(),
+        fetches: FETCHES.load(Ordering::Relaxed),
+        coalesced: COALESCED.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn concurrent_identical_requests_fetch_once() {
+        let calls = AtomicUsize::new(0);
+        let fetch = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(vec![1, 2, 3])
+        };
+
+        let results = futures::future::join_all(
+            (0..8).map(|_| coalesce::<Vec<i32>, _, _>("test:coalesce_once", fetch)),
+        )
+        .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        for result in results {
+            assert_eq!(result.unwrap(), [1, 2, 3]);
+        }
+        assert!(!in_flight().contains_key("test:coalesce_once"));
+    }
+
+    #[derive(Debug)]
+    struct Marker;
+
+    impl fmt::Display for Marker {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "上游失败")
+        }
+    }
+
+    impl std::error::Error for Marker {}
+
+    #[tokio::test]
+    async fn shared_error_keeps_original_chain() {
+        let calls = AtomicUsize::new(0);
+        let fetch = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Err::<i32, _>(anyhow::Error::new(Marker))
+        };
+
+        let results = futures::future::join_all(
+            (0..4).map(|_| coalesce::<i32, _, _>("test:coalesce_error", fetch)),
+        )
+        .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        for result in results {
+            let err = result.unwrap_err();
+            assert!(err.chain().any(|cause| cause.is::<Marker>()));
+            assert_eq!(err.to_string(), "上游失败");
+        }
+    }
+}
@@ -1,8 +1,22 @@
 //! 公共常量和辅助函数
 
-use chrono::Utc;
-use chrono_tz::Asia::Shanghai;
+use anyhow::{anyhow, Result};
+use calamine::{open_workbook_auto_from_rs, Data, Range, Reader};
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::{Asia::Shanghai, Tz};
 use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, USER_AGENT};
+use reqwest::{Client, ClientBuilder};
+use std::io::Cursor;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use super::blocking::run_blocking;
+use super::calendar::{is_quote_stale, latest_trading_date};
+use super::expiry::exchange_for_variety;
+use super::limiter::LimitedSend;
+use crate::config;
+use crate::models::FuturesVarietyName;
 
 // ==================== 新浪期货 API 常量 ====================
 
@@ -29,6 +43,9 @@ pub const SINA_HOLD_POS_API: &str =
 
 // ==================== 其他数据源常量 ====================
 
+/// 东方财富批量实时行情 API（新浪实时行情的备用数据源）
+pub const EASTMONEY_FUTURES_REALTIME_API: &str = "https://push2.eastmoney.com/api/qt/ulist.np/get";
+
 /// OpenCTP期货交易费用API
 pub const OPENCTP_FEES_URL: &str = "http://openctp.cn/fees.html";
 /// 九期网期货手续费API
@@ -54,9 +71,364 @@ pub const CZCE_VOL_RANK_URL: &str = "http://www.czce.com.cn/cn/DFSStaticFiles/Fu
 pub const DCE_VOL_RANK_URL: &str =
     "http://www.dce.com.cn/dcereport/publicweb/dailystat/memberDealPosi/batchDownload";
 
+/// 郑商所品种代码（合约月份为 3 位，如 MA505）
+pub const CZCE_VARIETIES: &[&str] = &[
+    "WH", "PM", "CF", "SR", "TA", "OI", "RI", "MA", "ME", "FG", "RS", "RM", "ZC", "JR", "LR",
+    "SF", "SM", "WT", "TC", "GN", "RO", "ER", "SRX", "SRY", "WSX", "WSY", "CY", "AP", "UR",
+    "CJ", "SA", "PK", "PF", "PX", "SH", "PR",
+];
+
+// ==================== 上游拒绝访问 ====================
+
+/// 上游因反爬虫机制拒绝访问（如大商所返回 412）
+#[derive(Debug)]
+pub struct UpstreamBlocked {
+    /// 数据源名称
+    pub source: &'static str,
+    /// 上游返回的状态码
+    pub status: u16,
+    /// 处理建议
+    pub suggestion: &'static str,
+}
+
+impl std::fmt::Display for UpstreamBlocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}API访问被拒绝({})，该交易所有反爬虫机制。\n建议: {}",
+            self.source, self.status, self.suggestion
+        )
+    }
+}
+
+impl std::error::Error for UpstreamBlocked {}
+
+/// 跳过证书校验的请求目标不在 http.allow_insecure_hosts 白名单中
+#[derive(Debug)]
+pub struct InsecureHostRejected {
+    /// 请求的域名
+    pub host: String,
+}
+
+impl std::fmt::Display for InsecureHostRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "拒绝以跳过证书校验的方式请求 {}：该域名不在 http.allow_insecure_hosts 中",
+            self.host
+        )
+    }
+}
+
+impl std::error::Error for InsecureHostRejected {}
+
+/// 郑商所对不存在的文件（如非交易日）返回 200 状态码的 HTML 页面，而不是 Excel 文件
+#[derive(Debug)]
+pub struct CzceFileMissing;
+
+impl std::fmt::Display for CzceFileMissing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "郑商所返回了 HTML 页面而不是数据文件，该日期可能没有数据")
+    }
+}
+
+impl std::error::Error for CzceFileMissing {}
+
+// ==================== HTTP 客户端 ====================
+
+/// 共享 HTTP 客户端（复用连接池）
+static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+static INSECURE_HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// 默认请求头（User-Agent 取自配置）
+pub fn default_headers() -> HeaderMap {
+    let user_agent = config::read(|c| c.api.user_agent.clone());
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(&user_agent).unwrap_or_else(|e| {
+            log::warn!("配置的 User-Agent 无效: {}，使用默认值", e);
+            HeaderValue::from_static("Mozilla/5.0")
+        }),
+    );
+    headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+    headers.insert(
+        ACCEPT_LANGUAGE,
+        HeaderValue::from_static("zh-CN,zh;q=0.9,en;q=0.8"),
+    );
+    headers
+}
+
+/// 带默认请求头、超时和连接池配置的客户端构建器
+///
+/// 需要 cookie、忽略证书等特殊设置时在此基础上追加
+pub fn http_client_builder() -> ClientBuilder {
+    let (timeout, connect_timeout, pool_max_idle, pool_idle_timeout) = config::read(|c| {
+        (c.api.timeout_secs, c.api.connect_timeout_secs, c.http.pool_max_idle_per_host, c.http.pool_idle_timeout_secs)
+    });
+    Client::builder()
+        .default_headers(default_headers())
+        .timeout(Duration::from_secs(timeout))
+        .connect_timeout(Duration::from_secs(connect_timeout))
+        .pool_max_idle_per_host(pool_max_idle)
+        .pool_idle_timeout(Duration::from_secs(pool_idle_timeout))
+}
+
+/// 上游请求的超时类别
+///
+/// 共享客户端的总超时为 api.timeout_secs，按类别在单个请求上覆盖
+#[derive(Debug, Clone, Copy)]
+pub enum RequestTimeout {
+    /// 实时行情、品种列表等轻量请求
+    Realtime,
+    /// 交易所 Excel / ZIP 文件下载
+    Download,
+}
+
+impl RequestTimeout {
+    /// 当前配置下该类别的超时时间，配置为 0 时使用 api.timeout_secs
+    pub fn duration(self) -> Duration {
+        config::read(|c| self.duration_in(&c.api))
+    }
+
+    /// 指定 API 配置下该类别的超时时间
+    fn duration_in(self, api: &config::ApiConfig) -> Duration {
+        let secs = match self {
+            RequestTimeout::Realtime => api.realtime_timeout_secs,
+            RequestTimeout::Download => api.download_timeout_secs,
+        };
+        Duration::from_secs(if secs == 0 { api.timeout_secs } else { secs })
+    }
+}
+
+/// 获取共享 HTTP 客户端
+pub fn http_client() -> Client {
+    HTTP_CLIENT
+        .get_or_init(|| {
+            http_client_builder().build().unwrap_or_else(|e| {
+                log::error!("构建 HTTP 客户端失败: {}，使用默认客户端", e);
+                Client::new()
+            })
+        })
+        .clone()
+}
+
+/// 获取跳过证书校验的共享 HTTP 客户端，url 为将要请求的地址
+///
+/// 国泰君安、99期货网、九期网、郑商所等站点使用自签名或不完整的证书链。只有 url 的域名
+/// 列在 http.allow_insecure_hosts 中时才返回该客户端，否则返回 InsecureHostRejected；
+/// 重定向到白名单以外的域名同样被拒绝，避免新增数据源时悄悄关闭证书校验
+pub fn insecure_http_client(url: &str) -> Result<Client> {
+    let allowlist = config::read(|c| c.http.allow_insecure_hosts.clone());
+    ensure_insecure_host_allowed(url, &allowlist)?;
+
+    Ok(INSECURE_HTTP_CLIENT
+        .get_or_init(|| {
+            http_client_builder()
+                .danger_accept_invalid_certs(true)
+                .redirect(insecure_redirect_policy(|| config::read(|c| c.http.allow_insecure_hosts.clone())))
+                .build()
+                .unwrap_or_else(|e| {
+                    log::error!("构建 HTTP 客户端失败: {}，使用默认客户端", e);
+                    Client::new()
+                })
+        })
+        .clone())
+}
+
+/// 跳过证书校验的客户端的重定向策略：每一跳的目标都须在 allowlist 返回的白名单中
+fn insecure_redirect_policy(allowlist: fn() -> Vec<String>) -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= 10 {
+            attempt.error("重定向次数过多")
+        } else if let Err(e) = ensure_insecure_host_allowed(attempt.url().as_str(), &allowlist()) {
+            attempt.error(e)
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// 校验跳过证书校验的请求目标：url 的域名须与 allowlist 中的某一项完全相同（不区分大小写）
+pub(crate) fn ensure_insecure_host_allowed(url: &str, allowlist: &[String]) -> Result<()> {
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_lowercase))
+        .ok_or_else(|| anyhow!("无效的请求地址: {}", url))?;
+    if allowlist.iter().any(|allowed| allowed.trim().eq_ignore_ascii_case(&host)) {
+        Ok(())
+    } else {
+        Err(InsecureHostRejected { host }.into())
+    }
+}
+
+/// 下载郑商所 Excel 文件并读取第一个工作表
+///
+/// url_stem 为不含扩展名的地址。先按日期阈值推断的格式（prefer_xlsx）请求，
+/// 下载或解析失败时自动改用另一种扩展名重试。两种扩展名都返回 HTML 页面时
+/// 返回 CzceFileMissing，由调用方按请求日期转为 404 / 425
+pub async fn fetch_czce_sheet(
+    client: &Client,
+    url_stem: &str,
+    prefer_xlsx: bool,
+) -> Result<Range<Data>> {
+    fetch_czce_sheet_with(url_stem, prefer_xlsx, |url| async move {
+        download_first_sheet(client, &url).await
+    })
+    .await
+}
+
+/// fetch_czce_sheet 的扩展名回退逻辑，download 按完整地址下载并解析第一个工作表
+async fn fetch_czce_sheet_with<F, Fut>(url_stem: &str, prefer_xlsx: bool, download: F) -> Result<Range<Data>>
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = Result<Range<Data>>>,
+{
+    let extensions = if prefer_xlsx {
+        ["xlsx", "xls"]
+    } else {
+        ["xls", "xlsx"]
+    };
+
+    let mut last_err = anyhow!("未尝试下载");
+    let mut all_html = true;
+    for ext in extensions {
+        let url = format!("{}.{}", url_stem, ext);
+        log::debug!("请求郑商所数据 URL: {}", url);
+
+        match download(url.clone()).await {
+            Ok(range) => {
+                log::info!("郑商所数据使用 .{} 格式解析成功: {}", ext, url);
+                return Ok(range);
+            }
+            Err(e) => {
+                log::warn!("郑商所数据 .{} 格式获取失败: {}", ext, e);
+                all_html &= e.is::<CzceFileMissing>();
+                last_err = e;
+            }
+        }
+    }
+
+    if all_html {
+        return Err(CzceFileMissing.into());
+    }
+    Err(last_err)
+}
+
+/// 响应内容是否为 HTML 页面（跳过 BOM 和开头空白后以 <!DOCTYPE 或 <html 开头，不区分大小写）
+fn is_html_page(bytes: &[u8]) -> bool {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    let start = bytes.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(bytes.len());
+    let head = &bytes[start..bytes.len().min(start + 9)];
+    head.eq_ignore_ascii_case(b"<!doctype")
+        || head.get(..5).is_some_and(|h| h.eq_ignore_ascii_case(b"<html"))
+}
+
+/// 下载 Excel 文件并读取第一个工作表
+async fn download_first_sheet(client: &Client, url: &str) -> Result<Range<Data>> {
+    let response = client
+        .get(url)
+        .timeout(RequestTimeout::Download.duration())
+        .send_limited()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("HTTP {}，可能是非交易日", response.status()));
+    }
+
+    sheet_from_bytes(response.bytes().await?).await
+}
+
+/// 解析下载到的文件内容；HTML 页面在交给 Excel 解析前即返回 CzceFileMissing
+async fn sheet_from_bytes<B: AsRef<[u8]> + Send + 'static>(bytes: B) -> Result<Range<Data>> {
+    if is_html_page(bytes.as_ref()) {
+        return Err(CzceFileMissing.into());
+    }
+    run_blocking("Excel 解析", move || read_first_sheet(bytes.as_ref())).await
+}
+
+/// 读取 Excel 文件的第一个工作表（同步解析，需在阻塞线程池中调用）
+fn read_first_sheet(bytes: &[u8]) -> Result<Range<Data>> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(bytes))
+        .map_err(|e| anyhow!("打开Excel文件失败: {}", e))?;
+
+    let first_sheet = workbook
+        .sheet_names()
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow!("Excel文件没有工作表"))?;
+
+    workbook
+        .worksheet_range(&first_sheet)
+        .map_err(|e| anyhow!("读取工作表失败: {}", e))
+}
+
 /// 获取北京时间字符串（ISO 8601 格式，带+08:00时区）
 pub fn get_beijing_time() -> String {
-    Utc::now().with_timezone(&Shanghai).to_rfc3339()
+    beijing_now().to_rfc3339()
+}
+
+/// 获取当前北京时间
+pub fn beijing_now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&Shanghai)
+}
+
+/// 获取北京时间当天日期（YYYYMMDD）
+///
+/// 所有"默认今天"的日期参数统一使用该函数，避免凌晨时段按 UTC 取到前一天
+pub fn beijing_today_yyyymmdd() -> String {
+    beijing_now().format("%Y%m%d").to_string()
+}
+
+/// 获取北京时间当天对应的最近交易日（YYYYMMDD），周末回退到周五
+///
+/// 持仓排名、仓单日报、现货价格等按日发布数据的 date 参数缺省时统一使用该函数
+pub fn beijing_trading_day_yyyymmdd() -> String {
+    latest_trading_date(beijing_now()).format("%Y%m%d").to_string()
+}
+
+/// 校验按日循环请求的日期跨度
+///
+/// 跨度超过 api.max_range_days 时返回错误，避免一次请求触发成千上万次上游请求
+pub fn check_date_range(start: NaiveDate, end: NaiveDate) -> Result<()> {
+    let max_days = config::read(|c| c.api.max_range_days);
+    let days = (end - start).num_days();
+    if days > max_days {
+        return Err(anyhow!(
+            "日期跨度 {} 天超过上限 {} 天，请缩小查询范围，或使用离线/批量工具获取长周期数据",
+            days,
+            max_days
+        ));
+    }
+    Ok(())
+}
+
+/// 解析上游行情时间（HH:MM:SS 或 HHMMSS），结合北京日期返回 (行情时间, 是否过期)，
+/// 行情时间为北京时间
+///
+/// 行情时间晚于当前时间时视为前一交易日（如夜盘跨零点），归到前一天；
+/// 是否过期按合约所属品种的交易时段判断，休市期间不视为过期
+pub fn parse_quote_time(time_str: &str, symbol: &str) -> Option<(DateTime<Tz>, bool)> {
+    parse_quote_time_at(time_str, symbol, beijing_now())
+}
+
+/// parse_quote_time 的实现，now 为当前北京时间
+fn parse_quote_time_at(time_str: &str, symbol: &str, now: DateTime<Tz>) -> Option<(DateTime<Tz>, bool)> {
+    let time_str = time_str.trim();
+    let time = NaiveTime::parse_from_str(time_str, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time_str, "%H%M%S"))
+        .ok()?;
+
+    let mut quote = Shanghai
+        .from_local_datetime(&now.date_naive().and_time(time))
+        .single()?;
+    if quote > now {
+        quote -= ChronoDuration::days(1);
+    }
+
+    let threshold = config::read(|c| c.api.stale_threshold_secs);
+    let stale = is_quote_stale(symbol, quote, now, threshold);
+    Some((quote, stale))
 }
 
 /// 从合约代码中提取品种代码
@@ -86,6 +458,224 @@ pub fn extract_contract_month(contract: &str) -> String {
     }
 }
 
+/// 解析带符号的整数，如 "-1,234" -> -1234
+///
+/// 仅去除千分位逗号并保留负号；空字符串或单独的 "-" 占位符视为 0
+pub fn parse_signed_int(s: &str) -> i64 {
+    let cleaned = s.trim().replace(',', "");
+    if cleaned.is_empty() || cleaned == "-" {
+        return 0;
+    }
+    cleaned
+        .parse::<i64>()
+        .or_else(|_| cleaned.parse::<f64>().map(|f| f as i64))
+        .unwrap_or(0)
+}
+
+/// 宽松解析成交量、持仓量等计数字段
+///
+/// - 空字符串返回 None
+/// - 兼容 "12345.0" 这类带小数的写法（四舍五入）
+/// - 负数按 0 处理，不丢弃整个字段
+///
+/// 解析失败或需要修正时记录 debug 日志，便于发现上游格式变化
+pub fn parse_lenient_u64(field: &str, s: &str) -> Option<u64> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    if let Ok(v) = s.parse::<u64>() {
+        return Some(v);
+    }
+    match s.parse::<f64>() {
+        Ok(v) if v.is_finite() && v < 0.0 => {
+            log::debug!("字段 {} 为负数 {}，按 0 处理", field, s);
+            Some(0)
+        }
+        Ok(v) if v.is_finite() => {
+            if v.fract() != 0.0 {
+                log::debug!("字段 {} 含小数 {}，四舍五入", field, s);
+            }
+            Some(v.round() as u64)
+        }
+        _ => {
+            log::debug!("字段 {} 无法解析为整数: {:?}", field, s);
+            None
+        }
+    }
+}
+
+/// 宽松解析价格等浮点字段，空字符串返回 None，解析失败时记录 debug 日志
+pub fn parse_lenient_f64(field: &str, s: &str) -> Option<f64> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    match s.parse::<f64>() {
+        Ok(v) if v.is_finite() => Some(v),
+        _ => {
+            log::debug!("字段 {} 无法解析为数值: {:?}", field, s);
+            None
+        }
+    }
+}
+
+/// 解析价格字段，字段缺失或为空时返回 None（表示未知，而不是 0）
+pub fn price_field(field: &str, token: Option<&str>) -> Option<f64> {
+    token.and_then(|s| parse_lenient_f64(field, s))
+}
+
+/// 计算涨跌额和涨跌幅（百分比），最新价或昨结算未知、昨结算为 0 时均为 0
+pub fn price_change(price: Option<f64>, prev_settlement: Option<f64>) -> (f64, f64) {
+    match (price, prev_settlement) {
+        (Some(price), Some(prev)) if prev != 0.0 => {
+            let change = price - prev;
+            (change, change / prev * 100.0)
+        }
+        _ => (0.0, 0.0),
+    }
+}
+
+/// 解析 CSV 文本为记录列表
+///
+/// 去除开头的 UTF-8 BOM；由 csv crate 处理双引号包裹的字段（字段内可含逗号、换行，"" 表示一个引号）。
+/// 各行字段数可以不同，空行和全为空白的行跳过
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes())
+        .records()
+        .filter_map(|record| match record {
+            Ok(record) => Some(record),
+            Err(e) => {
+                log::warn!("跳过无法解析的 CSV 行: {}", e);
+                None
+            }
+        })
+        .map(|record| record.iter().map(str::to_string).collect::<Vec<String>>())
+        .filter(|fields| fields.iter().any(|f| !f.trim().is_empty()))
+        .collect()
+}
+
+/// 解析失败时错误信息中保留的响应开头字符数
+const PARSE_SNIPPET_CHARS: usize = 500;
+
+/// 为上游响应的解析错误附加数据源名称、请求 URL 和响应开头片段
+///
+/// 上游字段变化导致解析失败时，诊断信息随错误进入日志和接口响应，而不只打印到标准输出；
+/// 原始错误保留在错误链中，error_status 的状态码映射不受影响
+pub fn parse_context<T>(result: Result<T>, source: &str, url: &str, body: &str) -> Result<T> {
+    result.map_err(|e| {
+        let snippet: String = body.chars().take(PARSE_SNIPPET_CHARS).collect();
+        let message = format!("解析{}响应失败（{}）: {}；响应开头: {}", source, url, e, snippet);
+        e.context(message)
+    })
+}
+
+/// 解析上游 JSON 响应，失败时错误附带数据源、URL 和响应开头片段
+pub fn parse_json(source: &str, url: &str, text: &str) -> Result<serde_json::Value> {
+    parse_context(serde_json::from_str(text).map_err(Into::into), source, url, text)
+}
+
+/// 解析可能被 JSONP 包裹的 JSON 响应
+///
+/// 优先按纯 JSON 解析；失败时去除开头的 /* ... */ 注释、callback(...) 或 var x= 前缀
+/// 以及结尾的分号后再解析
+pub fn parse_json_lenient(text: &str) -> Result<serde_json::Value> {
+    let text = text.trim();
+    if let Ok(value) = serde_json::from_str(text) {
+        return Ok(value);
+    }
+
+    let body = strip_jsonp(text).ok_or_else(|| {
+        let preview: String = text.chars().take(50).collect();
+        anyhow!("响应既不是 JSON 也不是 JSONP: {}", preview)
+    })?;
+    serde_json::from_str(body).map_err(|e| anyhow!("解析JSON失败: {}", e))
+}
+
+/// 取出 JSONP 包裹中的 JSON 部分
+fn strip_jsonp(text: &str) -> Option<&str> {
+    let mut text = text.trim();
+    if text.starts_with("/*") {
+        text = text[text.find("*/")? + 2..].trim_start();
+    }
+    let text = text.trim_end_matches(';').trim_end();
+
+    let body = match text.strip_suffix(')') {
+        Some(call) => &call[call.find('(')? + 1..],
+        None => text.split_once('=')?.1,
+    };
+    Some(body.trim())
+}
+
+/// 保留上游原始价格字符串（去除首尾空白），空字符串返回 None
+pub fn price_token(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// 调试输出中单个原始单元格保留的最大字符数
+const MAX_RAW_CELL_CHARS: usize = 200;
+
+/// 调试输出中每行原始单元格的总字节数上限
+const MAX_RAW_ROW_BYTES: usize = 2048;
+
+/// 截取表格行的原始单元格用于 ?debug_raw=1 调试输出
+///
+/// 单元格超过 MAX_RAW_CELL_CHARS 个字符时截断并以 … 结尾；累计超过 MAX_RAW_ROW_BYTES 后
+/// 其余单元格替换为一个 … 占位
+pub fn bounded_raw_cells(cells: &[String]) -> Vec<String> {
+    let mut raw = Vec::with_capacity(cells.len());
+    let mut total = 0;
+    for cell in cells {
+        let cell = match cell.char_indices().nth(MAX_RAW_CELL_CHARS) {
+            Some((end, _)) => format!("{}…", &cell[..end]),
+            None => cell.clone(),
+        };
+        total += cell.len();
+        if total > MAX_RAW_ROW_BYTES {
+            raw.push("…".to_string());
+            break;
+        }
+        raw.push(cell);
+    }
+    raw
+}
+
+/// 解析带单位后缀的数值，如 "10%"、"3.01元"、"0.5‱"、"1,000"
+///
+/// 只去除单位和千分位逗号，数值保持原始口径（百分比不换算为小数）；无法解析时返回 None
+pub fn parse_unit_number(s: &str) -> Option<f64> {
+    let cleaned = s
+        .trim()
+        .trim_end_matches(['%', '‱', '元'])
+        .trim()
+        .replace(',', "");
+    if cleaned.is_empty() || cleaned == "-" || cleaned == "--" {
+        return None;
+    }
+    cleaned.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// 解析费率或保证金率并换算为小数，如 "10%" → 0.1、"0.5‱" → 0.00005
+///
+/// 带 % 的按百分比、带 ‱ 的按万分比换算；不带单位的数值按 OpenCTP 费用表的口径视为已是小数
+/// （如 "0.0001"、保证金率 "0.1"）。无法解析时返回 None
+pub fn parse_rate_fraction(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let divisor = if s.ends_with('‱') {
+        10000.0
+    } else if s.ends_with('%') {
+        100.0
+    } else {
+        1.0
+    };
+    parse_unit_number(s).map(|v| v / divisor)
+}
+
 /// 解析基差字符串，如 "-176-0.22%" 或 "80.03%"
 pub fn parse_basis_string(s: &str) -> (f64, f64) {
     let s = s.trim();
@@ -129,110 +719,667 @@ pub fn parse_basis_string(s: &str) -> (f64, f64) {
     (basis, 0.0)
 }
 
+/// 中文品种名称与英文代码对照表
+///
+/// 同一品种的别名（如 "菜油"、"LLDPE"）各占一行
+const CHINESE_NAME_CODES: &[(&str, &str)] = &[
+    // 上海期货交易所
+    ("铜", "CU"),
+    ("螺纹钢", "RB"),
+    ("锌", "ZN"),
+    ("铝", "AL"),
+    ("黄金", "AU"),
+    ("线材", "WR"),
+    ("天然橡胶", "RU"),
+    ("铅", "PB"),
+    ("白银", "AG"),
+    ("沥青", "BU"),
+    ("石油沥青", "BU"),
+    ("热轧卷板", "HC"),
+    ("镍", "NI"),
+    ("锡", "SN"),
+    ("燃料油", "FU"),
+    ("不锈钢", "SS"),
+    ("纸浆", "SP"),
+    ("氧化铝", "AO"),
+    ("丁二烯橡胶", "BR"),
+    ("铸造铝合金", "AD"),
+    // 大连商品交易所
+    ("豆一", "A"),
+    ("豆二", "B"),
+    ("豆粕", "M"),
+    ("豆油", "Y"),
+    ("玉米", "C"),
+    ("玉米淀粉", "CS"),
+    ("棕榈油", "P"),
+    ("鸡蛋", "JD"),
+    ("聚乙烯", "L"),
+    ("LLDPE", "L"),
+    ("聚氯乙烯", "V"),
+    ("PVC", "V"),
+    ("聚丙烯", "PP"),
+    ("PP", "PP"),
+    ("焦炭", "J"),
+    ("焦煤", "JM"),
+    ("铁矿石", "I"),
+    ("乙二醇", "EG"),
+    ("苯乙烯", "EB"),
+    ("液化石油气", "PG"),
+    ("LPG", "PG"),
+    ("生猪", "LH"),
+    // 郑州商品交易所
+    ("白糖", "SR"),
+    ("棉花", "CF"),
+    ("PTA", "TA"),
+    ("菜籽油", "OI"),
+    ("菜油", "OI"),
+    ("菜籽油OI", "OI"),
+    ("菜籽粕", "RM"),
+    ("菜粕", "RM"),
+    ("甲醇", "MA"),
+    ("甲醇MA", "MA"),
+    ("玻璃", "FG"),
+    ("动力煤", "ZC"),
+    ("硅铁", "SF"),
+    ("锰硅", "SM"),
+    ("苹果", "AP"),
+    ("红枣", "CJ"),
+    ("尿素", "UR"),
+    ("纯碱", "SA"),
+    ("短纤", "PF"),
+    ("涤纶短纤", "PF"),
+    ("花生", "PK"),
+    ("菜籽", "RS"),
+    ("棉纱", "CY"),
+    ("粳稻", "JR"),
+    ("晚籼稻", "LR"),
+    ("早籼稻", "RI"),
+    ("强麦", "WH"),
+    ("强麦WH", "WH"),
+    ("普麦", "PM"),
+    ("烧碱", "SH"),
+    // 上海国际能源交易中心
+    ("原油", "SC"),
+    ("20号胶", "NR"),
+    ("低硫燃料油", "LU"),
+    ("国际铜", "BC"),
+    // 广州期货交易所
+    ("工业硅", "SI"),
+    ("碳酸锂", "LC"),
+    // 中国金融期货交易所
+    ("沪深300", "IF"),
+    ("上证50", "IH"),
+    ("中证500", "IC"),
+    ("中证1000", "IM"),
+    ("2年期国债", "TS"),
+    ("5年期国债", "TF"),
+    ("10年期国债", "T"),
+    ("30年期国债", "TL"),
+    ("PX", "PX"),
+];
+
+/// 品种名称中可去掉的交易所简称前缀（如 "沪铜"、"郑棉花"）
+const NAME_PREFIXES: &[&str] = &["沪", "郑", "连", "大连"];
+
+/// 品种名称中可去掉的后缀（如 "螺纹钢期货"、"豆粕主力"）
+const NAME_SUFFIXES: &[&str] = &["期货", "主力", "连续"];
+
 /// 中文品种名称到英文代码的映射
+///
+/// 先精确匹配；不中时去掉末尾大写代码（如 100ppi 的 "纯碱SA"）、括号注释（如 "棉花(郑)"）
+/// 以及已知的交易所前缀和后缀后再精确匹配；仍不中时按子串匹配，取名称中包含的最长已知品种名
+/// （如 "沪铜主力连续" 取 "铜"，"国际铜期货主连" 取 "国际铜" 而不是 "铜"），不包含任何已知品种名时返回 None
 pub fn chinese_to_english(name: &str) -> Option<&'static str> {
-    let result = match name {
-        // 上海期货交易所
-        "铜" => Some("CU"),
-        "螺纹钢" => Some("RB"),
-        "锌" => Some("ZN"),
-        "铝" => Some("AL"),
-        "黄金" => Some("AU"),
-        "线材" => Some("WR"),
-        "天然橡胶" => Some("RU"),
-        "铅" => Some("PB"),
-        "白银" => Some("AG"),
-        "沥青" | "石油沥青" => Some("BU"),
-        "热轧卷板" => Some("HC"),
-        "镍" => Some("NI"),
-        "锡" => Some("SN"),
-        "燃料油" => Some("FU"),
-        "不锈钢" => Some("SS"),
-        "纸浆" => Some("SP"),
-        "氧化铝" => Some("AO"),
-        "丁二烯橡胶" => Some("BR"),
-        // 大连商品交易所
-        "豆一" => Some("A"),
-        "豆二" => Some("B"),
-        "豆粕" => Some("M"),
-        "豆油" => Some("Y"),
-        "玉米" => Some("C"),
-        "玉米淀粉" => Some("CS"),
-        "棕榈油" => Some("P"),
-        "鸡蛋" => Some("JD"),
-        "聚乙烯" | "LLDPE" => Some("L"),
-        "聚氯乙烯" | "PVC" => Some("V"),
-        "聚丙烯" | "PP" => Some("PP"),
-        "焦炭" => Some("J"),
-        "焦煤" => Some("JM"),
-        "铁矿石" => Some("I"),
-        "乙二醇" => Some("EG"),
-        "苯乙烯" => Some("EB"),
-        "液化石油气" | "LPG" => Some("PG"),
-        "生猪" => Some("LH"),
-        // 郑州商品交易所
-        "白糖" => Some("SR"),
-        "棉花" => Some("CF"),
-        "PTA" => Some("TA"),
-        "菜籽油" | "菜油" | "菜籽油OI" => Some("OI"),
-        "菜籽粕" | "菜粕" => Some("RM"),
-        "甲醇" | "甲醇MA" => Some("MA"),
-        "玻璃" => Some("FG"),
-        "动力煤" => Some("ZC"),
-        "硅铁" => Some("SF"),
-        "锰硅" => Some("SM"),
-        "苹果" => Some("AP"),
-        "红枣" => Some("CJ"),
-        "尿素" => Some("UR"),
-        "纯碱" => Some("SA"),
-        "短纤" | "涤纶短纤" => Some("PF"),
-        "花生" => Some("PK"),
-        "菜籽" => Some("RS"),
-        "棉纱" => Some("CY"),
-        "粳稻" => Some("JR"),
-        "晚籼稻" => Some("LR"),
-        "早籼稻" => Some("RI"),
-        "强麦" | "强麦WH" => Some("WH"),
-        "普麦" => Some("PM"),
-        "烧碱" => Some("SH"),
-        // 上海国际能源交易中心
-        "原油" => Some("SC"),
-        "20号胶" => Some("NR"),
-        "低硫燃料油" => Some("LU"),
-        "国际铜" => Some("BC"),
-        // 广州期货交易所
-        "工业硅" => Some("SI"),
-        "碳酸锂" => Some("LC"),
-        // 中国金融期货交易所
-        "沪深300" => Some("IF"),
-        "上证50" => Some("IH"),
-        "中证500" => Some("IC"),
-        "中证1000" => Some("IM"),
-        "2年期国债" => Some("TS"),
-        "5年期国债" => Some("TF"),
-        "10年期国债" => Some("T"),
-        "30年期国债" => Some("TL"),
-        "PX" => Some("PX"),
-        _ => None,
+    let name = name.trim();
+    let exact = |n: &str| {
+        CHINESE_NAME_CODES
+            .iter()
+            .find(|(known, _)| *known == n)
+            .map(|(_, code)| *code)
     };
 
-    if result.is_some() {
-        return result;
+    if let Some(code) = exact(name) {
+        return Some(code);
+    }
+
+    let mut stripped = name.trim_end_matches(|c: char| c.is_ascii_uppercase());
+    if let Some(pos) = stripped.find(['(', '（']) {
+        if stripped.ends_with([')', '）']) {
+            stripped = &stripped[..pos];
+        }
+    }
+    for suffix in NAME_SUFFIXES {
+        stripped = stripped.strip_suffix(suffix).unwrap_or(stripped);
+    }
+    let stripped = stripped.trim();
+    if stripped.is_empty() {
+        return None;
+    }
+    if let Some(code) = exact(stripped) {
+        return Some(code);
+    }
+
+    if let Some(code) = NAME_PREFIXES
+        .iter()
+        .filter_map(|prefix| stripped.strip_prefix(prefix))
+        .find_map(exact)
+    {
+        return Some(code);
+    }
+
+    // 最长匹配优先，长度相同时取对照表中靠前的一项
+    CHINESE_NAME_CODES
+        .iter()
+        .rev()
+        .filter(|(known, _)| name.contains(known))
+        .max_by_key(|(known, _)| known.chars().count())
+        .map(|(_, code)| *code)
+}
+
+/// 品种对照表的交易所分组顺序
+const VARIETY_MAP_EXCHANGES: &[&str] = &["SHFE", "INE", "DCE", "CZCE", "GFEX", "CFFEX"];
+
+/// 中文品种名称与英文代码对照表（即 chinese_to_english 使用的映射）
+///
+/// 按交易所分组排列，组内保持对照表顺序；q 非空时只保留中文名称包含 q，
+/// 或英文代码包含 q（不区分大小写）的项，既可按中文查代码也可按代码查中文名
+pub fn variety_name_map(q: Option<&str>) -> Vec<FuturesVarietyName> {
+    let q = q.map(str::trim).filter(|q| !q.is_empty());
+    let mut entries: Vec<FuturesVarietyName> = CHINESE_NAME_CODES
+        .iter()
+        .filter(|(cn, en)| {
+            q.is_none_or(|q| cn.contains(q) || en.to_uppercase().contains(&q.to_uppercase()))
+        })
+        .map(|(cn, en)| FuturesVarietyName {
+            cn: cn.to_string(),
+            en: en.to_string(),
+            exchange: exchange_for_variety(en).unwrap_or("UNKNOWN").to_string(),
+        })
+        .collect();
+    entries.sort_by_key(|entry| {
+        VARIETY_MAP_EXCHANGES
+            .iter()
+            .position(|exchange| *exchange == entry.exchange)
+            .unwrap_or(VARIETY_MAP_EXCHANGES.len())
+    });
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_cells_are_bounded_per_cell_and_per_row() {
+        let cells = vec!["铜".to_string(), "10".to_string()];
+        assert_eq!(bounded_raw_cells(&cells), cells);
+
+        let long = bounded_raw_cells(&["x".repeat(500)]);
+        assert_eq!(long[0].chars().count(), MAX_RAW_CELL_CHARS + 1);
+        assert!(long[0].ends_with('…'));
+
+        let row = bounded_raw_cells(&vec!["y".repeat(500); 30]);
+        assert_eq!(row.last().unwrap(), "…");
+        assert!(row.iter().map(String::len).sum::<usize>() <= MAX_RAW_ROW_BYTES + "…".len());
+    }
+
+    /// 本地慢速上游：每个连接等待 delay 后返回固定响应，返回其地址
+    async fn slow_upstream(delay: Duration) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let _ = socket.read(&mut buf).await;
+                    tokio::time::sleep(delay).await;
+                    let _ = socket
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                        .await;
+                });
+            }
+        });
+        format!("http://{}/", addr)
+    }
+
+    #[tokio::test]
+    async fn slow_upstream_trips_realtime_timeout_but_not_download() {
+        let api = config::ApiConfig {
+            timeout_secs: 30,
+            realtime_timeout_secs: 1,
+            download_timeout_secs: 5,
+            ..Default::default()
+        };
+        let url = slow_upstream(Duration::from_millis(1500)).await;
+        let client = Client::new();
+
+        let err = client
+            .get(&url)
+            .timeout(RequestTimeout::Realtime.duration_in(&api))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(err.is_timeout(), "{}", err);
+
+        let response = client
+            .get(&url)
+            .timeout(RequestTimeout::Download.duration_in(&api))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+
+    #[test]
+    fn zero_category_timeout_falls_back_to_global() {
+        let api = config::ApiConfig {
+            timeout_secs: 12,
+            realtime_timeout_secs: 0,
+            download_timeout_secs: 60,
+            ..Default::default()
+        };
+        assert_eq!(RequestTimeout::Realtime.duration_in(&api), Duration::from_secs(12));
+        assert_eq!(RequestTimeout::Download.duration_in(&api), Duration::from_secs(60));
+
+        let defaults = config::ApiConfig::default();
+        assert!(RequestTimeout::Realtime.duration_in(&defaults) < RequestTimeout::Download.duration_in(&defaults));
+    }
+
+    #[test]
+    fn beijing_today_is_eight_digit_beijing_date() {
+        let before = (Utc::now() + chrono::Duration::hours(8)).date_naive();
+        let today = beijing_today_yyyymmdd();
+        let after = (Utc::now() + chrono::Duration::hours(8)).date_naive();
+
+        assert_eq!(today.len(), 8);
+        assert!(today.bytes().all(|b| b.is_ascii_digit()), "{}", today);
+        let date = NaiveDate::parse_from_str(&today, "%Y%m%d").unwrap();
+        // 北京时间固定为 UTC+8，调用前后跨零点时取其一
+        assert!(date == before || date == after, "{} {} {}", today, before, after);
+    }
+
+    #[test]
+    fn check_date_range_allows_exactly_max_days() {
+        let max_days = config::read(|c| c.api.max_range_days);
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert!(check_date_range(start, start).is_ok());
+        assert!(check_date_range(start, start + chrono::Duration::days(max_days)).is_ok());
+
+        let err = check_date_range(start, start + chrono::Duration::days(max_days + 1)).unwrap_err();
+        assert!(err.to_string().contains(&format!("日期跨度 {} 天超过上限 {} 天", max_days + 1, max_days)), "{}", err);
+    }
+
+    #[test]
+    fn parse_unit_number_strips_units() {
+        assert_eq!(parse_unit_number("3.01元"), Some(3.01));
+        assert_eq!(parse_unit_number("1,000"), Some(1000.0));
+        assert_eq!(parse_unit_number("--"), None);
+        assert_eq!(parse_unit_number(""), None);
+    }
+
+    #[test]
+    fn parse_rate_fraction_converts_units() {
+        assert_eq!(parse_rate_fraction("10%"), Some(0.1));
+        assert_eq!(parse_rate_fraction("0.5‱"), Some(0.00005));
+        assert_eq!(parse_rate_fraction("0.0001"), Some(0.0001));
+        assert_eq!(parse_rate_fraction(" 12% "), Some(0.12));
+        assert_eq!(parse_rate_fraction("-"), None);
+    }
+
+    #[test]
+    fn parse_lenient_u64_tolerates_format_drift() {
+        assert_eq!(parse_lenient_u64("open_interest", "12345.0"), Some(12345));
+        assert_eq!(parse_lenient_u64("open_interest", "12345"), Some(12345));
+        assert_eq!(parse_lenient_u64("open_interest", ""), None);
+        assert_eq!(parse_lenient_u64("open_interest", "  "), None);
+        assert_eq!(parse_lenient_u64("volume", "12345.6"), Some(12346));
+        assert_eq!(parse_lenient_u64("volume", "-3"), Some(0));
+        assert_eq!(parse_lenient_u64("volume", "abc"), None);
+    }
+
+    #[test]
+    fn parse_lenient_f64_keeps_sign() {
+        assert_eq!(parse_lenient_f64("change", "-12.5"), Some(-12.5));
+        assert_eq!(parse_lenient_f64("change", "12345.0"), Some(12345.0));
+        assert_eq!(parse_lenient_f64("change", ""), None);
+        assert_eq!(parse_lenient_f64("change", "NaN"), None);
+    }
+
+    #[test]
+    fn parse_signed_int_handles_thousands_and_placeholders() {
+        assert_eq!(parse_signed_int("-1,234"), -1234);
+        assert_eq!(parse_signed_int("1,234,567"), 1234567);
+        assert_eq!(parse_signed_int(" 56 "), 56);
+        assert_eq!(parse_signed_int("-12.0"), -12);
+        // 交易所用 "-" 或空白表示无数据
+        assert_eq!(parse_signed_int("-"), 0);
+        assert_eq!(parse_signed_int(""), 0);
+        assert_eq!(parse_signed_int("abc"), 0);
+    }
+
+    #[test]
+    fn parse_lenient_u64_rejects_placeholders() {
+        assert_eq!(parse_lenient_u64("volume", "-"), None);
+        assert_eq!(parse_lenient_u64("volume", "-1234.0"), Some(0));
+    }
+
+    #[test]
+    fn strip_jsonp_unwraps_callbacks_and_assignments() {
+        assert_eq!(strip_jsonp("var x=([{\"d\":1}]);"), Some("[{\"d\":1}]"));
+        assert_eq!(strip_jsonp("cb({\"a\":1})"), Some("{\"a\":1}"));
+        assert_eq!(strip_jsonp("/*<script>location.href='//sina.com';</script>*/\ncb({\"a\":1});"), Some("{\"a\":1}"));
+        assert_eq!(strip_jsonp("var data = {\"a\":1};"), Some("{\"a\":1}"));
+        assert_eq!(strip_jsonp("not a wrapper"), None);
+    }
+
+    #[test]
+    fn parse_json_lenient_accepts_json_and_jsonp() {
+        let expected = serde_json::json!({"a": [1, 2]});
+        assert_eq!(parse_json_lenient("{\"a\":[1,2]}").unwrap(), expected);
+        assert_eq!(parse_json_lenient("  {\"a\":[1,2]}\n").unwrap(), expected);
+        assert_eq!(parse_json_lenient("cb({\"a\":[1,2]})").unwrap(), expected);
+        assert_eq!(parse_json_lenient("var x=({\"a\":[1,2]});").unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_json_lenient_rejects_malformed_wrappers() {
+        let err = parse_json_lenient("<html>blocked</html>").unwrap_err();
+        assert!(err.to_string().contains("既不是 JSON 也不是 JSONP"), "{}", err);
+        let err = parse_json_lenient("cb({\"a\":1)").unwrap_err();
+        assert!(err.to_string().contains("解析JSON失败"), "{}", err);
+        assert!(parse_json_lenient("cb(").is_err());
+    }
+
+    #[test]
+    fn is_html_page_detects_czce_missing_file_pages() {
+        assert!(is_html_page(b"<!DOCTYPE html><html></html>"));
+        assert!(is_html_page(b"\xEF\xBB\xBF\r\n  <HTML><body>404</body></HTML>"));
+        assert!(is_html_page(b"<!doctype HTML>"));
+        // xlsx（zip）和 xls（OLE）文件头
+        assert!(!is_html_page(b"PK\x03\x04\x14\x00\x06\x00"));
+        assert!(!is_html_page(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1"));
+        assert!(!is_html_page(b"<htm"));
+        assert!(!is_html_page(b""));
+    }
+
+    #[tokio::test]
+    async fn html_bytes_are_rejected_before_excel_parsing() {
+        let err = sheet_from_bytes(b"\n<html><head><title>404</title></head></html>".to_vec())
+            .await
+            .unwrap_err();
+        assert!(err.is::<CzceFileMissing>(), "{}", err);
+
+        // 非 HTML 的无效内容才会交给 Excel 解析并得到解析错误
+        let err = sheet_from_bytes(b"not a spreadsheet".to_vec()).await.unwrap_err();
+        assert!(!err.is::<CzceFileMissing>());
+        assert!(err.to_string().contains("打开Excel文件失败"), "{}", err);
+    }
+
+    /// 按扩展名返回预置结果的郑商所下载源，记录请求过的地址
+    async fn czce_sheet_from(
+        prefer_xlsx: bool,
+        xls: fn() -> Result<Vec<u8>>,
+        xlsx: fn() -> Result<Vec<u8>>,
+    ) -> (Result<Range<Data>>, Vec<String>) {
+        let requested = std::sync::Mutex::new(Vec::new());
+        let result = fetch_czce_sheet_with("https://www.czce.com.cn/FutAndOpt_20251103", prefer_xlsx, |url| {
+            requested.lock().unwrap().push(url.clone());
+            let body = if url.ends_with(".xlsx") { xlsx() } else { xls() };
+            async move { sheet_from_bytes(body?).await }
+        })
+        .await;
+        (result, requested.into_inner().unwrap())
+    }
+
+    fn czce_xlsx() -> Result<Vec<u8>> {
+        Ok(include_bytes!("../../../tests/fixtures/czce_sheet.xlsx").to_vec())
+    }
+
+    fn czce_404() -> Result<Vec<u8>> {
+        Err(anyhow!("HTTP 404 Not Found，可能是非交易日"))
+    }
+
+    fn czce_html() -> Result<Vec<u8>> {
+        Ok(b"<!DOCTYPE html><html><body>404</body></html>".to_vec())
+    }
+
+    #[tokio::test]
+    async fn czce_sheet_falls_back_from_xls_404_to_xlsx() {
+        let (result, requested) = czce_sheet_from(false, czce_404, czce_xlsx).await;
+        let range = result.unwrap();
+        assert_eq!(
+            requested,
+            ["https://www.czce.com.cn/FutAndOpt_20251103.xls", "https://www.czce.com.cn/FutAndOpt_20251103.xlsx"]
+        );
+        assert_eq!(range.get_value((1, 0)), Some(&Data::String("SR".to_string())));
+        assert_eq!(range.get_value((1, 1)), Some(&Data::Float(1234.0)));
+    }
+
+    #[tokio::test]
+    async fn czce_sheet_stops_at_preferred_format() {
+        let (result, requested) = czce_sheet_from(true, czce_404, czce_xlsx).await;
+        assert!(result.is_ok());
+        assert_eq!(requested, ["https://www.czce.com.cn/FutAndOpt_20251103.xlsx"]);
+    }
+
+    #[tokio::test]
+    async fn czce_sheet_missing_only_when_both_formats_are_html() {
+        let (result, requested) = czce_sheet_from(false, czce_html, czce_html).await;
+        assert!(result.unwrap_err().is::<CzceFileMissing>());
+        assert_eq!(requested.len(), 2);
+
+        // 一种格式是网络错误时返回最后一个错误，不当作"该日期没有数据"
+        let (result, _) = czce_sheet_from(true, czce_404, czce_html).await;
+        let err = result.unwrap_err();
+        assert!(!err.is::<CzceFileMissing>());
+        assert!(err.to_string().contains("HTTP 404"), "{}", err);
+    }
+
+    #[test]
+    fn insecure_hosts_must_match_exactly() {
+        let allowlist = vec!["www.czce.com.cn".to_string()];
+        assert!(ensure_insecure_host_allowed("https://WWW.CZCE.com.cn/cn/x.xls", &allowlist).is_ok());
+        for url in ["https://czce.com.cn/", "https://www.czce.com.cn.example.com/", "https://example.com/?www.czce.com.cn"] {
+            let err = ensure_insecure_host_allowed(url, &allowlist).unwrap_err();
+            assert!(err.is::<InsecureHostRejected>(), "{}", url);
+        }
+        let err = ensure_insecure_host_allowed("not a url", &allowlist).unwrap_err();
+        assert!(!err.is::<InsecureHostRejected>());
+    }
+
+    #[test]
+    fn insecure_http_client_rejects_unlisted_host() {
+        let err = insecure_http_client("https://example.com/data.json").unwrap_err();
+        let rejected = err.downcast_ref::<InsecureHostRejected>().expect("应为 InsecureHostRejected");
+        assert_eq!(rejected.host, "example.com");
     }
 
-    // 模糊匹配
-    if name.contains("菜籽油") {
-        return Some("OI");
+    #[actix_web::test]
+    async fn insecure_redirects_to_unlisted_hosts_are_refused() {
+        use actix_web::{web, App, HttpResponse, HttpServer};
+
+        fn redirect_to(location: &'static str) -> HttpResponse {
+            HttpResponse::Found().insert_header(("Location", location)).finish()
+        }
+        let server = HttpServer::new(|| {
+            App::new()
+                .route("/ok", web::get().to(|| async { HttpResponse::Ok().body("ok") }))
+                .route("/same", web::get().to(|| async { redirect_to("/ok") }))
+                .route("/away", web::get().to(|| async { redirect_to("http://localhost/ok") }))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let port = server.addrs()[0].port();
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        let client = Client::builder()
+            .redirect(insecure_redirect_policy(|| vec!["127.0.0.1".to_string()]))
+            .build()
+            .unwrap();
+        let ok = client.get(format!("http://127.0.0.1:{}/same", port)).send().await.unwrap();
+        assert_eq!(ok.text().await.unwrap(), "ok");
+
+        let err = client.get(format!("http://127.0.0.1:{}/away", port)).send().await.unwrap_err();
+        assert!(err.is_redirect(), "{}", err);
+        let mut causes = Vec::new();
+        let mut source = std::error::Error::source(&err);
+        while let Some(cause) = source {
+            causes.push(cause.to_string());
+            source = cause.source();
+        }
+        assert!(causes.iter().any(|c| c.contains("拒绝以跳过证书校验的方式请求 localhost")), "{:?}", causes);
+
+        handle.stop(true).await;
     }
-    if name.contains("甲醇") {
-        return Some("MA");
+
+    #[actix_web::test]
+    async fn shared_client_sends_configured_user_agent() {
+        use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+
+        let server = HttpServer::new(|| {
+            App::new().route(
+                "/ua",
+                web::get().to(|req: HttpRequest| async move {
+                    let ua = req.headers().get(USER_AGENT).and_then(|v| v.to_str().ok()).unwrap_or_default();
+                    HttpResponse::Ok().body(ua.to_string())
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let port = server.addrs()[0].port();
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        let expected = config::read(|c| c.api.user_agent.clone());
+        assert!(!expected.is_empty());
+        let response = http_client().get(format!("http://127.0.0.1:{}/ua", port)).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), expected);
+
+        handle.stop(true).await;
+    }
+
+    /// 北京时间 "YYYY-MM-DD HH:MM:SS"
+    fn beijing(s: &str) -> DateTime<Tz> {
+        let naive = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+        Shanghai.from_local_datetime(&naive).unwrap()
     }
-    if name.contains("强麦") {
-        return Some("WH");
+
+    #[test]
+    fn quote_time_accepts_both_formats() {
+        let now = beijing("2025-03-12 15:30:00");
+        let (quote, stale) = parse_quote_time_at("14:59:59", "RB2505", now).unwrap();
+        assert_eq!(quote, beijing("2025-03-12 14:59:59"));
+        assert!(!stale, "收盘后不视为过期");
+        let (quote, _) = parse_quote_time_at(" 145959 ", "RB2505", now).unwrap();
+        assert_eq!(quote, beijing("2025-03-12 14:59:59"));
+
+        assert!(parse_quote_time_at("", "RB2505", now).is_none());
+        assert!(parse_quote_time_at("25:00:00", "RB2505", now).is_none());
+        assert!(parse_quote_time_at("145", "RB2505", now).is_none());
     }
-    if name.contains("棉纱") {
-        return Some("CY");
+
+    #[test]
+    fn quote_time_after_now_belongs_to_previous_day() {
+        // 凌晨抓取时，23:00 的夜盘行情属于前一天晚上
+        let now = beijing("2025-03-13 00:10:00");
+        let (quote, _) = parse_quote_time_at("23:00:00", "RB2505", now).unwrap();
+        assert_eq!(quote, beijing("2025-03-12 23:00:00"));
     }
 
-    None
+    #[test]
+    fn parse_context_truncates_multibyte_body_on_char_boundary() {
+        // 每个汉字 3 字节，按字节截断到 500 会落在字符中间
+        let body = format!("<html>{}</html>", "新浪行情数据".repeat(200));
+        let err = parse_json("新浪", "https://hq.sinajs.cn/list=nf_RB2601", &body).unwrap_err();
+        let message = err.to_string();
+        let snippet = message.split_once("响应开头: ").unwrap().1;
+        assert_eq!(snippet.chars().count(), PARSE_SNIPPET_CHARS);
+        assert!(body.starts_with(snippet));
+        assert!(message.starts_with("解析新浪响应失败（https://hq.sinajs.cn/list=nf_RB2601）"));
+        // 原始解析错误保留在错误链中
+        assert!(err.chain().any(|cause| cause.is::<serde_json::Error>()));
+
+        let short = "{\"a\":";
+        let err = parse_json("东方财富", "https://push2.eastmoney.com", short).unwrap_err();
+        assert!(err.to_string().ends_with("响应开头: {\"a\":"), "{}", err);
+    }
+
+    #[test]
+    fn parse_csv_strips_bom_and_keeps_quoted_commas() {
+        let records = parse_csv("\u{feff}a,\"b,c\",\"say \"\"hi\"\"\"\r\n\r\n,,\n1,2\n");
+        assert_eq!(records, [vec!["a", "b,c", "say \"hi\""], vec!["1", "2"]]);
+    }
+
+    #[test]
+    fn variety_map_contains_known_pairs_grouped_by_exchange() {
+        let map = variety_name_map(None);
+        assert!(map.len() > 50, "{}", map.len());
+        let cu = map.iter().find(|v| v.cn == "铜").unwrap();
+        assert_eq!((cu.en.as_str(), cu.exchange.as_str()), ("CU", "SHFE"));
+
+        // 按交易所分组，每个交易所只出现在一段连续区间内
+        let mut groups: Vec<&str> = map.iter().map(|v| v.exchange.as_str()).collect();
+        groups.dedup();
+        let order: Vec<&str> = VARIETY_MAP_EXCHANGES.iter().copied().filter(|e| groups.contains(e)).collect();
+        assert_eq!(groups, order);
+    }
+
+    #[test]
+    fn variety_map_filter_matches_either_side() {
+        let pairs = |q: &str| -> Vec<(String, String)> {
+            variety_name_map(Some(q)).into_iter().map(|v| (v.cn, v.en)).collect()
+        };
+        assert!(pairs("铜").contains(&("铜".to_string(), "CU".to_string())));
+        let oi = pairs("oi");
+        assert!(oi.len() > 1 && oi.iter().all(|(_, en)| en == "OI"), "{:?}", oi);
+        assert!(pairs("不存在的品种").is_empty());
+    }
+
+    #[test]
+    fn chinese_to_english_strips_known_affixes() {
+        assert_eq!(chinese_to_english("玉米淀粉"), Some("CS"));
+        assert_eq!(chinese_to_english("纯碱SA"), Some("SA"));
+        assert_eq!(chinese_to_english("螺纹钢期货"), Some("RB"));
+        assert_eq!(chinese_to_english("豆粕主力"), Some("M"));
+        assert_eq!(chinese_to_english("棉花(郑)"), Some("CF"));
+        assert_eq!(chinese_to_english("沪铜"), Some("CU"));
+        assert_eq!(chinese_to_english("沪深300"), Some("IF"));
+    }
+
+    #[test]
+    fn chinese_to_english_prefers_longest_contained_name() {
+        assert_eq!(chinese_to_english("沪铜主力连续"), Some("CU"));
+        assert_eq!(chinese_to_english("国际铜期货主连"), Some("BC"));
+        assert_eq!(chinese_to_english("低硫燃料油主连"), Some("LU"));
+        assert_eq!(chinese_to_english("上海螺纹钢指数"), Some("RB"));
+        // 铸造铝合金是独立品种，精确匹配优先于子串 "铝"
+        assert_eq!(chinese_to_english("铸造铝合金"), Some("AD"));
+        assert_eq!(chinese_to_english("铸造铝合金主连"), Some("AD"));
+        assert_eq!(chinese_to_english("氧化铝连续"), Some("AO"));
+    }
+
+    #[test]
+    fn chinese_to_english_rejects_unknown_names() {
+        assert_eq!(chinese_to_english("比特币"), None);
+        assert_eq!(chinese_to_english("期货"), None);
+        assert_eq!(chinese_to_english("主力连续"), None);
+        assert_eq!(chinese_to_english(""), None);
+    }
+
+    #[test]
+    fn price_change_is_signed() {
+        let (change, percent) = price_change(Some(99.0), Some(100.0));
+        assert_eq!(change, -1.0);
+        assert_eq!(percent, -1.0);
+        assert_eq!(price_change(None, Some(100.0)), (0.0, 0.0));
+        assert_eq!(price_change(Some(1.0), Some(0.0)), (0.0, 0.0));
+    }
 }
@@ -0,0 +1,195 @@
+//! 东方财富期货实时行情
+//!
+//! 作为新浪实时行情的备用数据源（见 realtime_source），使用 push2 的 ulist 批量行情接口，
+//! 按 secid（市场编号.合约代码）请求，字段映射到与新浪一致的 FuturesInfo
+
+use anyhow::{anyhow, Result};
+use chrono::{TimeZone, Utc};
+use chrono_tz::Asia::Shanghai;
+use serde_json::Value;
+
+use crate::config;
+use crate::models::{FuturesBatchItem, FuturesBatchStatus, FuturesInfo};
+
+use super::calendar::{is_quote_stale, trading_date};
+use super::common::{
+    beijing_now, get_beijing_time, http_client, parse_json_lenient, parse_lenient_f64, price_change,
+    RequestTimeout, EASTMONEY_FUTURES_REALTIME_API,
+};
+use super::expiry::exchange_for_variety;
+use super::limiter::LimitedSend;
+
+/// 请求的行情字段：
+/// f2 最新价、f12 代码、f13 市场、f14 名称、f15 最高、f16 最低、f17 开盘、
+/// f18 昨收、f28 昨结算、f108 持仓量、f5 成交量、f124 行情时间（Unix 秒）
+const FIELDS: &str = "f2,f5,f12,f13,f14,f15,f16,f17,f18,f28,f108,f124";
+
+/// 将合约代码转换为东方财富 secid（市场编号.合约代码）
+///
+/// 上期所、能源中心、大商所、广期所代码为小写；郑商所为大写且月份 3 位（SR2601 → SR601）；
+/// 中金所为大写。主力连续合约（如 CU0）和无法识别交易所的品种返回 None
+pub(crate) fn eastmoney_secid(symbol: &str) -> Option<String> {
+    let upper = symbol.trim().to_uppercase();
+    let code = upper
+        .strip_prefix("NF_")
+        .or_else(|| upper.strip_prefix("CFF_"))
+        .unwrap_or(&upper);
+
+    let letters = code.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+    let (variety, digits) = code.split_at(letters);
+    if variety.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    match (exchange_for_variety(variety)?, digits.len()) {
+        ("SHFE", 4) => Some(format!("113.{}", code.to_lowercase())),
+        ("INE", 4) => Some(format!("142.{}", code.to_lowercase())),
+        ("DCE", 4) => Some(format!("114.{}", code.to_lowercase())),
+        ("GFEX", 4) => Some(format!("225.{}", code.to_lowercase())),
+        ("CFFEX", 4) => Some(format!("220.{}", code)),
+        ("CZCE", 4) => Some(format!("115.{}{}", variety, &digits[1..])),
+        ("CZCE", 3) => Some(format!("115.{}", code)),
+        _ => None,
+    }
+}
+
+/// 获取多个合约的东方财富实时行情，每个请求的合约返回一项及其状态
+///
+/// 无法转换为 secid 的合约（如主力连续合约）直接返回无数据，不发送请求
+pub async fn get_eastmoney_realtime(symbols: &[String]) -> Result<Vec<FuturesBatchItem>> {
+    let requested: Vec<(String, Option<String>)> = symbols
+        .iter()
+        .map(|symbol| (symbol.clone(), eastmoney_secid(symbol)))
+        .collect();
+    let secids = requested
+        .iter()
+        .filter_map(|(_, secid)| secid.as_deref())
+        .collect::<Vec<_>>()
+        .join(",");
+    if secids.is_empty() {
+        return Ok(parse_eastmoney_realtime(&Value::Null, &requested));
+    }
+
+    let url = format!(
+        "{}?fltt=2&invt=2&secids={}&fields={}",
+        EASTMONEY_FUTURES_REALTIME_API, secids, FIELDS
+    );
+    log::debug!("请求东方财富实时行情 URL: {}", url);
+
+    let response = http_client()
+        .get(&url)
+        .timeout(RequestTimeout::Realtime.duration())
+        .send_limited()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("获取东方财富行情失败: {}", response.status()));
+    }
+
+    let text = response.text().await?;
+    let json = parse_json_lenient(&text)?;
+    Ok(parse_eastmoney_realtime(&json, &requested))
+}
+
+/// 解析东方财富 ulist 响应，requested 为 (原始代码, secid) 列表
+///
+/// 按 f13.f12 匹配请求的合约；data.diff 可能是数组，也可能是以序号为键的对象，
+/// 没有匹配项时 data 为 null
+pub(crate) fn parse_eastmoney_realtime(
+    json: &Value,
+    requested: &[(String, Option<String>)],
+) -> Vec<FuturesBatchItem> {
+    let rows: Vec<&Value> = match &json["data"]["diff"] {
+        Value::Array(rows) => rows.iter().collect(),
+        Value::Object(rows) => rows.values().collect(),
+        _ => Vec::new(),
+    };
+
+    requested
+        .iter()
+        .map(|(original, secid)| {
+            let row = secid.as_deref().and_then(|secid| {
+                rows.iter().copied().find(|row| {
+                    let market = field_token(&row["f13"]).unwrap_or_default();
+                    let code = field_token(&row["f12"]).unwrap_or_default();
+                    format!("{}.{}", market, code).eq_ignore_ascii_case(secid)
+                })
+            });
+            let data = row.and_then(|row| eastmoney_row_to_info(row, original));
+            if data.is_none() {
+                log::warn!("东方财富无 {} 的行情数据", original);
+            }
+            FuturesBatchItem {
+                symbol: original.clone(),
+                status: if data.is_some() {
+                    FuturesBatchStatus::Ok
+                } else {
+                    FuturesBatchStatus::NoData
+                },
+                data,
+            }
+        })
+        .collect()
+}
+
+/// 字段原始值：数字转为字符串，"-" 和空字符串表示缺失
+fn field_token(value: &Value) -> Option<String> {
+    let token = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        _ => return None,
+    };
+    (!token.is_empty() && token != "-").then_some(token)
+}
+
+fn field_f64(name: &str, value: &Value) -> Option<f64> {
+    field_token(value).and_then(|token| parse_lenient_f64(name, &token))
+}
+
+/// 将一行东方财富行情转换为 FuturesInfo，最新价和昨结算都缺失时视为无数据
+fn eastmoney_row_to_info(row: &Value, original_symbol: &str) -> Option<FuturesInfo> {
+    let current_price = field_f64("current_price", &row["f2"]);
+    let prev_settlement =
+        field_f64("prev_settlement", &row["f28"]).or_else(|| field_f64("prev_settlement", &row["f18"]));
+    if current_price.is_none() && prev_settlement.is_none() {
+        return None;
+    }
+    let prev_settlement_str = field_token(&row["f28"]).or_else(|| field_token(&row["f18"]));
+
+    let quote_time = row["f124"]
+        .as_i64()
+        .filter(|secs| *secs > 0)
+        .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+        .map(|t| t.with_timezone(&Shanghai));
+    let stale = quote_time.is_some_and(|t| {
+        is_quote_stale(original_symbol, t, beijing_now(), config::read(|c| c.api.stale_threshold_secs))
+    });
+    let trading_date = trading_date(quote_time.unwrap_or_else(beijing_now));
+    let (change, change_percent) = price_change(current_price, prev_settlement);
+
+    Some(FuturesInfo {
+        symbol: original_symbol.to_string(),
+        name: row["f14"].as_str().unwrap_or_default().to_string(),
+        current_price,
+        change,
+        change_percent,
+        volume: field_f64("volume", &row["f5"]).map(|v| v as u64).unwrap_or(0),
+        open: field_f64("open", &row["f17"]),
+        high: field_f64("high", &row["f15"]),
+        low: field_f64("low", &row["f16"]),
+        settlement: None,
+        prev_settlement,
+        open_interest: field_f64("open_interest", &row["f108"]).map(|v| v as u64),
+        updated_at: get_beijing_time(),
+        quote_time: quote_time.map(|t| t.to_rfc3339()),
+        trading_date: Some(trading_date.format("%Y-%m-%d").to_string()),
+        stale,
+        source: None,
+        current_price_str: field_token(&row["f2"]),
+        open_str: field_token(&row["f17"]),
+        high_str: field_token(&row["f15"]),
+        low_str: field_token(&row["f16"]),
+        settlement_str: None,
+        prev_settlement_str,
+    })
+}
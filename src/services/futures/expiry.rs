@@ -0,0 +1,349 @@
+//! 合约到期日推算
+//!
+//! 按各交易所合约规则中的最后交易日 / 最后交割日条款，推算具体合约月份的日期：
+//! - 上期所、能源中心：合约月份 15 日（非交易日顺延），交割日为其后第 5 个交易日；
+//!   原油、低硫燃料油为合约月份前一月的最后一个交易日
+//! - 大商所、广期所：合约月份第 10 个交易日，交割日为其后第 3 个交易日
+//! - 郑商所：合约月份第 10 个交易日，最后交割日为第 13 个交易日
+//! - 中金所：股指为合约月份第三个周五（现金交割，当日交割），国债为第二个周五，交割日为其后第 3 个交易日
+//!
+//! 交易日跳过周末和交易所节假日（见 calendar），节假日表未覆盖的年份只跳过周末
+//!
+//! 另提供按合约月份判断合约是否已摘牌的检查，实时行情请求已到期合约时返回 ContractExpired
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::fmt;
+
+use super::calendar::{latest_trading_day, next_trading_day, trading_date, upcoming_trading_day};
+use super::common::{beijing_now, extract_contract_month, CZCE_VARIETIES};
+use super::sina::validate_contract_symbol;
+use crate::config;
+use crate::models::FuturesContractExpiry;
+
+const SHFE_VARIETIES: &[&str] = &[
+    "CU", "AL", "ZN", "PB", "NI", "SN", "AU", "AG", "RB", "WR", "HC", "SS", "FU", "BU", "RU",
+    "SP", "AO", "BR", "AD",
+];
+const INE_VARIETIES: &[&str] = &["SC", "LU", "NR", "BC"];
+const DCE_VARIETIES: &[&str] = &[
+    "A", "B", "M", "Y", "P", "C", "CS", "RR", "JD", "LH", "J", "JM", "I", "FB", "BB", "L", "V",
+    "PP", "EG", "EB", "PG", "BZ", "LG",
+];
+const GFEX_VARIETIES: &[&str] = &["SI", "LC", "PS", "PT", "PD"];
+const CFFEX_INDEX_VARIETIES: &[&str] = &["IF", "IH", "IC", "IM"];
+const CFFEX_BOND_VARIETIES: &[&str] = &["T", "TF", "TS", "TL"];
+
+/// 最后交易日规则
+#[derive(Debug, Clone, Copy)]
+enum LastTradingDay {
+    /// 合约月份某日，非交易日顺延
+    DayOfMonth(u32),
+    /// 合约月份第 N 个交易日
+    NthTradingDay(u32),
+    /// 合约月份第 N 个周五，非交易日顺延
+    NthFriday(u8),
+    /// 合约月份前一月的最后一个交易日
+    EndOfPrevMonth,
+}
+
+/// 最后交割日规则
+#[derive(Debug, Clone, Copy)]
+enum DeliveryDay {
+    /// 最后交易日后第 N 个交易日
+    TradingDaysAfter(u32),
+    /// 合约月份第 N 个交易日
+    NthTradingDay(u32),
+    /// 与最后交易日相同（现金交割）
+    SameDay,
+}
+
+/// 品种所属交易所及其到期规则
+struct ExpiryRule {
+    exchange: &'static str,
+    last_trading_day: LastTradingDay,
+    delivery_day: DeliveryDay,
+    description: &'static str,
+}
+
+fn rule_for(variety: &str) -> Option<ExpiryRule> {
+    let rule = |exchange, last_trading_day, delivery_day, description| ExpiryRule {
+        exchange,
+        last_trading_day,
+        delivery_day,
+        description,
+    };
+    if matches!(variety, "SC" | "LU") {
+        Some(rule(
+            "INE",
+            LastTradingDay::EndOfPrevMonth,
+            DeliveryDay::TradingDaysAfter(5),
+            "合约月份前一月的最后一个交易日；最后交易日后连续五个交易日交割",
+        ))
+    } else if SHFE_VARIETIES.contains(&variety) || INE_VARIETIES.contains(&variety) {
+        let exchange = if INE_VARIETIES.contains(&variety) { "INE" } else { "SHFE" };
+        Some(rule(
+            exchange,
+            LastTradingDay::DayOfMonth(15),
+            DeliveryDay::TradingDaysAfter(5),
+            "合约月份 15 日（遇非交易日顺延）；最后交易日后连续五个交易日交割",
+        ))
+    } else if DCE_VARIETIES.contains(&variety) || GFEX_VARIETIES.contains(&variety) {
+        let exchange = if GFEX_VARIETIES.contains(&variety) { "GFEX" } else { "DCE" };
+        Some(rule(
+            exchange,
+            LastTradingDay::NthTradingDay(10),
+            DeliveryDay::TradingDaysAfter(3),
+            "合约月份第 10 个交易日；最后交割日为最后交易日后第 3 个交易日",
+        ))
+    } else if CZCE_VARIETIES.contains(&variety) {
+        Some(rule(
+            "CZCE",
+            LastTradingDay::NthTradingDay(10),
+            DeliveryDay::NthTradingDay(13),
+            "合约月份第 10 个交易日；最后交割日为合约月份第 13 个交易日",
+        ))
+    } else if CFFEX_INDEX_VARIETIES.contains(&variety) {
+        Some(rule(
+            "CFFEX",
+            LastTradingDay::NthFriday(3),
+            DeliveryDay::SameDay,
+            "合约到期月份的第三个周五（遇非交易日顺延）；现金交割，交割日同最后交易日",
+        ))
+    } else if CFFEX_BOND_VARIETIES.contains(&variety) {
+        Some(rule(
+            "CFFEX",
+            LastTradingDay::NthFriday(2),
+            DeliveryDay::TradingDaysAfter(3),
+            "合约到期月份的第二个周五；最后交割日为最后交易日后第 3 个交易日",
+        ))
+    } else {
+        None
+    }
+}
+
+/// 品种所属交易所代码（SHFE / INE / DCE / GFEX / CZCE / CFFEX），未知品种返回 None
+pub(crate) fn exchange_for_variety(variety: &str) -> Option<&'static str> {
+    rule_for(variety).map(|rule| rule.exchange)
+}
+
+/// 合约月份的第 N 个交易日
+fn nth_trading_day(year: i32, month: u32, n: u32) -> Option<NaiveDate> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let mut day = upcoming_trading_day(first);
+    for _ in 1..n {
+        day = next_trading_day(day);
+    }
+    Some(day)
+}
+
+/// 某日之后第 N 个交易日
+fn trading_days_after(date: NaiveDate, n: u32) -> NaiveDate {
+    (0..n).fold(date, |day, _| next_trading_day(day))
+}
+
+/// 合约代码中的年月：4 位为 YYMM，郑商所 3 位为 YMM（取离今年最近的年份）
+fn contract_month(digits: &str, today: NaiveDate) -> Option<(i32, u32)> {
+    let (year, month) = match digits.len() {
+        4 => (2000 + digits[..2].parse::<i32>().ok()?, digits[2..].parse::<u32>().ok()?),
+        3 => {
+            let current = today.year();
+            let mut year = current - current % 10 + digits[..1].parse::<i32>().ok()?;
+            if year + 5 < current {
+                year += 10;
+            } else if year > current + 5 {
+                year -= 10;
+            }
+            (year, digits[1..].parse::<u32>().ok()?)
+        }
+        _ => return None,
+    };
+    (1..=12).contains(&month).then_some((year, month))
+}
+
+/// 推算合约的最后交易日和最后交割日
+pub fn get_contract_expiry(symbol: &str) -> Result<FuturesContractExpiry> {
+    validate_contract_symbol(symbol)?;
+    contract_expiry_at(symbol, beijing_now().date_naive())
+}
+
+/// 以指定日期为今天推算合约到期信息
+pub(crate) fn contract_expiry_at(symbol: &str, today: NaiveDate) -> Result<FuturesContractExpiry> {
+    let upper = symbol.trim().to_uppercase();
+    let code = upper
+        .strip_prefix("NF_")
+        .or_else(|| upper.strip_prefix("CFF_"))
+        .unwrap_or(&upper);
+    let letters = code.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+    let (variety, digits) = code.split_at(letters);
+
+    if digits == "0" {
+        return Err(anyhow!("{} 为主力连续合约，没有到期日", symbol));
+    }
+    let (year, month) =
+        contract_month(digits, today).ok_or_else(|| anyhow!("合约月份无效: {}", symbol))?;
+    let rule = rule_for(variety).ok_or_else(|| anyhow!("暂不支持品种 {} 的到期日推算", variety))?;
+
+    let last_trading_day = match rule.last_trading_day {
+        LastTradingDay::DayOfMonth(day) => {
+            NaiveDate::from_ymd_opt(year, month, day).map(upcoming_trading_day)
+        }
+        LastTradingDay::NthTradingDay(n) => nth_trading_day(year, month, n),
+        LastTradingDay::NthFriday(n) => {
+            NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Fri, n)
+                .map(upcoming_trading_day)
+        }
+        LastTradingDay::EndOfPrevMonth => NaiveDate::from_ymd_opt(year, month, 1)
+            .map(|first| latest_trading_day(first - Duration::days(1))),
+    }
+    .ok_or_else(|| anyhow!("无法推算 {} 的最后交易日", symbol))?;
+
+    let delivery_day = match rule.delivery_day {
+        DeliveryDay::TradingDaysAfter(n) => Some(trading_days_after(last_trading_day, n)),
+        DeliveryDay::NthTradingDay(n) => nth_trading_day(year, month, n),
+        DeliveryDay::SameDay => Some(last_trading_day),
+    }
+    .ok_or_else(|| anyhow!("无法推算 {} 的最后交割日", symbol))?;
+
+    Ok(FuturesContractExpiry {
+        contract: code.to_string(),
+        variety: variety.to_string(),
+        exchange: rule.exchange.to_string(),
+        last_trading_day: last_trading_day.format("%Y-%m-%d").to_string(),
+        delivery_day: delivery_day.format("%Y-%m-%d").to_string(),
+        days_to_expiry: (last_trading_day - today).num_days(),
+        rule: rule.description.to_string(),
+    })
+}
+
+/// 合约月份已过，合约已到期摘牌
+#[derive(Debug)]
+pub struct ContractExpired {
+    /// 请求的合约代码
+    pub contract: String,
+    /// 合约月份 YYYY-MM
+    pub month: String,
+}
+
+impl fmt::Display for ContractExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "合约 {} 已于 {} 到期摘牌，没有实时行情，请改用当前在交易的合约",
+            self.contract, self.month
+        )
+    }
+}
+
+impl std::error::Error for ContractExpired {}
+
+/// 检查合约月份是否早于当前交易日所在月份，已到期时返回 ContractExpired
+///
+/// 主力连续（如 RB0）等无法解析出合约月份的代码不做判断；
+/// 配置 api.allow_expired_realtime 为 true 时跳过检查
+pub fn ensure_not_expired(symbol: &str) -> Result<()> {
+    if config::allow_expired_realtime() {
+        return Ok(());
+    }
+    ensure_not_expired_at(symbol, trading_date(beijing_now()))
+}
+
+/// 以指定交易日判断合约是否已到期
+pub(crate) fn ensure_not_expired_at(symbol: &str, trading_day: NaiveDate) -> Result<()> {
+    let digits = extract_contract_month(symbol);
+    let Some((year, month)) = contract_month(&digits, trading_day) else {
+        return Ok(());
+    };
+    if (year, month) < (trading_day.year(), trading_day.month()) {
+        return Err(ContractExpired {
+            contract: symbol.trim().to_uppercase(),
+            month: format!("{}-{:02}", year, month),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    /// (交易所, 最后交易日, 最后交割日)
+    fn expiry(symbol: &str, today: &str) -> [String; 3] {
+        let e = contract_expiry_at(symbol, date(today)).unwrap();
+        [e.exchange, e.last_trading_day, e.delivery_day]
+    }
+
+    #[test]
+    fn shfe_expires_on_15th_rolled_to_trading_day() {
+        assert_eq!(expiry("CU2405", "2024-04-01"), ["SHFE", "2024-05-15", "2024-05-22"]);
+        // 2025-06-15 为周日，顺延到周一
+        assert_eq!(expiry("rb2506", "2025-01-02"), ["SHFE", "2025-06-16", "2025-06-23"]);
+        // 原油为合约月份前一月的最后一个交易日（2024-11-30 为周六）
+        assert_eq!(expiry("SC2412", "2024-10-08"), ["INE", "2024-11-29", "2024-12-06"]);
+    }
+
+    #[test]
+    fn dce_czce_and_cffex_rules() {
+        // 第 10 个交易日，2025-01-01 元旦休市不计入
+        assert_eq!(expiry("M2501", "2024-10-08"), ["DCE", "2025-01-15", "2025-01-20"]);
+        // 郑商所 3 位年月取离今年最近的年份
+        assert_eq!(expiry("SR501", "2024-10-08"), ["CZCE", "2025-01-15", "2025-01-20"]);
+        assert_eq!(expiry("IF2412", "2024-10-08"), ["CFFEX", "2024-12-20", "2024-12-20"]);
+        assert_eq!(expiry("T2503", "2024-10-08"), ["CFFEX", "2025-03-14", "2025-03-19"]);
+    }
+
+    #[test]
+    fn days_to_expiry_and_unsupported_codes() {
+        let e = contract_expiry_at("IF2412", date("2024-12-10")).unwrap();
+        assert_eq!(e.days_to_expiry, 10);
+        let e = contract_expiry_at("IF2412", date("2024-12-23")).unwrap();
+        assert_eq!(e.days_to_expiry, -3);
+
+        assert!(contract_expiry_at("RB0", date("2024-12-10")).is_err());
+        assert!(contract_expiry_at("XX2501", date("2024-12-10")).is_err());
+        assert!(contract_expiry_at("RB2513", date("2024-12-10")).is_err());
+    }
+
+    #[test]
+    fn expired_contract_is_rejected() {
+        let err = ensure_not_expired_at("rb2410", date("2024-11-04")).unwrap_err();
+        assert!(err.is::<ContractExpired>());
+        assert!(ensure_not_expired_at("RB2411", date("2024-11-04")).is_ok());
+        assert!(ensure_not_expired_at("RB0", date("2024-11-04")).is_ok());
+    }
+
+    #[test]
+    fn long_expired_contract_reports_its_month() {
+        let err = ensure_not_expired_at("cu2301", date("2025-06-03")).unwrap_err();
+        let expired = err.downcast_ref::<ContractExpired>().unwrap();
+        assert_eq!(expired.contract, "CU2301");
+        assert_eq!(expired.month, "2023-01");
+        assert!(err.to_string().contains("CU2301 已于 2023-01 到期摘牌"), "{}", err);
+
+        // 郑商所 3 位年月与中金所合约同样判断
+        assert!(ensure_not_expired_at("MA501", date("2025-06-03")).unwrap_err().is::<ContractExpired>());
+        assert!(ensure_not_expired_at("IF2505", date("2025-06-03")).unwrap_err().is::<ContractExpired>());
+    }
+
+    #[test]
+    fn current_and_future_months_are_allowed() {
+        // 交割月当月仍可交易
+        assert!(ensure_not_expired_at("CU2506", date("2025-06-03")).is_ok());
+        assert!(ensure_not_expired_at("MA509", date("2025-06-03")).is_ok());
+        assert!(ensure_not_expired_at("IF2606", date("2025-06-03")).is_ok());
+    }
+
+    #[test]
+    fn checks_against_the_current_trading_date() {
+        // 默认配置不跳过检查
+        assert!(ensure_not_expired("CU2301").unwrap_err().is::<ContractExpired>());
+        let today = trading_date(beijing_now());
+        let current = format!("CU{:02}{:02}", today.year() % 100, today.month());
+        assert!(ensure_not_expired(&current).is_ok(), "{}", current);
+    }
+}
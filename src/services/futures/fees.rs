@@ -1,26 +1,61 @@
 //! 期货交易费用和规则相关
 
-use crate::models::{FuturesCommInfo, FuturesFeesInfo, FuturesRule};
+use crate::models::{
+    CommInfoData, FuturesCommInfo, FuturesCommInfoResult, FuturesFeesInfo, FuturesPerLotCost, FuturesRule,
+};
 use anyhow::{anyhow, Result};
-use chrono::Utc;
-use chrono_tz::Asia::Shanghai;
 use regex::Regex;
-use reqwest::Client;
+use std::future::Future;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
-use super::common::{GTJA_CALENDAR_URL, OPENCTP_FEES_URL, QIHUO_COMM_URL};
+use super::common::{
+    beijing_today_yyyymmdd, bounded_raw_cells, extract_variety, http_client, insecure_http_client,
+    parse_rate_fraction, parse_unit_number,
+    GTJA_CALENDAR_URL, OPENCTP_FEES_URL, QIHUO_COMM_URL,
+};
+use super::limiter::LimitedSend;
+use super::mock::{fixture, mock_enabled};
+use super::sina::FuturesService;
+
+/// 交易费用表缓存有效期（OpenCTP 每个交易日更新一次）
+const FEES_TTL: Duration = Duration::from_secs(3600);
+
+/// 交易费用表及其获取时间
+type CachedFees = (Instant, Vec<FuturesFeesInfo>);
+
+/// 进程内共享的交易费用表缓存
+static FEES_CACHE: OnceLock<RwLock<Option<CachedFees>>> = OnceLock::new();
 
 /// 获取期货交易费用参照表
 /// 对应 akshare 的 futures_fees_info() 函数
 /// 数据来源: http://openctp.cn/fees.html
+///
+/// 结果在进程内共享缓存 FEES_TTL，过期后由下一次请求刷新；模拟模式下不读取缓存
 pub async fn get_futures_fees_info() -> Result<Vec<FuturesFeesInfo>> {
-    let client = Client::new();
+    let cache = FEES_CACHE.get_or_init(|| RwLock::new(None));
+    if !mock_enabled() {
+        if let Some((cached_at, fees)) = cache.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            if cached_at.elapsed() < FEES_TTL {
+                return Ok(fees.clone());
+            }
+        }
+    }
 
-    println!("📡 请求期货交易费用数据 URL: {}", OPENCTP_FEES_URL);
+    let fees = fetch_futures_fees_info().await?;
+    *cache.write().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), fees.clone()));
+    Ok(fees)
+}
+
+/// 请求 OpenCTP 并解析交易费用表
+async fn fetch_futures_fees_info() -> Result<Vec<FuturesFeesInfo>> {
+    let client = http_client();
+
+    log::debug!("请求期货交易费用数据 URL: {}", OPENCTP_FEES_URL);
 
     let response = client
         .get(OPENCTP_FEES_URL)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -31,8 +66,113 @@ pub async fn get_futures_fees_info() -> Result<Vec<FuturesFeesInfo>> {
     parse_fees_html(&text)
 }
 
+/// 按交易所和品种过滤交易费用表
+///
+/// - exchange: 交易所代码（如 SHFE）或中文名称
+/// - product: 品种代码（如 rb，不区分大小写）或品种名称
+pub fn filter_fees_info(
+    fees: Vec<FuturesFeesInfo>,
+    exchange: Option<&str>,
+    product: Option<&str>,
+) -> Vec<FuturesFeesInfo> {
+    let exchange = exchange.map(str::trim).filter(|e| !e.is_empty());
+    let product = product.map(str::trim).filter(|p| !p.is_empty());
+
+    fees.into_iter()
+        .filter(|fee| exchange.is_none_or(|ex| fees_exchange_matches(fee, ex)))
+        .filter(|fee| {
+            product.is_none_or(|p| fee.product_code.eq_ignore_ascii_case(p) || fee.product_name == p)
+        })
+        .collect()
+}
+
+/// 按数值字段排序交易费用表，数值缺失的记录始终排在最后
+///
+/// field 为 FEES_SORT_FIELDS 之一，相同数值保持原有顺序
+pub fn sort_fees_info(fees: &mut [FuturesFeesInfo], field: &str, descending: bool) {
+    fees.sort_by(|a, b| match (a.sort_value(field), b.sort_value(field)) {
+        (Some(x), Some(y)) if descending => y.total_cmp(&x),
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+}
+
+/// 计算合约单手开平仓手续费和保证金
+///
+/// 按合约代码在交易费用表中查找，找不到时取同品种的第一条记录；价格取实时行情的最新价，
+/// 缺失时用昨结算价。费用表中没有该品种时返回 None
+pub async fn get_futures_per_lot_cost(symbol: &str) -> Result<Option<FuturesPerLotCost>> {
+    let fees = get_futures_fees_info().await?;
+    let Some(fee) = find_fee_for_contract(&fees, symbol) else {
+        return Ok(None);
+    };
+
+    let info = FuturesService::new().get_futures_info(symbol).await?;
+    let price = info
+        .current_price
+        .filter(|p| *p > 0.0)
+        .or(info.prev_settlement)
+        .ok_or_else(|| anyhow!("合约 {} 没有可用的价格", symbol))?;
+    compute_per_lot_cost(fee, symbol, price).map(Some)
+}
+
+/// 在交易费用表中查找合约：先按合约代码精确匹配，再按品种代码匹配
+fn find_fee_for_contract<'a>(fees: &'a [FuturesFeesInfo], symbol: &str) -> Option<&'a FuturesFeesInfo> {
+    let variety = extract_variety(symbol);
+    fees.iter()
+        .find(|fee| fee.contract_code.eq_ignore_ascii_case(symbol))
+        .or_else(|| fees.iter().find(|fee| fee.product_code.eq_ignore_ascii_case(&variety)))
+}
+
+/// 按金额费率和按手固定费用计算单手费用，两者都缺失时为 None
+fn per_lot_fee(notional: f64, rate: Option<f64>, fixed: Option<f64>) -> Option<f64> {
+    if rate.is_none() && fixed.is_none() {
+        return None;
+    }
+    Some(round_yuan(notional * rate.unwrap_or(0.0) + fixed.unwrap_or(0.0)))
+}
+
+fn round_yuan(v: f64) -> f64 {
+    (v * 100.0).round() / 100.0
+}
+
+/// 按费用表记录和价格计算单手成本
+///
+/// 交易费用表中每项手续费分为按金额的费率和按手的固定费用两列，单手费用为
+/// 价格 × 合约乘数 × 费率 + 固定费用；保证金为价格 × 合约乘数 × 保证金率
+pub fn compute_per_lot_cost(fee: &FuturesFeesInfo, symbol: &str, price: f64) -> Result<FuturesPerLotCost> {
+    let contract_size = fee
+        .contract_size_num
+        .filter(|size| *size > 0.0)
+        .ok_or_else(|| anyhow!("交易费用表中 {} 的合约乘数无效: {}", fee.contract_code, fee.contract_size))?;
+    let notional = price * contract_size;
+
+    Ok(FuturesPerLotCost {
+        symbol: symbol.to_string(),
+        exchange: fee.exchange.clone(),
+        contract_code: fee.contract_code.clone(),
+        product_code: fee.product_code.clone(),
+        price,
+        contract_size,
+        open_cost: per_lot_fee(notional, fee.open_fee_rate_num, fee.open_fee_num),
+        close_cost: per_lot_fee(notional, fee.close_fee_rate_num, fee.close_fee_num),
+        close_today_cost: per_lot_fee(notional, fee.close_today_fee_rate_num, fee.close_today_fee_num),
+        long_margin: fee.long_margin_rate_num.map(|r| round_yuan(notional * r)),
+        short_margin: fee.short_margin_rate_num.map(|r| round_yuan(notional * r)),
+        fees_updated_at: fee.updated_at.clone(),
+    })
+}
+
+/// 交易费用记录是否属于指定交易所（代码或中文名称）
+fn fees_exchange_matches(fee: &FuturesFeesInfo, name: &str) -> bool {
+    fee.exchange.eq_ignore_ascii_case(name)
+        || exchange_aliases(&fee.exchange.to_uppercase()).contains(&name)
+}
+
 /// 解析期货交易费用HTML
-fn parse_fees_html(html: &str) -> Result<Vec<FuturesFeesInfo>> {
+pub(crate) fn parse_fees_html(html: &str) -> Result<Vec<FuturesFeesInfo>> {
     let mut fees_list = Vec::new();
 
     let time_re = Regex::new(r"Generated at ([^.]+)\.").unwrap();
@@ -42,7 +182,7 @@ fn parse_fees_html(html: &str) -> Result<Vec<FuturesFeesInfo>> {
         .map(|m| m.as_str().trim().to_string())
         .unwrap_or_else(|| "未知".to_string());
 
-    println!("📅 数据更新时间: {}", updated_at);
+    log::debug!("数据更新时间: {}", updated_at);
 
     let tbody_start = html.find("<tbody>");
     let tbody_end = html.find("</tbody>");
@@ -88,11 +228,22 @@ fn parse_fees_html(html: &str) -> Result<Vec<FuturesFeesInfo>> {
                 long_margin_rate: cells[13].clone(),
                 short_margin_rate: cells[15].clone(),
                 updated_at: updated_at.clone(),
+                contract_size_num: parse_unit_number(&cells[5]),
+                price_tick_num: parse_unit_number(&cells[6]),
+                open_fee_rate_num: parse_rate_fraction(&cells[7]),
+                open_fee_num: parse_unit_number(&cells[8]),
+                close_fee_rate_num: parse_rate_fraction(&cells[9]),
+                close_fee_num: parse_unit_number(&cells[10]),
+                close_today_fee_rate_num: parse_rate_fraction(&cells[11]),
+                close_today_fee_num: parse_unit_number(&cells[12]),
+                long_margin_rate_num: parse_rate_fraction(&cells[13]),
+                short_margin_rate_num: parse_rate_fraction(&cells[15]),
+                raw: Some(bounded_raw_cells(&cells)),
             });
         }
     }
 
-    println!("📊 解析到 {} 条期货费用数据", fees_list.len());
+    log::debug!("解析到 {} 条期货费用数据", fees_list.len());
     Ok(fees_list)
 }
 
@@ -102,17 +253,14 @@ fn parse_fees_html(html: &str) -> Result<Vec<FuturesFeesInfo>> {
 pub async fn get_futures_comm_info(exchange: Option<&str>) -> Result<Vec<FuturesCommInfo>> {
     use scraper::{Html, Selector};
 
-    let client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .build()?;
+    let client = insecure_http_client(QIHUO_COMM_URL)?;
 
-    println!("📡 请求九期网期货手续费数据 URL: {}", QIHUO_COMM_URL);
+    log::debug!("请求九期网期货手续费数据 URL: {}", QIHUO_COMM_URL);
 
     let response = client
         .get(QIHUO_COMM_URL)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
         .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -259,6 +407,7 @@ pub async fn get_futures_comm_info(exchange: Option<&str>) -> Result<Vec<Futures
                 fee_total,
                 net_profit_per_tick,
                 remark,
+                raw: Some(bounded_raw_cells(&cells)),
             });
         }
     }
@@ -267,7 +416,7 @@ pub async fn get_futures_comm_info(exchange: Option<&str>) -> Result<Vec<Futures
         return Err(anyhow!("未能解析到期货手续费数据，请检查九期网是否可访问"));
     }
 
-    println!("📊 解析到 {} 条期货手续费数据", all_data.len());
+    log::debug!("解析到 {} 条期货手续费数据", all_data.len());
     Ok(all_data)
 }
 
@@ -275,23 +424,22 @@ pub async fn get_futures_comm_info(exchange: Option<&str>) -> Result<Vec<Futures
 /// 对应 akshare 的 futures_rule() 函数
 /// 数据来源: https://www.gtjaqh.com/pc/calendar.html
 pub async fn get_futures_rule(date: Option<&str>) -> Result<Vec<FuturesRule>> {
-    let client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .build()?;
+    if let Some(rules) = fixture("futures_rule") {
+        return rules;
+    }
 
-    let query_date = date.unwrap_or_else(|| {
-        let now = Utc::now().with_timezone(&Shanghai);
-        Box::leak(now.format("%Y%m%d").to_string().into_boxed_str())
-    });
+    let query_date = date
+        .map(|d| d.to_string())
+        .unwrap_or_else(beijing_today_yyyymmdd);
 
     let url = format!("{}?date={}", GTJA_CALENDAR_URL, query_date);
-    println!("📡 请求期货交易规则数据 URL: {}", url);
+    let client = insecure_http_client(&url)?;
+    log::debug!("请求期货交易规则数据 URL: {}", url);
 
     let response = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
         .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -299,16 +447,121 @@ pub async fn get_futures_rule(date: Option<&str>) -> Result<Vec<FuturesRule>> {
     }
 
     let text = response.text().await?;
+    if !has_rule_table(&text) {
+        // 非交易日页面不包含规则表格
+        log::debug!("{} 无交易规则数据，可能为非交易日", query_date);
+        return Ok(Vec::new());
+    }
     parse_futures_rule_html(&text)
 }
 
+/// 页面是否包含交易规则表格
+fn has_rule_table(html: &str) -> bool {
+    html.contains("交易保证金比例") || html.contains("涨跌停板幅度")
+}
+
+/// 获取期货手续费信息，九期网不可用时降级为 OpenCTP 交易费用表
+///
+/// 九期网请求失败或返回空表时改用 get_futures_fees_info，并在 note 中说明替换原因；
+/// exchange 为交易所中文名称（所有/上期所/上海期货交易所 等），降级时同样按交易所过滤
+pub async fn get_futures_comm_info_with_fallback(
+    exchange: Option<&str>,
+) -> Result<FuturesCommInfoResult> {
+    comm_info_with_fallback(exchange, get_futures_comm_info(exchange), get_futures_fees_info()).await
+}
+
+/// 降级逻辑本体，九期网和 OpenCTP 的获取由调用方传入；fees 仅在需要降级时才被等待
+async fn comm_info_with_fallback(
+    exchange: Option<&str>,
+    qihuo: impl Future<Output = Result<Vec<FuturesCommInfo>>>,
+    fees: impl Future<Output = Result<Vec<FuturesFeesInfo>>>,
+) -> Result<FuturesCommInfoResult> {
+    let reason = match qihuo.await {
+        Ok(data) if !data.is_empty() => {
+            return Ok(FuturesCommInfoResult {
+                source: "9qihuo".to_string(),
+                note: None,
+                data: CommInfoData::Qihuo(data),
+            });
+        }
+        Ok(_) => "九期网返回空数据".to_string(),
+        Err(e) => format!("九期网不可用: {}", e),
+    };
+
+    log::warn!("手续费信息降级为 OpenCTP 数据源，{}", reason);
+
+    let fees = fees.await?;
+    let fees = match exchange.map(str::trim).filter(|e| !e.is_empty() && *e != "所有") {
+        Some(name) => fees
+            .into_iter()
+            .filter(|f| fees_exchange_matches(f, name))
+            .collect(),
+        None => fees,
+    };
+
+    Ok(FuturesCommInfoResult {
+        source: "openctp".to_string(),
+        note: Some(format!(
+            "{}，已替换为 OpenCTP 交易费用表（字段与九期网不同，无现价和涨跌停板）",
+            reason
+        )),
+        data: CommInfoData::OpenCtp(fees),
+    })
+}
+
+/// 交易所代码对应的中文名称（国泰君安页面使用简称或全称）
+fn exchange_aliases(code: &str) -> &'static [&'static str] {
+    match code {
+        "SHFE" => &["上期所", "上海期货交易所"],
+        "INE" => &["能源中心", "上期能源", "上海国际能源交易中心"],
+        "DCE" => &["大商所", "大连商品交易所"],
+        "CZCE" => &["郑商所", "郑州商品交易所"],
+        "CFFEX" => &["中金所", "中国金融期货交易所"],
+        "GFEX" => &["广期所", "广州期货交易所"],
+        _ => &[],
+    }
+}
+
+/// 按交易所和品种过滤交易规则
+///
+/// - exchange: 交易所代码（如 SHFE）或中文名称
+/// - product: 品种代码（如 CU，不区分大小写）或品种名称
+pub fn filter_futures_rules(
+    rules: Vec<FuturesRule>,
+    exchange: Option<&str>,
+    product: Option<&str>,
+) -> Vec<FuturesRule> {
+    let exchange = exchange.map(str::trim).filter(|e| !e.is_empty());
+    let product = product.map(str::trim).filter(|p| !p.is_empty());
+
+    rules
+        .into_iter()
+        .filter(|rule| {
+            exchange.is_none_or(|ex| {
+                // 传入中文简称或全称时按其所属交易所的全部名称匹配
+                let aliases = ["SHFE", "INE", "DCE", "CZCE", "CFFEX", "GFEX"]
+                    .into_iter()
+                    .map(exchange_aliases)
+                    .find(|aliases| aliases.contains(&ex))
+                    .unwrap_or_else(|| exchange_aliases(&ex.to_uppercase()));
+                rule.exchange.eq_ignore_ascii_case(ex)
+                    || rule.exchange.contains(ex)
+                    || aliases.iter().any(|alias| rule.exchange.contains(alias))
+            })
+        })
+        .filter(|rule| {
+            product.is_none_or(|p| rule.code.eq_ignore_ascii_case(p) || rule.product == p)
+        })
+        .collect()
+}
+
 /// 解析期货交易规则HTML
-fn parse_futures_rule_html(html: &str) -> Result<Vec<FuturesRule>> {
+pub(crate) fn parse_futures_rule_html(html: &str) -> Result<Vec<FuturesRule>> {
     use scraper::{Html, Selector};
 
     let mut rules = Vec::new();
 
-    if !html.contains("交易保证金比例") && !html.contains("涨跌停板幅度") {
+    if !has_rule_table(html) {
         return Err(anyhow!("未找到交易规则数据表格"));
     }
 
@@ -384,10 +637,257 @@ fn parse_futures_rule_html(html: &str) -> Result<Vec<FuturesRule>> {
                 max_order_size,
                 special_note,
                 remark,
+                raw: Some(bounded_raw_cells(&cells)),
             });
         }
     }
 
-    println!("📊 解析到 {} 条期货交易规则数据", rules.len());
+    log::debug!("解析到 {} 条期货交易规则数据", rules.len());
     Ok(rules)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEES_HTML: &str = include_str!("../../../tests/fixtures/openctp_fees.html");
+    const RULE_HTML: &str = include_str!("../../../tests/fixtures/gtja_rule.html");
+
+    #[test]
+    fn parse_fees_html_fixture() {
+        let fees = parse_fees_html(FEES_HTML).unwrap();
+        assert_eq!(fees.len(), 4);
+
+        let cu = &fees[0];
+        assert_eq!(cu.exchange, "SHFE");
+        assert_eq!(cu.contract_code, "cu2601");
+        assert_eq!(cu.product_name, "铜");
+        assert_eq!(cu.contract_size, "5");
+        assert_eq!(cu.long_margin_rate, "0.1");
+        assert_eq!(cu.updated_at, "2025-12-05 15:40:12");
+
+        // 带背景色的单元格只保留内容
+        let ma = fees.iter().find(|f| f.product_code == "MA").unwrap();
+        assert_eq!(ma.close_fee, "2");
+        assert_eq!(ma.close_today_fee, "6");
+    }
+
+    fn comm_row(exchange: &str, code: &str) -> FuturesCommInfo {
+        FuturesCommInfo {
+            exchange: exchange.to_string(),
+            contract_name: String::new(),
+            contract_code: code.to_string(),
+            current_price: None,
+            limit_up: None,
+            limit_down: None,
+            margin_buy: None,
+            margin_sell: None,
+            margin_per_lot: None,
+            fee_open_ratio: None,
+            fee_open_yuan: None,
+            fee_close_yesterday_ratio: None,
+            fee_close_yesterday_yuan: None,
+            fee_close_today_ratio: None,
+            fee_close_today_yuan: None,
+            profit_per_tick: None,
+            fee_total: None,
+            net_profit_per_tick: None,
+            remark: None,
+            raw: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn comm_info_falls_back_to_openctp_when_qihuo_fails() {
+        let result = comm_info_with_fallback(
+            Some("上海期货交易所"),
+            async { Err(anyhow!("连接被重置")) },
+            async { parse_fees_html(FEES_HTML) },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.source, "openctp");
+        let note = result.note.unwrap();
+        assert!(note.contains("九期网不可用") && note.contains("连接被重置"), "{}", note);
+        let CommInfoData::OpenCtp(fees) = result.data else {
+            panic!("降级后应为 OpenCTP 数据");
+        };
+        assert!(!fees.is_empty());
+        assert!(fees.iter().all(|f| f.exchange == "SHFE"));
+    }
+
+    #[tokio::test]
+    async fn comm_info_falls_back_on_empty_qihuo_table() {
+        let result = comm_info_with_fallback(None, async { Ok(Vec::new()) }, async { parse_fees_html(FEES_HTML) })
+            .await
+            .unwrap();
+
+        assert_eq!(result.source, "openctp");
+        assert!(result.note.unwrap().contains("九期网返回空数据"));
+        let CommInfoData::OpenCtp(fees) = result.data else {
+            panic!("降级后应为 OpenCTP 数据");
+        };
+        assert_eq!(fees.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn comm_info_uses_qihuo_without_touching_openctp() {
+        let result = comm_info_with_fallback(
+            None,
+            async { Ok(vec![comm_row("上海期货交易所", "cu2601")]) },
+            async { panic!("九期网可用时不应请求 OpenCTP") },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.source, "9qihuo");
+        assert!(result.note.is_none());
+        assert!(matches!(result.data, CommInfoData::Qihuo(ref rows) if rows.len() == 1));
+    }
+
+    #[tokio::test]
+    async fn comm_info_fallback_propagates_openctp_failure() {
+        let err = comm_info_with_fallback(
+            None,
+            async { Err(anyhow!("九期网超时")) },
+            async { Err(anyhow!("OpenCTP 超时")) },
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("OpenCTP 超时"));
+    }
+
+    #[test]
+    fn parse_fees_html_without_table_fails() {
+        assert!(parse_fees_html("<html><body>维护中</body></html>").is_err());
+    }
+
+    #[test]
+    fn parse_futures_rule_html_fixture() {
+        let rules = parse_futures_rule_html(RULE_HTML).unwrap();
+        assert_eq!(rules.len(), 6);
+
+        let cu = &rules[0];
+        assert_eq!((cu.exchange.as_str(), cu.product.as_str(), cu.code.as_str()), ("上期所", "铜", "CU"));
+        assert_eq!(cu.margin_rate, Some(9.0));
+        assert_eq!(cu.price_limit, Some(7.0));
+        assert_eq!(cu.contract_size, Some(5.0));
+        assert_eq!(cu.price_tick, Some(10.0));
+        assert_eq!(cu.max_order_size, Some(500));
+
+        let if_rule = rules.iter().find(|r| r.code == "IF").unwrap();
+        assert_eq!(if_rule.price_tick, Some(0.2));
+        assert_eq!(if_rule.remark.as_deref(), Some("交割月前一月保证金提高"));
+
+        let lc = rules.iter().find(|r| r.code == "LC").unwrap();
+        assert_eq!(lc.margin_rate, None);
+        assert_eq!(lc.special_note.as_deref(), Some("LC2601保证金12%"));
+    }
+
+    #[test]
+    fn parsed_rows_carry_raw_cells_until_cleared() {
+        let mut rules = parse_futures_rule_html(RULE_HTML).unwrap();
+        let raw = rules[0].raw.as_deref().unwrap();
+        assert!(raw.iter().any(|c| c == "铜") && raw.iter().any(|c| c == "CU"), "{:?}", raw);
+        assert!(serde_json::to_value(&rules[0]).unwrap().get("_raw").is_some());
+
+        rules[0].clear_raw();
+        assert!(serde_json::to_value(&rules[0]).unwrap().get("_raw").is_none());
+
+        let mut fees = parse_fees_html(FEES_HTML).unwrap();
+        let raw = fees[0].raw.as_deref().unwrap();
+        assert!(raw.iter().any(|c| c.contains("cu2601")), "{:?}", raw);
+        fees[0].clear_raw();
+        assert!(serde_json::to_value(&fees[0]).unwrap().get("_raw").is_none());
+    }
+
+    fn rule_codes(rules: &[FuturesRule]) -> Vec<&str> {
+        rules.iter().map(|r| r.code.as_str()).collect()
+    }
+
+    #[test]
+    fn filter_rules_by_exchange_code_or_name() {
+        let rules = parse_futures_rule_html(RULE_HTML).unwrap();
+        let shfe = filter_futures_rules(rules.clone(), Some("shfe"), None);
+        assert!(!shfe.is_empty());
+        assert!(shfe.iter().all(|r| r.exchange == "上期所"));
+        assert!(rule_codes(&shfe).contains(&"CU"));
+
+        assert_eq!(rule_codes(&filter_futures_rules(rules.clone(), Some("上海期货交易所"), None)), rule_codes(&shfe));
+        assert_eq!(rule_codes(&filter_futures_rules(rules.clone(), Some("上期所"), None)), rule_codes(&shfe));
+        assert!(filter_futures_rules(rules, Some("NYMEX"), None).is_empty());
+    }
+
+    #[test]
+    fn filter_rules_by_product_code_or_name() {
+        let rules = parse_futures_rule_html(RULE_HTML).unwrap();
+        assert_eq!(rule_codes(&filter_futures_rules(rules.clone(), None, Some("cu"))), ["CU"]);
+        assert_eq!(rule_codes(&filter_futures_rules(rules.clone(), None, Some("铜"))), ["CU"]);
+        assert_eq!(rule_codes(&filter_futures_rules(rules.clone(), Some("SHFE"), Some("CU"))), ["CU"]);
+        assert!(filter_futures_rules(rules.clone(), Some("DCE"), Some("CU")).is_empty());
+
+        // 空白参数等同于不过滤
+        assert_eq!(filter_futures_rules(rules.clone(), Some(" "), Some("")).len(), rules.len());
+    }
+
+    #[test]
+    fn parse_futures_rule_html_without_table_fails() {
+        assert!(!has_rule_table("<html><body>今日休市</body></html>"));
+        assert!(parse_futures_rule_html("<html><body>今日休市</body></html>").is_err());
+    }
+
+    #[test]
+    fn parse_fees_html_numeric_fields() {
+        let fees = parse_fees_html(FEES_HTML).unwrap();
+        let cu = &fees[0];
+        assert_eq!(cu.contract_size_num, Some(5.0));
+        assert_eq!(cu.price_tick_num, Some(10.0));
+        assert_eq!(cu.open_fee_rate_num, Some(0.00005));
+        assert_eq!(cu.open_fee_num, Some(0.0));
+        assert_eq!(cu.close_today_fee_rate_num, Some(0.0001));
+        assert_eq!(cu.long_margin_rate_num, Some(0.1));
+        assert_eq!(cu.short_margin_rate_num, Some(0.1));
+
+        let m = fees.iter().find(|f| f.product_code == "m").unwrap();
+        assert_eq!(m.open_fee_num, Some(1.5));
+        assert_eq!(m.long_margin_rate_num, Some(0.07));
+    }
+
+    fn fee_with_margin(code: &str, margin: &str) -> FuturesFeesInfo {
+        let mut fee = parse_fees_html(FEES_HTML).unwrap().remove(0);
+        fee.product_code = code.to_string();
+        fee.long_margin_rate = margin.to_string();
+        fee.long_margin_rate_num = parse_rate_fraction(margin);
+        fee
+    }
+
+    #[test]
+    fn sort_compares_rates_in_one_unit() {
+        let mut fees = vec![
+            fee_with_margin("a", "12%"),
+            fee_with_margin("b", "0.1"),
+            fee_with_margin("c", "900‱"),
+            fee_with_margin("d", "--"),
+        ];
+        sort_fees_info(&mut fees, "long_margin_rate", true);
+        let order: Vec<&str> = fees.iter().map(|f| f.product_code.as_str()).collect();
+        assert_eq!(order, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn per_lot_cost_uses_fraction_rates() {
+        let fees = parse_fees_html(FEES_HTML).unwrap();
+        let cu = find_fee_for_contract(&fees, "CU2601").unwrap();
+        let cost = compute_per_lot_cost(cu, "CU2601", 80000.0).unwrap();
+        // 80000 × 5 = 400000
+        assert_eq!(cost.open_cost, Some(20.0));
+        assert_eq!(cost.close_today_cost, Some(40.0));
+        assert_eq!(cost.long_margin, Some(40000.0));
+
+        let ma = find_fee_for_contract(&fees, "MA605").unwrap();
+        let cost = compute_per_lot_cost(ma, "MA605", 2100.0).unwrap();
+        assert_eq!(cost.open_cost, Some(2.0));
+        assert_eq!(cost.close_today_cost, Some(6.0));
+    }
+}
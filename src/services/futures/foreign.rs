@@ -2,13 +2,14 @@
 
 use crate::models::{ForeignFuturesDetail, ForeignFuturesDetailItem, ForeignFuturesHistData, ForeignFuturesSymbol, FuturesInfo};
 use anyhow::{anyhow, Result};
-use chrono::Utc;
-use chrono_tz::Asia::Shanghai;
 use regex::Regex;
-use reqwest::Client;
 use std::collections::HashMap;
 
-use super::common::{get_beijing_time, SINA_FOREIGN_DAILY_API, SINA_FUTURES_REALTIME_API};
+use super::common::{
+    beijing_now, get_beijing_time, http_client, parse_context, price_change, RequestTimeout, SINA_FOREIGN_DAILY_API, SINA_FUTURES_REALTIME_API,
+};
+use super::limiter::LimitedSend;
+use super::mock::fixture;
 
 /// 获取外盘期货品种列表
 /// 对应 akshare 的 futures_hq_subscribe_exchange_symbol() 函数
@@ -45,15 +46,54 @@ pub fn get_foreign_futures_symbols() -> Vec<ForeignFuturesSymbol> {
     ]
 }
 
-/// 获取外盘期货实时行情
+/// 规范化外盘品种代码（转大写、去空白、去重），返回 (有效代码, 未知代码)
+pub fn validate_foreign_codes(codes: &[String]) -> (Vec<String>, Vec<String>) {
+    let known = get_foreign_futures_symbols();
+    let mut valid = Vec::new();
+    let mut unknown = Vec::new();
+
+    for code in codes {
+        let code = code.trim().to_uppercase();
+        if code.is_empty() || valid.contains(&code) || unknown.contains(&code) {
+            continue;
+        }
+        if known.iter().any(|s| s.code == code) {
+            valid.push(code);
+        } else {
+            unknown.push(code);
+        }
+    }
+
+    (valid, unknown)
+}
+
+/// 获取外盘期货实时行情，返回 (行情列表, 未知代码)
 /// 对应 akshare 的 futures_foreign_commodity_realtime() 函数
-pub async fn get_foreign_futures_realtime(codes: &[String]) -> Result<Vec<FuturesInfo>> {
-    use std::time::Duration;
+///
+/// 代码先经 validate_foreign_codes 规范化、去重，不在品种列表中的代码不请求上游，
+/// 原样返回给调用方作为提示；没有有效代码时不发请求，返回空列表
+pub async fn get_foreign_futures_realtime(
+    codes: &[String],
+) -> Result<(Vec<FuturesInfo>, Vec<String>)> {
+    let (codes, unknown) = validate_foreign_codes(codes);
+    if !unknown.is_empty() {
+        log::warn!("忽略未知的外盘品种代码: {}", unknown.join(", "));
+    }
+    if codes.is_empty() {
+        return Ok((Vec::new(), unknown));
+    }
+
+    if let Some(list) = fixture::<Vec<FuturesInfo>>("foreign_realtime") {
+        let list = list?;
+        let data = codes
+            .iter()
+            .filter_map(|code| list.iter().find(|info| info.symbol.eq_ignore_ascii_case(code)))
+            .cloned()
+            .collect();
+        return Ok((data, unknown));
+    }
 
-    let client = Client::builder()
-        .timeout(Duration::from_secs(30))
-        .connect_timeout(Duration::from_secs(10))
-        .build()?;
+    let client = http_client();
 
     let symbols_str = codes
         .iter()
@@ -62,19 +102,14 @@ pub async fn get_foreign_futures_realtime(codes: &[String]) -> Result<Vec<Future
         .join(",");
 
     let url = format!("{}?list={}", SINA_FUTURES_REALTIME_API, symbols_str);
-    println!("📡 请求外盘期货行情 URL: {}", url);
+    log::debug!("请求外盘期货行情 URL: {}", url);
 
     let response = client
         .get(&url)
-        .header("Accept", "*/*")
-        .header("Accept-Encoding", "gzip, deflate")
-        .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
         .header("Cache-Control", "no-cache")
-        .header("Host", "hq.sinajs.cn")
         .header("Pragma", "no-cache")
-        .header("Referer", "https://finance.sina.com.cn/")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .send()
+        .timeout(RequestTimeout::Realtime.duration())
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -83,13 +118,84 @@ pub async fn get_foreign_futures_realtime(codes: &[String]) -> Result<Vec<Future
 
     let text = response.text().await?;
     let preview: String = text.chars().take(500).collect();
-    println!("📥 原始响应数据: {}", preview);
+    log::debug!("原始响应数据: {}", preview);
+
+    Ok((parse_foreign_futures_data(&text, &codes)?, unknown))
+}
+
+/// 外盘品种类别（新浪 hf_ 行情字段布局因类别而异）
+///
+/// 各类别共有的字段：0 最新价、2 买价、3 卖价、4 最高、5 最低、6 行情时间、8 开盘、12 日期、13 名称
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ForeignCategory {
+    /// LME 3个月期金属：15 个字段，7 为昨结算，9~11 和 14 恒为 0（无持仓量和成交量）
+    LmeMetal,
+    /// 伦敦金/银/铂/钯现货：14 个字段，1 为昨收盘价（7 可能为空），无持仓量和成交量
+    SpotMetal,
+    /// 交易所期货（NYMEX/ICE 能源、COMEX、CBOT 等）：15 个字段，7 为昨结算，9 为持仓量，14 为成交量
+    ExchangeFutures,
+}
 
-    parse_foreign_futures_data(&text, codes)
+/// 行情字段偏移
+struct ForeignFieldLayout {
+    high: usize,
+    low: usize,
+    prev_settlement: usize,
+    /// prev_settlement 为空时使用的字段
+    prev_settlement_fallback: Option<usize>,
+    open: usize,
+    open_interest: Option<usize>,
+    volume: Option<usize>,
+}
+
+impl ForeignCategory {
+    /// 根据品种代码判断类别
+    fn from_code(code: &str) -> Self {
+        match code {
+            "CAD" | "AHD" | "ZSD" | "PBD" | "NID" | "SND" => Self::LmeMetal,
+            "XAU" | "XAG" | "XPT" | "XPD" => Self::SpotMetal,
+            _ => Self::ExchangeFutures,
+        }
+    }
+
+    fn layout(self) -> ForeignFieldLayout {
+        match self {
+            Self::LmeMetal => ForeignFieldLayout {
+                high: 4,
+                low: 5,
+                prev_settlement: 7,
+                prev_settlement_fallback: None,
+                open: 8,
+                open_interest: None,
+                volume: None,
+            },
+            Self::SpotMetal => ForeignFieldLayout {
+                high: 4,
+                low: 5,
+                prev_settlement: 1,
+                prev_settlement_fallback: Some(7),
+                open: 8,
+                open_interest: None,
+                volume: None,
+            },
+            Self::ExchangeFutures => ForeignFieldLayout {
+                high: 4,
+                low: 5,
+                prev_settlement: 7,
+                prev_settlement_fallback: None,
+                open: 8,
+                open_interest: Some(9),
+                volume: Some(14),
+            },
+        }
+    }
 }
 
 /// 解析外盘期货数据
-fn parse_foreign_futures_data(data: &str, codes: &[String]) -> Result<Vec<FuturesInfo>> {
+///
+/// 按每段响应中 hq_str_hf_<代码> 的变量名匹配请求的品种，而不是依赖行的位置，
+/// 上游缺行或顺序变化时不会错位；没有数据的品种不出现在结果中
+pub(crate) fn parse_foreign_futures_data(data: &str, codes: &[String]) -> Result<Vec<FuturesInfo>> {
     let mut results = Vec::new();
     let symbol_map = get_foreign_futures_symbols();
     let code_to_name: HashMap<String, String> = symbol_map
@@ -97,17 +203,23 @@ fn parse_foreign_futures_data(data: &str, codes: &[String]) -> Result<Vec<Future
         .map(|s| (s.code.clone(), s.symbol.clone()))
         .collect();
 
-    for (i, item) in data.split(';').filter(|s| !s.trim().is_empty()).enumerate() {
-        if i >= codes.len() {
-            break;
+    let mut segments: HashMap<String, &str> = HashMap::new();
+    for item in data.split(';') {
+        let Some((var, value)) = item.trim().split_once('=') else {
+            continue;
+        };
+        if let Some((_, code)) = var.trim().rsplit_once("hq_str_hf_") {
+            segments.insert(code.to_uppercase(), value);
         }
+    }
 
-        let parts: Vec<&str> = item.split('=').collect();
-        if parts.len() < 2 {
+    for code in codes {
+        let Some(value) = segments.get(&code.to_uppercase()) else {
+            log::warn!("上游无外盘品种 {} 的行情数据", code);
             continue;
-        }
+        };
 
-        let data_part = parts[1].trim_matches('"').trim_matches('\'');
+        let data_part = value.trim().trim_matches('"').trim_matches('\'');
         if data_part.is_empty() {
             continue;
         }
@@ -117,22 +229,22 @@ fn parse_foreign_futures_data(data: &str, codes: &[String]) -> Result<Vec<Future
             continue;
         }
 
-        let code = &codes[i];
         let name = code_to_name.get(code).cloned().unwrap_or(code.clone());
+        let layout = ForeignCategory::from_code(code).layout();
 
-        let current_price = fields[0].parse::<f64>().unwrap_or(0.0);
-        let high = fields[4].parse::<f64>().unwrap_or(0.0);
-        let low = fields[5].parse::<f64>().unwrap_or(0.0);
-        let prev_settlement = fields[7].parse::<f64>().unwrap_or(0.0);
-        let open = fields[8].parse::<f64>().unwrap_or(0.0);
-        let open_interest = fields[9].parse::<u64>().ok();
+        let field_f64 = |idx: usize| fields.get(idx).and_then(|s| s.trim().parse::<f64>().ok());
+        let field_u64 = |idx: usize| fields.get(idx).and_then(|s| s.trim().parse::<u64>().ok());
 
-        let change = current_price - prev_settlement;
-        let change_percent = if prev_settlement != 0.0 {
-            (change / prev_settlement) * 100.0
-        } else {
-            0.0
-        };
+        let current_price = field_f64(0);
+        let high = field_f64(layout.high);
+        let low = field_f64(layout.low);
+        let prev_settlement = field_f64(layout.prev_settlement)
+            .or_else(|| layout.prev_settlement_fallback.and_then(field_f64));
+        let open = field_f64(layout.open);
+        let open_interest = layout.open_interest.and_then(field_u64);
+        let volume = layout.volume.and_then(field_u64).unwrap_or(0);
+
+        let (change, change_percent) = price_change(current_price, prev_settlement);
 
         results.push(FuturesInfo {
             symbol: code.clone(),
@@ -140,14 +252,24 @@ fn parse_foreign_futures_data(data: &str, codes: &[String]) -> Result<Vec<Future
             current_price,
             change,
             change_percent,
-            volume: 0,
+            volume,
             open,
             high,
             low,
             settlement: None,
-            prev_settlement: Some(prev_settlement),
+            prev_settlement,
             open_interest,
             updated_at: get_beijing_time(),
+            quote_time: None,
+            trading_date: None,
+            stale: false,
+            source: None,
+            current_price_str: None,
+            open_str: None,
+            high_str: None,
+            low_str: None,
+            settlement_str: None,
+            prev_settlement_str: None,
         });
     }
 
@@ -157,9 +279,9 @@ fn parse_foreign_futures_data(data: &str, codes: &[String]) -> Result<Vec<Future
 /// 获取外盘期货历史数据（日K线）
 /// 对应 akshare 的 futures_foreign_hist() 函数
 pub async fn get_futures_foreign_hist(symbol: &str) -> Result<Vec<ForeignFuturesHistData>> {
-    let client = Client::new();
+    let client = http_client();
 
-    let now = Utc::now().with_timezone(&Shanghai);
+    let now = beijing_now();
     let today = format!(
         "{}_{}_{}",
         now.format("%Y"),
@@ -172,14 +294,12 @@ pub async fn get_futures_foreign_hist(symbol: &str) -> Result<Vec<ForeignFutures
         SINA_FOREIGN_DAILY_API, today
     );
 
-    println!("📡 请求外盘期货历史数据 URL: {}", url);
+    log::debug!("请求外盘期货历史数据 URL: {}", url);
 
     let response = client
         .get(&url)
         .query(&[("symbol", symbol), ("_", &today), ("source", "web")])
-        .header("Referer", "https://finance.sina.com.cn/")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -187,13 +307,13 @@ pub async fn get_futures_foreign_hist(symbol: &str) -> Result<Vec<ForeignFutures
     }
 
     let text = response.text().await?;
-    println!("📥 原始响应数据长度: {} 字节", text.len());
+    log::debug!("原始响应数据长度: {} 字节", text.len());
 
-    parse_foreign_hist_data(&text)
+    parse_context(parse_foreign_hist_data(&text), "新浪外盘日K线", &url, &text)
 }
 
 /// 解析外盘期货历史数据
-fn parse_foreign_hist_data(data: &str) -> Result<Vec<ForeignFuturesHistData>> {
+pub(crate) fn parse_foreign_hist_data(data: &str) -> Result<Vec<ForeignFuturesHistData>> {
     let mut history = Vec::new();
 
     let start = data.find('[');
@@ -209,45 +329,26 @@ fn parse_foreign_hist_data(data: &str) -> Result<Vec<ForeignFuturesHistData>> {
         serde_json::from_str(json_str).map_err(|e| anyhow!("解析JSON失败: {}", e))?;
 
     if let Some(arr) = json_data.as_array() {
-        println!("📈 解析到 {} 条外盘期货历史数据", arr.len());
+        log::debug!("解析到 {} 条外盘期货历史数据", arr.len());
 
         for item in arr {
             if item.is_object() {
                 history.push(ForeignFuturesHistData {
                     date: item["date"].as_str().unwrap_or("").to_string(),
-                    open: item["open"]
-                        .as_str()
-                        .or_else(|| item["open"].as_f64().map(|_| ""))
-                        .and_then(|s| {
-                            if s.is_empty() { item["open"].as_f64() } else { s.parse().ok() }
-                        })
-                        .unwrap_or(0.0),
-                    high: item["high"]
-                        .as_str()
-                        .or_else(|| item["high"].as_f64().map(|_| ""))
-                        .and_then(|s| {
-                            if s.is_empty() { item["high"].as_f64() } else { s.parse().ok() }
-                        })
-                        .unwrap_or(0.0),
-                    low: item["low"]
-                        .as_str()
-                        .or_else(|| item["low"].as_f64().map(|_| ""))
-                        .and_then(|s| {
-                            if s.is_empty() { item["low"].as_f64() } else { s.parse().ok() }
-                        })
-                        .unwrap_or(0.0),
-                    close: item["close"]
-                        .as_str()
-                        .or_else(|| item["close"].as_f64().map(|_| ""))
-                        .and_then(|s| {
-                            if s.is_empty() { item["close"].as_f64() } else { s.parse().ok() }
-                        })
-                        .unwrap_or(0.0),
+                    open: json_f64(&item["open"]).unwrap_or(0.0),
+                    high: json_f64(&item["high"]).unwrap_or(0.0),
+                    low: json_f64(&item["low"]).unwrap_or(0.0),
+                    close: json_f64(&item["close"]).unwrap_or(0.0),
                     volume: item["volume"]
                         .as_str()
                         .and_then(|s| s.parse().ok())
                         .or_else(|| item["volume"].as_u64())
                         .unwrap_or(0),
+                    settlement: json_f64(&item["settlement"])
+                        .or_else(|| json_f64(&item["settle"])),
+                    open_interest: json_u64(&item["position"])
+                        .or_else(|| json_u64(&item["hold"]))
+                        .or_else(|| json_u64(&item["open_interest"])),
                 });
             }
         }
@@ -256,18 +357,36 @@ fn parse_foreign_hist_data(data: &str) -> Result<Vec<ForeignFuturesHistData>> {
     Ok(history)
 }
 
+/// 读取字符串或数字形式的浮点数，缺失或无法解析时返回 None
+fn json_f64(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => value.as_f64(),
+    }
+}
+
+/// 读取字符串或数字形式的整数，兼容 "123.0" 这类写法
+fn json_u64(value: &serde_json::Value) -> Option<u64> {
+    match value {
+        serde_json::Value::String(s) => {
+            let s = s.trim();
+            s.parse().ok().or_else(|| s.parse::<f64>().ok().map(|f| f as u64))
+        }
+        _ => value.as_u64().or_else(|| value.as_f64().map(|f| f as u64)),
+    }
+}
+
 /// 获取外盘期货合约详情
 /// 对应 akshare 的 futures_foreign_detail() 函数
 pub async fn get_futures_foreign_detail(symbol: &str) -> Result<ForeignFuturesDetail> {
-    let client = Client::new();
+    let client = http_client();
 
     let url = format!("https://finance.sina.com.cn/futures/quotes/{}.shtml", symbol);
-    println!("📡 请求外盘期货合约详情 URL: {}", url);
+    log::debug!("请求外盘期货合约详情 URL: {}", url);
 
     let response = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -281,7 +400,7 @@ pub async fn get_futures_foreign_detail(symbol: &str) -> Result<ForeignFuturesDe
 }
 
 /// 解析外盘期货合约详情HTML
-fn parse_foreign_detail_html(html: &str) -> Result<ForeignFuturesDetail> {
+pub(crate) fn parse_foreign_detail_html(html: &str) -> Result<ForeignFuturesDetail> {
     let mut items = Vec::new();
 
     let table_re = Regex::new(r"<table[^>]*>([\s\S]*?)</table>").unwrap();
@@ -333,6 +452,135 @@ fn parse_foreign_detail_html(html: &str) -> Result<ForeignFuturesDetail> {
         }
     }
 
-    println!("📊 解析到 {} 条合约详情项", items.len());
+    log::debug!("解析到 {} 条合约详情项", items.len());
     Ok(ForeignFuturesDetail { items })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../../../tests/fixtures/sina_hf_realtime.txt");
+
+    fn parse_one(code: &str) -> FuturesInfo {
+        let mut rows = parse_foreign_futures_data(SAMPLE, &[code.to_string()]).unwrap();
+        assert_eq!(rows.len(), 1, "{} 应解析出一行", code);
+        rows.remove(0)
+    }
+
+    #[test]
+    fn categories_follow_symbol_groups() {
+        assert_eq!(ForeignCategory::from_code("CAD"), ForeignCategory::LmeMetal);
+        assert_eq!(ForeignCategory::from_code("XAU"), ForeignCategory::SpotMetal);
+        assert_eq!(ForeignCategory::from_code("CL"), ForeignCategory::ExchangeFutures);
+        // 品种列表中的每个代码都能归入一个类别，LME 与现货金属的代码都在列表中
+        let codes: Vec<String> = get_foreign_futures_symbols().into_iter().map(|s| s.code).collect();
+        for code in ["CAD", "AHD", "ZSD", "PBD", "NID", "SND", "XAU", "XAG", "XPT", "XPD"] {
+            assert!(codes.iter().any(|c| c == code), "{}", code);
+        }
+    }
+
+    #[test]
+    fn lme_metal_line() {
+        let info = parse_one("CAD");
+        assert_eq!(info.name, "LME铜3个月");
+        assert_eq!(info.current_price, Some(10635.5));
+        assert_eq!(info.high, Some(10660.0));
+        assert_eq!(info.low, Some(10610.0));
+        assert_eq!(info.prev_settlement, Some(10623.5));
+        assert_eq!(info.open, Some(10622.0));
+        assert_eq!(info.open_interest, None);
+        assert_eq!(info.volume, 0);
+    }
+
+    #[test]
+    fn energy_futures_line() {
+        let info = parse_one("CL");
+        assert_eq!(info.current_price, Some(58.92));
+        assert_eq!(info.high, Some(59.72));
+        assert_eq!(info.low, Some(58.68));
+        assert_eq!(info.prev_settlement, Some(59.48));
+        assert_eq!(info.open, Some(59.48));
+        assert_eq!(info.open_interest, Some(459319));
+        assert_eq!(info.volume, 199578);
+    }
+
+    #[test]
+    fn london_gold_line() {
+        let info = parse_one("XAU");
+        assert_eq!(info.current_price, Some(4207.55));
+        assert_eq!(info.high, Some(4218.36));
+        assert_eq!(info.low, Some(4195.12));
+        // 字段7为空，昨收取字段1
+        assert_eq!(info.prev_settlement, Some(4197.9));
+        assert_eq!(info.open, Some(4199.02));
+        assert_eq!(info.open_interest, None);
+        assert_eq!(info.volume, 0);
+    }
+
+    #[test]
+    fn hist_parses_optional_settlement_and_open_interest() {
+        let data = r#"var _GC=([
+            {"date":"2025-12-04","open":"4200.5","high":"4230.1","low":"4190.0","close":"4221.3","volume":"152345","settlement":"4219.8","position":"512345"},
+            {"date":"2025-12-05","open":4221.3,"high":4250,"low":4210.2,"close":4244.6,"volume":160001,"settle":4243.1,"hold":"513000.0"},
+            {"date":"2025-12-08","open":"4244.6","high":"4260.0","low":"4238.0","close":"4255.0","volume":"99999"}
+        ]);"#;
+        let history = parse_foreign_hist_data(data).unwrap();
+        assert_eq!(history.len(), 3);
+
+        assert_eq!(history[0].open, 4200.5);
+        assert_eq!(history[0].volume, 152345);
+        assert_eq!(history[0].settlement, Some(4219.8));
+        assert_eq!(history[0].open_interest, Some(512345));
+
+        // 数字形式与 settle / hold 别名
+        assert_eq!(history[1].high, 4250.0);
+        assert_eq!(history[1].volume, 160001);
+        assert_eq!(history[1].settlement, Some(4243.1));
+        assert_eq!(history[1].open_interest, Some(513000));
+
+        // 没有结算价和持仓量时为 None
+        assert_eq!(history[2].settlement, None);
+        assert_eq!(history[2].open_interest, None);
+    }
+
+    #[test]
+    fn hist_without_array_fails() {
+        assert!(parse_foreign_hist_data("var _GC=null;").is_err());
+    }
+
+    #[test]
+    fn rows_are_labelled_by_variable_name_not_position() {
+        // 请求顺序与响应顺序相反，且 GC 在响应中缺失
+        let codes: Vec<String> = ["XAU", "GC", "CAD"].iter().map(|c| c.to_string()).collect();
+        let rows = parse_foreign_futures_data(SAMPLE, &codes).unwrap();
+        let labelled: Vec<(&str, Option<f64>)> =
+            rows.iter().map(|r| (r.symbol.as_str(), r.current_price)).collect();
+        assert_eq!(labelled, [("XAU", Some(4207.55)), ("CAD", Some(10635.5))]);
+    }
+
+    #[test]
+    fn validate_foreign_codes_dedupes_and_reports_unknown() {
+        let codes: Vec<String> = ["gc", "XYZ", "GC ", " cl", ""].iter().map(|c| c.to_string()).collect();
+        let (valid, unknown) = validate_foreign_codes(&codes);
+        assert_eq!(valid, ["GC", "CL"]);
+        assert_eq!(unknown, ["XYZ"]);
+    }
+
+    #[test]
+    fn parse_foreign_detail_html_fixture() {
+        let html = include_str!("../../../tests/fixtures/sina_foreign_detail.html");
+        let detail = parse_foreign_detail_html(html).unwrap();
+        assert_eq!(detail.items.len(), 7);
+
+        let value = |name: &str| {
+            detail.items.iter().find(|item| item.name == name).map(|item| item.value.as_str())
+        };
+        assert_eq!(value("交易品种"), Some("伦敦金"));
+        assert_eq!(value("交易单位"), Some("100盎司/手"));
+        assert_eq!(value("最小变动价位"), Some("0.01美元/盎司"));
+        // 单元格内的标签被去除
+        assert_eq!(value("交易时间"), Some("周一至周五 07:00-次日05:00"));
+        assert_eq!(value("上市交易所"), Some("伦敦金银市场协会（LBMA）"));
+    }
+}
@@ -2,23 +2,52 @@
 
 use crate::models::{Futures99Symbol, FuturesInventory99};
 use anyhow::{anyhow, Result};
-use reqwest::Client;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
-use super::common::QH99_STOCK_URL;
+use super::common::{insecure_http_client, parse_json, QH99_STOCK_URL};
+use super::limiter::LimitedSend;
+use super::mock::mock_enabled;
+
+/// 99期货网品种映射缓存有效期
+const SYMBOL_99_TTL: Duration = Duration::from_secs(3600);
+
+/// 品种映射表及其获取时间
+type CachedSymbols99 = (Instant, Vec<Futures99Symbol>);
+
+/// 进程内共享的99期货网品种映射缓存
+static SYMBOL_99_CACHE: OnceLock<RwLock<Option<CachedSymbols99>>> = OnceLock::new();
 
 /// 获取99期货网品种映射表
 /// 对应 akshare 的 __get_99_symbol_map() 函数
+///
+/// 结果在进程内共享缓存 SYMBOL_99_TTL，过期后由下一次请求刷新；模拟模式下不读取缓存
 pub async fn get_99_symbol_map() -> Result<Vec<Futures99Symbol>> {
-    let client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .build()?;
+    let cache = SYMBOL_99_CACHE.get_or_init(|| RwLock::new(None));
+    if !mock_enabled() {
+        if let Some((cached_at, symbols)) =
+            cache.read().unwrap_or_else(|e| e.into_inner()).as_ref()
+        {
+            if cached_at.elapsed() < SYMBOL_99_TTL {
+                return Ok(symbols.clone());
+            }
+        }
+    }
 
-    println!("📡 请求99期货网品种映射 URL: {}", QH99_STOCK_URL);
+    let symbols = fetch_99_symbol_map().await?;
+    *cache.write().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), symbols.clone()));
+    Ok(symbols)
+}
+
+/// 请求99期货网页面并解析品种映射表
+async fn fetch_99_symbol_map() -> Result<Vec<Futures99Symbol>> {
+    let client = insecure_http_client(QH99_STOCK_URL)?;
+
+    log::debug!("请求99期货网品种映射 URL: {}", QH99_STOCK_URL);
 
     let response = client
         .get(QH99_STOCK_URL)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -37,8 +66,7 @@ pub async fn get_99_symbol_map() -> Result<Vec<Futures99Symbol>> {
         .ok_or_else(|| anyhow!("未找到__NEXT_DATA__脚本标签"))?;
 
     let json_text = script.text().collect::<String>();
-    let json_data: serde_json::Value =
-        serde_json::from_str(&json_text).map_err(|e| anyhow!("解析JSON失败: {}", e))?;
+    let json_data = parse_json("99期货网品种映射", QH99_STOCK_URL, &json_text)?;
 
     let mut symbols = Vec::new();
 
@@ -58,7 +86,7 @@ pub async fn get_99_symbol_map() -> Result<Vec<Futures99Symbol>> {
         }
     }
 
-    println!("📊 解析到 {} 个品种映射", symbols.len());
+    log::debug!("解析到 {} 个品种映射", symbols.len());
     Ok(symbols)
 }
 
@@ -68,25 +96,32 @@ pub async fn get_99_symbol_map() -> Result<Vec<Futures99Symbol>> {
 pub async fn get_futures_inventory_99(symbol: &str) -> Result<Vec<FuturesInventory99>> {
     let symbols = get_99_symbol_map().await?;
 
-    let product_id = symbols
-        .iter()
-        .find(|s| s.name == symbol || s.code.eq_ignore_ascii_case(symbol))
-        .map(|s| s.product_id)
+    let product_id = find_99_product_id(&symbols, symbol)
         .ok_or_else(|| anyhow!("未找到品种 {} 对应的编号", symbol))?;
 
-    println!("📡 品种 {} 对应的ID: {}", symbol, product_id);
+    log::debug!("品种 {} 对应的ID: {}", symbol, product_id);
 
-    let client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .build()?;
+    get_futures_inventory_99_by_id(product_id).await
+}
+
+/// 在品种映射表中按名称或代码（忽略大小写）查找品种编号
+pub fn find_99_product_id(symbols: &[Futures99Symbol], symbol: &str) -> Option<i64> {
+    symbols
+        .iter()
+        .find(|s| s.name == symbol || s.code.eq_ignore_ascii_case(symbol))
+        .map(|s| s.product_id)
+}
 
+/// 按品种编号获取99期货网库存数据
+pub async fn get_futures_inventory_99_by_id(product_id: i64) -> Result<Vec<FuturesInventory99>> {
     let url = format!("{}?productId={}", QH99_STOCK_URL, product_id);
-    println!("📡 请求99期货网库存数据 URL: {}", url);
+    let client = insecure_http_client(&url)?;
+
+    log::debug!("请求99期货网库存数据 URL: {}", url);
 
     let response = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -105,8 +140,7 @@ pub async fn get_futures_inventory_99(symbol: &str) -> Result<Vec<FuturesInvento
         .ok_or_else(|| anyhow!("未找到__NEXT_DATA__脚本标签"))?;
 
     let json_text = script.text().collect::<String>();
-    let json_data: serde_json::Value =
-        serde_json::from_str(&json_text).map_err(|e| anyhow!("解析JSON失败: {}", e))?;
+    let json_data = parse_json("99期货网库存", &url, &json_text)?;
 
     let mut inventory_list = Vec::new();
 
@@ -144,6 +178,86 @@ pub async fn get_futures_inventory_99(symbol: &str) -> Result<Vec<FuturesInvento
 
     inventory_list.sort_by(|a, b| a.date.cmp(&b.date));
 
-    println!("📊 解析到 {} 条库存数据", inventory_list.len());
+    log::debug!("解析到 {} 条库存数据", inventory_list.len());
     Ok(inventory_list)
 }
+
+/// 按日期范围过滤库存数据
+/// start_date / end_date: 格式 YYYYMMDD，闭区间
+/// limit: 过滤后仅保留最近的 N 条
+pub fn filter_inventory_99(
+    data: Vec<FuturesInventory99>,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+    limit: Option<usize>,
+) -> Vec<FuturesInventory99> {
+    let mut filtered: Vec<FuturesInventory99> = data
+        .into_iter()
+        .filter(|item| {
+            let date = item.date.replace('-', "");
+            start_date.is_none_or(|start| date.as_str() >= start)
+                && end_date.is_none_or(|end| date.as_str() <= end)
+        })
+        .collect();
+
+    if let Some(limit) = limit {
+        if filtered.len() > limit {
+            filtered.drain(..filtered.len() - limit);
+        }
+    }
+
+    filtered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inventory(dates: &[&str]) -> Vec<FuturesInventory99> {
+        dates
+            .iter()
+            .map(|date| FuturesInventory99 { date: date.to_string(), close_price: Some(4000.0), inventory: Some(1.0) })
+            .collect()
+    }
+
+    fn dates(data: &[FuturesInventory99]) -> Vec<&str> {
+        data.iter().map(|item| item.date.as_str()).collect()
+    }
+
+    const DATES: [&str; 5] = ["2024-01-05", "2024-01-12", "2024-01-19", "2024-01-26", "2024-02-02"];
+
+    #[test]
+    fn date_filter_is_inclusive_on_both_ends() {
+        let data = filter_inventory_99(inventory(&DATES), Some("20240112"), Some("20240126"), None);
+        assert_eq!(dates(&data), ["2024-01-12", "2024-01-19", "2024-01-26"]);
+
+        let data = filter_inventory_99(inventory(&DATES), Some("20240120"), None, None);
+        assert_eq!(dates(&data), ["2024-01-26", "2024-02-02"]);
+
+        let data = filter_inventory_99(inventory(&DATES), None, Some("20240105"), None);
+        assert_eq!(dates(&data), ["2024-01-05"]);
+
+        assert!(filter_inventory_99(inventory(&DATES), Some("20240301"), None, None).is_empty());
+    }
+
+    #[test]
+    fn limit_keeps_the_most_recent_rows_after_filtering() {
+        let data = filter_inventory_99(inventory(&DATES), None, Some("20240126"), Some(2));
+        assert_eq!(dates(&data), ["2024-01-19", "2024-01-26"]);
+
+        assert_eq!(filter_inventory_99(inventory(&DATES), None, None, Some(10)).len(), 5);
+        assert!(filter_inventory_99(inventory(&DATES), None, None, Some(0)).is_empty());
+    }
+
+    #[test]
+    fn product_id_matches_name_or_code_ignoring_case() {
+        let symbols = vec![
+            Futures99Symbol { product_id: 1, name: "豆一".to_string(), code: "A".to_string() },
+            Futures99Symbol { product_id: 7, name: "螺纹钢".to_string(), code: "RB".to_string() },
+        ];
+        assert_eq!(find_99_product_id(&symbols, "螺纹钢"), Some(7));
+        assert_eq!(find_99_product_id(&symbols, "rb"), Some(7));
+        assert_eq!(find_99_product_id(&symbols, "a"), Some(1));
+        assert_eq!(find_99_product_id(&symbols, "铜"), None);
+    }
+}
@@ -1,32 +1,38 @@
 //! K线数据相关函数
 
+use crate::config::DefaultsConfig;
 use crate::models::{FuturesHistoryData, FuturesQuery};
 use anyhow::{anyhow, Result};
-use reqwest::Client;
+use std::collections::BTreeMap;
+use std::future::Future;
 
-use super::common::{SINA_FUTURES_DAILY_API, SINA_FUTURES_MINUTE_API};
+use super::common::{http_client, parse_context, price_field, price_token, SINA_FUTURES_DAILY_API, SINA_FUTURES_MINUTE_API};
+use super::limiter::LimitedSend;
+use super::mock::fixture;
 
 /// 获取期货日K线历史数据
 /// 对应 akshare 的 futures_zh_daily_sina() 函数
+///
+/// 未指定 limit 时取 defaults.history_limit 条
 pub async fn get_futures_history(
     symbol: &str,
     query: &FuturesQuery,
+    defaults: &DefaultsConfig,
 ) -> Result<Vec<FuturesHistoryData>> {
-    let client = Client::new();
-    let limit = query.limit.unwrap_or(30);
+    let limit = query.limit.unwrap_or(defaults.history_limit);
+    if let Some(bars) = fixture("futures_history") {
+        return bars.map(|bars| mock_bars(bars, symbol, limit));
+    }
+
+    let client = http_client();
 
     let full_url = format!("{}?symbol={}", SINA_FUTURES_DAILY_API, symbol);
-    println!("📡 请求日K线数据 URL: {}", full_url);
+    log::debug!("请求日K线数据 URL: {}", full_url);
 
     let response = client
         .get(SINA_FUTURES_DAILY_API)
         .query(&[("symbol", symbol)])
-        .header("Referer", "https://finance.sina.com.cn/")
-        .header(
-            "User-Agent",
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-        )
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -35,8 +41,56 @@ pub async fn get_futures_history(
 
     let text = response.text().await?;
     let preview: String = text.chars().take(300).collect();
-    println!("📥 原始响应数据: {}", preview);
-    parse_sina_history_data(&text, symbol, limit)
+    log::debug!("原始响应数据: {}", preview);
+    let bars = parse_context(parse_sina_history_data(&text, symbol, usize::MAX), "新浪日K线", &full_url, &text)?;
+    Ok(select_bars(bars, query.start_date.as_deref(), query.end_date.as_deref(), limit))
+}
+
+/// 按 start_date/end_date（YYYYMMDD，含两端）过滤日K线，再取最近的 limit 条
+fn select_bars(
+    mut bars: Vec<FuturesHistoryData>,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+    limit: usize,
+) -> Vec<FuturesHistoryData> {
+    bars.retain(|bar| {
+        let date = bar.date.replace('-', "");
+        start_date.is_none_or(|start| date.as_str() >= start)
+            && end_date.is_none_or(|end| date.as_str() <= end)
+    });
+    let skip = bars.len().saturating_sub(limit);
+    bars.split_off(skip)
+}
+
+/// 分钟K线支持的周期（分钟）
+pub const MINUTE_PERIODS: &[u32] = &[1, 5, 15, 30, 60];
+
+/// 并发获取多个周期的分钟K线，按周期返回，任一周期失败时返回该错误
+pub async fn get_futures_minute_multi(
+    symbol: &str,
+    periods: &[u32],
+) -> Result<BTreeMap<u32, Vec<FuturesHistoryData>>> {
+    minute_multi_with(periods, |period| async move {
+        get_futures_minute_data(symbol, &period.to_string()).await
+    })
+    .await
+}
+
+/// 对每个周期并发调用 fetch，结果以周期为键
+async fn minute_multi_with<F, Fut>(
+    periods: &[u32],
+    fetch: F,
+) -> Result<BTreeMap<u32, Vec<FuturesHistoryData>>>
+where
+    F: Fn(u32) -> Fut,
+    Fut: Future<Output = Result<Vec<FuturesHistoryData>>>,
+{
+    let results = futures::future::join_all(periods.iter().map(|&period| {
+        let bars = fetch(period);
+        async move { Ok::<_, anyhow::Error>((period, bars.await?)) }
+    }))
+    .await;
+    results.into_iter().collect()
 }
 
 /// 获取期货分钟K线数据
@@ -46,23 +100,22 @@ pub async fn get_futures_minute_data(
     symbol: &str,
     period: &str,
 ) -> Result<Vec<FuturesHistoryData>> {
-    let client = Client::new();
+    if let Some(bars) = fixture("futures_minute") {
+        return bars.map(|bars| mock_bars(bars, symbol, usize::MAX));
+    }
+
+    let client = http_client();
 
     let full_url = format!(
         "{}?symbol={}&type={}",
         SINA_FUTURES_MINUTE_API, symbol, period
     );
-    println!("📡 请求分钟K线数据 URL: {}", full_url);
+    log::debug!("请求分钟K线数据 URL: {}", full_url);
 
     let response = client
         .get(SINA_FUTURES_MINUTE_API)
         .query(&[("symbol", symbol), ("type", period)])
-        .header("Referer", "https://finance.sina.com.cn/")
-        .header(
-            "User-Agent",
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-        )
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -71,12 +124,12 @@ pub async fn get_futures_minute_data(
 
     let text = response.text().await?;
     let preview: String = text.chars().take(300).collect();
-    println!("📥 原始响应数据: {}", preview);
-    parse_sina_minute_data(&text, symbol)
+    log::debug!("原始响应数据: {}", preview);
+    parse_context(parse_sina_minute_data(&text, symbol), "新浪分钟K线", &full_url, &text)
 }
 
 /// 解析新浪期货日K线历史数据
-fn parse_sina_history_data(
+pub(crate) fn parse_sina_history_data(
     data: &str,
     symbol: &str,
     limit: usize,
@@ -87,18 +140,18 @@ fn parse_sina_history_data(
     let end = data.rfind("])");
 
     if start.is_none() || end.is_none() {
-        println!("❌ 未找到有效的JSON数据边界");
+        log::debug!("未找到有效的JSON数据边界");
         return Err(anyhow!("无效的历史数据格式"));
     }
 
     let json_str = &data[start.unwrap() + 1..end.unwrap() + 1];
-    println!("📊 解析JSON数据，长度: {} 字节", json_str.len());
+    log::debug!("解析JSON数据，长度: {} 字节", json_str.len());
 
     let json_data: serde_json::Value =
         serde_json::from_str(json_str).map_err(|e| anyhow!("解析JSON失败: {}", e))?;
 
     if let Some(arr) = json_data.as_array() {
-        println!("📈 解析到 {} 条K线数据", arr.len());
+        log::debug!("解析到 {} 条K线数据", arr.len());
 
         let start_idx = if arr.len() > limit {
             arr.len() - limit
@@ -109,10 +162,10 @@ fn parse_sina_history_data(
         for item in arr.iter().skip(start_idx) {
             if item.is_object() {
                 let date = item["d"].as_str().unwrap_or("").to_string();
-                let open = item["o"].as_str().unwrap_or("0").parse().unwrap_or(0.0);
-                let high = item["h"].as_str().unwrap_or("0").parse().unwrap_or(0.0);
-                let low = item["l"].as_str().unwrap_or("0").parse().unwrap_or(0.0);
-                let close = item["c"].as_str().unwrap_or("0").parse().unwrap_or(0.0);
+                let open = price_field("open", item["o"].as_str());
+                let high = price_field("high", item["h"].as_str());
+                let low = price_field("low", item["l"].as_str());
+                let close = price_field("close", item["c"].as_str());
                 let volume = item["v"].as_str().unwrap_or("0").parse().unwrap_or(0);
                 let open_interest = item["p"].as_str().unwrap_or("0").parse().ok();
                 let settlement = item["s"].as_str().unwrap_or("0").parse().ok();
@@ -127,19 +180,29 @@ fn parse_sina_history_data(
                     volume,
                     open_interest,
                     settlement,
+                    open_str: item["o"].as_str().and_then(price_token),
+                    high_str: item["h"].as_str().and_then(price_token),
+                    low_str: item["l"].as_str().and_then(price_token),
+                    close_str: item["c"].as_str().and_then(price_token),
+                    settlement_str: item["s"].as_str().and_then(price_token),
                 });
             } else if let Some(fields) = item.as_array() {
                 if fields.len() >= 8 {
                     history.push(FuturesHistoryData {
                         symbol: symbol.to_string(),
                         date: fields[0].as_str().unwrap_or("").to_string(),
-                        open: fields[1].as_str().unwrap_or("0").parse().unwrap_or(0.0),
-                        high: fields[2].as_str().unwrap_or("0").parse().unwrap_or(0.0),
-                        low: fields[3].as_str().unwrap_or("0").parse().unwrap_or(0.0),
-                        close: fields[4].as_str().unwrap_or("0").parse().unwrap_or(0.0),
+                        open: price_field("open", fields[1].as_str()),
+                        high: price_field("high", fields[2].as_str()),
+                        low: price_field("low", fields[3].as_str()),
+                        close: price_field("close", fields[4].as_str()),
                         volume: fields[5].as_str().unwrap_or("0").parse().unwrap_or(0),
                         open_interest: fields[6].as_str().unwrap_or("0").parse().ok(),
                         settlement: fields[7].as_str().unwrap_or("0").parse().ok(),
+                        open_str: fields[1].as_str().and_then(price_token),
+                        high_str: fields[2].as_str().and_then(price_token),
+                        low_str: fields[3].as_str().and_then(price_token),
+                        close_str: fields[4].as_str().and_then(price_token),
+                        settlement_str: fields[7].as_str().and_then(price_token),
                     });
                 }
             }
@@ -150,54 +213,64 @@ fn parse_sina_history_data(
 }
 
 /// 解析新浪期货分钟K线数据
-fn parse_sina_minute_data(data: &str, symbol: &str) -> Result<Vec<FuturesHistoryData>> {
+pub(crate) fn parse_sina_minute_data(data: &str, symbol: &str) -> Result<Vec<FuturesHistoryData>> {
     let mut history = Vec::new();
 
     let start = data.find("([");
     let end = data.rfind("])");
 
     if start.is_none() || end.is_none() {
-        println!("❌ 未找到有效的JSON数据边界");
+        log::debug!("未找到有效的JSON数据边界");
         return Err(anyhow!("无效的分钟数据格式"));
     }
 
     let json_str = &data[start.unwrap() + 1..end.unwrap() + 1];
-    println!("📊 解析JSON数据，长度: {} 字节", json_str.len());
+    log::debug!("解析JSON数据，长度: {} 字节", json_str.len());
 
     let json_data: serde_json::Value =
         serde_json::from_str(json_str).map_err(|e| anyhow!("解析JSON失败: {}", e))?;
 
     if let Some(arr) = json_data.as_array() {
-        println!("📈 解析到 {} 条K线数据", arr.len());
+        log::debug!("解析到 {} 条K线数据", arr.len());
 
         for item in arr.iter() {
             if item.is_object() {
                 history.push(FuturesHistoryData {
                     symbol: symbol.to_string(),
                     date: item["d"].as_str().unwrap_or("").to_string(),
-                    open: item["o"].as_str().unwrap_or("0").parse().unwrap_or(0.0),
-                    high: item["h"].as_str().unwrap_or("0").parse().unwrap_or(0.0),
-                    low: item["l"].as_str().unwrap_or("0").parse().unwrap_or(0.0),
-                    close: item["c"].as_str().unwrap_or("0").parse().unwrap_or(0.0),
+                    open: price_field("open", item["o"].as_str()),
+                    high: price_field("high", item["h"].as_str()),
+                    low: price_field("low", item["l"].as_str()),
+                    close: price_field("close", item["c"].as_str()),
                     volume: item["v"].as_str().unwrap_or("0").parse().unwrap_or(0),
                     open_interest: item["p"].as_str().unwrap_or("0").parse().ok(),
                     settlement: None,
+                    open_str: item["o"].as_str().and_then(price_token),
+                    high_str: item["h"].as_str().and_then(price_token),
+                    low_str: item["l"].as_str().and_then(price_token),
+                    close_str: item["c"].as_str().and_then(price_token),
+                    settlement_str: None,
                 });
             } else if let Some(fields) = item.as_array() {
                 if fields.len() >= 6 {
                     history.push(FuturesHistoryData {
                         symbol: symbol.to_string(),
                         date: fields[0].as_str().unwrap_or("").to_string(),
-                        open: fields[1].as_str().unwrap_or("0").parse().unwrap_or(0.0),
-                        high: fields[2].as_str().unwrap_or("0").parse().unwrap_or(0.0),
-                        low: fields[3].as_str().unwrap_or("0").parse().unwrap_or(0.0),
-                        close: fields[4].as_str().unwrap_or("0").parse().unwrap_or(0.0),
+                        open: price_field("open", fields[1].as_str()),
+                        high: price_field("high", fields[2].as_str()),
+                        low: price_field("low", fields[3].as_str()),
+                        close: price_field("close", fields[4].as_str()),
                         volume: fields[5].as_str().unwrap_or("0").parse().unwrap_or(0),
                         open_interest: fields
                             .get(6)
                             .and_then(|v| v.as_str())
                             .and_then(|s| s.parse().ok()),
                         settlement: None,
+                        open_str: fields[1].as_str().and_then(price_token),
+                        high_str: fields[2].as_str().and_then(price_token),
+                        low_str: fields[3].as_str().and_then(price_token),
+                        close_str: fields[4].as_str().and_then(price_token),
+                        settlement_str: None,
                     });
                 }
             }
@@ -206,3 +279,118 @@ fn parse_sina_minute_data(data: &str, symbol: &str) -> Result<Vec<FuturesHistory
 
     Ok(history)
 }
+
+/// 模拟模式下将 fixture K线替换为请求的合约代码，并只保留最近 limit 条
+fn mock_bars(bars: Vec<FuturesHistoryData>, symbol: &str, limit: usize) -> Vec<FuturesHistoryData> {
+    let skip = bars.len().saturating_sub(limit);
+    bars.into_iter()
+        .skip(skip)
+        .map(|bar| FuturesHistoryData {
+            symbol: symbol.to_string(),
+            ..bar
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{self, DefaultsConfig};
+
+    #[tokio::test]
+    async fn history_default_limit_comes_from_config() {
+        config::init_mock_for_tests();
+        let query = FuturesQuery::default();
+
+        let bars = get_futures_history("RB2601", &query, &DefaultsConfig::default()).await.unwrap();
+        assert_eq!(bars.len(), 30);
+
+        let defaults = DefaultsConfig { history_limit: 7, ..Default::default() };
+        let bars = get_futures_history("RB2601", &query, &defaults).await.unwrap();
+        assert_eq!(bars.len(), 7);
+
+        // 显式 limit 优先于配置
+        let query = FuturesQuery::builder().limit(3).build();
+        assert_eq!(get_futures_history("RB2601", &query, &defaults).await.unwrap().len(), 3);
+    }
+
+    fn bar(date: &str) -> FuturesHistoryData {
+        FuturesHistoryData {
+            symbol: "CU2405".to_string(),
+            date: date.to_string(),
+            open: None,
+            high: None,
+            low: None,
+            close: None,
+            volume: 0,
+            open_interest: None,
+            settlement: None,
+            open_str: None,
+            high_str: None,
+            low_str: None,
+            close_str: None,
+            settlement_str: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn minute_multi_keys_bars_by_period() {
+        let by_period = minute_multi_with(&[15, 1, 5], |period| async move {
+            Ok((0..period).map(|_| bar(&format!("2025-01-02 09:{:02}:00", period))).collect())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(by_period.keys().copied().collect::<Vec<_>>(), vec![1, 5, 15]);
+        for (period, bars) in &by_period {
+            assert_eq!(bars.len(), *period as usize);
+            assert_eq!(bars[0].date, format!("2025-01-02 09:{:02}:00", period));
+        }
+    }
+
+    #[tokio::test]
+    async fn minute_multi_fails_when_any_period_fails() {
+        let err = minute_multi_with(&[1, 5], |period| async move {
+            if period == 5 {
+                Err(anyhow!("5 分钟数据失败"))
+            } else {
+                Ok(vec![bar("2025-01-02 09:01:00")])
+            }
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(err.to_string(), "5 分钟数据失败");
+    }
+
+    fn dates(bars: &[FuturesHistoryData]) -> Vec<&str> {
+        bars.iter().map(|b| b.date.as_str()).collect()
+    }
+
+    #[test]
+    fn history_empty_price_is_none_and_zero_is_zero() {
+        let data = r#"var _CU2405=([{"d":"2024-03-01","o":"","h":"0","l":"69800","c":"70100","v":"100","p":"2000","s":"70000"}]);"#;
+        let bars = parse_sina_history_data(data, "CU2405", 10).unwrap();
+        assert_eq!(bars[0].open, None);
+        assert_eq!(bars[0].high, Some(0.0));
+        assert_eq!(bars[0].close, Some(70100.0));
+    }
+
+    #[test]
+    fn select_bars_filters_range_before_limit() {
+        let bars = || -> Vec<_> {
+            ["2024-01-02", "2024-01-03", "2024-01-04", "2024-01-05"]
+                .into_iter()
+                .map(bar)
+                .collect()
+        };
+
+        let picked = select_bars(bars(), Some("20240103"), Some("20240104"), 30);
+        assert_eq!(dates(&picked), ["2024-01-03", "2024-01-04"]);
+
+        let picked = select_bars(bars(), None, Some("20240104"), 2);
+        assert_eq!(dates(&picked), ["2024-01-03", "2024-01-04"]);
+
+        let picked = select_bars(bars(), None, None, 1);
+        assert_eq!(dates(&picked), ["2024-01-05"]);
+    }
+}
@@ -0,0 +1,410 @@
+//! 新浪请求全局并发限制
+//!
+//! 所有发往新浪域名的请求共享一个进程级信号量，避免突发并发触发 IP 封禁。
+//! 同时按域名接入熔断，上游故障期间直接失败而不等待超时。
+//! 新浪返回 456/403 或"拒绝访问"页面时记录封禁截止时间，冷却期内所有新浪请求直接返回
+//! RateLimited，避免继续请求延长封禁。发出请求前按目标域名补齐 Referer / Host 等请求头（见 sources）
+
+use anyhow::bail;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use super::breaker::{check_upstream, record_failure, record_success};
+use super::mock::mock_enabled;
+use super::provenance::record_upstream;
+use super::source_health::{record_source_error, record_source_success};
+use super::sources::apply_source_headers;
+use crate::config;
+
+/// 新浪请求限流器
+struct SinaLimiter {
+    semaphore: Semaphore,
+    max_permits: AtomicUsize,
+    queue_warn_ms: AtomicU64,
+    /// 累计请求数
+    total_requests: AtomicU64,
+    /// 排队超过告警阈值的请求数
+    slow_acquires: AtomicU64,
+    /// 最长排队时间（毫秒）
+    max_wait_ms: AtomicU64,
+    /// 新浪 IP 封禁截止时间（Unix 毫秒，0 表示未封禁）
+    banned_until_ms: AtomicU64,
+    /// 累计检测到封禁的次数
+    bans: AtomicU64,
+    /// 封禁冷却期内被直接拒绝的请求数
+    ban_rejected: AtomicU64,
+}
+
+impl SinaLimiter {
+    fn new(max_permits: usize, queue_warn_ms: u64) -> Self {
+        SinaLimiter {
+            semaphore: Semaphore::new(max_permits),
+            max_permits: AtomicUsize::new(max_permits),
+            queue_warn_ms: AtomicU64::new(queue_warn_ms),
+            total_requests: AtomicU64::new(0),
+            slow_acquires: AtomicU64::new(0),
+            max_wait_ms: AtomicU64::new(0),
+            banned_until_ms: AtomicU64::new(0),
+            bans: AtomicU64::new(0),
+            ban_rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// 封禁冷却剩余秒数（向上取整），未封禁时返回 None
+    fn ban_remaining_secs(&self) -> Option<u64> {
+        let until = self.banned_until_ms.load(Ordering::Relaxed);
+        let now = now_ms();
+        (until > now).then(|| (until - now).div_ceil(1000))
+    }
+
+    /// 记录封禁，冷却 cooldown_secs 秒（0 表示不启用）；冷却期内再次封禁时延长截止时间但不重复计数
+    fn mark_banned(&self, cooldown_secs: u64) -> RateLimited {
+        if cooldown_secs > 0 {
+            let until = now_ms() + cooldown_secs * 1000;
+            let previous = self.banned_until_ms.fetch_max(until, Ordering::Relaxed);
+            if previous <= now_ms() {
+                self.bans.fetch_add(1, Ordering::Relaxed);
+                log::warn!("🚫 检测到新浪 IP 封禁，{} 秒内暂停请求新浪", cooldown_secs);
+            }
+        }
+        RateLimited {
+            retry_after_secs: self.ban_remaining_secs().unwrap_or(cooldown_secs),
+        }
+    }
+
+    /// 封禁冷却期内直接失败
+    fn check_ban(&self) -> Result<(), RateLimited> {
+        match self.ban_remaining_secs() {
+            Some(retry_after_secs) => {
+                self.ban_rejected.fetch_add(1, Ordering::Relaxed);
+                Err(RateLimited { retry_after_secs })
+            }
+            None => Ok(()),
+        }
+    }
+
+    async fn acquire(&self, url: &str) -> SemaphorePermit<'_> {
+        let start = Instant::now();
+        let permit = self
+            .semaphore
+            .acquire()
+            .await
+            .expect("新浪请求信号量不会被关闭");
+
+        let waited_ms = start.elapsed().as_millis() as u64;
+        self.total_requests.fetch_add(1, Ordering::Relaxed);
+        self.max_wait_ms.fetch_max(waited_ms, Ordering::Relaxed);
+        if waited_ms > self.queue_warn_ms.load(Ordering::Relaxed) {
+            self.slow_acquires.fetch_add(1, Ordering::Relaxed);
+            log::warn!("⏳ 新浪请求排队 {}ms: {}", waited_ms, url);
+        }
+
+        permit
+    }
+
+    fn resize(&'static self, max_permits: usize, queue_warn_ms: u64) {
+        let new_max = max_permits.max(1);
+        let old_max = self.max_permits.swap(new_max, Ordering::Relaxed);
+        self.queue_warn_ms.store(queue_warn_ms, Ordering::Relaxed);
+
+        if new_max > old_max {
+            self.semaphore.add_permits(new_max - old_max);
+        } else if new_max < old_max {
+            let shrink = old_max - new_max;
+            let forgotten = self.semaphore.forget_permits(shrink);
+            let remaining = (shrink - forgotten) as u32;
+            if remaining > 0 {
+                tokio::spawn(async move {
+                    if let Ok(permits) = self.semaphore.acquire_many(remaining).await {
+                        permits.forget();
+                    }
+                });
+            }
+        }
+
+        if new_max != old_max {
+            log::info!("新浪请求并发上限调整: {} -> {}", old_max, new_max);
+        }
+    }
+
+    fn stats(&self) -> SinaLimiterStats {
+        let max_permits = self.max_permits.load(Ordering::Relaxed);
+        let available = self.semaphore.available_permits();
+        SinaLimiterStats {
+            max_permits,
+            in_use: max_permits.saturating_sub(available),
+            available,
+            total_requests: self.total_requests.load(Ordering::Relaxed),
+            slow_acquires: self.slow_acquires.load(Ordering::Relaxed),
+            max_wait_ms: self.max_wait_ms.load(Ordering::Relaxed),
+            ban_remaining_secs: self.ban_remaining_secs().unwrap_or(0),
+            bans: self.bans.load(Ordering::Relaxed),
+            ban_rejected: self.ban_rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+static SINA_LIMITER: OnceLock<SinaLimiter> = OnceLock::new();
+
+fn limiter() -> &'static SinaLimiter {
+    SINA_LIMITER.get_or_init(|| {
+        let (max_permits, queue_warn_ms) =
+            config::read(|c| (c.api.sina_max_concurrency.max(1), c.api.sina_queue_warn_ms));
+        log::info!("新浪请求并发上限: {}", max_permits);
+        SinaLimiter::new(max_permits, queue_warn_ms)
+    })
+}
+
+/// 新浪 IP 封禁冷却中，请求未发出
+#[derive(Debug)]
+pub struct RateLimited {
+    /// 距离封禁冷却结束的剩余秒数
+    pub retry_after_secs: u64,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "IP被新浪封禁，冷却中，请 {} 秒后重试",
+            self.retry_after_secs
+        )
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// 记录新浪 IP 封禁，冷却时间为 api.sina_ban_cooldown_secs（0 表示不启用）
+///
+/// 返回对应的 RateLimited 错误，便于调用方在检测到封禁页面时直接返回
+pub(crate) fn mark_sina_banned() -> RateLimited {
+    limiter().mark_banned(config::read(|c| c.api.sina_ban_cooldown_secs))
+}
+
+/// 新浪封禁冷却期内直接失败
+pub(crate) fn check_sina_ban() -> Result<(), RateLimited> {
+    limiter().check_ban()
+}
+
+/// 判断是否为新浪域名
+pub(crate) fn is_sina_host(host: &str) -> bool {
+    host == "sina.com.cn"
+        || host.ends_with(".sina.com.cn")
+        || host.ends_with(".sinajs.cn")
+        || host.ends_with(".sina.cn")
+}
+
+/// 获取新浪请求许可，排队超过阈值时记录日志
+async fn acquire_sina_permit(url: &str) -> SemaphorePermit<'static> {
+    limiter().acquire(url).await
+}
+
+/// 调整新浪请求并发上限和排队告警阈值（配置热更新时调用）
+///
+/// 缩容时先回收空闲许可，其余许可在被归还后回收
+pub fn resize_sina_limiter(max_permits: usize, queue_warn_ms: u64) {
+    limiter().resize(max_permits, queue_warn_ms);
+}
+
+/// 带全局并发限制和熔断的请求发送
+///
+/// 新浪域名的请求需先获取许可，其他域名直接发送；上游熔断中时不发出请求，
+/// 返回 UpstreamUnavailable 错误；新浪封禁冷却中时返回 RateLimited 错误。上游正常响应时记录为当前响应的数据来源
+pub trait LimitedSend {
+    async fn send_limited(self) -> anyhow::Result<Response>;
+}
+
+impl LimitedSend for RequestBuilder {
+    async fn send_limited(self) -> anyhow::Result<Response> {
+        let (client, request) = self.build_split();
+        let mut request = request?;
+        let host = request.url().host_str().unwrap_or_default().to_string();
+        let url = request.url().clone();
+        apply_source_headers(&url, request.headers_mut());
+
+        let sina = is_sina_host(&host);
+        if sina {
+            check_sina_ban()?;
+        }
+        if mock_enabled() {
+            bail!("模拟模式已启用，该接口没有 fixture 数据（未请求上游 {}）", host);
+        }
+        check_upstream(&host)?;
+
+        let _permit = if sina {
+            Some(acquire_sina_permit(request.url().as_str()).await)
+        } else {
+            None
+        };
+
+        match client.execute(request).await {
+            Ok(response) => {
+                if sina && is_ban_status(response.status()) {
+                    mark_sina_banned();
+                }
+                if is_upstream_failure(response.status()) {
+                    record_failure(&host);
+                    record_source_error(&host, &format!("HTTP {}", response.status()));
+                } else {
+                    record_success(&host);
+                    record_source_success(&host);
+                    record_upstream(response.url());
+                }
+                Ok(response)
+            }
+            Err(e) => {
+                record_failure(&host);
+                record_source_error(&host, &e.to_string());
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// 判断响应状态是否表示上游故障或封禁
+///
+/// 新浪封禁 IP 时返回 403 或 456
+fn is_upstream_failure(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::FORBIDDEN
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status.as_u16() == 456
+}
+
+/// 判断是否为新浪封禁 IP 的响应状态（403 或 456）
+fn is_ban_status(status: StatusCode) -> bool {
+    status == StatusCode::FORBIDDEN || status.as_u16() == 456
+}
+
+/// 新浪请求限流器状态
+#[derive(Debug, Serialize)]
+pub struct SinaLimiterStats {
+    /// 最大并发数
+    pub max_permits: usize,
+    /// 当前占用的许可数
+    pub in_use: usize,
+    /// 当前可用的许可数
+    pub available: usize,
+    /// 累计请求数
+    pub total_requests: u64,
+    /// 排队超过告警阈值的请求数
+    pub slow_acquires: u64,
+    /// 最长排队时间（毫秒）
+    pub max_wait_ms: u64,
+    /// 封禁冷却剩余秒数（未封禁时为 0）
+    pub ban_remaining_secs: u64,
+    /// 累计检测到封禁的次数
+    pub bans: u64,
+    /// 封禁冷却期内被直接拒绝的请求数
+    pub ban_rejected: u64,
+}
+
+/// 获取新浪请求限流器状态
+pub fn sina_limiter_stats() -> SinaLimiterStats {
+    limiter().stats()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn ban_flag_short_circuits_subsequent_calls() {
+        let limiter = SinaLimiter::new(4, 1000);
+        assert!(limiter.check_ban().is_ok());
+
+        let banned = limiter.mark_banned(60);
+        assert!(banned.retry_after_secs > 58 && banned.retry_after_secs <= 60);
+
+        for _ in 0..3 {
+            let rejected = limiter.check_ban().unwrap_err();
+            assert!(rejected.retry_after_secs > 0 && rejected.retry_after_secs <= 60);
+        }
+        assert_eq!(limiter.ban_rejected.load(Ordering::Relaxed), 3);
+
+        // 冷却期内再次检测到封禁只延长截止时间，不重复计数
+        limiter.mark_banned(60);
+        assert_eq!(limiter.bans.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn zero_cooldown_disables_ban() {
+        let limiter = SinaLimiter::new(4, 1000);
+        assert_eq!(limiter.mark_banned(0).retry_after_secs, 0);
+        assert!(limiter.check_ban().is_ok());
+        assert_eq!(limiter.bans.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn permits_cap_concurrency_and_queued_requests_are_counted() {
+        let limiter = SinaLimiter::new(2, 5);
+        let first = limiter.acquire("a").await;
+        let second = limiter.acquire("b").await;
+        let stats = limiter.stats();
+        assert_eq!((stats.in_use, stats.available), (2, 0));
+
+        // 许可用尽时第三个请求排队，直到有许可归还
+        let third = limiter.acquire("c");
+        tokio::pin!(third);
+        assert!(tokio::time::timeout(Duration::from_millis(20), &mut third).await.is_err());
+        drop(first);
+        let third = tokio::time::timeout(Duration::from_secs(1), third).await.expect("归还许可后应获取成功");
+
+        let stats = limiter.stats();
+        assert_eq!(stats.total_requests, 3);
+        assert_eq!(stats.slow_acquires, 1);
+        assert!(stats.max_wait_ms >= 20, "{}", stats.max_wait_ms);
+        assert_eq!(stats.in_use, 2);
+        drop((second, third));
+        assert_eq!(limiter.stats().available, 2);
+    }
+
+    #[tokio::test]
+    async fn resize_adds_and_reclaims_permits() {
+        let limiter: &'static SinaLimiter = Box::leak(Box::new(SinaLimiter::new(4, 1000)));
+        limiter.resize(6, 1000);
+        assert_eq!((limiter.stats().max_permits, limiter.stats().available), (6, 6));
+
+        // 缩容时占用中的许可在归还后回收
+        let held = limiter.acquire("a").await;
+        limiter.resize(0, 1000);
+        let stats = limiter.stats();
+        assert_eq!(stats.max_permits, 1);
+        assert_eq!(stats.available, 0);
+        drop(held);
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while limiter.semaphore.available_permits() != 1 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("归还后应只剩 1 个许可");
+    }
+
+    #[tokio::test]
+    async fn banned_sina_request_is_not_sent() {
+        let client = reqwest::Client::new();
+        limiter().mark_banned(60);
+
+        // 冷却期内新浪请求在发出前失败；目标端口不可达，若真的发出会得到连接错误而非 RateLimited
+        let err = client
+            .get("https://hq.sinajs.cn:1/list=nf_RB0")
+            .send_limited()
+            .await
+            .unwrap_err();
+        assert!(err.is::<RateLimited>());
+    }
+}
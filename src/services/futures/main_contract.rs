@@ -1,19 +1,56 @@
 //! 主力连续合约相关
 
-use crate::models::{FuturesHoldPosition, FuturesMainContract, FuturesMainDailyData};
+use crate::models::{FuturesInfo, FuturesMainContract, FuturesMainDailyData};
 use anyhow::{anyhow, Result};
 use regex::Regex;
-use reqwest::Client;
 
-use super::common::{SINA_HOLD_POS_API, SINA_MAIN_DAILY_API};
+use super::common::{
+    http_client, parse_context, parse_json_lenient, RequestTimeout, SINA_FUTURES_LIST_API, SINA_FUTURES_SYMBOL_URL,
+    SINA_MAIN_DAILY_API,
+};
+use super::limiter::LimitedSend;
+use super::mock::fixture;
+use super::sina::FuturesService;
+use crate::config;
+
+/// 主力连续合约一览表包含的交易所
+const MAIN_CONTRACT_EXCHANGES: [&str; 5] = ["dce", "czce", "shfe", "cffex", "gfex"];
+/// 补充行情时每次批量请求的合约数
+const MAIN_QUOTE_BATCH_SIZE: usize = 30;
+/// 补充行情时同时进行的批量请求数
+const MAIN_QUOTE_CONCURRENCY: usize = 4;
 
 /// 获取主力连续合约一览表
 /// 对应 akshare 的 futures_display_main_sina() 函数
+///
+/// 在合约列表的基础上补充最新价和持仓量，便于客户端按流动性排序
 pub async fn get_futures_display_main_sina() -> Result<Vec<FuturesMainContract>> {
-    let mut all_contracts = Vec::new();
+    let mut contracts = list_main_contracts().await?;
+    attach_main_quotes(&mut contracts).await;
+    Ok(contracts)
+}
+
+/// 获取主力连续合约列表（不含行情）
+///
+/// 品种映射 JS 只下载一次，各交易所并发获取，单个交易所失败时跳过；
+/// 最后并入配置 futures.extra_main_contracts 中未被识别的合约
+pub(crate) async fn list_main_contracts() -> Result<Vec<FuturesMainContract>> {
+    if let Some(contracts) = fixture("futures_main_contracts") {
+        return contracts;
+    }
 
-    for exchange in &["dce", "czce", "shfe", "cffex", "gfex"] {
-        match get_main_contracts_by_exchange(exchange).await {
+    let js_text = fetch_symbol_nodes_js().await?;
+
+    let results = futures::future::join_all(
+        MAIN_CONTRACT_EXCHANGES
+            .iter()
+            .map(|exchange| get_main_contracts_by_exchange(&js_text, exchange)),
+    )
+    .await;
+
+    let mut all_contracts = Vec::new();
+    for (exchange, result) in MAIN_CONTRACT_EXCHANGES.iter().zip(results) {
+        match result {
             Ok(mut contracts) => all_contracts.append(&mut contracts),
             Err(e) => {
                 log::warn!("获取 {} 主力连续合约失败: {}", exchange, e);
@@ -21,31 +58,126 @@ pub async fn get_futures_display_main_sina() -> Result<Vec<FuturesMainContract>>
         }
     }
 
+    append_extra_main_contracts(&mut all_contracts).await;
     Ok(all_contracts)
 }
 
-/// 获取指定交易所的主力连续合约
-async fn get_main_contracts_by_exchange(exchange: &str) -> Result<Vec<FuturesMainContract>> {
-    let client = Client::new();
-    let mut contracts = Vec::new();
+/// 为主力连续合约补充最新价和持仓量
+///
+/// 按批并发获取实时行情，某一批失败时该批合约的行情字段保持为 None
+async fn attach_main_quotes(contracts: &mut [FuturesMainContract]) {
+    use futures::StreamExt;
+
+    let service = FuturesService::new();
+    let batches: Vec<Vec<String>> = contracts
+        .chunks(MAIN_QUOTE_BATCH_SIZE)
+        .map(|chunk| chunk.iter().map(|c| c.symbol.clone()).collect())
+        .collect();
+    let quotes: Vec<FuturesInfo> = futures::stream::iter(&batches)
+        .map(|symbols| service.get_multiple_futures(symbols))
+        .buffer_unordered(MAIN_QUOTE_CONCURRENCY)
+        .filter_map(|result| async move {
+            result
+                .map_err(|e| log::warn!("获取主力连续合约行情失败，该批合约不含行情字段: {}", e))
+                .ok()
+        })
+        .concat()
+        .await;
+
+    apply_main_quotes(contracts, &quotes);
+}
 
-    let symbol_url = "https://vip.stock.finance.sina.com.cn/quotes_service/view/js/qihuohangqing.js";
-    let response = client
-        .get(symbol_url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .send()
+/// 按合约代码把行情中的最新价和持仓量写入合约列表，没有行情的合约保持不变
+fn apply_main_quotes(contracts: &mut [FuturesMainContract], quotes: &[FuturesInfo]) {
+    for contract in contracts.iter_mut() {
+        if let Some(quote) = quotes.iter().find(|q| q.symbol.eq_ignore_ascii_case(&contract.symbol)) {
+            contract.last_price = quote.current_price;
+            contract.open_interest = quote.open_interest;
+        }
+    }
+}
+
+/// 并入配置的额外主力连续合约
+///
+/// 配置项为合约代码（如 SI0）或 交易所:合约代码（如 GFEX:SI0）；已存在的合约跳过。
+/// 名称通过实时行情获取，获取失败时以合约代码作为名称
+async fn append_extra_main_contracts(contracts: &mut Vec<FuturesMainContract>) {
+    let entries = config::read(|c| c.futures.extra_main_contracts.clone());
+    let mut extras = extra_main_contracts(contracts, &entries);
+    if extras.is_empty() {
+        return;
+    }
+
+    let symbols: Vec<String> = extras.iter().map(|c| c.symbol.clone()).collect();
+    match FuturesService::new().get_multiple_futures(&symbols).await {
+        Ok(quotes) => apply_quote_names(&mut extras, &quotes),
+        Err(e) => log::warn!("获取额外主力连续合约名称失败，使用合约代码作为名称: {}", e),
+    }
+
+    contracts.append(&mut extras);
+}
+
+/// 由配置项生成尚未出现在 existing 中的额外合约，名称暂以合约代码代替
+fn extra_main_contracts(existing: &[FuturesMainContract], entries: &[String]) -> Vec<FuturesMainContract> {
+    let mut extras: Vec<FuturesMainContract> = Vec::new();
+    for entry in entries {
+        let (exchange, symbol) = match entry.split_once(':') {
+            Some((exchange, symbol)) => (exchange.trim().to_uppercase(), symbol.trim().to_uppercase()),
+            None => (String::new(), entry.trim().to_uppercase()),
+        };
+        if symbol.is_empty()
+            || existing.iter().chain(&extras).any(|c| c.symbol.eq_ignore_ascii_case(&symbol))
+        {
+            continue;
+        }
+        extras.push(FuturesMainContract {
+            name: symbol.clone(),
+            symbol,
+            exchange,
+            last_price: None,
+            open_interest: None,
+        });
+    }
+    extras
+}
+
+/// 用实时行情中的名称替换额外合约的占位名称，行情缺失或名称为空时保持不变
+fn apply_quote_names(extras: &mut [FuturesMainContract], quotes: &[FuturesInfo]) {
+    for extra in extras {
+        if let Some(quote) = quotes
+            .iter()
+            .find(|q| q.symbol.eq_ignore_ascii_case(&extra.symbol) && !q.name.is_empty())
+        {
+            extra.name = quote.name.clone();
+        }
+    }
+}
+
+/// 下载新浪品种映射 JS 文件
+async fn fetch_symbol_nodes_js() -> Result<String> {
+    let response = http_client()
+        .get(SINA_FUTURES_SYMBOL_URL)
+        .timeout(RequestTimeout::Realtime.duration())
+        .send_limited()
         .await?;
 
     let bytes = response.bytes().await?;
-    let text = encoding_rs::GBK.decode(&bytes).0.to_string();
+    Ok(encoding_rs::GBK.decode(&bytes).0.to_string())
+}
 
-    let nodes = parse_exchange_nodes(&text, exchange)?;
+/// 获取指定交易所的主力连续合约
+async fn get_main_contracts_by_exchange(
+    js_text: &str,
+    exchange: &str,
+) -> Result<Vec<FuturesMainContract>> {
+    let client = http_client();
+    let mut contracts = Vec::new();
 
-    for node in nodes {
-        let list_url = "https://vip.stock.finance.sina.com.cn/quotes_service/api/json_v2.php/Market_Center.getHQFuturesData";
+    let nodes = parse_exchange_nodes(js_text, exchange)?;
 
+    for node in nodes {
         let response = client
-            .get(list_url)
+            .get(SINA_FUTURES_LIST_API)
             .query(&[
                 ("page", "1"),
                 ("sort", "position"),
@@ -53,12 +185,13 @@ async fn get_main_contracts_by_exchange(exchange: &str) -> Result<Vec<FuturesMai
                 ("node", &node),
                 ("base", "futures"),
             ])
-            .send()
+            .timeout(RequestTimeout::Realtime.duration())
+            .send_limited()
             .await;
 
         if let Ok(resp) = response {
             if let Ok(text) = resp.text().await {
-                if let Ok(json_data) = serde_json::from_str::<serde_json::Value>(&text) {
+                if let Ok(json_data) = parse_json_lenient(&text) {
                     if let Some(arr) = json_data.as_array() {
                         for item in arr {
                             let name = item["name"].as_str().unwrap_or("");
@@ -69,6 +202,8 @@ async fn get_main_contracts_by_exchange(exchange: &str) -> Result<Vec<FuturesMai
                                     symbol: symbol.to_string(),
                                     name: name.to_string(),
                                     exchange: exchange.to_uppercase(),
+                                    last_price: None,
+                                    open_interest: None,
                                 });
                                 break;
                             }
@@ -83,7 +218,7 @@ async fn get_main_contracts_by_exchange(exchange: &str) -> Result<Vec<FuturesMai
 }
 
 /// 解析交易所的品种node列表
-fn parse_exchange_nodes(js_text: &str, exchange: &str) -> Result<Vec<String>> {
+pub(crate) fn parse_exchange_nodes(js_text: &str, exchange: &str) -> Result<Vec<String>> {
     let mut nodes = Vec::new();
 
     let start = js_text.find("ARRFUTURESNODES = {");
@@ -124,7 +259,11 @@ pub async fn get_futures_main_sina(
     start_date: Option<&str>,
     end_date: Option<&str>,
 ) -> Result<Vec<FuturesMainDailyData>> {
-    let client = Client::new();
+    if let Some(data) = fixture::<Vec<FuturesMainDailyData>>("futures_main_daily") {
+        return Ok(filter_main_daily(data?, start_date, end_date));
+    }
+
+    let client = http_client();
 
     let trade_date = "20210817";
     let trade_date_fmt = format!(
@@ -139,13 +278,11 @@ pub async fn get_futures_main_sina(
         SINA_MAIN_DAILY_API, symbol, trade_date_fmt, symbol, trade_date_fmt
     );
 
-    println!("📡 请求主力连续日K线 URL: {}", url);
+    log::debug!("请求主力连续日K线 URL: {}", url);
 
     let response = client
         .get(&url)
-        .header("Referer", "https://finance.sina.com.cn/")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -153,22 +290,29 @@ pub async fn get_futures_main_sina(
     }
 
     let text = response.text().await?;
-    println!("📥 原始响应数据长度: {} 字节", text.len());
+    log::debug!("原始响应数据长度: {} 字节", text.len());
 
-    let mut data = parse_main_daily_data(&text)?;
+    let data = parse_context(parse_main_daily_data(&text), "新浪主力连续日K线", &url, &text)?;
+    Ok(filter_main_daily(data, start_date, end_date))
+}
 
+/// 按日期范围（YYYYMMDD，闭区间）过滤主力连续日K线
+fn filter_main_daily(
+    mut data: Vec<FuturesMainDailyData>,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Vec<FuturesMainDailyData> {
     if let Some(start) = start_date {
         data.retain(|d| d.date.replace("-", "").as_str() >= start);
     }
     if let Some(end) = end_date {
         data.retain(|d| d.date.replace("-", "").as_str() <= end);
     }
-
-    Ok(data)
+    data
 }
 
 /// 解析主力连续日K线数据
-fn parse_main_daily_data(data: &str) -> Result<Vec<FuturesMainDailyData>> {
+pub(crate) fn parse_main_daily_data(data: &str) -> Result<Vec<FuturesMainDailyData>> {
     let mut history = Vec::new();
 
     let start = data.find("([");
@@ -203,130 +347,75 @@ fn parse_main_daily_data(data: &str) -> Result<Vec<FuturesMainDailyData>> {
     Ok(history)
 }
 
-/// 获取期货持仓排名数据
-/// 对应 akshare 的 futures_hold_pos_sina() 函数
-pub async fn get_futures_hold_pos_sina(
-    pos_type: &str,
-    contract: &str,
-    date: &str,
-) -> Result<Vec<FuturesHoldPosition>> {
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
-
-    let formatted_date = if date.len() == 8 {
-        format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..])
-    } else {
-        date.to_string()
-    };
-
-    let url = format!("{}?t_breed={}&t_date={}", SINA_HOLD_POS_API, contract, formatted_date);
-    println!("📡 请求持仓排名 URL: {}", url);
-
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/webp,*/*;q=0.8")
-        .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
-        .header("Accept-Encoding", "gzip, deflate")
-        .header("Connection", "keep-alive")
-        .header("Referer", "https://vip.stock.finance.sina.com.cn/")
-        .header("Host", "vip.stock.finance.sina.com.cn")
-        .send()
-        .await?;
-
-    let status = response.status();
-    if !status.is_success() {
-        if status.as_u16() == 456 || status.as_u16() == 403 {
-            return Err(anyhow!("IP被新浪封禁，请稍后重试（5-60分钟后自动解封）"));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contract(symbol: &str, name: &str, exchange: &str) -> FuturesMainContract {
+        FuturesMainContract {
+            symbol: symbol.to_string(),
+            name: name.to_string(),
+            exchange: exchange.to_string(),
+            last_price: None,
+            open_interest: None,
         }
-        return Err(anyhow!("获取持仓排名失败: {}", status));
-    }
-
-    let bytes = response.bytes().await?;
-    let text = encoding_rs::GBK.decode(&bytes).0.to_string();
-
-    if text.contains("拒绝访问") || text.contains("IP 存在异常访问") {
-        return Err(anyhow!("IP被新浪封禁，请稍后重试（5-60分钟后自动解封）"));
     }
 
-    let table_index = match pos_type {
-        "volume" => 2,
-        "long" => 3,
-        "short" => 4,
-        _ => return Err(anyhow!("无效的持仓类型: {}, 应为 volume/long/short", pos_type)),
-    };
-
-    parse_hold_pos_html(&text, table_index, pos_type)
-}
-
-/// 解析持仓排名HTML数据
-fn parse_hold_pos_html(
-    html: &str,
-    table_index: usize,
-    pos_type: &str,
-) -> Result<Vec<FuturesHoldPosition>> {
-    let mut positions = Vec::new();
-
-    let table_re = Regex::new(r"<table[^>]*>([\s\S]*?)</table>").unwrap();
-    let tables: Vec<_> = table_re.captures_iter(html).collect();
-
-    if tables.len() <= table_index {
-        return Err(anyhow!("未找到持仓排名数据表格"));
+    fn entries(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
     }
 
-    let table_content = tables[table_index].get(1).map(|m| m.as_str()).unwrap_or("");
-
-    let row_re = Regex::new(r"<tr[^>]*>([\s\S]*?)</tr>").unwrap();
-    let cell_re = Regex::new(r"<td[^>]*>([\s\S]*?)</td>").unwrap();
-    let tag_re = Regex::new(r"<[^>]+>").unwrap();
-
-    let value_col_name = match pos_type {
-        "volume" => "成交量",
-        "long" => "多单持仓",
-        "short" => "空单持仓",
-        _ => "数值",
-    };
-
-    for (i, row_cap) in row_re.captures_iter(table_content).enumerate() {
-        if i == 0 {
-            continue;
-        }
+    #[test]
+    fn configured_extra_contract_is_merged_with_exchange() {
+        let existing = vec![contract("RB0", "螺纹钢连续", "SHFE"), contract("SI0", "工业硅连续", "GFEX")];
+        let extras = extra_main_contracts(&existing, &entries(&["gfex: lc0", "EC0", "si0", "LC0", " "]));
 
-        let row_content = row_cap.get(1).map(|m| m.as_str()).unwrap_or("");
-        let cells: Vec<_> = cell_re
-            .captures_iter(row_content)
-            .filter_map(|c| c.get(1).map(|m| m.as_str().trim()))
+        let merged: Vec<(&str, &str, &str)> = extras
+            .iter()
+            .map(|c| (c.symbol.as_str(), c.name.as_str(), c.exchange.as_str()))
             .collect();
+        assert_eq!(merged, [("LC0", "LC0", "GFEX"), ("EC0", "EC0", "")]);
+    }
 
-        if cells.len() >= 3 {
-            let clean_text = |s: &str| -> String {
-                tag_re.replace_all(s, "").trim().to_string()
-            };
-
-            let rank_str = clean_text(cells[0]);
-            let company = clean_text(cells[1]);
-            let value_str = clean_text(cells[2]);
+    fn fixture_quotes() -> Vec<FuturesInfo> {
+        serde_json::from_str(include_str!("../../../fixtures/futures_realtime.json")).unwrap()
+    }
 
-            if rank_str.contains("合计") || company.contains("合计") {
-                continue;
-            }
+    #[test]
+    fn main_contracts_are_enriched_with_quote_fields() {
+        let mut quotes = fixture_quotes();
+        quotes.truncate(2);
+        quotes[0].symbol = "rb0".to_string();
+        quotes[1].symbol = "CU0".to_string();
+        quotes[1].current_price = None;
+        quotes[1].open_interest = Some(0);
+
+        let mut contracts = vec![
+            contract("RB0", "螺纹钢连续", "shfe"),
+            contract("CU0", "沪铜连续", "shfe"),
+            contract("AU0", "黄金连续", "shfe"),
+        ];
+        apply_main_quotes(&mut contracts, &quotes);
+
+        assert_eq!(contracts[0].last_price, Some(3102.0));
+        assert_eq!(contracts[0].open_interest, Some(1987654));
+        assert_eq!((contracts[1].last_price, contracts[1].open_interest), (None, Some(0)));
+        assert_eq!((contracts[2].last_price, contracts[2].open_interest), (None, None));
+    }
 
-            let rank = rank_str.parse::<u32>().unwrap_or(0);
-            let value = value_str.replace(",", "").parse::<i64>().unwrap_or(0);
+    #[test]
+    fn extra_contract_names_come_from_quotes() {
+        let mut quotes = fixture_quotes();
+        quotes.truncate(2);
+        quotes[0].symbol = "lc0".to_string();
+        quotes[0].name = "碳酸锂连续".to_string();
+        quotes[1].symbol = "EC0".to_string();
+        quotes[1].name = String::new();
 
-            let change = if cells.len() >= 4 {
-                clean_text(cells[3]).replace(",", "").parse::<i64>().unwrap_or(0)
-            } else {
-                0
-            };
+        let mut extras = vec![contract("LC0", "LC0", "GFEX"), contract("EC0", "EC0", ""), contract("PS0", "PS0", "")];
+        apply_quote_names(&mut extras, &quotes);
 
-            if rank > 0 {
-                positions.push(FuturesHoldPosition { rank, company, value, change });
-            }
-        }
+        let names: Vec<&str> = extras.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["碳酸锂连续", "EC0", "PS0"]);
     }
-
-    println!("📊 解析到 {} 条{}排名数据", positions.len(), value_col_name);
-    Ok(positions)
 }
@@ -0,0 +1,40 @@
+//! 模拟模式
+//!
+//! 配置 mock.enabled 后，主要接口的服务函数在入口处读取 {fixtures_dir}/{name}.json 返回，
+//! 不再请求上游；没有 fixture 的接口在 send_limited 处直接失败，保证不会产生网络请求。
+//! 用于前端开发和无法访问上游的演示环境
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+
+use super::provenance::record_mock;
+use crate::config;
+
+/// 模拟模式是否启用
+pub fn mock_enabled() -> bool {
+    config::read(|c| c.mock.enabled)
+}
+
+/// 读取 fixture 数据，模拟模式未启用时返回 None
+///
+/// name 为不含扩展名的文件名，如 "futures_realtime"
+pub fn fixture<T: DeserializeOwned>(name: &str) -> Option<Result<T>> {
+    let mock = config::read(|c| c.mock.clone());
+    if !mock.enabled {
+        return None;
+    }
+
+    let path = PathBuf::from(mock.fixtures_dir).join(format!("{}.json", name));
+    let result = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow!("读取 fixture {} 失败: {}", path.display(), e))
+        .and_then(|content| {
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow!("解析 fixture {} 失败: {}", path.display(), e))
+        });
+    if result.is_ok() {
+        log::debug!("模拟模式: 返回 fixture {}", path.display());
+        record_mock(&path.display().to_string());
+    }
+    Some(result)
+}
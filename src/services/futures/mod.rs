@@ -4,6 +4,7 @@
 //!
 //! ## 数据来源
 //! - 新浪财经：实时行情、K线数据、持仓排名
+//! - 东方财富：实时行情（新浪不可用时的备用数据源）
 //! - 100ppi：现货价格及基差数据
 //! - 99期货网：库存数据
 //! - OpenCTP：交易费用数据
@@ -18,48 +19,102 @@
 //! - 外盘期货数据
 //! - 现货价格及基差
 //! - 交易费用和规则
+//! - 首页市场概览
 
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+mod availability;
+mod blocking;
+mod breaker;
+mod cache;
+mod calendar;
+mod coalesce;
 mod common;
+mod eastmoney;
+mod expiry;
 mod fees;
 mod foreign;
 mod inventory;
 mod kline;
+mod limiter;
 mod main_contract;
+mod mock;
+mod overview;
 mod position_rank;
+mod precision;
+mod provenance;
+mod realtime_source;
 mod sina;
+mod source_health;
+mod sources;
 mod spot;
 mod warehouse;
+mod warmup;
 
 // 重新导出公共类型和函数（这些是公共 API，供外部使用）
-pub use common::get_beijing_time;
-pub use fees::{get_futures_comm_info, get_futures_fees_info, get_futures_rule};
+pub use availability::{ensure_published, DataNotFound, DataNotYetAvailable};
+pub use coalesce::{coalesce, coalesce_stats, CoalesceStats, SharedError};
+pub use breaker::{upstream_breaker_stats, UpstreamBreakerStats, UpstreamUnavailable};
+pub use source_health::{source_health, SourceHealth};
+pub use cache::with_file_cache;
+pub use calendar::{is_market_open, is_quote_stale, latest_trading_date};
+pub use expiry::{ensure_not_expired, get_contract_expiry, ContractExpired};
+pub use common::{
+    beijing_now, beijing_today_yyyymmdd, beijing_trading_day_yyyymmdd, default_headers, get_beijing_time, http_client,
+    check_date_range, http_client_builder, insecure_http_client, parse_context, InsecureHostRejected,
+    RequestTimeout, UpstreamBlocked, variety_name_map,
+};
+pub use fees::{
+    compute_per_lot_cost, filter_fees_info, filter_futures_rules, get_futures_comm_info,
+    get_futures_comm_info_with_fallback, get_futures_fees_info, get_futures_per_lot_cost, get_futures_rule,
+    sort_fees_info,
+};
 pub use foreign::{
     get_foreign_futures_realtime, get_foreign_futures_symbols, get_futures_foreign_detail,
-    get_futures_foreign_hist,
+    get_futures_foreign_hist, validate_foreign_codes,
+};
+pub use inventory::{
+    filter_inventory_99, find_99_product_id, get_99_symbol_map, get_futures_inventory_99,
+    get_futures_inventory_99_by_id,
+};
+pub use kline::{get_futures_history, get_futures_minute_data, get_futures_minute_multi, MINUTE_PERIODS};
+pub use limiter::{
+    resize_sina_limiter, sina_limiter_stats, LimitedSend, RateLimited, SinaLimiterStats,
 };
-pub use inventory::{get_99_symbol_map, get_futures_inventory_99};
-pub use kline::{get_futures_history, get_futures_minute_data};
 pub use main_contract::{
-    get_futures_display_main_sina, get_futures_hold_pos_sina, get_futures_main_sina,
+    get_futures_display_main_sina, get_futures_main_sina,
+};
+pub use mock::{fixture, mock_enabled};
+pub use overview::get_futures_overview;
+pub use precision::{apply_history_precision, apply_info_precision, kline_meta};
+pub use provenance::{current_provenance, with_provenance_scope};
+pub use realtime_source::{
+    configured_realtime_sources, fetch_with_fallback, RealtimeSource, RealtimeSourceKind,
+};
+pub use sina::{validate_contract_symbol, FuturesService};
+pub use warmup::{
+    cache_warmup_stats, spawn_cache_warmup, spawn_symbol_mark_refresher, CacheWarmupItem,
+    CacheWarmupStats,
 };
-pub use sina::FuturesService;
 pub use spot::{
-    get_futures_spot_price, get_futures_spot_price_daily, get_futures_spot_price_previous,
+    filter_spot_price_previous, get_futures_spot_price, get_futures_spot_price_daily,
+    get_futures_spot_price_previous, get_latest_spot_price_previous, NonTradingDay,
 };
 
 // 持仓排名相关（公共 API，暂未在 handlers 中使用）
 pub use position_rank::{
-    futures_dce_position_rank, futures_dce_position_rank_other, futures_gfex_position_rank,
-    futures_hold_pos_sina as futures_hold_pos_sina_rank, get_cffex_rank_table, get_dce_rank_table,
-    get_gfex_rank_table, get_gfex_vars_list, get_rank_sum, get_rank_sum_daily,
-    get_rank_table_czce, get_shfe_rank_table,
+    compute_position_concentration, compute_position_net, futures_dce_position_rank, futures_dce_position_rank_other,
+    futures_gfex_position_rank, get_futures_hold_pos_sina,
+    get_cffex_rank_table, get_dce_rank_table, get_gfex_rank_table, get_gfex_vars_list,
+    get_position_concentration, get_position_diff, get_position_net, get_rank_sum, get_rank_sum_cached, get_rank_sum_daily,
+    get_rank_table, get_rank_table_czce, get_shfe_rank_table, rank_cache_key, HttpRankSource,
+    IncompleteRankSum, RankDateUnavailable, RankSource, RANK_EXCHANGES,
 };
 
 // 仓单日报相关（公共 API，暂未在 handlers 中使用）
 pub use warehouse::{
-    futures_gfex_warehouse_receipt, futures_shfe_warehouse_receipt,
+    aggregate_czce_receipts, aggregate_dce_receipts, aggregate_gfex_receipts,
+    aggregate_shfe_receipts, futures_gfex_warehouse_receipt, futures_shfe_warehouse_receipt,
     futures_warehouse_receipt_czce, futures_warehouse_receipt_dce,
 };
@@ -0,0 +1,152 @@
+//! 市场概览
+//!
+//! 首页看板一次性获取主力连续合约行情、涨跌幅榜和最近一日基差极值。
+//! 各部分并发获取，单个部分失败时省略；结果在内存中缓存 api.overview_cache_secs 秒
+
+use anyhow::{anyhow, Result};
+use chrono::Duration;
+use std::sync::OnceLock;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+use super::common::{beijing_now, get_beijing_time};
+use super::main_contract::list_main_contracts;
+use super::sina::FuturesService;
+use super::spot::get_futures_spot_price;
+use crate::config;
+use crate::models::{
+    FuturesInfo, FuturesOverview, FuturesQuote, OverviewBasis, OverviewMovers,
+};
+
+/// 概览中展示的主力连续合约
+const OVERVIEW_MAIN_SYMBOLS: [&str; 4] = ["RB0", "CU0", "I0", "IF0"];
+
+/// 涨跌幅榜各取前几名
+const OVERVIEW_MOVERS_COUNT: usize = 5;
+
+/// 查找最近有现货数据的日期时最多回溯的天数
+const SPOT_LOOKBACK_DAYS: i64 = 7;
+
+static OVERVIEW_CACHE: OnceLock<Mutex<Option<(Instant, FuturesOverview)>>> = OnceLock::new();
+
+/// 获取市场概览（带短时缓存）
+///
+/// 缓存锁在刷新期间持有，并发请求等待同一次刷新而不会重复请求上游；
+/// 所有部分均失败时返回错误且不写入缓存
+pub async fn get_futures_overview() -> Result<FuturesOverview> {
+    let ttl = std::time::Duration::from_secs(config::read(|c| c.api.overview_cache_secs));
+    let mut cache = OVERVIEW_CACHE.get_or_init(|| Mutex::new(None)).lock().await;
+
+    if let Some((cached_at, overview)) = cache.as_ref() {
+        if cached_at.elapsed() < ttl {
+            return Ok(overview.clone());
+        }
+    }
+
+    let overview = build_overview().await?;
+    *cache = Some((Instant::now(), overview.clone()));
+    Ok(overview)
+}
+
+/// 并发获取各部分并组装概览
+async fn build_overview() -> Result<FuturesOverview> {
+    let (main_quotes, top_movers, basis) =
+        futures::join!(fetch_main_quotes(), fetch_top_movers(), fetch_latest_basis());
+
+    let main_quotes = section("main_quotes", main_quotes);
+    let top_movers = section("top_movers", top_movers);
+    let basis = section("basis", basis);
+
+    if main_quotes.is_none() && top_movers.is_none() && basis.is_none() {
+        return Err(anyhow!("市场概览各部分均获取失败"));
+    }
+
+    Ok(FuturesOverview {
+        updated_at: get_beijing_time(),
+        main_quotes,
+        top_movers,
+        basis,
+    })
+}
+
+/// 单个部分失败时记录日志并省略
+fn section<T>(name: &str, result: Result<T>) -> Option<T> {
+    match result {
+        Ok(data) => Some(data),
+        Err(e) => {
+            log::warn!("市场概览 {} 获取失败: {}", name, e);
+            None
+        }
+    }
+}
+
+/// 获取精选主力连续合约行情
+async fn fetch_main_quotes() -> Result<Vec<FuturesQuote>> {
+    let symbols: Vec<String> = OVERVIEW_MAIN_SYMBOLS.iter().map(|s| s.to_string()).collect();
+    let list = FuturesService::new().get_multiple_futures(&symbols).await?;
+    Ok(list.iter().map(FuturesQuote::from).collect())
+}
+
+/// 获取全部主力连续合约行情并按涨跌幅排序
+async fn fetch_top_movers() -> Result<OverviewMovers> {
+    let symbols: Vec<String> = list_main_contracts()
+        .await?
+        .into_iter()
+        .map(|c| c.symbol)
+        .collect();
+    if symbols.is_empty() {
+        return Err(anyhow!("主力连续合约列表为空"));
+    }
+
+    let mut list: Vec<FuturesInfo> = FuturesService::new()
+        .get_multiple_futures(&symbols)
+        .await?
+        .into_iter()
+        .filter(|f| f.current_price.is_some_and(|p| p > 0.0))
+        .collect();
+    list.sort_by(|a, b| b.change_percent.total_cmp(&a.change_percent));
+
+    let gainers = list
+        .iter()
+        .take(OVERVIEW_MOVERS_COUNT)
+        .filter(|f| f.change_percent > 0.0)
+        .map(FuturesQuote::from)
+        .collect();
+    let losers = list
+        .iter()
+        .rev()
+        .take(OVERVIEW_MOVERS_COUNT)
+        .filter(|f| f.change_percent < 0.0)
+        .map(FuturesQuote::from)
+        .collect();
+
+    Ok(OverviewMovers { gainers, losers })
+}
+
+/// 获取最近有数据的一日现货基差，取主力基差率最高和最低的品种
+async fn fetch_latest_basis() -> Result<OverviewBasis> {
+    let today = beijing_now().date_naive();
+
+    for offset in 0..SPOT_LOOKBACK_DAYS {
+        let date = (today - Duration::days(offset)).format("%Y%m%d").to_string();
+        let data = match get_futures_spot_price(&date, None).await {
+            Ok(data) => data,
+            Err(e) => {
+                log::debug!("{} 现货价格获取失败，继续回溯: {}", date, e);
+                continue;
+            }
+        };
+
+        let max = data.iter().max_by(|a, b| a.dom_basis_rate.total_cmp(&b.dom_basis_rate));
+        let min = data.iter().min_by(|a, b| a.dom_basis_rate.total_cmp(&b.dom_basis_rate));
+        if let (Some(max), Some(min)) = (max, min) {
+            return Ok(OverviewBasis {
+                date,
+                max_basis: max.clone(),
+                min_basis: min.clone(),
+            });
+        }
+    }
+
+    Err(anyhow!("最近 {} 天没有现货基差数据", SPOT_LOOKBACK_DAYS))
+}
@@ -3,12 +3,28 @@
 //! 提供各交易所持仓排名数据的获取和处理
 
 use anyhow::{anyhow, Result};
+use calamine::{Data, Range};
+use chrono::Datelike;
 use regex::Regex;
 use reqwest::Client;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
 
+use super::common::{
+    check_date_range, fetch_czce_sheet, http_client, CzceFileMissing, SINA_HOLD_POS_API, CZCE_VARIETIES, http_client_builder, parse_csv, parse_json, parse_signed_int, RequestTimeout,
+    UpstreamBlocked,
+};
+use super::cache::with_file_cache;
+use super::calendar::variety_of;
+use super::availability::{ensure_published, DataNotFound, DataNotYetAvailable};
+use super::blocking::run_blocking;
+use super::spot::NonTradingDay;
+use super::limiter::{check_sina_ban, mark_sina_banned, LimitedSend};
+use super::mock::fixture;
 use crate::models::{
-    PositionRankData, RankSum, RankTableResponse, SinaHoldPosType, SinaHoldPosition,
+    parse_yyyymmdd, PositionConcentration, PositionDiffMember, PositionDiffResponse, PositionNetMember, PositionNetResponse,
+    PositionRankData, RankSum, RankTableResponse,
+    SinaHoldPosType, SinaHoldPosition,
 };
 
 /// 上海期货交易所会员成交及持仓排名表API
@@ -21,6 +37,9 @@ const CFFEX_VOL_RANK_URL: &str = "http://www.cffex.com.cn/sj/ccpm";
 const DCE_VOL_RANK_URL: &str =
     "http://www.dce.com.cn/dcereport/publicweb/dailystat/memberDealPosi/batchDownload";
 
+/// 广期所持仓排名同时请求的合约数（每个合约的三类排名另外并发请求）
+const GFEX_CONTRACT_CONCURRENCY: usize = 4;
+
 /// 从合约代码中提取品种代码
 fn extract_variety(symbol: &str) -> String {
     let re = Regex::new(r"^([A-Za-z]+)").unwrap();
@@ -30,57 +49,87 @@ fn extract_variety(symbol: &str) -> String {
         .unwrap_or_default()
 }
 
+/// 持仓排名表排序：合约按代码排序，合约内按名次排序（稳定排序，同名次保持上游顺序），
+/// 保证相同数据的重复请求输出完全一致
+fn sort_rank_tables(tables: &mut [RankTableResponse]) {
+    tables.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    for table in tables.iter_mut() {
+        table.data.sort_by_key(|d| d.rank);
+    }
+}
+
+/// 模拟模式下读取 fixture futures_rank_{exchange}，按品种过滤并排序；未启用时返回 None
+fn rank_fixture(exchange: &str, vars_list: Option<&[&str]>) -> Option<Result<Vec<RankTableResponse>>> {
+    let tables = fixture::<Vec<RankTableResponse>>(&format!("futures_rank_{}", exchange))?;
+    Some(tables.map(|tables| {
+        let mut tables: Vec<RankTableResponse> = tables
+            .into_iter()
+            .filter(|table| {
+                vars_list.is_none_or(|vars| vars.iter().any(|v| v.eq_ignore_ascii_case(&variety_of(&table.symbol))))
+            })
+            .collect();
+        sort_rank_tables(&mut tables);
+        tables
+    }))
+}
+
 // ==================== 新浪期货持仓排名 ====================
 
-/// 新浪财经-期货-成交持仓排名
+/// 按持仓类型获取新浪成交持仓排名
 /// 对应 akshare 的 futures_hold_pos_sina() 函数
 /// 数据来源: https://vip.stock.finance.sina.com.cn/q/view/vFutures_Positions_cjcc.php
 ///
-/// symbol: 数据类型，可选 "成交量"/"多单持仓"/"空单持仓" 或 "volume"/"long"/"short"
+/// /futures/hold_pos 与 /futures/hold-pos/sina 共用该函数
 /// contract: 期货合约代码，如 "OI2501", "IC2403"
 /// date: 查询日期，格式 YYYYMMDD
-pub async fn futures_hold_pos_sina(
-    symbol: &str,
+pub async fn get_futures_hold_pos_sina(
+    pos_type: SinaHoldPosType,
     contract: &str,
     date: &str,
 ) -> Result<Vec<SinaHoldPosition>> {
-    let pos_type = SinaHoldPosType::from_str(symbol).ok_or_else(|| {
-        anyhow!(
-            "无效的symbol参数: {}，可选: 成交量/多单持仓/空单持仓",
-            symbol
-        )
-    })?;
+    if let Some(rows) = fixture::<Vec<SinaHoldPosition>>("futures_hold_pos_sina") {
+        return rows;
+    }
 
-    let client = Client::new();
+    let client = http_client();
 
     // 格式化日期为 YYYY-MM-DD
-    let formatted_date = format!("{}-{}-{}", &date[0..4], &date[4..6], &date[6..8]);
+    let formatted_date = parse_yyyymmdd(date)?.format("%Y-%m-%d").to_string();
 
-    let url = "https://vip.stock.finance.sina.com.cn/q/view/vFutures_Positions_cjcc.php";
-
-    println!(
-        "📡 请求新浪期货持仓数据 URL: {}?t_breed={}&t_date={}",
-        url, contract, formatted_date
+    log::debug!(
+        "请求新浪期货持仓数据 URL: {}?t_breed={}&t_date={}",
+        SINA_HOLD_POS_API, contract, formatted_date
     );
 
     let response = client
-        .get(url)
+        .get(SINA_HOLD_POS_API)
         .query(&[("t_breed", contract), ("t_date", &formatted_date)])
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .header("Referer", "https://vip.stock.finance.sina.com.cn/")
-        .send()
+        .send_limited()
         .await?;
 
-    if !response.status().is_success() {
-        return Err(anyhow!("获取新浪期货持仓数据失败: {}", response.status()));
+    let status = response.status();
+    if !status.is_success() {
+        // 456/403 已由 send_limited 记录封禁，冷却中时返回 RateLimited
+        check_sina_ban()?;
+        return Err(anyhow!("获取新浪期货持仓数据失败: {}", status));
     }
 
     // 使用GBK编码读取
     let bytes = response.bytes().await?;
     let html = encoding_rs::GBK.decode(&bytes).0.to_string();
 
-    // 解析HTML表格
-    let document = scraper::Html::parse_document(&html);
+    if html.contains("拒绝访问") || html.contains("IP 存在异常访问") {
+        return Err(mark_sina_banned().into());
+    }
+
+    parse_sina_hold_pos_html(&html, pos_type)
+}
+
+/// 解析新浪成交持仓排名页面，按持仓类型取对应表格
+///
+/// 页面依次为成交量、多单持仓、空单持仓三张表，表格数不足时视为非交易日或合约不存在
+fn parse_sina_hold_pos_html(html: &str, pos_type: SinaHoldPosType) -> Result<Vec<SinaHoldPosition>> {
+    let document = scraper::Html::parse_document(html);
     let table_selector = scraper::Selector::parse("table").unwrap();
     let tables: Vec<_> = document.select(&table_selector).collect();
 
@@ -131,7 +180,7 @@ pub async fn futures_hold_pos_sina(
         });
     }
 
-    println!("📊 解析到 {} 条持仓排名数据", result.len());
+    log::debug!("解析到 {} 条{}排名数据", result.len(), pos_type.value_column_name());
     Ok(result)
 }
 
@@ -146,18 +195,18 @@ pub async fn get_shfe_rank_table(
     date: &str,
     vars_list: Option<Vec<&str>>,
 ) -> Result<Vec<RankTableResponse>> {
-    let client = Client::new();
+    if let Some(tables) = rank_fixture("shfe", vars_list.as_deref()) {
+        return tables;
+    }
+
+    let client = http_client();
 
     let url = format!("{}{}.dat", SHFE_VOL_RANK_URL, date);
-    println!("📡 请求上期所持仓排名数据 URL: {}", url);
+    log::debug!("请求上期所持仓排名数据 URL: {}", url);
 
     let response = client
         .get(&url)
-        .header(
-            "User-Agent",
-            "Mozilla/4.0 (compatible; MSIE 5.5; Windows NT)",
-        )
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -166,14 +215,13 @@ pub async fn get_shfe_rank_table(
 
     let text = response.text().await?;
 
-    let json_data: serde_json::Value =
-        serde_json::from_str(&text).map_err(|e| anyhow!("解析JSON失败: {}", e))?;
+    let json_data = parse_json("上期所持仓排名", &url, &text)?;
 
     let cursor = json_data["o_cursor"]
         .as_array()
         .ok_or_else(|| anyhow!("未找到o_cursor数据"))?;
 
-    let mut symbol_data: HashMap<String, Vec<PositionRankData>> = HashMap::new();
+    let mut symbol_data: BTreeMap<String, Vec<PositionRankData>> = BTreeMap::new();
 
     for item in cursor {
         let rank = item["RANK"].as_i64().unwrap_or(0) as i32;
@@ -233,9 +281,9 @@ pub async fn get_shfe_rank_table(
         .map(|(symbol, data)| RankTableResponse { symbol, data })
         .collect();
 
-    result.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    sort_rank_tables(&mut result);
 
-    println!("📊 解析到 {} 个合约的持仓排名数据", result.len());
+    log::debug!("解析到 {} 个合约的持仓排名数据", result.len());
     Ok(result)
 }
 
@@ -250,7 +298,11 @@ pub async fn get_cffex_rank_table(
     date: &str,
     vars_list: Option<Vec<&str>>,
 ) -> Result<Vec<RankTableResponse>> {
-    let client = Client::new();
+    if let Some(tables) = rank_fixture("cffex", vars_list.as_deref()) {
+        return tables;
+    }
+
+    let client = http_client();
 
     let cffex_vars = vec!["IF", "IC", "IM", "IH", "T", "TF", "TS", "TL"];
 
@@ -264,23 +316,20 @@ pub async fn get_cffex_rank_table(
 
     let mut all_results: Vec<RankTableResponse> = Vec::new();
 
-    let year_month = &date[..6];
-    let day = &date[6..8];
+    let trade_date = parse_yyyymmdd(date)?;
+    let year_month = trade_date.format("%Y%m");
+    let day = trade_date.format("%d");
 
     for var in target_vars {
         let url = format!(
             "{}/{}/{}/{}_1.csv",
             CFFEX_VOL_RANK_URL, year_month, day, var
         );
-        println!("📡 请求中金所 {} 持仓排名数据 URL: {}", var, url);
+        log::debug!("请求中金所 {} 持仓排名数据 URL: {}", var, url);
 
         let response = client
             .get(&url)
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-            )
-            .send()
+            .send_limited()
             .await;
 
         let response = match response {
@@ -299,64 +348,68 @@ pub async fn get_cffex_rank_table(
         let bytes = response.bytes().await?;
         let text = encoding_rs::GBK.decode(&bytes).0.to_string();
 
-        let mut symbol_data: HashMap<String, Vec<PositionRankData>> = HashMap::new();
+        all_results.extend(parse_cffex_rank_csv(&text));
+    }
 
-        let lines: Vec<&str> = text.lines().collect();
+    sort_rank_tables(&mut all_results);
 
-        for line in lines {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
+    log::debug!("解析到 {} 个合约的持仓排名数据", all_results.len());
+    Ok(all_results)
+}
 
-            if line.contains("交易日") || line.contains("合约") || line.contains("名次") {
-                continue;
-            }
 
-            let fields: Vec<&str> = line.split(',').collect();
-            if fields.len() < 12 {
-                continue;
-            }
+/// 解析中金所单个品种的持仓排名 CSV（已按 GBK 解码），按合约分组
+///
+/// 跳过表头行（含 交易日/合约/名次）和字段数不足 12 的行
+fn parse_cffex_rank_csv(text: &str) -> Vec<RankTableResponse> {
+    let mut symbol_data: BTreeMap<String, Vec<PositionRankData>> = BTreeMap::new();
+
+    for fields in parse_csv(text) {
+        if fields
+            .iter()
+            .any(|f| f.contains("交易日") || f.contains("合约") || f.contains("名次"))
+        {
+            continue;
+        }
 
-            let symbol = fields[1].trim().to_string();
-            if symbol.is_empty() {
-                continue;
-            }
+        if fields.len() < 12 {
+            continue;
+        }
 
-            let rank = fields[2].trim().parse::<i32>().unwrap_or(0);
-            if rank <= 0 {
-                continue;
-            }
+        let symbol = fields[1].trim().to_string();
+        if symbol.is_empty() {
+            continue;
+        }
 
-            let variety = extract_variety(&symbol);
+        let rank = fields[2].trim().parse::<i32>().unwrap_or(0);
+        if rank <= 0 {
+            continue;
+        }
 
-            let data = PositionRankData {
-                rank,
-                vol_party_name: fields[3].trim().to_string(),
-                vol: fields[4].trim().replace(",", "").parse().unwrap_or(0),
-                vol_chg: fields[5].trim().replace(",", "").parse().unwrap_or(0),
-                long_party_name: fields[6].trim().to_string(),
-                long_open_interest: fields[7].trim().replace(",", "").parse().unwrap_or(0),
-                long_open_interest_chg: fields[8].trim().replace(",", "").parse().unwrap_or(0),
-                short_party_name: fields[9].trim().to_string(),
-                short_open_interest: fields[10].trim().replace(",", "").parse().unwrap_or(0),
-                short_open_interest_chg: fields[11].trim().replace(",", "").parse().unwrap_or(0),
-                symbol: symbol.clone(),
-                variety,
-            };
+        let variety = extract_variety(&symbol);
 
-            symbol_data.entry(symbol).or_default().push(data);
-        }
+        let data = PositionRankData {
+            rank,
+            vol_party_name: fields[3].trim().to_string(),
+            vol: fields[4].trim().replace(",", "").parse().unwrap_or(0),
+            vol_chg: fields[5].trim().replace(",", "").parse().unwrap_or(0),
+            long_party_name: fields[6].trim().to_string(),
+            long_open_interest: fields[7].trim().replace(",", "").parse().unwrap_or(0),
+            long_open_interest_chg: fields[8].trim().replace(",", "").parse().unwrap_or(0),
+            short_party_name: fields[9].trim().to_string(),
+            short_open_interest: fields[10].trim().replace(",", "").parse().unwrap_or(0),
+            short_open_interest_chg: fields[11].trim().replace(",", "").parse().unwrap_or(0),
+            symbol: symbol.clone(),
+            variety,
+        };
 
-        for (symbol, data) in symbol_data {
-            all_results.push(RankTableResponse { symbol, data });
-        }
+        symbol_data.entry(symbol).or_default().push(data);
     }
 
-    all_results.sort_by(|a, b| a.symbol.cmp(&b.symbol));
-
-    println!("📊 解析到 {} 个合约的持仓排名数据", all_results.len());
-    Ok(all_results)
+    symbol_data
+        .into_iter()
+        .map(|(symbol, data)| RankTableResponse { symbol, data })
+        .collect()
 }
 
 
@@ -366,58 +419,36 @@ pub async fn get_cffex_rank_table(
 /// 对应 akshare 的 get_rank_table_czce() 函数
 /// 数据来源: https://www.czce.com.cn/cn/jysj/ccpm/H077003004index_1.htm
 /// date: 交易日期，格式 YYYYMMDD，数据从 20151008 开始
-pub async fn get_rank_table_czce(date: &str) -> Result<Vec<RankTableResponse>> {
-    use calamine::{open_workbook_auto_from_rs, Reader};
-
-    let client = Client::new();
-
-    let year = &date[..4];
-    let url = if date >= "20251102" {
-        format!(
-            "https://www.czce.com.cn/cn/DFSStaticFiles/Future/{}/{}/FutureDataHolding.xlsx",
-            year, date
-        )
-    } else {
-        format!(
-            "https://www.czce.com.cn/cn/DFSStaticFiles/Future/{}/{}/FutureDataHolding.xls",
-            year, date
-        )
-    };
-
-    println!("📡 请求郑商所持仓排名数据 URL: {}", url);
-
-    let response = client
-        .get(&url)
-        .header(
-            "User-Agent",
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-        )
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(anyhow!("获取郑商所持仓排名数据失败: {}", response.status()));
+/// vars_list: 品种代码列表，如 ["SR", "CF"]，为空时返回所有品种（解析时直接跳过未请求的合约）
+pub async fn get_rank_table_czce(
+    date: &str,
+    vars_list: Option<Vec<&str>>,
+) -> Result<Vec<RankTableResponse>> {
+    if let Some(tables) = rank_fixture("czce", vars_list.as_deref()) {
+        return tables;
     }
 
-    let bytes = response.bytes().await?;
-
-    use std::io::Cursor;
-    let cursor = Cursor::new(bytes.as_ref());
+    let client = http_client();
 
-    let mut workbook =
-        open_workbook_auto_from_rs(cursor).map_err(|e| anyhow!("打开Excel文件失败: {}", e))?;
-
-    let sheet_names = workbook.sheet_names();
-    if sheet_names.is_empty() {
-        return Err(anyhow!("Excel文件没有工作表"));
-    }
-    let first_sheet = sheet_names[0].clone();
+    let url_stem = format!(
+        "https://www.czce.com.cn/cn/DFSStaticFiles/Future/{}/{}/FutureDataHolding",
+        parse_yyyymmdd(date)?.year(),
+        date
+    );
+    let range = match fetch_czce_sheet(&client, &url_stem, date >= "20251102").await {
+        Ok(range) => range,
+        Err(e) if e.is::<CzceFileMissing>() => return ensure_published(date, Vec::new()),
+        Err(e) => return Err(anyhow!("获取郑商所持仓排名数据失败: {}", e)),
+    };
 
-    let range = workbook
-        .worksheet_range(&first_sheet)
-        .map_err(|e| anyhow!("读取工作表失败: {}", e))?;
+    let result = parse_czce_rank_sheet(&range, vars_list.as_deref());
+    log::debug!("解析到 {} 个合约的持仓排名数据", result.len());
+    Ok(result)
+}
 
-    let mut symbol_data: HashMap<String, Vec<PositionRankData>> = HashMap::new();
+/// 解析郑商所持仓排名工作表，vars_list 不为空时跳过未请求品种的合约
+fn parse_czce_rank_sheet(range: &Range<Data>, vars_list: Option<&[&str]>) -> Vec<RankTableResponse> {
+    let mut symbol_data: BTreeMap<String, Vec<PositionRankData>> = BTreeMap::new();
     let mut current_symbol = String::new();
     let symbol_re = Regex::new(r"([A-Za-z]+\d+)").unwrap();
 
@@ -434,6 +465,14 @@ pub async fn get_rank_table_czce(date: &str) -> Result<Vec<RankTableResponse>> {
                     .get(1)
                     .map(|m| m.as_str().to_uppercase())
                     .unwrap_or_default();
+
+                // 未请求的品种置空，跳过其后的明细行
+                if let Some(vars) = vars_list {
+                    let variety = extract_variety(&current_symbol);
+                    if !vars.iter().any(|v| v.eq_ignore_ascii_case(&variety)) {
+                        current_symbol.clear();
+                    }
+                }
             }
             continue;
         }
@@ -450,13 +489,7 @@ pub async fn get_rank_table_czce(date: &str) -> Result<Vec<RankTableResponse>> {
 
             let variety = extract_variety(&current_symbol);
 
-            let parse_num = |s: &str| -> i64 {
-                s.replace(",", "")
-                    .replace("-", "0")
-                    .trim()
-                    .parse()
-                    .unwrap_or(0)
-            };
+            let parse_num = parse_signed_int;
 
             let data = PositionRankData {
                 rank,
@@ -482,10 +515,8 @@ pub async fn get_rank_table_czce(date: &str) -> Result<Vec<RankTableResponse>> {
         .map(|(symbol, data)| RankTableResponse { symbol, data })
         .collect();
 
-    result.sort_by(|a, b| a.symbol.cmp(&b.symbol));
-
-    println!("📊 解析到 {} 个合约的持仓排名数据", result.len());
-    Ok(result)
+    sort_rank_tables(&mut result);
+    result
 }
 
 // ==================== 大商所持仓排名 ====================
@@ -499,12 +530,15 @@ pub async fn get_dce_rank_table(
     date: &str,
     vars_list: Option<Vec<&str>>,
 ) -> Result<Vec<RankTableResponse>> {
-    let client = Client::builder().cookie_store(true).build()?;
+    if let Some(tables) = rank_fixture("dce", vars_list.as_deref()) {
+        return tables;
+    }
+
+    let client = http_client_builder().cookie_store(true).build()?;
 
     let _home_resp = client
         .get("http://www.dce.com.cn/dalianshangpin/xqsj/tjsj26/rtj/rcjccpm/index.html")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .send()
+        .send_limited()
         .await;
 
     let payload = serde_json::json!({
@@ -515,39 +549,47 @@ pub async fn get_dce_rank_table(
         "lang": "zh"
     });
 
-    println!("📡 请求大商所持仓排名数据 URL: {}", DCE_VOL_RANK_URL);
+    log::debug!("请求大商所持仓排名数据 URL: {}", DCE_VOL_RANK_URL);
 
     let response = client
         .post(DCE_VOL_RANK_URL)
         .json(&payload)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
         .header("Accept", "application/json, text/plain, */*")
-        .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
-        .header("Accept-Encoding", "gzip, deflate")
-        .header("Origin", "http://www.dce.com.cn")
         .header("Referer", "http://www.dce.com.cn/dalianshangpin/xqsj/tjsj26/rtj/rcjccpm/index.html")
-        .header("Connection", "keep-alive")
-        .send()
+        .timeout(RequestTimeout::Download.duration())
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
         if response.status().as_u16() == 412 {
-            return Err(anyhow!(
-                "大商所API访问被拒绝(412)，该交易所有反爬虫机制。\n\
-                建议: 1) 稍后重试 2) 使用浏览器手动下载数据 3) 使用akshare的futures_dce_position_rank()接口"
-            ));
+            return Err(UpstreamBlocked {
+                source: "大商所",
+                status: 412,
+                suggestion: "1) 稍后重试 2) 使用浏览器手动下载数据 3) 使用akshare的futures_dce_position_rank()接口",
+            }
+            .into());
         }
         return Err(anyhow!("获取大商所持仓排名数据失败: {}", response.status()));
     }
 
     let bytes = response.bytes().await?;
+    let date = date.to_string();
+    let vars: Option<Vec<String>> = vars_list.map(|v| v.iter().map(|s| s.to_string()).collect());
+    run_blocking("大商所 ZIP 解析", move || parse_dce_rank_zip(&bytes, &date, vars.as_deref())).await
+}
 
+/// 解析大商所持仓排名 ZIP 文件（同步解析，在阻塞线程池中调用）
+fn parse_dce_rank_zip(
+    bytes: &[u8],
+    date: &str,
+    vars_list: Option<&[String]>,
+) -> Result<Vec<RankTableResponse>> {
     use std::io::{Cursor, Read};
-    let cursor = Cursor::new(bytes.as_ref());
+    let cursor = Cursor::new(bytes);
     let mut archive =
         zip::ZipArchive::new(cursor).map_err(|e| anyhow!("打开ZIP文件失败: {}", e))?;
 
-    let mut symbol_data: HashMap<String, Vec<PositionRankData>> = HashMap::new();
+    let mut symbol_data: BTreeMap<String, Vec<PositionRankData>> = BTreeMap::new();
 
     for i in 0..archive.len() {
         let mut file = archive
@@ -567,7 +609,7 @@ pub async fn get_dce_rank_table(
         let symbol = parts[1].to_uppercase();
         let variety = extract_variety(&symbol);
 
-        if let Some(ref vars) = vars_list {
+        if let Some(vars) = vars_list {
             if !vars.iter().any(|v| v.eq_ignore_ascii_case(&variety)) {
                 continue;
             }
@@ -641,9 +683,9 @@ pub async fn get_dce_rank_table(
         .map(|(symbol, data)| RankTableResponse { symbol, data })
         .collect();
 
-    result.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    sort_rank_tables(&mut result);
 
-    println!("📊 解析到 {} 个合约的持仓排名数据", result.len());
+    log::debug!("解析到 {} 个合约的持仓排名数据", result.len());
     Ok(result)
 }
 
@@ -683,9 +725,7 @@ pub async fn futures_dce_position_rank(
     date: &str,
     vars_list: Option<Vec<&str>>,
 ) -> Result<Vec<RankTableResponse>> {
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()?;
+    let client = http_client();
 
     let url = "http://www.dce.com.cn/dcereport/publicweb/dailystat/memberDealPosi/batchDownload";
 
@@ -697,34 +737,42 @@ pub async fn futures_dce_position_rank(
         "lang": "zh"
     });
 
-    println!("📡 请求大商所持仓排名数据(ZIP) URL: {}", url);
+    log::debug!("请求大商所持仓排名数据(ZIP) URL: {}", url);
 
     let response = client
         .post(url)
         .json(&payload)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .header("Accept", "*/*")
-        .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
-        .header("Accept-Encoding", "gzip, deflate")
-        .header("Origin", "http://www.dce.com.cn")
         .header("Referer", "http://www.dce.com.cn/dalianshangpin/xqsj/tjsj26/rtj/rcjccpm/index.html")
-        .send()
+        .timeout(RequestTimeout::Download.duration())
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
         if response.status().as_u16() == 412 {
-            return Err(anyhow!(
-                "大商所API访问被拒绝(412)，该交易所有反爬虫机制。\n\
-                建议: 1) 稍后重试 2) 使用浏览器手动下载数据 3) 尝试 futures_dce_position_rank_other() 接口"
-            ));
+            return Err(UpstreamBlocked {
+                source: "大商所",
+                status: 412,
+                suggestion: "1) 稍后重试 2) 使用浏览器手动下载数据 3) 尝试 futures_dce_position_rank_other() 接口",
+            }
+            .into());
         }
         return Err(anyhow!("获取大商所持仓排名数据失败: {}", response.status()));
     }
 
     let bytes = response.bytes().await?;
+    let date = date.to_string();
+    let vars: Option<Vec<String>> = vars_list.map(|v| v.iter().map(|s| s.to_string()).collect());
+    run_blocking("大商所 ZIP 解析", move || parse_dce_position_zip(&bytes, &date, vars.as_deref())).await
+}
 
+/// 解析大商所持仓排名 ZIP 文件（备用接口，同步解析，在阻塞线程池中调用）
+fn parse_dce_position_zip(
+    bytes: &[u8],
+    date: &str,
+    vars_list: Option<&[String]>,
+) -> Result<Vec<RankTableResponse>> {
     use std::io::{Cursor, Read};
-    let cursor = Cursor::new(bytes.as_ref());
+    let cursor = Cursor::new(bytes);
     let mut archive = match zip::ZipArchive::new(cursor) {
         Ok(a) => a,
         Err(e) => {
@@ -735,7 +783,7 @@ pub async fn futures_dce_position_rank(
         }
     };
 
-    let mut symbol_data: HashMap<String, Vec<PositionRankData>> = HashMap::new();
+    let mut symbol_data: BTreeMap<String, Vec<PositionRankData>> = BTreeMap::new();
 
     for i in 0..archive.len() {
         let mut file = archive
@@ -755,7 +803,7 @@ pub async fn futures_dce_position_rank(
         let symbol = parts[1].to_uppercase();
         let variety = extract_variety(&symbol);
 
-        if let Some(ref vars) = vars_list {
+        if let Some(vars) = vars_list {
             if !vars.iter().any(|v| v.eq_ignore_ascii_case(&variety)) {
                 continue;
             }
@@ -786,9 +834,9 @@ pub async fn futures_dce_position_rank(
         .map(|(symbol, data)| RankTableResponse { symbol, data })
         .collect();
 
-    result.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    sort_rank_tables(&mut result);
 
-    println!("📊 解析到 {} 个合约的持仓排名数据", result.len());
+    log::debug!("解析到 {} 个合约的持仓排名数据", result.len());
     Ok(result)
 }
 
@@ -887,18 +935,8 @@ fn parse_dce_rank_section(lines: &[&str], start: usize, end: usize) -> Vec<(Stri
 
         if fields.len() >= 4 {
             let name = fields[1].trim().replace(",", "").replace("-", "");
-            let value: i64 = fields[2]
-                .trim()
-                .replace(",", "")
-                .replace("-", "0")
-                .parse()
-                .unwrap_or(0);
-            let change: i64 = fields[3]
-                .trim()
-                .replace(",", "")
-                .replace("-", "0")
-                .parse()
-                .unwrap_or(0);
+            let value = parse_signed_int(fields[2]);
+            let change = parse_signed_int(fields[3]);
 
             if !name.is_empty() {
                 result.push((name, value, change));
@@ -912,17 +950,14 @@ fn parse_dce_rank_section(lines: &[&str], start: usize, end: usize) -> Vec<(Stri
 /// 大连商品交易所-每日持仓排名-具体合约-补充接口
 /// 对应 akshare 的 futures_dce_position_rank_other() 函数
 pub async fn futures_dce_position_rank_other(date: &str) -> Result<Vec<RankTableResponse>> {
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()?;
+    let client = http_client();
 
     let url = "http://www.dce.com.cn/publicweb/quotesdata/memberDealPosiQuotes.html";
 
-    let year: i32 = date[0..4].parse().map_err(|_| anyhow!("无效的日期格式"))?;
-    let month: i32 = date[4..6].parse().map_err(|_| anyhow!("无效的日期格式"))?;
-    let day: i32 = date[6..8].parse().map_err(|_| anyhow!("无效的日期格式"))?;
+    let trade_date = parse_yyyymmdd(date)?;
+    let (year, month, day) = (trade_date.year(), trade_date.month(), trade_date.day());
 
-    println!("📡 请求大商所持仓排名数据(HTML) URL: {}", url);
+    log::debug!("请求大商所持仓排名数据(HTML) URL: {}", url);
 
     let payload = [
         ("memberDealPosiQuotes.variety", "c"),
@@ -938,20 +973,19 @@ pub async fn futures_dce_position_rank_other(date: &str) -> Result<Vec<RankTable
     let response = client
         .post(url)
         .form(&payload)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
         .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
-        .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
-        .header("Origin", "http://www.dce.com.cn")
         .header("Referer", "http://www.dce.com.cn/publicweb/quotesdata/memberDealPosiQuotes.html")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
         if response.status().as_u16() == 412 {
-            return Err(anyhow!(
-                "大商所API访问被拒绝(412)，该交易所有反爬虫机制。\n\
-                建议: 1) 稍后重试 2) 使用浏览器手动下载数据"
-            ));
+            return Err(UpstreamBlocked {
+                source: "大商所",
+                status: 412,
+                suggestion: "1) 稍后重试 2) 使用浏览器手动下载数据",
+            }
+            .into());
         }
         return Err(anyhow!("获取大商所品种列表失败: {}", response.status()));
     }
@@ -964,7 +998,7 @@ pub async fn futures_dce_position_rank_other(date: &str) -> Result<Vec<RankTable
         return Err(anyhow!("未找到品种列表，可能是非交易日"));
     }
 
-    println!("📊 找到 {} 个品种", symbol_list.len());
+    log::debug!("找到 {} 个品种", symbol_list.len());
 
     let mut all_results: Vec<RankTableResponse> = Vec::new();
 
@@ -983,11 +1017,7 @@ pub async fn futures_dce_position_rank_other(date: &str) -> Result<Vec<RankTable
         let response = match client
             .post(url)
             .form(&payload)
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-            )
-            .send()
+            .send_limited()
             .await
         {
             Ok(r) => r,
@@ -1019,11 +1049,7 @@ pub async fn futures_dce_position_rank_other(date: &str) -> Result<Vec<RankTable
             let response = match client
                 .post(url)
                 .form(&payload)
-                .header(
-                    "User-Agent",
-                    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-                )
-                .send()
+                .send_limited()
                 .await
             {
                 Ok(r) => r,
@@ -1055,9 +1081,9 @@ pub async fn futures_dce_position_rank_other(date: &str) -> Result<Vec<RankTable
         }
     }
 
-    all_results.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    sort_rank_tables(&mut all_results);
 
-    println!("📊 解析到 {} 个合约的持仓排名数据", all_results.len());
+    log::debug!("解析到 {} 个合约的持仓排名数据", all_results.len());
     Ok(all_results)
 }
 
@@ -1176,19 +1202,18 @@ fn parse_dce_html_table(
         }
 
         let get_text = |idx: usize| -> String {
-            cells
+            let text = cells
                 .get(idx)
-                .map(|c| {
-                    c.text()
-                        .collect::<String>()
-                        .trim()
-                        .replace(",", "")
-                        .replace("-", "0")
-                })
-                .unwrap_or_default()
+                .map(|c| c.text().collect::<String>().trim().to_string())
+                .unwrap_or_default();
+            if text == "-" {
+                String::new()
+            } else {
+                text
+            }
         };
 
-        let get_num = |idx: usize| -> i64 { get_text(idx).parse().unwrap_or(0) };
+        let get_num = |idx: usize| -> i64 { parse_signed_int(&get_text(idx)) };
 
         result.push(PositionRankData {
             rank,
@@ -1215,17 +1240,16 @@ fn parse_dce_html_table(
 /// 获取广州期货交易所品种列表
 /// 对应 akshare 的 __futures_gfex_vars_list() 函数
 pub async fn get_gfex_vars_list() -> Result<Vec<String>> {
-    let client = Client::new();
+    let client = http_client();
     let url = "http://www.gfex.com.cn/u/interfacesWebVariety/loadList";
 
-    println!("📡 请求广期所品种列表 URL: {}", url);
+    log::debug!("请求广期所品种列表 URL: {}", url);
 
     let response = client
         .post(url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Safari/537.36")
         .header("Content-Length", "0")
         .header("Content-Type", "application/x-www-form-urlencoded")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -1244,7 +1268,7 @@ pub async fn get_gfex_vars_list() -> Result<Vec<String>> {
         .map(|s| s.to_string())
         .collect();
 
-    println!("📊 获取到 {} 个品种", vars.len());
+    log::debug!("获取到 {} 个品种", vars.len());
     Ok(vars)
 }
 
@@ -1257,9 +1281,8 @@ async fn get_gfex_contract_list(client: &Client, symbol: &str, date: &str) -> Re
     let response = client
         .post(url)
         .form(&payload)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Safari/537.36")
         .header("Content-Type", "application/x-www-form-urlencoded")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -1290,86 +1313,113 @@ async fn get_gfex_contract_list(client: &Client, symbol: &str, date: &str) -> Re
     Ok(contracts)
 }
 
-/// 获取广期所合约持仓排名数据
-async fn get_gfex_contract_data(
+/// 获取广期所单个合约某一类排名（1 成交量、2 持买单量、3 持卖单量），返回 (会员简称, 数量, 增减)
+///
+/// 上游返回非成功状态时视为该类排名无数据
+async fn get_gfex_rank_column(
     client: &Client,
     symbol: &str,
     contract_id: &str,
     date: &str,
-) -> Result<Vec<PositionRankData>> {
+    data_type: u8,
+) -> Result<Vec<(String, i64, i64)>> {
     let url = "http://www.gfex.com.cn/u/interfacesWebTiMemberDealPosiQuotes/loadList";
 
-    let mut vol_data: Vec<(String, i64, i64)> = Vec::new();
-    let mut long_data: Vec<(String, i64, i64)> = Vec::new();
-    let mut short_data: Vec<(String, i64, i64)> = Vec::new();
+    let data_type = data_type.to_string();
+    let payload = [
+        ("trade_date", date),
+        ("trade_type", "0"),
+        ("variety", symbol),
+        ("contract_id", contract_id),
+        ("data_type", &data_type),
+    ];
 
-    for data_type in 1..=3 {
-        let payload = [
-            ("trade_date", date),
-            ("trade_type", "0"),
-            ("variety", symbol),
-            ("contract_id", contract_id),
-            ("data_type", &data_type.to_string()),
-        ];
+    let response = client
+        .post(url)
+        .form(&payload)
+        .send_limited()
+        .await?;
 
-        let response = client
-            .post(url)
-            .form(&payload)
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-            )
-            .send()
-            .await?;
+    if !response.status().is_success() {
+        return Ok(Vec::new());
+    }
 
-        if !response.status().is_success() {
-            continue;
-        }
+    let json_data: serde_json::Value = response.json().await?;
 
-        let json_data: serde_json::Value = response.json().await?;
+    let Some(data) = json_data["data"].as_array() else {
+        return Ok(Vec::new());
+    };
 
-        if let Some(data) = json_data["data"].as_array() {
-            let parsed: Vec<(String, i64, i64)> = data
-                .iter()
-                .filter_map(|item| {
-                    let name = item["abbr"].as_str().unwrap_or("").to_string();
-                    let qty = item["todayQty"]
-                        .as_str()
-                        .or_else(|| item["todayQty"].as_i64().map(|_| ""))
-                        .unwrap_or("0")
-                        .parse::<i64>()
-                        .or_else(|_| item["todayQty"].as_i64().ok_or(()))
-                        .unwrap_or(0);
-                    let chg = item["qtySub"]
-                        .as_str()
-                        .or_else(|| item["todayQtyChg"].as_str())
-                        .unwrap_or("0")
-                        .parse::<i64>()
-                        .or_else(|_| {
-                            item["qtySub"]
-                                .as_i64()
-                                .or_else(|| item["todayQtyChg"].as_i64())
-                                .ok_or(())
-                        })
-                        .unwrap_or(0);
-
-                    if name.is_empty() || name == "合计" {
-                        None
-                    } else {
-                        Some((name, qty, chg))
-                    }
+    Ok(data
+        .iter()
+        .filter_map(|item| {
+            let name = item["abbr"].as_str().unwrap_or("").to_string();
+            let qty = item["todayQty"]
+                .as_str()
+                .or_else(|| item["todayQty"].as_i64().map(|_| ""))
+                .unwrap_or("0")
+                .parse::<i64>()
+                .or_else(|_| item["todayQty"].as_i64().ok_or(()))
+                .unwrap_or(0);
+            let chg = item["qtySub"]
+                .as_str()
+                .or_else(|| item["todayQtyChg"].as_str())
+                .unwrap_or("0")
+                .parse::<i64>()
+                .or_else(|_| {
+                    item["qtySub"]
+                        .as_i64()
+                        .or_else(|| item["todayQtyChg"].as_i64())
+                        .ok_or(())
                 })
-                .collect();
+                .unwrap_or(0);
 
-            match data_type {
-                1 => vol_data = parsed,
-                2 => long_data = parsed,
-                3 => short_data = parsed,
-                _ => {}
+            if name.is_empty() || name == "合计" {
+                None
+            } else {
+                Some((name, qty, chg))
             }
-        }
-    }
+        })
+        .collect())
+}
+
+/// 获取广期所合约持仓排名数据
+///
+/// 成交量、持买单量、持卖单量三类排名并发请求后按名次合并
+async fn get_gfex_contract_data(
+    client: &Client,
+    symbol: &str,
+    contract_id: &str,
+    date: &str,
+) -> Result<Vec<PositionRankData>> {
+    gfex_contract_data_with(symbol, contract_id, |data_type| {
+        get_gfex_rank_column(client, symbol, contract_id, date, data_type)
+    })
+    .await
+}
+
+/// 由 fetch 并发获取三类排名（参数为 data_type）后合并，便于替换请求层
+async fn gfex_contract_data_with<F, Fut>(
+    symbol: &str,
+    contract_id: &str,
+    fetch: F,
+) -> Result<Vec<PositionRankData>>
+where
+    F: Fn(u8) -> Fut,
+    Fut: Future<Output = Result<Vec<(String, i64, i64)>>>,
+{
+    let (vol_data, long_data, short_data) = tokio::join!(fetch(1), fetch(2), fetch(3));
+    Ok(merge_gfex_rank_columns(symbol, contract_id, &vol_data?, &long_data?, &short_data?))
+}
 
+/// 按名次合并广期所三类排名，某类较短时缺失的名次以空值补齐
+fn merge_gfex_rank_columns(
+    symbol: &str,
+    contract_id: &str,
+    vol_data: &[(String, i64, i64)],
+    long_data: &[(String, i64, i64)],
+    short_data: &[(String, i64, i64)],
+) -> Vec<PositionRankData> {
     let max_len = vol_data.len().max(long_data.len()).max(short_data.len());
     let mut result = Vec::new();
 
@@ -1394,7 +1444,40 @@ async fn get_gfex_contract_data(
         });
     }
 
-    Ok(result)
+    result
+}
+
+/// 并发获取广期所品种下各合约的持仓排名，同时请求的合约数不超过 GFEX_CONTRACT_CONCURRENCY
+///
+/// 单个合约失败时记录日志并跳过，无数据的合约不返回
+async fn get_gfex_contracts_rank(
+    client: &Client,
+    symbol: &str,
+    contracts: &[String],
+    date: &str,
+) -> Vec<RankTableResponse> {
+    use futures::StreamExt;
+
+    futures::stream::iter(contracts)
+        .map(|contract| async move {
+            (contract, get_gfex_contract_data(client, symbol, contract, date).await)
+        })
+        .buffered(GFEX_CONTRACT_CONCURRENCY)
+        .filter_map(|(contract, result)| async move {
+            match result {
+                Ok(data) if !data.is_empty() => Some(RankTableResponse {
+                    symbol: contract.to_uppercase(),
+                    data,
+                }),
+                Ok(_) => None,
+                Err(e) => {
+                    log::warn!("获取广期所 {} 合约数据失败: {}", contract, e);
+                    None
+                }
+            }
+        })
+        .collect()
+        .await
 }
 
 /// 获取广州期货交易所前20会员持仓排名数据
@@ -1403,7 +1486,11 @@ pub async fn get_gfex_rank_table(
     date: &str,
     vars_list: Option<Vec<&str>>,
 ) -> Result<Vec<RankTableResponse>> {
-    let client = Client::new();
+    if let Some(tables) = rank_fixture("gfex", vars_list.as_deref()) {
+        return tables;
+    }
+
+    let client = http_client();
 
     let gfex_vars = ["SI", "LC", "PS"];
 
@@ -1427,26 +1514,12 @@ pub async fn get_gfex_rank_table(
             }
         };
 
-        for contract in contract_list {
-            match get_gfex_contract_data(&client, &var, &contract, date).await {
-                Ok(data) => {
-                    if !data.is_empty() {
-                        all_results.push(RankTableResponse {
-                            symbol: contract.to_uppercase(),
-                            data,
-                        });
-                    }
-                }
-                Err(e) => {
-                    log::warn!("获取广期所 {} 合约数据失败: {}", contract, e);
-                }
-            }
-        }
+        all_results.extend(get_gfex_contracts_rank(&client, &var, &contract_list, date).await);
     }
 
-    all_results.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    sort_rank_tables(&mut all_results);
 
-    println!("📊 解析到 {} 个合约的持仓排名数据", all_results.len());
+    log::debug!("解析到 {} 个合约的持仓排名数据", all_results.len());
     Ok(all_results)
 }
 
@@ -1456,7 +1529,7 @@ pub async fn futures_gfex_position_rank(
     date: &str,
     vars_list: Option<Vec<&str>>,
 ) -> Result<Vec<RankTableResponse>> {
-    let client = Client::new();
+    let client = http_client();
 
     let target_vars: Vec<String> = match vars_list {
         Some(vars) => vars.into_iter().map(|v| v.to_lowercase()).collect(),
@@ -1469,7 +1542,7 @@ pub async fn futures_gfex_position_rank(
         },
     };
 
-    println!("📡 请求广期所持仓排名数据，品种: {:?}", target_vars);
+    log::debug!("请求广期所持仓排名数据，品种: {:?}", target_vars);
 
     let mut all_results: Vec<RankTableResponse> = Vec::new();
 
@@ -1487,42 +1560,196 @@ pub async fn futures_gfex_position_rank(
             continue;
         }
 
-        println!(
-            "  品种 {} 有 {} 个合约",
+        log::debug!(
+            "品种 {} 有 {} 个合约",
             var.to_uppercase(),
             contract_list.len()
         );
 
-        for contract in contract_list {
-            match get_gfex_contract_data(&client, &var, &contract, date).await {
-                Ok(data) => {
-                    if !data.is_empty() {
-                        all_results.push(RankTableResponse {
-                            symbol: contract.to_uppercase(),
-                            data,
-                        });
-                    }
-                }
-                Err(e) => {
-                    log::warn!("获取广期所 {} 合约数据失败: {}", contract, e);
-                }
-            }
-        }
+        all_results.extend(get_gfex_contracts_rank(&client, &var, &contract_list, date).await);
     }
 
-    all_results.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    sort_rank_tables(&mut all_results);
 
-    println!("📊 解析到 {} 个合约的持仓排名数据", all_results.len());
+    log::debug!("解析到 {} 个合约的持仓排名数据", all_results.len());
     Ok(all_results)
 }
 
 
+// ==================== 持仓排名统一入口 ====================
+
+/// 持仓排名表支持的交易所
+pub const RANK_EXCHANGES: [&str; 5] = ["SHFE", "CFFEX", "DCE", "CZCE", "GFEX"];
+
+/// 按交易所获取持仓排名表
+///
+/// exchange 为 RANK_EXCHANGES 之一（不区分大小写）。各交易所按 vars 过滤的方式不同
+/// （部分交易所整表下载），返回前统一按合约的品种代码再过滤一次
+pub async fn get_rank_table(
+    exchange: &str,
+    date: &str,
+    vars_list: Option<Vec<&str>>,
+) -> Result<Vec<RankTableResponse>> {
+    let varieties: Vec<String> = vars_list
+        .iter()
+        .flatten()
+        .map(|v| v.trim().to_uppercase())
+        .filter(|v| !v.is_empty())
+        .collect();
+
+    let tables = match exchange.to_uppercase().as_str() {
+        "SHFE" => get_shfe_rank_table(date, vars_list).await?,
+        "CFFEX" => get_cffex_rank_table(date, vars_list).await?,
+        "DCE" => get_dce_rank_table(date, vars_list).await?,
+        "CZCE" => get_rank_table_czce(date, vars_list).await?,
+        "GFEX" => get_gfex_rank_table(date, vars_list).await?,
+        other => {
+            return Err(anyhow!(
+                "不支持的交易所: {}，可选 {}",
+                other,
+                RANK_EXCHANGES.join("/")
+            ))
+        }
+    };
+
+    if varieties.is_empty() {
+        return Ok(tables);
+    }
+    Ok(tables
+        .into_iter()
+        .filter(|table| varieties.contains(&variety_of(&table.symbol)))
+        .collect())
+}
+
+
 // ==================== 持仓排名汇总 ====================
 
+/// 持仓排名表数据源
+///
+/// 汇总计算只依赖各交易所按合约分组的排名表，通过该 trait 获取，
+/// 与具体的上游请求解耦
+pub trait RankSource {
+    /// 上期所持仓排名表
+    fn shfe_rank(&self, date: &str, vars: Vec<&str>) -> impl Future<Output = Result<Vec<RankTableResponse>>>;
+    /// 大商所持仓排名表
+    fn dce_rank(&self, date: &str, vars: Vec<&str>) -> impl Future<Output = Result<Vec<RankTableResponse>>>;
+    /// 郑商所持仓排名表
+    fn czce_rank(&self, date: &str, vars: Vec<&str>) -> impl Future<Output = Result<Vec<RankTableResponse>>>;
+    /// 中金所持仓排名表
+    fn cffex_rank(&self, date: &str, vars: Vec<&str>) -> impl Future<Output = Result<Vec<RankTableResponse>>>;
+    /// 广期所持仓排名表
+    fn gfex_rank(&self, date: &str, vars: Vec<&str>) -> impl Future<Output = Result<Vec<RankTableResponse>>>;
+}
+
+/// 从各交易所官网获取持仓排名表
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HttpRankSource;
+
+impl RankSource for HttpRankSource {
+    async fn shfe_rank(&self, date: &str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+        get_shfe_rank_table(date, Some(vars)).await
+    }
+
+    async fn dce_rank(&self, date: &str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+        get_dce_rank_table(date, Some(vars)).await
+    }
+
+    async fn czce_rank(&self, date: &str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+        get_rank_table_czce(date, Some(vars)).await
+    }
+
+    async fn cffex_rank(&self, date: &str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+        get_cffex_rank_table(date, Some(vars)).await
+    }
+
+    async fn gfex_rank(&self, date: &str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+        get_gfex_rank_table(date, Some(vars)).await
+    }
+}
+
 /// 获取单日期货持仓排名汇总数据
 /// 对应 akshare 的 get_rank_sum() 函数
 /// 采集五个期货交易所前5、前10、前15、前20会员持仓排名数据
-pub async fn get_rank_sum(date: &str, vars_list: Option<Vec<String>>) -> Result<Vec<RankSum>> {
+///
+/// 单个交易所获取失败时记录日志并跳过，返回其余交易所的汇总
+pub async fn get_rank_sum(
+    source: &impl RankSource,
+    date: &str,
+    vars_list: Option<Vec<String>>,
+) -> Result<Vec<RankSum>> {
+    Ok(rank_sum_with_failures(source, date, vars_list).await?.0)
+}
+
+/// 持仓排名汇总中有交易所获取失败，结果不完整，不写入文件缓存
+#[derive(Debug)]
+pub struct IncompleteRankSum {
+    /// 获取失败的交易所代码
+    pub failed: Vec<&'static str>,
+    /// 其余交易所的汇总结果
+    pub data: Vec<RankSum>,
+}
+
+impl std::fmt::Display for IncompleteRankSum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "持仓排名汇总不完整，获取失败的交易所: {}", self.failed.join("/"))
+    }
+}
+
+impl std::error::Error for IncompleteRankSum {}
+
+/// 获取单日持仓排名汇总，带文件缓存
+///
+/// 历史日期命中缓存时不再请求各交易所；有交易所获取失败时返回其余交易所的汇总但不写入缓存，
+/// 避免不完整的结果被长期缓存
+pub async fn get_rank_sum_cached(
+    date: &str,
+    vars_list: Option<Vec<String>>,
+    no_cache: bool,
+) -> Result<Vec<RankSum>> {
+    let key = rank_cache_key(vars_list.as_ref().map(|vars| vars.join(",")).as_deref());
+    let result = with_file_cache("rank_sum", date, &key, no_cache, || async {
+        let (data, failed) = rank_sum_with_failures(&HttpRankSource, date, vars_list).await?;
+        if failed.is_empty() {
+            Ok(data)
+        } else {
+            Err(IncompleteRankSum { failed, data }.into())
+        }
+    })
+    .await;
+
+    match result {
+        Err(e) => match e.chain().find_map(|c| c.downcast_ref::<IncompleteRankSum>()) {
+            Some(incomplete) => {
+                log::warn!("{} {}，本次结果不写入缓存", date, incomplete);
+                Ok(incomplete.data.clone())
+            }
+            None => Err(e),
+        },
+        ok => ok,
+    }
+}
+
+/// 持仓排名缓存文件名：品种代码排序后拼接，未指定品种时为 all
+pub fn rank_cache_key(vars: Option<&str>) -> String {
+    let mut vars: Vec<String> = vars
+        .unwrap_or("")
+        .split(',')
+        .map(|s| s.trim().to_uppercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if vars.is_empty() {
+        return "all".to_string();
+    }
+    vars.sort();
+    vars.join("_")
+}
+
+/// 计算单日持仓排名汇总，同时返回获取失败的交易所代码
+async fn rank_sum_with_failures(
+    source: &impl RankSource,
+    date: &str,
+    vars_list: Option<Vec<String>>,
+) -> Result<(Vec<RankSum>, Vec<&'static str>)> {
     let dce_vars: Vec<&str> = vec![
         "C", "CS", "A", "B", "M", "Y", "P", "FB", "BB", "JD", "L", "V", "PP", "J", "JM", "I", "EG",
         "RR", "EB", "PG", "LH", "LG", "BZ",
@@ -1531,11 +1758,7 @@ pub async fn get_rank_sum(date: &str, vars_list: Option<Vec<String>>) -> Result<
         "CU", "AL", "ZN", "PB", "NI", "SN", "AU", "AG", "RB", "WR", "HC", "FU", "BU", "RU", "SC",
         "NR", "SP", "SS", "LU", "BC", "AO", "BR", "EC", "AD",
     ];
-    let czce_vars: Vec<&str> = vec![
-        "WH", "PM", "CF", "SR", "TA", "OI", "RI", "MA", "ME", "FG", "RS", "RM", "ZC", "JR", "LR",
-        "SF", "SM", "WT", "TC", "GN", "RO", "ER", "SRX", "SRY", "WSX", "WSY", "CY", "AP", "UR",
-        "CJ", "SA", "PK", "PF", "PX", "SH", "PR",
-    ];
+    let czce_vars: Vec<&str> = CZCE_VARIETIES.to_vec();
     let cffex_vars: Vec<&str> = vec!["IF", "IC", "IM", "IH", "T", "TF", "TS", "TL"];
     let gfex_vars: Vec<&str> = vec!["SI", "LC", "PS"];
 
@@ -1556,72 +1779,86 @@ pub async fn get_rank_sum(date: &str, vars_list: Option<Vec<String>>) -> Result<
     let cffex_target = filter_vars(&cffex_vars, &vars_list);
     let gfex_target = filter_vars(&gfex_vars, &vars_list);
 
-    let mut all_rank_data: HashMap<String, Vec<PositionRankData>> = HashMap::new();
+    let mut all_rank_data: BTreeMap<String, Vec<PositionRankData>> = BTreeMap::new();
+    let mut failed: Vec<&'static str> = Vec::new();
 
     // 获取大商所数据
     if !dce_target.is_empty() {
         let dce_refs: Vec<&str> = dce_target.iter().map(|s| s.as_str()).collect();
-        match get_dce_rank_table(date, Some(dce_refs)).await {
+        match source.dce_rank(date, dce_refs).await {
             Ok(data) => {
                 for item in data {
                     all_rank_data.insert(item.symbol.clone(), item.data);
                 }
             }
-            Err(e) => log::warn!("获取大商所数据失败: {}", e),
+            Err(e) => {
+                log::warn!("获取大商所数据失败: {}", e);
+                failed.push("DCE");
+            }
         }
     }
 
     // 获取上期所数据
     if !shfe_target.is_empty() {
         let shfe_refs: Vec<&str> = shfe_target.iter().map(|s| s.as_str()).collect();
-        match get_shfe_rank_table(date, Some(shfe_refs)).await {
+        match source.shfe_rank(date, shfe_refs).await {
             Ok(data) => {
                 for item in data {
                     all_rank_data.insert(item.symbol.clone(), item.data);
                 }
             }
-            Err(e) => log::warn!("获取上期所数据失败: {}", e),
+            Err(e) => {
+                log::warn!("获取上期所数据失败: {}", e);
+                failed.push("SHFE");
+            }
         }
     }
 
     // 获取郑商所数据
     if !czce_target.is_empty() {
-        match get_rank_table_czce(date).await {
+        let czce_refs: Vec<&str> = czce_target.iter().map(|s| s.as_str()).collect();
+        match source.czce_rank(date, czce_refs).await {
             Ok(data) => {
                 for item in data {
-                    let variety = extract_variety(&item.symbol);
-                    if czce_target.iter().any(|v| v.eq_ignore_ascii_case(&variety)) {
-                        all_rank_data.insert(item.symbol.clone(), item.data);
-                    }
+                    all_rank_data.insert(item.symbol.clone(), item.data);
                 }
             }
-            Err(e) => log::warn!("获取郑商所数据失败: {}", e),
+            Err(e) => {
+                log::warn!("获取郑商所数据失败: {}", e);
+                failed.push("CZCE");
+            }
         }
     }
 
     // 获取中金所数据
     if !cffex_target.is_empty() {
         let cffex_refs: Vec<&str> = cffex_target.iter().map(|s| s.as_str()).collect();
-        match get_cffex_rank_table(date, Some(cffex_refs)).await {
+        match source.cffex_rank(date, cffex_refs).await {
             Ok(data) => {
                 for item in data {
                     all_rank_data.insert(item.symbol.clone(), item.data);
                 }
             }
-            Err(e) => log::warn!("获取中金所数据失败: {}", e),
+            Err(e) => {
+                log::warn!("获取中金所数据失败: {}", e);
+                failed.push("CFFEX");
+            }
         }
     }
 
     // 获取广期所数据
     if !gfex_target.is_empty() {
         let gfex_refs: Vec<&str> = gfex_target.iter().map(|s| s.as_str()).collect();
-        match get_gfex_rank_table(date, Some(gfex_refs)).await {
+        match source.gfex_rank(date, gfex_refs).await {
             Ok(data) => {
                 for item in data {
                     all_rank_data.insert(item.symbol.clone(), item.data);
                 }
             }
-            Err(e) => log::warn!("获取广期所数据失败: {}", e),
+            Err(e) => {
+                log::warn!("获取广期所数据失败: {}", e);
+                failed.push("GFEX");
+            }
         }
     }
 
@@ -1670,7 +1907,7 @@ pub async fn get_rank_sum(date: &str, vars_list: Option<Vec<String>>) -> Result<
     }
 
     // 添加品种汇总
-    let mut variety_sums: HashMap<String, RankSum> = HashMap::new();
+    let mut variety_sums: BTreeMap<String, RankSum> = BTreeMap::new();
 
     for result in &results {
         let variety = &result.variety;
@@ -1723,8 +1960,8 @@ pub async fn get_rank_sum(date: &str, vars_list: Option<Vec<String>>) -> Result<
 
     results.sort_by(|a, b| a.symbol.cmp(&b.symbol));
 
-    println!("📊 计算得到 {} 条持仓排名汇总数据", results.len());
-    Ok(results)
+    log::debug!("计算得到 {} 条持仓排名汇总数据", results.len());
+    Ok((results, failed))
 }
 
 /// 获取日期范围内的期货持仓排名汇总数据
@@ -1744,33 +1981,832 @@ pub async fn get_rank_sum_daily(
     if start > end {
         return Err(anyhow!("开始日期不能大于结束日期"));
     }
+    check_date_range(start, end)?;
 
     let mut all_results: Vec<RankSum> = Vec::new();
     let mut current = start;
 
     while current <= end {
         let date_str = current.format("%Y%m%d").to_string();
-        println!("📅 正在获取 {} 的持仓排名数据...", date_str);
+        log::debug!("正在获取 {} 的持仓排名数据...", date_str);
 
         let vars_clone: Option<Vec<String>> = vars_list.clone();
 
-        match get_rank_sum(&date_str, vars_clone).await {
+        match get_rank_sum_cached(&date_str, vars_clone, false).await {
             Ok(mut data) => {
                 if !data.is_empty() {
-                    println!("  ✅ 获取到 {} 条数据", data.len());
+                    log::debug!("获取到 {} 条数据", data.len());
                     all_results.append(&mut data);
                 } else {
-                    println!("  ⚠️ {} 无数据（可能是非交易日）", date_str);
+                    log::debug!("{} 无数据（可能是非交易日）", date_str);
                 }
             }
             Err(e) => {
-                println!("  ❌ {} 获取失败: {}", date_str, e);
+                log::warn!("{} 获取失败: {}", date_str, e);
             }
         }
 
         current = current.succ_opt().unwrap_or(current);
     }
 
-    println!("📊 共获取 {} 条持仓排名汇总数据", all_results.len());
+    log::debug!("共获取 {} 条持仓排名汇总数据", all_results.len());
     Ok(all_results)
 }
+
+// ==================== 净持仓统计 ====================
+
+/// 获取合约会员净持仓及前20汇总
+/// exchange: 交易所代码 SHFE/CFFEX/DCE/CZCE/GFEX（不区分大小写）
+/// contract: 合约代码，如 "RB2510"
+/// date: 交易日期，格式 YYYYMMDD
+///
+/// 合约不在排名表中时返回 None
+pub async fn get_position_net(
+    exchange: &str,
+    contract: &str,
+    date: &str,
+) -> Result<Option<PositionNetResponse>> {
+    let exchange = exchange.to_uppercase();
+    Ok(fetch_contract_rank(&exchange, contract, date)
+        .await?
+        .map(|t| {
+            let mut net = compute_position_net(&t.data);
+            net.exchange = exchange.clone();
+            net.symbol = t.symbol;
+            net.date = date.to_string();
+            net
+        }))
+}
+
+/// 按交易所获取指定合约某日的持仓排名表，合约不在排名表中时返回 None
+async fn fetch_contract_rank(
+    exchange: &str,
+    contract: &str,
+    date: &str,
+) -> Result<Option<RankTableResponse>> {
+    let variety = extract_variety(contract);
+    let vars = Some(vec![variety.as_str()]);
+
+    let tables = match exchange.to_uppercase().as_str() {
+        "SHFE" => get_shfe_rank_table(date, vars).await?,
+        "CFFEX" => get_cffex_rank_table(date, vars).await?,
+        "DCE" => get_dce_rank_table(date, vars).await?,
+        "CZCE" => get_rank_table_czce(date, vars).await?,
+        "GFEX" => get_gfex_rank_table(date, vars).await?,
+        _ => return Err(anyhow!("不支持的交易所: {}", exchange)),
+    };
+
+    Ok(tables
+        .into_iter()
+        .find(|t| t.symbol.eq_ignore_ascii_case(contract)))
+}
+
+/// 根据持仓排名计算会员净持仓和前20汇总
+///
+/// 多空两侧按会员简称匹配，仅同时出现在两侧的会员计入明细；
+/// 前20汇总按各自排名表统计
+pub fn compute_position_net(data: &[PositionRankData]) -> PositionNetResponse {
+    let top20 = data.iter().filter(|d| d.rank <= 20);
+    let (mut long_top20, mut long_chg_top20, mut short_top20, mut short_chg_top20) = (0, 0, 0, 0);
+    for d in top20 {
+        if !d.long_party_name.is_empty() {
+            long_top20 += d.long_open_interest;
+            long_chg_top20 += d.long_open_interest_chg;
+        }
+        if !d.short_party_name.is_empty() {
+            short_top20 += d.short_open_interest;
+            short_chg_top20 += d.short_open_interest_chg;
+        }
+    }
+
+    let shorts: HashMap<&str, &PositionRankData> = data
+        .iter()
+        .filter(|d| !d.short_party_name.is_empty())
+        .map(|d| (d.short_party_name.trim(), d))
+        .collect();
+
+    let mut members: Vec<PositionNetMember> = data
+        .iter()
+        .filter(|d| !d.long_party_name.is_empty())
+        .filter_map(|long| {
+            let short = shorts.get(long.long_party_name.trim())?;
+            Some(PositionNetMember {
+                party_name: long.long_party_name.trim().to_string(),
+                long_open_interest: long.long_open_interest,
+                long_open_interest_chg: long.long_open_interest_chg,
+                short_open_interest: short.short_open_interest,
+                short_open_interest_chg: short.short_open_interest_chg,
+                net: long.long_open_interest - short.short_open_interest,
+                net_chg: long.long_open_interest_chg - short.short_open_interest_chg,
+            })
+        })
+        .collect();
+    members.sort_by_key(|m| std::cmp::Reverse(m.net));
+
+    PositionNetResponse {
+        exchange: String::new(),
+        symbol: data.first().map(|d| d.symbol.clone()).unwrap_or_default(),
+        date: String::new(),
+        long_top20,
+        long_chg_top20,
+        short_top20,
+        short_chg_top20,
+        net_top20: long_top20 - short_top20,
+        net_chg_top20: long_chg_top20 - short_chg_top20,
+        members,
+    }
+}
+
+// ==================== 持仓集中度 ====================
+
+/// 获取合约某日的持仓集中度（CR5 和 HHI），合约不在排名表中时返回 None
+pub async fn get_position_concentration(
+    exchange: &str,
+    contract: &str,
+    date: &str,
+) -> Result<Option<PositionConcentration>> {
+    let exchange = exchange.to_uppercase();
+    Ok(fetch_contract_rank(&exchange, contract, date)
+        .await?
+        .map(|t| {
+            let mut concentration = compute_position_concentration(&t.data);
+            concentration.exchange = exchange.clone();
+            concentration.symbol = t.symbol;
+            concentration.date = date.to_string();
+            concentration
+        }))
+}
+
+/// 根据持仓排名计算多空两侧的 CR5 和 HHI
+///
+/// 多空两侧按各自的排名表统计，只计入排名前20且会员简称非空的行
+pub fn compute_position_concentration(data: &[PositionRankData]) -> PositionConcentration {
+    let longs: Vec<(i32, i64)> = data
+        .iter()
+        .filter(|d| d.rank <= 20 && !d.long_party_name.is_empty())
+        .map(|d| (d.rank, d.long_open_interest))
+        .collect();
+    let shorts: Vec<(i32, i64)> = data
+        .iter()
+        .filter(|d| d.rank <= 20 && !d.short_party_name.is_empty())
+        .map(|d| (d.rank, d.short_open_interest))
+        .collect();
+    let (long_cr5, long_hhi) = concentration_ratios(&longs);
+    let (short_cr5, short_hhi) = concentration_ratios(&shorts);
+
+    PositionConcentration {
+        exchange: String::new(),
+        symbol: data.first().map(|d| d.symbol.clone()).unwrap_or_default(),
+        date: String::new(),
+        long_cr5,
+        short_cr5,
+        long_hhi,
+        short_hhi,
+    }
+}
+
+/// 单侧 (名次, 持仓) 列表的 (CR5, HHI)，持仓合计不为正时均为 None
+fn concentration_ratios(side: &[(i32, i64)]) -> (Option<f64>, Option<f64>) {
+    let total: i64 = side.iter().map(|&(_, oi)| oi).sum();
+    if total <= 0 {
+        return (None, None);
+    }
+    let total = total as f64;
+    let top5: i64 = side.iter().filter(|&&(rank, _)| rank <= 5).map(|&(_, oi)| oi).sum();
+    let hhi = side.iter().map(|&(_, oi)| (oi as f64 / total).powi(2)).sum();
+    (Some(top5 as f64 / total), Some(hhi))
+}
+
+// ==================== 持仓排名两日对比 ====================
+
+/// 某一日期没有持仓排名数据（非交易日、尚未发布或合约未上榜）
+#[derive(Debug)]
+pub struct RankDateUnavailable {
+    pub date: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for RankDateUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} 的持仓排名不可用: {}", self.date, self.reason)
+    }
+}
+
+impl std::error::Error for RankDateUnavailable {}
+
+/// 对比合约两个交易日的会员持仓排名
+/// exchange: 交易所代码 SHFE/CFFEX/DCE/CZCE/GFEX（不区分大小写）
+/// contract: 合约代码，如 "RB2510"
+/// date1/date2: 对比的两个交易日，格式 YYYYMMDD
+///
+/// 两日排名表并发获取；任一日期无数据时返回 RankDateUnavailable 错误，
+/// 网络、解析、熔断等其余错误原样返回
+pub async fn get_position_diff(
+    exchange: &str,
+    contract: &str,
+    date1: &str,
+    date2: &str,
+) -> Result<PositionDiffResponse> {
+    let exchange = exchange.to_uppercase();
+    let (r1, r2) = futures::join!(
+        fetch_contract_rank(&exchange, contract, date1),
+        fetch_contract_rank(&exchange, contract, date2),
+    );
+
+    let t1 = rank_for_date(r1, date1, contract)?;
+    let t2 = rank_for_date(r2, date2, contract)?;
+
+    let mut diff = compute_position_diff(&t1.data, &t2.data);
+    diff.exchange = exchange;
+    diff.symbol = t2.symbol;
+    diff.date1 = date1.to_string();
+    diff.date2 = date2.to_string();
+    Ok(diff)
+}
+
+/// 将单日排名获取结果转换为带日期的错误
+///
+/// 只有"该日期没有数据"（合约未上榜、非交易日、数据尚未发布）转为 RankDateUnavailable，
+/// 其余错误原样返回，由 error_status 决定状态码
+fn rank_for_date(
+    result: Result<Option<RankTableResponse>>,
+    date: &str,
+    contract: &str,
+) -> Result<RankTableResponse> {
+    match result {
+        Ok(Some(table)) => Ok(table),
+        Ok(None) => Err(RankDateUnavailable {
+            date: date.to_string(),
+            reason: format!("排名表中未找到合约 {}", contract),
+        }
+        .into()),
+        Err(e)
+            if e.chain().any(|c| {
+                c.is::<DataNotFound>() || c.is::<DataNotYetAvailable>() || c.is::<NonTradingDay>()
+            }) =>
+        {
+            Err(RankDateUnavailable {
+                date: date.to_string(),
+                reason: e.to_string(),
+            }
+            .into())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 按会员简称对比两日持仓排名
+///
+/// 多空两侧分别匹配，会员只在一侧出现时另一侧为 None；
+/// 另外列出两侧新进和退出前20的会员
+pub fn compute_position_diff(
+    d1: &[PositionRankData],
+    d2: &[PositionRankData],
+) -> PositionDiffResponse {
+    let long1 = side_positions(d1, |d| (&d.long_party_name, d.long_open_interest));
+    let long2 = side_positions(d2, |d| (&d.long_party_name, d.long_open_interest));
+    let short1 = side_positions(d1, |d| (&d.short_party_name, d.short_open_interest));
+    let short2 = side_positions(d2, |d| (&d.short_party_name, d.short_open_interest));
+
+    // 会员顺序：先按第二日排名出现顺序，再补充仅第一日出现的会员
+    let mut names: Vec<&str> = Vec::new();
+    for d in d2.iter().chain(d1.iter()) {
+        for name in [d.long_party_name.trim(), d.short_party_name.trim()] {
+            if !name.is_empty() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    let members = names
+        .iter()
+        .map(|name| {
+            let long_d1 = long1.get(name).copied();
+            let long_d2 = long2.get(name).copied();
+            let short_d1 = short1.get(name).copied();
+            let short_d2 = short2.get(name).copied();
+            PositionDiffMember {
+                party_name: name.to_string(),
+                long_d1,
+                long_d2,
+                long_delta: long_d2.unwrap_or(0) - long_d1.unwrap_or(0),
+                short_d1,
+                short_d2,
+                short_delta: short_d2.unwrap_or(0) - short_d1.unwrap_or(0),
+            }
+        })
+        .collect();
+
+    PositionDiffResponse {
+        exchange: String::new(),
+        symbol: d2.first().map(|d| d.symbol.clone()).unwrap_or_default(),
+        date1: String::new(),
+        date2: String::new(),
+        members,
+        long_entered: top20_changes(d2, d1, |d| &d.long_party_name),
+        long_left: top20_changes(d1, d2, |d| &d.long_party_name),
+        short_entered: top20_changes(d2, d1, |d| &d.short_party_name),
+        short_left: top20_changes(d1, d2, |d| &d.short_party_name),
+    }
+}
+
+/// 提取一侧（多或空）会员持仓，键为去空白的会员简称
+fn side_positions<'a>(
+    data: &'a [PositionRankData],
+    side: impl Fn(&'a PositionRankData) -> (&'a String, i64),
+) -> HashMap<&'a str, i64> {
+    data.iter()
+        .map(side)
+        .filter(|(name, _)| !name.trim().is_empty())
+        .map(|(name, oi)| (name.trim(), oi))
+        .collect()
+}
+
+/// 在 current 前20中但不在 other 前20中的会员（保持 current 的排名顺序）
+fn top20_changes<'a>(
+    current: &'a [PositionRankData],
+    other: &'a [PositionRankData],
+    name: impl Fn(&'a PositionRankData) -> &'a String,
+) -> Vec<String> {
+    let top20 = |data: &'a [PositionRankData]| {
+        data.iter()
+            .filter(|d| d.rank <= 20)
+            .map(&name)
+            .map(|n| n.trim())
+            .filter(|n| !n.is_empty())
+            .collect::<Vec<&str>>()
+    };
+    let others = top20(other);
+    top20(current)
+        .into_iter()
+        .filter(|n| !others.contains(n))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模拟广期所 POST 层：三类排名各自延迟返回，data_type 越小返回越晚
+    fn gfex_column(data_type: u8) -> Vec<(String, i64, i64)> {
+        let members: &[&str] = match data_type {
+            1 => &["国泰君安", "中信期货", "东证期货"],
+            2 => &["中信期货", "国泰君安"],
+            _ => &["东证期货", "永安期货", "中信期货", "银河期货"],
+        };
+        members
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.to_string(), 1000 * data_type as i64 - 10 * i as i64, i as i64 - 1))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn gfex_columns_fetched_concurrently_merge_like_sequential() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        let merged = gfex_contract_data_with("si", "si2501", |data_type| {
+            let (in_flight, max_in_flight) = (&in_flight, &max_in_flight);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(40 - 10 * data_type as u64)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(gfex_column(data_type))
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+
+        let sequential = merge_gfex_rank_columns("si", "si2501", &gfex_column(1), &gfex_column(2), &gfex_column(3));
+        assert_eq!(serde_json::to_value(&merged).unwrap(), serde_json::to_value(&sequential).unwrap());
+
+        assert_eq!(merged.len(), 4);
+        assert_eq!((merged[0].symbol.as_str(), merged[0].variety.as_str()), ("SI2501", "SI"));
+        assert_eq!((merged[0].vol_party_name.as_str(), merged[0].vol), ("国泰君安", 1000));
+        assert_eq!((merged[1].long_party_name.as_str(), merged[1].long_open_interest), ("国泰君安", 1990));
+        assert_eq!((merged[3].short_party_name.as_str(), merged[3].short_open_interest), ("银河期货", 2970));
+        assert_eq!((merged[3].vol_party_name.as_str(), merged[3].vol), ("", 0));
+    }
+
+    #[tokio::test]
+    async fn gfex_column_failure_fails_the_contract() {
+        let result = gfex_contract_data_with("si", "si2501", |data_type| async move {
+            if data_type == 2 {
+                Err(anyhow!("持买单量请求失败"))
+            } else {
+                Ok(gfex_column(data_type))
+            }
+        })
+        .await;
+        assert!(result.unwrap_err().to_string().contains("持买单量"));
+    }
+
+    fn row(rank: i32, long: (&str, i64), short: (&str, i64)) -> PositionRankData {
+        PositionRankData {
+            rank,
+            vol_party_name: String::new(),
+            vol: 0,
+            vol_chg: 0,
+            long_party_name: long.0.to_string(),
+            long_open_interest: long.1,
+            long_open_interest_chg: 0,
+            short_party_name: short.0.to_string(),
+            short_open_interest: short.1,
+            short_open_interest_chg: 0,
+            symbol: "rb2510".to_string(),
+            variety: "RB".to_string(),
+        }
+    }
+
+    fn table(data: Vec<PositionRankData>) -> RankTableResponse {
+        RankTableResponse { symbol: "rb2510".to_string(), data }
+    }
+
+    fn contract(symbol: &str, vols: &[i64]) -> RankTableResponse {
+        let data = vols
+            .iter()
+            .enumerate()
+            .map(|(i, &vol)| PositionRankData {
+                vol,
+                symbol: symbol.to_string(),
+                variety: extract_variety(symbol),
+                ..row(i as i32 + 1, ("多头会员", vol * 2), ("空头会员", vol * 3))
+            })
+            .collect();
+        RankTableResponse { symbol: symbol.to_string(), data }
+    }
+
+    /// 按交易所返回预置排名表的数据源，failing 中的交易所返回错误，并记录被请求的交易所
+    #[derive(Default)]
+    struct MockRankSource {
+        tables: HashMap<&'static str, Vec<RankTableResponse>>,
+        failing: Vec<&'static str>,
+        calls: std::sync::Mutex<Vec<&'static str>>,
+    }
+
+    impl MockRankSource {
+        fn respond(&self, exchange: &'static str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+            self.calls.lock().unwrap().push(exchange);
+            if self.failing.contains(&exchange) {
+                return Err(anyhow!("{} 连接超时", exchange));
+            }
+            Ok(self
+                .tables
+                .get(exchange)
+                .into_iter()
+                .flatten()
+                .filter(|t| vars.contains(&extract_variety(&t.symbol).as_str()))
+                .cloned()
+                .collect())
+        }
+    }
+
+    impl RankSource for MockRankSource {
+        async fn shfe_rank(&self, _date: &str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+            self.respond("SHFE", vars)
+        }
+
+        async fn dce_rank(&self, _date: &str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+            self.respond("DCE", vars)
+        }
+
+        async fn czce_rank(&self, _date: &str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+            self.respond("CZCE", vars)
+        }
+
+        async fn cffex_rank(&self, _date: &str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+            self.respond("CFFEX", vars)
+        }
+
+        async fn gfex_rank(&self, _date: &str, vars: Vec<&str>) -> Result<Vec<RankTableResponse>> {
+            self.respond("GFEX", vars)
+        }
+    }
+
+    #[tokio::test]
+    async fn rank_sum_merges_contracts_into_variety_totals() {
+        let vols: Vec<i64> = (1..=25).rev().collect();
+        let source = MockRankSource {
+            tables: HashMap::from([
+                ("SHFE", vec![contract("rb2510", &vols), contract("rb2601", &[10, 5])]),
+                ("DCE", vec![contract("m2509", &[7])]),
+            ]),
+            ..Default::default()
+        };
+
+        let (sums, failed) = rank_sum_with_failures(&source, "20250102", None).await.unwrap();
+        assert!(failed.is_empty());
+
+        let by_symbol = |symbol: &str| sums.iter().find(|s| s.symbol == symbol).unwrap();
+        let rb2510 = by_symbol("rb2510");
+        assert_eq!(rb2510.vol_top5, 25 + 24 + 23 + 22 + 21);
+        assert_eq!(rb2510.vol_top20, (6..=25).sum::<i64>());
+        assert_eq!(rb2510.long_open_interest_top20, 2 * (6..=25).sum::<i64>());
+
+        let rb = by_symbol("RB");
+        assert_eq!(rb.variety, "RB");
+        assert_eq!(rb.vol_top5, rb2510.vol_top5 + 15);
+        assert_eq!(rb.short_open_interest_top20, rb2510.short_open_interest_top20 + 45);
+        assert_eq!(rb.date, "20250102");
+
+        assert_eq!(by_symbol("M").vol_top20, 7);
+        assert_eq!(sums.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn rank_sum_reports_failed_exchanges_and_skips_unrequested() {
+        let source = MockRankSource {
+            tables: HashMap::from([("SHFE", vec![contract("cu2508", &[3, 2, 1])])]),
+            failing: vec!["DCE"],
+            ..Default::default()
+        };
+
+        let vars = Some(vec!["cu".to_string(), "M".to_string()]);
+        let (sums, failed) = rank_sum_with_failures(&source, "20250102", vars).await.unwrap();
+        assert_eq!(failed, ["DCE"]);
+        assert_eq!(*source.calls.lock().unwrap(), ["DCE", "SHFE"]);
+
+        let symbols: Vec<&str> = sums.iter().map(|s| s.symbol.as_str()).collect();
+        assert_eq!(symbols, ["CU", "cu2508"]);
+        assert_eq!(sums[0].vol_top5, 6);
+
+        let all = get_rank_sum(&source, "20250102", Some(vec!["CU".to_string()])).await.unwrap();
+        assert_eq!(all.len(), 2);
+    }
+
+    #[test]
+    fn sorted_rank_tables_serialize_identically_regardless_of_input_order() {
+        let ordered = vec![contract("rb2510", &[30, 20, 10]), contract("rb2601", &[8, 4])];
+        let mut shuffled: Vec<RankTableResponse> = ordered.iter().rev().cloned().collect();
+        for table in shuffled.iter_mut() {
+            table.data.reverse();
+        }
+
+        let mut first = ordered.clone();
+        sort_rank_tables(&mut first);
+        sort_rank_tables(&mut shuffled);
+        assert_eq!(serde_json::to_string(&first).unwrap(), serde_json::to_string(&shuffled).unwrap());
+        assert_eq!(first[0].symbol, "rb2510");
+        let ranks: Vec<i32> = first[0].data.iter().map(|d| d.rank).collect();
+        assert_eq!(ranks, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn repeated_rank_sum_is_byte_identical() {
+        let source = MockRankSource {
+            tables: HashMap::from([
+                ("SHFE", vec![contract("rb2601", &[10, 5]), contract("cu2508", &[3]), contract("rb2510", &[9, 8, 7])]),
+                ("DCE", vec![contract("m2509", &[7]), contract("i2509", &[6, 2])]),
+                ("CZCE", vec![contract("SR601", &[4]), contract("MA601", &[4])]),
+            ]),
+            ..Default::default()
+        };
+
+        let first = get_rank_sum(&source, "20250102", None).await.unwrap();
+        let second = get_rank_sum(&source, "20250102", None).await.unwrap();
+        assert_eq!(serde_json::to_string(&first).unwrap(), serde_json::to_string(&second).unwrap());
+    }
+
+    /// 按行构造郑商所持仓排名工作表
+    fn czce_sheet(rows: &[Vec<String>]) -> Range<Data> {
+        let width = rows.iter().map(Vec::len).max().unwrap_or(1) as u32;
+        let mut range = Range::new((0, 0), (rows.len() as u32 - 1, width - 1));
+        for (r, row) in rows.iter().enumerate() {
+            for (c, cell) in row.iter().enumerate() {
+                range.set_value((r as u32, c as u32), Data::String(cell.clone()));
+            }
+        }
+        range
+    }
+
+    /// 一个合约的表头、明细行和合计行
+    fn czce_contract(symbol: &str, vols: &[i64]) -> Vec<Vec<String>> {
+        let cells = |cells: &[&str]| cells.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        let mut rows = vec![
+            vec![format!("合约：{}      日期：2025-01-02", symbol)],
+            cells(&["名次", "会员简称", "成交量", "增减", "会员简称", "持买仓量", "增减", "会员简称", "持卖仓量", "增减"]),
+        ];
+        for (i, vol) in vols.iter().enumerate() {
+            let (rank, vol) = ((i + 1).to_string(), vol.to_string());
+            rows.push(cells(&[&rank, "华泰期货", &vol, "-10", "中信期货", "1,200", "5", "国泰君安", "900", "-3"]));
+        }
+        rows.push(cells(&["合计", "", "999", "", "", "", "", "", "", ""]));
+        rows
+    }
+
+    #[test]
+    fn czce_rank_sheet_filters_to_requested_varieties() {
+        let rows = [czce_contract("SR601", &[300, 200]), czce_contract("CF601", &[150]), czce_contract("sr605", &[80])]
+            .concat();
+        let range = czce_sheet(&rows);
+
+        let all = parse_czce_rank_sheet(&range, None);
+        let symbols: Vec<&str> = all.iter().map(|t| t.symbol.as_str()).collect();
+        assert_eq!(symbols, ["CF601", "SR601", "SR605"]);
+
+        let sugar = parse_czce_rank_sheet(&range, Some(&["sr"]));
+        let symbols: Vec<&str> = sugar.iter().map(|t| t.symbol.as_str()).collect();
+        assert_eq!(symbols, ["SR601", "SR605"]);
+        assert!(sugar.iter().flat_map(|t| &t.data).all(|d| d.variety == "SR"));
+        assert_eq!(sugar[0].data.len(), 2);
+        assert_eq!(sugar[0].data[0].vol, 300);
+        assert_eq!(sugar[0].data[0].long_open_interest, 1200);
+        assert_eq!(sugar[0].data[0].short_open_interest_chg, -3);
+
+        assert!(parse_czce_rank_sheet(&range, Some(&["MA"])).is_empty());
+    }
+
+    #[test]
+    fn cffex_csv_handles_quoted_fields_with_commas() {
+        let bytes = include_bytes!("../../../tests/fixtures/cffex_rank_IF.csv");
+        let text = encoding_rs::GBK.decode(bytes).0;
+        let tables = parse_cffex_rank_csv(&text);
+
+        let symbols: Vec<&str> = tables.iter().map(|t| t.symbol.as_str()).collect();
+        assert_eq!(symbols, ["IF2501", "IF2503"]);
+
+        let if2501 = &tables[0].data;
+        assert_eq!(if2501.len(), 2);
+        assert_eq!(if2501[0].vol, 12345);
+        assert_eq!(if2501[0].vol_chg, -210);
+        assert_eq!(if2501[0].long_open_interest, 8001);
+        assert_eq!(if2501[1].vol_party_name, "海通期货,上海");
+        assert_eq!(if2501[1].short_party_name, "国泰君安");
+        assert_eq!(if2501[1].short_open_interest_chg, 44);
+        assert_eq!(if2501[1].variety, "IF");
+
+        // 合计行名次为空，跳过
+        assert_eq!(tables[1].data.len(), 1);
+        assert_eq!(tables[1].data[0].long_open_interest, 2100);
+    }
+
+    #[test]
+    fn position_diff_joins_members_by_side() {
+        let d1 = vec![
+            row(1, ("中信期货", 1000), ("国泰君安", 900)),
+            row(2, ("永安期货", 800), ("中信期货", 700)),
+        ];
+        let d2 = vec![
+            row(1, ("中信期货", 1200), ("中信期货", 600)),
+            row(2, ("银河期货 ", 500), ("国泰君安", 950)),
+        ];
+        let diff = compute_position_diff(&d1, &d2);
+
+        let names: Vec<&str> = diff.members.iter().map(|m| m.party_name.as_str()).collect();
+        assert_eq!(names, ["中信期货", "银河期货", "国泰君安", "永安期货"]);
+
+        let citic = &diff.members[0];
+        assert_eq!((citic.long_d1, citic.long_d2, citic.long_delta), (Some(1000), Some(1200), 200));
+        assert_eq!((citic.short_d1, citic.short_d2, citic.short_delta), (Some(700), Some(600), -100));
+
+        let galaxy = &diff.members[1];
+        assert_eq!((galaxy.long_d1, galaxy.long_d2, galaxy.long_delta), (None, Some(500), 500));
+        assert_eq!((galaxy.short_d1, galaxy.short_d2), (None, None));
+
+        let yongan = &diff.members[3];
+        assert_eq!((yongan.long_d1, yongan.long_d2, yongan.long_delta), (Some(800), None, -800));
+
+        assert_eq!(diff.long_entered, ["银河期货"]);
+        assert_eq!(diff.long_left, ["永安期货"]);
+        assert!(diff.short_entered.is_empty());
+        assert!(diff.short_left.is_empty());
+    }
+
+    #[test]
+    fn rank_for_date_only_maps_missing_data_to_unavailable() {
+        let ok = rank_for_date(Ok(Some(table(vec![]))), "20250102", "rb2510");
+        assert!(ok.is_ok());
+
+        let missing = rank_for_date(Ok(None), "20250102", "rb2510").unwrap_err();
+        assert!(missing.is::<RankDateUnavailable>());
+
+        let not_found: anyhow::Error = DataNotFound {
+            date: "20250104".to_string(),
+            reason: "非交易日",
+        }
+        .into();
+        let err = rank_for_date(Err(not_found), "20250104", "rb2510").unwrap_err();
+        assert!(err.is::<RankDateUnavailable>());
+
+        let network = rank_for_date(Err(anyhow!("连接超时")), "20250102", "rb2510").unwrap_err();
+        assert!(!network.is::<RankDateUnavailable>());
+        assert_eq!(network.to_string(), "连接超时");
+    }
+
+    #[test]
+    fn sina_hold_pos_picks_table_by_type() {
+        let bytes = include_bytes!("../../../tests/fixtures/sina_hold_pos_OI2501.html");
+        let html = encoding_rs::GBK.decode(bytes).0;
+        let rows = |pos_type| -> Vec<(i32, String, i64, i64)> {
+            parse_sina_hold_pos_html(&html, pos_type)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.rank, r.company, r.value, r.change))
+                .collect()
+        };
+
+        assert_eq!(
+            rows(SinaHoldPosType::Volume),
+            [
+                (1, "国泰君安".to_string(), 45210, 3102),
+                (2, "中信期货".to_string(), 38907, -1450),
+                (3, "东证期货".to_string(), 21004, 0),
+            ]
+        );
+        assert_eq!(
+            rows(SinaHoldPosType::Long),
+            [(1, "中粮期货".to_string(), 60118, 2001), (2, "海通期货".to_string(), 30552, 0)]
+        );
+        assert_eq!(
+            rows(SinaHoldPosType::Short),
+            [(1, "中信期货".to_string(), 70890, -5230), (2, "国泰君安".to_string(), 40006, 812)]
+        );
+    }
+
+    #[test]
+    fn sina_hold_pos_without_tables_is_an_error() {
+        let html = "<html><body><table><tr><td>暂无数据</td></tr></table></body></html>";
+        assert!(parse_sina_hold_pos_html(html, SinaHoldPosType::Volume).is_err());
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("应有数值");
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn concentration_with_fewer_than_five_members() {
+        // 多头只有 3 家会员，CR5 为 1；空头 2 家各占一半
+        let data = vec![
+            row(1, ("中信期货", 600), ("国泰君安", 500)),
+            row(2, ("国泰君安", 300), ("永安期货", 500)),
+            row(3, ("永安期货", 100), ("", 0)),
+        ];
+        let c = compute_position_concentration(&data);
+        assert_eq!(c.symbol, "rb2510");
+        assert_close(c.long_cr5, 1.0);
+        assert_close(c.long_hhi, 0.6 * 0.6 + 0.3 * 0.3 + 0.1 * 0.1);
+        assert_close(c.short_cr5, 1.0);
+        assert_close(c.short_hhi, 0.5);
+    }
+
+    #[test]
+    fn concentration_cr5_counts_top_five_ranks_only() {
+        let data: Vec<PositionRankData> =
+            (1..=10).map(|rank| row(rank, ("会员", 100), ("会员", 100))).collect();
+        let c = compute_position_concentration(&data);
+        assert_close(c.long_cr5, 0.5);
+        assert_close(c.long_hhi, 0.1);
+    }
+
+    #[test]
+    fn concentration_is_none_for_zero_total() {
+        let data = vec![
+            row(1, ("中信期货", 0), ("国泰君安", 100)),
+            row(2, ("国泰君安", 0), ("", 0)),
+        ];
+        let c = compute_position_concentration(&data);
+        assert_eq!((c.long_cr5, c.long_hhi), (None, None));
+        assert_close(c.short_cr5, 1.0);
+        assert_close(c.short_hhi, 1.0);
+
+        let empty = compute_position_concentration(&[]);
+        assert_eq!((empty.long_cr5, empty.short_cr5, empty.long_hhi, empty.short_hhi), (None, None, None, None));
+        assert_eq!(empty.symbol, "");
+    }
+
+    #[test]
+    fn position_net_pairs_members_present_on_both_sides() {
+        let mut data = vec![
+            row(1, ("中信期货", 1000), ("国泰君安", 900)),
+            row(2, ("国泰君安", 800), ("中信期货", 300)),
+            // 只上多头榜的会员不出现在净持仓明细中，但计入前20多头合计
+            row(3, ("永安期货", 500), ("东证期货", 200)),
+        ];
+        data[0].long_open_interest_chg = 50;
+        data[1].short_open_interest_chg = -20;
+        data.push(PositionRankData { rank: 21, ..row(21, ("海通期货", 100), ("", 0)) });
+
+        let net = compute_position_net(&data);
+        assert_eq!(net.symbol, "rb2510");
+        assert_eq!(net.long_top20, 2300);
+        assert_eq!(net.short_top20, 1400);
+        assert_eq!(net.net_top20, 900);
+        assert_eq!(net.long_chg_top20, 50);
+        assert_eq!(net.short_chg_top20, -20);
+        assert_eq!(net.net_chg_top20, 70);
+
+        let names: Vec<&str> = net.members.iter().map(|m| m.party_name.as_str()).collect();
+        assert_eq!(names, ["中信期货", "国泰君安"]);
+        let citic = &net.members[0];
+        assert_eq!((citic.long_open_interest, citic.short_open_interest, citic.net), (1000, 300, 700));
+        assert_eq!(citic.net_chg, 70);
+        assert_eq!(net.members[1].net, -100);
+    }
+}
@@ -0,0 +1,298 @@
+//! 价格精度
+//!
+//! 浮点价格序列化后可能出现表示误差（如 75100.00000001）。?precision=fixed 时返回上游原始
+//! 价格字符串，并按交易规则中的最小变动价位补齐或去除多余的零，不经过浮点转换。
+//! K 线 ?with_meta=1 时同样由交易规则给出最小变动价位和合约乘数
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::calendar::variety_of;
+use super::coalesce::coalesce;
+use super::common::beijing_trading_day_yyyymmdd;
+use super::fees::get_futures_rule;
+use crate::models::{FuturesHistoryData, FuturesInfo, KlineMeta};
+
+/// 交易规则获取失败或为空时，在该时长内直接按无规则处理，不再请求上游
+const RULE_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// 品种的最小变动价位和合约乘数
+#[derive(Debug, Clone, Copy)]
+struct RuleParams {
+    price_tick: Option<f64>,
+    contract_size: Option<f64>,
+}
+
+/// 品种交易规则参数（品种代码大写 -> 参数），获取失败或为空时为 None
+type RuleTable = Option<Arc<HashMap<String, RuleParams>>>;
+
+/// 某个交易日的交易规则参数缓存
+struct CachedRules {
+    date: String,
+    rules: RuleTable,
+    fetched_at: Instant,
+}
+
+impl CachedRules {
+    /// 是否可直接用于该交易日：成功结果当日有效，失败结果在 RULE_RETRY_AFTER 内有效
+    fn is_fresh(&self, date: &str, now: Instant) -> bool {
+        self.date == date
+            && (self.rules.is_some() || now.duration_since(self.fetched_at) < RULE_RETRY_AFTER)
+    }
+}
+
+/// 按交易日缓存的品种交易规则参数
+static RULE_PARAMS: OnceLock<Mutex<Option<CachedRules>>> = OnceLock::new();
+
+fn rule_cache() -> std::sync::MutexGuard<'static, Option<CachedRules>> {
+    RULE_PARAMS
+        .get_or_init(|| Mutex::new(None))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 查询品种的交易规则参数，交易规则获取失败或无该品种时返回 None
+///
+/// 按最近交易日获取交易规则，每个交易日只获取一次；失败或为空时短时缓存，避免每个请求都访问上游。
+/// 获取期间不持有缓存锁，并发请求通过 coalesce 合并为一次上游请求
+async fn rule_params(symbol: &str) -> Option<RuleParams> {
+    let date = beijing_trading_day_yyyymmdd();
+    let cached = rule_cache()
+        .as_ref()
+        .filter(|cached| cached.is_fresh(&date, Instant::now()))
+        .map(|cached| cached.rules.clone());
+
+    let rules = match cached {
+        Some(rules) => rules,
+        None => {
+            let rules = fetch_rule_table(&date).await;
+            *rule_cache() = Some(CachedRules {
+                date,
+                rules: rules.clone(),
+                fetched_at: Instant::now(),
+            });
+            rules
+        }
+    };
+    rules?.get(&variety_of(symbol)).copied()
+}
+
+/// 获取某交易日的交易规则并按品种建表
+async fn fetch_rule_table(date: &str) -> RuleTable {
+    let key = format!("futures_rule:{}", date);
+    match coalesce(&key, || get_futures_rule(Some(date))).await {
+        Ok(rules) if !rules.is_empty() => Some(Arc::new(
+            rules
+                .iter()
+                .map(|rule| {
+                    let params = RuleParams {
+                        price_tick: rule.price_tick,
+                        contract_size: rule.contract_size,
+                    };
+                    (rule.code.to_uppercase(), params)
+                })
+                .collect(),
+        )),
+        Ok(_) => {
+            log::debug!("{} 无交易规则数据，价格字符串保持上游格式", date);
+            None
+        }
+        Err(e) => {
+            log::warn!("获取交易规则失败，价格字符串保持上游格式: {}", e);
+            None
+        }
+    }
+}
+
+/// 查询品种价格的小数位数，交易规则获取失败或无该品种时返回 None
+async fn tick_decimals(symbol: &str) -> Option<usize> {
+    rule_params(symbol).await?.price_tick.map(decimals_of)
+}
+
+/// 品种报价单位，未列出且在交易规则中存在的品种为 元/吨
+///
+/// 交易规则表不含报价单位，按各交易所合约规格维护
+const PRICE_UNITS: &[(&str, &str)] = &[
+    ("AU", "元/克"),
+    ("AG", "元/千克"),
+    ("PT", "元/克"),
+    ("PD", "元/克"),
+    ("SC", "元/桶"),
+    ("JD", "元/500千克"),
+    ("BB", "元/张"),
+    ("FB", "元/立方米"),
+    ("LG", "元/立方米"),
+    ("IF", "指数点"),
+    ("IH", "指数点"),
+    ("IC", "指数点"),
+    ("IM", "指数点"),
+    ("EC", "指数点"),
+    ("T", "百元净价"),
+    ("TF", "百元净价"),
+    ("TS", "百元净价"),
+    ("TL", "百元净价"),
+];
+
+/// 查询合约所属品种的报价单位；未列出的品种仅在交易规则中存在（known）时按 元/吨，否则返回 None
+fn price_unit(symbol: &str, known: bool) -> Option<&'static str> {
+    let variety = variety_of(symbol);
+    PRICE_UNITS
+        .iter()
+        .find(|(code, _)| *code == variety)
+        .map(|(_, unit)| *unit)
+        .or(known.then_some("元/吨"))
+}
+
+/// K 线品种元数据：报价单位、最小变动价位和合约乘数，每个响应只查询一次
+///
+/// 交易规则获取失败或无该品种时，最小变动价位和合约乘数为 None
+pub async fn kline_meta(symbol: &str) -> KlineMeta {
+    let params = rule_params(symbol).await;
+    KlineMeta {
+        price_unit: price_unit(symbol, params.is_some()).map(str::to_string),
+        tick_size: params.and_then(|p| p.price_tick),
+        contract_multiplier: params.and_then(|p| p.contract_size),
+    }
+}
+
+/// 最小变动价位对应的小数位数，如 1 -> 0、0.2 -> 1、0.02 -> 2
+fn decimals_of(tick: f64) -> usize {
+    let text = tick.to_string();
+    text.split_once('.').map(|(_, frac)| frac.len()).unwrap_or(0)
+}
+
+/// 将价格字符串对齐到指定小数位数，只补零或去除末尾的零，不做舍入
+///
+/// 需要去除非零数字时（上游精度高于最小变动价位）保留原始字符串
+fn fix_decimals(token: &str, places: usize) -> String {
+    let (int_part, frac) = token.split_once('.').unwrap_or((token, ""));
+    if frac.len() > places && frac[places..].chars().any(|c| c != '0') {
+        return token.to_string();
+    }
+
+    let mut frac: String = frac.chars().take(places).collect();
+    while frac.len() < places {
+        frac.push('0');
+    }
+    if frac.is_empty() {
+        int_part.to_string()
+    } else {
+        format!("{}.{}", int_part, frac)
+    }
+}
+
+fn fix_field(field: &mut Option<String>, places: Option<usize>) {
+    if let (Some(token), Some(places)) = (field.as_mut(), places) {
+        *token = fix_decimals(token, places);
+    }
+}
+
+/// 按 precision 参数处理实时行情的字符串价格：raw 时去除，fixed 时按最小变动价位对齐
+pub async fn apply_info_precision(info: &mut FuturesInfo, fixed: bool) {
+    if !fixed {
+        info.clear_price_strings();
+        return;
+    }
+
+    let places = tick_decimals(&info.symbol).await;
+    fix_info_prices(info, places);
+}
+
+/// 将实时行情的字符串价格对齐到指定小数位数，places 为 None 时保持上游原始格式
+fn fix_info_prices(info: &mut FuturesInfo, places: Option<usize>) {
+    for field in [
+        &mut info.current_price_str,
+        &mut info.open_str,
+        &mut info.high_str,
+        &mut info.low_str,
+        &mut info.settlement_str,
+        &mut info.prev_settlement_str,
+    ] {
+        fix_field(field, places);
+    }
+}
+
+/// 按 precision 参数处理K线数据的字符串价格：raw 时去除，fixed 时按最小变动价位对齐
+pub async fn apply_history_precision(history: &mut [FuturesHistoryData], fixed: bool) {
+    if !fixed {
+        history.iter_mut().for_each(FuturesHistoryData::clear_price_strings);
+        return;
+    }
+
+    let Some(first) = history.first() else {
+        return;
+    };
+    let places = tick_decimals(&first.symbol).await;
+    for bar in history.iter_mut() {
+        for field in [
+            &mut bar.open_str,
+            &mut bar.high_str,
+            &mut bar.low_str,
+            &mut bar.close_str,
+            &mut bar.settlement_str,
+        ] {
+            fix_field(field, places);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::futures::FuturesService;
+
+    const SINA_LINE: &str = "var hq_str_nf_RB2601=\"螺纹钢2601,145959,3088.000,3115.000,3080.000,3090.000,3101.000,3102.000,3102.000,0.000,3089.000,1150,1230,12345,6789,沪,螺纹钢,2025-12-05,1\";";
+
+    #[test]
+    fn fixed_price_string_matches_upstream_token() {
+        let mut info = FuturesService::parse_sina_realtime_data(SINA_LINE, "RB2601").unwrap();
+        fix_info_prices(&mut info, None);
+        assert_eq!(info.current_price_str.as_deref(), Some("3102.000"));
+        assert_eq!(info.open_str.as_deref(), Some("3088.000"));
+
+        fix_info_prices(&mut info, Some(3));
+        assert_eq!(info.current_price_str.as_deref(), Some("3102.000"));
+
+        fix_info_prices(&mut info, Some(0));
+        assert_eq!(info.current_price_str.as_deref(), Some("3102"));
+        assert_eq!(info.high_str.as_deref(), Some("3115"));
+    }
+
+    #[test]
+    fn fix_decimals_never_rounds() {
+        assert_eq!(fix_decimals("75100.00000001", 0), "75100.00000001");
+        assert_eq!(fix_decimals("3.5", 2), "3.50");
+        assert_eq!(fix_decimals("3102", 1), "3102.0");
+        assert_eq!(decimals_of(0.2), 1);
+        assert_eq!(decimals_of(5.0), 0);
+    }
+
+    #[test]
+    fn price_unit_from_table_or_default_for_known_varieties() {
+        assert_eq!(price_unit("au2602", true), Some("元/克"));
+        assert_eq!(price_unit("IF2606", false), Some("指数点"));
+        assert_eq!(price_unit("CU2601", true), Some("元/吨"));
+        assert_eq!(price_unit("XX2601", false), None);
+    }
+
+    #[test]
+    fn failed_rule_fetch_is_cached_briefly() {
+        let now = Instant::now();
+        let failed = CachedRules {
+            date: "20250102".to_string(),
+            rules: None,
+            fetched_at: now,
+        };
+        assert!(failed.is_fresh("20250102", now + Duration::from_secs(30)));
+        assert!(!failed.is_fresh("20250102", now + RULE_RETRY_AFTER));
+        assert!(!failed.is_fresh("20250103", now));
+
+        let loaded = CachedRules {
+            rules: Some(Arc::new(HashMap::new())),
+            ..failed
+        };
+        assert!(loaded.is_fresh("20250102", now + Duration::from_secs(86_400)));
+        assert!(!loaded.is_fresh("20250103", now));
+    }
+}
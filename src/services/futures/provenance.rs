@@ -0,0 +1,147 @@
+//! 数据来源追踪
+//!
+//! 每个 HTTP 请求在独立的 task-local 作用域中处理（见 ProvenanceMiddleware）。
+//! send_limited 发出上游请求时记录来源，构造成功响应时附加到 ApiResponse.provenance。
+//! 一次请求访问多个上游时保留最近一次
+
+use std::cell::RefCell;
+use std::future::Future;
+
+use super::common::get_beijing_time;
+use crate::models::DataProvenance;
+
+tokio::task_local! {
+    static PROVENANCE: RefCell<Option<DataProvenance>>;
+}
+
+/// 在新的来源追踪作用域中执行
+pub async fn with_provenance_scope<F: Future>(fut: F) -> F::Output {
+    PROVENANCE.scope(RefCell::new(None), fut).await
+}
+
+/// 获取当前作用域记录的数据来源，不在作用域内或未访问上游时返回 None
+pub fn current_provenance() -> Option<DataProvenance> {
+    PROVENANCE.try_with(|p| p.borrow().clone()).ok().flatten()
+}
+
+/// 恢复其他请求记录的数据来源（合并请求时由等待者复制发起者的来源）
+pub(crate) fn restore_provenance(provenance: Option<DataProvenance>) {
+    if let Some(provenance) = provenance {
+        let _ = PROVENANCE.try_with(|p| *p.borrow_mut() = Some(provenance));
+    }
+}
+
+/// 记录一次上游请求
+pub(crate) fn record_upstream(url: &reqwest::Url) {
+    record(source_for_host(url.host_str().unwrap_or_default()), url.to_string());
+}
+
+/// 记录一次本地文件缓存命中
+pub(crate) fn record_cache(path: &str) {
+    record("cache", path.to_string());
+}
+
+/// 记录一次模拟模式下的 fixture 读取
+pub(crate) fn record_mock(path: &str) {
+    record("mock", path.to_string());
+}
+
+fn record(source: &'static str, url: String) {
+    let provenance = DataProvenance {
+        source,
+        fetched_at: get_beijing_time(),
+        url,
+    };
+    let _ = PROVENANCE.try_with(|p| *p.borrow_mut() = Some(provenance));
+}
+
+/// 根据上游域名确定来源标签
+fn source_for_host(host: &str) -> &'static str {
+    const SOURCES: [(&str, &str); 14] = [
+        ("sina.com.cn", "sina"),
+        ("sinajs.cn", "sina"),
+        ("sina.cn", "sina"),
+        ("100ppi.com", "100ppi"),
+        ("99qh.com", "99qihuo"),
+        ("9qihuo.com", "9qihuo"),
+        ("openctp.cn", "openctp"),
+        ("gtjaqh.com", "gtja"),
+        ("shfe.com.cn", "shfe"),
+        ("dce.com.cn", "dce"),
+        ("czce.com.cn", "czce"),
+        ("cffex.com.cn", "cffex"),
+        ("gfex.com.cn", "gfex"),
+        ("eastmoney.com", "eastmoney"),
+    ];
+
+    SOURCES
+        .iter()
+        .find(|(domain, _)| host == *domain || host.ends_with(&format!(".{}", domain)))
+        .map(|(_, source)| *source)
+        .unwrap_or("unknown")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::futures::common::{
+        EASTMONEY_FUTURES_REALTIME_API, GTJA_CALENDAR_URL, OPENCTP_FEES_URL, QH99_STOCK_URL, QIHUO_COMM_URL,
+        SHFE_VOL_RANK_URL, SINA_FUTURES_REALTIME_API, SINA_HOLD_POS_API, SPOT_PRICE_URL,
+    };
+
+    fn source_of(url: &str) -> &'static str {
+        source_for_host(reqwest::Url::parse(url).unwrap().host_str().unwrap())
+    }
+
+    #[test]
+    fn upstream_urls_map_to_source_labels() {
+        let cases = [
+            (SPOT_PRICE_URL, "100ppi"),
+            (SINA_FUTURES_REALTIME_API, "sina"),
+            (SINA_HOLD_POS_API, "sina"),
+            (QH99_STOCK_URL, "99qihuo"),
+            (QIHUO_COMM_URL, "9qihuo"),
+            (OPENCTP_FEES_URL, "openctp"),
+            (GTJA_CALENDAR_URL, "gtja"),
+            (SHFE_VOL_RANK_URL, "shfe"),
+            (EASTMONEY_FUTURES_REALTIME_API, "eastmoney"),
+        ];
+        for (url, source) in cases {
+            assert_eq!(source_of(url), source, "{}", url);
+        }
+        // 只按完整域名或子域名匹配
+        assert_eq!(source_for_host("notsina.com.cn"), "unknown");
+        assert_eq!(source_for_host("example.com"), "unknown");
+    }
+
+    #[tokio::test]
+    async fn provenance_is_recorded_per_scope() {
+        let spot = reqwest::Url::parse(&format!("{}/20240105.html", SPOT_PRICE_URL)).unwrap();
+        let recorded = with_provenance_scope(async {
+            assert!(current_provenance().is_none());
+            record_upstream(&spot);
+            current_provenance()
+        })
+        .await
+        .unwrap();
+        assert_eq!(recorded.source, "100ppi");
+        assert_eq!(recorded.url, spot.as_str());
+
+        // 作用域外不记录，新的作用域从空开始
+        record_upstream(&spot);
+        assert!(current_provenance().is_none());
+        assert!(with_provenance_scope(async { current_provenance() }).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn latest_record_wins_within_a_scope() {
+        let provenance = with_provenance_scope(async {
+            record_upstream(&reqwest::Url::parse(SINA_FUTURES_REALTIME_API).unwrap());
+            record_cache("/tmp/cache/spot.json");
+            current_provenance()
+        })
+        .await
+        .unwrap();
+        assert_eq!((provenance.source, provenance.url.as_str()), ("cache", "/tmp/cache/spot.json"));
+    }
+}
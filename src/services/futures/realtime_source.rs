@@ -0,0 +1,253 @@
+//! 实时行情数据源及兜底顺序
+//!
+//! 新浪是国内期货实时行情的主数据源，但频繁请求容易被封禁（封禁冷却期内直接返回 429）。
+//! RealtimeSource 为每个数据源提供按合约批量获取行情的方法，fetch_with_fallback 按
+//! api.realtime_sources 配置的顺序依次尝试：前一个数据源请求失败，或部分合约无数据、解析失败时，
+//! 只对这些合约请求下一个数据源。返回的行情在 FuturesInfo.source 中标记实际来源
+
+use anyhow::{anyhow, Result};
+use std::future::Future;
+
+use crate::config;
+use crate::models::{FuturesBatchItem, FuturesBatchStatus};
+
+/// 实时行情数据源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RealtimeSourceKind {
+    /// 新浪（hq.sinajs.cn）
+    Sina,
+    /// 东方财富（push2.eastmoney.com）
+    Eastmoney,
+}
+
+impl RealtimeSourceKind {
+    /// 配置和 FuturesInfo.source 中使用的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sina => "sina",
+            Self::Eastmoney => "eastmoney",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "sina" => Some(Self::Sina),
+            "eastmoney" => Some(Self::Eastmoney),
+            _ => None,
+        }
+    }
+}
+
+/// 按 api.realtime_sources 解析数据源顺序
+///
+/// 忽略未知名称和重复项；配置为空或全部无效时只使用新浪
+pub fn configured_realtime_sources() -> Vec<RealtimeSourceKind> {
+    let mut order = Vec::new();
+    for name in &config::read(|c| c.api.realtime_sources.clone()) {
+        match RealtimeSourceKind::from_name(name) {
+            Some(kind) if !order.contains(&kind) => order.push(kind),
+            Some(_) => {}
+            None => log::warn!("未知的实时行情数据源 {}，已忽略", name),
+        }
+    }
+    if order.is_empty() {
+        order.push(RealtimeSourceKind::Sina);
+    }
+    order
+}
+
+/// 实时行情获取接口
+///
+/// 每个数据源一个方法，每个请求的合约返回一项及其状态。生产实现为 FuturesService
+pub trait RealtimeSource {
+    /// 新浪实时行情
+    fn sina(&self, symbols: &[String]) -> impl Future<Output = Result<Vec<FuturesBatchItem>>>;
+    /// 东方财富实时行情
+    fn eastmoney(&self, symbols: &[String]) -> impl Future<Output = Result<Vec<FuturesBatchItem>>>;
+}
+
+async fn fetch_from<S: RealtimeSource>(
+    source: &S,
+    kind: RealtimeSourceKind,
+    symbols: &[String],
+) -> Result<Vec<FuturesBatchItem>> {
+    match kind {
+        RealtimeSourceKind::Sina => source.sina(symbols).await,
+        RealtimeSourceKind::Eastmoney => source.eastmoney(symbols).await,
+    }
+}
+
+/// 按 order 顺序获取实时行情，结果保持 symbols 的顺序（symbols 应已去重）
+///
+/// 只有行情正常的合约会被后续数据源的结果替换；所有数据源都请求失败时返回第一个错误，
+/// 以保留新浪封禁（429）等原始错误
+pub async fn fetch_with_fallback<S: RealtimeSource>(
+    source: &S,
+    order: &[RealtimeSourceKind],
+    symbols: &[String],
+) -> Result<Vec<FuturesBatchItem>> {
+    let mut items: Option<Vec<FuturesBatchItem>> = None;
+    let mut first_error = None;
+
+    for &kind in order {
+        let pending: Vec<String> = match &items {
+            None => symbols.to_vec(),
+            Some(items) => items
+                .iter()
+                .filter(|item| item.status != FuturesBatchStatus::Ok)
+                .map(|item| item.symbol.clone())
+                .collect(),
+        };
+        if pending.is_empty() {
+            break;
+        }
+        if items.is_some() || first_error.is_some() {
+            log::info!("{} 个合约改用 {} 实时行情", pending.len(), kind.name());
+        }
+
+        let fetched = match fetch_from(source, kind, &pending).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                log::warn!("实时行情数据源 {} 请求失败: {}", kind.name(), e);
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+        let tagged = fetched.into_iter().map(|mut item| {
+            if let Some(data) = item.data.as_mut() {
+                data.source = Some(kind.name().to_string());
+            }
+            item
+        });
+
+        match items.as_mut() {
+            None => items = Some(tagged.collect()),
+            Some(items) => {
+                for item in tagged.filter(|item| item.status == FuturesBatchStatus::Ok) {
+                    if let Some(slot) = items.iter_mut().find(|slot| slot.symbol == item.symbol) {
+                        *slot = item;
+                    }
+                }
+            }
+        }
+    }
+
+    items.ok_or_else(|| first_error.unwrap_or_else(|| anyhow!("未配置实时行情数据源")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FuturesInfo;
+    use crate::services::futures::RateLimited;
+    use std::cell::RefCell;
+
+    fn sample_info(symbol: &str) -> FuturesInfo {
+        let infos: Vec<FuturesInfo> =
+            serde_json::from_str(include_str!("../../../fixtures/futures_realtime.json")).unwrap();
+        infos.into_iter().find(|info| info.symbol == symbol).unwrap()
+    }
+
+    fn ok(symbol: &str) -> FuturesBatchItem {
+        FuturesBatchItem { symbol: symbol.to_string(), status: FuturesBatchStatus::Ok, data: Some(sample_info(symbol)) }
+    }
+
+    fn no_data(symbol: &str) -> FuturesBatchItem {
+        FuturesBatchItem { symbol: symbol.to_string(), status: FuturesBatchStatus::NoData, data: None }
+    }
+
+    /// 新浪按 sina_ok 返回行情（None 表示封禁），东方财富对所有合约返回行情，并记录每次请求
+    struct MockSource {
+        sina_ok: Option<Vec<&'static str>>,
+        eastmoney_fails: bool,
+        calls: RefCell<Vec<(RealtimeSourceKind, Vec<String>)>>,
+    }
+
+    impl MockSource {
+        fn new(sina_ok: Option<Vec<&'static str>>, eastmoney_fails: bool) -> Self {
+            Self { sina_ok, eastmoney_fails, calls: RefCell::new(Vec::new()) }
+        }
+    }
+
+    impl RealtimeSource for MockSource {
+        async fn sina(&self, symbols: &[String]) -> Result<Vec<FuturesBatchItem>> {
+            self.calls.borrow_mut().push((RealtimeSourceKind::Sina, symbols.to_vec()));
+            let Some(sina_ok) = &self.sina_ok else {
+                return Err(RateLimited { retry_after_secs: 60 }.into());
+            };
+            Ok(symbols
+                .iter()
+                .map(|s| if sina_ok.contains(&s.as_str()) { ok(s) } else { no_data(s) })
+                .collect())
+        }
+
+        async fn eastmoney(&self, symbols: &[String]) -> Result<Vec<FuturesBatchItem>> {
+            self.calls.borrow_mut().push((RealtimeSourceKind::Eastmoney, symbols.to_vec()));
+            if self.eastmoney_fails {
+                return Err(anyhow!("东方财富请求失败"));
+            }
+            Ok(symbols.iter().map(|s| ok(s)).collect())
+        }
+    }
+
+    const ORDER: [RealtimeSourceKind; 2] = [RealtimeSourceKind::Sina, RealtimeSourceKind::Eastmoney];
+
+    fn symbols(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn sina_ban_falls_back_to_eastmoney() {
+        let source = MockSource::new(None, false);
+        let items = fetch_with_fallback(&source, &ORDER, &symbols(&["RB2601", "CU2601"])).await.unwrap();
+
+        assert_eq!(items.len(), 2);
+        for (item, symbol) in items.iter().zip(["RB2601", "CU2601"]) {
+            assert_eq!(item.symbol, symbol);
+            assert_eq!(item.status, FuturesBatchStatus::Ok);
+            let data = item.data.as_ref().unwrap();
+            assert_eq!(data.symbol, symbol);
+            assert!(data.current_price.is_some());
+            assert_eq!(data.source.as_deref(), Some("eastmoney"));
+        }
+        assert_eq!(source.calls.borrow().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn only_missing_symbols_go_to_the_next_source() {
+        let source = MockSource::new(Some(vec!["RB2601"]), false);
+        let items = fetch_with_fallback(&source, &ORDER, &symbols(&["RB2601", "CU2601", "AU2602"]))
+            .await
+            .unwrap();
+
+        let sources: Vec<_> = items.iter().map(|item| item.data.as_ref().unwrap().source.as_deref()).collect();
+        assert_eq!(sources, [Some("sina"), Some("eastmoney"), Some("eastmoney")]);
+        assert_eq!(items[1].symbol, "CU2601");
+        assert_eq!(
+            source.calls.borrow()[1],
+            (RealtimeSourceKind::Eastmoney, symbols(&["CU2601", "AU2602"]))
+        );
+    }
+
+    #[tokio::test]
+    async fn secondary_is_not_called_when_primary_covers_everything() {
+        let source = MockSource::new(Some(vec!["RB2601"]), false);
+        let items = fetch_with_fallback(&source, &ORDER, &symbols(&["RB2601"])).await.unwrap();
+        assert_eq!(items[0].data.as_ref().unwrap().source.as_deref(), Some("sina"));
+        assert_eq!(source.calls.borrow().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn all_sources_failing_keeps_the_sina_ban_error() {
+        let source = MockSource::new(None, true);
+        let err = fetch_with_fallback(&source, &ORDER, &symbols(&["RB2601"])).await.unwrap_err();
+        assert!(err.is::<RateLimited>(), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn secondary_failure_keeps_primary_no_data_items() {
+        let source = MockSource::new(Some(vec![]), true);
+        let items = fetch_with_fallback(&source, &ORDER, &symbols(&["RB2601"])).await.unwrap();
+        assert_eq!(items[0].status, FuturesBatchStatus::NoData);
+    }
+}
@@ -3,16 +3,29 @@
 //! 封装期货数据的获取逻辑，参考 akshare/futures/futures_zh_sina.py 实现
 
 use crate::models::{
-    FuturesContractDetail, FuturesExchange, FuturesInfo, FuturesQuery, FuturesSymbolMark,
+    FuturesBatchItem, FuturesBatchStatus, FuturesContractDetail, FuturesExchange, FuturesInfo,
+    FuturesQuery, FuturesSymbolEntry, FuturesSymbolMark, RealtimeRequestDebug,
 };
 use anyhow::{anyhow, Result};
 use regex::Regex;
 use reqwest::Client;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
+use super::calendar::trading_date;
+use super::expiry::ensure_not_expired;
 use super::common::{
-    get_beijing_time, SINA_CONTRACT_DETAIL_URL, SINA_FUTURES_LIST_API, SINA_FUTURES_REALTIME_API,
+    beijing_now, extract_variety, get_beijing_time, http_client, parse_context, parse_json_lenient, parse_lenient_f64, parse_lenient_u64,
+    parse_quote_time, price_change, price_token, CZCE_VARIETIES, RequestTimeout, SINA_CONTRACT_DETAIL_URL, SINA_FUTURES_LIST_API, SINA_FUTURES_REALTIME_API,
     SINA_FUTURES_SYMBOL_URL,
 };
+use super::eastmoney::get_eastmoney_realtime;
+use super::limiter::LimitedSend;
+use super::mock::fixture;
+use super::realtime_source::{configured_realtime_sources, fetch_with_fallback, RealtimeSource};
+use crate::config::{self, DefaultsConfig};
 
 /// 期货数据服务
 ///
@@ -26,37 +39,182 @@ use super::common::{
 pub struct FuturesService {
     /// HTTP 客户端
     client: Client,
-    /// 品种映射缓存
-    symbol_mark_cache: Option<Vec<FuturesSymbolMark>>,
+}
+
+/// 品种映射缓存有效期
+pub(crate) const SYMBOL_MARK_TTL: Duration = Duration::from_secs(3600);
+
+/// 品种映射表及其获取时间
+type CachedSymbolMarks = (Instant, Vec<FuturesSymbolMark>);
+
+/// 新浪期货列表返回空数据时，重试前的等待时间
+const EMPTY_LIST_RETRY_DELAY: Duration = Duration::from_millis(300);
+
+/// 进程内共享的品种映射缓存，各服务实例共用
+static SYMBOL_MARK_CACHE: OnceLock<RwLock<Option<CachedSymbolMarks>>> = OnceLock::new();
+
+fn symbol_mark_cache() -> &'static RwLock<Option<CachedSymbolMarks>> {
+    SYMBOL_MARK_CACHE.get_or_init(|| RwLock::new(None))
+}
+
+/// 读取品种映射缓存：未过期时直接返回；否则调用 refresh 刷新，刷新失败时返回过期的缓存，
+/// 没有缓存时才返回错误
+async fn cached_symbol_marks(
+    cache: &RwLock<Option<CachedSymbolMarks>>,
+    ttl: Duration,
+    refresh: impl Future<Output = Result<Vec<FuturesSymbolMark>>>,
+) -> Result<Vec<FuturesSymbolMark>> {
+    let stale = match cache.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        Some((cached_at, symbols)) if cached_at.elapsed() < ttl => {
+            return Ok(symbols.clone());
+        }
+        Some((_, symbols)) => Some(symbols.clone()),
+        None => None,
+    };
+
+    match store_symbol_marks(cache, refresh.await) {
+        Ok(symbols) => Ok(symbols),
+        Err(e) => match stale {
+            Some(symbols) => {
+                log::warn!("刷新品种映射失败，继续使用过期缓存: {}", e);
+                Ok(symbols)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// 获取成功时写入缓存并重置获取时间，失败时保留原缓存
+fn store_symbol_marks(
+    cache: &RwLock<Option<CachedSymbolMarks>>,
+    result: Result<Vec<FuturesSymbolMark>>,
+) -> Result<Vec<FuturesSymbolMark>> {
+    let symbols = result?;
+    *cache.write().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), symbols.clone()));
+    Ok(symbols)
+}
+
+/// 首次结果为空时等待 delay 后重试一次，请求出错时不重试
+async fn retry_once_on_empty<T, F, Fut>(node: &str, delay: Duration, fetch: F) -> Result<Vec<T>>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    let list = fetch().await?;
+    if !list.is_empty() {
+        return Ok(list);
+    }
+
+    tokio::time::sleep(delay).await;
+    let retried = fetch().await?;
+    if !retried.is_empty() {
+        log::info!("新浪期货列表 {} 首次返回空数据，重试后获取到 {} 条", node, retried.len());
+    }
+    Ok(retried)
+}
+
+/// 新浪实时行情的空数据标记：hq_str_xxx=""; 或 hq_str_xxx="FAILURE";
+fn is_empty_quote(item: &str) -> bool {
+    item.split_once('=').is_some_and(|(_, value)| {
+        let value = value.trim().trim_matches('"').trim();
+        value.is_empty() || value.eq_ignore_ascii_case("FAILURE")
+    })
+}
+
+/// 校验国内期货合约代码格式
+///
+/// 合约代码为 1-4 个品种字母（单字母品种如 T、M、A）加 4 位月份，郑商所品种也可为 3 位（如 MA505），
+/// 或品种字母加 0 的主力连续合约（如 CU0）；可带新浪行情前缀 nf_ / CFF_。
+/// 只拦截明显错误的输入（如 CU240），不校验品种和月份是否存在
+pub fn validate_contract_symbol(symbol: &str) -> Result<()> {
+    let upper = symbol.trim().to_uppercase();
+    let code = upper
+        .strip_prefix("NF_")
+        .or_else(|| upper.strip_prefix("CFF_"))
+        .unwrap_or(&upper);
+
+    let letters = code.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+    let (variety, digits) = code.split_at(letters);
+    let month_len_ok = match digits.len() {
+        4 => true,
+        3 => CZCE_VARIETIES.contains(&variety),
+        _ => digits == "0",
+    };
+    let valid = (1..=4).contains(&letters)
+        && !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && month_len_ok;
+
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "合约代码格式无效: {}（应为品种字母加 4 位月份如 CU2405，郑商所可为 3 位如 MA505，或主力连续合约如 CU0）",
+            symbol
+        ))
+    }
+}
+
+/// 交易所代码对应的品种映射表中的交易所名称（能源中心品种归在上期所）
+fn symbol_mark_exchange_name(code: &str) -> Option<&'static str> {
+    match code.to_uppercase().as_str() {
+        "CZCE" => Some("郑州商品交易所"),
+        "DCE" => Some("大连商品交易所"),
+        "SHFE" | "INE" => Some("上海期货交易所"),
+        "CFFEX" => Some("中国金融期货交易所"),
+        "GFEX" => Some("广州期货交易所"),
+        _ => None,
+    }
+}
+
+/// 品种映射表中的交易所名称对应的交易所代码
+fn symbol_mark_exchange_code(name: &str) -> &str {
+    match name {
+        "郑州商品交易所" => "CZCE",
+        "大连商品交易所" => "DCE",
+        "上海期货交易所" => "SHFE",
+        "中国金融期货交易所" => "CFFEX",
+        "广州期货交易所" => "GFEX",
+        other => other,
+    }
 }
 
 impl FuturesService {
     /// 创建新的期货服务实例
     pub fn new() -> Self {
         Self {
-            client: Client::new(),
-            symbol_mark_cache: None,
+            client: http_client(),
         }
     }
 
     // ==================== 品种映射相关 ====================
 
     /// 获取期货品种和代码映射表
+    ///
+    /// 结果在进程内共享缓存 SYMBOL_MARK_TTL，过期后由下一次请求刷新（开启后台刷新时通常不会过期）。
+    /// 刷新失败时继续返回过期的缓存并记录警告，没有缓存时才返回错误
     pub async fn get_symbol_mark(&mut self) -> Result<Vec<FuturesSymbolMark>> {
-        if let Some(ref cache) = self.symbol_mark_cache {
-            return Ok(cache.clone());
+        if let Some(symbols) = fixture("futures_symbol_marks") {
+            return symbols;
         }
 
-        println!("📡 请求品种映射数据 URL: {}", SINA_FUTURES_SYMBOL_URL);
+        cached_symbol_marks(symbol_mark_cache(), SYMBOL_MARK_TTL, self.fetch_symbol_mark()).await
+    }
+
+    /// 从新浪获取品种映射表并写入共享缓存，不检查缓存是否过期；失败时保留原缓存
+    pub async fn refresh_symbol_mark(&self) -> Result<Vec<FuturesSymbolMark>> {
+        store_symbol_marks(symbol_mark_cache(), self.fetch_symbol_mark().await)
+    }
+
+    /// 从新浪下载并解析品种映射表
+    async fn fetch_symbol_mark(&self) -> Result<Vec<FuturesSymbolMark>> {
+        log::debug!("请求品种映射数据 URL: {}", SINA_FUTURES_SYMBOL_URL);
 
         let response = self
             .client
             .get(SINA_FUTURES_SYMBOL_URL)
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-            )
-            .send()
+            .timeout(RequestTimeout::Realtime.duration())
+            .send_limited()
             .await?;
 
         if !response.status().is_success() {
@@ -66,14 +224,39 @@ impl FuturesService {
         let bytes = response.bytes().await?;
         let text = encoding_rs::GBK.decode(&bytes).0.to_string();
 
-        let symbols = self.parse_symbol_mark_js(&text)?;
-        self.symbol_mark_cache = Some(symbols.clone());
+        Self::parse_symbol_mark_js(&text)
+    }
 
-        Ok(symbols)
+    /// 获取按交易所代码分组的品种映射表，exchange 为交易所代码时只返回该交易所
+    pub async fn get_symbol_marks_grouped(
+        &mut self,
+        exchange: Option<&str>,
+    ) -> Result<BTreeMap<String, Vec<FuturesSymbolEntry>>> {
+        let exchange_name = match exchange.map(str::trim).filter(|e| !e.is_empty()) {
+            Some(code) => Some(
+                symbol_mark_exchange_name(code).ok_or_else(|| anyhow!("未知交易所: {}", code))?,
+            ),
+            None => None,
+        };
+
+        let mut grouped: BTreeMap<String, Vec<FuturesSymbolEntry>> = BTreeMap::new();
+        for mark in self.get_symbol_mark().await? {
+            if exchange_name.is_some_and(|name| mark.exchange != name) {
+                continue;
+            }
+            grouped
+                .entry(symbol_mark_exchange_code(&mark.exchange).to_string())
+                .or_default()
+                .push(FuturesSymbolEntry {
+                    symbol: mark.symbol,
+                    mark: mark.mark,
+                });
+        }
+        Ok(grouped)
     }
 
     /// 解析新浪 JS 文件中的品种映射数据
-    fn parse_symbol_mark_js(&self, js_text: &str) -> Result<Vec<FuturesSymbolMark>> {
+    pub(crate) fn parse_symbol_mark_js(js_text: &str) -> Result<Vec<FuturesSymbolMark>> {
         let mut symbols = Vec::new();
 
         let start = js_text.find("ARRFUTURESNODES = {");
@@ -94,14 +277,21 @@ impl FuturesService {
         ];
 
         let item_re = Regex::new(r"\['([^']+)',\s*'([^']+)',\s*'[^']*'").unwrap();
+        // 每个交易所的品种数组从 "<代码>: [" 开始，到下一个交易所键之前结束
+        let key_re = Regex::new(r"\b([a-z]+)\s*:\s*\[").unwrap();
+        let keys: Vec<(&str, usize, usize)> = key_re
+            .captures_iter(content)
+            .filter_map(|cap| {
+                let all = cap.get(0)?;
+                Some((cap.get(1)?.as_str(), all.start(), all.end()))
+            })
+            .collect();
 
         for (exchange_code, exchange_name) in exchanges {
-            let pattern = format!(r"{}\s*:\s*\[", exchange_code);
-            let re = Regex::new(&pattern).unwrap();
-
-            if let Some(m) = re.find(content) {
-                let start_pos = m.end();
-                let remaining = &content[start_pos..];
+            if let Some(pos) = keys.iter().position(|(key, _, _)| *key == exchange_code) {
+                let start_pos = keys[pos].2;
+                let end_pos = keys.get(pos + 1).map_or(content.len(), |next| next.1);
+                let remaining = &content[start_pos..end_pos];
 
                 for cap in item_re.captures_iter(remaining) {
                     let symbol_name = cap.get(1).map(|m| m.as_str()).unwrap_or("");
@@ -118,7 +308,7 @@ impl FuturesService {
             }
         }
 
-        println!("📊 解析到 {} 个品种映射", symbols.len());
+        log::debug!("解析到 {} 个品种映射", symbols.len());
         Ok(symbols)
     }
 
@@ -144,20 +334,28 @@ impl FuturesService {
         ))
     }
 
+    /// 查找与品种名称匹配的候选品种
+    ///
+    /// 名称完全一致时只返回该品种，否则返回所有包含该名称的品种（可能为空或多个）
+    pub async fn find_symbol_candidates(&mut self, symbol: &str) -> Result<Vec<FuturesSymbolMark>> {
+        let symbols = self.get_symbol_mark().await?;
+
+        if let Some(exact) = symbols.iter().find(|s| s.symbol == symbol) {
+            return Ok(vec![exact.clone()]);
+        }
+
+        Ok(symbols
+            .into_iter()
+            .filter(|s| s.symbol.contains(symbol))
+            .collect())
+    }
+
     /// 获取指定交易所的所有品种
     pub async fn get_exchange_symbols(&mut self, exchange: &str) -> Result<Vec<FuturesSymbolMark>> {
+        let exchange_name = symbol_mark_exchange_name(exchange)
+            .ok_or_else(|| anyhow!("未知交易所: {}", exchange))?;
         let symbols = self.get_symbol_mark().await?;
 
-        let exchange_name = match exchange.to_uppercase().as_str() {
-            "CZCE" => "郑州商品交易所",
-            "DCE" => "大连商品交易所",
-            "SHFE" => "上海期货交易所",
-            "CFFEX" => "中国金融期货交易所",
-            "GFEX" => "广州期货交易所",
-            "INE" => "上海期货交易所",
-            _ => return Err(anyhow!("未知交易所: {}", exchange)),
-        };
-
         Ok(symbols
             .into_iter()
             .filter(|s| s.exchange == exchange_name)
@@ -167,66 +365,125 @@ impl FuturesService {
     // ==================== 实时行情相关 ====================
 
     /// 获取单个期货合约实时数据
+    ///
+    /// 按 api.realtime_sources 顺序请求，新浪失败或无数据时改用备用数据源
     pub async fn get_futures_info(&self, symbol: &str) -> Result<FuturesInfo> {
-        let formatted_symbol = self.format_symbol_for_realtime(symbol);
-        let rn_code = self.generate_random_code();
-        let url = format!(
+        if let Some(list) = fixture::<Vec<FuturesInfo>>("futures_realtime") {
+            return list?
+                .into_iter()
+                .find(|info| info.symbol.eq_ignore_ascii_case(symbol))
+                .ok_or_else(|| anyhow!("fixture 中没有合约 {}", symbol));
+        }
+        // fixture 为固定快照，其中的合约可能已过月份，只对真实请求做到期检查
+        ensure_not_expired(symbol)?;
+
+        let items =
+            fetch_with_fallback(self, &configured_realtime_sources(), &[symbol.to_string()]).await?;
+        match items.into_iter().next() {
+            Some(FuturesBatchItem { data: Some(info), .. }) => Ok(info),
+            Some(FuturesBatchItem { status: FuturesBatchStatus::ParseError, .. }) => {
+                Err(anyhow!("解析 {} 行情数据失败", symbol))
+            }
+            _ => Err(anyhow!("API返回空数据")),
+        }
+    }
+
+    /// 构建实时行情请求 URL，list 为逗号分隔的新浪格式代码
+    fn realtime_url(&self, list: &str) -> String {
+        format!(
             "{}/rn={}&list={}",
-            SINA_FUTURES_REALTIME_API, rn_code, formatted_symbol
-        );
+            SINA_FUTURES_REALTIME_API,
+            self.generate_random_code(),
+            list
+        )
+    }
 
-        println!("📡 请求实时行情 URL: {}", url);
+    /// 描述单个合约的实时行情请求（不发送请求），用于排查代码格式问题
+    pub fn describe_realtime_request(&self, symbol: &str) -> RealtimeRequestDebug {
+        let formatted_symbol = self.format_symbol_for_realtime(symbol);
+        RealtimeRequestDebug {
+            symbol: symbol.to_string(),
+            is_cffex: formatted_symbol.starts_with("CFF_"),
+            url: self.realtime_url(&formatted_symbol),
+            formatted_symbol,
+        }
+    }
 
-        let response = self.client
-            .get(&url)
-            .header("Accept", "*/*")
-            .header("Accept-Encoding", "gzip, deflate")
-            .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
-            .header("Cache-Control", "no-cache")
-            .header("Host", "hq.sinajs.cn")
-            .header("Pragma", "no-cache")
-            .header("Proxy-Connection", "keep-alive")
-            .header("Referer", "https://vip.stock.finance.sina.com.cn/")
-            .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/97.0.4692.71 Safari/537.36")
-            .send()
-            .await?;
+    /// 获取多个期货合约实时数据
+    ///
+    /// 按新浪格式化后的代码去重，结果保持请求顺序；无数据的合约会被跳过
+    pub async fn get_multiple_futures(&self, symbols: &[String]) -> Result<Vec<FuturesInfo>> {
+        let items = self.get_multiple_futures_detailed(symbols).await?;
+        Ok(items.into_iter().filter_map(|item| item.data).collect())
+    }
 
-        if !response.status().is_success() {
-            return Err(anyhow!("获取数据失败: {}", response.status()));
+    /// 获取多个期货合约实时数据，并返回每个合约的获取状态
+    ///
+    /// 按新浪格式化后的代码去重，结果保持请求顺序；无数据或解析失败的合约同样返回一项，
+    /// 客户端据此得知哪些合约没有行情。按 api.realtime_sources 顺序请求，
+    /// 新浪请求失败或部分合约无数据时，只对这些合约请求备用数据源
+    pub async fn get_multiple_futures_detailed(
+        &self,
+        symbols: &[String],
+    ) -> Result<Vec<FuturesBatchItem>> {
+        if let Some(list) = fixture::<Vec<FuturesInfo>>("futures_realtime") {
+            let list = list?;
+            return Ok(symbols
+                .iter()
+                .map(|symbol| {
+                    let data = list
+                        .iter()
+                        .find(|info| info.symbol.eq_ignore_ascii_case(symbol))
+                        .cloned();
+                    FuturesBatchItem {
+                        symbol: symbol.clone(),
+                        status: if data.is_some() {
+                            FuturesBatchStatus::Ok
+                        } else {
+                            FuturesBatchStatus::NoData
+                        },
+                        data,
+                    }
+                })
+                .collect());
         }
 
-        let text = response.text().await?;
-        self.parse_sina_realtime_data(&text, symbol)
+        let unique: Vec<String> =
+            self.realtime_requests(symbols).into_iter().map(|(original, _)| original).collect();
+        fetch_with_fallback(self, &configured_realtime_sources(), &unique).await
     }
 
-    /// 获取多个期货合约实时数据
-    pub async fn get_multiple_futures(&self, symbols: &[String]) -> Result<Vec<FuturesInfo>> {
-        let formatted_symbols: Vec<String> = symbols
-            .iter()
-            .map(|s| self.format_symbol_for_realtime(s))
-            .collect();
+    /// 按新浪格式化后的代码去重，返回 (原始代码, 新浪格式代码) 列表，保持首次出现的顺序
+    fn realtime_requests(&self, symbols: &[String]) -> Vec<(String, String)> {
+        let mut requested: Vec<(String, String)> = Vec::new();
+        for symbol in symbols {
+            let formatted = self.format_symbol_for_realtime(symbol);
+            if !requested.iter().any(|(_, f)| *f == formatted) {
+                requested.push((symbol.clone(), formatted));
+            }
+        }
+        requested
+    }
 
-        let symbols_str = formatted_symbols.join(",");
-        let rn_code = self.generate_random_code();
-        let url = format!(
-            "{}/rn={}&list={}",
-            SINA_FUTURES_REALTIME_API, rn_code, symbols_str
-        );
+    /// 请求新浪实时行情，按新浪格式化后的代码去重
+    async fn fetch_sina_realtime(&self, symbols: &[String]) -> Result<Vec<FuturesBatchItem>> {
+        let requested = self.realtime_requests(symbols);
 
-        println!("📡 请求批量实时行情 URL: {}", url);
+        let symbols_str = requested
+            .iter()
+            .map(|(_, formatted)| formatted.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        let url = self.realtime_url(&symbols_str);
+
+        log::debug!("请求批量实时行情 URL: {}", url);
 
         let response = self.client
             .get(&url)
-            .header("Accept", "*/*")
-            .header("Accept-Encoding", "gzip, deflate")
-            .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
             .header("Cache-Control", "no-cache")
-            .header("Host", "hq.sinajs.cn")
             .header("Pragma", "no-cache")
-            .header("Proxy-Connection", "keep-alive")
-            .header("Referer", "https://vip.stock.finance.sina.com.cn/")
-            .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/97.0.4692.71 Safari/537.36")
-            .send()
+            .timeout(RequestTimeout::Realtime.duration())
+            .send_limited()
             .await?;
 
         if !response.status().is_success() {
@@ -234,7 +491,7 @@ impl FuturesService {
         }
 
         let text = response.text().await?;
-        self.parse_multiple_realtime_data(&text, symbols)
+        Ok(Self::parse_multiple_realtime_detailed(&text, &requested))
     }
 
     /// 获取品种所有合约实时数据
@@ -247,14 +504,21 @@ impl FuturesService {
     }
 
     /// 获取期货列表（按交易所或品种）
-    pub async fn list_main_futures(&mut self, query: &FuturesQuery) -> Result<Vec<FuturesInfo>> {
+    ///
+    /// 指定交易所时最多请求 defaults.list_varieties_per_exchange 个品种，未指定 limit 时取
+    /// defaults.list_limit 条；未指定交易所时每个交易所请求 defaults.list_varieties_all_exchanges 个品种
+    pub async fn list_main_futures(
+        &mut self,
+        query: &FuturesQuery,
+        defaults: &DefaultsConfig,
+    ) -> Result<Vec<FuturesInfo>> {
         match query.exchange.as_deref() {
             Some(exchange) => {
                 let exchange_symbols = self.get_exchange_symbols(exchange).await?;
                 let mut all_futures = Vec::new();
-                let limit = query.limit.unwrap_or(20);
+                let limit = query.limit.unwrap_or(defaults.list_limit);
 
-                for symbol_mark in exchange_symbols.iter().take(5) {
+                for symbol_mark in exchange_symbols.iter().take(defaults.list_varieties_per_exchange) {
                     match self.get_futures_by_node(&symbol_mark.mark, Some(1)).await {
                         Ok(mut futures) => all_futures.append(&mut futures),
                         Err(e) => log::warn!("获取品种 {} 数据失败: {}", symbol_mark.symbol, e),
@@ -264,7 +528,7 @@ impl FuturesService {
                     }
                 }
 
-                all_futures.sort_by(|a, b| b.open_interest.cmp(&a.open_interest));
+                all_futures.sort_by_key(|f| std::cmp::Reverse(f.open_interest));
                 all_futures.truncate(limit);
                 Ok(all_futures)
             }
@@ -274,7 +538,7 @@ impl FuturesService {
 
                 for exchange in exchanges {
                     if let Ok(symbols) = self.get_exchange_symbols(exchange).await {
-                        for symbol_mark in symbols.iter().take(2) {
+                        for symbol_mark in symbols.iter().take(defaults.list_varieties_all_exchanges) {
                             if let Ok(mut futures) =
                                 self.get_futures_by_node(&symbol_mark.mark, Some(1)).await
                             {
@@ -292,16 +556,32 @@ impl FuturesService {
     }
 
     /// 通过node参数获取期货数据
+    ///
+    /// limit 为 None 时并非不限条数，而是取配置 api.node_list_limit（默认 100），
+    /// 避免大品种一次返回过多合约。
+    ///
+    /// 新浪在高负载时偶尔返回 200 但内容为空或被截断，解析结果为空时等待片刻重试一次，
+    /// 重试仍为空则按没有合约返回；请求失败或状态码非 200 不重试
     pub async fn get_futures_by_node(
         &self,
         node: &str,
         limit: Option<usize>,
     ) -> Result<Vec<FuturesInfo>> {
+        if let Some(list) = fixture::<Vec<FuturesInfo>>("futures_realtime") {
+            let limit = limit.unwrap_or_else(|| config::read(|c| c.api.node_list_limit));
+            return Ok(list?.into_iter().take(limit).collect());
+        }
+
+        retry_once_on_empty(node, EMPTY_LIST_RETRY_DELAY, || self.fetch_node_list(node, limit)).await
+    }
+
+    /// 请求并解析一次新浪期货列表，响应体为空白时视为空列表
+    async fn fetch_node_list(&self, node: &str, limit: Option<usize>) -> Result<Vec<FuturesInfo>> {
         let full_url = format!(
             "{}?page=1&sort=position&asc=0&node={}&base=futures",
             SINA_FUTURES_LIST_API, node
         );
-        println!("📡 请求期货列表 URL: {}", full_url);
+        log::debug!("请求期货列表 URL: {}", full_url);
 
         let response = self
             .client
@@ -313,7 +593,8 @@ impl FuturesService {
                 ("node", node),
                 ("base", "futures"),
             ])
-            .send()
+            .timeout(RequestTimeout::Realtime.duration())
+            .send_limited()
             .await?;
 
         if !response.status().is_success() {
@@ -322,23 +603,26 @@ impl FuturesService {
 
         let text = response.text().await?;
         let preview: String = text.chars().take(300).collect();
-        println!("📥 原始响应数据: {}", preview);
-
-        let json_data: serde_json::Value =
-            serde_json::from_str(&text).map_err(|e| anyhow!("解析JSON失败: {}", e))?;
-
-        let mut futures_list = Vec::new();
+        log::debug!("原始响应数据: {}", preview);
 
-        if let Some(data_array) = json_data.as_array() {
-            let limit = limit.unwrap_or(data_array.len());
-            for item in data_array.iter().take(limit) {
-                if let Ok(futures_info) = self.parse_sina_list_data(item) {
-                    futures_list.push(futures_info);
-                }
-            }
+        if text.trim().is_empty() {
+            return Ok(Vec::new());
         }
+        let json_data = parse_context(parse_json_lenient(&text), "新浪期货列表", &full_url, &text)?;
+        Ok(Self::parse_node_list(&json_data, limit))
+    }
 
-        Ok(futures_list)
+    /// 解析新浪期货列表，limit 为 None 时取配置 api.node_list_limit，跳过无法解析的行
+    fn parse_node_list(json_data: &serde_json::Value, limit: Option<usize>) -> Vec<FuturesInfo> {
+        let Some(data_array) = json_data.as_array() else {
+            return Vec::new();
+        };
+        let limit = limit.unwrap_or_else(|| config::read(|c| c.api.node_list_limit));
+        data_array
+            .iter()
+            .take(limit)
+            .filter_map(|item| Self::parse_sina_list_data(item).ok())
+            .collect()
     }
 
     // ==================== 主力合约相关 ====================
@@ -356,7 +640,7 @@ impl FuturesService {
                             futures.iter().max_by_key(|f| f.open_interest.unwrap_or(0))
                         {
                             main_contracts.push(main.symbol.clone());
-                            println!("  {} 主力合约: {}", symbol_mark.symbol, main.symbol);
+                            log::debug!("{} 主力合约: {}", symbol_mark.symbol, main.symbol);
                         }
                     }
                 }
@@ -374,16 +658,12 @@ impl FuturesService {
     /// 获取期货合约详情
     pub async fn get_contract_detail(&self, symbol: &str) -> Result<FuturesContractDetail> {
         let url = format!("{}/{}.shtml", SINA_CONTRACT_DETAIL_URL, symbol);
-        println!("📡 请求合约详情 URL: {}", url);
+        log::debug!("请求合约详情 URL: {}", url);
 
         let response = self
             .client
             .get(&url)
-            .header(
-                "User-Agent",
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-            )
-            .send()
+            .send_limited()
             .await?;
 
         if !response.status().is_success() {
@@ -393,11 +673,11 @@ impl FuturesService {
         let bytes = response.bytes().await?;
         let text = encoding_rs::GBK.decode(&bytes).0.to_string();
 
-        self.parse_contract_detail(&text, symbol)
+        Self::parse_contract_detail(&text, symbol)
     }
 
     /// 解析合约详情HTML
-    fn parse_contract_detail(&self, html: &str, symbol: &str) -> Result<FuturesContractDetail> {
+    pub(crate) fn parse_contract_detail(html: &str, symbol: &str) -> Result<FuturesContractDetail> {
         let extract_value = |pattern: &str| -> String {
             let re = Regex::new(pattern).ok();
             re.and_then(|r| r.captures(html))
@@ -491,16 +771,16 @@ impl FuturesService {
     }
 
     /// 判断是否为中金所合约
+    ///
+    /// 按完整的品种字母比较，避免 TA（PTA）等以 T 开头的商品期货被误判为国债期货
     fn is_cffex_symbol(&self, symbol: &str) -> bool {
         let cffex_products = ["IF", "IC", "IH", "IM", "T", "TF", "TS", "TL"];
-        cffex_products
-            .iter()
-            .any(|&product| symbol.starts_with(product))
+        let variety = extract_variety(symbol);
+        cffex_products.contains(&variety.as_str())
     }
 
     /// 解析新浪期货实时数据
     pub fn parse_sina_realtime_data(
-        &self,
         data: &str,
         original_symbol: &str,
     ) -> Result<FuturesInfo> {
@@ -534,20 +814,21 @@ impl FuturesService {
             }
 
             let name = fields[0].to_string();
-            let open = fields[2].parse::<f64>().unwrap_or(0.0);
-            let high = fields[3].parse::<f64>().unwrap_or(0.0);
-            let low = fields[4].parse::<f64>().unwrap_or(0.0);
-            let current_price = fields[8].parse::<f64>().unwrap_or(0.0);
-            let prev_settlement = fields[10].parse::<f64>().unwrap_or(0.0);
-            let open_interest = fields[13].parse::<u64>().ok();
-            let volume = fields[14].parse::<u64>().unwrap_or(0);
-
-            let change = current_price - prev_settlement;
-            let change_percent = if prev_settlement != 0.0 {
-                (change / prev_settlement) * 100.0
-            } else {
-                0.0
+            let open = parse_lenient_f64("open", fields[2]);
+            let high = parse_lenient_f64("high", fields[3]);
+            let low = parse_lenient_f64("low", fields[4]);
+            let current_price = parse_lenient_f64("current_price", fields[8]);
+            let prev_settlement = parse_lenient_f64("prev_settlement", fields[10]);
+            let open_interest = parse_lenient_u64("open_interest", fields[13]);
+            let volume = parse_lenient_u64("volume", fields[14]).unwrap_or(0);
+            let (quote_time, stale) = match parse_quote_time(fields[1], original_symbol) {
+                Some((t, stale)) => (Some(t), stale),
+                None => (None, false),
             };
+            // 夜盘行情归属下一个交易日；没有行情时间时按抓取时间计算
+            let trading_date = trading_date(quote_time.unwrap_or_else(beijing_now));
+
+            let (change, change_percent) = price_change(current_price, prev_settlement);
 
             return Ok(FuturesInfo {
                 symbol: original_symbol.to_string(),
@@ -560,87 +841,93 @@ impl FuturesService {
                 high,
                 low,
                 settlement: None,
-                prev_settlement: Some(prev_settlement),
+                prev_settlement,
                 open_interest,
                 updated_at: get_beijing_time(),
+                quote_time: quote_time.map(|t| t.to_rfc3339()),
+                trading_date: Some(trading_date.format("%Y-%m-%d").to_string()),
+                stale,
+                source: None,
+                current_price_str: price_token(fields[8]),
+                open_str: price_token(fields[2]),
+                high_str: price_token(fields[3]),
+                low_str: price_token(fields[4]),
+                settlement_str: None,
+                prev_settlement_str: price_token(fields[10]),
             });
         }
 
         Err(anyhow!("无法解析响应数据: {}", data))
     }
 
-    /// 解析多个期货合约实时数据
-    fn parse_multiple_realtime_data(
-        &self,
+    /// 解析多个期货合约实时数据，每个请求的合约返回一项及其状态
+    ///
+    /// requested 为 (原始代码, 新浪格式代码) 列表。按每段响应中 hq_str_<代码> 的变量名
+    /// 匹配请求的合约，而不是依赖行的位置，避免上游缺行时结果错位。
+    /// 变量值为空字符串或 FAILURE 时视为无数据（未知或已退市合约）
+    pub(crate) fn parse_multiple_realtime_detailed(
         data: &str,
-        original_symbols: &[String],
-    ) -> Result<Vec<FuturesInfo>> {
-        let mut results = Vec::new();
-
-        let items: Vec<&str> = data.split(';').filter(|s| !s.trim().is_empty()).collect();
+        requested: &[(String, String)],
+    ) -> Vec<FuturesBatchItem> {
+        let mut segments: HashMap<String, &str> = HashMap::new();
+        for item in data.split(';') {
+            let item = item.trim();
+            let Some((var, _)) = item.split_once('=') else {
+                continue;
+            };
+            if let Some(code) = var.trim().rsplit("hq_str_").next() {
+                segments.insert(code.to_lowercase(), item);
+            }
+        }
 
-        for (i, item) in items.iter().enumerate() {
-            if i < original_symbols.len() {
-                match self.parse_sina_realtime_data(item, &original_symbols[i]) {
-                    Ok(futures_info) => results.push(futures_info),
+        requested
+            .iter()
+            .map(|(original, formatted)| {
+                let item = segments
+                    .get(&formatted.to_lowercase())
+                    .filter(|item| !is_empty_quote(item));
+                let Some(item) = item else {
+                    log::warn!("上游无 {} 的行情数据", original);
+                    return FuturesBatchItem {
+                        symbol: original.clone(),
+                        status: FuturesBatchStatus::NoData,
+                        data: None,
+                    };
+                };
+                match Self::parse_sina_realtime_data(item, original) {
+                    Ok(futures_info) => FuturesBatchItem {
+                        symbol: original.clone(),
+                        status: FuturesBatchStatus::Ok,
+                        data: Some(futures_info),
+                    },
                     Err(e) => {
-                        log::warn!("解析 {} 数据失败: {}", original_symbols[i], e);
-                        continue;
+                        log::warn!("解析 {} 数据失败: {}", original, e);
+                        FuturesBatchItem {
+                            symbol: original.clone(),
+                            status: FuturesBatchStatus::ParseError,
+                            data: None,
+                        }
                     }
                 }
-            }
-        }
-
-        Ok(results)
+            })
+            .collect()
     }
 
     /// 解析新浪期货列表数据
-    fn parse_sina_list_data(&self, item: &serde_json::Value) -> Result<FuturesInfo> {
+    pub(crate) fn parse_sina_list_data(item: &serde_json::Value) -> Result<FuturesInfo> {
         let symbol = item["symbol"].as_str().unwrap_or("").to_string();
         let name = item["name"].as_str().unwrap_or("").to_string();
-        let current_price = item["trade"]
-            .as_str()
-            .unwrap_or("0")
-            .parse::<f64>()
-            .unwrap_or(0.0);
-        let prev_settlement = item["presettlement"]
-            .as_str()
-            .unwrap_or("0")
-            .parse::<f64>()
-            .unwrap_or(0.0);
-        let open = item["open"]
-            .as_str()
-            .unwrap_or("0")
-            .parse::<f64>()
-            .unwrap_or(0.0);
-        let high = item["high"]
-            .as_str()
-            .unwrap_or("0")
-            .parse::<f64>()
-            .unwrap_or(0.0);
-        let low = item["low"]
-            .as_str()
-            .unwrap_or("0")
-            .parse::<f64>()
-            .unwrap_or(0.0);
-        let volume = item["volume"]
-            .as_str()
-            .unwrap_or("0")
-            .parse::<u64>()
-            .unwrap_or(0);
-        let open_interest = item["position"].as_str().unwrap_or("0").parse::<u64>().ok();
-        let settlement = item["settlement"]
-            .as_str()
-            .unwrap_or("0")
-            .parse::<f64>()
-            .ok();
-
-        let change = current_price - prev_settlement;
-        let change_percent = if prev_settlement != 0.0 {
-            (change / prev_settlement) * 100.0
-        } else {
-            0.0
-        };
+        let text = |key: &str| item[key].as_str().unwrap_or("");
+        let current_price = parse_lenient_f64("trade", text("trade"));
+        let prev_settlement = parse_lenient_f64("presettlement", text("presettlement"));
+        let open = parse_lenient_f64("open", text("open"));
+        let high = parse_lenient_f64("high", text("high"));
+        let low = parse_lenient_f64("low", text("low"));
+        let volume = parse_lenient_u64("volume", text("volume")).unwrap_or(0);
+        let open_interest = parse_lenient_u64("position", text("position"));
+        let settlement = parse_lenient_f64("settlement", text("settlement"));
+
+        let (change, change_percent) = price_change(current_price, prev_settlement);
 
         Ok(FuturesInfo {
             symbol,
@@ -653,9 +940,390 @@ impl FuturesService {
             high,
             low,
             settlement,
-            prev_settlement: Some(prev_settlement),
+            prev_settlement,
             open_interest,
             updated_at: get_beijing_time(),
+            quote_time: None,
+            trading_date: Some(trading_date(beijing_now()).format("%Y-%m-%d").to_string()),
+            stale: false,
+            source: None,
+            current_price_str: price_token(text("trade")),
+            open_str: price_token(text("open")),
+            high_str: price_token(text("high")),
+            low_str: price_token(text("low")),
+            settlement_str: price_token(text("settlement")),
+            prev_settlement_str: price_token(text("presettlement")),
         })
     }
 }
+
+impl RealtimeSource for FuturesService {
+    async fn sina(&self, symbols: &[String]) -> Result<Vec<FuturesBatchItem>> {
+        self.fetch_sina_realtime(symbols).await
+    }
+
+    async fn eastmoney(&self, symbols: &[String]) -> Result<Vec<FuturesBatchItem>> {
+        get_eastmoney_realtime(symbols).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_symbol_mark_js_keeps_each_exchange_separate() {
+        let js = include_str!("../../../tests/fixtures/sina_qihuohangqing.js");
+        let marks = FuturesService::parse_symbol_mark_js(js).unwrap();
+        assert_eq!(marks.len(), 19);
+
+        let count = |exchange: &str| marks.iter().filter(|m| m.exchange == exchange).count();
+        assert_eq!(count("郑州商品交易所"), 5);
+        assert_eq!(count("大连商品交易所"), 4);
+        assert_eq!(count("上海期货交易所"), 5);
+        assert_eq!(count("中国金融期货交易所"), 3);
+        assert_eq!(count("广州期货交易所"), 2);
+
+        let tong = marks.iter().find(|m| m.symbol == "铜").unwrap();
+        assert_eq!(tong.mark, "tong_qh");
+        assert_eq!(tong.exchange, "上海期货交易所");
+        let lc = marks.iter().find(|m| m.symbol == "碳酸锂").unwrap();
+        assert_eq!(lc.exchange, "广州期货交易所");
+    }
+
+    #[test]
+    fn parse_symbol_mark_js_rejects_other_scripts() {
+        assert!(FuturesService::parse_symbol_mark_js("var x = 1;").is_err());
+    }
+
+    fn realtime_line(open_interest: &str, volume: &str) -> String {
+        format!(
+            "var hq_str_nf_RB2601=\"螺纹钢2601,145959,3088.000,3115.000,3080.000,3090.000,3101.000,3102.000,3102.000,0.000,3089.000,1150,1230,{},{},沪,螺纹钢,2025-12-05,1\";",
+            open_interest, volume
+        )
+    }
+
+    #[test]
+    fn realtime_open_interest_with_decimal_suffix() {
+        let info = FuturesService::parse_sina_realtime_data(&realtime_line("12345.0", "6789"), "RB2601").unwrap();
+        assert_eq!(info.open_interest, Some(12345));
+        assert_eq!(info.volume, 6789);
+        assert_eq!(info.current_price, Some(3102.0));
+        assert_eq!(info.current_price_str.as_deref(), Some("3102.000"));
+    }
+
+    #[test]
+    fn realtime_quote_time_sets_trading_date() {
+        let info = FuturesService::parse_sina_realtime_data(&realtime_line("12345", "6789"), "RB2601").unwrap();
+        let quote_time = info.quote_time.as_deref().expect("应有行情时间");
+        let quote = chrono::DateTime::parse_from_rfc3339(quote_time).unwrap();
+        assert_eq!(quote.format("%H:%M:%S%:z").to_string(), "14:59:59+08:00");
+        let expected = trading_date(quote.with_timezone(&chrono_tz::Asia::Shanghai));
+        assert_eq!(info.trading_date, Some(expected.format("%Y-%m-%d").to_string()));
+    }
+
+    #[test]
+    fn realtime_without_time_field_has_no_quote_time() {
+        let line = realtime_line("12345", "6789").replacen(",145959,", ",,", 1);
+        let info = FuturesService::parse_sina_realtime_data(&line, "RB2601").unwrap();
+        assert_eq!(info.quote_time, None);
+        assert!(!info.stale);
+        // 没有行情时间时按抓取时间计算交易日
+        let date = info.trading_date.expect("应有交易日");
+        assert!(chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_ok(), "{}", date);
+    }
+
+    #[test]
+    fn realtime_plain_and_empty_fields() {
+        let info = FuturesService::parse_sina_realtime_data(&realtime_line("12345", ""), "RB2601").unwrap();
+        assert_eq!(info.open_interest, Some(12345));
+        assert_eq!(info.volume, 0);
+
+        let info = FuturesService::parse_sina_realtime_data(&realtime_line("", "6789"), "RB2601").unwrap();
+        assert_eq!(info.open_interest, None);
+        assert_eq!(info.volume, 6789);
+    }
+
+    #[test]
+    fn realtime_empty_price_is_none_and_zero_is_zero() {
+        let line = realtime_line("12345", "6789").replacen(",3088.000,3115.000,", ",,0,", 1);
+        let info = FuturesService::parse_sina_realtime_data(&line, "RB2601").unwrap();
+        assert_eq!(info.open, None);
+        assert_eq!(info.open_str, None);
+        assert_eq!(info.high, Some(0.0));
+        assert_eq!(info.current_price, Some(3102.0));
+    }
+
+    #[test]
+    fn batch_rows_are_matched_by_variable_name() {
+        // 上游缺少 CU2601 一行、M2601 返回 FAILURE，且行的顺序与请求顺序不同
+        let au = realtime_line("198765", "54321")
+            .replace("nf_RB2601", "nf_AU2602")
+            .replace("螺纹钢2601", "黄金2602");
+        let data = format!(
+            "{}\nvar hq_str_nf_M2601=\"FAILURE\";\n{}\n",
+            au,
+            realtime_line("1987654", "6789")
+        );
+        let requested: Vec<(String, String)> = ["CU2601", "rb2601", "AU2602", "M2601"]
+            .iter()
+            .map(|s| (s.to_string(), format!("nf_{}", s.to_uppercase())))
+            .collect();
+
+        let items = FuturesService::parse_multiple_realtime_detailed(&data, &requested);
+        let summary: Vec<(&str, FuturesBatchStatus)> =
+            items.iter().map(|item| (item.symbol.as_str(), item.status)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("CU2601", FuturesBatchStatus::NoData),
+                ("rb2601", FuturesBatchStatus::Ok),
+                ("AU2602", FuturesBatchStatus::Ok),
+                ("M2601", FuturesBatchStatus::NoData),
+            ]
+        );
+        let rb = items[1].data.as_ref().unwrap();
+        assert_eq!((rb.symbol.as_str(), rb.name.as_str(), rb.open_interest), ("rb2601", "螺纹钢2601", Some(1987654)));
+        let au = items[2].data.as_ref().unwrap();
+        assert_eq!((au.symbol.as_str(), au.name.as_str(), au.open_interest), ("AU2602", "黄金2602", Some(198765)));
+    }
+
+    #[test]
+    fn empty_string_sentinel_is_no_data_and_keeps_alignment() {
+        let data = format!("var hq_str_nf_ZN2601=\"\";\n{}\nvar hq_str_nf_AL2601=\"铝2601,1\";\n", realtime_line("1987654", "6789"));
+        let requested: Vec<(String, String)> = ["ZN2601", "RB2601", "AL2601"]
+            .iter()
+            .map(|s| (s.to_string(), format!("nf_{}", s)))
+            .collect();
+
+        let items = FuturesService::parse_multiple_realtime_detailed(&data, &requested);
+        let summary: Vec<(&str, FuturesBatchStatus, bool)> =
+            items.iter().map(|item| (item.symbol.as_str(), item.status, item.data.is_some())).collect();
+        assert_eq!(
+            summary,
+            [
+                ("ZN2601", FuturesBatchStatus::NoData, false),
+                ("RB2601", FuturesBatchStatus::Ok, true),
+                ("AL2601", FuturesBatchStatus::ParseError, false),
+            ]
+        );
+        assert_eq!(items[1].data.as_ref().unwrap().name, "螺纹钢2601");
+        assert!(is_empty_quote("var hq_str_nf_ZN2601=\" \""));
+        assert!(!is_empty_quote(&realtime_line("1", "1")));
+    }
+
+    fn marks(n: usize) -> Vec<FuturesSymbolMark> {
+        (0..n)
+            .map(|i| FuturesSymbolMark {
+                exchange: "上海期货交易所".to_string(),
+                symbol: format!("品种{}", i),
+                mark: format!("m{}_qh", i),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn symbol_mark_cache_serves_fresh_then_stale_on_failed_refresh() {
+        let cache = RwLock::new(None);
+        let ttl = Duration::from_secs(1);
+
+        let err = cached_symbol_marks(&cache, ttl, async { Err(anyhow!("上游超时")) }).await.unwrap_err();
+        assert!(err.to_string().contains("上游超时"));
+
+        assert_eq!(cached_symbol_marks(&cache, ttl, async { Ok(marks(2)) }).await.unwrap().len(), 2);
+        let fresh = cached_symbol_marks(&cache, ttl, async { panic!("缓存未过期时不应刷新") }).await.unwrap();
+        assert_eq!(fresh.len(), 2);
+
+        // 模拟越过有效期：获取时间提前到 ttl 之前
+        if let Some((cached_at, _)) = cache.write().unwrap().as_mut() {
+            *cached_at -= ttl;
+        }
+        let stale = cached_symbol_marks(&cache, ttl, async { Err(anyhow!("上游超时")) }).await.unwrap();
+        assert_eq!(stale.len(), 2);
+        assert!(store_symbol_marks(&cache, Err(anyhow!("刷新失败"))).is_err());
+        assert_eq!(cache.read().unwrap().as_ref().unwrap().1.len(), 2);
+
+        let refreshed = cached_symbol_marks(&cache, ttl, async { Ok(marks(3)) }).await.unwrap();
+        assert_eq!(refreshed.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn symbol_mark_cache_never_empty_across_ttl_with_refresher() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = RwLock::new(None);
+        let ttl = Duration::from_millis(100);
+        let ticks = AtomicUsize::new(0);
+        store_symbol_marks(&cache, Ok(marks(2))).unwrap();
+
+        // 后台刷新间隔小于有效期；每三次刷新失败一次，失败时保留原缓存
+        let refresher = async {
+            for _ in 0..15 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                let tick = ticks.fetch_add(1, Ordering::SeqCst);
+                let result = if tick % 3 == 2 { Err(anyhow!("刷新失败")) } else { Ok(marks(2 + tick % 2)) };
+                let _ = store_symbol_marks(&cache, result);
+            }
+        };
+        // 前台请求持续超过多个有效期，刷新函数总是失败，只能依赖缓存
+        let foreground = async {
+            for _ in 0..30 {
+                let symbols = cached_symbol_marks(&cache, ttl, async { Err(anyhow!("前台刷新失败")) })
+                    .await
+                    .unwrap();
+                assert!(!symbols.is_empty());
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::join!(refresher, foreground);
+
+        assert_eq!(ticks.load(Ordering::SeqCst), 15);
+        assert!(cache.read().unwrap().is_some());
+    }
+
+    /// 依次返回预设结果的列表请求，返回最终结果和请求次数
+    async fn retry_scripted(responses: Vec<Result<Vec<u32>>>) -> (Result<Vec<u32>>, usize) {
+        let calls = std::cell::Cell::new(0);
+        let responses = std::cell::RefCell::new(responses.into_iter());
+        let result = retry_once_on_empty("nf_RB", Duration::ZERO, || {
+            calls.set(calls.get() + 1);
+            std::future::ready(responses.borrow_mut().next().expect("unexpected extra request"))
+        })
+        .await;
+        (result, calls.get())
+    }
+
+    #[tokio::test]
+    async fn empty_list_is_retried_once_and_recovers() {
+        let (list, calls) = retry_scripted(vec![Ok(vec![]), Ok(vec![1, 2])]).await;
+        assert_eq!(list.unwrap(), vec![1, 2]);
+        assert_eq!(calls, 2);
+    }
+
+    #[tokio::test]
+    async fn empty_list_retry_is_capped_and_errors_are_not_retried() {
+        let (list, calls) = retry_scripted(vec![Ok(vec![]), Ok(vec![])]).await;
+        assert!(list.unwrap().is_empty());
+        assert_eq!(calls, 2);
+
+        let (list, calls) = retry_scripted(vec![Ok(vec![7])]).await;
+        assert_eq!(list.unwrap(), vec![7]);
+        assert_eq!(calls, 1);
+
+        let (list, calls) = retry_scripted(vec![Err(anyhow!("HTTP 502"))]).await;
+        assert!(list.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn list_default_limits_come_from_config() {
+        crate::config::init_mock_for_tests();
+        let shfe = FuturesQuery::builder().exchange("SHFE").build();
+        let count = |defaults: DefaultsConfig| {
+            let shfe = &shfe;
+            async move { FuturesService::new().list_main_futures(shfe, &defaults).await.unwrap().len() }
+        };
+
+        assert_eq!(count(DefaultsConfig::default()).await, 3);
+        assert_eq!(count(DefaultsConfig { list_limit: 2, ..Default::default() }).await, 2);
+        assert_eq!(count(DefaultsConfig { list_varieties_per_exchange: 1, ..Default::default() }).await, 1);
+
+        let query = FuturesQuery::builder().exchange("SHFE").limit(1).build();
+        let defaults = DefaultsConfig { list_limit: 2, ..Default::default() };
+        assert_eq!(FuturesService::new().list_main_futures(&query, &defaults).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn realtime_requests_dedupe_by_sina_code_in_request_order() {
+        let symbols: Vec<String> =
+            ["rb2601", "IF2512", "RB2601", "nf_RB2601", "CU2601", "cff_if2512"].iter().map(|s| s.to_string()).collect();
+        let requested = FuturesService::new().realtime_requests(&symbols);
+        let pairs: Vec<(&str, &str)> = requested.iter().map(|(o, f)| (o.as_str(), f.as_str())).collect();
+        assert_eq!(pairs, [("rb2601", "nf_RB2601"), ("IF2512", "CFF_IF2512"), ("CU2601", "nf_CU2601")]);
+    }
+
+    #[test]
+    fn describe_realtime_request_formats_each_exchange() {
+        let service = FuturesService::new();
+        let cases = [
+            ("CU2405", "nf_CU2405", false),
+            ("rb2510", "nf_RB2510", false),
+            ("MA505", "nf_MA505", false),
+            ("IF2412", "CFF_IF2412", true),
+            ("t2503", "CFF_T2503", true),
+            ("TA505", "nf_TA505", false),
+            ("nf_SR601", "nf_SR601", false),
+            ("cff_IC2412", "CFF_IC2412", true),
+        ];
+        for (symbol, formatted, is_cffex) in cases {
+            let debug = service.describe_realtime_request(symbol);
+            assert_eq!(debug.symbol, symbol);
+            assert_eq!(debug.formatted_symbol, formatted, "{}", symbol);
+            assert_eq!(debug.is_cffex, is_cffex, "{}", symbol);
+            assert!(debug.url.starts_with(SINA_FUTURES_REALTIME_API), "{}", debug.url);
+            assert!(debug.url.ends_with(&format!("&list={}", formatted)), "{}", debug.url);
+        }
+    }
+
+    #[test]
+    fn node_list_without_limit_is_capped_by_config() {
+        let rows: Vec<serde_json::Value> = (0..200)
+            .map(|i| {
+                serde_json::json!({
+                    "symbol": format!("RB{}", 2601 + i),
+                    "name": "螺纹钢",
+                    "trade": "3102.000",
+                    "presettlement": "3089.000",
+                    "open": "3088.000",
+                    "high": "3115.000",
+                    "low": "3080.000",
+                    "volume": "1000",
+                    "position": (200 - i).to_string(),
+                })
+            })
+            .collect();
+        let json = serde_json::Value::Array(rows);
+
+        let cap = config::read(|c| c.api.node_list_limit);
+        assert!(cap < 200, "默认上限应小于测试数据量: {}", cap);
+        let capped = FuturesService::parse_node_list(&json, None);
+        assert_eq!(capped.len(), cap);
+        assert_eq!(capped[0].symbol, "RB2601");
+
+        let one = FuturesService::parse_node_list(&json, Some(1));
+        assert_eq!(one.len(), 1);
+        assert_eq!(one[0].open_interest, Some(200));
+
+        assert_eq!(FuturesService::parse_node_list(&json, Some(500)).len(), 200);
+        assert!(FuturesService::parse_node_list(&serde_json::json!({"error": 1}), None).is_empty());
+    }
+
+    #[test]
+    fn contract_symbol_accepts_monthly_and_main_codes() {
+        for symbol in ["CU2405", "MA505", "CU0", "nf_RB2510", "CFF_IF2412", " AU2606 "] {
+            assert!(validate_contract_symbol(symbol).is_ok(), "{}", symbol);
+        }
+    }
+
+    #[test]
+    fn contract_symbol_is_case_insensitive() {
+        assert!(validate_contract_symbol("rb2510").is_ok());
+        assert!(validate_contract_symbol("ma505").is_ok());
+    }
+
+    #[test]
+    fn contract_symbol_rejects_wrong_lengths() {
+        // 5 位月份、5 位品种字母、非郑商所品种的 3 位月份
+        for symbol in ["CU24055", "ABCDE2405", "CU240", "RB25", "2405", "CU"] {
+            assert!(validate_contract_symbol(symbol).is_err(), "{}", symbol);
+        }
+    }
+
+    #[test]
+    fn contract_symbol_rejects_illegal_characters() {
+        for symbol in ["", "CU24-5", "RB2510!", "RB 2510", "铜2405", "CU2405.SHF"] {
+            let err = validate_contract_symbol(symbol).unwrap_err();
+            assert!(err.to_string().contains("合约代码格式无效"), "{}", symbol);
+        }
+    }
+}
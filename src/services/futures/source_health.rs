@@ -0,0 +1,190 @@
+//! 上游数据源健康状态
+//!
+//! send_limited 在每次请求结束后记录各域名的最近成功时间、最近错误和连续失败次数，
+//! 与熔断状态合并为数据源状态看板（GET /admin/sources），用于判断新浪等上游当前是否在拒绝请求
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use super::breaker::upstream_breaker_stats;
+use super::common::get_beijing_time;
+use super::limiter::{is_sina_host, sina_limiter_stats};
+use super::sources::KNOWN_SOURCES;
+
+/// 错误信息保留的最大字符数
+const MAX_ERROR_CHARS: usize = 200;
+
+#[derive(Debug, Default)]
+struct Health {
+    last_success_at: Option<String>,
+    last_error: Option<String>,
+    last_error_at: Option<String>,
+    consecutive_failures: u32,
+}
+
+static HEALTH: OnceLock<Mutex<HashMap<String, Health>>> = OnceLock::new();
+
+fn health() -> std::sync::MutexGuard<'static, HashMap<String, Health>> {
+    HEALTH
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 记录上游请求成功
+pub fn record_source_success(host: &str) {
+    let mut map = health();
+    let entry = map.entry(host.to_string()).or_default();
+    entry.last_success_at = Some(get_beijing_time());
+    entry.consecutive_failures = 0;
+}
+
+/// 记录上游请求失败，错误信息去除 URL 路径和查询参数并截断后保存
+pub fn record_source_error(host: &str, error: &str) {
+    let mut map = health();
+    let entry = map.entry(host.to_string()).or_default();
+    entry.last_error = Some(redact_error(error));
+    entry.last_error_at = Some(get_beijing_time());
+    entry.consecutive_failures += 1;
+}
+
+/// 错误信息中的 URL 只保留协议和域名（路径和查询参数可能包含请求参数），并限制长度
+fn redact_error(error: &str) -> String {
+    let redacted: Vec<String> = error
+        .split(' ')
+        .map(|word| {
+            let Some(scheme_end) = word.find("://") else {
+                return word.to_string();
+            };
+            let host_end = word[scheme_end + 3..]
+                .find(['/', '?', ')'])
+                .map(|i| scheme_end + 3 + i)
+                .unwrap_or(word.len());
+            // 保留 URL 后的标点，如 "(https://host/path?a=1):" 中的 "):"
+            let rest = &word[host_end..];
+            let tail = &rest[rest.trim_end_matches([')', ':', ',', ';']).len()..];
+            if host_end < word.len() - tail.len() {
+                format!("{}/…{}", &word[..host_end], tail)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect();
+    redacted.join(" ").chars().take(MAX_ERROR_CHARS).collect()
+}
+
+/// 单个上游数据源的状态
+#[derive(Debug, Serialize)]
+pub struct SourceHealth {
+    /// 数据源名称
+    pub name: String,
+    /// 上游域名
+    pub host: String,
+    /// 最近一次成功请求的时间（北京时间），启动后未请求过时为 null
+    pub last_success_at: Option<String>,
+    /// 最近一次错误（URL 只保留域名）
+    pub last_error: Option<String>,
+    /// 最近一次错误的时间（北京时间）
+    pub last_error_at: Option<String>,
+    /// 连续失败次数（成功后清零）
+    pub consecutive_failures: u32,
+    /// 熔断状态：closed / open / half_open
+    pub breaker_state: &'static str,
+    /// 熔断剩余秒数
+    pub breaker_open_remaining_secs: u64,
+    /// 新浪封禁冷却剩余秒数（非新浪数据源为 0）
+    pub ban_remaining_secs: u64,
+}
+
+/// 获取所有已知上游数据源的状态，另附启动后请求过的未登记域名
+pub fn source_health() -> Vec<SourceHealth> {
+    let breakers = upstream_breaker_stats();
+    let ban_remaining_secs = sina_limiter_stats().ban_remaining_secs;
+    let map = health();
+
+    let mut hosts: Vec<(String, String)> = KNOWN_SOURCES
+        .iter()
+        .map(|(name, host)| (name.to_string(), host.to_string()))
+        .collect();
+    let mut unknown: Vec<&String> = map
+        .keys()
+        .chain(breakers.iter().map(|b| &b.host))
+        .filter(|host| !KNOWN_SOURCES.iter().any(|(_, known)| known == host))
+        .collect();
+    unknown.sort();
+    unknown.dedup();
+    hosts.extend(unknown.into_iter().map(|host| (host.clone(), host.clone())));
+
+    hosts
+        .into_iter()
+        .map(|(name, host)| {
+            let entry = map.get(&host);
+            let breaker = breakers.iter().find(|b| b.host == host);
+            SourceHealth {
+                last_success_at: entry.and_then(|h| h.last_success_at.clone()),
+                last_error: entry.and_then(|h| h.last_error.clone()),
+                last_error_at: entry.and_then(|h| h.last_error_at.clone()),
+                consecutive_failures: entry.map(|h| h.consecutive_failures).unwrap_or(0),
+                breaker_state: breaker.map(|b| b.state).unwrap_or("closed"),
+                breaker_open_remaining_secs: breaker.map(|b| b.open_remaining_secs).unwrap_or(0),
+                ban_remaining_secs: if is_sina_host(&host) { ban_remaining_secs } else { 0 },
+                name,
+                host,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn board_lists_every_known_source_in_order() {
+        let board = source_health();
+        let hosts: Vec<&str> = board.iter().take(KNOWN_SOURCES.len()).map(|s| s.host.as_str()).collect();
+        let known: Vec<&str> = KNOWN_SOURCES.iter().map(|(_, host)| *host).collect();
+        assert_eq!(hosts, known);
+
+        for (entry, (name, _)) in board.iter().zip(KNOWN_SOURCES) {
+            assert_eq!(entry.name, *name);
+            assert!(["closed", "open", "half_open"].contains(&entry.breaker_state), "{:?}", entry);
+        }
+        let json = serde_json::to_value(&board[0]).unwrap();
+        for key in ["last_success_at", "last_error", "consecutive_failures", "breaker_state", "ban_remaining_secs"] {
+            assert!(json.get(key).is_some(), "缺少字段 {}", key);
+        }
+    }
+
+    #[test]
+    fn failures_accumulate_and_reset_on_success_for_unlisted_host() {
+        let host = "source-health.test.invalid";
+        record_source_error(host, "error sending request for url (https://source-health.test.invalid/a?token=x): timeout");
+        record_source_error(host, "timeout");
+
+        let entry = || source_health().into_iter().find(|s| s.host == host).unwrap();
+        let failing = entry();
+        assert_eq!((failing.name.as_str(), failing.consecutive_failures), (host, 2));
+        assert_eq!(failing.last_error.as_deref(), Some("timeout"));
+        assert!(failing.last_success_at.is_none() && failing.last_error_at.is_some());
+        assert_eq!(failing.ban_remaining_secs, 0);
+
+        record_source_success(host);
+        let recovered = entry();
+        assert_eq!(recovered.consecutive_failures, 0);
+        assert!(recovered.last_success_at.is_some());
+        // 最近错误保留，便于查看恢复前的原因
+        assert_eq!(recovered.last_error.as_deref(), Some("timeout"));
+    }
+
+    #[test]
+    fn redact_error_keeps_only_scheme_and_host() {
+        assert_eq!(
+            redact_error("error sending request for url (https://hq.sinajs.cn/list=nf_RB0?x=1): dns error"),
+            "error sending request for url (https://hq.sinajs.cn/…): dns error"
+        );
+        assert_eq!(redact_error("http://www.dce.com.cn"), "http://www.dce.com.cn");
+        assert_eq!(redact_error(&"x".repeat(500)).chars().count(), MAX_ERROR_CHARS);
+    }
+}
@@ -0,0 +1,165 @@
+//! 上游数据源请求头配置
+//!
+//! 部分上游（尤其是新浪）会拒绝缺少预期 Referer / Host 的请求。各数据源的请求头在此集中定义，
+//! 由 send_limited 按目标 URL 的域名自动补齐，调整反爬虫相关请求头时只需修改这里。
+//! 调用方显式设置的同名请求头优先（如大商所各数据页面的 Referer）
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, HOST, ORIGIN, REFERER};
+use reqwest::Url;
+
+/// 数据源请求头配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderProfile {
+    /// Referer
+    pub referer: &'static str,
+    /// Host（None 表示由 URL 决定）
+    pub host: Option<&'static str>,
+    /// Origin（None 表示不设置）
+    pub origin: Option<&'static str>,
+}
+
+/// 新浪实时行情（hq.sinajs.cn）
+const SINA_HQ: HeaderProfile = HeaderProfile {
+    referer: "https://vip.stock.finance.sina.com.cn/",
+    host: Some("hq.sinajs.cn"),
+    origin: None,
+};
+
+/// 新浪行情中心（品种列表、持仓排名、股票列表）
+const SINA_VIP: HeaderProfile = HeaderProfile {
+    referer: "https://vip.stock.finance.sina.com.cn/",
+    host: Some("vip.stock.finance.sina.com.cn"),
+    origin: None,
+};
+
+/// 新浪财经 K 线、合约详情、复权因子等
+const SINA_FINANCE: HeaderProfile = HeaderProfile {
+    referer: "https://finance.sina.com.cn/",
+    host: None,
+    origin: None,
+};
+
+/// 大商所
+const DCE: HeaderProfile = HeaderProfile {
+    referer: "http://www.dce.com.cn/",
+    host: None,
+    origin: Some("http://www.dce.com.cn"),
+};
+
+/// 上期所
+const SHFE: HeaderProfile = HeaderProfile {
+    referer: "https://www.shfe.com.cn/",
+    host: None,
+    origin: None,
+};
+
+/// 东方财富行情
+const EASTMONEY: HeaderProfile = HeaderProfile {
+    referer: "https://quote.eastmoney.com/",
+    host: None,
+    origin: None,
+};
+
+/// 已知上游数据源：(名称, 域名)，数据源状态看板按此顺序列出
+pub const KNOWN_SOURCES: &[(&str, &str)] = &[
+    ("新浪实时行情", "hq.sinajs.cn"),
+    ("新浪行情中心", "vip.stock.finance.sina.com.cn"),
+    ("新浪K线", "stock2.finance.sina.com.cn"),
+    ("新浪财经", "finance.sina.com.cn"),
+    ("新浪股票K线", "quotes.sina.cn"),
+    ("上期所", "www.shfe.com.cn"),
+    ("大商所", "www.dce.com.cn"),
+    ("郑商所", "www.czce.com.cn"),
+    ("中金所", "www.cffex.com.cn"),
+    ("广期所", "www.gfex.com.cn"),
+    ("100ppi", "www.100ppi.com"),
+    ("99期货网", "www.99qh.com"),
+    ("OpenCTP", "openctp.cn"),
+    ("9期货", "www.9qihuo.com"),
+    ("国泰君安期货", "www.gtjaqh.com"),
+    ("东方财富行情", "push2.eastmoney.com"),
+];
+
+/// 按域名查找数据源请求头配置，未配置的域名返回 None
+pub fn profile_for(url: &Url) -> Option<HeaderProfile> {
+    match url.host_str()? {
+        "hq.sinajs.cn" => Some(SINA_HQ),
+        "vip.stock.finance.sina.com.cn" => Some(SINA_VIP),
+        "stock2.finance.sina.com.cn" | "finance.sina.com.cn" | "quotes.sina.cn" => {
+            Some(SINA_FINANCE)
+        }
+        "www.dce.com.cn" => Some(DCE),
+        "www.shfe.com.cn" => Some(SHFE),
+        "push2.eastmoney.com" => Some(EASTMONEY),
+        _ => None,
+    }
+}
+
+/// 按目标 URL 补齐数据源请求头，已设置的请求头保持不变
+pub fn apply_source_headers(url: &Url, headers: &mut HeaderMap) {
+    let Some(profile) = profile_for(url) else {
+        return;
+    };
+
+    let mut insert = |name: HeaderName, value: &'static str| {
+        if !headers.contains_key(&name) {
+            headers.insert(name, HeaderValue::from_static(value));
+        }
+    };
+    insert(REFERER, profile.referer);
+    if let Some(host) = profile.host {
+        insert(HOST, host);
+    }
+    if let Some(origin) = profile.origin {
+        insert(ORIGIN, origin);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::futures::common::{SINA_FUTURES_LIST_API, SINA_FUTURES_REALTIME_API};
+
+    /// 按 send_limited 的方式构建请求并补齐请求头
+    fn prepared(builder: reqwest::RequestBuilder) -> reqwest::Request {
+        let mut request = builder.build().unwrap();
+        let url = request.url().clone();
+        apply_source_headers(&url, request.headers_mut());
+        request
+    }
+
+    fn header(request: &reqwest::Request, name: HeaderName) -> Option<&str> {
+        request.headers().get(name).map(|v| v.to_str().unwrap())
+    }
+
+    #[test]
+    fn realtime_and_list_requests_carry_their_own_profiles() {
+        let client = reqwest::Client::new();
+
+        let realtime = prepared(client.get(format!("{}/list=nf_RB0", SINA_FUTURES_REALTIME_API)));
+        assert_eq!(header(&realtime, HOST), Some("hq.sinajs.cn"));
+        assert_eq!(header(&realtime, REFERER), Some("https://vip.stock.finance.sina.com.cn/"));
+        assert_eq!(header(&realtime, ORIGIN), None);
+
+        let list = prepared(client.get(SINA_FUTURES_LIST_API).query(&[("node", "rb_qh")]));
+        assert_eq!(header(&list, HOST), Some("vip.stock.finance.sina.com.cn"));
+        assert_eq!(header(&list, REFERER), Some("https://vip.stock.finance.sina.com.cn/"));
+    }
+
+    #[test]
+    fn explicit_headers_win_and_unknown_hosts_are_untouched() {
+        let client = reqwest::Client::new();
+
+        let dce = prepared(
+            client
+                .get("http://www.dce.com.cn/dalianshangpin/xqsj/tjsj26/rtj/rcjccpm/index.html")
+                .header(REFERER, "http://www.dce.com.cn/custom"),
+        );
+        assert_eq!(header(&dce, REFERER), Some("http://www.dce.com.cn/custom"));
+        assert_eq!(header(&dce, ORIGIN), Some("http://www.dce.com.cn"));
+        assert_eq!(header(&dce, HOST), None);
+
+        let other = prepared(client.get("https://www.czce.com.cn/"));
+        assert!(other.headers().is_empty());
+    }
+}
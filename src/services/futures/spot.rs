@@ -1,76 +1,124 @@
 //! 现货价格及基差数据
 
-use crate::models::{FuturesSpotPrice, FuturesSpotPricePrevious};
+use crate::models::{parse_yyyymmdd, FuturesSpotPrice, FuturesSpotPricePrevious};
 use anyhow::{anyhow, Result};
-use reqwest::Client;
+use chrono::{Datelike, Duration, Weekday};
 
+use super::breaker::UpstreamUnavailable;
 use super::common::{
-    chinese_to_english, extract_contract_month, parse_basis_string, SPOT_PRICE_PREVIOUS_URL,
-    SPOT_PRICE_URL,
+    check_date_range, chinese_to_english, extract_contract_month, http_client, parse_basis_string,
+    SPOT_PRICE_PREVIOUS_URL, SPOT_PRICE_URL,
 };
+use super::limiter::{LimitedSend, RateLimited};
+use super::mock::fixture;
+
+/// 现货价格历史回溯的最大天数
+const SPOT_PREVIOUS_LOOKBACK_DAYS: i64 = 10;
+
+/// 页面正常返回但没有任何数据行，视为非交易日
+#[derive(Debug)]
+pub struct NonTradingDay {
+    pub date: String,
+}
+
+impl std::fmt::Display for NonTradingDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} 为非交易日，没有现货价格数据", self.date)
+    }
+}
+
+impl std::error::Error for NonTradingDay {}
 
 /// 获取期货现货价格及基差数据
 /// 对应 akshare 的 futures_spot_price() 函数
 /// 数据来源: https://www.100ppi.com/sf/
+///
+/// 页面不存在或数据表没有数据行时返回 NonTradingDay 错误；
+/// 按品种过滤后为空时仍返回空列表
 pub async fn get_futures_spot_price(
     date: &str,
     symbols: Option<Vec<&str>>,
 ) -> Result<Vec<FuturesSpotPrice>> {
-    use scraper::{Html, Selector};
-
-    let formatted_date = if date.len() == 8 {
-        format!("{}-{}-{}", &date[0..4], &date[4..6], &date[6..8])
-    } else {
-        date.to_string()
-    };
+    let formatted_date = parse_yyyymmdd(date)?.format("%Y-%m-%d").to_string();
+
+    if let Some(rows) = fixture::<Vec<FuturesSpotPrice>>("futures_spot_price") {
+        return Ok(rows?
+            .into_iter()
+            .filter(|row| {
+                symbols
+                    .as_ref()
+                    .is_none_or(|s| s.iter().any(|x| x.eq_ignore_ascii_case(&row.symbol)))
+            })
+            .map(|row| FuturesSpotPrice {
+                date: date.to_string(),
+                ..row
+            })
+            .collect());
+    }
 
     let url = format!("{}/day-{}.html", SPOT_PRICE_URL, formatted_date);
-    println!("📡 请求现货价格数据 URL: {}", url);
+    log::debug!("请求现货价格数据 URL: {}", url);
 
-    let client = Client::new();
+    let client = http_client();
     let response = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
         .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
-        .send()
+        .send_limited()
         .await?;
 
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(NonTradingDay {
+            date: date.replace('-', ""),
+        }
+        .into());
+    }
     if !response.status().is_success() {
         return Err(anyhow!("获取现货价格数据失败: {}", response.status()));
     }
 
     let text = response.text().await?;
-    let document = Html::parse_document(&text);
+    parse_spot_price_page(&text, date, symbols.as_deref())
+}
+
+/// 解析 100ppi 现货价格页面
+///
+/// 先由首列的品种名判断是否为请求的品种，未请求的行不再提取其余单元格文本、
+/// 解析价格和计算基差；非交易日判断仍按全部数据行计数
+fn parse_spot_price_page(
+    text: &str,
+    date: &str,
+    symbols: Option<&[&str]>,
+) -> Result<Vec<FuturesSpotPrice>> {
+    use scraper::{Html, Selector};
+
+    let document = Html::parse_document(text);
 
     let table_selector = Selector::parse("table#fdata").unwrap();
     let tr_selector = Selector::parse("tr").unwrap();
     let td_selector = Selector::parse("td").unwrap();
 
     let mut spot_prices = Vec::new();
+    let mut data_rows = 0usize;
 
-    let main_table = document.select(&table_selector).next();
-    if main_table.is_none() {
-        return Err(anyhow!("未找到数据表格(#fdata)"));
-    }
-
-    let main_table = main_table.unwrap();
-    let rows: Vec<_> = main_table.select(&tr_selector).collect();
-
-    for row in rows {
-        let cells: Vec<String> = row
-            .select(&td_selector)
-            .map(|cell| cell.text().collect::<Vec<_>>().join("").trim().to_string())
-            .collect();
+    let main_table = document
+        .select(&table_selector)
+        .next()
+        .ok_or_else(|| anyhow!("未找到数据表格(#fdata)"))?;
 
-        if cells.len() < 10 {
+    for row in main_table.select(&tr_selector) {
+        let tds: Vec<_> = row.select(&td_selector).collect();
+        if tds.len() < 10 {
             continue;
         }
+        let cell_text =
+            |i: usize| tds[i].text().collect::<Vec<_>>().join("").trim().to_string();
 
-        let first_cell = cells[0].replace('\u{a0}', "").trim().to_string();
+        let first_cell = cell_text(0).replace('\u{a0}', "").trim().to_string();
 
         if first_cell.contains("交易所") || first_cell == "商品" || first_cell.is_empty() {
             continue;
         }
+        data_rows += 1;
 
         let chinese_name = first_cell.trim();
         let symbol = match chinese_to_english(chinese_name) {
@@ -84,96 +132,99 @@ pub async fn get_futures_spot_price(
             }
         };
 
-        if let Some(ref filter_symbols) = symbols {
+        if let Some(filter_symbols) = symbols {
             if !filter_symbols.iter().any(|s| s.eq_ignore_ascii_case(&symbol)) {
                 continue;
             }
         }
 
-        let spot_price = cells
-            .get(1)
-            .map(|s| s.replace('\u{a0}', "").replace(",", ""))
-            .and_then(|s| s.trim().parse::<f64>().ok())
-            .unwrap_or(0.0);
-
-        if spot_price == 0.0 {
-            continue;
+        let cells: Vec<String> = (0..tds.len()).map(cell_text).collect();
+        if let Some(spot_price) = spot_price_row(&cells, symbol, date) {
+            spot_prices.push(spot_price);
         }
+    }
 
-        let near_contract_raw = cells.get(2).map(|s| s.replace('\u{a0}', "")).unwrap_or_default();
-        let near_contract_price = cells
-            .get(3)
-            .map(|s| s.replace('\u{a0}', "").replace(",", ""))
-            .and_then(|s| s.trim().parse::<f64>().ok())
-            .unwrap_or(0.0);
-
-        let dominant_contract_raw = cells.get(7).map(|s| s.replace('\u{a0}', "")).unwrap_or_default();
-        let dominant_contract_price = cells
-            .get(8)
-            .map(|s| s.replace('\u{a0}', "").replace(",", ""))
-            .and_then(|s| s.trim().parse::<f64>().ok())
-            .unwrap_or(0.0);
-
-        let near_month = extract_contract_month(&near_contract_raw);
-        let dominant_month = extract_contract_month(&dominant_contract_raw);
-
-        let near_contract = format!("{}{}", symbol.to_lowercase(), near_month);
-        let dominant_contract = format!("{}{}", symbol.to_lowercase(), dominant_month);
-
-        let near_basis = near_contract_price - spot_price;
-        let dom_basis = dominant_contract_price - spot_price;
+    if data_rows == 0 {
+        return Err(NonTradingDay {
+            date: date.replace('-', ""),
+        }
+        .into());
+    }
 
-        let near_basis_rate = if spot_price != 0.0 {
-            near_contract_price / spot_price - 1.0
-        } else {
-            0.0
-        };
+    log::debug!("解析到 {} 条现货价格数据", spot_prices.len());
+    Ok(spot_prices)
+}
 
-        let dom_basis_rate = if spot_price != 0.0 {
-            dominant_contract_price / spot_price - 1.0
-        } else {
-            0.0
-        };
+/// 由一行单元格文本计算现货价格和基差，现货价格缺失或为 0 时跳过该行
+fn spot_price_row(cells: &[String], symbol: String, date: &str) -> Option<FuturesSpotPrice> {
+    let spot_price = cells
+        .get(1)
+        .map(|s| s.replace('\u{a0}', "").replace(",", ""))
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .unwrap_or(0.0);
 
-        spot_prices.push(FuturesSpotPrice {
-            date: date.replace("-", ""),
-            symbol,
-            spot_price,
-            near_contract,
-            near_contract_price,
-            dominant_contract,
-            dominant_contract_price,
-            near_basis,
-            dom_basis,
-            near_basis_rate,
-            dom_basis_rate,
-        });
+    if spot_price == 0.0 {
+        return None;
     }
 
-    println!("📊 解析到 {} 条现货价格数据", spot_prices.len());
-    Ok(spot_prices)
+    let near_contract_raw = cells.get(2).map(|s| s.replace('\u{a0}', "")).unwrap_or_default();
+    let near_contract_price = cells
+        .get(3)
+        .map(|s| s.replace('\u{a0}', "").replace(",", ""))
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .unwrap_or(0.0);
+
+    let dominant_contract_raw = cells.get(7).map(|s| s.replace('\u{a0}', "")).unwrap_or_default();
+    let dominant_contract_price = cells
+        .get(8)
+        .map(|s| s.replace('\u{a0}', "").replace(",", ""))
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .unwrap_or(0.0);
+
+    let near_month = extract_contract_month(&near_contract_raw);
+    let dominant_month = extract_contract_month(&dominant_contract_raw);
+
+    let near_contract = format!("{}{}", symbol.to_lowercase(), near_month);
+    let dominant_contract = format!("{}{}", symbol.to_lowercase(), dominant_month);
+
+    let near_basis = near_contract_price - spot_price;
+    let dom_basis = dominant_contract_price - spot_price;
+
+    let near_basis_rate = near_contract_price / spot_price - 1.0;
+    let dom_basis_rate = dominant_contract_price / spot_price - 1.0;
+
+    Some(FuturesSpotPrice {
+        date: date.replace("-", ""),
+        symbol,
+        spot_price,
+        near_contract,
+        near_contract_price,
+        dominant_contract,
+        dominant_contract_price,
+        near_basis,
+        dom_basis,
+        near_basis_rate,
+        dom_basis_rate,
+    })
 }
 
 /// 获取期货现货价格及基差历史数据（包含180日统计）
 /// 对应 akshare 的 futures_spot_price_previous() 函数
 pub async fn get_futures_spot_price_previous(date: &str) -> Result<Vec<FuturesSpotPricePrevious>> {
-    use scraper::{Html, Selector};
+    let formatted_date = parse_yyyymmdd(date)?.format("%Y-%m-%d").to_string();
 
-    let formatted_date = if date.len() == 8 {
-        format!("{}-{}-{}", &date[0..4], &date[4..6], &date[6..8])
-    } else {
-        date.to_string()
-    };
+    if let Some(rows) = fixture::<Vec<FuturesSpotPricePrevious>>("futures_spot_price_previous") {
+        return rows;
+    }
 
     let url = format!("{}/day-{}.html", SPOT_PRICE_PREVIOUS_URL, formatted_date);
-    println!("📡 请求现货价格历史数据 URL: {}", url);
+    log::debug!("请求现货价格历史数据 URL: {}", url);
 
-    let client = Client::new();
+    let client = http_client();
     let response = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
         .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -181,7 +232,14 @@ pub async fn get_futures_spot_price_previous(date: &str) -> Result<Vec<FuturesSp
     }
 
     let text = response.text().await?;
-    let document = Html::parse_document(&text);
+    parse_spot_price_previous_page(&text)
+}
+
+/// 解析 100ppi 现货价格历史页面（含 180 日基差统计）
+fn parse_spot_price_previous_page(text: &str) -> Result<Vec<FuturesSpotPricePrevious>> {
+    use scraper::{Html, Selector};
+
+    let document = Html::parse_document(text);
 
     let table_selector = Selector::parse("table#fdata").unwrap();
     let tr_selector = Selector::parse("tr").unwrap();
@@ -262,15 +320,84 @@ pub async fn get_futures_spot_price_previous(date: &str) -> Result<Vec<FuturesSp
             basis_180d_high,
             basis_180d_low,
             basis_180d_avg,
+            basis_percentile: basis_percentile(basis, basis_180d_high, basis_180d_low),
         });
     }
 
-    println!("📊 解析到 {} 条现货价格历史数据", spot_prices.len());
+    log::debug!("解析到 {} 条现货价格历史数据", spot_prices.len());
     Ok(spot_prices)
 }
 
+/// 获取指定日期或之前最近一个有数据的交易日的现货价格历史数据
+///
+/// 从 date 起向前最多回溯 SPOT_PREVIOUS_LOOKBACK_DAYS 天，跳过周末，返回实际命中的日期和数据；
+/// 上游熔断或新浪封禁冷却时直接返回错误，不再继续回溯
+pub async fn get_latest_spot_price_previous(
+    date: &str,
+) -> Result<(String, Vec<FuturesSpotPricePrevious>)> {
+    let start = parse_yyyymmdd(date)?;
+
+    for offset in 0..SPOT_PREVIOUS_LOOKBACK_DAYS {
+        let day = start - Duration::days(offset);
+        if matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+            continue;
+        }
+        let day = day.format("%Y%m%d").to_string();
+        match get_futures_spot_price_previous(&day).await {
+            Ok(data) if !data.is_empty() => {
+                if offset > 0 {
+                    log::info!("{} 无现货价格历史数据，已回溯至 {}", date, day);
+                }
+                return Ok((day, data));
+            }
+            Ok(_) => log::debug!("{} 现货价格历史数据为空，继续回溯", day),
+            Err(e)
+                if e
+                    .chain()
+                    .any(|c| c.is::<UpstreamUnavailable>() || c.is::<RateLimited>()) =>
+            {
+                return Err(e);
+            }
+            Err(e) => log::debug!("{} 现货价格历史数据获取失败，继续回溯: {}", day, e),
+        }
+    }
+
+    Err(anyhow!(
+        "{} 及之前 {} 天没有现货价格历史数据",
+        date,
+        SPOT_PREVIOUS_LOOKBACK_DAYS - 1
+    ))
+}
+
+/// 按商品名称关键字过滤现货价格历史数据，关键字为空时不过滤
+pub fn filter_spot_price_previous(
+    data: Vec<FuturesSpotPricePrevious>,
+    commodity: Option<&str>,
+) -> Vec<FuturesSpotPricePrevious> {
+    match commodity.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(keyword) => data
+            .into_iter()
+            .filter(|item| item.commodity.contains(keyword))
+            .collect(),
+        None => data,
+    }
+}
+
+/// 计算当前基差在 180 日高低区间中的位置
+///
+/// (basis - low) / (high - low)，结果截断到 [0,1]；高低缺失或 high <= low 时返回 None
+fn basis_percentile(basis: f64, high: Option<f64>, low: Option<f64>) -> Option<f64> {
+    let (high, low) = (high?, low?);
+    if high <= low {
+        return None;
+    }
+    Some(((basis - low) / (high - low)).clamp(0.0, 1.0))
+}
+
 /// 获取期货现货价格日线数据（日期范围）
 /// 对应 akshare 的 futures_spot_price_daily() 函数
+///
+/// 非交易日跳过，其他错误直接返回
 pub async fn get_futures_spot_price_daily(
     start_date: &str,
     end_date: &str,
@@ -286,8 +413,9 @@ pub async fn get_futures_spot_price_daily(
     if start > end {
         return Err(anyhow!("开始日期不能大于结束日期"));
     }
+    check_date_range(start, end)?;
 
-    println!("📡 获取现货价格日线数据: {} 至 {}", start_date, end_date);
+    log::debug!("获取现货价格日线数据: {} 至 {}", start_date, end_date);
 
     let mut all_data = Vec::new();
     let mut current = start;
@@ -296,19 +424,150 @@ pub async fn get_futures_spot_price_daily(
         let date_str = current.format("%Y%m%d").to_string();
 
         match get_futures_spot_price(&date_str, symbols.clone()).await {
-            Ok(data) => {
-                if !data.is_empty() {
-                    all_data.extend(data);
-                }
-            }
-            Err(e) => {
-                println!("  ⚠️ {} 数据获取失败（可能是非交易日）: {}", date_str, e);
+            Ok(data) => all_data.extend(data),
+            Err(e) if e.is::<NonTradingDay>() => {
+                log::debug!("{} 为非交易日，跳过", date_str);
             }
+            Err(e) => return Err(e.context(format!("{} 现货价格获取失败", date_str))),
         }
 
         current = current.succ_opt().unwrap_or(current);
     }
 
-    println!("📊 共获取 {} 条现货价格日线数据", all_data.len());
+    log::debug!("共获取 {} 条现货价格日线数据", all_data.len());
     Ok(all_data)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basis_percentile_mid_range() {
+        assert_eq!(basis_percentile(150.0, Some(200.0), Some(100.0)), Some(0.5));
+        assert_eq!(basis_percentile(-25.0, Some(0.0), Some(-100.0)), Some(0.75));
+    }
+
+    #[test]
+    fn basis_percentile_at_and_beyond_extremes() {
+        assert_eq!(basis_percentile(100.0, Some(200.0), Some(100.0)), Some(0.0));
+        assert_eq!(basis_percentile(200.0, Some(200.0), Some(100.0)), Some(1.0));
+        // 上游 180 日统计不含当日时，当前基差可能超出区间
+        assert_eq!(basis_percentile(50.0, Some(200.0), Some(100.0)), Some(0.0));
+        assert_eq!(basis_percentile(260.0, Some(200.0), Some(100.0)), Some(1.0));
+    }
+
+    #[test]
+    fn basis_percentile_degenerate_range() {
+        assert_eq!(basis_percentile(100.0, Some(100.0), Some(100.0)), None);
+        assert_eq!(basis_percentile(100.0, Some(90.0), Some(110.0)), None);
+        assert_eq!(basis_percentile(100.0, None, Some(90.0)), None);
+        assert_eq!(basis_percentile(100.0, Some(110.0), None), None);
+    }
+
+    #[test]
+    fn empty_day_page_is_non_trading_day() {
+        let html = include_str!("../../../tests/fixtures/100ppi_sf_empty_day.html");
+        let err = parse_spot_price_page(html, "20240106", None).unwrap_err();
+        assert_eq!(err.downcast_ref::<NonTradingDay>().unwrap().date, "20240106");
+    }
+
+    #[test]
+    fn missing_table_is_parse_failure_not_non_trading_day() {
+        let err = parse_spot_price_page("<html><body></body></html>", "20240105", None).unwrap_err();
+        assert!(!err.is::<NonTradingDay>());
+    }
+
+    #[test]
+    fn trading_day_page_parses_rows_and_symbol_filter_may_be_empty() {
+        let html = include_str!("../../../tests/fixtures/100ppi_sf_day.html");
+
+        let rows = parse_spot_price_page(html, "2024-01-05", None).unwrap();
+        let symbols: Vec<&str> = rows.iter().map(|r| r.symbol.as_str()).collect();
+        assert_eq!(symbols, ["CU", "RB"]);
+        assert_eq!(rows[0].date, "20240105");
+        assert_eq!(rows[0].spot_price, 68960.0);
+        assert_eq!(rows[0].dominant_contract, "cu2402");
+        assert_eq!(rows[0].dom_basis, -60.0);
+
+        let rows = parse_spot_price_page(html, "20240105", Some(&["rb"])).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].symbol, "RB");
+
+        // 有数据行但过滤后为空，不是非交易日
+        assert!(parse_spot_price_page(html, "20240105", Some(&["AU"])).unwrap().is_empty());
+    }
+
+    /// 生成含多个品种的现货价格页面，每行 11 列，与 100ppi 页面布局一致
+    fn spot_page(names: &[&str]) -> String {
+        let rows: String = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let spot = 1000.0 * (i + 1) as f64;
+                format!(
+                    "<tr><td>{}</td><td>{:.2}</td><td>2501</td><td>{}</td><td>-10.00</td><td>-1.00%</td>\
+                     <td>&nbsp;</td><td>2505</td><td>{}</td><td>5.00</td><td>0.50%</td></tr>",
+                    name,
+                    spot,
+                    spot - 10.0,
+                    spot + 5.0
+                )
+            })
+            .collect();
+        format!("<table id=\"fdata\"><tr><td colspan=\"11\">上海期货交易所</td></tr>{}</table>", rows)
+    }
+
+    #[test]
+    fn symbol_filter_matches_full_parse_then_filter() {
+        let names = ["铜", "铝", "螺纹钢", "黄金", "豆粕", "PTA", "甲醇", "未知品种", "SC"];
+        let html = spot_page(&names);
+        let full = parse_spot_price_page(&html, "20250103", None).unwrap();
+        assert_eq!(full.len(), 8);
+
+        let filters: [&[&str]; 5] = [&["CU"], &["rb", "AL"], &["TA", "m", "MA"], &["SC"], &["ZZ"]];
+        for filter in filters {
+            let expected: Vec<_> = full
+                .iter()
+                .filter(|row| filter.iter().any(|s| s.eq_ignore_ascii_case(&row.symbol)))
+                .collect();
+            let filtered = parse_spot_price_page(&html, "20250103", Some(filter)).unwrap();
+            assert_eq!(
+                serde_json::to_value(&filtered).unwrap(),
+                serde_json::to_value(&expected).unwrap(),
+                "{:?}",
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn previous_page_parses_basis_and_180_day_stats() {
+        let html = include_str!("../../../tests/fixtures/100ppi_sf2_day.html");
+        let rows = parse_spot_price_previous_page(html).unwrap();
+
+        // 现货价格缺失的线材行跳过
+        let names: Vec<&str> = rows.iter().map(|r| r.commodity.as_str()).collect();
+        assert_eq!(names, ["铜", "螺纹钢", "豆粕"]);
+
+        let cu = &rows[0];
+        assert_eq!((cu.spot_price, cu.dominant_contract.as_str(), cu.dominant_price), (68960.0, "cu2402", 68900.0));
+        assert_eq!((cu.basis, cu.basis_rate), (-60.0, -0.09));
+        assert_eq!((cu.basis_180d_high, cu.basis_180d_low, cu.basis_180d_avg), (Some(1250.0), Some(-410.0), Some(320.5)));
+        assert!((cu.basis_percentile.unwrap() - 350.0 / 1660.0).abs() < 1e-12);
+
+        assert_eq!((rows[1].basis, rows[1].basis_rate, rows[1].basis_percentile), (20.0, 0.5, Some(0.6)));
+        // 180 日高低相同时分位为空
+        assert_eq!(rows[2].basis_percentile, None);
+
+        let filtered = filter_spot_price_previous(rows.clone(), Some(" 螺纹 "));
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].commodity, "螺纹钢");
+        assert_eq!(filter_spot_price_previous(rows, Some("")).len(), 3);
+    }
+
+    #[test]
+    fn previous_page_without_table_fails() {
+        assert!(parse_spot_price_previous_page("<html><body>维护中</body></html>").is_err());
+    }
+}
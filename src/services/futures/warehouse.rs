@@ -3,13 +3,18 @@
 //! 提供各交易所仓单日报数据的获取和处理
 
 use anyhow::{anyhow, Result};
-use reqwest::Client;
-use std::collections::{HashMap, HashSet};
+use chrono::Datelike;
+use std::collections::{BTreeMap, BTreeSet};
 
+use super::common::{
+    fetch_czce_sheet, http_client, CzceFileMissing, http_client_builder, insecure_http_client, UpstreamBlocked,
+};
+use super::availability::ensure_published;
+use super::limiter::LimitedSend;
 use crate::models::{
-    CzceWarehouseReceipt, CzceWarehouseReceiptResponse, DceWarehouseReceipt,
+    parse_yyyymmdd, CzceWarehouseReceipt, CzceWarehouseReceiptResponse, DceWarehouseReceipt,
     GfexWarehouseReceipt, GfexWarehouseReceiptResponse, ShfeWarehouseReceipt,
-    ShfeWarehouseReceiptResponse,
+    ShfeWarehouseReceiptResponse, WarehouseReceiptTotal,
 };
 
 /// 郑州商品交易所-交易数据-仓单日报
@@ -20,59 +25,19 @@ use crate::models::{
 pub async fn futures_warehouse_receipt_czce(
     date: &str,
 ) -> Result<Vec<CzceWarehouseReceiptResponse>> {
-    let client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .timeout(std::time::Duration::from_secs(30))
-        .build()?;
-
     let date_num: i32 = date.parse().unwrap_or(0);
-    let url = if date_num > 20251101 {
-        format!(
-            "http://www.czce.com.cn/cn/DFSStaticFiles/Future/{}/{}/FutureDataWhsheet.xlsx",
-            &date[0..4],
-            date
-        )
-    } else {
-        format!(
-            "http://www.czce.com.cn/cn/DFSStaticFiles/Future/{}/{}/FutureDataWhsheet.xls",
-            &date[0..4],
-            date
-        )
+    let url_stem = format!(
+        "http://www.czce.com.cn/cn/DFSStaticFiles/Future/{}/{}/FutureDataWhsheet",
+        parse_yyyymmdd(date)?.year(),
+        date
+    );
+    let client = insecure_http_client(&url_stem)?;
+    let range = match fetch_czce_sheet(&client, &url_stem, date_num > 20251101).await {
+        Ok(range) => range,
+        Err(e) if e.is::<CzceFileMissing>() => return ensure_published(date, Vec::new()),
+        Err(e) => return Err(anyhow!("获取郑商所仓单日报数据失败: {}", e)),
     };
 
-    println!("📡 请求郑商所仓单日报数据 URL: {}", url);
-
-    let response = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(anyhow!(
-            "获取郑商所仓单日报数据失败: {}，可能是非交易日",
-            response.status()
-        ));
-    }
-
-    let bytes = response.bytes().await?;
-
-    use calamine::{open_workbook_auto_from_rs, Reader};
-    use std::io::Cursor;
-
-    let cursor = Cursor::new(bytes.as_ref());
-    let mut workbook =
-        open_workbook_auto_from_rs(cursor).map_err(|e| anyhow!("打开Excel文件失败: {}", e))?;
-
-    let sheet_names = workbook.sheet_names().to_vec();
-    if sheet_names.is_empty() {
-        return Err(anyhow!("Excel文件没有工作表"));
-    }
-
-    let range = workbook
-        .worksheet_range(&sheet_names[0])
-        .map_err(|e| anyhow!("读取工作表失败: {}", e))?;
-
     let mut rows: Vec<Vec<String>> = Vec::new();
     for row in range.rows() {
         let row_data: Vec<String> = row
@@ -130,6 +95,12 @@ pub async fn futures_warehouse_receipt_czce(
             continue;
         }
 
+        let unit = rows[start_idx..header_idx]
+            .iter()
+            .flatten()
+            .find_map(|cell| extract_unit(cell))
+            .unwrap_or_else(|| warehouse_receipt_unit("CZCE", &symbol).to_string());
+
         let mut data: Vec<CzceWarehouseReceipt> = Vec::new();
         for row in rows.iter().take(end_idx).skip(header_idx + 1) {
             if row.is_empty()
@@ -146,11 +117,11 @@ pub async fn futures_warehouse_receipt_czce(
             }
 
             let parse_num = |s: &str| -> Option<i64> {
-                let cleaned = s.trim().replace(",", "").replace("-", "");
-                if cleaned.is_empty() {
+                let cleaned = s.trim();
+                if cleaned.is_empty() || cleaned == "-" {
                     None
                 } else {
-                    cleaned.parse().ok()
+                    cleaned.replace(',', "").parse().ok()
                 }
             };
 
@@ -163,6 +134,7 @@ pub async fn futures_warehouse_receipt_czce(
                 warehouse_receipt,
                 valid_forecast,
                 change,
+                unit: unit.clone(),
             });
         }
 
@@ -173,7 +145,7 @@ pub async fn futures_warehouse_receipt_czce(
 
     result.sort_by(|a, b| a.symbol.cmp(&b.symbol));
 
-    println!("📊 解析到 {} 个品种的仓单日报数据", result.len());
+    log::debug!("解析到 {} 个品种的仓单日报数据", result.len());
     Ok(result)
 }
 
@@ -191,15 +163,13 @@ fn extract_letters(s: &str) -> String {
 ///
 /// date: 交易日期，格式 YYYYMMDD
 pub async fn futures_warehouse_receipt_dce(date: &str) -> Result<Vec<DceWarehouseReceipt>> {
-    let client = Client::builder()
+    let client = http_client_builder()
         .cookie_store(true)
-        .timeout(std::time::Duration::from_secs(30))
         .build()?;
 
     let _home_resp = client
         .get("http://www.dce.com.cn/dalianshangpin/xqsj/tjsj26/rtj/cdrb/index.html")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .send()
+        .send_limited()
         .await;
 
     let url = "http://www.dce.com.cn/dcereport/publicweb/dailystat/wbillWeeklyQuotes";
@@ -209,25 +179,24 @@ pub async fn futures_warehouse_receipt_dce(date: &str) -> Result<Vec<DceWarehous
         "varietyId": "all"
     });
 
-    println!("📡 请求大商所仓单日报数据 URL: {}", url);
+    log::debug!("请求大商所仓单日报数据 URL: {}", url);
 
     let response = client
         .post(url)
         .json(&payload)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
         .header("Accept", "application/json, text/plain, */*")
-        .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
-        .header("Origin", "http://www.dce.com.cn")
         .header("Referer", "http://www.dce.com.cn/dalianshangpin/xqsj/tjsj26/rtj/cdrb/index.html")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
         if response.status().as_u16() == 412 {
-            return Err(anyhow!(
-                "大商所API访问被拒绝(412)，该交易所有反爬虫机制。\n\
-                建议: 1) 稍后重试 2) 使用浏览器手动查看数据"
-            ));
+            return Err(UpstreamBlocked {
+                source: "大商所",
+                status: 412,
+                suggestion: "1) 稍后重试 2) 使用浏览器手动查看数据",
+            }
+            .into());
         }
         return Err(anyhow!(
             "获取大商所仓单日报数据失败: {}，可能是非交易日",
@@ -265,6 +234,9 @@ pub async fn futures_warehouse_receipt_dce(date: &str) -> Result<Vec<DceWarehous
             .or_else(|| item["diff"].as_str().and_then(|s| s.parse().ok()))
             .unwrap_or(0);
 
+        let unit = source_unit(item)
+            .unwrap_or_else(|| warehouse_receipt_unit("DCE", &variety_code).to_string());
+
         result.push(DceWarehouseReceipt {
             variety_code,
             variety_name,
@@ -273,10 +245,11 @@ pub async fn futures_warehouse_receipt_dce(date: &str) -> Result<Vec<DceWarehous
             last_receipt,
             today_receipt,
             change,
+            unit,
         });
     }
 
-    println!("📊 解析到 {} 条仓单日报数据", result.len());
+    log::debug!("解析到 {} 条仓单日报数据", result.len());
     Ok(result)
 }
 
@@ -288,20 +261,18 @@ pub async fn futures_warehouse_receipt_dce(date: &str) -> Result<Vec<DceWarehous
 pub async fn futures_shfe_warehouse_receipt(
     date: &str,
 ) -> Result<Vec<ShfeWarehouseReceiptResponse>> {
-    let client = Client::new();
+    let client = http_client();
 
     let url = format!(
         "https://www.shfe.com.cn/data/tradedata/future/dailydata/{}dailystock.dat",
         date
     );
 
-    println!("📡 请求上期所仓单日报 URL: {}", url);
+    log::debug!("请求上期所仓单日报 URL: {}", url);
 
     let response = client
         .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .header("Referer", "https://www.shfe.com.cn/")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -317,7 +288,7 @@ pub async fn futures_shfe_warehouse_receipt(
         .as_array()
         .ok_or_else(|| anyhow!("未找到o_cursor数据"))?;
 
-    let mut grouped: HashMap<String, Vec<ShfeWarehouseReceipt>> = HashMap::new();
+    let mut grouped: BTreeMap<String, Vec<ShfeWarehouseReceipt>> = BTreeMap::new();
 
     for item in o_cursor {
         let var_name = item["VARNAME"]
@@ -386,7 +357,7 @@ pub async fn futures_shfe_warehouse_receipt(
 
     result.sort_by(|a, b| a.symbol.cmp(&b.symbol));
 
-    println!("📊 解析到 {} 个品种的仓单日报数据", result.len());
+    log::debug!("解析到 {} 个品种的仓单日报数据", result.len());
     Ok(result)
 }
 
@@ -398,20 +369,19 @@ pub async fn futures_shfe_warehouse_receipt(
 pub async fn futures_gfex_warehouse_receipt(
     date: &str,
 ) -> Result<Vec<GfexWarehouseReceiptResponse>> {
-    let client = Client::new();
+    let client = http_client();
 
     let url = "http://www.gfex.com.cn/u/interfacesWebTdWbillWeeklyQuotes/loadList";
 
     let payload = [("gen_date", date)];
 
-    println!("📡 请求广期所仓单日报数据 URL: {}", url);
+    log::debug!("请求广期所仓单日报数据 URL: {}", url);
 
     let response = client
         .post(url)
         .form(&payload)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
         .header("Content-Type", "application/x-www-form-urlencoded")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -427,7 +397,7 @@ pub async fn futures_gfex_warehouse_receipt(
         .as_array()
         .ok_or_else(|| anyhow!("未找到data数组"))?;
 
-    let mut symbol_set: HashSet<String> = HashSet::new();
+    let mut symbol_set: BTreeSet<String> = BTreeSet::new();
     for item in data_array {
         if let Some(symbol) = item["varietyOrder"].as_str() {
             if !symbol.is_empty() {
@@ -471,12 +441,16 @@ pub async fn futures_gfex_warehouse_receipt(
                 .or_else(|| item["regWbillQty"].as_str().and_then(|s| s.parse().ok()))
                 .unwrap_or(0);
 
+            let unit = source_unit(item)
+                .unwrap_or_else(|| warehouse_receipt_unit("GFEX", &symbol).to_string());
+
             data.push(GfexWarehouseReceipt {
                 variety,
                 warehouse,
                 last_receipt,
                 today_receipt,
                 change,
+                unit,
             });
         }
 
@@ -487,6 +461,212 @@ pub async fn futures_gfex_warehouse_receipt(
 
     result.sort_by(|a, b| a.symbol.cmp(&b.symbol));
 
-    println!("📊 解析到 {} 个品种的仓单日报数据", result.len());
+    log::debug!("解析到 {} 个品种的仓单日报数据", result.len());
     Ok(result)
 }
+
+// ==================== 按品种汇总 ====================
+
+/// 郑商所仓单按品种汇总，昨日仓单由今日仓单减去增减推算，缺失的数量按 0 计
+pub fn aggregate_czce_receipts(data: &[CzceWarehouseReceiptResponse]) -> Vec<WarehouseReceiptTotal> {
+    sum_by_variety(data.iter().flat_map(|group| {
+        group.data.iter().map(move |r| {
+            let today = r.warehouse_receipt.unwrap_or(0);
+            let change = r.change.unwrap_or(0);
+            (group.symbol.as_str(), r.warehouse.as_str(), today, today - change, change)
+        })
+    }))
+}
+
+/// 大商所仓单按品种代码汇总
+pub fn aggregate_dce_receipts(data: &[DceWarehouseReceipt]) -> Vec<WarehouseReceiptTotal> {
+    sum_by_variety(data.iter().map(|r| {
+        (r.variety_code.as_str(), r.warehouse.as_str(), r.today_receipt, r.last_receipt, r.change)
+    }))
+}
+
+/// 上期所仓单按品种名称汇总
+pub fn aggregate_shfe_receipts(data: &[ShfeWarehouseReceiptResponse]) -> Vec<WarehouseReceiptTotal> {
+    sum_by_variety(data.iter().flat_map(|group| {
+        group.data.iter().map(move |r| {
+            (group.symbol.as_str(), r.warehouse.as_str(), r.today_receipt, r.last_receipt, r.change)
+        })
+    }))
+}
+
+/// 广期所仓单按品种代码汇总
+pub fn aggregate_gfex_receipts(data: &[GfexWarehouseReceiptResponse]) -> Vec<WarehouseReceiptTotal> {
+    sum_by_variety(data.iter().flat_map(|group| {
+        group.data.iter().map(move |r| {
+            (group.symbol.as_str(), r.warehouse.as_str(), r.today_receipt, r.last_receipt, r.change)
+        })
+    }))
+}
+
+/// 按品种累加 (品种, 仓库, 今日, 昨日, 增减)，结果按品种排序
+///
+/// 上游表格中的合计、小计、总计行会重复计数，汇总时跳过
+fn sum_by_variety<'a>(
+    rows: impl Iterator<Item = (&'a str, &'a str, i64, i64, i64)>,
+) -> Vec<WarehouseReceiptTotal> {
+    let mut totals: BTreeMap<&str, WarehouseReceiptTotal> = BTreeMap::new();
+    for (variety, warehouse, today, last, change) in rows {
+        if ["合计", "小计", "总计"].iter().any(|t| warehouse.contains(t)) {
+            continue;
+        }
+        let total = totals.entry(variety).or_insert_with(|| WarehouseReceiptTotal {
+            variety: variety.to_string(),
+            total_today: 0,
+            total_last: 0,
+            total_change: 0,
+            warehouse_count: 0,
+        });
+        total.total_today += today;
+        total.total_last += last;
+        total.total_change += change;
+        total.warehouse_count += 1;
+    }
+    totals.into_values().collect()
+}
+
+// ==================== 仓单单位 ====================
+
+/// 各交易所仓单数量单位，品种为 "*" 表示该交易所默认单位
+///
+/// 仅在数据源未给出单位时使用
+const WAREHOUSE_RECEIPT_UNITS: &[(&str, &str, &str)] = &[
+    ("CZCE", "*", "张"),
+    ("DCE", "*", "手"),
+    ("GFEX", "SI", "手"),
+    ("GFEX", "LC", "手"),
+    ("GFEX", "PS", "手"),
+];
+
+/// 查询仓单数量单位，未知时返回空字符串
+fn warehouse_receipt_unit(exchange: &str, variety: &str) -> &'static str {
+    let lookup = |v: &str| {
+        WAREHOUSE_RECEIPT_UNITS
+            .iter()
+            .find(|(ex, var, _)| *ex == exchange && var.eq_ignore_ascii_case(v))
+            .map(|(_, _, unit)| *unit)
+    };
+    lookup(variety).or_else(|| lookup("*")).unwrap_or("")
+}
+
+/// 从数据源记录中读取单位字段
+fn source_unit(item: &serde_json::Value) -> Option<String> {
+    ["unit", "UNIT", "wbillUnit"]
+        .iter()
+        .filter_map(|key| item[*key].as_str())
+        .map(str::trim)
+        .find(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// 从表头文本中提取单位，如 "单位：张" 或 "（单位:手）"
+fn extract_unit(cell: &str) -> Option<String> {
+    let rest = &cell[cell.find("单位")? + "单位".len()..];
+    let rest = rest.trim_start_matches([':', '：', ' ']);
+    let unit: String = rest
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !matches!(c, ')' | '）' | ',' | '，' | ';' | '；'))
+        .collect();
+    (!unit.is_empty()).then_some(unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dce(variety: &str, warehouse: &str, last: i64, today: i64) -> DceWarehouseReceipt {
+        DceWarehouseReceipt {
+            variety_code: variety.to_string(),
+            variety_name: String::new(),
+            warehouse: warehouse.to_string(),
+            delivery_location: None,
+            last_receipt: last,
+            today_receipt: today,
+            change: today - last,
+            unit: "手".to_string(),
+        }
+    }
+
+    fn total(variety: &str, today: i64, last: i64, change: i64, count: usize) -> WarehouseReceiptTotal {
+        WarehouseReceiptTotal {
+            variety: variety.to_string(),
+            total_today: today,
+            total_last: last,
+            total_change: change,
+            warehouse_count: count,
+        }
+    }
+
+    #[test]
+    fn dce_receipts_sum_by_variety_and_skip_total_rows() {
+        let rows = vec![
+            dce("M", "中储粮镇江", 100, 120),
+            dce("A", "北良港", 50, 40),
+            dce("M", "中粮东莞", 30, 30),
+            dce("M", "豆粕小计", 130, 150),
+            dce("", "总计", 180, 190),
+        ];
+        assert_eq!(
+            aggregate_dce_receipts(&rows),
+            [total("A", 40, 50, -10, 1), total("M", 150, 130, 20, 2)]
+        );
+    }
+
+    #[test]
+    fn czce_total_last_is_derived_from_change() {
+        let receipt = |warehouse: &str, today: Option<i64>, change: Option<i64>| CzceWarehouseReceipt {
+            warehouse: warehouse.to_string(),
+            warehouse_receipt: today,
+            valid_forecast: None,
+            change,
+            unit: "张".to_string(),
+        };
+        let data = vec![CzceWarehouseReceiptResponse {
+            symbol: "SR".to_string(),
+            data: vec![
+                receipt("中粮屯河", Some(1000), Some(-50)),
+                receipt("广西糖网", Some(200), None),
+                receipt("合计", Some(1200), Some(-50)),
+            ],
+        }];
+        assert_eq!(aggregate_czce_receipts(&data), [total("SR", 1200, 1250, -50, 2)]);
+    }
+
+    #[test]
+    fn aggregate_of_nothing_is_empty() {
+        assert!(aggregate_dce_receipts(&[]).is_empty());
+        assert!(aggregate_shfe_receipts(&[]).is_empty());
+    }
+
+    #[test]
+    fn unit_table_covers_known_varieties_and_defaults_to_empty() {
+        assert_eq!(warehouse_receipt_unit("CZCE", "SR"), "张");
+        assert_eq!(warehouse_receipt_unit("CZCE", "TA"), "张");
+        assert_eq!(warehouse_receipt_unit("DCE", "m"), "手");
+        assert_eq!(warehouse_receipt_unit("GFEX", "lc"), "手");
+        assert_eq!(warehouse_receipt_unit("GFEX", "SI"), "手");
+        assert_eq!(warehouse_receipt_unit("GFEX", "XX"), "");
+        assert_eq!(warehouse_receipt_unit("SHFE", "CU"), "");
+    }
+
+    #[test]
+    fn source_unit_wins_over_table() {
+        let item = serde_json::json!({ "wbillUnit": " 吨 " });
+        assert_eq!(source_unit(&item).as_deref(), Some("吨"));
+        assert_eq!(source_unit(&serde_json::json!({ "unit": "" })), None);
+        assert_eq!(source_unit(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn unit_is_extracted_from_czce_header_text() {
+        assert_eq!(extract_unit("品种：白糖SR     单位：张").as_deref(), Some("张"));
+        assert_eq!(extract_unit("（单位:手）").as_deref(), Some("手"));
+        assert_eq!(extract_unit("单位： 吨，日期"), Some("吨".to_string()));
+        assert_eq!(extract_unit("品种：白糖SR"), None);
+        assert_eq!(extract_unit("单位："), None);
+    }
+}
@@ -0,0 +1,196 @@
+//! 启动缓存预热
+//!
+//! 开启 startup.warm_cache 时，服务启动后在后台获取品种映射、99期货网品种映射和 OpenCTP
+//! 交易费用表，写入进程内缓存，避免首个请求等待上游。预热失败只记录警告，不影响启动。
+//!
+//! 配置 startup.symbol_mark_refresh_secs 后另在后台定期刷新品种映射，赶在缓存过期前完成，
+//! 前台请求始终命中缓存；刷新失败保留原缓存
+
+use anyhow::Result;
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use super::common::get_beijing_time;
+use super::fees::get_futures_fees_info;
+use super::inventory::get_99_symbol_map;
+use super::mock::mock_enabled;
+use super::sina::{FuturesService, SYMBOL_MARK_TTL};
+
+/// 后台刷新距离品种映射缓存过期的最小提前量
+const SYMBOL_MARK_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// 单项缓存的预热结果
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheWarmupItem {
+    /// 缓存名称：symbol_marks / symbols_99 / fees
+    pub name: &'static str,
+    /// 是否预热成功
+    pub ok: bool,
+    /// 预热后的记录数
+    pub count: usize,
+    /// 失败原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 缓存预热状态（GET /metrics 的 cache_warmup）
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheWarmupStats {
+    /// 开始时间（北京时间）
+    pub started_at: String,
+    /// 完成时间，预热进行中为 None
+    pub finished_at: Option<String>,
+    /// 耗时（毫秒），预热进行中为 None
+    pub elapsed_ms: Option<u64>,
+    /// 各项缓存的预热结果，预热进行中为空
+    pub items: Vec<CacheWarmupItem>,
+}
+
+/// 最近一次预热的状态，未开启预热时为 None
+static WARMUP_STATS: OnceLock<Mutex<Option<CacheWarmupStats>>> = OnceLock::new();
+
+fn stats_slot() -> &'static Mutex<Option<CacheWarmupStats>> {
+    WARMUP_STATS.get_or_init(|| Mutex::new(None))
+}
+
+/// 当前缓存预热状态，未开启预热时返回 None
+pub fn cache_warmup_stats() -> Option<CacheWarmupStats> {
+    stats_slot().lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// 在后台启动缓存预热，立即返回，不阻塞服务启动
+pub fn spawn_cache_warmup() {
+    tokio::spawn(warm_caches());
+}
+
+/// 在后台定期刷新品种映射缓存
+///
+/// 间隔超过缓存有效期减去提前量时按该上限执行，保证刷新总在过期前完成
+pub fn spawn_symbol_mark_refresher(interval_secs: u64) {
+    let max_interval = SYMBOL_MARK_TTL - SYMBOL_MARK_REFRESH_MARGIN;
+    let mut interval = Duration::from_secs(interval_secs);
+    if interval > max_interval {
+        log::warn!(
+            "品种映射刷新间隔 {} 秒超过缓存有效期，按 {} 秒执行",
+            interval_secs,
+            max_interval.as_secs()
+        );
+        interval = max_interval;
+    }
+    log::info!("品种映射后台刷新已启用，间隔 {} 秒", interval.as_secs());
+
+    tokio::spawn(async move {
+        let service = FuturesService::new();
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // 首次 tick 立即触发，之后按间隔刷新
+        loop {
+            ticker.tick().await;
+            if mock_enabled() {
+                continue;
+            }
+            match service.refresh_symbol_mark().await {
+                Ok(symbols) => log::debug!("品种映射已刷新: {} 条", symbols.len()),
+                Err(e) => log::warn!("品种映射后台刷新失败，继续使用原缓存: {}", e),
+            }
+        }
+    });
+}
+
+/// 并发预热各项共享缓存
+async fn warm_caches() {
+    let started = Instant::now();
+    *stats_slot().lock().unwrap_or_else(|e| e.into_inner()) = Some(CacheWarmupStats {
+        started_at: get_beijing_time(),
+        finished_at: None,
+        elapsed_ms: None,
+        items: Vec::new(),
+    });
+    log::info!("开始预热缓存");
+
+    let mut service = FuturesService::new();
+    let (symbol_marks, symbols_99, fees) = tokio::join!(
+        service.get_symbol_mark(),
+        get_99_symbol_map(),
+        get_futures_fees_info(),
+    );
+    let items = vec![
+        warmup_item("symbol_marks", symbol_marks),
+        warmup_item("symbols_99", symbols_99),
+        warmup_item("fees", fees),
+    ];
+
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    log::info!(
+        "缓存预热完成，耗时 {} ms，成功 {}/{}",
+        elapsed_ms,
+        items.iter().filter(|item| item.ok).count(),
+        items.len()
+    );
+    if let Some(stats) = stats_slot().lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        stats.finished_at = Some(get_beijing_time());
+        stats.elapsed_ms = Some(elapsed_ms);
+        stats.items = items;
+    }
+}
+
+fn warmup_item<T>(name: &'static str, result: Result<Vec<T>>) -> CacheWarmupItem {
+    match result {
+        Ok(data) => {
+            log::info!("缓存预热 {}: {} 条", name, data.len());
+            CacheWarmupItem {
+                name,
+                ok: true,
+                count: data.len(),
+                error: None,
+            }
+        }
+        Err(e) => {
+            log::warn!("缓存预热 {} 失败: {}", name, e);
+            CacheWarmupItem {
+                name,
+                ok: false,
+                count: 0,
+                error: Some(e.to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    #[tokio::test]
+    async fn warmup_runs_in_background_and_records_each_cache() {
+        config::init_mock_for_tests();
+
+        let started = Instant::now();
+        spawn_cache_warmup();
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        let stats = loop {
+            if let Some(stats) = cache_warmup_stats().filter(|s| s.finished_at.is_some()) {
+                break stats;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5), "缓存预热未在 5 秒内完成");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+
+        let names: Vec<&str> = stats.items.iter().map(|item| item.name).collect();
+        assert_eq!(names, ["symbol_marks", "symbols_99", "fees"]);
+        assert!(stats.elapsed_ms.is_some());
+
+        // 模拟模式下品种映射来自 fixture；其余两项没有 fixture，只记录失败，不影响预热完成
+        let fixture: Vec<serde_json::Value> =
+            serde_json::from_str(include_str!("../../../fixtures/futures_symbol_marks.json")).unwrap();
+        let symbol_marks = &stats.items[0];
+        assert!(symbol_marks.ok, "{:?}", symbol_marks.error);
+        assert_eq!(symbol_marks.count, fixture.len());
+        for item in &stats.items[1..] {
+            assert!(!item.ok && item.count == 0 && item.error.is_some(), "{:?}", item);
+        }
+    }
+}
@@ -2,7 +2,9 @@
 //!
 //! 提供股票相关的数据服务，支持多种数据源
 
+pub mod parquet;
 pub mod sina;
 
 // 重新导出常用函数，保持对外接口一致
-pub use sina::{get_stock_info, get_stock_history, list_stocks};
+pub use parquet::{encode_stock_history_parquet, PARQUET_CONTENT_TYPE};
+pub use sina::{get_stock_info, get_stock_history, get_stock_history_range, list_stocks};
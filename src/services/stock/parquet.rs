@@ -0,0 +1,138 @@
+//! 批量历史K线的 Parquet 输出
+//!
+//! 列类型固定：code / date 为 UTF8 字符串，OHLC 为 DOUBLE，volume 为 INT64。
+//! 行数据一次性写入单个行组，适合批量导出的数据量
+
+use anyhow::Result;
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::sync::Arc;
+
+use crate::models::StockHistoryData;
+
+/// Parquet 输出的 Content-Type
+pub const PARQUET_CONTENT_TYPE: &str = "application/vnd.apache.parquet";
+
+/// 批量历史K线的 Parquet 模式
+const STOCK_HISTORY_SCHEMA: &str = "
+message stock_history {
+    REQUIRED BYTE_ARRAY code (UTF8);
+    REQUIRED BYTE_ARRAY date (UTF8);
+    REQUIRED DOUBLE open;
+    REQUIRED DOUBLE high;
+    REQUIRED DOUBLE low;
+    REQUIRED DOUBLE close;
+    REQUIRED INT64 volume;
+}
+";
+
+/// 将 (股票代码, K线) 行序列化为 Parquet 文件字节
+pub fn encode_stock_history_parquet(rows: &[(String, StockHistoryData)]) -> Result<Vec<u8>> {
+    let schema = Arc::new(parse_message_type(STOCK_HISTORY_SCHEMA)?);
+    let props = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(Vec::new(), schema, props)?;
+
+    let strings = |get: fn(&(String, StockHistoryData)) -> &str| -> Vec<ByteArray> {
+        rows.iter().map(|row| ByteArray::from(get(row))).collect()
+    };
+    let doubles = |get: fn(&StockHistoryData) -> f64| -> Vec<f64> {
+        rows.iter().map(|(_, data)| get(data)).collect()
+    };
+
+    let mut row_group = writer.next_row_group()?;
+    for values in [strings(|(code, _)| code), strings(|(_, data)| &data.date)] {
+        let mut column = row_group.next_column()?.expect("模式中的字符串列");
+        column.typed::<ByteArrayType>().write_batch(&values, None, None)?;
+        column.close()?;
+    }
+    for values in [
+        doubles(|d| d.open),
+        doubles(|d| d.high),
+        doubles(|d| d.low),
+        doubles(|d| d.close),
+    ] {
+        let mut column = row_group.next_column()?.expect("模式中的价格列");
+        column.typed::<DoubleType>().write_batch(&values, None, None)?;
+        column.close()?;
+    }
+    let volumes: Vec<i64> = rows
+        .iter()
+        .map(|(_, data)| i64::try_from(data.volume).unwrap_or(i64::MAX))
+        .collect();
+    let mut column = row_group.next_column()?.expect("模式中的成交量列");
+    column.typed::<Int64Type>().write_batch(&volumes, None, None)?;
+    column.close()?;
+    row_group.close()?;
+
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::web::Bytes;
+    use parquet::basic::Type as PhysicalType;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+
+    fn bar(date: &str, close: f64, volume: u64) -> StockHistoryData {
+        StockHistoryData {
+            symbol: String::new(),
+            date: date.to_string(),
+            open: close - 0.1,
+            high: close + 0.2,
+            low: close - 0.3,
+            close,
+            volume,
+        }
+    }
+
+    #[test]
+    fn parquet_round_trip_keeps_schema_and_rows() {
+        let rows = vec![
+            ("sh600000".to_string(), bar("2024-01-02", 7.1, 1_000)),
+            ("sh600000".to_string(), bar("2024-01-03", 7.2, 2_000)),
+            ("sz000001".to_string(), bar("2024-01-02", 9.5, 5_000_000_000)),
+        ];
+        let bytes = encode_stock_history_parquet(&rows).unwrap();
+        assert_eq!(&bytes[..4], b"PAR1");
+
+        let reader = SerializedFileReader::new(Bytes::from(bytes)).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 3);
+
+        let columns: Vec<(String, PhysicalType)> = metadata
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|c| (c.name().to_string(), c.physical_type()))
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                ("code".to_string(), PhysicalType::BYTE_ARRAY),
+                ("date".to_string(), PhysicalType::BYTE_ARRAY),
+                ("open".to_string(), PhysicalType::DOUBLE),
+                ("high".to_string(), PhysicalType::DOUBLE),
+                ("low".to_string(), PhysicalType::DOUBLE),
+                ("close".to_string(), PhysicalType::DOUBLE),
+                ("volume".to_string(), PhysicalType::INT64),
+            ]
+        );
+
+        let last = reader.get_row_iter(None).unwrap().last().unwrap().unwrap();
+        assert_eq!(last.get_string(0).unwrap(), "sz000001");
+        assert_eq!(last.get_string(1).unwrap(), "2024-01-02");
+        assert_eq!(last.get_double(5).unwrap(), 9.5);
+        assert_eq!(last.get_long(6).unwrap(), 5_000_000_000);
+    }
+
+    #[test]
+    fn empty_input_writes_valid_file() {
+        let bytes = encode_stock_history_parquet(&[]).unwrap();
+        let reader = SerializedFileReader::new(Bytes::from(bytes)).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 0);
+    }
+}
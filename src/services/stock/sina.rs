@@ -1,13 +1,14 @@
 //! 新浪财经股票接口实现
 //!
-//! 提供实时行情、历史K线、股票列表等数据
-//! 对接 https://hq.sinajs.cn 和 https://quotes.sina.cn
+//! 提供实时行情、历史K线（含前/后复权）、股票列表等数据
+//! 对接 https://hq.sinajs.cn、https://quotes.sina.cn 和 https://finance.sina.com.cn（复权因子）
 
 use anyhow::{anyhow, Result};
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
 use chrono_tz::Asia::Shanghai;
-use reqwest::Client;
-use crate::models::{StockInfo, StockHistoryData, StockQuery};
+use crate::config::DefaultsConfig;
+use crate::models::{StockAdjust, StockAdjustFactor, StockInfo, StockHistoryData, StockQuery};
+use crate::services::futures::{fixture, http_client, parse_context, LimitedSend, RequestTimeout};
 
 /// 获取北京时间字符串（ISO 8601 格式，带+08:00时区）
 fn get_beijing_time() -> String {
@@ -18,17 +19,20 @@ fn get_beijing_time() -> String {
 ///
 /// 对接新浪财经实时行情 API: https://hq.sinajs.cn/list=<symbol>
 pub async fn get_stock_info(symbol: &str) -> Result<StockInfo> {
-    let client = Client::new();
+    if let Some(stocks) = fixture::<Vec<StockInfo>>("stocks") {
+        return stocks?
+            .into_iter()
+            .find(|stock| stock.symbol.eq_ignore_ascii_case(symbol))
+            .ok_or_else(|| anyhow!("fixture 中没有股票 {}", symbol));
+    }
+
+    let client = http_client();
     let url = format!("https://hq.sinajs.cn/list={}", symbol);
 
     let response = client
         .get(&url)
-        .header("Referer", "https://finance.sina.com.cn/")
-        .header(
-            "User-Agent",
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36",
-        )
-        .send()
+        .timeout(RequestTimeout::Realtime.duration())
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -86,10 +90,51 @@ fn parse_sina_stock_info(data: &str, symbol: &str) -> Result<StockInfo> {
     })
 }
 
+/// 未指定条数或开始日期时默认返回的日K线条数
+const DEFAULT_HISTORY_BARS: usize = 30;
+
 /// 获取股票历史K线数据
+///
+/// 指定 start_date/end_date 时按日期范围获取（指定 limit 时只保留范围内最近的 limit 条），
+/// 否则取最近 limit 条（默认 30）；adjust 为 qfq/hfq 时按新浪复权因子计算复权价格
 pub async fn get_stock_history(symbol: &str, query: &StockQuery) -> Result<Vec<StockHistoryData>> {
-    let client = Client::new();
-    let limit = query.limit.unwrap_or(30);
+    let (start, end) = query.date_range()?;
+    let adjust = query.adjust_mode()?;
+
+    let mut bars = if start.is_some() || end.is_some() {
+        let mut bars = get_stock_history_range(symbol, start, end).await?;
+        if let Some(limit) = query.limit {
+            let skip = bars.len().saturating_sub(limit);
+            bars.drain(..skip);
+        }
+        bars
+    } else {
+        fetch_daily_bars(symbol, query.limit.unwrap_or(DEFAULT_HISTORY_BARS)).await?
+    };
+
+    if adjust != StockAdjust::None {
+        let factors = get_adjust_factors(symbol, adjust).await?;
+        apply_adjust_factors(&mut bars, &factors, adjust);
+    }
+    Ok(bars)
+}
+
+/// 获取最近 limit 条不复权日K线（按日期升序）
+async fn fetch_daily_bars(symbol: &str, limit: usize) -> Result<Vec<StockHistoryData>> {
+    let client = http_client();
+
+    if let Some(bars) = fixture::<Vec<StockHistoryData>>("stock_history") {
+        let bars = bars?;
+        let skip = bars.len().saturating_sub(limit);
+        return Ok(bars
+            .into_iter()
+            .skip(skip)
+            .map(|bar| StockHistoryData {
+                symbol: symbol.to_string(),
+                ..bar
+            })
+            .collect());
+    }
 
     // 使用新浪财经分钟线/日线接口 (JSON 格式比较容易解析)
     // scale=240 表示日线
@@ -103,8 +148,7 @@ pub async fn get_stock_history(symbol: &str, query: &StockQuery) -> Result<Vec<S
             ("ma", "no"),
             ("datalen", &limit.to_string()),
         ])
-        .header("Referer", "https://finance.sina.com.cn/")
-        .send()
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -112,7 +156,143 @@ pub async fn get_stock_history(symbol: &str, query: &StockQuery) -> Result<Vec<S
     }
 
     let text = response.text().await?;
-    parse_sina_stock_history(&text, symbol)
+    parse_context(parse_sina_stock_history(&text, symbol), "新浪股票K线", url, &text)
+}
+
+/// 单次最多请求的日K线条数
+const MAX_HISTORY_BARS: i64 = 2000;
+
+/// 获取日期范围内的股票日K线（不复权）
+///
+/// 新浪接口只能按条数取最近的数据，因此按开始日期到今天的自然日数请求后再按日期过滤；
+/// 未指定开始日期时取结束日期（默认今天）及之前最近 30 条
+pub async fn get_stock_history_range(
+    symbol: &str,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> Result<Vec<StockHistoryData>> {
+    let today = Utc::now().with_timezone(&Shanghai).date_naive();
+    let days = match (start, end) {
+        (Some(start), _) => (today - start).num_days() + 1,
+        (None, Some(end)) => (today - end).num_days() + DEFAULT_HISTORY_BARS as i64,
+        (None, None) => DEFAULT_HISTORY_BARS as i64,
+    };
+    let limit = days.clamp(1, MAX_HISTORY_BARS) as usize;
+
+    let mut history: Vec<StockHistoryData> = fetch_daily_bars(symbol, limit)
+        .await?
+        .into_iter()
+        .filter(|bar| {
+            let Ok(date) = NaiveDate::parse_from_str(&bar.date, "%Y-%m-%d") else {
+                return false;
+            };
+            start.is_none_or(|s| date >= s) && end.is_none_or(|e| date <= e)
+        })
+        .collect();
+
+    if start.is_none() {
+        let skip = history.len().saturating_sub(DEFAULT_HISTORY_BARS);
+        history.drain(..skip);
+    }
+    Ok(history)
+}
+
+/// 获取新浪复权因子（按日期升序）
+///
+/// 数据来源: https://finance.sina.com.cn/realstock/company/<symbol>/qfq.js（后复权为 hfq.js），
+/// 与 akshare 的 stock_zh_a_daily 一致；模拟模式下读取 fixture stock_qfq_factors / stock_hfq_factors
+async fn get_adjust_factors(symbol: &str, adjust: StockAdjust) -> Result<Vec<StockAdjustFactor>> {
+    let Some(name) = adjust.factor_name() else {
+        return Ok(Vec::new());
+    };
+
+    if let Some(factors) = fixture::<Vec<StockAdjustFactor>>(&format!("stock_{}_factors", name)) {
+        let mut factors = factors?;
+        factors.sort_by(|a, b| a.date.cmp(&b.date));
+        return Ok(factors);
+    }
+
+    let url = format!(
+        "https://finance.sina.com.cn/realstock/company/{}/{}.js",
+        symbol.to_lowercase(),
+        name
+    );
+    let response = http_client()
+        .get(&url)
+        .send_limited()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("获取复权因子失败: {}", response.status()));
+    }
+
+    let text = response.text().await?;
+    parse_context(parse_adjust_factors(&text), "新浪复权因子", &url, &text)
+}
+
+/// 解析新浪复权因子
+///
+/// 格式: var qfq_data = {"total":2,"data":[{"d":"2024-06-20","f":"1.0000"},{"d":"1900-01-01","f":"1.2500"}]}
+/// 后面可能跟有注释行，只取等号后的第一行
+fn parse_adjust_factors(text: &str) -> Result<Vec<StockAdjustFactor>> {
+    let json_str = text
+        .split_once('=')
+        .and_then(|(_, rest)| rest.lines().next())
+        .map(|line| line.trim().trim_end_matches(';'))
+        .ok_or_else(|| anyhow!("解析复权因子失败"))?;
+
+    let json_data: serde_json::Value = serde_json::from_str(json_str)?;
+    let items = json_data["data"]
+        .as_array()
+        .ok_or_else(|| anyhow!("复权因子数据为空"))?;
+
+    let mut factors: Vec<StockAdjustFactor> = items
+        .iter()
+        .filter_map(|item| {
+            Some(StockAdjustFactor {
+                date: item["d"].as_str()?.to_string(),
+                factor: item["f"].as_str()?.parse().ok()?,
+            })
+        })
+        .filter(|f| f.factor > 0.0)
+        .collect();
+
+    if factors.is_empty() {
+        return Err(anyhow!("复权因子数据为空"));
+    }
+    factors.sort_by(|a, b| a.date.cmp(&b.date));
+    Ok(factors)
+}
+
+/// 按复权因子调整开高低收价格，成交量不变
+///
+/// 每根K线使用生效日期不晚于该日的最新因子（早于所有因子日期时为 1）；
+/// 前复权为价格除以因子，后复权为价格乘以因子，结果保留 4 位小数
+fn apply_adjust_factors(
+    bars: &mut [StockHistoryData],
+    factors: &[StockAdjustFactor],
+    adjust: StockAdjust,
+) {
+    for bar in bars.iter_mut() {
+        let factor = factors
+            .iter()
+            .rev()
+            .find(|f| f.date.as_str() <= bar.date.as_str())
+            .map(|f| f.factor)
+            .unwrap_or(1.0);
+        let scale = |price: f64| {
+            let adjusted = match adjust {
+                StockAdjust::None => price,
+                StockAdjust::Qfq => price / factor,
+                StockAdjust::Hfq => price * factor,
+            };
+            (adjusted * 10_000.0).round() / 10_000.0
+        };
+        bar.open = scale(bar.open);
+        bar.high = scale(bar.high);
+        bar.low = scale(bar.low);
+        bar.close = scale(bar.close);
+    }
 }
 
 fn parse_sina_stock_history(data: &str, symbol: &str) -> Result<Vec<StockHistoryData>> {
@@ -142,10 +322,14 @@ fn parse_sina_stock_history(data: &str, symbol: &str) -> Result<Vec<StockHistory
 }
 
 /// 获取股票列表（实时行情）
-/// 对应 akshare 的 stock_zh_a_spot
-pub async fn list_stocks(query: &StockQuery) -> Result<Vec<StockInfo>> {
-    let client = Client::new();
-    let limit = query.limit.unwrap_or(20);
+/// 对应 akshare 的 stock_zh_a_spot，未指定 limit 时取 defaults.stock_list_limit 条
+pub async fn list_stocks(query: &StockQuery, defaults: &DefaultsConfig) -> Result<Vec<StockInfo>> {
+    let client = http_client();
+    let limit = query.limit.unwrap_or(defaults.stock_list_limit);
+
+    if let Some(stocks) = fixture::<Vec<StockInfo>>("stocks") {
+        return Ok(stocks?.into_iter().take(limit).collect());
+    }
 
     let url = "http://vip.stock.finance.sina.com.cn/quotes_service/api/json_v2.php/Market_Center.getHQNodeData";
 
@@ -158,7 +342,8 @@ pub async fn list_stocks(query: &StockQuery) -> Result<Vec<StockInfo>> {
             ("sort", "symbol"),
             ("asc", "1"),
         ])
-        .send()
+        .timeout(RequestTimeout::Realtime.duration())
+        .send_limited()
         .await?;
 
     if !response.status().is_success() {
@@ -190,3 +375,78 @@ pub async fn list_stocks(query: &StockQuery) -> Result<Vec<StockInfo>> {
 
     Ok(stocks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{self, DefaultsConfig};
+
+    #[tokio::test]
+    async fn stock_list_default_limit_comes_from_config() {
+        config::init_mock_for_tests();
+        let query = StockQuery { symbol: None, start_date: None, end_date: None, limit: None, adjust: None };
+
+        let defaults = DefaultsConfig { stock_list_limit: 2, ..Default::default() };
+        assert_eq!(list_stocks(&query, &defaults).await.unwrap().len(), 2);
+        let defaults = DefaultsConfig { stock_list_limit: 3, ..Default::default() };
+        assert_eq!(list_stocks(&query, &defaults).await.unwrap().len(), 3);
+
+        let query = StockQuery { limit: Some(1), ..query };
+        assert_eq!(list_stocks(&query, &defaults).await.unwrap().len(), 1);
+    }
+
+    /// 2024-06-20 一拆二：拆分前收盘 20 元，拆分后收盘 10 元
+    fn split_series() -> Vec<StockHistoryData> {
+        [("2024-06-18", 20.0), ("2024-06-19", 20.4), ("2024-06-20", 10.2), ("2024-06-21", 10.0)]
+            .into_iter()
+            .map(|(date, close)| StockHistoryData {
+                symbol: "SH600000".to_string(),
+                date: date.to_string(),
+                open: close,
+                high: close + 0.2,
+                low: close - 0.2,
+                close,
+                volume: 1_000,
+            })
+            .collect()
+    }
+
+    fn closes(bars: &[StockHistoryData]) -> Vec<f64> {
+        bars.iter().map(|bar| bar.close).collect()
+    }
+
+    #[test]
+    fn qfq_keeps_latest_prices_across_split() {
+        let factors = parse_adjust_factors(
+            "var qfq_data = {\"total\":2,\"data\":[{\"d\":\"2024-06-20\",\"f\":\"1.0000\"},{\"d\":\"1900-01-01\",\"f\":\"2.0000\"}]};\n/* comment */",
+        )
+        .unwrap();
+        assert_eq!(factors[0].date, "1900-01-01");
+
+        let mut bars = split_series();
+        apply_adjust_factors(&mut bars, &factors, StockAdjust::Qfq);
+        assert_eq!(closes(&bars), [10.0, 10.2, 10.2, 10.0]);
+        assert_eq!(bars[0].high, 10.1);
+        assert_eq!(bars[0].volume, 1_000);
+    }
+
+    #[test]
+    fn hfq_keeps_first_prices_across_split() {
+        let factors = vec![
+            StockAdjustFactor { date: "1900-01-01".to_string(), factor: 1.0 },
+            StockAdjustFactor { date: "2024-06-20".to_string(), factor: 2.0 },
+        ];
+        let mut bars = split_series();
+        apply_adjust_factors(&mut bars, &factors, StockAdjust::Hfq);
+        assert_eq!(closes(&bars), [20.0, 20.4, 20.4, 20.0]);
+        assert_eq!(bars[3].low, 19.6);
+    }
+
+    #[test]
+    fn bars_before_first_factor_are_unadjusted() {
+        let factors = vec![StockAdjustFactor { date: "2024-06-20".to_string(), factor: 2.0 }];
+        let mut bars = split_series();
+        apply_adjust_factors(&mut bars, &factors, StockAdjust::Qfq);
+        assert_eq!(closes(&bars), [20.0, 20.4, 5.1, 5.0]);
+    }
+}
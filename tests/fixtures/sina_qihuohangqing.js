@@ -0,0 +1,8 @@
+var ARRFUTURESNODES = {
+czce: ['郑州商品交易所', ['PTA', 'pta_qh', '16'], ['菜油', 'caiyou_qh', '16'], ['白糖', 'baitang_qh', '16'], ['棉花', 'mianhua_qh', '16'], ['甲醇', 'jiachun_qh', '16']],
+dce: ['大连商品交易所', ['豆一', 'dou1_qh', '16'], ['豆粕', 'doupo_qh', '16'], ['铁矿石', 'tks_qh', '16'], ['焦炭', 'jiaotan_qh', '16']],
+shfe: ['上海期货交易所', ['铜', 'tong_qh', '16'], ['铝', 'lv_qh', '16'], ['螺纹钢', 'luowen_qh', '16'], ['黄金', 'hj_qh', '16'], ['原油', 'yuanyou_qh', '16']],
+cffex: ['中国金融期货交易所', ['沪深300', 'qz_qh', '16'], ['上证50', 'szfz_qh', '16'], ['10年期国债', 'gz10_qh', '16']],
+gfex: ['广州期货交易所', ['工业硅', 'gys_qh', '16'], ['碳酸锂', 'tsl_qh', '16']]
+};
+var ARRFUTURESNODES_NAMES = ['czce', 'dce', 'shfe', 'cffex', 'gfex'];